@@ -0,0 +1,11 @@
+//! Content digests shared by the manifest, fragment cache and lock file
+
+use sha2::{Digest, Sha256};
+
+/// Hex-encoded SHA-256 digest
+pub fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
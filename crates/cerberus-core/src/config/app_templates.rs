@@ -0,0 +1,257 @@
+//! # Application templates
+//!
+//! Presets for applications that need more than a domain and an upstream:
+//! WebSocket streaming, large uploads, edge caching, and, for Fediverse
+//! servers, federation endpoints that other servers call and that must not
+//! be sent through the Anubis challenge. A template expands into a
+//! `[[services]]` entry that records it in `template`, so the generators
+//! know which paths a layer-1 Nginx routes past Anubis.
+//!
+//! `cerberus init --template <name>` writes [`starter_config`] around the
+//! expanded service.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Config, SCHEMA_VERSION, ServiceConfig, upstream_host};
+use crate::{CerberusError, Result};
+
+/// Federation and API paths of Misskey, also used for a
+/// `special_routing_service` without a template
+pub const MISSKEY_FEDERATION_PATHS: &str = r"streaming|inbox|outbox|api|\.well-known|url";
+
+/// Application a service is expanded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum AppTemplate {
+    /// Misskey Fediverse server
+    Misskey,
+    /// Mastodon Fediverse server
+    Mastodon,
+    /// Nextcloud file hosting
+    Nextcloud,
+    /// Gitea Git forge
+    Gitea,
+}
+
+impl AppTemplate {
+    /// Every template, in the order `cerberus init` lists them
+    pub const ALL: [AppTemplate; 4] = [
+        AppTemplate::Misskey,
+        AppTemplate::Mastodon,
+        AppTemplate::Nextcloud,
+        AppTemplate::Gitea,
+    ];
+
+    /// Name used in `template = "..."`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AppTemplate::Misskey => "misskey",
+            AppTemplate::Mastodon => "mastodon",
+            AppTemplate::Nextcloud => "nextcloud",
+            AppTemplate::Gitea => "gitea",
+        }
+    }
+
+    /// One-line description of the application
+    pub fn description(self) -> &'static str {
+        match self {
+            AppTemplate::Misskey => "Misskey (Fediverse)",
+            AppTemplate::Mastodon => "Mastodon (Fediverse)",
+            AppTemplate::Nextcloud => "Nextcloud",
+            AppTemplate::Gitea => "Gitea",
+        }
+    }
+
+    /// Port the application's web server listens on by default
+    pub fn default_port(self) -> u16 {
+        match self {
+            AppTemplate::Misskey | AppTemplate::Mastodon | AppTemplate::Gitea => 3000,
+            AppTemplate::Nextcloud => 80,
+        }
+    }
+
+    /// Default upstream: a container named after the template
+    pub fn default_upstream(self) -> String {
+        format!("http://{}:{}", self.as_str(), self.default_port())
+    }
+
+    /// Paths a layer-1 Nginx routes past Anubis, as a regex alternation
+    ///
+    /// Only Fediverse servers have them: other servers deliver activities
+    /// and clients stream timelines without a browser to solve challenges.
+    pub fn federation_paths(self) -> Option<&'static str> {
+        match self {
+            AppTemplate::Misskey => Some(MISSKEY_FEDERATION_PATHS),
+            AppTemplate::Mastodon => {
+                Some(r"api|inbox|users/[^/]+/(inbox|outbox)|\.well-known|nodeinfo|oauth")
+            }
+            AppTemplate::Nextcloud | AppTemplate::Gitea => None,
+        }
+    }
+
+    /// `[[services]]` entry of the application, as commented TOML
+    pub fn service_toml(self, name: &str, domain: &str, upstream: &str) -> String {
+        let mut toml = format!(
+            "[[services]]\n\
+             name = \"{name}\"\n\
+             template = \"{}\"\n\
+             domain = \"{domain}\"\n\
+             upstream = \"{upstream}\"\n",
+            self.as_str()
+        );
+        toml.push_str(match self {
+            AppTemplate::Misskey => {
+                "# Timelines stream over WebSocket\n\
+                 websocket = true\n\
+                 # Drive uploads (Misskey's default maxFileSize)\n\
+                 max_body_size = \"256m\"\n\
+                 \n\
+                 # Pages and files; API calls are POST requests and never cached\n\
+                 [services.cache]\n\
+                 enabled = true\n\
+                 ttl = \"10m\"\n\
+                 stale_if_error = \"1h\"\n\
+                 bypass_cookies = [\"token\"]\n"
+            }
+            AppTemplate::Mastodon => {
+                "# Timelines stream over WebSocket\n\
+                 websocket = true\n\
+                 # Media uploads (Mastodon accepts videos up to 99 MB)\n\
+                 max_body_size = \"100m\"\n\
+                 \n\
+                 # Public pages and media; signed-in sessions skip the cache\n\
+                 [services.cache]\n\
+                 enabled = true\n\
+                 ttl = \"10m\"\n\
+                 stale_if_error = \"1h\"\n\
+                 bypass_cookies = [\"_session_id\", \"_mastodon_session\"]\n"
+            }
+            AppTemplate::Nextcloud => {
+                "# notify_push keeps clients up to date over WebSocket\n\
+                 websocket = true\n\
+                 # WebDAV sync uploads whole files\n\
+                 max_body_size = \"10G\"\n\
+                 # Responses are per user, so nothing is cached at the edge\n"
+            }
+            AppTemplate::Gitea => {
+                "# Pushes over HTTP and LFS uploads\n\
+                 max_body_size = \"512m\"\n\
+                 # Responses are per user, so nothing is cached at the edge\n"
+            }
+        });
+        if self == AppTemplate::Mastodon {
+            toml.push_str(&format!(
+                "\n\
+                 # The streaming API is a separate server; Nginx only\n\
+                 [services.snippets]\n\
+                 server = \"\"\"\n\
+                 location ^~ /api/v1/streaming {{\n\
+                 \x20   proxy_pass http://{}:4000;\n\
+                 \x20   include /etc/nginx/conf.d/proxy_params.conf;\n\
+                 \x20   proxy_http_version 1.1;\n\
+                 \x20   proxy_set_header Upgrade $http_upgrade;\n\
+                 \x20   proxy_set_header Connection $connection_upgrade;\n\
+                 }}\n\
+                 \"\"\"\n",
+                upstream_host(upstream)
+            ));
+        }
+        toml
+    }
+
+    /// Expand the template into a service
+    ///
+    /// # Errors
+    /// Returns error if the name, domain, or upstream do not form a valid
+    /// TOML string
+    pub fn service(self, name: &str, domain: &str, upstream: &str) -> Result<ServiceConfig> {
+        let mut table: toml::Table = toml::from_str(&self.service_toml(name, domain, upstream))
+            .map_err(|e| CerberusError::validation(format!("Template {}: {e}", self.as_str())))?;
+        let service = table
+            .remove("services")
+            .and_then(|services| services.as_array()?.first().cloned())
+            .ok_or_else(|| {
+                CerberusError::validation(format!(
+                    "Template {} has no [[services]] entry",
+                    self.as_str()
+                ))
+            })?;
+        service
+            .try_into()
+            .map_err(|e| CerberusError::validation(format!("Template {}: {e}", self.as_str())))
+    }
+}
+
+impl std::fmt::Display for AppTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Starter configuration for an application
+///
+/// Two Nginx layers with Anubis in front: layer 1 publishes port 80 and
+/// sends browsers through the challenge, layer 2 routes the domain to the
+/// application.
+pub fn starter_config(template: AppTemplate, domain: &str, upstream: &str) -> String {
+    format!(
+        "# Cerberus configuration for {description}\n\
+         # Generated by `cerberus init --template {name}`\n\
+         schema_version = {SCHEMA_VERSION}\n\
+         \n\
+         [project]\n\
+         name = \"{name}\"\n\
+         \n\
+         # Challenge browsers before they reach the application\n\
+         [anubis]\n\
+         enabled = true\n\
+         \n\
+         # Layer 1: publishes the stack and sends requests through Anubis\n\
+         [[proxies]]\n\
+         name = \"proxy\"\n\
+         type = \"nginx\"\n\
+         external_port = 80\n\
+         layer = 1\n\
+         default_upstream = \"http://anubis:8080\"\n\
+         \n\
+         # Layer 2: routes each domain to its service\n\
+         [[proxies]]\n\
+         name = \"proxy-2\"\n\
+         type = \"nginx\"\n\
+         layer = 2\n\
+         \n\
+         {service}",
+        description = template.description(),
+        name = template.as_str(),
+        service = template.service_toml(template.as_str(), domain, upstream),
+    )
+}
+
+/// Write the starter configuration of an application to `path`
+///
+/// # Errors
+/// Returns error if `path` exists and `force` is not set, if the domain or
+/// upstream make the configuration invalid, or if the file cannot be written
+pub fn write_starter_config(
+    path: &Path,
+    template: AppTemplate,
+    domain: &str,
+    upstream: &str,
+    force: bool,
+) -> Result<()> {
+    if path.exists() && !force {
+        return Err(CerberusError::validation(format!(
+            "{} already exists; rerun with --force to overwrite it",
+            path.display()
+        )));
+    }
+    let content = starter_config(template, domain, upstream);
+    let config: Config =
+        toml::from_str(&content).map_err(|e| CerberusError::toml_parse(path, e))?;
+    config.validate()?;
+    std::fs::write(path, content).map_err(|e| CerberusError::io(path, e))
+}
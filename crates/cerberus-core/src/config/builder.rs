@@ -0,0 +1,314 @@
+//! # Configuration builders
+//!
+//! Build a [`Config`] in code instead of loading it from TOML. Each builder
+//! starts from the same defaults as an equivalent TOML file and runs the
+//! usual validation in `build()`.
+//!
+//! ```
+//! use cerberus_core::config::{ConfigBuilder, ProxyConfigBuilder, ProxyType, ServiceConfigBuilder};
+//!
+//! let config = ConfigBuilder::new("example")
+//!     .proxy(
+//!         ProxyConfigBuilder::new("edge", ProxyType::Caddy)
+//!             .external_port(80)
+//!             .build()?,
+//!     )
+//!     .service(
+//!         ServiceConfigBuilder::new("app", "app.example.com", "http://app:3000")
+//!             .image("app:latest")
+//!             .build()?,
+//!     )
+//!     .build()?;
+//! assert_eq!(config.proxies[0].internal_port, 80);
+//! # Ok::<(), cerberus_core::CerberusError>(())
+//! ```
+
+use serde::de::DeserializeOwned;
+
+use super::{
+    AnubisConfig, CommandConfig, CompressionConfig, Config, DeployTarget, InstancePortStrategy,
+    NetworkConfig, ProxyConfig, ProxyType, RouteConfig, ServiceConfig, validate_proxy,
+    validate_service,
+};
+use crate::{CerberusError, Result};
+
+/// Deserialize a value from its required TOML keys so serde defaults apply
+fn from_required<T: DeserializeOwned>(table: toml::Table) -> T {
+    table.try_into().expect("required keys always deserialize")
+}
+
+/// Builder for [`Config`]
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Start a configuration for a project
+    pub fn new(project_name: impl Into<String>) -> Self {
+        let mut project = toml::Table::new();
+        project.insert("name".to_string(), project_name.into().into());
+        let mut table = toml::Table::new();
+        table.insert("project".to_string(), project.into());
+
+        Self {
+            config: from_required(table),
+        }
+    }
+
+    /// Enable auto-scaling
+    pub fn scaling(mut self, scaling: bool) -> Self {
+        self.config.project.scaling = scaling;
+        self
+    }
+
+    /// Set the deployment target
+    pub fn target(mut self, target: DeployTarget) -> Self {
+        self.config.project.target = target;
+        self
+    }
+
+    /// Set the time zone every generated container logs in
+    pub fn timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.config.project.timezone = Some(timezone.into());
+        self
+    }
+
+    /// Set the Anubis configuration
+    pub fn anubis(mut self, anubis: AnubisConfig) -> Self {
+        self.config.anubis = anubis;
+        self
+    }
+
+    /// Add a proxy layer
+    pub fn proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.config.proxies.push(proxy);
+        self
+    }
+
+    /// Add a backend service
+    pub fn service(mut self, service: ServiceConfig) -> Self {
+        self.config.services.push(service);
+        self
+    }
+
+    /// Declare a Docker network
+    pub fn network(mut self, name: impl Into<String>, network: NetworkConfig) -> Self {
+        self.config.networks.insert(name.into(), network);
+        self
+    }
+
+    /// Normalize the upstreams, then validate and return the configuration
+    ///
+    /// Upstreams are normalized as [`Config::load`] does.
+    ///
+    /// # Errors
+    /// Returns error if the configuration is invalid
+    pub fn build(mut self) -> Result<Config> {
+        self.config.normalize_upstreams();
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Builder for [`ProxyConfig`]
+#[derive(Debug, Clone)]
+pub struct ProxyConfigBuilder {
+    proxy: ProxyConfig,
+}
+
+impl ProxyConfigBuilder {
+    /// Start a proxy of the given type
+    pub fn new(name: impl Into<String>, proxy_type: ProxyType) -> Self {
+        let mut table = toml::Table::new();
+        table.insert("name".to_string(), name.into().into());
+        table.insert("type".to_string(), proxy_type.as_str().into());
+
+        Self {
+            proxy: from_required(table),
+        }
+    }
+
+    /// Publish the proxy on a host port
+    pub fn external_port(mut self, port: u16) -> Self {
+        self.proxy.external_port = Some(port);
+        self
+    }
+
+    /// Set the container port
+    pub fn internal_port(mut self, port: u16) -> Self {
+        self.proxy.internal_port = port;
+        self
+    }
+
+    /// Set the proxy layer
+    pub fn layer(mut self, layer: u8) -> Self {
+        self.proxy.layer = Some(layer);
+        self
+    }
+
+    /// Set the number of instances
+    pub fn instances(mut self, instances: u8) -> Self {
+        self.proxy.instances = instances;
+        self
+    }
+
+    /// Set how the instances publish the host port
+    pub fn instance_port_strategy(mut self, strategy: InstancePortStrategy) -> Self {
+        self.proxy.instance_port_strategy = strategy;
+        self
+    }
+
+    /// Set the maximum number of connections
+    pub fn max_connections(mut self, max_connections: u32) -> Self {
+        self.proxy.max_connections = Some(max_connections);
+        self
+    }
+
+    /// Set the upstream for unmatched requests
+    pub fn default_upstream(mut self, upstream: impl Into<String>) -> Self {
+        self.proxy.default_upstream = Some(upstream.into());
+        self
+    }
+
+    /// Add a routing rule
+    pub fn route(mut self, route: RouteConfig) -> Self {
+        self.proxy.routes.push(route);
+        self
+    }
+
+    /// Join a Docker network
+    pub fn network(mut self, network: impl Into<String>) -> Self {
+        self.proxy.networks.push(network.into());
+        self
+    }
+
+    /// Override the container command
+    pub fn command(mut self, command: CommandConfig) -> Self {
+        self.proxy.command = Some(command);
+        self
+    }
+
+    /// Set an environment variable
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.proxy.environment.insert(key.into(), value.into());
+        self
+    }
+
+    /// Validate and return the proxy
+    ///
+    /// # Errors
+    /// Returns error if the proxy is invalid
+    pub fn build(self) -> Result<ProxyConfig> {
+        if self.proxy.name.trim().is_empty() {
+            return Err(CerberusError::validation("Proxy name cannot be empty"));
+        }
+        validate_proxy(&self.proxy)?;
+        Ok(self.proxy)
+    }
+}
+
+/// Builder for [`ServiceConfig`]
+#[derive(Debug, Clone)]
+pub struct ServiceConfigBuilder {
+    service: ServiceConfig,
+}
+
+impl ServiceConfigBuilder {
+    /// Start a service served on a domain
+    pub fn new(
+        name: impl Into<String>,
+        domain: impl Into<String>,
+        upstream: impl Into<String>,
+    ) -> Self {
+        let mut table = toml::Table::new();
+        table.insert("name".to_string(), name.into().into());
+        table.insert("domain".to_string(), domain.into().into());
+        table.insert("upstream".to_string(), upstream.into().into());
+
+        Self {
+            service: from_required(table),
+        }
+    }
+
+    /// Also serve another domain; `*.example.com` matches one label
+    pub fn alias(mut self, domain: impl Into<String>) -> Self {
+        self.service.domains.push(domain.into());
+        self
+    }
+
+    /// Enable WebSocket support
+    pub fn websocket(mut self, websocket: bool) -> Self {
+        self.service.websocket = websocket;
+        self
+    }
+
+    /// Enable response compression
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.service.compression.enabled = compress;
+        self
+    }
+
+    /// Set the compression algorithms, levels, and types
+    pub fn compression(mut self, compression: CompressionConfig) -> Self {
+        self.service.compression = compression;
+        self
+    }
+
+    /// Set the maximum request body size
+    pub fn max_body_size(mut self, size: impl Into<String>) -> Self {
+        self.service.max_body_size = size.into();
+        self
+    }
+
+    /// Allow an HTTP method (all methods are allowed when none are set)
+    pub fn allow_method(mut self, method: impl Into<String>) -> Self {
+        self.service.allowed_methods.push(method.into());
+        self
+    }
+
+    /// Run the service from an image
+    pub fn image(mut self, image: impl Into<String>) -> Self {
+        self.service.image = Some(image.into());
+        self
+    }
+
+    /// Publish a port (`host:container`)
+    pub fn port(mut self, port: impl Into<String>) -> Self {
+        self.service.ports.push(port.into());
+        self
+    }
+
+    /// Set an environment variable
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.service.environment.insert(key.into(), value.into());
+        self
+    }
+
+    /// Mount a volume
+    pub fn volume(mut self, volume: impl Into<String>) -> Self {
+        self.service.volumes.push(volume.into());
+        self
+    }
+
+    /// Set the number of replicas
+    pub fn replicas(mut self, replicas: u32) -> Self {
+        self.service.replicas = Some(replicas);
+        self
+    }
+
+    /// Validate and return the service
+    ///
+    /// References to other services (such as `depends_on`) are checked when
+    /// the whole configuration is built.
+    ///
+    /// # Errors
+    /// Returns error if the service is invalid
+    pub fn build(self) -> Result<ServiceConfig> {
+        if self.service.name.trim().is_empty() {
+            return Err(CerberusError::validation("Service name cannot be empty"));
+        }
+        validate_service(&self.service)?;
+        Ok(self.service)
+    }
+}
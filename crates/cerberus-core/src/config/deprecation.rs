@@ -0,0 +1,600 @@
+//! # Deprecated configuration keys and schema versions
+//!
+//! Renamed keys stay readable through an entry in [`DEPRECATIONS`]; loading
+//! moves them to their replacement before the configuration is read. Every
+//! deprecated key found is recorded in
+//! [`Config::deprecations`](super::Config::deprecations): `cerberus validate`
+//! reports them as `deprecated-key` lint findings, `cerberus generate` logs
+//! them, and `cerberus migrate` renames them in place.
+//!
+//! Each breaking change of the configuration format bumps [`SCHEMA_VERSION`]
+//! and adds a step to [`MIGRATIONS`]. A configuration declares the version
+//! it is written for with the top-level `schema_version`; `cerberus migrate`
+//! runs the steps after that version and records the current one. Files
+//! without `schema_version` are read as version 1.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use toml_edit::{Decor, DocumentMut, Item, Key, Table, TableLike};
+
+use crate::{CerberusError, Result};
+
+/// A renamed configuration key
+///
+/// A trailing `*` in the old key matches every key with that prefix. The
+/// matched part is read as a snake_case header name and written in
+/// `Header-Case` where the replacement has `*`, so
+/// `headers_request_x_tenant` becomes `headers.request.set.X-Tenant`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Deprecation {
+    /// Old key, dotted; `[]` stands for every entry of an array of tables
+    pub key: &'static str,
+    /// Key that replaces it, in the same table or a table below it
+    pub replacement: &'static str,
+    /// Version the old key was deprecated in
+    pub since: &'static str,
+    /// Version that stops reading the old key
+    pub removal: &'static str,
+    /// Schema version whose migration renames the key
+    pub schema_version: u32,
+}
+
+/// Configuration schema version written by `cerberus migrate`
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Top-level key declaring the schema version of a configuration
+pub(super) const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// A step upgrading configurations to a schema version
+#[derive(Debug, Clone, Copy)]
+pub struct SchemaMigration {
+    /// Schema version the step upgrades to
+    pub version: u32,
+    /// What changed in this version
+    pub summary: &'static str,
+    apply: fn(&mut DocumentMut),
+}
+
+/// Every schema migration, in version order
+pub const MIGRATIONS: &[SchemaMigration] = &[
+    SchemaMigration {
+        version: 2,
+        summary: "proxies[].upstream is renamed to proxies[].default_upstream",
+        apply: rename_deprecated::<2>,
+    },
+    SchemaMigration {
+        version: 3,
+        summary: "flat headers_request_*/headers_response_* keys move to [services.headers] \
+                  and compress to [services.compression]",
+        apply: rename_deprecated::<3>,
+    },
+];
+
+/// Every deprecated key, oldest first
+pub const DEPRECATIONS: &[Deprecation] = &[
+    Deprecation {
+        key: "proxies[].upstream",
+        replacement: "proxies[].default_upstream",
+        since: "0.1.0",
+        removal: "0.3.0",
+        schema_version: 2,
+    },
+    Deprecation {
+        key: "services[].headers_request_*",
+        replacement: "services[].headers.request.set.*",
+        since: "0.2.0",
+        removal: "0.4.0",
+        schema_version: 3,
+    },
+    Deprecation {
+        key: "services[].headers_response_*",
+        replacement: "services[].headers.response.set.*",
+        since: "0.2.0",
+        removal: "0.4.0",
+        schema_version: 3,
+    },
+    Deprecation {
+        key: "services[].compress",
+        replacement: "services[].compression.enabled",
+        since: "0.2.0",
+        removal: "0.4.0",
+        schema_version: 3,
+    },
+];
+
+/// A deprecated key found in a configuration
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DeprecatedKey {
+    /// Where the key was found, e.g. `proxies[0].upstream`
+    pub key: String,
+    /// Key to use instead, e.g. `proxies[0].default_upstream`
+    pub replacement: String,
+    /// Version the key was deprecated in
+    pub since: &'static str,
+    /// Version that stops reading the key
+    pub removal: &'static str,
+    /// Schema version whose migration renames the key
+    pub schema_version: u32,
+}
+
+impl DeprecatedKey {
+    /// Warning explaining what to change and by when
+    pub fn message(&self) -> String {
+        format!(
+            "{} is deprecated since {} and will be removed in {}; use {} (`cerberus migrate` rewrites it)",
+            self.key, self.since, self.removal, self.replacement
+        )
+    }
+}
+
+impl Deprecation {
+    /// Table path, the old key name, and the replacement's path below the table
+    fn split(&self) -> (Vec<&'static str>, &'static str, Vec<&'static str>) {
+        let (parent, old) = self.key.rsplit_once('.').unwrap_or(("", self.key));
+        let new = self
+            .replacement
+            .strip_prefix(parent)
+            .unwrap_or(self.replacement)
+            .split('.')
+            .filter(|part| !part.is_empty())
+            .collect();
+        let parent = parent.split('.').filter(|part| !part.is_empty()).collect();
+        (parent, old, new)
+    }
+}
+
+/// Replacement path of a key when the old key name matches it
+fn replacement(old: &str, new: &[&str], key: &str) -> Option<Vec<String>> {
+    let matched = match old.strip_suffix('*') {
+        Some(prefix) => Some(header_name(
+            key.strip_prefix(prefix).filter(|name| !name.is_empty())?,
+        )),
+        None if key == old => None,
+        None => return None,
+    };
+    Some(
+        new.iter()
+            .map(|part| match (&matched, *part) {
+                (Some(name), "*") => name.clone(),
+                _ => part.to_string(),
+            })
+            .collect(),
+    )
+}
+
+/// `x_tenant_id` as a header name, `X-Tenant-Id`
+fn header_name(key: &str) -> String {
+    key.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_ascii_uppercase().to_string() + chars.as_str()
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Value at a path below a table
+fn lookup<'a>(table: &'a toml::Table, path: &[String]) -> Option<&'a toml::Value> {
+    let (last, parents) = path.split_last()?;
+    let mut table = table;
+    for part in parents {
+        table = table.get(part)?.as_table()?;
+    }
+    table.get(last)
+}
+
+/// Schema version a configuration table declares, 1 when it sets none
+///
+/// # Errors
+/// Returns error if `schema_version` is not a supported version
+pub(crate) fn declared_version(table: &toml::Table) -> Result<u32> {
+    match table.get(SCHEMA_VERSION_KEY) {
+        None => Ok(1),
+        Some(value) => {
+            let version = value
+                .as_integer()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(|| {
+                    CerberusError::validation(format!(
+                        "schema_version must be a version number, got {value}"
+                    ))
+                })?;
+            validate_schema_version(version)?;
+            Ok(version)
+        }
+    }
+}
+
+/// Check that a declared schema version can be read
+///
+/// # Errors
+/// Returns error if the version is 0 or newer than [`SCHEMA_VERSION`]
+pub(crate) fn validate_schema_version(version: u32) -> Result<()> {
+    if version == 0 {
+        return Err(CerberusError::validation(
+            "schema_version must be at least 1",
+        ));
+    }
+    if version > SCHEMA_VERSION {
+        return Err(CerberusError::validation(format!(
+            "schema_version {version} is newer than this cerberus supports ({SCHEMA_VERSION}); upgrade cerberus"
+        )));
+    }
+    Ok(())
+}
+
+/// Deprecated keys used by a configuration table
+///
+/// # Errors
+/// Returns error if a table sets both a deprecated key and its replacement,
+/// or uses a key its declared schema version no longer has
+pub(crate) fn deprecated_keys(table: &toml::Table) -> Result<Vec<DeprecatedKey>> {
+    let version = declared_version(table)?;
+    let mut found = Vec::new();
+    for deprecation in DEPRECATIONS {
+        let (parent, old, new) = deprecation.split();
+        for (path, table) in tables(table, &parent, String::new()) {
+            for name in table.keys() {
+                let Some(new_path) = replacement(old, &new, name) else {
+                    continue;
+                };
+                let key = join(&path, name);
+                let replacement = join(&path, &new_path.join("."));
+                if lookup(table, &new_path).is_some() {
+                    return Err(CerberusError::validation(format!(
+                        "{key} and {replacement} are both set; remove the deprecated {key}"
+                    )));
+                }
+                if deprecation.schema_version <= version {
+                    return Err(CerberusError::validation(format!(
+                        "{key} was replaced by {replacement} in schema version {}",
+                        deprecation.schema_version
+                    )));
+                }
+                found.push(DeprecatedKey {
+                    key,
+                    replacement,
+                    since: deprecation.since,
+                    removal: deprecation.removal,
+                    schema_version: deprecation.schema_version,
+                });
+            }
+        }
+    }
+    Ok(found)
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// Tables at a path, with their concrete paths
+fn tables<'a>(
+    table: &'a toml::Table,
+    parent: &[&str],
+    path: String,
+) -> Vec<(String, &'a toml::Table)> {
+    let Some((first, rest)) = parent.split_first() else {
+        return vec![(path, table)];
+    };
+    let (name, each) = match first.strip_suffix("[]") {
+        Some(name) => (name, true),
+        None => (*first, false),
+    };
+    let path = join(&path, name);
+    match (table.get(name), each) {
+        (Some(toml::Value::Table(table)), false) => tables(table, rest, path),
+        (Some(toml::Value::Array(entries)), true) => entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| Some((index, entry.as_table()?)))
+            .flat_map(|(index, table)| tables(table, rest, format!("{path}[{index}]")))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Move deprecated keys of a configuration table to their replacements
+///
+/// Run after [`deprecated_keys`] has rejected tables that set both.
+pub(crate) fn upgrade(table: &mut toml::Table) {
+    for deprecation in DEPRECATIONS {
+        let (parent, old, new) = deprecation.split();
+        upgrade_in(table, &parent, old, &new);
+    }
+}
+
+/// Move deprecated keys in every table at a path
+fn upgrade_in(table: &mut toml::Table, parent: &[&str], old: &str, new: &[&str]) {
+    let Some((first, rest)) = parent.split_first() else {
+        let moves: Vec<(String, Vec<String>)> = table
+            .keys()
+            .filter_map(|key| Some((key.clone(), replacement(old, new, key)?)))
+            .collect();
+        for (key, new_path) in moves {
+            let Some(value) = table.remove(&key) else {
+                continue;
+            };
+            let (last, parents) = new_path.split_last().expect("replacement is not empty");
+            let mut target = &mut *table;
+            for part in parents {
+                let entry = target
+                    .entry(part.clone())
+                    .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+                let Some(next) = entry.as_table_mut() else {
+                    return;
+                };
+                target = next;
+            }
+            target.insert(last.clone(), value);
+        }
+        return;
+    };
+    match (
+        table.get_mut(first.trim_end_matches("[]")),
+        first.ends_with("[]"),
+    ) {
+        (Some(toml::Value::Table(table)), false) => upgrade_in(table, rest, old, new),
+        (Some(toml::Value::Array(entries)), true) => {
+            for table in entries.iter_mut().filter_map(toml::Value::as_table_mut) {
+                upgrade_in(table, rest, old, new);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Result of migrating a configuration and its included files
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationReport {
+    /// Schema version the configuration declared
+    pub from: u32,
+    /// Schema version it declares now, [`SCHEMA_VERSION`]
+    pub to: u32,
+    /// Renamed keys, with the file each was found in
+    pub renamed: Vec<(PathBuf, DeprecatedKey)>,
+}
+
+/// Migrate a configuration file and the files it includes in place
+///
+/// Included files follow the schema version of the main file, which is set
+/// to [`SCHEMA_VERSION`]. Files are only written when they change.
+///
+/// # Errors
+/// Returns error if a file cannot be read, parsed, or written
+pub fn migrate(path: &Path) -> Result<MigrationReport> {
+    let content = std::fs::read_to_string(path).map_err(|e| CerberusError::io(path, e))?;
+    let from = declared_version(&parse(&content)?)?;
+    let mut renamed = Vec::new();
+
+    let (updated, keys) = migrate_document(&content, from, true)?;
+    write_changed(path, &content, &updated)?;
+    renamed.extend(keys.into_iter().map(|key| (path.to_path_buf(), key)));
+
+    for file in super::included_files(path)? {
+        let content = std::fs::read_to_string(&file).map_err(|e| CerberusError::io(&file, e))?;
+        let (updated, keys) = migrate_document(&content, from, false)?;
+        write_changed(&file, &content, &updated)?;
+        renamed.extend(keys.into_iter().map(|key| (file.clone(), key)));
+    }
+
+    Ok(MigrationReport {
+        from,
+        to: SCHEMA_VERSION,
+        renamed,
+    })
+}
+
+fn write_changed(path: &Path, content: &str, updated: &str) -> Result<()> {
+    if updated != content {
+        std::fs::write(path, updated).map_err(|e| CerberusError::io(path, e))?;
+    }
+    Ok(())
+}
+
+fn parse(content: &str) -> Result<toml::Table> {
+    toml::from_str(content)
+        .map_err(|e| CerberusError::config(format!("Failed to parse configuration: {e}")))
+}
+
+/// Migrate configuration file content to [`SCHEMA_VERSION`]
+///
+/// Runs the [`MIGRATIONS`] after the declared schema version and sets
+/// `schema_version`. Comments, formatting, and the position of each renamed
+/// key are kept.
+///
+/// # Errors
+/// Returns error if the content cannot be parsed or sets a deprecated key
+/// together with its replacement
+pub fn migrate_content(content: &str) -> Result<(String, Vec<DeprecatedKey>)> {
+    let from = declared_version(&parse(content)?)?;
+    migrate_document(content, from, true)
+}
+
+/// Run the migrations after a schema version on file content
+///
+/// Only the main file records the version; included files would set it twice.
+fn migrate_document(
+    content: &str,
+    from: u32,
+    record_version: bool,
+) -> Result<(String, Vec<DeprecatedKey>)> {
+    let renamed: Vec<DeprecatedKey> = deprecated_keys(&parse(content)?)?
+        .into_iter()
+        .filter(|key| key.schema_version > from)
+        .collect();
+
+    let mut document: DocumentMut = content
+        .parse()
+        .map_err(|e| CerberusError::config(format!("Failed to parse configuration: {e}")))?;
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > from)
+    {
+        (migration.apply)(&mut document);
+    }
+    if record_version {
+        set_schema_version(&mut document);
+    }
+
+    Ok((document.to_string(), renamed))
+}
+
+/// Rename the deprecated keys a schema version replaced
+fn rename_deprecated<const VERSION: u32>(document: &mut DocumentMut) {
+    for deprecation in DEPRECATIONS
+        .iter()
+        .filter(|deprecation| deprecation.schema_version == VERSION)
+    {
+        let (parent, old, new) = deprecation.split();
+        rename_in(document.as_table_mut(), &parent, old, &new);
+    }
+}
+
+/// Set `schema_version` to the current version
+///
+/// A new key goes first, below the comments heading the file; comments
+/// separated from the first item by a blank line are taken as the heading.
+fn set_schema_version(document: &mut DocumentMut) {
+    let version = toml_edit::value(i64::from(SCHEMA_VERSION));
+    let table = document.as_table_mut();
+    if let Some(item) = table.get_mut(SCHEMA_VERSION_KEY) {
+        if item.as_integer() != Some(i64::from(SCHEMA_VERSION)) {
+            *item = version;
+        }
+        return;
+    }
+
+    let has_values = table.iter().any(|(_, item)| item.is_value());
+    let mut heading = String::new();
+    with_first_decor(table, |decor| {
+        let prefix = decor
+            .prefix()
+            .and_then(|prefix| prefix.as_str())
+            .unwrap_or_default()
+            .to_string();
+        match prefix.rfind("\n\n") {
+            Some(end) => {
+                heading = prefix[..end + 2].to_string();
+                decor.set_prefix(format!("\n{}", &prefix[end + 2..]));
+            }
+            // Keep a blank line between the key and the first table
+            None if !has_values => decor.set_prefix(format!("\n{prefix}")),
+            None => {}
+        }
+    });
+
+    table.insert(SCHEMA_VERSION_KEY, version);
+    table.sort_values_by(|a, _, b, _| {
+        (b.get() == SCHEMA_VERSION_KEY).cmp(&(a.get() == SCHEMA_VERSION_KEY))
+    });
+    if let Some(mut key) = table.key_mut(SCHEMA_VERSION_KEY) {
+        key.leaf_decor_mut().set_prefix(heading);
+    }
+}
+
+/// Edit the decor of the item written first: the first top-level value, or
+/// else the first table
+fn with_first_decor(table: &mut Table, edit: impl FnOnce(&mut Decor)) {
+    let first_value = table
+        .iter()
+        .find(|(_, item)| item.is_value())
+        .map(|(key, _)| key.to_string());
+    if let Some(key) = first_value {
+        if let Some(mut key) = table.key_mut(&key) {
+            edit(key.leaf_decor_mut());
+        }
+        return;
+    }
+    if let Some(first) = table
+        .iter_mut()
+        .filter_map(|(_, item)| match item {
+            Item::Table(table) => Some(table),
+            Item::ArrayOfTables(entries) => entries.get_mut(0),
+            _ => None,
+        })
+        .filter(|table| table.position().is_some())
+        .min_by_key(|table| table.position())
+    {
+        edit(first.decor_mut());
+    }
+}
+
+/// Rename a key in every table at a path
+fn rename_in(table: &mut Table, parent: &[&str], old: &str, new: &[&str]) {
+    let Some((first, rest)) = parent.split_first() else {
+        let moves: Vec<(String, Vec<String>)> = table
+            .iter()
+            .filter_map(|(key, _)| Some((key.to_string(), replacement(old, new, key)?)))
+            .collect();
+        for (key, new_path) in moves {
+            match new_path.as_slice() {
+                [new] => rename(table, &key, new),
+                _ => move_below(table, &key, &new_path),
+            }
+        }
+        return;
+    };
+    match (
+        table.get_mut(first.trim_end_matches("[]")),
+        first.ends_with("[]"),
+    ) {
+        (Some(Item::Table(table)), false) => rename_in(table, rest, old, new),
+        (Some(Item::ArrayOfTables(entries)), true) => {
+            for table in entries.iter_mut() {
+                rename_in(table, rest, old, new);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Rename a key, keeping its value, decoration, and position
+fn rename(table: &mut Table, old: &str, new: &str) {
+    let order: Vec<String> = table
+        .iter()
+        .map(|(key, _)| if key == old { new } else { key }.to_string())
+        .collect();
+    let Some((key, item)) = table.remove_entry(old) else {
+        return;
+    };
+    table.insert_formatted(
+        &Key::new(new).with_leaf_decor(key.leaf_decor().clone()),
+        item,
+    );
+    let position = |key: &Key| order.iter().position(|name| name == key.get());
+    table.sort_values_by(|a, _, b, _| position(a).cmp(&position(b)));
+}
+
+/// Move a key into a table below, written as a dotted key
+/// (`headers.request.set.X-Tenant = "acme"`) unless the table already exists
+fn move_below(table: &mut Table, old: &str, new_path: &[String]) {
+    let Some((key, item)) = table.remove_entry(old) else {
+        return;
+    };
+    let (last, parents) = new_path.split_last().expect("replacement is not empty");
+    let mut target: &mut dyn TableLike = table;
+    for part in parents {
+        let entry = target.entry(part).or_insert_with(|| {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            table.set_dotted(true);
+            Item::Table(table)
+        });
+        let Some(next) = entry.as_table_like_mut() else {
+            return;
+        };
+        target = next;
+    }
+    target.insert(last, item);
+    if let Some(mut new_key) = target.key_mut(last) {
+        new_key.leaf_decor_mut().clone_from(key.leaf_decor());
+    }
+}
@@ -0,0 +1,432 @@
+//! # Configuration inheritance
+//!
+//! Resolves the top-level `extends` directive. The base configuration is a
+//! local path (relative to the extending file) or a file in a git repository
+//! written as `host/owner/repo/path/to/config.toml@ref`:
+//!
+//! ```toml
+//! extends = "github.com/ourorg/cerberus-base/config.toml@v3"
+//! ```
+//!
+//! The local configuration is laid over the base: tables are merged key by
+//! key, local values replace base values, and entries of arrays of tables
+//! (`[[proxies]]`, `[[services]]`) with the same `name` are merged while new
+//! ones are appended. Other arrays are replaced as a whole.
+//!
+//! The commit a remote reference resolved to is pinned in `cerberus.lock`
+//! next to the configuration, together with the SHA-256 of the base file, so
+//! every machine builds from the same base until `cerberus lock` moves the
+//! pin. Fetched bases are cached in `.cerberus/extends/`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::checksum::sha256_hex;
+use crate::{CerberusError, Result};
+
+/// Top-level key naming the base configuration
+pub(super) const EXTENDS_KEY: &str = "extends";
+
+/// Lockfile pinning the base revision, next to the configuration file
+pub const LOCK_FILE: &str = "cerberus.lock";
+
+/// Where a base configuration comes from
+#[derive(Debug, Clone, PartialEq)]
+enum BaseSource {
+    /// File on disk, relative to the extending file
+    Local(PathBuf),
+    /// File in a git repository at a tag, branch, or commit
+    Remote {
+        repository: String,
+        path: String,
+        reference: String,
+    },
+}
+
+impl BaseSource {
+    /// Parse an `extends` value
+    ///
+    /// Values starting with `.` or `/`, or without an `@ref`, are local paths.
+    fn parse(spec: &str) -> Result<Self> {
+        let remote = spec
+            .strip_prefix("https://")
+            .unwrap_or(spec)
+            .rsplit_once('@')
+            .filter(|_| !spec.starts_with(['.', '/']));
+        let Some((location, reference)) = remote else {
+            return Ok(Self::Local(PathBuf::from(spec)));
+        };
+
+        let mut segments = location.splitn(4, '/');
+        match (
+            segments.next(),
+            segments.next(),
+            segments.next(),
+            segments.next(),
+        ) {
+            (Some(host), Some(owner), Some(repo), Some(path))
+                if host.contains('.')
+                    && !owner.is_empty()
+                    && !repo.is_empty()
+                    && !path.is_empty()
+                    && !reference.is_empty() =>
+            {
+                Ok(Self::Remote {
+                    repository: format!("https://{host}/{owner}/{repo}"),
+                    path: path.to_string(),
+                    reference: reference.to_string(),
+                })
+            }
+            _ => Err(CerberusError::config(format!(
+                "extends '{spec}' must be a local path or host/owner/repo/path/to/config.toml@ref"
+            ))),
+        }
+    }
+}
+
+/// Contents of `cerberus.lock`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct Lockfile {
+    extends: LockedBase,
+}
+
+/// Pinned revision of a remote base configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LockedBase {
+    /// `extends` value the pin was resolved from
+    pub source: String,
+    /// Repository the base is fetched from
+    pub repository: String,
+    /// Commit the reference resolved to
+    pub revision: String,
+    /// SHA-256 of the base file at that commit
+    pub sha256: String,
+}
+
+/// Lay the configuration over the base it extends
+///
+/// `merged` is the configuration with its includes merged, if it has any.
+/// Returns `None` when there is nothing to extend and no includes, so the
+/// file can be deserialized directly and keep line numbers in its errors.
+pub(crate) fn based(
+    path: &Path,
+    content: &str,
+    merged: Option<toml::Table>,
+) -> Result<Option<toml::Table>> {
+    let mut local = match merged {
+        Some(table) => table,
+        None => {
+            let table: toml::Table =
+                toml::from_str(content).map_err(|e| CerberusError::toml_parse(path, e))?;
+            if !table.contains_key(EXTENDS_KEY) {
+                return Ok(None);
+            }
+            table
+        }
+    };
+    let Some(spec) = local.remove(EXTENDS_KEY) else {
+        return Ok(Some(local));
+    };
+    let spec = spec.as_str().ok_or_else(|| {
+        CerberusError::config(format!(
+            "{}: extends must be a path or repository reference",
+            path.display()
+        ))
+    })?;
+
+    let mut base = load_base(path, spec, false)?;
+    overlay(&mut base, local);
+    Ok(Some(base))
+}
+
+/// Base table a configuration extends, with the file or reference it came from
+///
+/// # Errors
+/// Returns error if the configuration or its base cannot be read
+pub(super) fn base_table(path: &Path) -> Result<Option<(String, toml::Table)>> {
+    let content = std::fs::read_to_string(path).map_err(|e| CerberusError::io(path, e))?;
+    let table: toml::Table =
+        toml::from_str(&content).map_err(|e| CerberusError::toml_parse(path, e))?;
+    let Some(spec) = table.get(EXTENDS_KEY).and_then(toml::Value::as_str) else {
+        return Ok(None);
+    };
+    let origin = match BaseSource::parse(spec)? {
+        BaseSource::Local(base) => config_dir(path).join(base).display().to_string(),
+        BaseSource::Remote { .. } => spec.to_string(),
+    };
+    Ok(Some((origin, load_base(path, spec, false)?)))
+}
+
+/// Local base file of a configuration, for watching
+///
+/// # Errors
+/// Returns error if the configuration cannot be read or parsed
+pub fn local_base(path: &Path) -> Result<Option<PathBuf>> {
+    let content = std::fs::read_to_string(path).map_err(|e| CerberusError::io(path, e))?;
+    let table: toml::Table =
+        toml::from_str(&content).map_err(|e| CerberusError::toml_parse(path, e))?;
+    let Some(spec) = table.get(EXTENDS_KEY).and_then(toml::Value::as_str) else {
+        return Ok(None);
+    };
+    Ok(match BaseSource::parse(spec)? {
+        BaseSource::Local(base) => Some(config_dir(path).join(base)),
+        BaseSource::Remote { .. } => None,
+    })
+}
+
+/// Resolve the configuration's remote base again and rewrite the lockfile
+///
+/// Returns `None` when the configuration does not extend a remote base.
+///
+/// # Errors
+/// Returns error if the reference cannot be resolved or fetched
+pub fn update_lock(path: &Path) -> Result<Option<LockedBase>> {
+    let content = std::fs::read_to_string(path).map_err(|e| CerberusError::io(path, e))?;
+    let table: toml::Table =
+        toml::from_str(&content).map_err(|e| CerberusError::toml_parse(path, e))?;
+    let Some(spec) = table.get(EXTENDS_KEY).and_then(toml::Value::as_str) else {
+        return Ok(None);
+    };
+    if matches!(BaseSource::parse(spec)?, BaseSource::Local(_)) {
+        return Ok(None);
+    }
+
+    load_base(path, spec, true)?;
+    Ok(read_lock(&config_dir(path))?.map(|lock| lock.extends))
+}
+
+fn config_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Read and parse the base configuration, pinning remote bases
+fn load_base(path: &Path, spec: &str, update: bool) -> Result<toml::Table> {
+    let (content, origin) = match BaseSource::parse(spec)? {
+        BaseSource::Local(base) => {
+            let file = config_dir(path).join(base);
+            if !file.is_file() {
+                return Err(CerberusError::config(format!(
+                    "{}: base configuration {} does not exist",
+                    path.display(),
+                    file.display()
+                )));
+            }
+            let content =
+                std::fs::read_to_string(&file).map_err(|e| CerberusError::io(&file, e))?;
+            (content, file)
+        }
+        BaseSource::Remote {
+            repository,
+            path: file,
+            reference,
+        } => {
+            let dir = config_dir(path);
+            let content = remote_base(&dir, spec, &repository, &file, &reference, update)?;
+            (content, PathBuf::from(spec))
+        }
+    };
+
+    let base: toml::Table =
+        toml::from_str(&content).map_err(|e| CerberusError::toml_parse(&origin, e))?;
+    if base.contains_key(EXTENDS_KEY) || base.contains_key("include") {
+        return Err(CerberusError::config(format!(
+            "{}: a base configuration cannot use extends or include",
+            origin.display()
+        )));
+    }
+    Ok(base)
+}
+
+/// Content of a remote base at its pinned revision
+///
+/// The lockfile is written when it is missing, names another source, or
+/// `update` asks to resolve the reference again.
+fn remote_base(
+    dir: &Path,
+    spec: &str,
+    repository: &str,
+    file: &str,
+    reference: &str,
+    update: bool,
+) -> Result<String> {
+    let pinned = read_lock(dir)?
+        .map(|lock| lock.extends)
+        .filter(|locked| !update && locked.source == spec);
+
+    let cache = dir.join(".cerberus").join("extends");
+    if let Some(locked) = &pinned
+        && let Ok(content) = std::fs::read_to_string(cache.join(format!("{}.toml", locked.sha256)))
+        && sha256_hex(content.as_bytes()) == locked.sha256
+    {
+        return Ok(content);
+    }
+
+    let revision = match &pinned {
+        Some(locked) => locked.revision.clone(),
+        None => resolve_reference(repository, reference)?,
+    };
+    let content = fetch_file(repository, &revision, file)?;
+    let sha256 = sha256_hex(content.as_bytes());
+
+    match &pinned {
+        Some(locked) if locked.sha256 != sha256 => {
+            return Err(CerberusError::config(format!(
+                "{spec}: base at pinned revision {revision} does not match the checksum in {LOCK_FILE}"
+            )));
+        }
+        Some(_) => {}
+        None => write_lock(
+            dir,
+            &LockedBase {
+                source: spec.to_string(),
+                repository: repository.to_string(),
+                revision,
+                sha256: sha256.clone(),
+            },
+        )?,
+    }
+
+    std::fs::create_dir_all(&cache).map_err(|e| CerberusError::io(&cache, e))?;
+    let cached = cache.join(format!("{sha256}.toml"));
+    std::fs::write(&cached, &content).map_err(|e| CerberusError::io(&cached, e))?;
+    Ok(content)
+}
+
+fn read_lock(dir: &Path) -> Result<Option<Lockfile>> {
+    let path = dir.join(LOCK_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path).map_err(|e| CerberusError::io(&path, e))?;
+    toml::from_str(&content)
+        .map(Some)
+        .map_err(|e| CerberusError::toml_parse(&path, e))
+}
+
+fn write_lock(dir: &Path, locked: &LockedBase) -> Result<()> {
+    let path = dir.join(LOCK_FILE);
+    let lock = toml::to_string(&Lockfile {
+        extends: locked.clone(),
+    })
+    .map_err(|e| CerberusError::config(format!("Failed to serialize {LOCK_FILE}: {e}")))?;
+    let content = format!(
+        "# Generated by Cerberus: pins the base configuration of `extends`.\n# Run `cerberus lock` to move the pin.\n\n{lock}"
+    );
+    std::fs::write(&path, content).map_err(|e| CerberusError::io(&path, e))
+}
+
+/// Commit a tag, branch, or commit of a repository points to
+fn resolve_reference(repository: &str, reference: &str) -> Result<String> {
+    if reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(reference.to_string());
+    }
+
+    let peeled = format!("{reference}^{{}}");
+    let output = git(None, &["ls-remote", repository, reference, &peeled])?;
+    parse_ls_remote(&output, reference).ok_or_else(|| {
+        CerberusError::config(format!(
+            "extends: {reference} was not found in {repository}"
+        ))
+    })
+}
+
+/// Pick the commit of a reference from `git ls-remote` output
+///
+/// Annotated tags are listed twice; the peeled `^{}` line names the commit.
+fn parse_ls_remote(output: &str, reference: &str) -> Option<String> {
+    let refs: Vec<(&str, &str)> = output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .collect();
+    refs.iter()
+        .find(|(_, name)| name.ends_with("^{}"))
+        .or_else(|| {
+            refs.iter()
+                .find(|(_, name)| *name == format!("refs/tags/{reference}"))
+        })
+        .or_else(|| refs.first())
+        .map(|(commit, _)| commit.to_string())
+}
+
+/// Read one file of a repository at a commit
+fn fetch_file(repository: &str, revision: &str, file: &str) -> Result<String> {
+    let checkout = std::env::temp_dir().join(format!(
+        "cerberus-extends-{}-{revision}",
+        std::process::id()
+    ));
+    let result = (|| {
+        git(None, &["init", "-q", &checkout.to_string_lossy()])?;
+        git(
+            Some(&checkout),
+            &["fetch", "-q", "--depth", "1", repository, revision],
+        )?;
+        git(Some(&checkout), &["show", &format!("{revision}:{file}")])
+    })();
+    let _ = std::fs::remove_dir_all(&checkout);
+    result
+}
+
+fn git(dir: Option<&Path>, args: &[&str]) -> Result<String> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.arg("-C").arg(dir);
+    }
+    let output = command
+        .args(args)
+        .output()
+        .map_err(|e| CerberusError::io("git", e))?;
+    if !output.status.success() {
+        return Err(CerberusError::config(format!(
+            "extends: git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Lay the local configuration over its base
+fn overlay(base: &mut toml::Table, local: toml::Table) {
+    for (key, value) in local {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(table)) => {
+                overlay(existing, table);
+            }
+            (Some(toml::Value::Array(existing)), toml::Value::Array(array))
+                if is_named(existing) && is_named(&array) =>
+            {
+                for entry in array {
+                    let name = entry.get("name").cloned();
+                    match existing
+                        .iter_mut()
+                        .find(|candidate| candidate.get("name") == name.as_ref())
+                    {
+                        Some(toml::Value::Table(existing)) => {
+                            if let toml::Value::Table(entry) = entry {
+                                overlay(existing, entry);
+                            }
+                        }
+                        _ => existing.push(entry),
+                    }
+                }
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Whether every entry of an array is a table with a `name`
+fn is_named(array: &[toml::Value]) -> bool {
+    array.iter().all(|entry| {
+        entry
+            .as_table()
+            .is_some_and(|table| table.contains_key("name"))
+    })
+}
@@ -0,0 +1,183 @@
+//! # Configuration includes
+//!
+//! Resolves the top-level `include = ["conf.d/*.toml"]` directive. Included
+//! files are merged into the main file in order: patterns as listed, and the
+//! files matched by one pattern sorted by name. Tables are merged key by key
+//! and arrays of tables (`[[proxies]]`, `[[services]]`) are appended; setting
+//! the same value in two files is an error naming the second file.
+
+use std::path::{Path, PathBuf};
+
+use crate::{CerberusError, Result};
+
+/// Top-level key listing the files to merge into the main configuration
+pub(super) const INCLUDE_KEY: &str = "include";
+
+/// Merge everything a configuration file includes into its table
+///
+/// Returns `None` when the file has no `include`, so it can be deserialized
+/// directly and keep line numbers in its error messages.
+pub(crate) fn merged(path: &Path, content: &str) -> Result<Option<toml::Table>> {
+    let mut table: toml::Table =
+        toml::from_str(content).map_err(|e| CerberusError::toml_parse(path, e))?;
+    let Some(include) = table.remove(INCLUDE_KEY) else {
+        return Ok(None);
+    };
+
+    for file in resolve(path, &patterns(path, include)?)? {
+        let included = read_table(&file)?;
+        if included.contains_key(INCLUDE_KEY) {
+            return Err(CerberusError::config(format!(
+                "{}: include is only supported in the main configuration file",
+                file.display()
+            )));
+        }
+        merge(&mut table, included, "", &file)?;
+    }
+
+    Ok(Some(table))
+}
+
+/// Files included by a configuration file, in merge order
+///
+/// # Errors
+/// Returns error if the file cannot be parsed or an include cannot be resolved
+pub fn included_files(path: &Path) -> Result<Vec<PathBuf>> {
+    match read_table(path)?.remove(INCLUDE_KEY) {
+        Some(include) => resolve(path, &patterns(path, include)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn read_table(path: &Path) -> Result<toml::Table> {
+    let content = std::fs::read_to_string(path).map_err(|e| CerberusError::io(path, e))?;
+    toml::from_str(&content).map_err(|e| CerberusError::toml_parse(path, e))
+}
+
+/// Read the `include` value as a list of patterns
+fn patterns(path: &Path, include: toml::Value) -> Result<Vec<String>> {
+    let invalid = || {
+        CerberusError::config(format!(
+            "{}: include must be a list of file paths or patterns",
+            path.display()
+        ))
+    };
+
+    include
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|pattern| pattern.as_str().map(str::to_string).ok_or_else(invalid))
+        .collect()
+}
+
+/// Expand include patterns relative to the including file's directory
+///
+/// Wildcards (`*`, `?`) are supported in the file name only. A literal path
+/// must exist; a pattern may match nothing.
+fn resolve(path: &Path, patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let base = path.parent().unwrap_or(Path::new(""));
+    let mut files = Vec::new();
+
+    for pattern in patterns {
+        let full = base.join(pattern);
+        let name = full
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if !name.contains(['*', '?']) {
+            if !full.is_file() {
+                return Err(CerberusError::config(format!(
+                    "{}: included file {} does not exist",
+                    path.display(),
+                    full.display()
+                )));
+            }
+            files.push(full);
+            continue;
+        }
+
+        let dir = full.parent().unwrap_or(Path::new(""));
+        let lookup = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let entries = std::fs::read_dir(lookup).map_err(|e| CerberusError::io(lookup, e))?;
+        let mut matched: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .filter(|entry| wildcard_match(&name, &entry.file_name().to_string_lossy()))
+            .map(|entry| dir.join(entry.file_name()))
+            .collect();
+        matched.sort();
+        files.extend(matched);
+    }
+
+    files.dedup();
+    Ok(files)
+}
+
+/// Match a file name against a pattern with `*` and `?` wildcards
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Last `*` seen and the name position it currently covers up to
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Merge an included table into the accumulated configuration
+fn merge(target: &mut toml::Table, source: toml::Table, prefix: &str, file: &Path) -> Result<()> {
+    for (key, value) in source {
+        let name = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        match (target.get_mut(&key), value) {
+            (None, value) => {
+                target.insert(key, value);
+            }
+            (Some(toml::Value::Table(existing)), toml::Value::Table(table)) => {
+                merge(existing, table, &name, file)?;
+            }
+            (Some(toml::Value::Array(existing)), toml::Value::Array(array)) => {
+                existing.extend(array);
+            }
+            (Some(_), _) => {
+                return Err(CerberusError::config(format!(
+                    "{}: {name} is already set by an earlier configuration file",
+                    file.display()
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
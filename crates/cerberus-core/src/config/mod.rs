@@ -0,0 +1,8726 @@
+//! # Configuration management for Cerberus
+//!
+//! This module handles loading, parsing, and validating TOML configuration files.
+//! It provides type-safe access to all configuration options with sensible defaults.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::{CerberusError, Result};
+
+mod app_templates;
+mod builder;
+mod deprecation;
+mod extends;
+mod include;
+mod provenance;
+#[cfg(feature = "schema")]
+mod schema;
+pub use app_templates::{
+    AppTemplate, MISSKEY_FEDERATION_PATHS, starter_config, write_starter_config,
+};
+pub use builder::{ConfigBuilder, ProxyConfigBuilder, ServiceConfigBuilder};
+pub use deprecation::{
+    DEPRECATIONS, DeprecatedKey, Deprecation, MIGRATIONS, MigrationReport, SCHEMA_VERSION,
+    SchemaMigration, migrate, migrate_content,
+};
+pub use extends::{LOCK_FILE, LockedBase, local_base, update_lock};
+pub use include::included_files;
+pub use provenance::{effective_toml, merged_toml};
+#[cfg(feature = "schema")]
+pub use schema::json_schema;
+
+/// Main configuration structure
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Config {
+    /// Configuration format version the file is written for (default: 1)
+    ///
+    /// `cerberus migrate` upgrades older files and sets it to
+    /// [`SCHEMA_VERSION`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+
+    /// Project-level configuration
+    pub project: ProjectConfig,
+
+    /// Global settings
+    #[serde(default)]
+    pub global: GlobalConfig,
+
+    /// TLS/SSL configuration
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// Anubis DDoS protection configuration
+    #[serde(default)]
+    pub anubis: AnubisConfig,
+
+    /// Proxy layer configurations
+    #[serde(default)]
+    pub proxies: Vec<ProxyConfig>,
+
+    /// Backend service configurations
+    #[serde(default)]
+    pub services: Vec<ServiceConfig>,
+
+    /// Named upstream pools that services and routes use as their `upstream`
+    #[serde(default)]
+    pub upstreams: Vec<UpstreamPoolConfig>,
+
+    /// Docker networks configuration
+    #[serde(default)]
+    pub networks: BTreeMap<String, NetworkConfig>,
+
+    /// Docker volumes configuration
+    #[serde(default)]
+    pub volumes: BTreeMap<String, VolumeConfig>,
+
+    /// Docker secrets configuration
+    #[serde(default)]
+    pub secrets: BTreeMap<String, SecretConfig>,
+
+    /// Docker configs configuration
+    #[serde(default)]
+    pub configs: BTreeMap<String, ConfigFileConfig>,
+
+    /// Logging configuration
+    #[serde(default)]
+    pub logging: LoggingConfig,
+
+    /// Nomad job settings
+    #[serde(default)]
+    pub nomad: NomadConfig,
+
+    /// Security settings applied across all proxies
+    #[serde(default)]
+    pub security: SecurityConfig,
+
+    /// Release deployment settings
+    #[serde(default)]
+    pub deployment: DeploymentConfig,
+
+    /// Web application firewall (OWASP CRS) settings
+    #[serde(default)]
+    pub waf: WafConfig,
+
+    /// Named generation targets rendered by `cerberus generate`
+    ///
+    /// Keyed by target, so each target is rendered into at most one
+    /// directory.
+    #[serde(default)]
+    pub outputs: std::collections::BTreeMap<DeployTarget, OutputConfig>,
+
+    /// Alert channels and per-event routing
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+
+    /// DNS records required by the service domains
+    #[serde(default)]
+    pub dns: DnsConfig,
+
+    /// Opinionated checks reported by `cerberus validate`
+    #[serde(default)]
+    pub lint: LintConfig,
+
+    /// vault-agent sidecar used for `vault://` references with `--no-resolve`
+    #[serde(default)]
+    pub vault: VaultConfig,
+
+    /// Container options for proxies and services that leave them unset
+    #[serde(default)]
+    pub defaults: DefaultsConfig,
+
+    /// Log shipping and other observability services
+    #[serde(default)]
+    pub observability: ObservabilityStackConfig,
+
+    /// Generated file sets kept for audit and rollback
+    #[serde(default)]
+    pub history: HistoryConfig,
+
+    /// Protected route to the proxies' statistics pages and dashboards
+    #[serde(default)]
+    pub ops: OpsConfig,
+
+    /// Pages the proxies serve instead of their built-in error responses
+    #[serde(default)]
+    pub error_pages: ErrorPagesConfig,
+
+    /// robots.txt and security.txt served by the layer-1 proxies
+    #[serde(default)]
+    pub content: ContentConfig,
+
+    /// Deprecated keys the configuration was loaded with
+    #[serde(skip)]
+    pub deprecations: Vec<DeprecatedKey>,
+}
+
+/// Container options applied to every proxy and service (`[defaults]`)
+///
+/// A proxy or service setting the same option overrides the default.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DefaultsConfig {
+    /// When the image is pulled
+    #[serde(default)]
+    pub pull_policy: Option<PullPolicy>,
+
+    /// Image platform, e.g. "linux/amd64" on mixed-architecture hosts
+    #[serde(default)]
+    pub platform: Option<String>,
+
+    /// Platforms images are built for, e.g. ["linux/amd64", "linux/arm64"]
+    #[serde(default)]
+    pub platforms: Vec<String>,
+
+    /// Time a container gets to stop before it is killed (e.g. "30s")
+    #[serde(default)]
+    pub stop_grace_period: Option<String>,
+}
+
+/// Compose `pull_policy` of a container image
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum PullPolicy {
+    /// Pull the image on every start
+    Always,
+    /// Never pull; the image must exist locally
+    Never,
+    /// Pull only when the image is missing locally
+    Missing,
+    /// Build the image instead of pulling it
+    Build,
+}
+
+impl PullPolicy {
+    /// Name used in configuration and docker-compose.yaml
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PullPolicy::Always => "always",
+            PullPolicy::Never => "never",
+            PullPolicy::Missing => "missing",
+            PullPolicy::Build => "build",
+        }
+    }
+}
+
+/// Container options of a proxy or service with `[defaults]` applied
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ContainerOptions {
+    /// When the image is pulled
+    pub pull_policy: Option<PullPolicy>,
+    /// Image platform
+    pub platform: Option<String>,
+    /// Platforms the image is built for
+    pub platforms: Vec<String>,
+    /// Seconds the container gets to stop before it is killed
+    pub stop_grace_period_secs: Option<u64>,
+}
+
+/// Project-level configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProjectConfig {
+    /// Project name
+    pub name: String,
+
+    /// Enable auto-scaling
+    #[serde(default)]
+    pub scaling: bool,
+
+    /// Deployment target the generated files are intended for
+    #[serde(default)]
+    pub target: DeployTarget,
+
+    /// IANA time zone every generated container logs in, e.g. "Asia/Tokyo"
+    ///
+    /// Sets `TZ` and mounts the host's `/etc/localtime` read-only.
+    #[serde(default)]
+    pub timezone: Option<String>,
+
+    /// Task runner the generated operations file is written for
+    #[serde(default)]
+    pub task_runner: TaskRunner,
+
+    /// Serve clients over IPv6 as well as IPv4
+    ///
+    /// Proxies listen on both address families, and the default networks
+    /// are created dual-stack. A proxy publishing ports must then join a
+    /// network under `[networks]` that sets `enable_ipv6`, if it joins any.
+    #[serde(default)]
+    pub ipv6: bool,
+}
+
+/// Task runner of the operations file next to `docker-compose.yaml`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum TaskRunner {
+    /// GNU Make (`Makefile`)
+    #[default]
+    Make,
+    /// just (`justfile`)
+    Just,
+}
+
+impl TaskRunner {
+    /// Name of the operations file
+    pub fn file_name(self) -> &'static str {
+        match self {
+            TaskRunner::Make => "Makefile",
+            TaskRunner::Just => "justfile",
+        }
+    }
+}
+
+/// Deployment target for generated files
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum DeployTarget {
+    /// Docker Compose (docker-compose.yaml)
+    #[default]
+    Compose,
+    /// HashiCorp Nomad (jobspec in JSON format)
+    Nomad,
+    /// Docker Swarm (docker-stack.yaml for `docker stack deploy`)
+    Swarm,
+}
+
+impl DeployTarget {
+    /// Name used in `project.target`, `[outputs.<name>]` and `--target`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeployTarget::Compose => "compose",
+            DeployTarget::Nomad => "nomad",
+            DeployTarget::Swarm => "swarm",
+        }
+    }
+}
+
+/// A declared generation target (`[outputs.compose]`, `[outputs.nomad]`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OutputConfig {
+    /// Directory the target is rendered into
+    ///
+    /// A relative path is resolved against the directory of the
+    /// configuration file by [`Config::load`], as `include` and `extends`
+    /// are.
+    pub path: String,
+
+    /// Nomad namespace for the job (overrides `nomad.namespace`)
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+/// HashiCorp Nomad job settings (used when `project.target = "nomad"`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NomadConfig {
+    /// Datacenters the job may be placed in
+    #[serde(default = "default_nomad_datacenters")]
+    pub datacenters: Vec<String>,
+
+    /// Region to submit the job to
+    #[serde(default)]
+    pub region: Option<String>,
+
+    /// Namespace to submit the job to
+    #[serde(default)]
+    pub namespace: Option<String>,
+
+    /// Directory on Nomad clients where the generated files are deployed
+    #[serde(default = "default_nomad_config_dir")]
+    pub config_dir: String,
+}
+
+impl Default for NomadConfig {
+    fn default() -> Self {
+        Self {
+            datacenters: default_nomad_datacenters(),
+            region: None,
+            namespace: None,
+            config_dir: default_nomad_config_dir(),
+        }
+    }
+}
+
+fn default_nomad_datacenters() -> Vec<String> {
+    vec!["dc1".to_string()]
+}
+
+fn default_nomad_config_dir() -> String {
+    "/opt/cerberus".to_string()
+}
+
+/// Global Caddy/proxy settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GlobalConfig {
+    /// Automatic HTTPS setting
+    #[serde(default = "default_auto_https")]
+    pub auto_https: String,
+
+    /// Admin API setting
+    #[serde(default = "default_admin")]
+    pub admin: String,
+}
+
+impl Default for GlobalConfig {
+    fn default() -> Self {
+        Self {
+            auto_https: default_auto_https(),
+            admin: default_admin(),
+        }
+    }
+}
+
+fn default_auto_https() -> String {
+    "off".to_string()
+}
+
+fn default_admin() -> String {
+    "off".to_string()
+}
+
+/// TLS/SSL configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TlsConfig {
+    /// Enable TLS
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// CA configuration
+    #[serde(default)]
+    pub ca: Option<CaConfig>,
+
+    /// Certificate configurations
+    #[serde(default)]
+    pub certificates: Vec<CertificateConfig>,
+
+    /// Redirect plain HTTP requests to HTTPS on the edge proxies
+    #[serde(default)]
+    pub redirect_http: Option<RedirectHttpConfig>,
+
+    /// Strict-Transport-Security header sent with every response
+    #[serde(default)]
+    pub hsts: HstsConfig,
+
+    /// Certificates the edge proxies obtain themselves over ACME DNS-01
+    #[serde(default)]
+    pub acme: Option<AcmeConfig>,
+}
+
+/// Certificates issued to the edge proxies over ACME DNS-01 (`[tls.acme]`)
+///
+/// DNS-01 is the only ACME challenge that issues wildcard certificates.
+/// Caddy and Traefik edge proxies answer it through the DNS provider's API,
+/// reading the API credentials from Compose secrets.
+///
+/// ```toml
+/// [tls.acme]
+/// email = "ops@example.com"
+/// provider = "cloudflare"
+/// domains = ["example.com", "*.example.com"]
+/// credentials = { CF_DNS_API_TOKEN = "cloudflare_token" }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AcmeConfig {
+    /// Account email the CA sends expiry notices to
+    pub email: String,
+
+    /// DNS provider answering the challenges
+    pub provider: AcmeDnsProvider,
+
+    /// Domains to issue certificates for; `*.example.com` for a wildcard
+    pub domains: Vec<String>,
+
+    /// Provider credentials: environment variable name to `[secrets]` entry
+    #[serde(default)]
+    pub credentials: BTreeMap<String, String>,
+
+    /// ACME directory URL (default: Let's Encrypt production)
+    #[serde(default)]
+    pub ca: Option<String>,
+
+    /// Host port HTTPS is published on
+    #[serde(default = "default_https_port")]
+    pub https_port: u16,
+
+    /// Port HTTPS is served on inside the container
+    #[serde(default = "default_acme_listen_port")]
+    pub listen_port: u16,
+}
+
+fn default_acme_listen_port() -> u16 {
+    8443
+}
+
+/// DNS provider of ACME DNS-01 challenges
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum AcmeDnsProvider {
+    /// Cloudflare
+    Cloudflare,
+    /// Amazon Route 53
+    Route53,
+    /// DigitalOcean
+    Digitalocean,
+}
+
+/// A credential a DNS provider reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AcmeCredential {
+    /// Environment variable Traefik reads it from, with a `_FILE` suffix for a file
+    pub env: &'static str,
+    /// Option of Caddy's DNS provider module
+    pub caddy_option: &'static str,
+    /// Whether the provider needs it
+    pub required: bool,
+}
+
+impl AcmeDnsProvider {
+    /// Provider name in Caddy's `acme_dns` and Traefik's `dnsChallenge`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Cloudflare => "cloudflare",
+            Self::Route53 => "route53",
+            Self::Digitalocean => "digitalocean",
+        }
+    }
+
+    /// Caddy module implementing the provider
+    pub fn caddy_module(self) -> &'static str {
+        match self {
+            Self::Cloudflare => "github.com/caddy-dns/cloudflare",
+            Self::Route53 => "github.com/caddy-dns/route53",
+            Self::Digitalocean => "github.com/caddy-dns/digitalocean",
+        }
+    }
+
+    /// Credentials the provider reads
+    pub fn credentials(self) -> &'static [AcmeCredential] {
+        const fn credential(
+            env: &'static str,
+            caddy_option: &'static str,
+            required: bool,
+        ) -> AcmeCredential {
+            AcmeCredential {
+                env,
+                caddy_option,
+                required,
+            }
+        }
+        const CLOUDFLARE: &[AcmeCredential] = &[
+            credential("CF_DNS_API_TOKEN", "api_token", true),
+            credential("CF_ZONE_API_TOKEN", "zone_token", false),
+        ];
+        const ROUTE53: &[AcmeCredential] = &[
+            credential("AWS_ACCESS_KEY_ID", "access_key_id", true),
+            credential("AWS_SECRET_ACCESS_KEY", "secret_access_key", true),
+            credential("AWS_REGION", "region", false),
+        ];
+        const DIGITALOCEAN: &[AcmeCredential] = &[credential("DO_AUTH_TOKEN", "auth_token", true)];
+        match self {
+            Self::Cloudflare => CLOUDFLARE,
+            Self::Route53 => ROUTE53,
+            Self::Digitalocean => DIGITALOCEAN,
+        }
+    }
+}
+
+impl AcmeConfig {
+    /// Credentials with the secrets they are read from, in provider order
+    pub fn secret_credentials(&self) -> Vec<(AcmeCredential, &str)> {
+        self.provider
+            .credentials()
+            .iter()
+            .filter_map(|credential| {
+                let secret = self.credentials.get(credential.env)?;
+                Some((*credential, secret.as_str()))
+            })
+            .collect()
+    }
+}
+
+/// Path prefix of ACME HTTP-01 challenges, always served over plain HTTP
+pub const ACME_CHALLENGE_PATH: &str = "/.well-known/acme-challenge/";
+
+/// HTTP to HTTPS redirect (`[tls.redirect_http]`)
+///
+/// The edge proxies sit behind the TLS terminator, so a request counts as
+/// plain HTTP when `X-Forwarded-Proto` is not `https`. ACME challenge paths
+/// are never redirected.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RedirectHttpConfig {
+    /// Port HTTPS is served on
+    #[serde(default = "default_https_port")]
+    pub https_port: u16,
+
+    /// Redirect status code (301, 302, 307 or 308)
+    #[serde(default = "default_redirect_status")]
+    pub status: u16,
+}
+
+impl Default for RedirectHttpConfig {
+    fn default() -> Self {
+        Self {
+            https_port: default_https_port(),
+            status: default_redirect_status(),
+        }
+    }
+}
+
+fn default_https_port() -> u16 {
+    443
+}
+
+fn default_redirect_status() -> u16 {
+    301
+}
+
+impl RedirectHttpConfig {
+    /// `:port` suffix of the redirect target, empty for the standard port
+    pub fn port_suffix(&self) -> String {
+        if self.https_port == 443 {
+            String::new()
+        } else {
+            format!(":{}", self.https_port)
+        }
+    }
+
+    /// Whether browsers may cache the redirect
+    pub fn is_permanent(&self) -> bool {
+        matches!(self.status, 301 | 308)
+    }
+}
+
+/// Strict-Transport-Security header (`[tls.hsts]`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HstsConfig {
+    /// Send the header
+    #[serde(default = "default_hsts_enabled")]
+    pub enabled: bool,
+
+    /// Seconds browsers remember to use HTTPS
+    #[serde(default = "default_hsts_max_age")]
+    pub max_age: u64,
+
+    /// Apply to every subdomain as well
+    #[serde(default = "default_hsts_enabled", rename = "includeSubdomains")]
+    pub include_subdomains: bool,
+
+    /// Ask to be included in browser preload lists
+    #[serde(default = "default_hsts_enabled")]
+    pub preload: bool,
+}
+
+impl Default for HstsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_age: default_hsts_max_age(),
+            include_subdomains: true,
+            preload: true,
+        }
+    }
+}
+
+fn default_hsts_enabled() -> bool {
+    true
+}
+
+fn default_hsts_max_age() -> u64 {
+    31536000
+}
+
+impl HstsConfig {
+    /// Header value, or `None` when HSTS is disabled
+    pub fn header_value(&self) -> Option<String> {
+        if !self.enabled {
+            return None;
+        }
+        let mut value = format!("max-age={}", self.max_age);
+        if self.include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if self.preload {
+            value.push_str("; preload");
+        }
+        Some(value)
+    }
+}
+
+/// Certificate Authority configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CaConfig {
+    /// Enable internal CA
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Root certificate path
+    pub root_cert: Option<String>,
+
+    /// Root key path
+    pub root_key: Option<String>,
+}
+
+/// Individual certificate configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CertificateConfig {
+    /// Domain pattern (e.g., "*.example.com")
+    pub domain: String,
+
+    /// Certificate file path
+    pub cert_file: String,
+
+    /// Private key file path
+    pub key_file: String,
+}
+
+/// Docker build configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DockerBuildConfig {
+    /// Build context path
+    pub context: String,
+
+    /// Dockerfile path
+    #[serde(default)]
+    pub dockerfile: Option<String>,
+
+    /// Build args
+    #[serde(default)]
+    pub args: BTreeMap<String, String>,
+
+    /// Build target stage
+    #[serde(default)]
+    pub target: Option<String>,
+
+    /// Additional contexts
+    #[serde(default)]
+    pub additional_contexts: BTreeMap<String, String>,
+}
+
+/// Docker service dependencies
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum DependsOn {
+    /// Simple list of service names
+    Simple(Vec<String>),
+    /// Detailed dependencies with conditions
+    Detailed(BTreeMap<String, DependencyCondition>),
+}
+
+/// Conditions Docker Compose accepts in long-form `depends_on`
+pub const DEPENDENCY_CONDITIONS: &[&str] = &[
+    "service_started",
+    "service_healthy",
+    "service_completed_successfully",
+];
+
+/// Dependency condition
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DependencyCondition {
+    /// Condition type (`service_started`, `service_healthy`,
+    /// `service_completed_successfully`)
+    pub condition: String,
+    /// Optional restart flag
+    #[serde(default)]
+    pub restart: Option<bool>,
+}
+
+/// Healthcheck configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HealthcheckConfig {
+    /// Test command
+    pub test: Vec<String>,
+
+    /// Check interval
+    #[serde(default = "default_healthcheck_interval")]
+    pub interval: String,
+
+    /// Timeout
+    #[serde(default = "default_healthcheck_timeout")]
+    pub timeout: String,
+
+    /// Retries
+    #[serde(default = "default_healthcheck_retries")]
+    pub retries: u32,
+
+    /// Start period
+    #[serde(default)]
+    pub start_period: Option<String>,
+
+    /// Start interval
+    #[serde(default)]
+    pub start_interval: Option<String>,
+}
+
+fn default_healthcheck_interval() -> String {
+    "30s".to_string()
+}
+
+fn default_healthcheck_timeout() -> String {
+    "10s".to_string()
+}
+
+fn default_healthcheck_retries() -> u32 {
+    3
+}
+
+/// Logging configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LoggingDriverConfig {
+    /// Driver type
+    pub driver: String,
+
+    /// Driver options
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+}
+
+/// Resource limits
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ResourcesConfig {
+    /// Resource limits
+    #[serde(default)]
+    pub limits: Option<ResourceLimits>,
+
+    /// Resource reservations
+    #[serde(default)]
+    pub reservations: Option<ResourceLimits>,
+}
+
+/// Resource limits specification
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ResourceLimits {
+    /// CPU limit
+    #[serde(default)]
+    pub cpus: Option<String>,
+
+    /// Memory limit
+    #[serde(default)]
+    pub memory: Option<String>,
+
+    /// PID limit
+    #[serde(default)]
+    pub pids: Option<u32>,
+}
+
+/// Anubis DDoS protection configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AnubisConfig {
+    /// Enable Anubis DDoS protection
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Bind address for Anubis
+    #[serde(default = "default_anubis_bind")]
+    pub bind: String,
+
+    /// Target upstream for protected traffic
+    #[serde(default = "default_anubis_target")]
+    pub target: String,
+
+    /// Challenge difficulty level (1-10)
+    #[serde(default = "default_anubis_difficulty")]
+    pub difficulty: u8,
+
+    /// Metrics endpoint bind address
+    #[serde(default = "default_anubis_metrics_bind")]
+    pub metrics_bind: String,
+
+    /// Publish the metrics port on the host (Prometheus in the stack reaches it either way)
+    #[serde(default)]
+    pub expose_metrics: bool,
+
+    /// Docker image for Anubis
+    #[serde(default = "default_anubis_image")]
+    pub image: String,
+
+    /// Serve robots.txt flag
+    #[serde(default = "default_serve_robots_txt")]
+    pub serve_robots_txt: String,
+
+    /// Policy filename path
+    #[serde(default = "default_policy_fname")]
+    pub policy_fname: String,
+
+    /// Docker volumes
+    #[serde(default)]
+    pub volumes: Vec<String>,
+
+    /// Docker networks
+    #[serde(default)]
+    pub networks: Vec<String>,
+
+    /// Docker restart policy
+    #[serde(default = "default_anubis_restart")]
+    pub restart: String,
+
+    /// Limits on the requests layer-1 proxies pass to Anubis
+    #[serde(default)]
+    pub protection: AnubisProtectionConfig,
+
+    /// Further Anubis containers protecting some domains with their own settings
+    #[serde(default)]
+    pub instances: Vec<AnubisInstanceConfig>,
+}
+
+/// Anubis container for some domains (`[[anubis.instances]]`)
+///
+/// ```toml
+/// [[anubis.instances]]
+/// name = "anubis-shop"
+/// domains = ["shop.example.com"]
+/// difficulty = 8
+/// ```
+///
+/// Layer-1 proxies send the listed domains to the instance instead of the
+/// global Anubis. Unset settings are taken from `[anubis]`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AnubisInstanceConfig {
+    /// Name of the container and its compose service
+    pub name: String,
+
+    /// Domains challenged by this instance
+    pub domains: Vec<String>,
+
+    /// Bind address (default: `anubis.bind`)
+    #[serde(default)]
+    pub bind: Option<String>,
+
+    /// Target upstream for protected traffic (default: `anubis.target`)
+    #[serde(default)]
+    pub target: Option<String>,
+
+    /// Challenge difficulty level, 1-10 (default: `anubis.difficulty`)
+    #[serde(default)]
+    pub difficulty: Option<u8>,
+
+    /// Metrics endpoint bind address (default: `anubis.metrics_bind`)
+    #[serde(default)]
+    pub metrics_bind: Option<String>,
+}
+
+/// An Anubis container with the `[anubis]` defaults filled in
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnubisContainer {
+    /// Name of the container
+    pub name: String,
+    /// Domains it challenges; empty for the global Anubis, which takes the rest
+    pub domains: Vec<String>,
+    /// Bind address
+    pub bind: String,
+    /// Target upstream for protected traffic
+    pub target: String,
+    /// Challenge difficulty level
+    pub difficulty: u8,
+    /// Metrics endpoint bind address
+    pub metrics_bind: String,
+}
+
+impl AnubisContainer {
+    /// URL proxies send challenged requests to
+    pub fn url(&self) -> String {
+        let port = self.bind.rsplit(':').next().unwrap_or_default();
+        format!("http://{}:{port}", self.name)
+    }
+
+    /// Port of the metrics endpoint, from `metrics_bind`
+    pub fn metrics_port(&self) -> Option<u16> {
+        self.metrics_bind.rsplit(':').next()?.parse().ok()
+    }
+
+    /// Directory of its bot policy and environment, relative to the output directory
+    pub fn config_dir(&self) -> String {
+        if self.domains.is_empty() {
+            "anubis".to_string()
+        } else {
+            format!("anubis/{}", self.name)
+        }
+    }
+
+    /// Whether it challenges a host
+    pub fn serves(&self, host: &str) -> bool {
+        self.domains.iter().any(|domain| domain == host)
+    }
+}
+
+impl Default for AnubisConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: default_anubis_bind(),
+            target: default_anubis_target(),
+            difficulty: default_anubis_difficulty(),
+            metrics_bind: default_anubis_metrics_bind(),
+            expose_metrics: false,
+            image: default_anubis_image(),
+            serve_robots_txt: default_serve_robots_txt(),
+            policy_fname: default_policy_fname(),
+            volumes: Vec::new(),
+            networks: Vec::new(),
+            restart: default_anubis_restart(),
+            protection: AnubisProtectionConfig::default(),
+            instances: Vec::new(),
+        }
+    }
+}
+
+impl AnubisConfig {
+    /// URL proxies send challenged requests to
+    pub fn url(&self) -> String {
+        let port = self.bind.rsplit(':').next().unwrap_or_default();
+        format!("http://anubis:{port}")
+    }
+
+    /// Every Anubis container, the global one first
+    pub fn containers(&self) -> Vec<AnubisContainer> {
+        let global = AnubisContainer {
+            name: "anubis".to_string(),
+            domains: Vec::new(),
+            bind: self.bind.clone(),
+            target: self.target.clone(),
+            difficulty: self.difficulty,
+            metrics_bind: self.metrics_bind.clone(),
+        };
+        std::iter::once(global)
+            .chain(self.instances.iter().map(|instance| {
+                AnubisContainer {
+                    name: instance.name.clone(),
+                    domains: instance.domains.clone(),
+                    bind: instance.bind.clone().unwrap_or_else(|| self.bind.clone()),
+                    target: instance
+                        .target
+                        .clone()
+                        .unwrap_or_else(|| self.target.clone()),
+                    difficulty: instance.difficulty.unwrap_or(self.difficulty),
+                    metrics_bind: instance
+                        .metrics_bind
+                        .clone()
+                        .unwrap_or_else(|| self.metrics_bind.clone()),
+                }
+            }))
+            .collect()
+    }
+
+    /// URL challenged requests for a host are sent to
+    pub fn url_for(&self, host: &str) -> String {
+        self.containers()
+            .into_iter()
+            .find(|container| container.serves(host))
+            .map_or_else(|| self.url(), |container| container.url())
+    }
+
+    /// Port of the metrics endpoint, from `metrics_bind`
+    pub fn metrics_port(&self) -> Option<u16> {
+        self.metrics_bind.rsplit(':').next()?.parse().ok()
+    }
+}
+
+fn default_anubis_bind() -> String {
+    ":8080".to_string()
+}
+
+fn default_anubis_target() -> String {
+    "http://proxy-2:80".to_string()
+}
+
+fn default_anubis_difficulty() -> u8 {
+    5
+}
+
+fn default_anubis_metrics_bind() -> String {
+    ":9090".to_string()
+}
+
+fn default_anubis_image() -> String {
+    "ghcr.io/techarohq/anubis:latest".to_string()
+}
+
+fn default_serve_robots_txt() -> String {
+    "true".to_string()
+}
+
+fn default_policy_fname() -> String {
+    "/data/cfg/botPolicy.json".to_string()
+}
+
+fn default_anubis_restart() -> String {
+    "always".to_string()
+}
+
+/// Request limits in front of Anubis (`[anubis.protection]`)
+///
+/// Layer-1 proxies reject pathological requests before they reach the
+/// challenge, so Anubis cannot be overwhelmed by huge URIs, header blocks
+/// or bodies. Nginx bounds the number of headers only through their total
+/// size.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AnubisProtectionConfig {
+    /// Longest request URI, in bytes; longer ones get 414
+    #[serde(default = "default_protection_max_uri_length")]
+    pub max_uri_length: u32,
+
+    /// Largest single header line, including the request line (e.g. `"8k"`)
+    #[serde(default = "default_protection_max_header_size")]
+    pub max_header_size: String,
+
+    /// Largest header block of a request (e.g. `"32k"`)
+    #[serde(default = "default_protection_max_headers_size")]
+    pub max_headers_size: String,
+
+    /// Largest body sent to the challenge endpoints, which are not buffered
+    #[serde(default = "default_protection_challenge_body_size")]
+    pub challenge_body_size: String,
+}
+
+impl Default for AnubisProtectionConfig {
+    fn default() -> Self {
+        Self {
+            max_uri_length: default_protection_max_uri_length(),
+            max_header_size: default_protection_max_header_size(),
+            max_headers_size: default_protection_max_headers_size(),
+            challenge_body_size: default_protection_challenge_body_size(),
+        }
+    }
+}
+
+impl AnubisProtectionConfig {
+    /// Number and size of nginx's `large_client_header_buffers`
+    pub fn header_buffers(&self) -> (u64, u64) {
+        let line = parse_size_bytes(&self.max_header_size).unwrap_or(8 << 10);
+        let total = parse_size_bytes(&self.max_headers_size).unwrap_or(32 << 10);
+        (total.div_ceil(line).max(1), line)
+    }
+}
+
+fn default_protection_max_uri_length() -> u32 {
+    4096
+}
+
+fn default_protection_max_header_size() -> String {
+    "8k".to_string()
+}
+
+fn default_protection_max_headers_size() -> String {
+    "32k".to_string()
+}
+
+fn default_protection_challenge_body_size() -> String {
+    "64k".to_string()
+}
+
+/// Validate `[anubis.protection]`
+fn validate_anubis_protection(protection: &AnubisProtectionConfig) -> Result<()> {
+    let size = |field: &str, value: &str| {
+        parse_size_bytes(value)
+            .filter(|bytes| *bytes > 0)
+            .ok_or_else(|| {
+                CerberusError::validation(format!(
+                    "anubis.protection.{field} '{value}' is not a valid size"
+                ))
+            })
+    };
+    let line = size("max_header_size", &protection.max_header_size)?;
+    let total = size("max_headers_size", &protection.max_headers_size)?;
+    size("challenge_body_size", &protection.challenge_body_size)?;
+
+    if line < 1 << 10 {
+        return Err(CerberusError::validation(
+            "anubis.protection.max_header_size must be at least 1k",
+        ));
+    }
+    if total < line {
+        return Err(CerberusError::validation(format!(
+            "anubis.protection.max_headers_size '{}' is smaller than max_header_size '{}'",
+            protection.max_headers_size, protection.max_header_size
+        )));
+    }
+    // The request line has to fit in one header buffer
+    if protection.max_uri_length < 256 || u64::from(protection.max_uri_length) >= line {
+        return Err(CerberusError::validation(format!(
+            "anubis.protection.max_uri_length must be between 256 and max_header_size ({line} bytes)"
+        )));
+    }
+    Ok(())
+}
+
+/// Validate `[[anubis.instances]]`
+fn validate_anubis_instances(config: &Config) -> Result<()> {
+    let instances = &config.anubis.instances;
+    if instances.is_empty() {
+        return Ok(());
+    }
+    if !config.anubis.enabled {
+        return Err(CerberusError::validation(
+            "anubis.instances requires anubis.enabled",
+        ));
+    }
+
+    let mut names = std::collections::HashSet::new();
+    let mut domains = std::collections::HashSet::new();
+    for instance in instances {
+        let name = &instance.name;
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            return Err(CerberusError::validation(format!(
+                "Anubis instance name '{name}' must contain only lowercase letters, digits, '-' and '_'"
+            )));
+        }
+        if name == "anubis"
+            || config.proxies.iter().any(|proxy| &proxy.name == name)
+            || config.services.iter().any(|service| &service.name == name)
+        {
+            return Err(CerberusError::validation(format!(
+                "Anubis instance name '{name}' is already used by another container"
+            )));
+        }
+        if !names.insert(name.as_str()) {
+            return Err(CerberusError::validation(format!(
+                "Duplicate Anubis instance name '{name}'"
+            )));
+        }
+
+        if instance.domains.is_empty() {
+            return Err(CerberusError::validation(format!(
+                "Anubis instance {name} needs at least one domain"
+            )));
+        }
+        for domain in &instance.domains {
+            if !is_valid_host(domain) || domain.starts_with("*.") {
+                return Err(CerberusError::validation(format!(
+                    "Anubis instance {name} has invalid domain '{domain}'"
+                )));
+            }
+            if !domains.insert(domain.as_str()) {
+                return Err(CerberusError::validation(format!(
+                    "Domain '{domain}' is assigned to more than one Anubis instance"
+                )));
+            }
+            // Layer-1 proxies pass service hosts straight to the next layer
+            if let Some(service) = config
+                .services
+                .iter()
+                .find(|service| service.hosts().iter().any(|host| host == domain))
+            {
+                return Err(CerberusError::validation(format!(
+                    "Anubis instance {name} domain '{domain}' is served by service {} and bypasses Anubis",
+                    service.name
+                )));
+            }
+        }
+
+        if instance
+            .difficulty
+            .is_some_and(|difficulty| !(1..=10).contains(&difficulty))
+        {
+            return Err(CerberusError::validation(format!(
+                "Anubis instance {name} difficulty must be between 1 and 10"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Validate that Anubis sits between layer-1 proxies and the next layer
+///
+/// Anubis is generated whenever it is enabled, whatever the proxy types;
+/// stacks with no layer 1 to put it behind, or with instances a layer-1
+/// proxy cannot route to, are rejected. A topology that merely leaves
+/// Anubis unused is reported by the `anubis-topology` lint rule.
+fn validate_anubis_topology(config: &Config) -> Result<()> {
+    if !config.anubis.enabled || config.proxies.is_empty() {
+        return Ok(());
+    }
+
+    let edges: Vec<&ProxyConfig> = config
+        .proxies
+        .iter()
+        .filter(|proxy| proxy.layer.unwrap_or(1) == 1)
+        .collect();
+    if edges.is_empty() {
+        return Err(CerberusError::validation(
+            "anubis.enabled needs a layer-1 proxy to pass requests to Anubis",
+        ));
+    }
+
+    // Only Nginx maps the domains of instances at the edge
+    if !config.anubis.instances.is_empty()
+        && let Some(proxy) = edges
+            .iter()
+            .find(|proxy| proxy.proxy_type != ProxyType::Nginx && config.sends_to_anubis(proxy))
+    {
+        return Err(CerberusError::validation(format!(
+            "anubis.instances need a layer-1 Nginx proxy to send their domains to them; \
+             {} proxy {} would send every domain to the global Anubis",
+            proxy.proxy_type, proxy.name
+        )));
+    }
+    Ok(())
+}
+
+/// Validate that the networks match whether proxies listen on IPv6
+///
+/// Docker forwards a published port to the container's IPv6 address when
+/// its network has IPv6, so a proxy listening on IPv4 only refuses those
+/// connections, and a dual-stack proxy on an IPv4-only network never sees
+/// an IPv6 client. External networks are the operator's to configure.
+fn validate_ipv6(config: &Config) -> Result<()> {
+    for (name, network) in &config.networks {
+        let subnets: Vec<&str> = network
+            .ipam
+            .iter()
+            .flat_map(|ipam| &ipam.config)
+            .filter_map(|entry| entry.subnet.as_deref())
+            .collect();
+        let ipv6_subnet = subnets.iter().any(|subnet| subnet.contains(':'));
+        if network.enable_ipv6 && !subnets.is_empty() && !ipv6_subnet {
+            return Err(CerberusError::validation(format!(
+                "Network {name} enables IPv6, but its ipam.config has no IPv6 subnet; \
+                 add one such as fd00:1::/64"
+            )));
+        }
+        if !network.enable_ipv6 && ipv6_subnet {
+            return Err(CerberusError::validation(format!(
+                "Network {name} has an IPv6 subnet but enable_ipv6 is false"
+            )));
+        }
+    }
+
+    for proxy in &config.proxies {
+        let Some(port) = proxy.external_port else {
+            continue;
+        };
+        let networks: Vec<(&str, &NetworkConfig)> = proxy
+            .network_names()
+            .into_iter()
+            .filter_map(|name| config.networks.get(name).map(|network| (name, network)))
+            .filter(|(_, network)| !network.external)
+            .collect();
+        if config.project.ipv6
+            && !networks.is_empty()
+            && networks.iter().all(|(_, network)| !network.enable_ipv6)
+        {
+            return Err(CerberusError::validation(format!(
+                "project.ipv6 is set, but proxy {} publishes port {port} without joining \
+                 a network with enable_ipv6",
+                proxy.name
+            )));
+        }
+        if !config.project.ipv6
+            && let Some((name, _)) = networks.iter().find(|(_, network)| network.enable_ipv6)
+        {
+            return Err(CerberusError::validation(format!(
+                "Network {name} enables IPv6, but proxy {} listens on IPv4 only; \
+                 set project.ipv6 = true",
+                proxy.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Docker network configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NetworkConfig {
+    /// Network driver
+    #[serde(default = "default_network_driver")]
+    pub driver: String,
+
+    /// Driver options
+    #[serde(default)]
+    pub driver_opts: BTreeMap<String, String>,
+
+    /// IPAM configuration
+    #[serde(default)]
+    pub ipam: Option<IpamConfig>,
+
+    /// External network flag
+    #[serde(default)]
+    pub external: bool,
+
+    /// External network name
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Internal-only network: no route to or from the host network stack
+    #[serde(default)]
+    pub internal: bool,
+
+    /// Enable IPv6
+    #[serde(default)]
+    pub enable_ipv6: bool,
+
+    /// Labels
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+fn default_network_driver() -> String {
+    "bridge".to_string()
+}
+
+/// IPAM (IP Address Management) configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IpamConfig {
+    /// IPAM driver
+    #[serde(default)]
+    pub driver: Option<String>,
+
+    /// Driver options
+    #[serde(default)]
+    pub driver_opts: BTreeMap<String, String>,
+
+    /// Network configuration
+    #[serde(default)]
+    pub config: Vec<IpamNetworkConfig>,
+}
+
+/// IPAM network configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IpamNetworkConfig {
+    /// Subnet
+    #[serde(default)]
+    pub subnet: Option<String>,
+
+    /// IP range
+    #[serde(default)]
+    pub ip_range: Option<String>,
+
+    /// Gateway
+    #[serde(default)]
+    pub gateway: Option<String>,
+
+    /// Auxiliary addresses
+    #[serde(default)]
+    pub aux_addresses: BTreeMap<String, String>,
+}
+
+/// Docker volume configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VolumeConfig {
+    /// Volume driver
+    #[serde(default)]
+    pub driver: Option<String>,
+
+    /// Driver options
+    #[serde(default)]
+    pub driver_opts: BTreeMap<String, String>,
+
+    /// External volume flag
+    #[serde(default)]
+    pub external: bool,
+
+    /// External volume name
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Labels
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Docker secret configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum SecretConfig {
+    /// SOPS- or age-encrypted file, decrypted at generate time
+    ///
+    /// Listed first so `file` plus `format` is not read as a plain file.
+    Encrypted {
+        /// Path of the encrypted file
+        file: String,
+        /// Encryption tool the file was written with
+        format: EncryptedFormat,
+    },
+    /// Simple file-based secret
+    File {
+        /// File path
+        file: String,
+    },
+    /// Environment variable secret
+    Environment {
+        /// Environment variable name
+        environment: String,
+    },
+    /// External secret reference
+    External {
+        /// External flag
+        external: bool,
+        /// External secret name
+        #[serde(default)]
+        name: Option<String>,
+    },
+    /// Inline content secret
+    Content {
+        /// Content string
+        content: String,
+    },
+    /// Secret read from a command's stdout at generate time
+    ///
+    /// Lets password managers supply the value, e.g.
+    /// `command = "op read op://vault/item/field"`.
+    Exec {
+        /// Shell command printing the secret value
+        command: String,
+    },
+    /// Secret read from HashiCorp Vault, e.g. `vault = "vault://secret/data/app#password"`
+    Vault {
+        /// `vault://` reference to the secret
+        vault: String,
+    },
+}
+
+impl SecretConfig {
+    /// Whether Cerberus writes the value into the output directory
+    ///
+    /// Compose can only mount files, environment variables, or external
+    /// secrets, so inline, command, and Vault secrets are written out first.
+    /// Decrypted secrets go to tmpfs instead and are not included.
+    pub fn is_resolved_locally(&self) -> bool {
+        matches!(
+            self,
+            Self::Content { .. } | Self::Exec { .. } | Self::Vault { .. }
+        )
+    }
+}
+
+/// Scheme of references to secrets stored in HashiCorp Vault
+pub const VAULT_SCHEME: &str = "vault://";
+
+/// A `vault://<path>#<key>` reference to one field of a Vault secret
+///
+/// The path is the API path below `/v1/`, so KV version 2 secrets include
+/// `data/` (`vault://secret/data/app#password`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VaultRef {
+    /// Secret path, without leading or trailing slashes
+    pub path: String,
+    /// Field of the secret
+    pub key: String,
+}
+
+impl VaultRef {
+    /// Parse a reference, returning `None` for values without the scheme
+    ///
+    /// # Errors
+    /// Returns a description of the problem if the path or key is missing
+    /// or malformed
+    pub fn parse(value: &str) -> std::result::Result<Option<Self>, String> {
+        let Some(reference) = value.strip_prefix(VAULT_SCHEME) else {
+            return Ok(None);
+        };
+        let invalid = |reason: &str| {
+            format!("invalid Vault reference '{value}': {reason} (expected vault://<path>#<key>)")
+        };
+
+        let (path, key) = reference
+            .split_once('#')
+            .ok_or_else(|| invalid("missing #<key>"))?;
+        let path = path.trim_matches('/');
+        if path.is_empty() {
+            return Err(invalid("missing path"));
+        }
+        if path
+            .split('/')
+            .any(|segment| segment.is_empty() || segment == "." || segment == "..")
+            || path.contains(['"', '?', ' '])
+        {
+            return Err(invalid("malformed path"));
+        }
+        if key.is_empty() || key.contains(['"', '#', ' ']) {
+            return Err(invalid("malformed key"));
+        }
+
+        Ok(Some(Self {
+            path: path.to_string(),
+            key: key.to_string(),
+        }))
+    }
+
+    /// consul-template expression printing the field, for vault-agent and Nomad
+    ///
+    /// KV version 2 responses nest the fields under `data`.
+    pub fn template(&self) -> String {
+        format!(
+            r#"{{{{ with secret "{path}" }}}}{{{{ if .Data.metadata }}}}{{{{ index .Data.data "{key}" }}}}{{{{ else }}}}{{{{ index .Data "{key}" }}}}{{{{ end }}}}{{{{ end }}}}"#,
+            path = self.path,
+            key = self.key
+        )
+    }
+}
+
+impl std::fmt::Display for VaultRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{VAULT_SCHEME}{}#{}", self.path, self.key)
+    }
+}
+
+/// vault-agent sidecar settings (`[vault]`)
+///
+/// With `--no-resolve`, Cerberus does not contact Vault itself: a vault-agent
+/// container renders every `vault://` reference into `secrets/` instead. The
+/// agent reaches Vault at `$VAULT_ADDR` and logs in with the token file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VaultConfig {
+    /// vault-agent container image
+    #[serde(default = "default_vault_agent_image")]
+    pub agent_image: String,
+
+    /// Host file holding the agent's Vault token
+    #[serde(default = "default_vault_token_file")]
+    pub token_file: String,
+}
+
+impl Default for VaultConfig {
+    fn default() -> Self {
+        Self {
+            agent_image: default_vault_agent_image(),
+            token_file: default_vault_token_file(),
+        }
+    }
+}
+
+fn default_vault_agent_image() -> String {
+    "hashicorp/vault:1.17".to_string()
+}
+
+fn default_vault_token_file() -> String {
+    "~/.vault-token".to_string()
+}
+
+/// Operations route (`[ops]`)
+///
+/// The edge proxies serve the HAProxy statistics pages and Traefik
+/// dashboards under `domain`, each at `/<proxy name>/`, instead of
+/// publishing their ports on the host.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OpsConfig {
+    /// Host name of the route (e.g. "ops.example.com"); unset disables it
+    #[serde(default)]
+    pub domain: Option<String>,
+
+    /// Users allowed in, htpasswd style with bcrypt hashes (`user:$2y$...`)
+    #[serde(default)]
+    pub users: Vec<String>,
+
+    /// Client addresses or CIDR ranges allowed in (empty = any)
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+/// Dashboard reachable through the operations route
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpsDashboard {
+    /// Proxy serving the dashboard
+    pub proxy: String,
+    /// `host:port` of the dashboard listener
+    pub address: String,
+    /// Path of the dashboard's start page
+    pub path: &'static str,
+}
+
+impl OpsConfig {
+    /// Users split into name and bcrypt hash
+    pub fn credentials(&self) -> Vec<(&str, &str)> {
+        self.users
+            .iter()
+            .filter_map(|user| user.split_once(':'))
+            .collect()
+    }
+}
+
+/// Custom error pages (`[error_pages]`)
+///
+/// Pages are written to [`ERROR_PAGES_DIR`] in the output directory and
+/// mounted read-only into every proxy.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct ErrorPagesConfig {
+    /// Pages by HTTP status code (e.g. `[error_pages.pages.404]`)
+    #[serde(default)]
+    pub pages: BTreeMap<String, ErrorPageConfig>,
+
+    /// Serve a built-in maintenance page for 502, 503 and 504 responses
+    /// that have no page of their own
+    #[serde(default)]
+    pub maintenance: bool,
+}
+
+/// Content of one error page; exactly one of `file` and `html` is set
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct ErrorPageConfig {
+    /// HTML file, relative to the directory `cerberus` runs in
+    #[serde(default)]
+    pub file: Option<String>,
+
+    /// Inline HTML
+    #[serde(default)]
+    pub html: Option<String>,
+}
+
+/// Directory in the output directory holding the error pages
+pub const ERROR_PAGES_DIR: &str = "error-pages";
+
+/// Directory the error pages are mounted at inside the proxies
+pub const CONTAINER_ERROR_PAGES_DIR: &str = "/usr/share/cerberus/error-pages";
+
+/// Status codes the built-in maintenance page covers
+pub const MAINTENANCE_STATUSES: [u16; 3] = [502, 503, 504];
+
+/// Error status codes HAProxy accepts in `errorfile`
+const HAPROXY_ERROR_STATUSES: [u16; 16] = [
+    400, 401, 403, 404, 405, 407, 408, 410, 413, 425, 429, 500, 501, 502, 503, 504,
+];
+
+impl ErrorPagesConfig {
+    /// Whether any error page is served
+    pub fn is_enabled(&self) -> bool {
+        self.maintenance || !self.pages.is_empty()
+    }
+
+    /// Status codes with a page, in ascending order
+    ///
+    /// Codes that do not parse are left out; validation rejects them.
+    pub fn statuses(&self) -> Vec<u16> {
+        let mut statuses: Vec<u16> = self
+            .pages
+            .keys()
+            .filter_map(|status| status.parse().ok())
+            .collect();
+        if self.maintenance {
+            statuses.extend(MAINTENANCE_STATUSES);
+        }
+        statuses.sort_unstable();
+        statuses.dedup();
+        statuses
+    }
+
+    /// Page configured for a status code, if it is not the maintenance page
+    pub fn page(&self, status: u16) -> Option<&ErrorPageConfig> {
+        self.pages.get(&status.to_string())
+    }
+}
+
+/// Well-known files the layer-1 proxies serve themselves (`[content]`)
+///
+/// Files are written to [`CONTENT_DIR`] in the output directory, mounted
+/// read-only into every layer-1 proxy, and let through Anubis without a
+/// challenge.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct ContentConfig {
+    /// `/robots.txt`
+    #[serde(default)]
+    pub robots: Option<RobotsConfig>,
+
+    /// `/.well-known/security.txt` (RFC 9116)
+    #[serde(default)]
+    pub security_txt: Option<SecurityTxtConfig>,
+}
+
+/// Content of robots.txt; either `file` or the rule groups
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct RobotsConfig {
+    /// File served as is, relative to the directory `cerberus` runs in
+    #[serde(default)]
+    pub file: Option<String>,
+
+    /// Rule groups, one per user agent (`[[content.robots.agents]]`)
+    #[serde(default)]
+    pub agents: Vec<RobotsAgentConfig>,
+
+    /// Absolute URLs of the sitemaps
+    #[serde(default)]
+    pub sitemaps: Vec<String>,
+}
+
+/// Rules robots.txt gives one user agent
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct RobotsAgentConfig {
+    /// User agent the group applies to ("*" for every crawler)
+    pub user_agent: String,
+
+    /// Path prefixes the agent may crawl
+    #[serde(default)]
+    pub allow: Vec<String>,
+
+    /// Path prefixes the agent must not crawl
+    #[serde(default)]
+    pub disallow: Vec<String>,
+
+    /// Seconds between requests, for crawlers that honour it
+    #[serde(default)]
+    pub crawl_delay: Option<u32>,
+}
+
+/// Fields of security.txt (RFC 9116)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct SecurityTxtConfig {
+    /// Where to report vulnerabilities (mailto:, tel: or https:// URIs)
+    pub contact: Vec<String>,
+
+    /// When the file goes stale, as an RFC 3339 timestamp
+    pub expires: String,
+
+    /// Keys to encrypt reports with
+    #[serde(default)]
+    pub encryption: Vec<String>,
+
+    /// Pages thanking reporters
+    #[serde(default)]
+    pub acknowledgments: Vec<String>,
+
+    /// Languages reports can be written in (e.g. "en", "ja")
+    #[serde(default)]
+    pub preferred_languages: Vec<String>,
+
+    /// URLs the file is published at
+    #[serde(default)]
+    pub canonical: Vec<String>,
+
+    /// Vulnerability disclosure policy
+    #[serde(default)]
+    pub policy: Vec<String>,
+
+    /// Security job openings
+    #[serde(default)]
+    pub hiring: Vec<String>,
+}
+
+/// Directory in the output directory holding the `[content]` files
+pub const CONTENT_DIR: &str = "content";
+
+/// Directory the `[content]` files are mounted at inside the proxies
+pub const CONTAINER_CONTENT_DIR: &str = "/usr/share/cerberus/content";
+
+impl ContentConfig {
+    /// Whether any file is served
+    pub fn is_enabled(&self) -> bool {
+        self.robots.is_some() || self.security_txt.is_some()
+    }
+
+    /// Request path and file name of every served file
+    pub fn files(&self) -> Vec<(&'static str, &'static str)> {
+        let mut files = Vec::new();
+        if self.robots.is_some() {
+            files.push(("/robots.txt", "robots.txt"));
+        }
+        if self.security_txt.is_some() {
+            files.push(("/.well-known/security.txt", "security.txt"));
+        }
+        files
+    }
+}
+
+/// Generation history (`[history]`)
+///
+/// Each `cerberus generate` records the written files under
+/// `.cerberus/history` next to the configuration. Unchanged files are
+/// stored once, so a run costs little more than the files it changed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HistoryConfig {
+    /// Record generations at all
+    #[serde(default = "default_history_enabled")]
+    pub enabled: bool,
+
+    /// Number of generations kept; older ones are pruned
+    #[serde(default = "default_history_keep")]
+    pub keep: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_history_enabled(),
+            keep: default_history_keep(),
+        }
+    }
+}
+
+fn default_history_enabled() -> bool {
+    true
+}
+
+fn default_history_keep() -> usize {
+    50
+}
+
+/// Tool an encrypted secret file was written with
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum EncryptedFormat {
+    /// `sops --decrypt`, with any key source SOPS supports
+    Sops,
+    /// `age --decrypt` with an age identity file
+    Age,
+}
+
+/// Docker config file configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum ConfigFileConfig {
+    /// Simple file-based config
+    File {
+        /// File path
+        file: String,
+    },
+    /// Environment variable config
+    Environment {
+        /// Environment variable name
+        environment: String,
+    },
+    /// External config reference
+    External {
+        /// External flag
+        external: bool,
+        /// External config name
+        #[serde(default)]
+        name: Option<String>,
+    },
+    /// Inline content config
+    Content {
+        /// Content string
+        content: String,
+    },
+}
+
+/// Service secret reference
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum ServiceSecretRef {
+    /// Simple secret name
+    Simple(String),
+    /// Detailed secret configuration
+    Detailed {
+        /// Secret name
+        source: String,
+        /// Target path in container
+        #[serde(default)]
+        target: Option<String>,
+        /// File mode (octal)
+        #[serde(default)]
+        mode: Option<u32>,
+        /// User ID
+        #[serde(default)]
+        uid: Option<String>,
+        /// Group ID
+        #[serde(default)]
+        gid: Option<String>,
+    },
+}
+
+/// Service config reference
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum ServiceConfigRef {
+    /// Simple config name
+    Simple(String),
+    /// Detailed config configuration
+    Detailed {
+        /// Config name
+        source: String,
+        /// Target path in container
+        #[serde(default)]
+        target: Option<String>,
+        /// File mode (octal)
+        #[serde(default)]
+        mode: Option<u32>,
+        /// User ID
+        #[serde(default)]
+        uid: Option<String>,
+        /// Group ID
+        #[serde(default)]
+        gid: Option<String>,
+    },
+}
+
+/// Docker deploy configuration for Swarm mode
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DeployConfig {
+    /// Deployment mode
+    #[serde(default)]
+    pub mode: Option<String>,
+
+    /// Number of replicas
+    #[serde(default)]
+    pub replicas: Option<u32>,
+
+    /// Resource constraints
+    #[serde(default)]
+    pub resources: Option<ResourcesConfig>,
+
+    /// Update configuration
+    #[serde(default)]
+    pub update_config: Option<UpdateConfig>,
+
+    /// Rollback configuration
+    #[serde(default)]
+    pub rollback_config: Option<RollbackConfig>,
+
+    /// Restart policy
+    #[serde(default)]
+    pub restart_policy: Option<RestartPolicyConfig>,
+
+    /// Placement constraints
+    #[serde(default)]
+    pub placement: Option<PlacementConfig>,
+
+    /// Labels
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Update configuration for deployments
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UpdateConfig {
+    /// Parallelism level
+    #[serde(default)]
+    pub parallelism: Option<u32>,
+
+    /// Update delay
+    #[serde(default)]
+    pub delay: Option<String>,
+
+    /// Failure action
+    #[serde(default)]
+    pub failure_action: Option<String>,
+
+    /// Monitor duration
+    #[serde(default)]
+    pub monitor: Option<String>,
+
+    /// Max failure ratio
+    #[serde(default)]
+    pub max_failure_ratio: Option<f64>,
+
+    /// Update order
+    #[serde(default)]
+    pub order: Option<String>,
+}
+
+/// Rollback configuration for deployments
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RollbackConfig {
+    /// Parallelism level
+    #[serde(default)]
+    pub parallelism: Option<u32>,
+
+    /// Rollback delay
+    #[serde(default)]
+    pub delay: Option<String>,
+
+    /// Failure action
+    #[serde(default)]
+    pub failure_action: Option<String>,
+
+    /// Monitor duration
+    #[serde(default)]
+    pub monitor: Option<String>,
+
+    /// Max failure ratio
+    #[serde(default)]
+    pub max_failure_ratio: Option<f64>,
+
+    /// Rollback order
+    #[serde(default)]
+    pub order: Option<String>,
+}
+
+/// Restart policy configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RestartPolicyConfig {
+    /// Restart condition
+    #[serde(default)]
+    pub condition: Option<String>,
+
+    /// Restart delay
+    #[serde(default)]
+    pub delay: Option<String>,
+
+    /// Max attempts
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+
+    /// Restart window
+    #[serde(default)]
+    pub window: Option<String>,
+}
+
+/// Placement configuration for services
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PlacementConfig {
+    /// Placement constraints
+    #[serde(default)]
+    pub constraints: Vec<String>,
+
+    /// Placement preferences
+    #[serde(default)]
+    pub preferences: Vec<PlacementPreference>,
+
+    /// Max replicas per node
+    #[serde(default)]
+    pub max_replicas_per_node: Option<u32>,
+}
+
+/// Placement preference
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PlacementPreference {
+    /// Spread configuration
+    pub spread: String,
+}
+
+/// Proxy type enumeration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyType {
+    Caddy,
+    Nginx,
+    #[serde(rename = "haproxy")]
+    HaProxy,
+    Traefik,
+    Varnish,
+}
+
+impl ProxyType {
+    /// Convert to string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProxyType::Caddy => "caddy",
+            ProxyType::Nginx => "nginx",
+            ProxyType::HaProxy => "haproxy",
+            ProxyType::Traefik => "traefik",
+            ProxyType::Varnish => "varnish",
+        }
+    }
+
+    /// Non-root `uid:gid` a hardened container runs as
+    ///
+    /// `None` keeps the image's user, which is already unprivileged.
+    pub fn hardened_user(&self) -> Option<&'static str> {
+        match self {
+            ProxyType::Caddy => Some("1000:1000"),
+            ProxyType::Nginx => Some("101:101"),
+            ProxyType::Traefik => Some("65534:65534"),
+            ProxyType::HaProxy | ProxyType::Varnish => None,
+        }
+    }
+
+    /// Directories the proxy writes at runtime (pid files, caches,
+    /// certificates), mounted as tmpfs when the root filesystem is read-only
+    pub fn runtime_dirs(&self) -> &'static [&'static str] {
+        match self {
+            ProxyType::Caddy => &["/data", "/config", "/tmp"],
+            ProxyType::Nginx => &["/var/cache/nginx", "/run", "/tmp"],
+            ProxyType::HaProxy => &["/run/haproxy", "/tmp"],
+            ProxyType::Traefik => &["/tmp"],
+            ProxyType::Varnish => &["/var/lib/varnish", "/tmp"],
+        }
+    }
+
+    /// Directory the generated configuration is mounted at in the container
+    pub fn config_dir(&self) -> &'static str {
+        match self {
+            ProxyType::Caddy => "/etc/caddy",
+            ProxyType::Nginx => "/etc/nginx",
+            ProxyType::HaProxy => "/usr/local/etc/haproxy",
+            ProxyType::Traefik => "/etc/traefik",
+            ProxyType::Varnish => "/etc/varnish",
+        }
+    }
+
+    /// Shell command that makes the proxy parse its mounted configuration
+    ///
+    /// Traefik has no check mode; it reports invalid dynamic files in its log.
+    pub fn check_command(&self) -> Option<String> {
+        let dir = self.config_dir();
+        match self {
+            ProxyType::Nginx => Some("nginx -t -q".to_string()),
+            ProxyType::HaProxy => Some(format!("haproxy -c -q -f {dir}/haproxy.cfg")),
+            ProxyType::Caddy => Some(format!(
+                "caddy validate --config {dir}/Caddyfile --adapter caddyfile"
+            )),
+            ProxyType::Varnish => Some(format!("varnishd -C -f {dir}/default.vcl > /dev/null")),
+            ProxyType::Traefik => None,
+        }
+    }
+
+    /// Directory the shared log volume is mounted at in the container
+    pub fn log_dir(&self) -> &'static str {
+        match self {
+            ProxyType::Caddy => "/var/log/caddy",
+            ProxyType::Nginx => "/var/log/nginx",
+            ProxyType::Traefik => "/var/log/traefik",
+            _ => "/var/log/proxy",
+        }
+    }
+
+    /// Whether the proxy only finds the mounted config when told its path
+    ///
+    /// Nginx and Traefik read their default paths; HAProxy and Caddy get the
+    /// path from the image's default command, which an override replaces.
+    pub fn needs_config_flag(&self) -> bool {
+        matches!(self, ProxyType::Caddy | ProxyType::HaProxy)
+    }
+}
+
+/// Container entrypoint or command override
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum CommandConfig {
+    /// Single string, split into words like a shell would
+    Shell(String),
+    /// Explicit argument list
+    Exec(Vec<String>),
+}
+
+impl CommandConfig {
+    /// Individual arguments (shell form is split on whitespace, honoring quotes)
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            CommandConfig::Exec(args) => args.clone(),
+            CommandConfig::Shell(command) => {
+                let mut args = Vec::new();
+                let mut current = String::new();
+                let mut quote = None;
+                let mut in_word = false;
+                for c in command.chars() {
+                    match (quote, c) {
+                        (Some(q), c) if c == q => quote = None,
+                        (Some(_), c) => current.push(c),
+                        (None, '"' | '\'') => {
+                            quote = Some(c);
+                            in_word = true;
+                        }
+                        (None, c) if c.is_whitespace() => {
+                            if in_word {
+                                args.push(std::mem::take(&mut current));
+                                in_word = false;
+                            }
+                        }
+                        (None, c) => {
+                            current.push(c);
+                            in_word = true;
+                        }
+                    }
+                }
+                if in_word {
+                    args.push(current);
+                }
+                args
+            }
+        }
+    }
+
+    /// Whether any argument mentions the given text
+    pub fn mentions(&self, text: &str) -> bool {
+        self.args().iter().any(|arg| arg.contains(text))
+    }
+}
+
+impl std::fmt::Display for ProxyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Route type for conditional routing
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum RouteType {
+    /// Direct routing (bypass DDoS protection)
+    Direct,
+    /// Conditional routing (some paths bypass DDoS protection)
+    Conditional,
+}
+
+/// Transport a service or route is proxied over
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    /// HTTP, routed by domain
+    #[default]
+    Http,
+    /// TCP stream forwarded from its own port (databases, mail)
+    Tcp,
+    /// UDP datagrams forwarded from their own port (game servers, DNS)
+    Udp,
+}
+
+impl Protocol {
+    /// Name used in configuration and generated files
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Protocol::Http => "http",
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+        }
+    }
+
+    /// Whether traffic is forwarded as a layer-4 stream instead of routed by host
+    pub fn is_stream(self) -> bool {
+        self != Protocol::Http
+    }
+}
+
+/// Layer-4 stream forwarded by a proxy
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StreamRoute {
+    /// Identifier used in generated configs
+    pub id: String,
+    /// Service name or route domain the stream belongs to
+    pub name: String,
+    /// Transport of the stream
+    pub protocol: Protocol,
+    /// Port the proxy listens on
+    pub listen_port: u16,
+    /// `host:port` the stream is forwarded to
+    pub address: String,
+    /// TLS server names routed to the stream when tcp streams share a port
+    pub server_names: Vec<String>,
+}
+
+/// Parse an HTTP upstream, reading a bare `host[:port]` as `http://`
+pub(crate) fn parse_upstream(upstream: &str) -> std::result::Result<url::Url, url::ParseError> {
+    if upstream.contains("://") {
+        url::Url::parse(upstream)
+    } else {
+        url::Url::parse(&format!("http://{upstream}"))
+    }
+}
+
+/// `host:port` of a stream upstream (`db:5432` or `tcp://db:5432`)
+pub fn stream_address(upstream: &str) -> Option<String> {
+    let address = upstream
+        .split_once("://")
+        .map_or(upstream, |(_, rest)| rest)
+        .trim_end_matches('/');
+    // Without a default port, `tcp://` keeps whatever port was written
+    let url = parse_upstream(&format!("tcp://{address}")).ok()?;
+    let host = url.host_str().filter(|host| !host.is_empty())?;
+    let port = url.port().filter(|&port| port > 0)?;
+    (url.path().is_empty() && url.query().is_none() && url.fragment().is_none())
+        .then(|| format!("{host}:{port}"))
+}
+
+/// Host part of an upstream URL, e.g. `proxy-2` or `[::1]`
+pub(crate) fn upstream_host(upstream: &str) -> String {
+    parse_upstream(upstream)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Host and port a connection to an upstream is opened to
+///
+/// Without a port, the scheme's default port is used, and 80 for schemes
+/// without one.
+pub(crate) fn upstream_address(upstream: &str) -> (String, u16) {
+    match parse_upstream(upstream) {
+        Ok(url) => (
+            url.host_str().unwrap_or_default().to_string(),
+            url.port_or_known_default().unwrap_or(80),
+        ),
+        Err(_) => (upstream.to_string(), 80),
+    }
+}
+
+/// An HTTP upstream with its scheme and host lowercased
+///
+/// The port and path are kept as written: a default port such as `:80` stays
+/// explicit, and a trailing slash changes how nginx rewrites the request URI.
+/// Anything else, such as a pool name or a stream address, is returned as is.
+pub fn normalize_upstream(upstream: &str) -> String {
+    let Some((_, rest)) = upstream.split_once("://") else {
+        return upstream.to_string();
+    };
+    let (authority, tail) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+    let url = match url::Url::parse(upstream) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && !authority.contains('@') => url,
+        _ => return upstream.to_string(),
+    };
+    let Some(host) = url.host_str() else {
+        return upstream.to_string();
+    };
+    let port = match authority.rsplit_once(':') {
+        // An empty port is left for validation to report
+        Some((_, "")) => return upstream.to_string(),
+        Some((_, port)) if !port.contains(']') => url
+            .port_or_known_default()
+            .map_or(String::new(), |port| format!(":{port}")),
+        _ => String::new(),
+    };
+    format!("{}://{host}{port}{tail}", url.scheme())
+}
+
+/// Whether an upstream points at the loopback or unspecified address
+///
+/// Inside a container such an address is the container itself, not the host.
+pub(crate) fn is_local_upstream(upstream: &str) -> bool {
+    match parse_upstream(upstream)
+        .ok()
+        .and_then(|url| url.host().map(|host| host.to_owned()))
+    {
+        Some(url::Host::Domain(domain)) => domain == "localhost" || domain.ends_with(".localhost"),
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback() || ip.is_unspecified(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback() || ip.is_unspecified(),
+        None => false,
+    }
+}
+
+/// Routing configuration for proxy layers
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RouteConfig {
+    /// Type of routing
+    #[serde(rename = "type")]
+    pub route_type: RouteType,
+
+    /// Domain this route applies to
+    pub domain: String,
+
+    /// Upstream destination, or the name of an `[[upstreams]]` pool
+    pub upstream: String,
+
+    /// Paths that bypass DDoS protection (for conditional routing)
+    #[serde(default)]
+    pub bypass_paths: Vec<String>,
+
+    /// Anubis challenge difficulty override for this route (1-10)
+    #[serde(default)]
+    pub difficulty: Option<u8>,
+
+    /// HTTP methods accepted at the edge (empty = all methods)
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+
+    /// Path prefixes with their own Anubis protection
+    ///
+    /// With paths, `upstream` receives the bypassed requests and challenged
+    /// requests go to Anubis. Paths no prefix matches follow the route type.
+    #[serde(default)]
+    pub paths: Vec<RoutePathConfig>,
+
+    /// Transport of the route; `tcp` and `udp` forward `listen_port` to `upstream`
+    #[serde(default)]
+    pub protocol: Protocol,
+
+    /// Port the proxy listens on for a `tcp` or `udp` route
+    #[serde(default)]
+    pub listen_port: Option<u16>,
+
+    /// Upstream timeouts and retry budget of the route's path rules
+    #[serde(default)]
+    pub timeouts: TimeoutsConfig,
+}
+
+impl RouteConfig {
+    /// Whether requests no path prefix matches are challenged by Anubis
+    pub fn protected_by_default(&self) -> bool {
+        self.route_type != RouteType::Direct
+    }
+
+    /// Path rules with the longest prefix first, as the edge matches them
+    pub fn path_rules(&self) -> Vec<&RoutePathConfig> {
+        let mut rules: Vec<&RoutePathConfig> = self.paths.iter().collect();
+        rules.sort_by_key(|rule| std::cmp::Reverse(rule.prefix.len()));
+        rules
+    }
+}
+
+/// Anubis protection of a path prefix within a route
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RoutePathConfig {
+    /// Path prefix, e.g. `/api/`
+    pub prefix: String,
+
+    /// Whether requests under the prefix are challenged by Anubis
+    pub protect: bool,
+}
+
+/// Proxy layer configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProxyConfig {
+    /// Proxy instance name
+    pub name: String,
+
+    /// Proxy software type
+    #[serde(rename = "type")]
+    pub proxy_type: ProxyType,
+
+    /// Host port published to the internet
+    ///
+    /// `None` publishes nothing: inner layers are reachable only from other
+    /// containers on their networks. The proxy always listens on
+    /// `internal_port` inside the container.
+    #[serde(default)]
+    pub external_port: Option<u16>,
+
+    /// Internal container port
+    #[serde(default = "default_internal_port")]
+    pub internal_port: u16,
+
+    /// Proxy layer number
+    #[serde(default)]
+    pub layer: Option<u8>,
+
+    /// Number of instances for scaling
+    #[serde(default = "default_instances")]
+    pub instances: u8,
+
+    /// Host ports of the containers when the proxy runs more than one
+    #[serde(default)]
+    pub instance_port_strategy: InstancePortStrategy,
+
+    /// Load balancing algorithm
+    #[serde(default)]
+    pub algorithm: Option<String>,
+
+    /// Maximum connections
+    #[serde(default)]
+    pub max_connections: Option<u32>,
+
+    /// Default upstream for unmatched requests (formerly `upstream`)
+    #[serde(default, alias = "upstream")]
+    pub default_upstream: Option<String>,
+
+    /// Specific routing configurations
+    #[serde(default)]
+    pub routes: Vec<RouteConfig>,
+
+    /// Docker build context path
+    #[serde(default)]
+    pub build_context: Option<String>,
+
+    /// Docker build dockerfile path
+    #[serde(default)]
+    pub build_dockerfile: Option<String>,
+
+    /// Docker entrypoint override (string or list form)
+    #[serde(default)]
+    pub entrypoint: Option<CommandConfig>,
+
+    /// Docker command override (string or list form)
+    #[serde(default)]
+    pub command: Option<CommandConfig>,
+
+    /// Docker volumes
+    #[serde(default)]
+    pub volumes: Vec<String>,
+
+    /// Docker networks
+    #[serde(default)]
+    pub networks: Vec<String>,
+
+    /// Docker restart policy
+    #[serde(default)]
+    pub restart: Option<String>,
+
+    /// Docker secrets references
+    #[serde(default)]
+    pub secrets: Vec<ServiceSecretRef>,
+
+    /// Docker configs references
+    #[serde(default)]
+    pub configs: Vec<ServiceConfigRef>,
+
+    /// Docker service dependencies
+    #[serde(default)]
+    pub depends_on: Option<DependsOn>,
+
+    /// Docker healthcheck configuration
+    #[serde(default)]
+    pub healthcheck: Option<HealthcheckConfig>,
+
+    /// Docker logging configuration
+    #[serde(default)]
+    pub logging: Option<LoggingDriverConfig>,
+
+    /// Docker resource constraints
+    #[serde(default)]
+    pub deploy: Option<DeployConfig>,
+
+    /// Environment variables
+    #[serde(default)]
+    pub environment: BTreeMap<String, String>,
+
+    /// Environment files
+    #[serde(default)]
+    pub env_file: Vec<String>,
+
+    /// Exposed ports (internal only)
+    #[serde(default)]
+    pub expose: Vec<String>,
+
+    /// External links (deprecated but supported)
+    #[serde(default)]
+    pub external_links: Vec<String>,
+
+    /// Labels
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+
+    /// Service whose federation paths a layer-1 Nginx routes past Anubis
+    ///
+    /// Defaults to the first service with a Fediverse `template`.
+    #[serde(default)]
+    pub special_routing_service: Option<String>,
+
+    /// HAProxy statistics page and runtime API socket
+    #[serde(default)]
+    pub stats: Option<StatsConfig>,
+
+    /// Varnish cache settings
+    #[serde(default)]
+    pub varnish: Option<VarnishConfig>,
+
+    /// Connection draining on shutdown and reload
+    #[serde(default)]
+    pub drain: Option<DrainConfig>,
+
+    /// Worker, connection and timeout limits
+    #[serde(default)]
+    pub tuning: Option<TuningConfig>,
+
+    /// Raw directives injected into the generated configuration
+    #[serde(default, alias = "extra_config")]
+    pub snippets: Option<SnippetsConfig>,
+
+    /// When the proxy image is pulled (overrides `[defaults]`)
+    #[serde(default)]
+    pub pull_policy: Option<PullPolicy>,
+
+    /// Proxy image platform, e.g. "linux/arm64" (overrides `[defaults]`)
+    #[serde(default)]
+    pub platform: Option<String>,
+
+    /// Platforms the proxy image is built for (overrides `[defaults]`)
+    #[serde(default)]
+    pub platforms: Vec<String>,
+
+    /// Time the proxy gets to stop before it is killed (overrides `[defaults]`)
+    #[serde(default)]
+    pub stop_grace_period: Option<String>,
+}
+
+/// Graceful shutdown settings (`[proxies.drain]`)
+///
+/// On stop the proxy closes its listeners and lets in-flight requests finish
+/// for up to `timeout` (Nginx `worker_shutdown_timeout`, HAProxy soft-stop
+/// bounded by `hard-stop-after`, Caddy `grace_period`, Traefik
+/// `graceTimeOut`) before Docker kills it after `stop_grace_period`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DrainConfig {
+    /// Time in-flight requests get to finish (e.g. "30s")
+    #[serde(default = "default_drain_timeout")]
+    pub timeout: String,
+
+    /// Time the runtime waits before killing the container
+    ///
+    /// Defaults to `timeout` plus five seconds.
+    #[serde(default)]
+    pub stop_grace_period: Option<String>,
+
+    /// Start the proxy through a generated entrypoint that drains on
+    /// SIGTERM and reloads the configuration on SIGHUP
+    #[serde(default = "default_drain_entrypoint")]
+    pub entrypoint: bool,
+}
+
+impl Default for DrainConfig {
+    fn default() -> Self {
+        Self {
+            timeout: default_drain_timeout(),
+            stop_grace_period: None,
+            entrypoint: default_drain_entrypoint(),
+        }
+    }
+}
+
+fn default_drain_timeout() -> String {
+    "30s".to_string()
+}
+
+fn default_drain_entrypoint() -> bool {
+    true
+}
+
+/// Worker, connection and timeout limits (`[proxies.tuning]`)
+///
+/// Unset knobs keep the generated defaults. Which knobs apply depends on
+/// the proxy type: Nginx takes the worker settings and
+/// `client_max_body_size`, HAProxy takes `maxconn` and the `timeout_*`
+/// settings, and both take `keepalive_timeout` (HAProxy
+/// `timeout http-keep-alive`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TuningConfig {
+    /// Nginx worker processes: "auto" or a count (default "auto")
+    #[serde(default)]
+    pub worker_processes: Option<WorkerProcesses>,
+
+    /// Connections per Nginx worker (default 1024)
+    #[serde(default)]
+    pub worker_connections: Option<u32>,
+
+    /// Idle time a client connection is kept open (Nginx default "65s",
+    /// HAProxy default "2s")
+    #[serde(default)]
+    pub keepalive_timeout: Option<String>,
+
+    /// Largest request body an edge Nginx proxy accepts (default "10G")
+    #[serde(default)]
+    pub client_max_body_size: Option<String>,
+
+    /// Concurrent HAProxy connections (default 4096)
+    #[serde(default)]
+    pub maxconn: Option<u32>,
+
+    /// HAProxy time to connect to a server (default "5s")
+    #[serde(default)]
+    pub timeout_connect: Option<String>,
+
+    /// HAProxy client inactivity timeout (default "50s")
+    #[serde(default)]
+    pub timeout_client: Option<String>,
+
+    /// HAProxy server inactivity timeout (default "50s")
+    #[serde(default)]
+    pub timeout_server: Option<String>,
+}
+
+impl TuningConfig {
+    /// Names of the knobs that are set
+    pub fn set_keys(&self) -> Vec<&'static str> {
+        [
+            ("worker_processes", self.worker_processes.is_some()),
+            ("worker_connections", self.worker_connections.is_some()),
+            ("keepalive_timeout", self.keepalive_timeout.is_some()),
+            ("client_max_body_size", self.client_max_body_size.is_some()),
+            ("maxconn", self.maxconn.is_some()),
+            ("timeout_connect", self.timeout_connect.is_some()),
+            ("timeout_client", self.timeout_client.is_some()),
+            ("timeout_server", self.timeout_server.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(key, set)| set.then_some(key))
+        .collect()
+    }
+
+    /// Whether a knob of the main `nginx.conf` is set
+    pub fn tunes_nginx_main(&self) -> bool {
+        self.worker_processes.is_some()
+            || self.worker_connections.is_some()
+            || self.keepalive_timeout.is_some()
+    }
+}
+
+/// Knobs each proxy type accepts in `[proxies.tuning]`
+fn tuning_keys(proxy_type: &ProxyType) -> &'static [&'static str] {
+    match proxy_type {
+        ProxyType::Nginx => &[
+            "worker_processes",
+            "worker_connections",
+            "keepalive_timeout",
+            "client_max_body_size",
+        ],
+        ProxyType::HaProxy => &[
+            "maxconn",
+            "keepalive_timeout",
+            "timeout_connect",
+            "timeout_client",
+            "timeout_server",
+        ],
+        _ => &[],
+    }
+}
+
+/// Raw directives injected into generated proxy configurations
+/// (`[proxies.snippets]`, `[services.snippets]`; alias `extra_config`)
+///
+/// Each scope is copied verbatim, so it must be valid for the proxy that
+/// receives it. Which scopes apply depends on the proxy type: see
+/// [`snippet_scopes`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct SnippetsConfig {
+    /// Top-level directives: Nginx `http {}`, the Caddy global options,
+    /// HAProxy `global`
+    #[serde(default)]
+    pub http: Option<String>,
+
+    /// Directives of the virtual host: Nginx `server {}`, the Caddy site
+    /// block, the HAProxy frontend
+    #[serde(default)]
+    pub server: Option<String>,
+
+    /// Directives next to `proxy_pass`: Nginx `location /`, the Caddy
+    /// `handle` block of a service, the HAProxy backend of a service
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+impl SnippetsConfig {
+    /// Scopes that are set, with their directives
+    pub fn set_scopes(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("http", &self.http),
+            ("server", &self.server),
+            ("location", &self.location),
+        ]
+        .into_iter()
+        .filter_map(|(scope, value)| Some((scope, value.as_deref()?)))
+        .collect()
+    }
+}
+
+/// Snippet scopes a proxy type accepts from `[proxies.snippets]` (`service`
+/// false) or from the `[services.snippets]` of the services it routes
+pub fn snippet_scopes(proxy_type: &ProxyType, service: bool) -> &'static [&'static str] {
+    match (proxy_type, service) {
+        (ProxyType::Nginx, _) => &["http", "server", "location"],
+        (ProxyType::Caddy | ProxyType::HaProxy, false) => &["http", "server"],
+        (ProxyType::Caddy | ProxyType::HaProxy, true) => &["location"],
+        _ => &[],
+    }
+}
+
+/// Nginx `worker_processes`: "auto" or a fixed count
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum WorkerProcesses {
+    /// Fixed number of workers
+    Count(u32),
+    /// Keyword; only "auto" (one worker per CPU core) is valid
+    Keyword(String),
+}
+
+impl std::fmt::Display for WorkerProcesses {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkerProcesses::Count(count) => write!(f, "{count}"),
+            WorkerProcesses::Keyword(keyword) => write!(f, "{keyword}"),
+        }
+    }
+}
+
+/// Varnish cache layer settings (`[proxies.varnish]`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VarnishConfig {
+    /// Size of the in-memory cache (e.g. "256M", "1G")
+    #[serde(default = "default_varnish_storage")]
+    pub storage: String,
+
+    /// Addresses or CIDR ranges allowed to send `PURGE` requests
+    #[serde(default = "default_varnish_purge_acl")]
+    pub purge_acl: Vec<String>,
+}
+
+impl Default for VarnishConfig {
+    fn default() -> Self {
+        Self {
+            storage: default_varnish_storage(),
+            purge_acl: default_varnish_purge_acl(),
+        }
+    }
+}
+
+fn default_varnish_storage() -> String {
+    "256M".to_string()
+}
+
+fn default_varnish_purge_acl() -> Vec<String> {
+    vec!["localhost".to_string()]
+}
+
+/// HAProxy statistics frontend and admin socket (`[proxies.stats]`)
+///
+/// The admin socket of every container is placed on a shared volume so
+/// other containers can read connection counts from the runtime API.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StatsConfig {
+    /// Serve the statistics page and share the admin socket
+    #[serde(default = "default_stats_enabled")]
+    pub enabled: bool,
+
+    /// Port of the statistics frontend, published on the host unless
+    /// `[ops]` routes to it
+    #[serde(default = "default_stats_port")]
+    pub port: u16,
+
+    /// Basic auth credentials for the statistics page (`user:password`)
+    #[serde(default)]
+    pub credentials: Option<String>,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_stats_enabled(),
+            port: default_stats_port(),
+            credentials: None,
+        }
+    }
+}
+
+fn default_stats_enabled() -> bool {
+    true
+}
+
+fn default_stats_port() -> u16 {
+    8404
+}
+
+fn default_internal_port() -> u16 {
+    80
+}
+
+fn default_instances() -> u8 {
+    1
+}
+
+/// How the containers of a proxy with several instances publish its port
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum InstancePortStrategy {
+    /// Each container binds the next host port after the first one
+    #[default]
+    Offset,
+    /// Docker picks a free host port for each container
+    Random,
+    /// Only a generated load balancer in front of the containers binds the port
+    InternalOnly,
+}
+
+impl InstancePortStrategy {
+    /// Name used in configuration
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InstancePortStrategy::Offset => "offset",
+            InstancePortStrategy::Random => "random",
+            InstancePortStrategy::InternalOnly => "internal-only",
+        }
+    }
+}
+
+impl ProxyConfig {
+    /// Whether the proxy routes each service itself
+    ///
+    /// Layer-1 Nginx sends every service to the next layer or Anubis, and
+    /// Varnish only caches.
+    pub fn renders_services(&self) -> bool {
+        match self.proxy_type {
+            ProxyType::Caddy | ProxyType::HaProxy | ProxyType::Traefik => true,
+            ProxyType::Nginx => self.layer.unwrap_or(1) != 1,
+            ProxyType::Varnish => false,
+        }
+    }
+
+    /// Replicas requested through `deploy.replicas`
+    pub fn deploy_replicas(&self) -> Option<u32> {
+        self.deploy.as_ref().and_then(|deploy| deploy.replicas)
+    }
+
+    /// Number of separately named containers generated for this proxy
+    ///
+    /// `instances` only duplicates containers when scaling is enabled and
+    /// `deploy.replicas` is not used; validation rejects setting both.
+    pub fn duplicated_instances(&self, scaling: bool) -> u8 {
+        if scaling && self.deploy_replicas().is_none() {
+            self.instances.max(1)
+        } else {
+            1
+        }
+    }
+
+    /// Total number of containers running this proxy
+    pub fn container_count(&self, scaling: bool) -> u32 {
+        self.deploy_replicas()
+            .unwrap_or_else(|| u32::from(self.duplicated_instances(scaling)))
+    }
+
+    /// Names of the separately named containers generated for this proxy
+    pub fn container_names(&self, scaling: bool) -> Vec<String> {
+        (1..=self.duplicated_instances(scaling))
+            .map(|instance| match instance {
+                1 => self.name.clone(),
+                n => format!("{}-{n}", self.name),
+            })
+            .collect()
+    }
+
+    /// Name of the load balancer in front of the containers, if one is generated
+    ///
+    /// Only `internal-only` proxies publishing a port over several
+    /// containers get one.
+    pub fn instance_balancer(&self, scaling: bool) -> Option<String> {
+        (self.instance_port_strategy == InstancePortStrategy::InternalOnly
+            && self.external_port.is_some()
+            && self.container_count(scaling) > 1)
+            .then(|| format!("{}-lb", self.name))
+    }
+
+    /// Enabled statistics configuration of an HAProxy proxy
+    pub fn enabled_stats(&self) -> Option<&StatsConfig> {
+        self.stats
+            .as_ref()
+            .filter(|stats| stats.enabled && self.proxy_type == ProxyType::HaProxy)
+    }
+
+    /// Environment variables the proxy image reads its settings from
+    ///
+    /// The official Varnish image takes its listen port and cache size from
+    /// the environment rather than from the VCL.
+    pub fn image_environment(&self) -> Vec<(&'static str, String)> {
+        match self.proxy_type {
+            ProxyType::Varnish => vec![
+                ("VARNISH_HTTP_PORT", self.internal_port.to_string()),
+                (
+                    "VARNISH_SIZE",
+                    self.varnish.clone().unwrap_or_default().storage,
+                ),
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Seconds in-flight requests get to finish on shutdown
+    pub fn drain_timeout_secs(&self) -> Option<u64> {
+        self.drain
+            .as_ref()
+            .and_then(|drain| parse_duration_secs(&drain.timeout))
+    }
+
+    /// Seconds the runtime waits for the proxy to stop before killing it
+    ///
+    /// The proxy's own `stop_grace_period` wins over the one derived from
+    /// `[proxies.drain]`; `[defaults]` only applies through
+    /// [`ProxyConfig::container_options`].
+    pub fn stop_grace_period_secs(&self) -> Option<u64> {
+        if let Some(period) = &self.stop_grace_period {
+            return parse_duration_secs(period);
+        }
+        let drain = self.drain.as_ref()?;
+        match &drain.stop_grace_period {
+            Some(period) => parse_duration_secs(period),
+            None => self.drain_timeout_secs().map(|timeout| timeout + 5),
+        }
+    }
+
+    /// Container options of the proxy, falling back to `[defaults]`
+    pub fn container_options(&self, defaults: &DefaultsConfig) -> ContainerOptions {
+        ContainerOptions {
+            pull_policy: self.pull_policy.or(defaults.pull_policy),
+            platform: self.platform.clone().or_else(|| defaults.platform.clone()),
+            platforms: if self.platforms.is_empty() {
+                defaults.platforms.clone()
+            } else {
+                self.platforms.clone()
+            },
+            stop_grace_period_secs: self.stop_grace_period_secs().or_else(|| {
+                defaults
+                    .stop_grace_period
+                    .as_deref()
+                    .and_then(parse_duration_secs)
+            }),
+        }
+    }
+
+    /// Whether the proxy starts through the generated draining entrypoint
+    ///
+    /// Varnish keeps the image's entrypoint, which configures the cache
+    /// from the environment.
+    pub fn uses_drain_entrypoint(&self) -> bool {
+        self.drain.as_ref().is_some_and(|drain| drain.entrypoint)
+            && self.proxy_type != ProxyType::Varnish
+    }
+
+    /// Resource limits and reservations from `deploy.resources`
+    pub fn resources(&self) -> Option<&ResourcesConfig> {
+        self.deploy
+            .as_ref()
+            .and_then(|deploy| deploy.resources.as_ref())
+    }
+
+    /// Signal that makes a running proxy reload its configuration in place
+    ///
+    /// `None` means no signal reloads the proxy: Caddy reloads through its
+    /// admin API, Traefik only watches its dynamic files, and Varnish needs
+    /// `varnishreload`.
+    pub fn reload_signal(&self) -> Option<&'static str> {
+        match self.proxy_type {
+            ProxyType::Nginx => Some("HUP"),
+            // The draining entrypoint forwards SIGHUP as SIGUSR2
+            ProxyType::HaProxy if self.uses_drain_entrypoint() => Some("HUP"),
+            ProxyType::HaProxy => Some("USR2"),
+            ProxyType::Caddy | ProxyType::Traefik | ProxyType::Varnish => None,
+        }
+    }
+
+    /// Networks the proxy joins, falling back to the default topology
+    pub fn network_names(&self) -> Vec<&str> {
+        if self.networks.is_empty() {
+            vec!["front-net", "back-net"]
+        } else {
+            self.networks.iter().map(String::as_str).collect()
+        }
+    }
+}
+
+/// Named pool of upstream servers (`[[upstreams]]`)
+///
+/// Services and routes set `upstream` to the pool's name instead of a URL.
+/// Nginx renders one `upstream` block per pool that every service using it
+/// shares; the other proxies list the pool's servers in each backend.
+///
+/// ```toml
+/// [[upstreams]]
+/// name = "app-cluster"
+/// lb_policy = "least_conn"
+/// keepalive = 32
+/// servers = [
+///     { url = "http://app-1:3000", weight = 3 },
+///     { url = "http://app-2:3000" },
+/// ]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct UpstreamPoolConfig {
+    /// Name services and routes reference in `upstream`
+    pub name: String,
+
+    /// Servers requests are balanced across
+    pub servers: Vec<UpstreamServerConfig>,
+
+    /// How a server is picked for each request
+    #[serde(default)]
+    pub lb_policy: LbPolicy,
+
+    /// Idle connections kept open to each server
+    #[serde(default)]
+    pub keepalive: Option<u32>,
+}
+
+impl UpstreamPoolConfig {
+    /// Scheme every server of the pool is reached with
+    pub fn scheme(&self) -> &str {
+        self.servers
+            .first()
+            .and_then(|server| server.url.split_once("://"))
+            .map_or("http", |(scheme, _)| scheme)
+    }
+
+    /// Whether any server carries a weight other than the default
+    pub fn is_weighted(&self) -> bool {
+        self.servers.iter().any(|server| server.weight != 1)
+    }
+}
+
+/// One server of an upstream pool
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct UpstreamServerConfig {
+    /// Server URL (e.g. "http://app-1:3000")
+    pub url: String,
+
+    /// Share of requests relative to the other servers (1-256, default: 1)
+    #[serde(default = "default_upstream_weight")]
+    pub weight: u32,
+}
+
+fn default_upstream_weight() -> u32 {
+    1
+}
+
+/// Load-balancing policy of an upstream pool
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LbPolicy {
+    /// Take turns, in proportion to the weights
+    #[default]
+    RoundRobin,
+    /// Server with the fewest active connections
+    LeastConn,
+    /// Same server for the same client address
+    IpHash,
+    /// Random server
+    Random,
+}
+
+impl LbPolicy {
+    /// Name used in configuration
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LbPolicy::RoundRobin => "round_robin",
+            LbPolicy::LeastConn => "least_conn",
+            LbPolicy::IpHash => "ip_hash",
+            LbPolicy::Random => "random",
+        }
+    }
+
+    /// Directive of an nginx `upstream` block; round robin needs none
+    pub fn nginx(self) -> Option<&'static str> {
+        match self {
+            LbPolicy::RoundRobin => None,
+            LbPolicy::LeastConn => Some("least_conn"),
+            LbPolicy::IpHash => Some("ip_hash"),
+            LbPolicy::Random => Some("random"),
+        }
+    }
+
+    /// HAProxy `balance` algorithm
+    pub fn haproxy(self) -> &'static str {
+        match self {
+            LbPolicy::RoundRobin => "roundrobin",
+            LbPolicy::LeastConn => "leastconn",
+            LbPolicy::IpHash => "source",
+            LbPolicy::Random => "random",
+        }
+    }
+}
+
+/// Backend service configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ServiceConfig {
+    /// Service name
+    pub name: String,
+
+    /// Application template the service was expanded from
+    #[serde(default)]
+    pub template: Option<AppTemplate>,
+
+    /// Domain this service serves
+    #[serde(default)]
+    pub domain: String,
+
+    /// Further domains served by this service; `*.` matches one label
+    #[serde(default)]
+    pub domains: Vec<String>,
+
+    /// Upstream URL, or the name of an `[[upstreams]]` pool
+    pub upstream: String,
+
+    /// Enable WebSocket support
+    #[serde(default)]
+    pub websocket: bool,
+
+    /// Response compression
+    #[serde(default)]
+    pub compression: CompressionConfig,
+
+    /// Maximum request body size
+    #[serde(default = "default_max_body_size")]
+    pub max_body_size: String,
+
+    /// HTTP methods accepted at the edge (empty = all methods)
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+
+    /// Edge caching policy
+    #[serde(default)]
+    pub cache: Option<CacheConfig>,
+
+    /// Active health checks the proxies run against the upstream
+    #[serde(default)]
+    pub health: Option<UpstreamHealthConfig>,
+
+    /// Upstreams that take requests while `upstream` is down, in order
+    #[serde(default)]
+    pub backup_upstreams: Vec<String>,
+
+    /// How failed requests are retried on the next upstream
+    #[serde(default)]
+    pub failover: Option<FailoverConfig>,
+
+    /// Upstream timeouts and retry budget (unset = the proxy defaults)
+    #[serde(default)]
+    pub timeouts: TimeoutsConfig,
+
+    /// Docker image for the backend container
+    #[serde(default)]
+    pub image: Option<String>,
+
+    /// Docker build configuration for the backend container
+    #[serde(default)]
+    pub build: Option<DockerBuildConfig>,
+
+    /// Published ports ("host:container")
+    #[serde(default)]
+    pub ports: Vec<String>,
+
+    /// Environment variables
+    #[serde(default)]
+    pub environment: BTreeMap<String, String>,
+
+    /// Docker volumes
+    #[serde(default)]
+    pub volumes: Vec<String>,
+
+    /// Docker service dependencies
+    #[serde(default)]
+    pub depends_on: Option<DependsOn>,
+
+    /// Docker healthcheck configuration
+    #[serde(default)]
+    pub healthcheck: Option<HealthcheckConfig>,
+
+    /// Number of container replicas
+    #[serde(default)]
+    pub replicas: Option<u32>,
+
+    /// Time windows during which paths of this service are served
+    #[serde(default)]
+    pub schedules: Vec<ScheduleConfig>,
+
+    /// Upstream of the release being rolled out (green pool or canary)
+    #[serde(default)]
+    pub candidate_upstream: Option<String>,
+
+    /// Ownership and SLO metadata carried into generated artifacts
+    #[serde(default)]
+    pub observability: Option<ObservabilityConfig>,
+
+    /// Per-service WAF settings
+    #[serde(default)]
+    pub waf: ServiceWafConfig,
+
+    /// Transport of the service; `tcp` and `udp` are forwarded by the edge proxy
+    #[serde(default)]
+    pub protocol: Protocol,
+
+    /// Port the edge proxy listens on for a `tcp` or `udp` service
+    #[serde(default)]
+    pub listen_port: Option<u16>,
+
+    /// When the backend image is pulled (overrides `[defaults]`)
+    #[serde(default)]
+    pub pull_policy: Option<PullPolicy>,
+
+    /// Backend image platform, e.g. "linux/amd64" (overrides `[defaults]`)
+    #[serde(default)]
+    pub platform: Option<String>,
+
+    /// Platforms the backend image is built for (overrides `[defaults]`)
+    #[serde(default)]
+    pub platforms: Vec<String>,
+
+    /// Time the backend gets to stop before it is killed (overrides `[defaults]`)
+    #[serde(default)]
+    pub stop_grace_period: Option<String>,
+
+    /// Request and response headers the proxies change
+    #[serde(default)]
+    pub headers: HeadersConfig,
+
+    /// Authentication the proxies require before forwarding requests
+    #[serde(default)]
+    pub auth: Option<ServiceAuthConfig>,
+
+    /// Raw directives injected where the proxies route this service
+    #[serde(default, alias = "extra_config")]
+    pub snippets: Option<SnippetsConfig>,
+}
+
+fn default_compression() -> bool {
+    true
+}
+
+impl ServiceConfig {
+    /// Every domain the service answers on, `domain` first
+    pub fn hosts(&self) -> Vec<&str> {
+        let mut hosts: Vec<&str> = Vec::new();
+        for host in std::iter::once(&self.domain).chain(&self.domains) {
+            if !host.is_empty() && !hosts.contains(&host.as_str()) {
+                hosts.push(host);
+            }
+        }
+        hosts
+    }
+
+    /// Domain used where only one fits, such as labels and messages
+    pub fn primary_domain(&self) -> &str {
+        self.hosts().first().copied().unwrap_or_default()
+    }
+
+    /// Failover policy of the service, the defaults when it only lists
+    /// `backup_upstreams`
+    pub fn failover_policy(&self) -> Option<FailoverConfig> {
+        match &self.failover {
+            Some(failover) => Some(failover.clone()),
+            None if !self.backup_upstreams.is_empty() => Some(FailoverConfig::default()),
+            None => None,
+        }
+    }
+
+    /// Container options of the backend, falling back to `[defaults]`
+    pub fn container_options(&self, defaults: &DefaultsConfig) -> ContainerOptions {
+        ContainerOptions {
+            pull_policy: self.pull_policy.or(defaults.pull_policy),
+            platform: self.platform.clone().or_else(|| defaults.platform.clone()),
+            platforms: if self.platforms.is_empty() {
+                defaults.platforms.clone()
+            } else {
+                self.platforms.clone()
+            },
+            stop_grace_period_secs: self
+                .stop_grace_period
+                .as_deref()
+                .or(defaults.stop_grace_period.as_deref())
+                .and_then(parse_duration_secs),
+        }
+    }
+
+    /// Whether the service defines its own container (image or build)
+    pub fn has_container(&self) -> bool {
+        self.image.is_some() || self.build.is_some()
+    }
+
+    /// Whether the generated container has a healthcheck
+    ///
+    /// Placeholder containers (no image or build) check the upstream as
+    /// `[services.health]` says, with HTTP by default.
+    pub fn has_healthcheck(&self) -> bool {
+        self.healthcheck.is_some()
+            || (!self.has_container()
+                && self
+                    .health
+                    .as_ref()
+                    .is_none_or(UpstreamHealthConfig::is_enabled))
+    }
+
+    /// Upstream health checks the proxies run, unless `type = "none"`
+    pub fn upstream_health(&self) -> Option<&UpstreamHealthConfig> {
+        self.health.as_ref().filter(|health| health.is_enabled())
+    }
+
+    /// Environment variables whose value is a `vault://` reference, by name
+    ///
+    /// # Errors
+    /// Returns error if a reference is malformed
+    pub fn vault_environment(&self) -> Result<BTreeMap<&str, VaultRef>> {
+        let mut references = BTreeMap::new();
+        for (key, value) in &self.environment {
+            if let Some(reference) = VaultRef::parse(value).map_err(|e| {
+                CerberusError::validation(format!("Service {} environment {key}: {e}", self.name))
+            })? {
+                references.insert(key.as_str(), reference);
+            }
+        }
+        Ok(references)
+    }
+
+    /// Identifier of a schedule used in generated proxy configs and flag files
+    pub fn schedule_id(&self, index: usize) -> String {
+        format!("{}_schedule_{index}", self.name.replace('-', "_"))
+    }
+}
+
+/// Ownership and SLO metadata for a service
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ObservabilityConfig {
+    /// Team owning the service (used for alert routing)
+    #[serde(default)]
+    pub team: Option<String>,
+
+    /// Service tier (e.g. "critical", "standard")
+    #[serde(default)]
+    pub tier: Option<String>,
+
+    /// Availability objective in percent (e.g. 99.9)
+    #[serde(default)]
+    pub slo_target: Option<f64>,
+}
+
+impl ObservabilityConfig {
+    /// Label names and values for the metadata that is set
+    pub fn labels(&self) -> Vec<(&'static str, String)> {
+        [
+            ("team", self.team.clone()),
+            ("tier", self.tier.clone()),
+            (
+                "slo_target",
+                self.slo_target.map(|target| target.to_string()),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+        .collect()
+    }
+}
+
+/// Observability services run next to the proxies (`[observability]`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ObservabilityStackConfig {
+    /// Access log shipping to Loki
+    #[serde(default)]
+    pub logs: Option<LogShippingConfig>,
+
+    /// OpenTelemetry traces of the proxies
+    #[serde(default)]
+    pub tracing: Option<TracingConfig>,
+}
+
+/// OpenTelemetry tracing (`[observability.tracing]`)
+///
+/// Nginx, Caddy and Traefik emit a span per request to a bundled
+/// OpenTelemetry Collector, which batches them and exports them to
+/// `endpoint`. Every layer propagates the W3C `traceparent` header, so
+/// the spans of one request join a single trace.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct TracingConfig {
+    /// OTLP endpoint the collector exports to (e.g. "http://tempo:4317")
+    pub endpoint: String,
+
+    /// OTLP transport of the endpoint
+    #[serde(default)]
+    pub protocol: OtlpProtocol,
+
+    /// Share of the requests entering layer 1 that are traced (0.0 to 1.0)
+    ///
+    /// Inner layers follow the decision carried in `traceparent`.
+    #[serde(default = "default_tracing_sample_rate")]
+    pub sample_rate: f64,
+
+    /// Collector image
+    #[serde(default = "default_otel_collector_image")]
+    pub collector_image: String,
+}
+
+fn default_tracing_sample_rate() -> f64 {
+    1.0
+}
+
+fn default_otel_collector_image() -> String {
+    "otel/opentelemetry-collector:latest".to_string()
+}
+
+/// Host and gRPC port the proxies send their spans to
+pub const OTEL_COLLECTOR_ENDPOINT: &str = "otel-collector:4317";
+
+impl TracingConfig {
+    /// Whether a proxy emits spans; HAProxy and Varnish only pass
+    /// `traceparent` on
+    pub fn applies_to(&self, proxy: &ProxyConfig) -> bool {
+        matches!(
+            proxy.proxy_type,
+            ProxyType::Nginx | ProxyType::Caddy | ProxyType::Traefik
+        )
+    }
+
+    /// Whether the collector connects to the endpoint without TLS
+    pub fn insecure(&self) -> bool {
+        self.endpoint.starts_with("http://")
+    }
+}
+
+/// Transport of an OTLP endpoint
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum OtlpProtocol {
+    /// OTLP over gRPC, usually port 4317
+    #[default]
+    Grpc,
+    /// OTLP over HTTP, usually port 4318
+    Http,
+}
+
+impl OtlpProtocol {
+    /// Name used in configuration
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OtlpProtocol::Grpc => "grpc",
+            OtlpProtocol::Http => "http",
+        }
+    }
+}
+
+/// Access log shipping (`[observability.logs]`)
+///
+/// A Vector or Promtail container reads the access logs the proxies write
+/// to `built/logs` (HAProxy's through the Docker socket) and pushes them to
+/// Loki, either a bundled container or an existing instance.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LogShippingConfig {
+    /// Agent reading and parsing the logs
+    #[serde(default)]
+    pub shipper: LogShipper,
+
+    /// Shipper image (defaults to the shipper's official image)
+    #[serde(default)]
+    pub image: Option<String>,
+
+    /// Existing Loki to push to instead of running a bundled one
+    #[serde(default)]
+    pub loki_url: Option<String>,
+
+    /// Bundled Loki image
+    #[serde(default = "default_loki_image")]
+    pub loki_image: String,
+
+    /// Host port publishing the bundled Loki's API
+    #[serde(default)]
+    pub loki_port: Option<u16>,
+
+    /// How long the bundled Loki keeps logs (e.g. "7d")
+    #[serde(default = "default_log_retention")]
+    pub retention: String,
+}
+
+impl Default for LogShippingConfig {
+    fn default() -> Self {
+        Self {
+            shipper: LogShipper::default(),
+            image: None,
+            loki_url: None,
+            loki_image: default_loki_image(),
+            loki_port: None,
+            retention: default_log_retention(),
+        }
+    }
+}
+
+fn default_loki_image() -> String {
+    "grafana/loki:latest".to_string()
+}
+
+fn default_log_retention() -> String {
+    "7d".to_string()
+}
+
+impl LogShippingConfig {
+    /// Shipper image, the configured one or the shipper's default
+    pub fn shipper_image(&self) -> &str {
+        self.image
+            .as_deref()
+            .unwrap_or_else(|| self.shipper.default_image())
+    }
+
+    /// Whether a Loki container is generated
+    pub fn bundles_loki(&self) -> bool {
+        self.loki_url.is_none()
+    }
+
+    /// Base URL of the Loki the shipper pushes to
+    pub fn loki_url(&self) -> &str {
+        self.loki_url
+            .as_deref()
+            .map_or("http://loki:3100", |url| url.trim_end_matches('/'))
+    }
+}
+
+/// Agent shipping the access logs to Loki
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum LogShipper {
+    /// Vector (`timberio/vector`)
+    #[default]
+    Vector,
+    /// Grafana Promtail
+    Promtail,
+}
+
+impl LogShipper {
+    /// Name used in configuration, as the container name and config file stem
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogShipper::Vector => "vector",
+            LogShipper::Promtail => "promtail",
+        }
+    }
+
+    /// Official image of the shipper
+    pub fn default_image(self) -> &'static str {
+        match self {
+            LogShipper::Vector => "timberio/vector:latest-alpine",
+            LogShipper::Promtail => "grafana/promtail:latest",
+        }
+    }
+}
+
+fn default_max_body_size() -> String {
+    "1m".to_string()
+}
+
+/// Upstream health checks run by the proxies (`[services.health]`)
+///
+/// Unlike the Docker `healthcheck`, which only restarts the container,
+/// these take an unhealthy upstream out of the proxy's rotation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UpstreamHealthConfig {
+    /// How the upstream is checked
+    #[serde(rename = "type", default)]
+    pub check_type: HealthCheckType,
+
+    /// Path requested on the upstream (`http` checks only)
+    #[serde(default = "default_health_path")]
+    pub path: String,
+
+    /// Time between two checks (e.g. "10s")
+    #[serde(default = "default_health_interval")]
+    pub interval: String,
+
+    /// Time a check may take before it counts as failed
+    #[serde(default = "default_health_timeout")]
+    pub timeout: String,
+
+    /// Status code a healthy upstream answers with
+    #[serde(default = "default_health_expected_status")]
+    pub expected_status: u16,
+
+    /// Consecutive failed checks before the upstream is taken out of rotation
+    #[serde(default = "default_health_unhealthy_threshold")]
+    pub unhealthy_threshold: u32,
+}
+
+impl Default for UpstreamHealthConfig {
+    fn default() -> Self {
+        Self {
+            check_type: HealthCheckType::default(),
+            path: default_health_path(),
+            interval: default_health_interval(),
+            timeout: default_health_timeout(),
+            expected_status: default_health_expected_status(),
+            unhealthy_threshold: default_health_unhealthy_threshold(),
+        }
+    }
+}
+
+impl UpstreamHealthConfig {
+    /// Whether the upstream is checked at all
+    pub fn is_enabled(&self) -> bool {
+        self.check_type != HealthCheckType::None
+    }
+}
+
+/// How an upstream is health checked (`services.health.type`)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum HealthCheckType {
+    /// Request `path` and expect `expected_status`
+    #[default]
+    Http,
+    /// Only open a TCP connection, for backends without a health endpoint
+    Tcp,
+    /// No checks: the upstream is always considered healthy
+    None,
+}
+
+fn default_health_path() -> String {
+    "/health".to_string()
+}
+
+fn default_health_interval() -> String {
+    "10s".to_string()
+}
+
+fn default_health_timeout() -> String {
+    "5s".to_string()
+}
+
+fn default_health_expected_status() -> u16 {
+    200
+}
+
+fn default_health_unhealthy_threshold() -> u32 {
+    3
+}
+
+/// How the proxies retry a request that failed on an upstream
+///
+/// ```toml
+/// [[services]]
+/// upstream = "http://app:3000"
+/// backup_upstreams = ["http://app-standby:3000"]
+///
+/// [services.failover]
+/// retries = 2
+/// timeout = "3s"
+/// next_upstream = ["error", "timeout", "http_503"]
+/// ```
+///
+/// A request is sent to the next upstream on one of the `next_upstream`
+/// failures; the backups only take requests once the primary is down.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FailoverConfig {
+    /// Further attempts after the first one failed
+    #[serde(default = "default_failover_retries")]
+    pub retries: u32,
+
+    /// Time to connect to an upstream before the next one is tried
+    #[serde(default = "default_failover_timeout")]
+    pub timeout: String,
+
+    /// Failures that send the request to the next upstream
+    #[serde(default = "default_failover_next_upstream")]
+    pub next_upstream: Vec<FailoverCondition>,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            retries: default_failover_retries(),
+            timeout: default_failover_timeout(),
+            next_upstream: default_failover_next_upstream(),
+        }
+    }
+}
+
+/// Failure that sends a request to the next upstream
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum FailoverCondition {
+    /// The connection failed or broke before a response arrived
+    #[serde(rename = "error")]
+    Error,
+    /// The upstream did not answer in time
+    #[serde(rename = "timeout")]
+    Timeout,
+    /// The upstream answered 500 Internal Server Error
+    #[serde(rename = "http_500")]
+    Http500,
+    /// The upstream answered 502 Bad Gateway
+    #[serde(rename = "http_502")]
+    Http502,
+    /// The upstream answered 503 Service Unavailable
+    #[serde(rename = "http_503")]
+    Http503,
+    /// The upstream answered 504 Gateway Timeout
+    #[serde(rename = "http_504")]
+    Http504,
+}
+
+impl FailoverCondition {
+    /// Name used in `next_upstream` and Nginx's `proxy_next_upstream`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FailoverCondition::Error => "error",
+            FailoverCondition::Timeout => "timeout",
+            FailoverCondition::Http500 => "http_500",
+            FailoverCondition::Http502 => "http_502",
+            FailoverCondition::Http503 => "http_503",
+            FailoverCondition::Http504 => "http_504",
+        }
+    }
+
+    /// Response status of the failure, if it is one
+    pub fn status(self) -> Option<u16> {
+        match self {
+            FailoverCondition::Error | FailoverCondition::Timeout => None,
+            FailoverCondition::Http500 => Some(500),
+            FailoverCondition::Http502 => Some(502),
+            FailoverCondition::Http503 => Some(503),
+            FailoverCondition::Http504 => Some(504),
+        }
+    }
+
+    /// HAProxy `retry-on` keywords of the failure
+    pub fn haproxy_retry_on(self) -> &'static str {
+        match self {
+            FailoverCondition::Error => "conn-failure empty-response",
+            FailoverCondition::Timeout => "response-timeout",
+            FailoverCondition::Http500 => "500",
+            FailoverCondition::Http502 => "502",
+            FailoverCondition::Http503 => "503",
+            FailoverCondition::Http504 => "504",
+        }
+    }
+}
+
+fn default_failover_retries() -> u32 {
+    1
+}
+
+fn default_failover_timeout() -> String {
+    "5s".to_string()
+}
+
+fn default_failover_next_upstream() -> Vec<FailoverCondition> {
+    vec![
+        FailoverCondition::Error,
+        FailoverCondition::Timeout,
+        FailoverCondition::Http502,
+        FailoverCondition::Http503,
+        FailoverCondition::Http504,
+    ]
+}
+
+/// Upstream timeouts and retry budget of a service or route
+///
+/// ```toml
+/// [services.timeouts]
+/// connect = "5s"
+/// read = "2m"
+/// send = "2m"
+/// idle = "90s"
+/// retries = 2
+/// ```
+///
+/// Unset fields keep the proxy defaults (30s for Nginx). `connect` and
+/// `retries` take precedence over `[services.failover]`. HAProxy and Traefik
+/// have no separate `send` timeout, and Nginx retries only across
+/// `backup_upstreams`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct TimeoutsConfig {
+    /// Time to establish a connection to the upstream
+    #[serde(default)]
+    pub connect: Option<String>,
+
+    /// Time to wait for the upstream's response
+    #[serde(default)]
+    pub read: Option<String>,
+
+    /// Time to wait while sending the request to the upstream
+    #[serde(default)]
+    pub send: Option<String>,
+
+    /// Time an idle keep-alive connection stays open
+    #[serde(default)]
+    pub idle: Option<String>,
+
+    /// Further attempts after the first one failed
+    #[serde(default)]
+    pub retries: Option<u32>,
+}
+
+impl TimeoutsConfig {
+    /// Whether no timeout or retry budget is set
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The set timeouts with their field names
+    pub fn durations(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("connect", &self.connect),
+            ("read", &self.read),
+            ("send", &self.send),
+            ("idle", &self.idle),
+        ]
+        .into_iter()
+        .filter_map(|(field, value)| value.as_deref().map(|value| (field, value)))
+        .collect()
+    }
+}
+
+/// Header changes the proxies apply to a service's traffic
+///
+/// ```toml
+/// [services.headers.request.set]
+/// X-Tenant = "acme"
+///
+/// [services.headers.response]
+/// remove = ["X-Powered-By"]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HeadersConfig {
+    /// Changes to requests forwarded to the upstream
+    #[serde(default)]
+    pub request: HeaderRules,
+
+    /// Changes to responses returned to the client
+    #[serde(default)]
+    pub response: HeaderRules,
+}
+
+impl HeadersConfig {
+    /// Whether no header is changed
+    pub fn is_empty(&self) -> bool {
+        self.request.is_empty() && self.response.is_empty()
+    }
+}
+
+/// Headers set, added, and removed in one direction
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HeaderRules {
+    /// Headers replaced with a value, or created
+    #[serde(default)]
+    pub set: BTreeMap<String, String>,
+
+    /// Values appended to a header, keeping the values already present
+    #[serde(default)]
+    pub add: BTreeMap<String, String>,
+
+    /// Headers dropped
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+impl HeaderRules {
+    /// Whether no header is changed
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.add.is_empty() && self.remove.is_empty()
+    }
+}
+
+/// Authentication in front of a service (`[services.auth]`)
+///
+/// ```toml
+/// [services.auth]
+/// type = "basic"
+/// users = ["alice:$2y$05$..."]
+/// ```
+///
+/// `basic` checks htpasswd users with bcrypt hashes at the proxies;
+/// `users_secret` instead names a `[secrets]` entry holding htpasswd lines,
+/// which only Nginx and Traefik read at runtime. `forward` asks the
+/// `[security.forward_auth]` provider about every request, which HAProxy
+/// cannot do.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ServiceAuthConfig {
+    /// How requests are authenticated
+    #[serde(rename = "type")]
+    pub auth_type: AuthType,
+
+    /// Users allowed in, htpasswd style with bcrypt hashes (basic only)
+    #[serde(default)]
+    pub users: Vec<String>,
+
+    /// Secret holding the htpasswd file, instead of `users` (basic only)
+    #[serde(default)]
+    pub users_secret: Option<String>,
+
+    /// Realm shown in the browser's login prompt (basic only)
+    #[serde(default = "default_auth_realm")]
+    pub realm: String,
+}
+
+/// Authentication method of a service
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum AuthType {
+    /// HTTP basic authentication against htpasswd users
+    Basic,
+    /// Checked by the `[security.forward_auth]` provider
+    Forward,
+}
+
+fn default_auth_realm() -> String {
+    "Restricted".to_string()
+}
+
+impl ServiceAuthConfig {
+    /// Users split into name and bcrypt hash
+    pub fn credentials(&self) -> Vec<(&str, &str)> {
+        self.users
+            .iter()
+            .filter_map(|user| user.split_once(':'))
+            .collect()
+    }
+}
+
+/// Response compression of a service (`[services.compression]`)
+///
+/// ```toml
+/// [services.compression]
+/// algorithms = ["zstd", "brotli", "gzip"]
+/// min_size = "1k"
+/// types = ["text/html", "text/css", "application/json"]
+///
+/// [services.compression.levels]
+/// brotli = 5
+/// ```
+///
+/// Nginx needs modules for brotli and zstd and Caddy one for brotli; their
+/// images are then built from the generated Dockerfile. HAProxy compresses
+/// with gzip only; Caddy applies the gzip level only, and Traefik none.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CompressionConfig {
+    /// Compress the service's responses
+    #[serde(default = "default_compression")]
+    pub enabled: bool,
+
+    /// Encodings offered to clients, preferred first
+    #[serde(default = "default_compression_algorithms")]
+    pub algorithms: Vec<CompressionAlgorithm>,
+
+    /// Compression level of each algorithm (unset = its default)
+    #[serde(default)]
+    pub levels: CompressionLevels,
+
+    /// Smallest response body that is compressed (e.g. "1k")
+    #[serde(default = "default_compression_min_size")]
+    pub min_size: String,
+
+    /// MIME types of the responses that are compressed
+    #[serde(default = "default_compression_types")]
+    pub types: Vec<String>,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_compression(),
+            algorithms: default_compression_algorithms(),
+            levels: CompressionLevels::default(),
+            min_size: default_compression_min_size(),
+            types: default_compression_types(),
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// Whether responses are compressed with an algorithm
+    pub fn uses(&self, algorithm: CompressionAlgorithm) -> bool {
+        self.enabled && self.algorithms.contains(&algorithm)
+    }
+
+    /// Level an algorithm compresses at
+    pub fn level(&self, algorithm: CompressionAlgorithm) -> u32 {
+        let level = match algorithm {
+            CompressionAlgorithm::Gzip => self.levels.gzip,
+            CompressionAlgorithm::Brotli => self.levels.brotli,
+            CompressionAlgorithm::Zstd => self.levels.zstd,
+        };
+        level.unwrap_or_else(|| algorithm.default_level())
+    }
+
+    /// Smallest compressed response body in bytes
+    pub fn min_size_bytes(&self) -> u64 {
+        parse_size_bytes(&self.min_size).unwrap_or(0)
+    }
+
+    /// Whether the settings differ from the defaults other than `enabled`
+    pub fn is_customized(&self) -> bool {
+        let defaults = Self {
+            enabled: self.enabled,
+            ..Self::default()
+        };
+        *self != defaults
+    }
+}
+
+/// Compression level of each algorithm (`[services.compression.levels]`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CompressionLevels {
+    /// gzip level, 1 to 9
+    #[serde(default)]
+    pub gzip: Option<u32>,
+
+    /// Brotli quality, 0 to 11
+    #[serde(default)]
+    pub brotli: Option<u32>,
+
+    /// zstd level, 1 to 22
+    #[serde(default)]
+    pub zstd: Option<u32>,
+}
+
+/// Content encoding a proxy compresses responses with
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionAlgorithm {
+    /// gzip, supported by every proxy
+    Gzip,
+    /// Brotli (`br`)
+    Brotli,
+    /// Zstandard
+    Zstd,
+}
+
+impl CompressionAlgorithm {
+    /// Name used in `algorithms`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CompressionAlgorithm::Gzip => "gzip",
+            CompressionAlgorithm::Brotli => "brotli",
+            CompressionAlgorithm::Zstd => "zstd",
+        }
+    }
+
+    /// Token of the encoding in `Accept-Encoding` and `Content-Encoding`
+    pub fn encoding(self) -> &'static str {
+        match self {
+            CompressionAlgorithm::Gzip => "gzip",
+            CompressionAlgorithm::Brotli => "br",
+            CompressionAlgorithm::Zstd => "zstd",
+        }
+    }
+
+    /// Levels the algorithm accepts
+    pub fn level_range(self) -> std::ops::RangeInclusive<u32> {
+        match self {
+            CompressionAlgorithm::Gzip => 1..=9,
+            CompressionAlgorithm::Brotli => 0..=11,
+            CompressionAlgorithm::Zstd => 1..=22,
+        }
+    }
+
+    /// Level used when `levels` leaves the algorithm unset
+    pub fn default_level(self) -> u32 {
+        match self {
+            CompressionAlgorithm::Gzip | CompressionAlgorithm::Brotli => 6,
+            CompressionAlgorithm::Zstd => 3,
+        }
+    }
+}
+
+fn default_compression_algorithms() -> Vec<CompressionAlgorithm> {
+    vec![CompressionAlgorithm::Gzip]
+}
+
+fn default_compression_min_size() -> String {
+    "1k".to_string()
+}
+
+fn default_compression_types() -> Vec<String> {
+    [
+        "text/html",
+        "text/plain",
+        "text/css",
+        "text/xml",
+        "text/javascript",
+        "application/javascript",
+        "application/json",
+        "application/xml",
+        "application/xml+rss",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+/// Edge caching policy for a service
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CacheConfig {
+    /// Enable caching at the edge
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// How long fresh responses are cached (e.g. "10m")
+    #[serde(default = "default_cache_ttl")]
+    pub ttl: String,
+
+    /// Serve stale content while revalidating in the background (e.g. "30s")
+    #[serde(default)]
+    pub stale_while_revalidate: Option<String>,
+
+    /// Serve stale content when the backend errors or is down (e.g. "1d")
+    #[serde(default)]
+    pub stale_if_error: Option<String>,
+
+    /// Path prefixes never cached by a Varnish layer (e.g. "/account")
+    #[serde(default)]
+    pub bypass_paths: Vec<String>,
+
+    /// Request methods whose responses are cached
+    #[serde(default = "default_cache_methods")]
+    pub methods: Vec<String>,
+
+    /// Cookies that make a request skip the cache (e.g. "session")
+    #[serde(default)]
+    pub bypass_cookies: Vec<String>,
+
+    /// Request parts responses are cached by: "host", "method", "uri" or
+    /// "header:<Name>" (empty = host and uri)
+    #[serde(default)]
+    pub cache_key: Vec<String>,
+
+    /// Disk space the nginx cache zone may use (e.g. "1g")
+    #[serde(default = "default_cache_max_size")]
+    pub max_size: String,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl: default_cache_ttl(),
+            stale_while_revalidate: None,
+            stale_if_error: None,
+            bypass_paths: Vec::new(),
+            methods: default_cache_methods(),
+            bypass_cookies: Vec::new(),
+            cache_key: Vec::new(),
+            max_size: default_cache_max_size(),
+        }
+    }
+}
+
+fn default_cache_ttl() -> String {
+    "10m".to_string()
+}
+
+fn default_cache_methods() -> Vec<String> {
+    vec!["GET".to_string(), "HEAD".to_string()]
+}
+
+fn default_cache_max_size() -> String {
+    "1g".to_string()
+}
+
+/// HTTP methods whose responses the proxies can cache
+pub const CACHE_METHODS: &[&str] = &["GET", "HEAD", "POST"];
+
+/// Request attribute that distinguishes cached responses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheKeyPart {
+    /// Requested host name
+    Host,
+    /// Request method
+    Method,
+    /// Path and query string
+    Uri,
+    /// Value of a request header
+    Header(String),
+}
+
+impl CacheKeyPart {
+    /// Parse a `cache_key` entry such as "uri" or "header:Accept-Language"
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "host" => Some(Self::Host),
+            "method" => Some(Self::Method),
+            "uri" => Some(Self::Uri),
+            _ => value
+                .strip_prefix("header:")
+                .filter(|name| {
+                    !name.is_empty()
+                        && name
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                })
+                .map(|name| Self::Header(name.to_string())),
+        }
+    }
+}
+
+impl CacheConfig {
+    /// Build the RFC 5861 `Cache-Control` extensions for downstream caches
+    pub fn stale_cache_control(&self) -> Option<String> {
+        let directives: Vec<String> = [
+            ("stale-while-revalidate", &self.stale_while_revalidate),
+            ("stale-if-error", &self.stale_if_error),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| {
+            value
+                .as_deref()
+                .and_then(parse_duration_secs)
+                .map(|secs| format!("{name}={secs}"))
+        })
+        .collect();
+
+        (!directives.is_empty()).then(|| directives.join(", "))
+    }
+
+    /// How long entries must be kept on disk so stale copies stay servable
+    pub fn retention(&self) -> String {
+        [
+            Some(&self.ttl),
+            self.stale_while_revalidate.as_ref(),
+            self.stale_if_error.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .max_by_key(|value| parse_duration_secs(value).unwrap_or(0))
+        .cloned()
+        .unwrap_or_else(default_cache_ttl)
+    }
+
+    /// Request parts responses are cached by, defaulting to host and uri
+    pub fn key_parts(&self) -> Vec<CacheKeyPart> {
+        if self.cache_key.is_empty() {
+            return vec![CacheKeyPart::Host, CacheKeyPart::Uri];
+        }
+        self.cache_key
+            .iter()
+            .filter_map(|part| CacheKeyPart::parse(part))
+            .collect()
+    }
+
+    /// Size of the nginx keys zone: a hundredth of `max_size` in whole
+    /// megabytes, at least 1m (one megabyte holds about 8000 keys)
+    pub fn keys_zone_size(&self) -> String {
+        let bytes = parse_size_bytes(&self.max_size).unwrap_or(1 << 30);
+        format!("{}m", ((bytes / 100) >> 20).max(1))
+    }
+}
+
+/// Time window during which a path is routed to its service
+///
+/// Outside the window, requests go to `fallback_upstream` or receive
+/// `fallback_status`. Times are evaluated in the proxy container's local time.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ScheduleConfig {
+    /// Path prefix the schedule applies to ("/" = whole service)
+    #[serde(default = "default_schedule_path")]
+    pub path: String,
+
+    /// Daily window, e.g. "09:00-18:00" (may wrap past midnight, e.g. "22:00-06:00")
+    #[serde(default)]
+    pub active_hours: Option<String>,
+
+    /// Days of the week the window applies to, e.g. ["mon", "tue"] (empty = every day)
+    #[serde(default)]
+    pub active_days: Vec<String>,
+
+    /// First active date, inclusive (YYYY-MM-DD)
+    #[serde(default)]
+    pub active_from: Option<String>,
+
+    /// Last active date, inclusive (YYYY-MM-DD)
+    #[serde(default)]
+    pub active_until: Option<String>,
+
+    /// Upstream serving requests outside the window (e.g. a maintenance page)
+    #[serde(default)]
+    pub fallback_upstream: Option<String>,
+
+    /// Status returned outside the window when no fallback upstream is set
+    #[serde(default = "default_schedule_fallback_status")]
+    pub fallback_status: u16,
+}
+
+fn default_schedule_path() -> String {
+    "/".to_string()
+}
+
+fn default_schedule_fallback_status() -> u16 {
+    503
+}
+
+/// Day names accepted in `active_days`, in ISO order (Monday = 1)
+pub const WEEKDAYS: &[&str] = &["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+impl ScheduleConfig {
+    /// Daily window as `(start, end)` minutes since midnight, end exclusive
+    pub fn hours(&self) -> Option<(u16, u16)> {
+        let (start, end) = self.active_hours.as_deref()?.split_once('-')?;
+        Some((parse_clock(start)?, parse_clock(end)?))
+    }
+
+    /// Daily window as inclusive `HHMM` ranges (split in two when it wraps past midnight)
+    pub fn clock_ranges(&self) -> Vec<(u16, u16)> {
+        let Some((start, end)) = self.hours() else {
+            return Vec::new();
+        };
+        let hhmm = |minutes: u16| minutes / 60 * 100 + minutes % 60;
+        let last = (end + 24 * 60 - 1) % (24 * 60);
+
+        if start <= last {
+            vec![(hhmm(start), hhmm(last))]
+        } else {
+            vec![(hhmm(start), 2359), (0, hhmm(last))]
+        }
+    }
+
+    /// ISO weekday numbers (Monday = 1) of `active_days`
+    pub fn day_numbers(&self) -> Vec<u8> {
+        self.active_days
+            .iter()
+            .filter_map(|day| {
+                WEEKDAYS
+                    .iter()
+                    .position(|name| day.eq_ignore_ascii_case(name))
+            })
+            .map(|index| index as u8 + 1)
+            .collect()
+    }
+
+    /// Date range as `YYYYMMDD` numbers, inclusive
+    pub fn date_range(&self) -> (Option<u32>, Option<u32>) {
+        (
+            self.active_from.as_deref().and_then(parse_date),
+            self.active_until.as_deref().and_then(parse_date),
+        )
+    }
+}
+
+/// Parse "HH:MM" into minutes since midnight ("24:00" is end of day)
+fn parse_clock(value: &str) -> Option<u16> {
+    let (hours, minutes) = value.trim().split_once(':')?;
+    let hours: u16 = hours.parse().ok()?;
+    let minutes: u16 = minutes.parse().ok()?;
+    let total = hours * 60 + minutes;
+    (minutes < 60 && total <= 24 * 60).then_some(total)
+}
+
+/// Parse "YYYY-MM-DD" into a `YYYYMMDD` number
+fn parse_date(value: &str) -> Option<u32> {
+    let mut parts = value.trim().splitn(3, '-');
+    let year: u32 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    ((1..=12).contains(&month) && (1..=31).contains(&day))
+        .then_some(year * 10000 + month * 100 + day)
+}
+
+/// Validate a service's detailed `depends_on` conditions
+fn validate_depends_on(service: &ServiceConfig, services: &[ServiceConfig]) -> Result<()> {
+    let Some(DependsOn::Detailed(conditions)) = &service.depends_on else {
+        return Ok(());
+    };
+
+    let mut names: Vec<&String> = conditions.keys().collect();
+    names.sort();
+    for name in names {
+        let condition = &conditions[name].condition;
+        if !DEPENDENCY_CONDITIONS.contains(&condition.as_str()) {
+            return Err(CerberusError::validation(format!(
+                "Service {} depends_on.{name} has unknown condition '{condition}' (expected one of: {})",
+                service.name,
+                DEPENDENCY_CONDITIONS.join(", ")
+            )));
+        }
+
+        if condition == "service_healthy" {
+            match services.iter().find(|other| &other.name == name) {
+                Some(other) if other.has_healthcheck() => {}
+                Some(_) => {
+                    return Err(CerberusError::validation(format!(
+                        "Service {} waits for {name} to be healthy, but {name} defines no healthcheck",
+                        service.name
+                    )));
+                }
+                None => {
+                    return Err(CerberusError::validation(format!(
+                        "Service {} waits for {name} to be healthy, but no service named {name} is defined",
+                        service.name
+                    )));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate the settings of a single proxy
+fn validate_proxy(proxy: &ProxyConfig) -> Result<()> {
+    if let Some(port) = proxy.external_port
+        && port == 0
+    {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} external_port must be greater than 0",
+            proxy.name
+        )));
+    }
+
+    for (field, value) in [
+        ("entrypoint", &proxy.entrypoint),
+        ("command", &proxy.command),
+    ] {
+        if let Some(value) = value
+            && value.args().iter().all(|arg| arg.trim().is_empty())
+        {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} {field} cannot be empty",
+                proxy.name
+            )));
+        }
+    }
+
+    let config_dir = proxy.proxy_type.config_dir();
+    if proxy.proxy_type.needs_config_flag()
+        && (proxy.entrypoint.is_some() || proxy.command.is_some())
+        && ![&proxy.entrypoint, &proxy.command]
+            .into_iter()
+            .flatten()
+            .any(|value| value.mentions(config_dir))
+    {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} overrides the {} entrypoint/command without the config path; \
+             pass a file under {config_dir} (the image's default command is replaced)",
+            proxy.name, proxy.proxy_type
+        )));
+    }
+
+    if let Some(stats) = &proxy.stats {
+        validate_stats(proxy, stats)?;
+    }
+
+    if let Some(varnish) = &proxy.varnish {
+        validate_varnish(proxy, varnish)?;
+    }
+
+    if let Some(drain) = &proxy.drain {
+        validate_drain(proxy, drain)?;
+    }
+
+    if let Some(tuning) = &proxy.tuning {
+        validate_tuning(proxy, tuning)?;
+    }
+
+    if let Some(snippets) = &proxy.snippets {
+        validate_snippets(&format!("Proxy {}", proxy.name), snippets)?;
+        let applicable = snippet_scopes(&proxy.proxy_type, false);
+        if let Some((scope, _)) = snippets
+            .set_scopes()
+            .into_iter()
+            .find(|(scope, _)| !applicable.contains(scope))
+        {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} snippets.{scope} is not supported by {} proxies",
+                proxy.name, proxy.proxy_type
+            )));
+        }
+    }
+
+    validate_container_options(
+        &format!("Proxy {}", proxy.name),
+        proxy.platform.as_deref(),
+        &proxy.platforms,
+        proxy.stop_grace_period.as_deref(),
+    )?;
+
+    if proxy.proxy_type == ProxyType::Varnish && proxy.layer.unwrap_or(1) < 2 {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} is a varnish cache and must sit behind another proxy (layer 2 or higher)",
+            proxy.name
+        )));
+    }
+
+    if proxy.instances == 0 {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} instances must be greater than 0",
+            proxy.name
+        )));
+    }
+
+    if proxy.deploy_replicas() == Some(0) {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} deploy.replicas must be greater than 0",
+            proxy.name
+        )));
+    }
+
+    if let Some(upstream) = &proxy.default_upstream {
+        validate_upstream_url(&format!("Proxy {}", proxy.name), upstream)?;
+    }
+
+    for route in &proxy.routes {
+        if let Some(difficulty) = route.difficulty {
+            if route.route_type == RouteType::Direct {
+                return Err(CerberusError::validation(format!(
+                    "Route {} on proxy {} is direct and bypasses Anubis; difficulty cannot be set",
+                    route.domain, proxy.name
+                )));
+            }
+
+            if !(1..=10).contains(&difficulty) {
+                return Err(CerberusError::validation(format!(
+                    "Route {} on proxy {} difficulty must be between 1 and 10",
+                    route.domain, proxy.name
+                )));
+            }
+        }
+
+        validate_methods(&route.allowed_methods, &format!("Route {}", route.domain))?;
+        if !route.protocol.is_stream() {
+            validate_upstream_url(
+                &format!("Route {} on proxy {}", route.domain, proxy.name),
+                &route.upstream,
+            )?;
+        }
+        validate_route_paths(proxy, route)?;
+        validate_timeouts(
+            &format!("Route {} on proxy {}", route.domain, proxy.name),
+            &route.timeouts,
+        )?;
+        validate_stream(
+            &format!("Route {} on proxy {}", route.domain, proxy.name),
+            route.protocol,
+            route.listen_port,
+            &route.upstream,
+            &[
+                ("bypass_paths", !route.bypass_paths.is_empty()),
+                ("difficulty", route.difficulty.is_some()),
+                ("allowed_methods", !route.allowed_methods.is_empty()),
+                ("paths", !route.paths.is_empty()),
+                ("timeouts", !route.timeouts.is_empty()),
+            ],
+        )?;
+        if !route.timeouts.is_empty() && route.paths.is_empty() {
+            return Err(CerberusError::validation(format!(
+                "Route {} on proxy {} sets timeouts, which apply to its paths; set them on the service instead",
+                route.domain, proxy.name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate the `[services.compression]` settings of a service
+fn validate_compression(service: &ServiceConfig) -> Result<()> {
+    let compression = &service.compression;
+    if !compression.enabled {
+        return Ok(());
+    }
+
+    if compression.algorithms.is_empty() {
+        return Err(CerberusError::validation(format!(
+            "Service {} compression.algorithms cannot be empty; set enabled = false instead",
+            service.name
+        )));
+    }
+    for (index, algorithm) in compression.algorithms.iter().enumerate() {
+        if compression.algorithms[..index].contains(algorithm) {
+            return Err(CerberusError::validation(format!(
+                "Service {} compression.algorithms lists '{}' twice",
+                service.name,
+                algorithm.as_str()
+            )));
+        }
+    }
+
+    for (algorithm, level) in [
+        (CompressionAlgorithm::Gzip, compression.levels.gzip),
+        (CompressionAlgorithm::Brotli, compression.levels.brotli),
+        (CompressionAlgorithm::Zstd, compression.levels.zstd),
+    ] {
+        let Some(level) = level else {
+            continue;
+        };
+        let range = algorithm.level_range();
+        if !range.contains(&level) {
+            return Err(CerberusError::validation(format!(
+                "Service {} compression.levels.{} must be between {} and {}, got {level}",
+                service.name,
+                algorithm.as_str(),
+                range.start(),
+                range.end()
+            )));
+        }
+    }
+
+    if parse_size_bytes(&compression.min_size).is_none() {
+        return Err(CerberusError::validation(format!(
+            "Service {} compression.min_size must be a size such as 256, 1k or 4k",
+            service.name
+        )));
+    }
+
+    if compression.types.is_empty() {
+        return Err(CerberusError::validation(format!(
+            "Service {} compression.types cannot be empty",
+            service.name
+        )));
+    }
+    for mime in &compression.types {
+        let valid = mime.split_once('/').is_some_and(|(kind, subtype)| {
+            [kind, subtype].iter().all(|part| {
+                !part.is_empty()
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+            })
+        });
+        if !valid {
+            return Err(CerberusError::validation(format!(
+                "Service {} compression.types entry '{mime}' is not a MIME type such as text/css",
+                service.name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate the settings of a single service
+fn validate_service(service: &ServiceConfig) -> Result<()> {
+    if service.hosts().is_empty() {
+        return Err(CerberusError::validation(format!(
+            "Service {} domain cannot be empty; set domain or domains",
+            service.name
+        )));
+    }
+
+    for host in service.hosts() {
+        if !is_valid_host(host) {
+            return Err(CerberusError::validation(format!(
+                "Service {} domain '{host}' is not a host name; \
+                 a wildcard may only replace the first label (*.example.com)",
+                service.name
+            )));
+        }
+    }
+
+    if service.upstream.trim().is_empty() {
+        return Err(CerberusError::validation(format!(
+            "Service {} upstream cannot be empty",
+            service.name
+        )));
+    }
+    if !service.protocol.is_stream() {
+        for upstream in std::iter::once(&service.upstream).chain(&service.candidate_upstream) {
+            validate_upstream_url(&format!("Service {}", service.name), upstream)?;
+        }
+    }
+
+    validate_methods(
+        &service.allowed_methods,
+        &format!("Service {}", service.name),
+    )?;
+
+    if parse_size_bytes(&service.max_body_size).is_none() {
+        return Err(CerberusError::validation(format!(
+            "Service {} max_body_size must be a size such as 512k, 10m or 1g",
+            service.name
+        )));
+    }
+
+    validate_compression(service)?;
+
+    validate_schedules(service)?;
+
+    validate_container_options(
+        &format!("Service {}", service.name),
+        service.platform.as_deref(),
+        &service.platforms,
+        service.stop_grace_period.as_deref(),
+    )?;
+    if service.pull_policy == Some(PullPolicy::Build) && service.build.is_none() {
+        return Err(CerberusError::validation(format!(
+            "Service {} pull_policy = \"build\" requires a [services.build] section",
+            service.name
+        )));
+    }
+
+    validate_stream(
+        &format!("Service {}", service.name),
+        service.protocol,
+        service.listen_port,
+        &service.upstream,
+        &[
+            ("allowed_methods", !service.allowed_methods.is_empty()),
+            ("cache", service.cache.is_some()),
+            ("health", service.health.is_some()),
+            ("headers", !service.headers.is_empty()),
+            ("schedules", !service.schedules.is_empty()),
+            ("candidate_upstream", service.candidate_upstream.is_some()),
+            ("auth", service.auth.is_some()),
+            ("backup_upstreams", !service.backup_upstreams.is_empty()),
+            ("failover", service.failover.is_some()),
+            ("timeouts", !service.timeouts.is_empty()),
+        ],
+    )?;
+
+    validate_failover(service)?;
+    validate_timeouts(&format!("Service {}", service.name), &service.timeouts)?;
+
+    service.vault_environment()?;
+
+    if let Some(observability) = &service.observability {
+        if observability
+            .slo_target
+            .is_some_and(|target| !(target > 0.0 && target <= 100.0))
+        {
+            return Err(CerberusError::validation(format!(
+                "Service {} observability.slo_target must be a percentage above 0 and at most 100",
+                service.name
+            )));
+        }
+        for (name, value) in observability.labels() {
+            if value.trim().is_empty() || value.contains(['"', '\n']) {
+                return Err(CerberusError::validation(format!(
+                    "Service {} observability.{name} is not a valid label value",
+                    service.name
+                )));
+            }
+        }
+    }
+
+    if service.waf.exclude_rules.contains(&0) {
+        return Err(CerberusError::validation(format!(
+            "Service {} waf.exclude_rules must contain CRS rule IDs",
+            service.name
+        )));
+    }
+
+    if service.replicas == Some(0) {
+        return Err(CerberusError::validation(format!(
+            "Service {} replicas must be greater than 0",
+            service.name
+        )));
+    }
+
+    if let Some(build) = &service.build
+        && build.context.trim().is_empty()
+    {
+        return Err(CerberusError::validation(format!(
+            "Service {} build context cannot be empty",
+            service.name
+        )));
+    }
+
+    if let Some(health) = &service.health {
+        validate_upstream_health(service, health)?;
+    }
+
+    for (direction, rules) in [
+        ("request", &service.headers.request),
+        ("response", &service.headers.response),
+    ] {
+        validate_header_rules(service, direction, rules)?;
+    }
+
+    if let Some(cache) = &service.cache {
+        let durations = [
+            ("ttl", Some(&cache.ttl)),
+            (
+                "stale_while_revalidate",
+                cache.stale_while_revalidate.as_ref(),
+            ),
+            ("stale_if_error", cache.stale_if_error.as_ref()),
+        ];
+        for (field, value) in durations {
+            if let Some(value) = value
+                && parse_duration_secs(value).is_none()
+            {
+                return Err(CerberusError::validation(format!(
+                    "Service {} cache {field} '{value}' is not a valid duration",
+                    service.name
+                )));
+            }
+        }
+
+        if let Some(path) = cache.bypass_paths.iter().find(|path| {
+            !path.starts_with('/') || path.contains(|c: char| c == '"' || c.is_whitespace())
+        }) {
+            return Err(CerberusError::validation(format!(
+                "Service {} cache bypass path '{path}' must start with / and contain no spaces or quotes",
+                service.name
+            )));
+        }
+
+        if cache.methods.is_empty() {
+            return Err(CerberusError::validation(format!(
+                "Service {} cache methods must list at least one method",
+                service.name
+            )));
+        }
+        if let Some(method) = cache
+            .methods
+            .iter()
+            .find(|method| !CACHE_METHODS.contains(&method.as_str()))
+        {
+            return Err(CerberusError::validation(format!(
+                "Service {} cache methods contains '{method}' (expected one of {})",
+                service.name,
+                CACHE_METHODS.join(", ")
+            )));
+        }
+
+        // nginx exposes cookies as $cookie_<name> variables
+        if let Some(cookie) = cache.bypass_cookies.iter().find(|cookie| {
+            cookie.is_empty()
+                || !cookie
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }) {
+            return Err(CerberusError::validation(format!(
+                "Service {} cache bypass cookie '{cookie}' may only contain letters, digits and _",
+                service.name
+            )));
+        }
+
+        if let Some(part) = cache
+            .cache_key
+            .iter()
+            .find(|part| CacheKeyPart::parse(part).is_none())
+        {
+            return Err(CerberusError::validation(format!(
+                "Service {} cache_key part '{part}' must be host, method, uri or header:<Name>",
+                service.name
+            )));
+        }
+
+        if parse_size_bytes(&cache.max_size).is_none_or(|bytes| bytes == 0) {
+            return Err(CerberusError::validation(format!(
+                "Service {} cache max_size '{}' is not a valid size",
+                service.name, cache.max_size
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate the header changes of a service in one direction
+fn validate_header_rules(
+    service: &ServiceConfig,
+    direction: &str,
+    rules: &HeaderRules,
+) -> Result<()> {
+    let owner = format!("Service {} headers.{direction}", service.name);
+    let names = rules
+        .set
+        .keys()
+        .chain(rules.add.keys())
+        .chain(&rules.remove);
+    for name in names {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(CerberusError::validation(format!(
+                "{owner} contains invalid header name '{name}'"
+            )));
+        }
+        if direction == "request" && name.eq_ignore_ascii_case("host") {
+            return Err(CerberusError::validation(format!(
+                "{owner} cannot change the Host header; the proxies route on it"
+            )));
+        }
+    }
+
+    for (name, value) in rules.set.iter().chain(&rules.add) {
+        if value.chars().any(char::is_control) || value.contains("\"}") {
+            return Err(CerberusError::validation(format!(
+                "{owner} value of {name} contains a line break, control character or \"}}"
+            )));
+        }
+    }
+
+    // Each header gets one kind of change, so proxies never depend on their order
+    let mut seen = std::collections::BTreeMap::new();
+    for (kind, name) in rules
+        .set
+        .keys()
+        .map(|name| ("set", name))
+        .chain(rules.add.keys().map(|name| ("add", name)))
+        .chain(rules.remove.iter().map(|name| ("remove", name)))
+    {
+        if let Some(first) = seen.insert(name.to_ascii_lowercase(), kind) {
+            return Err(CerberusError::validation(format!(
+                "{owner} lists {name} in both {first} and {kind}"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate the upstream health checks of a service
+fn validate_upstream_health(service: &ServiceConfig, health: &UpstreamHealthConfig) -> Result<()> {
+    if !health.path.starts_with('/')
+        || health
+            .path
+            .contains(|c: char| c == '"' || c.is_whitespace())
+    {
+        return Err(CerberusError::validation(format!(
+            "Service {} health path '{}' must start with / and contain no spaces or quotes",
+            service.name, health.path
+        )));
+    }
+
+    let mut durations = Vec::new();
+    for (field, value) in [("interval", &health.interval), ("timeout", &health.timeout)] {
+        match parse_duration_secs(value) {
+            Some(secs) if secs > 0 => durations.push(secs),
+            _ => {
+                return Err(CerberusError::validation(format!(
+                    "Service {} health {field} '{value}' is not a valid duration",
+                    service.name
+                )));
+            }
+        }
+    }
+    if durations[1] > durations[0] {
+        return Err(CerberusError::validation(format!(
+            "Service {} health timeout must not be longer than the interval",
+            service.name
+        )));
+    }
+
+    if !(100..=599).contains(&health.expected_status) {
+        return Err(CerberusError::validation(format!(
+            "Service {} health expected_status {} is not an HTTP status code",
+            service.name, health.expected_status
+        )));
+    }
+
+    if health.unhealthy_threshold == 0 {
+        return Err(CerberusError::validation(format!(
+            "Service {} health unhealthy_threshold must be greater than 0",
+            service.name
+        )));
+    }
+
+    Ok(())
+}
+
+/// Validate the backup upstreams and failover policy of a service
+///
+/// The proxies send a request to whichever upstream is next under one
+/// scheme and path, so backups are `scheme://host[:port]` addresses using
+/// the scheme of `upstream`.
+fn validate_failover(service: &ServiceConfig) -> Result<()> {
+    let scheme = |upstream: &str| {
+        upstream
+            .split_once("://")
+            .map_or("http", |(scheme, _)| scheme)
+            .to_string()
+    };
+    for (index, backup) in service.backup_upstreams.iter().enumerate() {
+        let address = backup
+            .split_once("://")
+            .map_or(backup.as_str(), |(_, rest)| rest);
+        if address.is_empty()
+            || address.trim_end_matches('/').contains('/')
+            || backup.contains(|c: char| c.is_whitespace() || c == '"')
+        {
+            return Err(CerberusError::validation(format!(
+                "Service {} backup upstream '{backup}' must be an address such as http://standby:3000",
+                service.name
+            )));
+        }
+        validate_upstream_url(&format!("Service {} backup", service.name), backup)?;
+        if scheme(backup) != scheme(&service.upstream) {
+            return Err(CerberusError::validation(format!(
+                "Service {} backup upstream '{backup}' must use the {} scheme of its upstream",
+                service.name,
+                scheme(&service.upstream)
+            )));
+        }
+        if backup.trim_end_matches('/') == service.upstream.trim_end_matches('/')
+            || service.backup_upstreams[..index].contains(backup)
+        {
+            return Err(CerberusError::validation(format!(
+                "Service {} lists upstream '{backup}' more than once",
+                service.name
+            )));
+        }
+    }
+
+    if !service.backup_upstreams.is_empty() && service.candidate_upstream.is_some() {
+        return Err(CerberusError::validation(format!(
+            "Service {} cannot combine backup_upstreams with candidate_upstream",
+            service.name
+        )));
+    }
+
+    if let Some(failover) = &service.failover {
+        if parse_duration_secs(&failover.timeout).is_none_or(|secs| secs == 0) {
+            return Err(CerberusError::validation(format!(
+                "Service {} failover timeout '{}' is not a valid duration",
+                service.name, failover.timeout
+            )));
+        }
+        if failover.next_upstream.is_empty() {
+            return Err(CerberusError::validation(format!(
+                "Service {} failover next_upstream must list at least one condition",
+                service.name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate the `[[upstreams]]` pools and the services that use them
+fn validate_upstream_pools(config: &Config) -> Result<()> {
+    let mut names = std::collections::HashSet::new();
+    for pool in &config.upstreams {
+        let name = &pool.name;
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            return Err(CerberusError::validation(format!(
+                "Upstream pool name '{name}' must contain only lowercase letters, digits, '-' and '_'"
+            )));
+        }
+        // A bare upstream host would otherwise be ambiguous
+        if config.proxies.iter().any(|proxy| &proxy.name == name)
+            || config.services.iter().any(|service| &service.name == name)
+        {
+            return Err(CerberusError::validation(format!(
+                "Upstream pool name '{name}' is already used by a proxy or service"
+            )));
+        }
+        if !names.insert(name.as_str()) {
+            return Err(CerberusError::validation(format!(
+                "Upstream pool {name} is defined more than once"
+            )));
+        }
+
+        if pool.servers.is_empty() {
+            return Err(CerberusError::validation(format!(
+                "Upstream pool {name} needs at least one server"
+            )));
+        }
+        for (index, server) in pool.servers.iter().enumerate() {
+            let url = &server.url;
+            let address = match url.split_once("://") {
+                Some(("http" | "https", address)) => address.trim_end_matches('/'),
+                _ => "",
+            };
+            if address.is_empty()
+                || address.contains('/')
+                || url.contains(|c: char| c.is_whitespace() || c == '"')
+            {
+                return Err(CerberusError::validation(format!(
+                    "Upstream pool {name} server '{url}' must be an address such as http://app-1:3000"
+                )));
+            }
+            validate_upstream_url(&format!("Upstream pool {name}"), url)?;
+            if !url.starts_with(&format!("{}://", pool.scheme())) {
+                return Err(CerberusError::validation(format!(
+                    "Upstream pool {name} server '{url}' must use the {} scheme of the first server",
+                    pool.scheme()
+                )));
+            }
+            if pool.servers[..index]
+                .iter()
+                .any(|other| other.url.trim_end_matches('/') == url.trim_end_matches('/'))
+            {
+                return Err(CerberusError::validation(format!(
+                    "Upstream pool {name} lists server '{url}' more than once"
+                )));
+            }
+            if !(1..=256).contains(&server.weight) {
+                return Err(CerberusError::validation(format!(
+                    "Upstream pool {name} server '{url}' weight must be between 1 and 256"
+                )));
+            }
+        }
+        if pool.keepalive == Some(0) {
+            return Err(CerberusError::validation(format!(
+                "Upstream pool {name} keepalive must be greater than 0"
+            )));
+        }
+    }
+
+    // The pool's servers replace the settings that add servers per service
+    for service in &config.services {
+        let Some(pool) = config.upstream_pool(&service.upstream) else {
+            continue;
+        };
+        for (setting, set) in [
+            ("backup_upstreams", !service.backup_upstreams.is_empty()),
+            ("failover", service.failover.is_some()),
+            ("candidate_upstream", service.candidate_upstream.is_some()),
+        ] {
+            if set {
+                return Err(CerberusError::validation(format!(
+                    "Service {} uses upstream pool {}, which cannot be combined with {setting}",
+                    service.name, pool.name
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Validate the `timeouts` of a service or route
+fn validate_timeouts(owner: &str, timeouts: &TimeoutsConfig) -> Result<()> {
+    for (field, value) in timeouts.durations() {
+        if parse_duration_secs(value).is_none_or(|secs| secs == 0) {
+            return Err(CerberusError::validation(format!(
+                "{owner} timeouts.{field} '{value}' is not a valid duration"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Validate the graceful shutdown settings of a proxy
+fn validate_drain(proxy: &ProxyConfig, drain: &DrainConfig) -> Result<()> {
+    let Some(timeout) = parse_duration_secs(&drain.timeout) else {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} drain timeout '{}' is not a valid duration",
+            proxy.name, drain.timeout
+        )));
+    };
+
+    if drain.stop_grace_period.is_some() && proxy.stop_grace_period.is_some() {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} sets both stop_grace_period and drain.stop_grace_period; use only one",
+            proxy.name
+        )));
+    }
+
+    if let Some(period) = drain
+        .stop_grace_period
+        .as_ref()
+        .or(proxy.stop_grace_period.as_ref())
+    {
+        match parse_duration_secs(period) {
+            None => {
+                return Err(CerberusError::validation(format!(
+                    "Proxy {} stop_grace_period '{period}' is not a valid duration",
+                    proxy.name
+                )));
+            }
+            Some(period) if period <= timeout => {
+                return Err(CerberusError::validation(format!(
+                    "Proxy {} stop_grace_period must be longer than the drain timeout, \
+                     or the container is killed while requests are still draining",
+                    proxy.name
+                )));
+            }
+            Some(_) => {}
+        }
+    }
+
+    if proxy.uses_drain_entrypoint() && proxy.entrypoint.is_some() {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} sets an entrypoint, which replaces the draining entrypoint; \
+             set drain.entrypoint = false to keep it",
+            proxy.name
+        )));
+    }
+
+    Ok(())
+}
+
+/// Reject options `docker stack deploy` cannot honour
+fn validate_swarm(config: &Config) -> Result<()> {
+    let image_options = |owner: String, options: ContainerOptions| {
+        if options.pull_policy.is_some() {
+            return Err(CerberusError::validation(format!(
+                "{owner} sets pull_policy, which Swarm does not support; \
+                 docker stack deploy resolves images itself"
+            )));
+        }
+        if options.platform.is_some() || !options.platforms.is_empty() {
+            return Err(CerberusError::validation(format!(
+                "{owner} sets platform, which Swarm does not support; \
+                 use placement constraints on the nodes instead"
+            )));
+        }
+        Ok(())
+    };
+
+    for proxy in &config.proxies {
+        image_options(
+            format!("Proxy {}", proxy.name),
+            proxy.container_options(&config.defaults),
+        )?;
+        if config.builds_proxy_image(proxy) {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} builds its image for the WAF, cache, compression or ACME DNS \
+                 modules, which docker stack deploy cannot do; disable [waf], Caddy caching, \
+                 brotli/zstd compression and Caddy [tls.acme] for the swarm target",
+                proxy.name
+            )));
+        }
+    }
+
+    for service in &config.services {
+        let owner = format!("Service {}", service.name);
+        image_options(owner.clone(), service.container_options(&config.defaults))?;
+        if service.build.is_some() {
+            return Err(CerberusError::validation(format!(
+                "{owner} has a [services.build] section, which docker stack deploy cannot build; \
+                 push the image and set image instead"
+            )));
+        }
+        if matches!(&service.depends_on, Some(DependsOn::Detailed(conditions)) if !conditions.is_empty())
+        {
+            return Err(CerberusError::validation(format!(
+                "{owner} sets depends_on conditions, which Swarm does not support; \
+                 list the dependencies without conditions"
+            )));
+        }
+        if service
+            .environment
+            .values()
+            .any(|value| value.starts_with(VAULT_SCHEME))
+        {
+            return Err(CerberusError::validation(format!(
+                "{owner} reads {VAULT_SCHEME} environment values, which a Swarm stack cannot \
+                 render; use a [secrets] entry instead"
+            )));
+        }
+    }
+
+    for (name, network) in &config.networks {
+        if !network.external && !matches!(network.driver.as_str(), "bridge" | "overlay") {
+            return Err(CerberusError::validation(format!(
+                "Network {name} uses the {} driver; Swarm stacks need overlay networks",
+                network.driver
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate that every snippet scope holds directives
+fn validate_snippets(owner: &str, snippets: &SnippetsConfig) -> Result<()> {
+    if let Some((scope, _)) = snippets
+        .set_scopes()
+        .into_iter()
+        .find(|(_, directives)| directives.trim().is_empty())
+    {
+        return Err(CerberusError::validation(format!(
+            "{owner} snippets.{scope} cannot be empty"
+        )));
+    }
+    Ok(())
+}
+
+/// Validate the snippets of a service against the proxies routing it
+fn validate_service_snippets(config: &Config, service: &ServiceConfig) -> Result<()> {
+    let Some(snippets) = &service.snippets else {
+        return Ok(());
+    };
+    let owner = format!("Service {}", service.name);
+    validate_snippets(&owner, snippets)?;
+    if service.protocol.is_stream() {
+        return Err(CerberusError::validation(format!(
+            "{owner} sets snippets, but {} services are forwarded without HTTP routing",
+            service.protocol.as_str()
+        )));
+    }
+    for proxy in config
+        .proxies
+        .iter()
+        .filter(|proxy| proxy.renders_services())
+    {
+        let applicable = snippet_scopes(&proxy.proxy_type, true);
+        if let Some((scope, _)) = snippets
+            .set_scopes()
+            .into_iter()
+            .find(|(scope, _)| !applicable.contains(scope))
+        {
+            return Err(CerberusError::validation(format!(
+                "{owner} snippets.{scope} is not supported by {} proxy {}, which routes it",
+                proxy.proxy_type, proxy.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Validate the tuning knobs of a proxy against its type
+fn validate_tuning(proxy: &ProxyConfig, tuning: &TuningConfig) -> Result<()> {
+    let applicable = tuning_keys(&proxy.proxy_type);
+    if let Some(key) = tuning
+        .set_keys()
+        .into_iter()
+        .find(|key| !applicable.contains(key))
+    {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} tuning.{key} does not apply to {} proxies",
+            proxy.name, proxy.proxy_type
+        )));
+    }
+
+    match &tuning.worker_processes {
+        Some(WorkerProcesses::Count(0)) => {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} tuning.worker_processes must be greater than 0",
+                proxy.name
+            )));
+        }
+        Some(WorkerProcesses::Keyword(keyword)) if keyword != "auto" => {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} tuning.worker_processes '{keyword}' must be \"auto\" or a number",
+                proxy.name
+            )));
+        }
+        _ => {}
+    }
+
+    for (key, value) in [
+        ("worker_connections", tuning.worker_connections),
+        ("maxconn", tuning.maxconn),
+    ] {
+        if value == Some(0) {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} tuning.{key} must be greater than 0",
+                proxy.name
+            )));
+        }
+    }
+
+    for (key, value) in [
+        ("keepalive_timeout", &tuning.keepalive_timeout),
+        ("timeout_connect", &tuning.timeout_connect),
+        ("timeout_client", &tuning.timeout_client),
+        ("timeout_server", &tuning.timeout_server),
+    ] {
+        if let Some(value) = value
+            && parse_duration_secs(value).is_none_or(|secs| secs == 0)
+        {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} tuning.{key} '{value}' must be a duration such as 30s or 2m",
+                proxy.name
+            )));
+        }
+    }
+
+    if let Some(size) = &tuning.client_max_body_size {
+        if proxy.layer.unwrap_or(1) > 1 {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} tuning.client_max_body_size only applies to layer 1; \
+                 set max_body_size on its services instead",
+                proxy.name
+            )));
+        }
+
+        if parse_size_bytes(size).is_none() {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} tuning.client_max_body_size '{size}' must be a size such as 512k, 10m or 1g",
+                proxy.name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate a Varnish cache layer
+fn validate_varnish(proxy: &ProxyConfig, varnish: &VarnishConfig) -> Result<()> {
+    if proxy.proxy_type != ProxyType::Varnish {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} varnish settings are only supported for varnish",
+            proxy.name
+        )));
+    }
+
+    let (size, unit) = varnish
+        .storage
+        .split_at(varnish.storage.trim_end_matches(char::is_alphabetic).len());
+    if size.parse::<u64>().map_or(true, |size| size == 0)
+        || !matches!(unit, "" | "k" | "K" | "m" | "M" | "g" | "G")
+    {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} varnish storage '{}' must be a size such as 256M or 1G",
+            proxy.name, varnish.storage
+        )));
+    }
+
+    for entry in &varnish.purge_acl {
+        let (host, mask) = match entry.split_once('/') {
+            Some((host, mask)) => (host, Some(mask)),
+            None => (entry.as_str(), None),
+        };
+        let valid = !host.is_empty()
+            && host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | ':' | '-'))
+            && mask.is_none_or(|mask| mask.parse::<u8>().is_ok_and(|mask| mask <= 128));
+        if !valid {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} varnish purge_acl entry '{entry}' must be a host, address or CIDR range",
+                proxy.name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate the statistics settings of a proxy
+fn validate_stats(proxy: &ProxyConfig, stats: &StatsConfig) -> Result<()> {
+    if proxy.proxy_type != ProxyType::HaProxy {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} stats is only supported for haproxy",
+            proxy.name
+        )));
+    }
+
+    // 8405 is the built-in health check listener
+    if stats.port == 0 || stats.port == proxy.internal_port || stats.port == 8405 {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} stats port {} must be non-zero and not used by the proxy",
+            proxy.name, stats.port
+        )));
+    }
+
+    if let Some(credentials) = &stats.credentials {
+        let valid = credentials.split_once(':').is_some_and(|(user, password)| {
+            !user.is_empty() && !password.is_empty() && !credentials.contains(char::is_whitespace)
+        });
+        if !valid {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} stats credentials must be user:password without spaces",
+                proxy.name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate a service's schedules
+fn validate_schedules(service: &ServiceConfig) -> Result<()> {
+    for schedule in &service.schedules {
+        let owner = format!("Service {} schedule for {}", service.name, schedule.path);
+
+        if !schedule.path.starts_with('/') {
+            return Err(CerberusError::validation(format!(
+                "{owner}: path must start with '/'"
+            )));
+        }
+
+        if let Some(hours) = &schedule.active_hours {
+            match schedule.hours() {
+                Some((start, end)) if start != end => {}
+                _ => {
+                    return Err(CerberusError::validation(format!(
+                        "{owner}: active_hours '{hours}' must be \"HH:MM-HH:MM\" with different start and end"
+                    )));
+                }
+            }
+        }
+
+        if schedule.day_numbers().len() != schedule.active_days.len() {
+            return Err(CerberusError::validation(format!(
+                "{owner}: active_days must be one of {}",
+                WEEKDAYS.join(", ")
+            )));
+        }
+
+        for date in [&schedule.active_from, &schedule.active_until]
+            .into_iter()
+            .flatten()
+        {
+            if parse_date(date).is_none() {
+                return Err(CerberusError::validation(format!(
+                    "{owner}: '{date}' is not a YYYY-MM-DD date"
+                )));
+            }
+        }
+
+        if let (Some(from), Some(until)) = schedule.date_range()
+            && from > until
+        {
+            return Err(CerberusError::validation(format!(
+                "{owner}: active_from must not be after active_until"
+            )));
+        }
+
+        if schedule.active_hours.is_none()
+            && schedule.active_days.is_empty()
+            && schedule.active_from.is_none()
+            && schedule.active_until.is_none()
+        {
+            return Err(CerberusError::validation(format!(
+                "{owner}: at least one of active_hours, active_days, active_from or active_until is required"
+            )));
+        }
+
+        if !(100..=599).contains(&schedule.fallback_status) {
+            return Err(CerberusError::validation(format!(
+                "{owner}: fallback_status must be a valid HTTP status code"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// HTTP methods accepted in `allowed_methods` lists
+pub const HTTP_METHODS: &[&str] = &[
+    "GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS", "CONNECT", "TRACE",
+];
+
+/// Release deployment settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DeploymentConfig {
+    /// Traffic split between current and candidate upstreams
+    #[serde(default)]
+    pub strategy: Option<DeploymentStrategy>,
+}
+
+/// Traffic split between a service's `upstream` and `candidate_upstream`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DeploymentStrategy {
+    /// Strategy type
+    #[serde(rename = "type")]
+    pub strategy_type: StrategyType,
+
+    /// Pool receiving traffic (blue_green only; blue = `upstream`, green = `candidate_upstream`)
+    #[serde(default)]
+    pub active: DeploymentColor,
+
+    /// Percentage of traffic sent to the canary (canary only)
+    #[serde(default = "default_canary_weight")]
+    pub weight: u8,
+}
+
+/// Deployment strategy type
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyType {
+    /// Two full pools, all traffic on the active one
+    BlueGreen,
+    /// A weighted share of traffic on the candidate
+    Canary,
+}
+
+/// Blue/green pool
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum DeploymentColor {
+    /// The service's `upstream`
+    #[default]
+    Blue,
+    /// The service's `candidate_upstream`
+    Green,
+}
+
+fn default_canary_weight() -> u8 {
+    10
+}
+
+impl DeploymentStrategy {
+    /// Names of the current and candidate pools
+    pub fn pool_labels(&self) -> (&'static str, &'static str) {
+        match self.strategy_type {
+            StrategyType::BlueGreen => ("blue", "green"),
+            StrategyType::Canary => ("stable", "canary"),
+        }
+    }
+
+    /// Percentage of traffic routed to the candidate pool
+    pub fn candidate_weight(&self) -> u8 {
+        match (self.strategy_type, self.active) {
+            (StrategyType::BlueGreen, DeploymentColor::Blue) => 0,
+            (StrategyType::BlueGreen, DeploymentColor::Green) => 100,
+            (StrategyType::Canary, _) => self.weight,
+        }
+    }
+}
+
+/// Web application firewall settings
+///
+/// Nginx proxies run ModSecurity and Caddy proxies run Coraza, both with the
+/// OWASP Core Rule Set. Only proxies that route per service (Caddy and
+/// layer-2 Nginx) inspect traffic.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WafConfig {
+    /// Enable the WAF
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// CRS paranoia level (1-4)
+    #[serde(default = "default_paranoia_level")]
+    pub paranoia_level: u8,
+
+    /// Whether matching requests are blocked or only logged
+    #[serde(default)]
+    pub mode: WafMode,
+
+    /// Write matched transactions to the audit log volume
+    #[serde(default = "default_waf_audit_log")]
+    pub audit_log: bool,
+}
+
+impl Default for WafConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            paranoia_level: default_paranoia_level(),
+            mode: WafMode::default(),
+            audit_log: true,
+        }
+    }
+}
+
+fn default_paranoia_level() -> u8 {
+    1
+}
+
+fn default_waf_audit_log() -> bool {
+    true
+}
+
+/// WAF enforcement mode
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum WafMode {
+    /// Reject requests exceeding the anomaly threshold
+    #[default]
+    Block,
+    /// Log matches without blocking
+    Detect,
+}
+
+impl WafConfig {
+    /// Whether a proxy runs the WAF
+    pub fn applies_to(&self, proxy: &ProxyConfig) -> bool {
+        self.enabled
+            && match proxy.proxy_type {
+                ProxyType::Caddy => true,
+                ProxyType::Nginx => proxy.layer.unwrap_or(1) != 1,
+                ProxyType::HaProxy | ProxyType::Traefik | ProxyType::Varnish => false,
+            }
+    }
+
+    /// Value of ModSecurity/Coraza `SecRuleEngine`
+    pub fn rule_engine(&self) -> &'static str {
+        match self.mode {
+            WafMode::Block => "On",
+            WafMode::Detect => "DetectionOnly",
+        }
+    }
+}
+
+/// Per-service WAF settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ServiceWafConfig {
+    /// Inspect this service's traffic when the WAF is enabled
+    #[serde(default = "default_service_waf_enabled")]
+    pub enabled: bool,
+
+    /// CRS rule IDs disabled for this service (false positives)
+    #[serde(default)]
+    pub exclude_rules: Vec<u32>,
+}
+
+fn default_service_waf_enabled() -> bool {
+    true
+}
+
+impl Default for ServiceWafConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            exclude_rules: Vec::new(),
+        }
+    }
+}
+
+/// Security settings applied across all proxies
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SecurityConfig {
+    /// Headers stripped at the proxies
+    #[serde(default)]
+    pub header_scrub: HeaderScrubConfig,
+
+    /// CrowdSec agent and bouncers
+    #[serde(default)]
+    pub crowdsec: CrowdSecConfig,
+
+    /// Client addresses reported by a CDN or load balancer in front
+    #[serde(default)]
+    pub real_ip: RealIpConfig,
+
+    /// Read-only, unprivileged proxy and Anubis containers
+    #[serde(default)]
+    pub hardening: HardeningConfig,
+
+    /// Seccomp, AppArmor and SELinux confinement of the proxy containers
+    #[serde(default)]
+    pub profiles: ProfilesConfig,
+
+    /// Provider checking services with `[services.auth] type = "forward"`
+    #[serde(default)]
+    pub forward_auth: ForwardAuthConfig,
+}
+
+/// Forward authentication provider (`[security.forward_auth]`)
+///
+/// ```toml
+/// [security.forward_auth]
+/// provider = "oauth2-proxy"
+/// secrets = ["oauth2_client_secret", "oauth2_cookie_secret"]
+///
+/// [security.forward_auth.environment]
+/// OAUTH2_PROXY_PROVIDER = "oidc"
+/// OAUTH2_PROXY_OIDC_ISSUER_URL = "https://id.example.com"
+/// ```
+///
+/// The provider runs as a sidecar on the backend networks unless `address`
+/// points at one running elsewhere. oauth2-proxy is answered on `/oauth2/`
+/// of every protected host; Authelia's portal needs its own service.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ForwardAuthConfig {
+    /// Provider answering the checks; unset disables forward auth
+    #[serde(default)]
+    pub provider: Option<AuthProvider>,
+
+    /// Image of the sidecar (default: the provider's image)
+    #[serde(default)]
+    pub image: Option<String>,
+
+    /// URL of a provider outside the stack (e.g. "http://auth.internal:4180"); no sidecar is generated
+    #[serde(default)]
+    pub address: Option<String>,
+
+    /// Environment of the sidecar
+    #[serde(default)]
+    pub environment: BTreeMap<String, String>,
+
+    /// Secrets mounted into the sidecar
+    #[serde(default)]
+    pub secrets: Vec<String>,
+
+    /// Host directory mounted as Authelia's `/config`
+    #[serde(default)]
+    pub config_dir: Option<String>,
+}
+
+/// Forward authentication provider
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum AuthProvider {
+    /// OAuth2 Proxy, signing users in with an OIDC or OAuth2 provider
+    #[serde(rename = "oauth2-proxy")]
+    Oauth2Proxy,
+    /// Authelia
+    Authelia,
+}
+
+impl AuthProvider {
+    /// Name used in `provider` and as the sidecar's service name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Oauth2Proxy => "oauth2-proxy",
+            Self::Authelia => "authelia",
+        }
+    }
+
+    /// Image of the sidecar
+    pub fn default_image(&self) -> &'static str {
+        match self {
+            Self::Oauth2Proxy => "quay.io/oauth2-proxy/oauth2-proxy:v7.6.0",
+            Self::Authelia => "authelia/authelia:4.38",
+        }
+    }
+
+    /// Port the provider listens on
+    pub fn port(&self) -> u16 {
+        match self {
+            Self::Oauth2Proxy => 4180,
+            Self::Authelia => 9091,
+        }
+    }
+
+    /// Path answering checks with 2xx or 401, for Nginx's auth_request
+    pub fn auth_request_path(&self) -> &'static str {
+        match self {
+            Self::Oauth2Proxy => "/oauth2/auth",
+            Self::Authelia => "/api/authz/auth-request",
+        }
+    }
+
+    /// Path answering checks with 2xx or a redirect to the sign-in page
+    ///
+    /// oauth2-proxy redirects from its proxy endpoint, which answers 202
+    /// through its `static://202` upstream once the user is signed in.
+    pub fn forward_auth_path(&self) -> &'static str {
+        match self {
+            Self::Oauth2Proxy => "/",
+            Self::Authelia => "/api/authz/forward-auth",
+        }
+    }
+
+    /// Response headers identifying the user, passed on to the upstream
+    pub fn user_headers(&self) -> &'static [&'static str] {
+        match self {
+            Self::Oauth2Proxy => &["X-Auth-Request-User", "X-Auth-Request-Email"],
+            Self::Authelia => &[
+                "Remote-User",
+                "Remote-Groups",
+                "Remote-Email",
+                "Remote-Name",
+            ],
+        }
+    }
+}
+
+impl ForwardAuthConfig {
+    /// Whether the provider runs as a sidecar of the stack
+    pub fn has_sidecar(&self) -> bool {
+        self.provider.is_some() && self.address.is_none()
+    }
+
+    /// Base URL of the provider, without a trailing slash
+    pub fn base_url(&self) -> Option<String> {
+        let provider = self.provider?;
+        Some(match &self.address {
+            Some(address) => address.trim_end_matches('/').to_string(),
+            None => format!("http://{}:{}", provider.as_str(), provider.port()),
+        })
+    }
+
+    /// Image of the sidecar
+    pub fn image(&self) -> Option<&str> {
+        let provider = self.provider?;
+        Some(self.image.as_deref().unwrap_or(provider.default_image()))
+    }
+}
+
+/// Hardened container mode (`[security.hardening]`)
+///
+/// Proxies and Anubis run with a read-only root filesystem, without
+/// capabilities or privilege escalation, and as a non-root user; the
+/// directories they write at runtime become tmpfs mounts. Proxies then have
+/// to listen on unprivileged ports.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HardeningConfig {
+    /// Harden the generated containers
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Additional writable directories, mounted as tmpfs in every hardened container
+    #[serde(default)]
+    pub tmpfs: Vec<String>,
+
+    /// Size limit of each tmpfs mount (e.g. "64m")
+    #[serde(default = "default_tmpfs_size")]
+    pub tmpfs_size: String,
+}
+
+fn default_tmpfs_size() -> String {
+    "64m".to_string()
+}
+
+impl Default for HardeningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tmpfs: Vec::new(),
+            tmpfs_size: default_tmpfs_size(),
+        }
+    }
+}
+
+/// Confinement profiles of the proxy containers (`[security.profiles]`)
+///
+/// ```toml
+/// [security.profiles]
+/// enabled = true
+/// allow_syscalls = ["io_uring_setup", "io_uring_enter"]
+/// selinux_type = "cerberus_proxy_t"
+/// ```
+///
+/// Each proxy gets a seccomp profile allowing only the system calls the
+/// proxies and their entrypoint scripts make, and an AppArmor profile that
+/// must be loaded on the host before the containers start. SELinux policies
+/// are not generated; `selinux_type` labels the containers with a type
+/// installed separately.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct ProfilesConfig {
+    /// Generate the profiles and apply them to the proxy containers
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Apply the generated seccomp profile
+    #[serde(default = "default_profile_applied")]
+    pub seccomp: bool,
+
+    /// Apply the generated AppArmor profile
+    #[serde(default = "default_profile_applied")]
+    pub apparmor: bool,
+
+    /// System calls allowed in addition to the generated list
+    #[serde(default)]
+    pub allow_syscalls: Vec<String>,
+
+    /// SELinux type the proxy containers run as (unset = the engine's default)
+    #[serde(default)]
+    pub selinux_type: Option<String>,
+}
+
+fn default_profile_applied() -> bool {
+    true
+}
+
+impl Default for ProfilesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seccomp: default_profile_applied(),
+            apparmor: default_profile_applied(),
+            allow_syscalls: Vec::new(),
+            selinux_type: None,
+        }
+    }
+}
+
+/// Address ranges Cloudflare sends requests from, as published at
+/// <https://www.cloudflare.com/ips/>
+pub const CLOUDFLARE_RANGES: &[&str] = &[
+    "173.245.48.0/20",
+    "103.21.244.0/22",
+    "103.22.200.0/22",
+    "103.31.4.0/22",
+    "141.101.64.0/18",
+    "108.162.192.0/18",
+    "190.93.240.0/20",
+    "188.114.96.0/20",
+    "197.234.240.0/22",
+    "198.41.128.0/17",
+    "162.158.0.0/15",
+    "104.16.0.0/13",
+    "104.24.0.0/14",
+    "172.64.0.0/13",
+    "131.0.72.0/22",
+    "2400:cb00::/32",
+    "2606:4700::/32",
+    "2803:f800::/32",
+    "2405:b500::/32",
+    "2405:8100::/32",
+    "2a06:98c0::/29",
+    "2c0f:f248::/32",
+];
+
+/// Real client addresses behind a CDN (`[security.real_ip]`)
+///
+/// Edge proxies take the client address from `header` when a request comes
+/// from a trusted range, so Anubis, CrowdSec, rate limits and access logs
+/// see the visitor instead of the CDN.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RealIpConfig {
+    /// CDN in front of the edge proxies; unset trusts no one
+    #[serde(default)]
+    pub provider: Option<RealIpProvider>,
+
+    /// Trusted ranges, added to those of a known provider
+    #[serde(default)]
+    pub trusted: Vec<String>,
+
+    /// Header carrying the client address (default: the provider's)
+    #[serde(default)]
+    pub header: Option<String>,
+}
+
+/// CDN or load balancer reporting client addresses
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum RealIpProvider {
+    /// Cloudflare, with its published ranges and `CF-Connecting-IP`
+    Cloudflare,
+    /// Only the ranges listed in `trusted`, with `X-Forwarded-For`
+    Custom,
+}
+
+impl RealIpProvider {
+    /// Name used in configuration and generated comments
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RealIpProvider::Cloudflare => "cloudflare",
+            RealIpProvider::Custom => "custom",
+        }
+    }
+}
+
+impl RealIpConfig {
+    /// Whether edge proxies trust a client address header
+    pub fn is_enabled(&self) -> bool {
+        self.provider.is_some()
+    }
+
+    /// Ranges whose client address header is trusted
+    pub fn trusted_ranges(&self) -> Vec<String> {
+        let known: &[&str] = match self.provider {
+            Some(RealIpProvider::Cloudflare) => CLOUDFLARE_RANGES,
+            _ => &[],
+        };
+        known
+            .iter()
+            .map(|range| range.to_string())
+            .chain(self.trusted.iter().cloned())
+            .collect()
+    }
+
+    /// Header carrying the client address
+    pub fn header(&self) -> &str {
+        match (&self.header, self.provider) {
+            (Some(header), _) => header,
+            (None, Some(RealIpProvider::Cloudflare)) => "CF-Connecting-IP",
+            (None, _) => "X-Forwarded-For",
+        }
+    }
+}
+
+/// CrowdSec intrusion prevention
+///
+/// The agent parses the proxies' shared access logs; edge Nginx and Traefik
+/// proxies query a forward-auth bouncer and edge HAProxy proxies an SPOA
+/// bouncer before routing a request.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CrowdSecConfig {
+    /// Enable the CrowdSec services
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// CrowdSec agent (and local API) image
+    #[serde(default = "default_crowdsec_image")]
+    pub image: String,
+
+    /// Forward-auth bouncer image used by Nginx and Traefik
+    #[serde(default = "default_crowdsec_bouncer_image")]
+    pub bouncer_image: String,
+
+    /// SPOA bouncer image used by HAProxy
+    #[serde(default = "default_crowdsec_spoa_image")]
+    pub spoa_image: String,
+
+    /// Environment variable holding the bouncer API key
+    #[serde(default = "default_crowdsec_api_key_env")]
+    pub api_key_env: String,
+
+    /// Hub collections installed in addition to those of the proxy types
+    #[serde(default)]
+    pub collections: Vec<String>,
+}
+
+impl Default for CrowdSecConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            image: default_crowdsec_image(),
+            bouncer_image: default_crowdsec_bouncer_image(),
+            spoa_image: default_crowdsec_spoa_image(),
+            api_key_env: default_crowdsec_api_key_env(),
+            collections: Vec::new(),
+        }
+    }
+}
+
+fn default_crowdsec_image() -> String {
+    "crowdsecurity/crowdsec:latest".to_string()
+}
+
+fn default_crowdsec_bouncer_image() -> String {
+    "fbonalair/traefik-crowdsec-bouncer:latest".to_string()
+}
+
+fn default_crowdsec_spoa_image() -> String {
+    "crowdsecurity/spoa-bouncer:latest".to_string()
+}
+
+fn default_crowdsec_api_key_env() -> String {
+    "CROWDSEC_BOUNCER_KEY".to_string()
+}
+
+impl CrowdSecConfig {
+    /// Hub collections for the configured proxies plus any extras
+    pub fn collections_for(&self, proxies: &[ProxyConfig]) -> Vec<String> {
+        let mut collections: Vec<String> = Vec::new();
+        // Varnish writes no access log for the agent to parse
+        for proxy in proxies
+            .iter()
+            .filter(|proxy| proxy.proxy_type != ProxyType::Varnish)
+        {
+            let collection = format!("crowdsecurity/{}", proxy.proxy_type.as_str());
+            if !collections.contains(&collection) {
+                collections.push(collection);
+            }
+        }
+        for collection in &self.collections {
+            if !collections.contains(collection) {
+                collections.push(collection.clone());
+            }
+        }
+        collections
+    }
+}
+
+/// DNS record plan (`[dns]`)
+///
+/// Every service and route domain must point at the deployment hosts.
+/// `cerberus generate` writes the records in the provider's format and
+/// `cerberus dns check` compares them with live DNS.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DnsConfig {
+    /// Addresses of the deployment hosts, or a single hostname to alias
+    #[serde(default)]
+    pub hosts: Vec<String>,
+
+    /// Format of the generated record plan
+    #[serde(default)]
+    pub provider: DnsProvider,
+
+    /// Record TTL in seconds
+    #[serde(default = "default_dns_ttl")]
+    pub ttl: u32,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            hosts: Vec::new(),
+            provider: DnsProvider::default(),
+            ttl: default_dns_ttl(),
+        }
+    }
+}
+
+fn default_dns_ttl() -> u32 {
+    300
+}
+
+/// Format of the DNS record plan
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum DnsProvider {
+    /// BIND zone-file fragment (`dns/records.zone`)
+    #[default]
+    Zone,
+    /// Cloudflare API record bodies (`dns/cloudflare.json`)
+    Cloudflare,
+    /// Route 53 change batch (`dns/route53.json`)
+    Route53,
+}
+
+/// Validate the DNS record plan settings
+/// Check that a time zone looks like an IANA name ("UTC", "Asia/Tokyo", "Etc/GMT+9")
+fn validate_timezone(timezone: &str) -> Result<()> {
+    let valid = !timezone.is_empty()
+        && timezone.split('/').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
+        });
+    if !valid {
+        return Err(CerberusError::validation(format!(
+            "project.timezone '{timezone}' is not a time zone name like \"Asia/Tokyo\" or \"UTC\""
+        )));
+    }
+    Ok(())
+}
+
+fn validate_dns(dns: &DnsConfig) -> Result<()> {
+    let mut aliases = 0;
+    for host in &dns.hosts {
+        if host.parse::<std::net::IpAddr>().is_ok() {
+            continue;
+        }
+        let valid = !host.is_empty()
+            && host.trim_end_matches('.').split('.').all(|label| {
+                !label.is_empty()
+                    && !label.starts_with('-')
+                    && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            });
+        if !valid {
+            return Err(CerberusError::validation(format!(
+                "dns.hosts entry '{host}' is neither an IP address nor a hostname"
+            )));
+        }
+        aliases += 1;
+    }
+
+    if aliases > 0 && dns.hosts.len() > 1 {
+        return Err(CerberusError::validation(
+            "dns.hosts may list addresses or a single hostname (CNAME target), not both",
+        ));
+    }
+
+    if dns.ttl == 0 {
+        return Err(CerberusError::validation("dns.ttl must be greater than 0"));
+    }
+
+    Ok(())
+}
+
+fn validate_tls(tls: &TlsConfig) -> Result<()> {
+    if let Some(redirect) = &tls.redirect_http {
+        if !matches!(redirect.status, 301 | 302 | 307 | 308) {
+            return Err(CerberusError::validation(format!(
+                "tls.redirect_http.status {} must be 301, 302, 307 or 308",
+                redirect.status
+            )));
+        }
+        if redirect.https_port == 0 {
+            return Err(CerberusError::validation(
+                "tls.redirect_http.https_port must be greater than 0",
+            ));
+        }
+    }
+
+    let hsts = &tls.hsts;
+    if hsts.enabled && hsts.preload {
+        if !hsts.include_subdomains {
+            return Err(CerberusError::validation(
+                "tls.hsts.preload requires includeSubdomains = true",
+            ));
+        }
+        if hsts.max_age < default_hsts_max_age() {
+            return Err(CerberusError::validation(format!(
+                "tls.hsts.preload requires max_age of at least {} (one year)",
+                default_hsts_max_age()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate `[tls.acme]`
+fn validate_acme(config: &Config) -> Result<()> {
+    let Some(acme) = &config.tls.acme else {
+        return Ok(());
+    };
+    let provider = acme.provider.as_str();
+
+    if !acme.email.contains('@') {
+        return Err(CerberusError::validation(format!(
+            "tls.acme.email '{}' is not an email address",
+            acme.email
+        )));
+    }
+    if acme.domains.is_empty() {
+        return Err(CerberusError::validation(
+            "tls.acme.domains must list at least one domain",
+        ));
+    }
+    if let Some(domain) = acme.domains.iter().find(|domain| !is_valid_host(domain)) {
+        return Err(CerberusError::validation(format!(
+            "tls.acme domain '{domain}' is not a valid domain"
+        )));
+    }
+    if let Some(ca) = acme.ca.as_deref().filter(|ca| !ca.starts_with("https://")) {
+        return Err(CerberusError::validation(format!(
+            "tls.acme.ca '{ca}' must be an https:// ACME directory URL"
+        )));
+    }
+    if acme.https_port == 0 || acme.listen_port == 0 {
+        return Err(CerberusError::validation(
+            "tls.acme.https_port and listen_port must be greater than 0",
+        ));
+    }
+
+    let known = acme.provider.credentials();
+    for (env, secret) in &acme.credentials {
+        if !known.iter().any(|credential| credential.env == env) {
+            let names: Vec<&str> = known.iter().map(|credential| credential.env).collect();
+            return Err(CerberusError::validation(format!(
+                "tls.acme credential {env} is not read by the {provider} provider (expected {})",
+                names.join(", ")
+            )));
+        }
+        if !config.secrets.contains_key(secret) {
+            return Err(CerberusError::validation(format!(
+                "tls.acme credential {env} secret {secret} is not defined in [secrets]"
+            )));
+        }
+    }
+    if let Some(missing) = known
+        .iter()
+        .find(|credential| credential.required && !acme.credentials.contains_key(credential.env))
+    {
+        return Err(CerberusError::validation(format!(
+            "tls.acme.credentials must set {} for the {provider} provider",
+            missing.env
+        )));
+    }
+
+    if config.project.target == DeployTarget::Nomad
+        || config.outputs.contains_key(&DeployTarget::Nomad)
+    {
+        return Err(CerberusError::validation(
+            "tls.acme is only supported by the compose and swarm targets",
+        ));
+    }
+    let edges: Vec<&ProxyConfig> = config
+        .proxies
+        .iter()
+        .filter(|proxy| proxy.external_port.is_some())
+        .collect();
+    if edges.is_empty() {
+        return Err(CerberusError::validation(
+            "tls.acme needs an edge proxy with an external_port",
+        ));
+    }
+    for proxy in edges {
+        if !matches!(proxy.proxy_type, ProxyType::Caddy | ProxyType::Traefik) {
+            return Err(CerberusError::validation(format!(
+                "tls.acme is supported on caddy and traefik edge proxies, but {} is {}",
+                proxy.name, proxy.proxy_type
+            )));
+        }
+        // Every container would order its own certificates
+        if proxy.container_count(config.project.scaling) > 1 {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} runs several containers, which cannot share the ACME certificate \
+                 storage; run a single edge container with tls.acme",
+                proxy.name
+            )));
+        }
+        if proxy.internal_port == acme.listen_port {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} internal_port {} is tls.acme.listen_port",
+                proxy.name, proxy.internal_port
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn validate_log_shipping(logs: &LogShippingConfig, proxies: &[ProxyConfig]) -> Result<()> {
+    if let Some(url) = &logs.loki_url {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(CerberusError::validation(format!(
+                "observability.logs.loki_url '{url}' must be an http:// or https:// URL"
+            )));
+        }
+        if logs.loki_port.is_some() {
+            return Err(CerberusError::validation(
+                "observability.logs.loki_port only applies to the bundled Loki, not loki_url",
+            ));
+        }
+    }
+
+    match parse_duration_secs(&logs.retention) {
+        Some(secs) if secs >= 86400 && secs % 3600 == 0 => {}
+        _ => {
+            return Err(CerberusError::validation(format!(
+                "observability.logs.retention '{}' must be whole hours of at least 24h (e.g. \"7d\")",
+                logs.retention
+            )));
+        }
+    }
+
+    if let Some(port) = logs.loki_port {
+        if port == 0 {
+            return Err(CerberusError::validation(
+                "observability.logs.loki_port must be greater than 0",
+            ));
+        }
+        if let Some(proxy) = proxies
+            .iter()
+            .find(|proxy| proxy.external_port == Some(port))
+        {
+            return Err(CerberusError::validation(format!(
+                "observability.logs.loki_port {port} is already published by proxy '{}'",
+                proxy.name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_tracing(config: &Config, tracing: &TracingConfig) -> Result<()> {
+    if config.project.target == DeployTarget::Nomad
+        || config.outputs.contains_key(&DeployTarget::Nomad)
+    {
+        return Err(CerberusError::validation(
+            "observability.tracing is only supported by the compose and swarm targets, \
+             which run the OpenTelemetry Collector",
+        ));
+    }
+    if !tracing.endpoint.starts_with("http://") && !tracing.endpoint.starts_with("https://") {
+        return Err(CerberusError::validation(format!(
+            "observability.tracing.endpoint '{}' must be an http:// or https:// URL",
+            tracing.endpoint
+        )));
+    }
+    if !(0.0..=1.0).contains(&tracing.sample_rate) {
+        return Err(CerberusError::validation(format!(
+            "observability.tracing.sample_rate {} must be between 0.0 and 1.0",
+            tracing.sample_rate
+        )));
+    }
+    // The otel image cannot carry ModSecurity
+    if let Some(proxy) = config
+        .proxies
+        .iter()
+        .find(|proxy| proxy.proxy_type == ProxyType::Nginx && config.waf.applies_to(proxy))
+    {
+        return Err(CerberusError::validation(format!(
+            "Proxy {} runs the WAF, whose nginx image has no OpenTelemetry module; \
+             disable [waf] for it or remove [observability.tracing]",
+            proxy.name
+        )));
+    }
+    Ok(())
+}
+
+/// Opinionated configuration checks (`[lint]`)
+///
+/// Every rule has a default severity that `[lint.rules]` can raise, lower,
+/// or turn `off`:
+///
+/// ```toml
+/// [lint.rules]
+/// floating-image-tag = "error"
+/// no-resource-limits = "off"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LintConfig {
+    /// Severity per rule, overriding the rule's default
+    #[serde(default)]
+    pub rules: std::collections::BTreeMap<LintRule, LintSeverity>,
+
+    /// Highest Anubis difficulty not reported as too high
+    #[serde(default = "default_lint_max_anubis_difficulty")]
+    pub max_anubis_difficulty: u8,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            rules: std::collections::BTreeMap::new(),
+            max_anubis_difficulty: default_lint_max_anubis_difficulty(),
+        }
+    }
+}
+
+impl LintConfig {
+    /// Severity a rule is reported with
+    pub fn severity(&self, rule: LintRule) -> LintSeverity {
+        self.rules
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| rule.default_severity())
+    }
+}
+
+fn default_lint_max_anubis_difficulty() -> u8 {
+    5
+}
+
+/// Rule checked by `cerberus validate`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum LintRule {
+    /// An image uses `latest`, no tag, or a tag without a version
+    FloatingImageTag,
+    /// A service container has no healthcheck
+    MissingHealthcheck,
+    /// A proxy exposes its admin API or statistics without authentication
+    AdminApiEnabled,
+    /// A proxy container has no CPU or memory limit
+    NoResourceLimits,
+    /// The Anubis challenge is harder than `max_anubis_difficulty`
+    AnubisDifficulty,
+    /// A renamed key is still used under its deprecated name
+    DeprecatedKey,
+    /// Containers log in different time zones
+    MixedTimezones,
+    /// Anubis is enabled but no layer-1 proxy or no next layer is wired to it
+    AnubisTopology,
+    /// An upstream points at localhost, which is the proxy's own container
+    LocalhostUpstream,
+}
+
+impl LintRule {
+    /// Every rule, in reporting order
+    pub const ALL: [LintRule; 9] = [
+        LintRule::FloatingImageTag,
+        LintRule::MissingHealthcheck,
+        LintRule::AdminApiEnabled,
+        LintRule::NoResourceLimits,
+        LintRule::AnubisDifficulty,
+        LintRule::DeprecatedKey,
+        LintRule::MixedTimezones,
+        LintRule::AnubisTopology,
+        LintRule::LocalhostUpstream,
+    ];
+
+    /// Identifier used in `[lint.rules]` and in reports
+    pub fn id(&self) -> &'static str {
+        match self {
+            LintRule::FloatingImageTag => "floating-image-tag",
+            LintRule::MissingHealthcheck => "missing-healthcheck",
+            LintRule::AdminApiEnabled => "admin-api-enabled",
+            LintRule::NoResourceLimits => "no-resource-limits",
+            LintRule::AnubisDifficulty => "anubis-difficulty",
+            LintRule::DeprecatedKey => "deprecated-key",
+            LintRule::MixedTimezones => "mixed-timezones",
+            LintRule::AnubisTopology => "anubis-topology",
+            LintRule::LocalhostUpstream => "localhost-upstream",
+        }
+    }
+
+    /// Severity used when `[lint.rules]` does not set one
+    pub fn default_severity(&self) -> LintSeverity {
+        match self {
+            LintRule::NoResourceLimits => LintSeverity::Info,
+            _ => LintSeverity::Warning,
+        }
+    }
+}
+
+/// How a lint finding is reported
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    /// The rule is not checked
+    Off,
+    /// Reported only
+    Info,
+    /// Reported, and fails `validate --strict`
+    Warning,
+    /// Fails `validate`
+    Error,
+}
+
+/// Alert channels shared by every command and daemon
+///
+/// ```toml
+/// [notifications.channels.ops]
+/// type = "discord"
+/// webhook_url = "https://discord.com/api/webhooks/..."
+///
+/// [notifications.routes]
+/// scaling = ["ops"]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NotificationsConfig {
+    /// Named delivery channels
+    #[serde(default)]
+    pub channels: std::collections::BTreeMap<String, ChannelConfig>,
+
+    /// Channels each event is sent to (events without a route are dropped)
+    #[serde(default)]
+    pub routes: std::collections::BTreeMap<NotificationEvent, Vec<String>>,
+}
+
+/// Event that triggers a notification
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// The autoscaler changed the number of containers
+    Scaling,
+    /// A certificate is about to expire
+    CertExpiry,
+    /// A release was switched, weighted or promoted
+    Deploy,
+}
+
+impl NotificationEvent {
+    /// Name used in `[notifications.routes]`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationEvent::Scaling => "scaling",
+            NotificationEvent::CertExpiry => "cert_expiry",
+            NotificationEvent::Deploy => "deploy",
+        }
+    }
+}
+
+/// A notification delivery channel
+///
+/// Secrets are read from the environment variables named by the `*_env`
+/// fields when a notification is sent.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ChannelConfig {
+    /// Email through an SMTP server
+    Smtp {
+        /// Server URL (`smtp://host:587` or `smtps://host:465`)
+        url: String,
+        /// Sender address
+        from: String,
+        /// Recipient addresses
+        to: Vec<String>,
+        /// SMTP user name
+        #[serde(default)]
+        username: Option<String>,
+        /// Environment variable holding the SMTP password
+        #[serde(default)]
+        password_env: Option<String>,
+    },
+    /// Telegram bot message
+    Telegram {
+        /// Environment variable holding the bot token
+        bot_token_env: String,
+        /// Chat (or channel) ID messages are sent to
+        chat_id: String,
+    },
+    /// Discord webhook
+    Discord {
+        /// Webhook URL
+        webhook_url: String,
+    },
+    /// Matrix room message
+    Matrix {
+        /// Homeserver base URL
+        homeserver: String,
+        /// Room ID (`!room:example.org`)
+        room_id: String,
+        /// Environment variable holding the access token
+        access_token_env: String,
+    },
+    /// JSON POST to an arbitrary URL
+    Webhook {
+        /// Endpoint URL
+        url: String,
+        /// Extra request headers
+        #[serde(default)]
+        headers: std::collections::BTreeMap<String, String>,
+    },
+}
+
+/// Validate notification channels and routes
+fn validate_notifications(notifications: &NotificationsConfig) -> Result<()> {
+    for (name, channel) in &notifications.channels {
+        let urls: Vec<&str> = match channel {
+            ChannelConfig::Smtp { url, from, to, .. } => {
+                if !(url.starts_with("smtp://") || url.starts_with("smtps://")) {
+                    return Err(CerberusError::validation(format!(
+                        "notifications.channels.{name} url must start with smtp:// or smtps://"
+                    )));
+                }
+                if !from.contains('@') || to.is_empty() || to.iter().any(|to| !to.contains('@')) {
+                    return Err(CerberusError::validation(format!(
+                        "notifications.channels.{name} needs a from address and at least one to address"
+                    )));
+                }
+                Vec::new()
+            }
+            ChannelConfig::Telegram { chat_id, .. } if chat_id.trim().is_empty() => {
+                return Err(CerberusError::validation(format!(
+                    "notifications.channels.{name} chat_id cannot be empty"
+                )));
+            }
+            ChannelConfig::Telegram { .. } => Vec::new(),
+            ChannelConfig::Discord { webhook_url } => vec![webhook_url],
+            ChannelConfig::Matrix {
+                homeserver,
+                room_id,
+                ..
+            } => {
+                if !room_id.starts_with('!') || !room_id.contains(':') {
+                    return Err(CerberusError::validation(format!(
+                        "notifications.channels.{name} room_id '{room_id}' must look like !room:server"
+                    )));
+                }
+                vec![homeserver]
+            }
+            ChannelConfig::Webhook { url, .. } => vec![url],
+        };
+
+        if let Some(url) = urls
+            .iter()
+            .find(|url| !(url.starts_with("http://") || url.starts_with("https://")))
+        {
+            return Err(CerberusError::validation(format!(
+                "notifications.channels.{name} URL '{url}' must start with http:// or https://"
+            )));
+        }
+    }
+
+    for (event, channels) in &notifications.routes {
+        if let Some(channel) = channels
+            .iter()
+            .find(|channel| !notifications.channels.contains_key(*channel))
+        {
+            return Err(CerberusError::validation(format!(
+                "notifications.routes.{} references unknown channel '{channel}'",
+                event.as_str()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Headers removed from proxied traffic
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HeaderScrubConfig {
+    /// Headers stripped from inbound client requests at edge proxies
+    /// (e.g. spoofed `X-Forwarded-For`, internal headers)
+    #[serde(default)]
+    pub request: Vec<String>,
+
+    /// Headers stripped from responses at every proxy (e.g. `Server`, `X-Powered-By`)
+    #[serde(default)]
+    pub response: Vec<String>,
+}
+
+/// Validate a list of header names to scrub
+fn validate_header_names(headers: &[String], field: &str) -> Result<()> {
+    for header in headers {
+        let valid = !header.is_empty()
+            && header
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(CerberusError::validation(format!(
+                "security.header_scrub.{field} contains invalid header name '{header}'"
+            )));
+        }
+        if header.eq_ignore_ascii_case("host") {
+            return Err(CerberusError::validation(format!(
+                "security.header_scrub.{field} cannot strip the Host header"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Validate the trusted ranges and header of `[security.real_ip]`
+fn validate_real_ip(real_ip: &RealIpConfig) -> Result<()> {
+    let Some(provider) = real_ip.provider else {
+        if !real_ip.trusted.is_empty() || real_ip.header.is_some() {
+            return Err(CerberusError::validation(
+                "security.real_ip.trusted and header require security.real_ip.provider",
+            ));
+        }
+        return Ok(());
+    };
+    if provider == RealIpProvider::Custom && real_ip.trusted.is_empty() {
+        return Err(CerberusError::validation(
+            "security.real_ip provider 'custom' must list its trusted ranges",
+        ));
+    }
+    for entry in &real_ip.trusted {
+        if !is_valid_cidr(entry) {
+            return Err(CerberusError::validation(format!(
+                "security.real_ip.trusted entry '{entry}' must be an address or CIDR range"
+            )));
+        }
+    }
+    let header = real_ip.header();
+    if header.is_empty()
+        || !header
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(CerberusError::validation(format!(
+            "security.real_ip.header '{header}' is not a valid header name"
+        )));
+    }
+    Ok(())
+}
+
+/// Whether a user is an htpasswd line with a bcrypt hash
+///
+/// bcrypt is the only scheme every proxy type verifies.
+fn is_valid_htpasswd_user(user: &str) -> bool {
+    user.split_once(':').is_some_and(|(name, hash)| {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            && ["$2a$", "$2b$", "$2y$"]
+                .iter()
+                .any(|prefix| hash.starts_with(prefix))
+            && !hash.contains(|c: char| c.is_whitespace() || c == '"')
+    })
+}
+
+/// Validate `[services.auth]` and the `[security.forward_auth]` provider
+///
+/// Only proxies that route each service apply the authentication, and each
+/// of them has to support the chosen method.
+fn validate_auth(config: &Config) -> Result<()> {
+    let forward_auth = &config.security.forward_auth;
+    if let Some(provider) = forward_auth.provider {
+        if let Some(address) = &forward_auth.address
+            && !(address.starts_with("http://") || address.starts_with("https://"))
+        {
+            return Err(CerberusError::validation(format!(
+                "security.forward_auth.address '{address}' must be an http:// or https:// URL"
+            )));
+        }
+        if let Some(secret) = forward_auth
+            .secrets
+            .iter()
+            .find(|secret| !config.secrets.contains_key(*secret))
+        {
+            return Err(CerberusError::validation(format!(
+                "security.forward_auth secret {secret} is not defined in [secrets]"
+            )));
+        }
+        match (provider, &forward_auth.config_dir) {
+            (AuthProvider::Authelia, None) if forward_auth.has_sidecar() => {
+                return Err(CerberusError::validation(
+                    "security.forward_auth.config_dir is required for the authelia sidecar",
+                ));
+            }
+            (AuthProvider::Oauth2Proxy, Some(_)) => {
+                return Err(CerberusError::validation(
+                    "security.forward_auth.config_dir is only used by authelia; \
+                     configure oauth2-proxy through environment",
+                ));
+            }
+            _ => {}
+        }
+        if forward_auth.has_sidecar()
+            && (config.project.target == DeployTarget::Nomad
+                || config.outputs.contains_key(&DeployTarget::Nomad))
+        {
+            return Err(CerberusError::validation(
+                "security.forward_auth sidecar is only generated for the compose and swarm targets; \
+                 set address to a provider running elsewhere",
+            ));
+        }
+    }
+
+    for service in &config.services {
+        let Some(auth) = &service.auth else {
+            continue;
+        };
+        let proxies: Vec<&ProxyConfig> = config
+            .proxies
+            .iter()
+            .filter(|proxy| proxy.renders_services())
+            .collect();
+        if proxies.is_empty() {
+            return Err(CerberusError::validation(format!(
+                "Service {} sets [services.auth], but no proxy routes services itself; \
+                 add a Caddy, HAProxy, Traefik or layer-2 Nginx proxy",
+                service.name
+            )));
+        }
+
+        match auth.auth_type {
+            AuthType::Basic => {
+                match (auth.users.is_empty(), &auth.users_secret) {
+                    (true, None) => {
+                        return Err(CerberusError::validation(format!(
+                            "Service {} auth must list users or set users_secret",
+                            service.name
+                        )));
+                    }
+                    (false, Some(_)) => {
+                        return Err(CerberusError::validation(format!(
+                            "Service {} auth sets both users and users_secret; use one",
+                            service.name
+                        )));
+                    }
+                    _ => {}
+                }
+                for user in &auth.users {
+                    if !is_valid_htpasswd_user(user) {
+                        let name = user.split_once(':').map_or(user.as_str(), |(name, _)| name);
+                        return Err(CerberusError::validation(format!(
+                            "Service {} auth user '{name}' must be name:bcrypt-hash (e.g. from htpasswd -nB)",
+                            service.name
+                        )));
+                    }
+                }
+                if auth.realm.trim().is_empty() || auth.realm.contains(['"', '\n']) {
+                    return Err(CerberusError::validation(format!(
+                        "Service {} auth.realm must be text without quotes",
+                        service.name
+                    )));
+                }
+                if let Some(secret) = &auth.users_secret {
+                    if !config.secrets.contains_key(secret) {
+                        return Err(CerberusError::validation(format!(
+                            "Service {} auth.users_secret {secret} is not defined in [secrets]",
+                            service.name
+                        )));
+                    }
+                    // Caddy and HAProxy only take users written into their config
+                    if let Some(proxy) = proxies.iter().find(|proxy| {
+                        matches!(proxy.proxy_type, ProxyType::Caddy | ProxyType::HaProxy)
+                    }) {
+                        return Err(CerberusError::validation(format!(
+                            "Service {} auth.users_secret cannot be read by {} proxy {}; list the users instead",
+                            service.name, proxy.proxy_type, proxy.name
+                        )));
+                    }
+                }
+            }
+            AuthType::Forward => {
+                if forward_auth.provider.is_none() {
+                    return Err(CerberusError::validation(format!(
+                        "Service {} uses forward auth, but [security.forward_auth] sets no provider",
+                        service.name
+                    )));
+                }
+                if !auth.users.is_empty() || auth.users_secret.is_some() {
+                    return Err(CerberusError::validation(format!(
+                        "Service {} auth users only apply to type = \"basic\"",
+                        service.name
+                    )));
+                }
+                if let Some(proxy) = proxies
+                    .iter()
+                    .find(|proxy| proxy.proxy_type == ProxyType::HaProxy)
+                {
+                    return Err(CerberusError::validation(format!(
+                        "Service {} uses forward auth, which HAProxy proxy {} cannot do; use basic auth",
+                        service.name, proxy.name
+                    )));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate that the containers can run hardened
+///
+/// A non-root proxy cannot bind ports below 1024, and Nomad jobs are not
+/// rendered with the Compose security options.
+/// Validate `[security.profiles]`
+///
+/// `docker stack deploy` ignores `security_opt`, so the profiles only
+/// confine containers started with Compose.
+fn validate_profiles(config: &Config) -> Result<()> {
+    let profiles = &config.security.profiles;
+    if config.project.target != DeployTarget::Compose
+        || config
+            .outputs
+            .keys()
+            .any(|target| *target != DeployTarget::Compose)
+    {
+        return Err(CerberusError::validation(
+            "security.profiles is only supported by the compose target",
+        ));
+    }
+    let identifier = |value: &str| {
+        !value.is_empty()
+            && value
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    };
+    if let Some(syscall) = profiles
+        .allow_syscalls
+        .iter()
+        .find(|syscall| !identifier(syscall))
+    {
+        return Err(CerberusError::validation(format!(
+            "security.profiles.allow_syscalls entry '{syscall}' is not a system call name"
+        )));
+    }
+    if let Some(selinux_type) = profiles
+        .selinux_type
+        .as_deref()
+        .filter(|selinux_type| !identifier(selinux_type))
+    {
+        return Err(CerberusError::validation(format!(
+            "security.profiles.selinux_type '{selinux_type}' must be an SELinux type such as cerberus_proxy_t"
+        )));
+    }
+    Ok(())
+}
+
+fn validate_hardening(config: &Config) -> Result<()> {
+    let hardening = &config.security.hardening;
+    if config.project.target == DeployTarget::Nomad
+        || config.outputs.contains_key(&DeployTarget::Nomad)
+    {
+        return Err(CerberusError::validation(
+            "security.hardening is only supported by the compose and swarm targets",
+        ));
+    }
+    for path in &hardening.tmpfs {
+        if !path.starts_with('/') || path.contains([':', ',', ' ']) {
+            return Err(CerberusError::validation(format!(
+                "security.hardening.tmpfs entry '{path}' must be an absolute container path"
+            )));
+        }
+    }
+    if parse_size_bytes(&hardening.tmpfs_size).is_none_or(|bytes| bytes == 0) {
+        return Err(CerberusError::validation(format!(
+            "security.hardening.tmpfs_size '{}' must be a size such as 64m",
+            hardening.tmpfs_size
+        )));
+    }
+    for proxy in &config.proxies {
+        if proxy.internal_port < 1024 {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} listens on privileged port {}, which a hardened container cannot \
+                 bind; set internal_port to 1024 or above (e.g. {})",
+                proxy.name,
+                proxy.internal_port,
+                proxy.internal_port as u32 + 8000
+            )));
+        }
+        if let Some(stream) = config
+            .streams(proxy)
+            .into_iter()
+            .find(|stream| stream.listen_port < 1024)
+        {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} forwards {} on privileged port {}, which a hardened container \
+                 cannot bind; use a listen_port of 1024 or above",
+                proxy.name, stream.name, stream.listen_port
+            )));
+        }
+        if let Some(acme) = config.acme(proxy).filter(|acme| acme.listen_port < 1024) {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} serves HTTPS on privileged port {}, which a hardened container \
+                 cannot bind; set tls.acme.listen_port to 1024 or above",
+                proxy.name, acme.listen_port
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Validate the per-path protection of a route
+fn validate_route_paths(proxy: &ProxyConfig, route: &RouteConfig) -> Result<()> {
+    if route.paths.is_empty() {
+        return Ok(());
+    }
+
+    let owner = format!("Route {} on proxy {}", route.domain, proxy.name);
+    // Anubis sits between layer 1 and layer 2, so only the edge can pick paths
+    if proxy.layer.unwrap_or(1) != 1 {
+        return Err(CerberusError::validation(format!(
+            "{owner} sets paths, which only apply to layer 1 proxies"
+        )));
+    }
+    if !matches!(proxy.proxy_type, ProxyType::Nginx | ProxyType::Caddy) {
+        return Err(CerberusError::validation(format!(
+            "{owner} sets paths, which are supported on nginx and caddy proxies"
+        )));
+    }
+    if route.upstream.contains("anubis") {
+        return Err(CerberusError::validation(format!(
+            "{owner} sets paths, so upstream must be the next layer; protected paths go to Anubis"
+        )));
+    }
+
+    for (index, path) in route.paths.iter().enumerate() {
+        if !path.prefix.starts_with('/') || path.prefix.contains(['*', ' ']) {
+            return Err(CerberusError::validation(format!(
+                "{owner} path prefix {} must start with / and contain no wildcards",
+                path.prefix
+            )));
+        }
+        if route.paths[..index]
+            .iter()
+            .any(|other| other.prefix == path.prefix)
+        {
+            return Err(CerberusError::validation(format!(
+                "{owner} lists path prefix {} more than once",
+                path.prefix
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate the `platform`, `platforms` and `stop_grace_period` of a container
+fn validate_container_options(
+    owner: &str,
+    platform: Option<&str>,
+    platforms: &[String],
+    stop_grace_period: Option<&str>,
+) -> Result<()> {
+    for (key, value) in platform
+        .map(|platform| ("platform", platform))
+        .into_iter()
+        .chain(
+            platforms
+                .iter()
+                .map(|platform| ("platforms", platform.as_str())),
+        )
+    {
+        let parts: Vec<&str> = value.split('/').collect();
+        if !(2..=3).contains(&parts.len())
+            || parts.iter().any(|part| {
+                part.is_empty()
+                    || !part
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            })
+        {
+            return Err(CerberusError::validation(format!(
+                "{owner} {key} '{value}' must be os/arch[/variant], e.g. linux/amd64"
+            )));
+        }
+    }
+    if let Some(duplicate) = platforms
+        .iter()
+        .enumerate()
+        .find(|(i, platform)| platforms[..*i].contains(platform))
+        .map(|(_, platform)| platform)
+    {
+        return Err(CerberusError::validation(format!(
+            "{owner} lists platform '{duplicate}' twice in platforms"
+        )));
+    }
+    if let Some(period) = stop_grace_period
+        && parse_duration_secs(period).is_none()
+    {
+        return Err(CerberusError::validation(format!(
+            "{owner} stop_grace_period '{period}' is not a valid duration"
+        )));
+    }
+    Ok(())
+}
+
+/// Validate an HTTP upstream: `http(s)://host[:port][/path]` or a bare host
+fn validate_upstream_url(owner: &str, upstream: &str) -> Result<()> {
+    if let Some((scheme, _)) = upstream.split_once("://")
+        && !scheme.eq_ignore_ascii_case("http")
+        && !scheme.eq_ignore_ascii_case("https")
+    {
+        return Err(CerberusError::validation(format!(
+            "{owner} upstream '{upstream}' uses unsupported scheme '{scheme}'; use http:// or https://"
+        )));
+    }
+    if let Err(e) = parse_upstream(upstream) {
+        return Err(CerberusError::validation(format!(
+            "{owner} upstream '{upstream}' is not a valid URL: {e}"
+        )));
+    }
+    let address = upstream
+        .split_once("://")
+        .map_or(upstream, |(_, rest)| rest);
+    if address
+        .split(['/', '?', '#'])
+        .next()
+        .is_some_and(|authority| authority.ends_with(':'))
+    {
+        return Err(CerberusError::validation(format!(
+            "{owner} upstream '{upstream}' is missing the port after ':'"
+        )));
+    }
+    Ok(())
+}
+
+/// Validate the layer-4 settings of a service or route
+///
+/// `http_settings` lists the settings only HTTP routing honours, and
+/// whether they are set.
+fn validate_stream(
+    owner: &str,
+    protocol: Protocol,
+    listen_port: Option<u16>,
+    upstream: &str,
+    http_settings: &[(&str, bool)],
+) -> Result<()> {
+    if !protocol.is_stream() {
+        if listen_port.is_some() {
+            return Err(CerberusError::validation(format!(
+                "{owner} sets listen_port, which only applies to tcp and udp"
+            )));
+        }
+        return Ok(());
+    }
+
+    let protocol = protocol.as_str();
+    if listen_port.is_none_or(|port| port == 0) {
+        return Err(CerberusError::validation(format!(
+            "{owner} uses {protocol} and needs a listen_port greater than 0"
+        )));
+    }
+    if stream_address(upstream).is_none() {
+        return Err(CerberusError::validation(format!(
+            "{owner} upstream {upstream} must be host:port (optionally {protocol}://host:port) for {protocol}"
+        )));
+    }
+    if let Some((setting, _)) = http_settings.iter().find(|(_, set)| *set) {
+        return Err(CerberusError::validation(format!(
+            "{owner} uses {protocol}, which does not support {setting}"
+        )));
+    }
+    Ok(())
+}
+
+/// Validate an `allowed_methods` list
+fn validate_methods(methods: &[String], owner: &str) -> Result<()> {
+    for method in methods {
+        if !HTTP_METHODS.contains(&method.as_str()) {
+            return Err(CerberusError::validation(format!(
+                "{owner} allowed_methods contains unknown method '{method}' (expected one of {})",
+                HTTP_METHODS.join(", ")
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Validate the operations route
+fn validate_ops(config: &Config) -> Result<()> {
+    let ops = &config.ops;
+    let Some(domain) = &ops.domain else {
+        if !ops.users.is_empty() || !ops.allow.is_empty() {
+            return Err(CerberusError::validation(
+                "ops users and allow require ops.domain",
+            ));
+        }
+        return Ok(());
+    };
+
+    if domain.starts_with("*.") || !is_valid_host(domain) {
+        return Err(CerberusError::validation(format!(
+            "ops.domain '{domain}' must be a host name"
+        )));
+    }
+    if let Some(service) = config.services.iter().find(|service| {
+        service
+            .hosts()
+            .iter()
+            .any(|host| host.eq_ignore_ascii_case(domain))
+    }) {
+        return Err(CerberusError::validation(format!(
+            "ops.domain {domain} is already served by service {}",
+            service.name
+        )));
+    }
+
+    // The dashboards are never reachable without credentials
+    if ops.users.is_empty() {
+        return Err(CerberusError::validation(
+            "ops.users must list at least one user",
+        ));
+    }
+    for user in &ops.users {
+        if !is_valid_htpasswd_user(user) {
+            let name = user.split_once(':').map_or(user.as_str(), |(name, _)| name);
+            return Err(CerberusError::validation(format!(
+                "ops user '{name}' must be name:bcrypt-hash (e.g. from htpasswd -nB)"
+            )));
+        }
+    }
+
+    for entry in &ops.allow {
+        if !is_valid_cidr(entry) {
+            return Err(CerberusError::validation(format!(
+                "ops allow entry '{entry}' must be an address or CIDR range"
+            )));
+        }
+    }
+
+    if config.ops_dashboards().is_empty() {
+        return Err(CerberusError::validation(
+            "ops.domain has no dashboards to serve; enable stats on an HAProxy proxy or add a Traefik proxy",
+        ));
+    }
+    if !config.proxies.iter().any(|proxy| {
+        proxy.external_port.is_some()
+            && match proxy.proxy_type {
+                ProxyType::Nginx => proxy.layer.unwrap_or(1) == 1,
+                ProxyType::Caddy | ProxyType::HaProxy | ProxyType::Traefik => true,
+                ProxyType::Varnish => false,
+            }
+    }) {
+        return Err(CerberusError::validation(
+            "ops.domain requires an edge proxy with external_port to serve it",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Validate the status codes and sources of `[error_pages]`
+fn validate_error_pages(config: &Config) -> Result<()> {
+    for (status, page) in &config.error_pages.pages {
+        let code = status
+            .parse::<u16>()
+            .ok()
+            .filter(|code| (400..=599).contains(code))
+            .ok_or_else(|| {
+                CerberusError::validation(format!(
+                    "error_pages.pages key '{status}' must be an HTTP status code from 400 to 599"
+                ))
+            })?;
+        match (&page.file, &page.html) {
+            (Some(_), Some(_)) => {
+                return Err(CerberusError::validation(format!(
+                    "error_pages.pages.{status} sets both file and html; keep one"
+                )));
+            }
+            (None, None) => {
+                return Err(CerberusError::validation(format!(
+                    "error_pages.pages.{status} needs a file or html"
+                )));
+            }
+            (Some(file), None) if file.trim().is_empty() => {
+                return Err(CerberusError::validation(format!(
+                    "error_pages.pages.{status} file cannot be empty"
+                )));
+            }
+            _ => {}
+        }
+        if !HAPROXY_ERROR_STATUSES.contains(&code)
+            && let Some(proxy) = config
+                .proxies
+                .iter()
+                .find(|proxy| proxy.proxy_type == ProxyType::HaProxy)
+        {
+            return Err(CerberusError::validation(format!(
+                "error_pages.pages.{status} cannot be served by haproxy proxy {}; \
+                 HAProxy errorfile supports {}",
+                proxy.name,
+                HAPROXY_ERROR_STATUSES
+                    .iter()
+                    .map(u16::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+    }
+    if config.error_pages.is_enabled()
+        && config.project.target == DeployTarget::Nomad
+        && let Some(proxy) = config
+            .proxies
+            .iter()
+            .find(|proxy| proxy.proxy_type == ProxyType::Traefik)
+    {
+        return Err(CerberusError::validation(format!(
+            "error_pages cannot be served by traefik proxy {} on the nomad target; \
+             Traefik fetches them from the error-pages container of the compose and swarm targets",
+            proxy.name
+        )));
+    }
+    Ok(())
+}
+
+/// Validate the sources and fields of `[content]`
+fn validate_content(config: &Config) -> Result<()> {
+    if let Some(robots) = &config.content.robots {
+        match &robots.file {
+            Some(_) if !robots.agents.is_empty() || !robots.sitemaps.is_empty() => {
+                return Err(CerberusError::validation(
+                    "content.robots sets both file and agents or sitemaps; keep one",
+                ));
+            }
+            Some(file) if file.trim().is_empty() => {
+                return Err(CerberusError::validation(
+                    "content.robots file cannot be empty",
+                ));
+            }
+            None if robots.agents.is_empty() && robots.sitemaps.is_empty() => {
+                return Err(CerberusError::validation(
+                    "content.robots needs a file, agents or sitemaps",
+                ));
+            }
+            _ => {}
+        }
+        for agent in &robots.agents {
+            if agent.user_agent.trim().is_empty() {
+                return Err(CerberusError::validation(
+                    "content.robots.agents user_agent cannot be empty",
+                ));
+            }
+            if let Some(path) =
+                agent.allow.iter().chain(&agent.disallow).find(|path| {
+                    !path.is_empty() && !path.starts_with('/') && !path.starts_with('*')
+                })
+            {
+                return Err(CerberusError::validation(format!(
+                    "content.robots.agents path '{path}' for {} must start with / or *",
+                    agent.user_agent
+                )));
+            }
+        }
+        if let Some(sitemap) = robots
+            .sitemaps
+            .iter()
+            .find(|sitemap| !sitemap.starts_with("http://") && !sitemap.starts_with("https://"))
+        {
+            return Err(CerberusError::validation(format!(
+                "content.robots sitemap '{sitemap}' must be an absolute http:// or https:// URL"
+            )));
+        }
+    }
+
+    if let Some(security_txt) = &config.content.security_txt {
+        if security_txt.contact.is_empty() {
+            return Err(CerberusError::validation(
+                "content.security_txt needs at least one contact",
+            ));
+        }
+        if let Some(contact) = security_txt.contact.iter().find(|contact| {
+            !["mailto:", "tel:", "https://"]
+                .iter()
+                .any(|scheme| contact.starts_with(scheme))
+        }) {
+            return Err(CerberusError::validation(format!(
+                "content.security_txt contact '{contact}' must be a mailto:, tel: or https:// URI"
+            )));
+        }
+        let expires = security_txt.expires.trim();
+        if !expires
+            .split_once('T')
+            .is_some_and(|(date, time)| parse_date(date).is_some() && !time.is_empty())
+        {
+            return Err(CerberusError::validation(format!(
+                "content.security_txt expires '{expires}' must be an RFC 3339 timestamp \
+                 (e.g. 2026-12-31T23:59:59Z)"
+            )));
+        }
+        if let Some(url) = security_txt
+            .acknowledgments
+            .iter()
+            .chain(&security_txt.canonical)
+            .chain(&security_txt.policy)
+            .chain(&security_txt.hiring)
+            .find(|url| !url.starts_with("https://"))
+        {
+            return Err(CerberusError::validation(format!(
+                "content.security_txt URL '{url}' must use https://"
+            )));
+        }
+    }
+
+    // Traefik cannot serve files itself
+    if config.content.is_enabled()
+        && let Some(proxy) = config
+            .proxies
+            .iter()
+            .find(|proxy| proxy.proxy_type == ProxyType::Traefik && proxy.layer.unwrap_or(1) == 1)
+    {
+        return Err(CerberusError::validation(format!(
+            "content cannot be served by traefik proxy {} at layer 1; \
+             use an nginx, caddy or haproxy proxy there",
+            proxy.name
+        )));
+    }
+    Ok(())
+}
+
+/// Whether a service domain is a host name, optionally `*.` followed by one
+/// Whether an entry is an IP address or a CIDR range
+fn is_valid_cidr(entry: &str) -> bool {
+    let (address, mask) = match entry.split_once('/') {
+        Some((address, mask)) => (address, Some(mask)),
+        None => (entry, None),
+    };
+    address.parse::<std::net::IpAddr>().is_ok_and(|address| {
+        let bits = if address.is_ipv4() { 32 } else { 128 };
+        mask.is_none_or(|mask| mask.parse::<u8>().is_ok_and(|mask| mask <= bits))
+    })
+}
+
+fn is_valid_host(host: &str) -> bool {
+    let name = host.strip_prefix("*.").unwrap_or(host);
+    !name.is_empty()
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+/// Whether a domain pattern matches a host
+///
+/// A leading `*.` matches exactly one label, as in Caddy and the generated
+/// Nginx, HAProxy, Traefik and Varnish matchers.
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    let (pattern, host) = (pattern.to_lowercase(), host.to_lowercase());
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => host
+            .split_once('.')
+            .is_some_and(|(label, rest)| label != "*" && rest == suffix),
+        None => pattern == host,
+    }
+}
+
+/// Check that no two services answer on the same host
+///
+/// Proxies order exact and wildcard matches differently, so an exact domain
+/// covered by another service's wildcard is rejected too.
+fn validate_service_domains(services: &[ServiceConfig]) -> Result<()> {
+    let hosts: Vec<(&str, &str)> = services
+        .iter()
+        .filter(|service| !service.protocol.is_stream())
+        .flat_map(|service| {
+            service
+                .hosts()
+                .into_iter()
+                .map(move |host| (service.name.as_str(), host))
+        })
+        .collect();
+
+    for (index, (service, host)) in hosts.iter().enumerate() {
+        for (other_service, other) in &hosts[index + 1..] {
+            if service == other_service {
+                continue;
+            }
+            if host.eq_ignore_ascii_case(other) {
+                return Err(CerberusError::validation(format!(
+                    "Domain {host} is served by both services {service} and {other_service}"
+                )));
+            }
+            if let Some((wildcard, wildcard_service, exact, exact_service)) =
+                if host_matches(host, other) {
+                    Some((host, service, other, other_service))
+                } else if host_matches(other, host) {
+                    Some((other, other_service, host, service))
+                } else {
+                    None
+                }
+            {
+                return Err(CerberusError::validation(format!(
+                    "Domain {exact} of service {exact_service} overlaps wildcard {wildcard} of service {wildcard_service}"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse a duration such as "30s", "10m", "1h" or "7d" into seconds
+///
+/// A bare number is interpreted as seconds.
+pub fn parse_duration_secs(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().ok()?;
+
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return None,
+    };
+
+    number.checked_mul(multiplier)
+}
+
+/// Parse a size such as "512k", "10m" or "1G" into bytes
+///
+/// Units are binary and case-insensitive, with an optional trailing "b"
+/// ("100MB"); a bare number is interpreted as bytes.
+pub fn parse_size_bytes(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().ok()?;
+
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        _ => return None,
+    };
+
+    number.checked_mul(multiplier)
+}
+
+/// Logging configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LoggingConfig {
+    /// Log level
+    #[serde(default = "default_log_level")]
+    pub level: String,
+
+    /// Log format
+    #[serde(default = "default_log_format")]
+    pub format: String,
+
+    /// Log output destination
+    #[serde(default = "default_log_output")]
+    pub output: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            format: default_log_format(),
+            output: default_log_output(),
+        }
+    }
+}
+
+fn default_log_level() -> String {
+    "INFO".to_string()
+}
+
+fn default_log_format() -> String {
+    "json".to_string()
+}
+
+fn default_log_output() -> String {
+    "/var/log/cerberus.log".to_string()
+}
+
+impl Config {
+    /// Whether any secret or service environment value is read from Vault
+    pub fn uses_vault(&self) -> bool {
+        self.secrets
+            .values()
+            .any(|secret| matches!(secret, SecretConfig::Vault { .. }))
+            || self.services.iter().any(|service| {
+                service
+                    .environment
+                    .values()
+                    .any(|value| value.starts_with(VAULT_SCHEME))
+            })
+    }
+
+    /// Access log files a proxy writes to the shared log directory
+    ///
+    /// A layer-1 Nginx keeps the image's `access.log`, inner Nginx layers
+    /// log per service, Caddy and Traefik per proxy. HAProxy logs to stdout
+    /// and Varnish writes no access log, so they have no files.
+    pub fn access_log_files(&self, proxy: &ProxyConfig) -> Vec<String> {
+        match proxy.proxy_type {
+            ProxyType::Nginx if proxy.layer.unwrap_or(1) == 1 => vec!["access.log".to_string()],
+            ProxyType::Nginx => self
+                .services
+                .iter()
+                .map(|service| format!("{}_access.log", service.name))
+                .collect(),
+            ProxyType::Caddy | ProxyType::Traefik => vec![format!("{}_access.log", proxy.name)],
+            ProxyType::HaProxy | ProxyType::Varnish => Vec::new(),
+        }
+    }
+
+    /// Varnish layer directly behind a proxy, if any
+    pub fn cache_layer(&self, proxy: &ProxyConfig) -> Option<&ProxyConfig> {
+        if proxy.proxy_type == ProxyType::Varnish {
+            return None;
+        }
+
+        let next_layer = proxy.layer.unwrap_or(1) + 1;
+        self.proxies.iter().find(|other| {
+            other.proxy_type == ProxyType::Varnish && other.layer.unwrap_or(1) == next_layer
+        })
+    }
+
+    /// Whether a proxy stores `[services.cache]` responses itself
+    ///
+    /// Caddy and inner Nginx layers render the services; a layer-1 Nginx only
+    /// routes domains, and a Varnish layer behind a proxy caches for it.
+    pub fn edge_caches(&self, proxy: &ProxyConfig) -> bool {
+        let renders_services = match proxy.proxy_type {
+            ProxyType::Caddy => true,
+            ProxyType::Nginx => proxy.layer.unwrap_or(1) != 1,
+            ProxyType::HaProxy | ProxyType::Traefik | ProxyType::Varnish => false,
+        };
+        renders_services
+            && self.cache_layer(proxy).is_none()
+            && self.services.iter().any(|service| {
+                !service.protocol.is_stream()
+                    && service.cache.as_ref().is_some_and(|cache| cache.enabled)
+            })
+    }
+
+    /// Whether a proxy image is built from its Dockerfile: for the WAF, for
+    /// the cache handler Caddy lacks by default, for compression modules, or
+    /// for Caddy's ACME DNS provider
+    pub fn builds_proxy_image(&self, proxy: &ProxyConfig) -> bool {
+        self.waf.applies_to(proxy)
+            || (proxy.proxy_type == ProxyType::Caddy && self.edge_caches(proxy))
+            || !self.compression_modules(proxy).is_empty()
+            || (proxy.proxy_type == ProxyType::Caddy && self.acme(proxy).is_some())
+    }
+
+    /// `[tls.acme]` settings of an edge proxy that obtains certificates
+    pub fn acme(&self, proxy: &ProxyConfig) -> Option<&AcmeConfig> {
+        self.tls.acme.as_ref().filter(|_| {
+            proxy.external_port.is_some()
+                && matches!(proxy.proxy_type, ProxyType::Caddy | ProxyType::Traefik)
+        })
+    }
+
+    /// Compression algorithms a proxy needs modules beyond its image for
+    ///
+    /// Caddy and inner Nginx layers compress the services' responses. Nginx
+    /// has neither brotli nor zstd built in; Caddy lacks a brotli encoder.
+    pub fn compression_modules(&self, proxy: &ProxyConfig) -> Vec<CompressionAlgorithm> {
+        let missing: &[CompressionAlgorithm] = match proxy.proxy_type {
+            ProxyType::Caddy => &[CompressionAlgorithm::Brotli],
+            ProxyType::Nginx if proxy.layer.unwrap_or(1) != 1 => {
+                &[CompressionAlgorithm::Brotli, CompressionAlgorithm::Zstd]
+            }
+            _ => &[],
+        };
+        missing
+            .iter()
+            .copied()
+            .filter(|&algorithm| {
+                self.services.iter().any(|service| {
+                    !service.protocol.is_stream() && service.compression.uses(algorithm)
+                })
+            })
+            .collect()
+    }
+
+    /// Statistics settings of a proxy whose port is published on the host
+    ///
+    /// With `[ops]` the edge proxies route to the statistics page instead.
+    pub fn published_stats<'p>(&self, proxy: &'p ProxyConfig) -> Option<&'p StatsConfig> {
+        proxy.enabled_stats().filter(|_| self.ops.domain.is_none())
+    }
+
+    /// Dashboards served through `[ops]`: the statistics page of every
+    /// HAProxy with stats enabled and the dashboard of every Traefik
+    pub fn ops_dashboards(&self) -> Vec<OpsDashboard> {
+        if self.ops.domain.is_none() {
+            return Vec::new();
+        }
+        self.proxies
+            .iter()
+            .filter_map(|proxy| match proxy.proxy_type {
+                ProxyType::HaProxy => proxy.enabled_stats().map(|stats| OpsDashboard {
+                    proxy: proxy.name.clone(),
+                    address: format!("{}:{}", proxy.name, stats.port),
+                    path: "/stats",
+                }),
+                ProxyType::Traefik => Some(OpsDashboard {
+                    proxy: proxy.name.clone(),
+                    address: format!("{}:8080", proxy.name),
+                    path: "/dashboard/",
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Pool an `upstream` names, if it names one rather than a URL
+    pub fn upstream_pool(&self, upstream: &str) -> Option<&UpstreamPoolConfig> {
+        self.upstreams.iter().find(|pool| pool.name == upstream)
+    }
+
+    /// Upstream a proxy sends requests no service or route matches
+    ///
+    /// A layer-1 proxy without `default_upstream` sends them through Anubis
+    /// when it is enabled.
+    pub fn default_upstream(&self, proxy: &ProxyConfig) -> Option<String> {
+        proxy.default_upstream.clone().or_else(|| {
+            (self.anubis.enabled
+                && proxy.layer.unwrap_or(1) == 1
+                && proxy.proxy_type != ProxyType::Varnish)
+                .then(|| self.anubis.url())
+        })
+    }
+
+    /// Whether a layer-1 proxy passes requests to Anubis
+    ///
+    /// Layer-1 Nginx sends challenges there itself; other proxies do through
+    /// their default upstream or the protected paths of their routes.
+    pub fn sends_to_anubis(&self, proxy: &ProxyConfig) -> bool {
+        if !self.anubis.enabled || proxy.layer.unwrap_or(1) != 1 {
+            return false;
+        }
+        let containers = self.anubis.containers();
+        proxy.proxy_type == ProxyType::Nginx
+            || self.default_upstream(proxy).is_some_and(|upstream| {
+                let host = upstream_host(&upstream);
+                containers.iter().any(|container| container.name == host)
+            })
+            || proxy.routes.iter().any(|route| {
+                route.paths.iter().any(|path| path.protect)
+                    || (!route.paths.is_empty()
+                        && route.protected_by_default()
+                        && !route.paths.iter().any(|path| path.prefix == "/"))
+            })
+    }
+
+    /// Whether an Nginx proxy replaces the image's main `nginx.conf`
+    ///
+    /// Needed for `stream {}` blocks, for tuned worker settings, and for
+    /// loading compression or OpenTelemetry modules, none of which can be set
+    /// from `conf.d`.
+    pub fn generates_nginx_main(&self, proxy: &ProxyConfig) -> bool {
+        proxy.proxy_type == ProxyType::Nginx
+            && (!self.streams(proxy).is_empty()
+                || self.tracing(proxy).is_some()
+                || proxy
+                    .tuning
+                    .as_ref()
+                    .is_some_and(TuningConfig::tunes_nginx_main)
+                || !self.compression_modules(proxy).is_empty())
+    }
+
+    /// Whether a proxy serves the `[content]` files
+    pub fn serves_content(&self, proxy: &ProxyConfig) -> bool {
+        self.content.is_enabled() && proxy.layer.unwrap_or(1) == 1
+    }
+
+    /// `[observability.tracing]` settings of a proxy that emits spans
+    pub fn tracing(&self, proxy: &ProxyConfig) -> Option<&TracingConfig> {
+        self.observability
+            .tracing
+            .as_ref()
+            .filter(|tracing| tracing.applies_to(proxy))
+    }
+
+    /// Layer-4 streams a proxy forwards
+    ///
+    /// A proxy publishing an `external_port` receives the `tcp` and `udp`
+    /// services; every proxy forwards its own `tcp` and `udp` routes.
+    pub fn streams(&self, proxy: &ProxyConfig) -> Vec<StreamRoute> {
+        let services = self
+            .services
+            .iter()
+            .filter(|_| proxy.external_port.is_some())
+            .map(|service| {
+                (
+                    &service.name,
+                    service.protocol,
+                    service.listen_port,
+                    &service.upstream,
+                    service.hosts(),
+                )
+            });
+        let routes = proxy.routes.iter().map(|route| {
+            (
+                &route.domain,
+                route.protocol,
+                route.listen_port,
+                &route.upstream,
+                vec![route.domain.as_str()],
+            )
+        });
+
+        services
+            .chain(routes)
+            .filter(|(_, protocol, _, _, _)| protocol.is_stream())
+            .filter_map(|(name, protocol, listen_port, upstream, hosts)| {
+                Some(StreamRoute {
+                    id: format!("stream_{}", name.replace(['.', '-', '*'], "_")),
+                    name: name.clone(),
+                    protocol,
+                    listen_port: listen_port?,
+                    address: stream_address(upstream)?,
+                    server_names: hosts.into_iter().map(str::to_string).collect(),
+                })
+            })
+            .collect()
+    }
+
+    /// Check that every layer-4 stream has a proxy able to forward it on a free port
+    fn validate_streams(&self) -> Result<()> {
+        if let Some(service) = self
+            .services
+            .iter()
+            .find(|service| service.protocol.is_stream())
+        {
+            let edges: Vec<&str> = self
+                .proxies
+                .iter()
+                .filter(|proxy| proxy.external_port.is_some())
+                .map(|proxy| proxy.name.as_str())
+                .collect();
+            if edges.len() != 1 {
+                return Err(CerberusError::validation(format!(
+                    "Service {} uses {}, which needs exactly one proxy with an external_port to receive it (found {})",
+                    service.name,
+                    service.protocol.as_str(),
+                    if edges.is_empty() {
+                        "none".to_string()
+                    } else {
+                        edges.join(", ")
+                    }
+                )));
+            }
+        }
+
+        let mut listening: Vec<(u16, Protocol, String, Vec<String>)> = Vec::new();
+        for proxy in &self.proxies {
+            for stream in self.streams(proxy) {
+                let protocol = stream.protocol.as_str();
+                let supported = match proxy.proxy_type {
+                    ProxyType::Nginx | ProxyType::Traefik => true,
+                    ProxyType::HaProxy => stream.protocol == Protocol::Tcp,
+                    _ => false,
+                };
+                if !supported {
+                    return Err(CerberusError::validation(format!(
+                        "Proxy {} is a {} proxy and cannot forward {protocol} stream {}; \
+                         use nginx or traefik (or haproxy for tcp)",
+                        proxy.name,
+                        proxy.proxy_type.as_str(),
+                        stream.name
+                    )));
+                }
+                if stream.protocol == Protocol::Tcp
+                    && (stream.listen_port == proxy.internal_port
+                        || proxy.external_port == Some(stream.listen_port))
+                {
+                    return Err(CerberusError::validation(format!(
+                        "{protocol} stream {} listens on port {}, which proxy {} already uses for HTTP",
+                        stream.name, stream.listen_port, proxy.name
+                    )));
+                }
+                // tcp streams sharing a port are told apart by TLS SNI
+                for (_, _, other, other_names) in
+                    listening.iter().filter(|(port, other_protocol, _, _)| {
+                        *port == stream.listen_port && *other_protocol == stream.protocol
+                    })
+                {
+                    if stream.protocol == Protocol::Udp {
+                        return Err(CerberusError::validation(format!(
+                            "{protocol} streams {other} and {} both listen on port {}",
+                            stream.name, stream.listen_port
+                        )));
+                    }
+                    if let Some(name) = stream.server_names.iter().find(|name| {
+                        other_names.iter().any(|other_name| {
+                            host_matches(name, other_name) || host_matches(other_name, name)
+                        })
+                    }) {
+                        return Err(CerberusError::validation(format!(
+                            "{protocol} streams {other} and {} share port {} but both match server name {name}",
+                            stream.name, stream.listen_port
+                        )));
+                    }
+                }
+                listening.push((
+                    stream.listen_port,
+                    stream.protocol,
+                    stream.name,
+                    stream.server_names,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a network is declared with `internal = true`
+    pub fn is_internal_network(&self, name: &str) -> bool {
+        self.networks
+            .get(name)
+            .is_some_and(|network| network.internal)
+    }
+
+    /// Load configuration from a TOML file
+    ///
+    /// # Arguments
+    /// * `path` - Path to the TOML configuration file
+    ///
+    /// # Errors
+    /// Returns error if file cannot be read or parsed
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| CerberusError::io(path, e))?;
+
+        let merged = include::merged(path, &content)?;
+        let table = extends::based(path, &content, merged)?;
+        let (deprecations, table) = match table {
+            Some(mut table) => {
+                let deprecations = deprecation::deprecated_keys(&table)?;
+                deprecation::upgrade(&mut table);
+                (deprecations, Some(table))
+            }
+            None => {
+                let mut table: toml::Table =
+                    toml::from_str(&content).map_err(|e| CerberusError::toml_parse(path, e))?;
+                let deprecations = deprecation::deprecated_keys(&table)?;
+                // Without deprecated keys the content is read directly, so
+                // parse errors point into the file
+                if deprecations.is_empty() {
+                    (deprecations, None)
+                } else {
+                    deprecation::upgrade(&mut table);
+                    (deprecations, Some(table))
+                }
+            }
+        };
+        let mut config: Config = match table {
+            Some(table) => table
+                .try_into()
+                .map_err(|e| CerberusError::toml_parse(path, e))?,
+            None => toml::from_str(&content).map_err(|e| CerberusError::toml_parse(path, e))?,
+        };
+        config.deprecations = deprecations;
+        config.normalize_upstreams();
+        config.resolve_output_paths(path);
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Resolve relative `[outputs]` paths against the configuration file
+    ///
+    /// Empty paths are left for `validate` to reject.
+    fn resolve_output_paths(&mut self, path: &Path) {
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => return,
+        };
+        for output in self.outputs.values_mut() {
+            if !output.path.trim().is_empty() && Path::new(&output.path).is_relative() {
+                output.path = dir.join(&output.path).display().to_string();
+            }
+        }
+    }
+
+    /// Lowercase the scheme and host of every HTTP upstream
+    ///
+    /// Generators compare upstreams as strings, so `HTTP://App:3000` and
+    /// `http://app:3000` must end up the same.
+    pub fn normalize_upstreams(&mut self) {
+        for service in &mut self.services {
+            service.upstream = normalize_upstream(&service.upstream);
+            if let Some(candidate) = &mut service.candidate_upstream {
+                *candidate = normalize_upstream(candidate);
+            }
+            for backup in &mut service.backup_upstreams {
+                *backup = normalize_upstream(backup);
+            }
+        }
+        for proxy in &mut self.proxies {
+            if let Some(upstream) = &mut proxy.default_upstream {
+                *upstream = normalize_upstream(upstream);
+            }
+            for route in &mut proxy.routes {
+                route.upstream = normalize_upstream(&route.upstream);
+            }
+        }
+        for server in self.upstreams.iter_mut().flat_map(|pool| &mut pool.servers) {
+            server.url = normalize_upstream(&server.url);
+        }
+    }
+
+    /// Validate the configuration
+    ///
+    /// Performs semantic validation beyond what's possible with serde
+    ///
+    /// # Errors
+    /// Returns error if configuration is invalid
+    pub fn validate(&self) -> Result<()> {
+        if let Some(version) = self.schema_version {
+            deprecation::validate_schema_version(version)?;
+        }
+
+        // Validate project name is not empty
+        if self.project.name.trim().is_empty() {
+            return Err(CerberusError::validation("Project name cannot be empty"));
+        }
+        if let Some(timezone) = &self.project.timezone {
+            validate_timezone(timezone)?;
+        }
+
+        // Validate proxy configurations
+        for (index, proxy) in self.proxies.iter().enumerate() {
+            if proxy.name.trim().is_empty() {
+                return Err(CerberusError::validation(format!(
+                    "Proxy {index} name cannot be empty"
+                )));
+            }
+
+            validate_proxy(proxy)?;
+
+            if !self.anubis.enabled
+                && let Some(route) = proxy
+                    .routes
+                    .iter()
+                    .find(|route| route.paths.iter().any(|path| path.protect))
+            {
+                return Err(CerberusError::validation(format!(
+                    "Route {} on proxy {} protects paths with Anubis, but Anubis is not enabled",
+                    route.domain, proxy.name
+                )));
+            }
+
+            if proxy.instance_port_strategy != InstancePortStrategy::Offset
+                && (self.project.target != DeployTarget::Compose
+                    || self
+                        .outputs
+                        .keys()
+                        .any(|target| *target != DeployTarget::Compose))
+            {
+                return Err(CerberusError::validation(format!(
+                    "Proxy {} sets instance_port_strategy = \"{}\", which only the compose target supports; \
+                     Swarm's routing mesh and Nomad publish the port of every replica themselves",
+                    proxy.name,
+                    proxy.instance_port_strategy.as_str()
+                )));
+            }
+            if let Some(balancer) = proxy.instance_balancer(self.project.scaling)
+                && (self.proxies.iter().any(|other| other.name == balancer)
+                    || self.services.iter().any(|service| service.name == balancer))
+            {
+                return Err(CerberusError::validation(format!(
+                    "Proxy {} gets a load balancer named {balancer}, which is already the name of a proxy or service",
+                    proxy.name
+                )));
+            }
+
+            // Compose duplicates containers for instances, so both would multiply
+            if let Some(replicas) = proxy.deploy_replicas()
+                && self.project.scaling
+                && proxy.instances > 1
+                && (matches!(
+                    self.project.target,
+                    DeployTarget::Compose | DeployTarget::Swarm
+                ) || u32::from(proxy.instances) != replicas)
+            {
+                return Err(CerberusError::validation(format!(
+                    "Proxy {} sets both instances = {} and deploy.replicas = {}; use only one to set the number of containers",
+                    proxy.name, proxy.instances, replicas
+                )));
+            }
+        }
+
+        if self.anubis.expose_metrics && self.anubis.metrics_port().is_none() {
+            return Err(CerberusError::validation(format!(
+                "Anubis metrics_bind {} has no port to expose",
+                self.anubis.metrics_bind
+            )));
+        }
+
+        for (name, network) in &self.networks {
+            if network.internal && network.external {
+                return Err(CerberusError::validation(format!(
+                    "Network {name} cannot be both external and internal"
+                )));
+            }
+        }
+
+        for (name, secret) in &self.secrets {
+            // Locally resolved secrets are written to a file named after them
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+                || name.starts_with('.')
+            {
+                return Err(CerberusError::validation(format!(
+                    "Secret name '{name}' may only contain letters, digits, '.', '_' and '-'"
+                )));
+            }
+            match secret {
+                SecretConfig::Exec { command } if command.trim().is_empty() => {
+                    return Err(CerberusError::validation(format!(
+                        "Secret {name} command cannot be empty"
+                    )));
+                }
+                SecretConfig::Encrypted { file, .. } if file.trim().is_empty() => {
+                    return Err(CerberusError::validation(format!(
+                        "Secret {name} file cannot be empty"
+                    )));
+                }
+                SecretConfig::Vault { vault } => match VaultRef::parse(vault) {
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        return Err(CerberusError::validation(format!(
+                            "Secret {name} vault must be a {VAULT_SCHEME}<path>#<key> reference"
+                        )));
+                    }
+                    Err(e) => {
+                        return Err(CerberusError::validation(format!("Secret {name}: {e}")));
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        // Only layer 1 may touch the host network stack once a tier is internal
+        for proxy in &self.proxies {
+            let Some(port) = proxy.external_port else {
+                continue;
+            };
+            let networks = proxy.network_names();
+            let internal: Vec<&str> = networks
+                .iter()
+                .copied()
+                .filter(|name| self.is_internal_network(name))
+                .collect();
+            if internal.len() == networks.len() {
+                return Err(CerberusError::validation(format!(
+                    "Proxy {} publishes port {port} but only joins internal networks; \
+                     add a non-internal network or remove external_port",
+                    proxy.name
+                )));
+            }
+            if proxy.layer.unwrap_or(1) > 1 && !internal.is_empty() {
+                return Err(CerberusError::validation(format!(
+                    "Proxy {} is on internal network {} and cannot publish port {port}; \
+                     only layer 1 may publish host ports",
+                    proxy.name, internal[0]
+                )));
+            }
+        }
+
+        validate_ipv6(self)?;
+
+        if self.history.enabled && self.history.keep == 0 {
+            return Err(CerberusError::validation(
+                "history.keep must be greater than 0; set history.enabled = false to stop recording",
+            ));
+        }
+
+        // Validate service configurations
+        for (index, service) in self.services.iter().enumerate() {
+            if service.name.trim().is_empty() {
+                return Err(CerberusError::validation(format!(
+                    "Service {index} name cannot be empty"
+                )));
+            }
+
+            validate_service(service)?;
+            validate_depends_on(service, &self.services)?;
+            validate_service_snippets(self, service)?;
+
+            if !service.ports.is_empty() && self.is_internal_network("back-net") {
+                return Err(CerberusError::validation(format!(
+                    "Service {} publishes ports but back-net is internal; \
+                     use a layer 1 proxy to reach it",
+                    service.name
+                )));
+            }
+
+            if service.candidate_upstream.is_some() && self.deployment.strategy.is_none() {
+                return Err(CerberusError::validation(format!(
+                    "Service {} candidate_upstream requires [deployment.strategy]",
+                    service.name
+                )));
+            }
+        }
+
+        validate_upstream_pools(self)?;
+        validate_service_domains(&self.services)?;
+        validate_ops(self)?;
+        validate_auth(self)?;
+        validate_error_pages(self)?;
+        validate_content(self)?;
+
+        // Varnish speaks plain HTTP to its backends and routes to `upstream` only
+        for proxy in self
+            .proxies
+            .iter()
+            .filter(|proxy| proxy.proxy_type == ProxyType::Varnish)
+        {
+            if self.services.is_empty() && proxy.default_upstream.is_none() {
+                return Err(CerberusError::validation(format!(
+                    "Proxy {} is a varnish cache without services or default_upstream to cache",
+                    proxy.name
+                )));
+            }
+            for (owner, upstream) in self
+                .services
+                .iter()
+                .map(|service| (service.name.as_str(), &service.upstream))
+                .chain(
+                    proxy
+                        .default_upstream
+                        .iter()
+                        .map(|upstream| (proxy.name.as_str(), upstream)),
+                )
+            {
+                if upstream.starts_with("https://") {
+                    return Err(CerberusError::validation(format!(
+                        "{owner} upstream {upstream} uses HTTPS, which varnish proxy {} cannot connect to",
+                        proxy.name
+                    )));
+                }
+            }
+            if let Some(service) = self.services.iter().find(|service| {
+                service.cache.as_ref().is_some_and(|cache| {
+                    cache.enabled && cache.methods.iter().any(|method| method == "POST")
+                })
+            }) {
+                return Err(CerberusError::validation(format!(
+                    "Service {} caches POST responses, which varnish proxy {} cannot do",
+                    service.name, proxy.name
+                )));
+            }
+            if let Some(service) = self
+                .services
+                .iter()
+                .find(|service| service.candidate_upstream.is_some())
+            {
+                return Err(CerberusError::validation(format!(
+                    "Service {} candidate_upstream cannot be split behind varnish proxy {}",
+                    service.name, proxy.name
+                )));
+            }
+            if let Some(service) = self
+                .services
+                .iter()
+                .find(|service| service.failover_policy().is_some())
+            {
+                return Err(CerberusError::validation(format!(
+                    "Service {} cannot fail over to backup upstreams behind varnish proxy {}",
+                    service.name, proxy.name
+                )));
+            }
+            if let Some(service) = self
+                .services
+                .iter()
+                .find(|service| self.upstream_pool(&service.upstream).is_some())
+            {
+                return Err(CerberusError::validation(format!(
+                    "Service {} uses upstream pool {}, which varnish proxy {} cannot balance",
+                    service.name, service.upstream, proxy.name
+                )));
+            }
+        }
+
+        self.validate_streams()?;
+
+        validate_container_options(
+            "defaults",
+            self.defaults.platform.as_deref(),
+            &self.defaults.platforms,
+            self.defaults.stop_grace_period.as_deref(),
+        )?;
+        // A fixed platform has to be one the image is built for, wherever
+        // either comes from
+        let options = self
+            .proxies
+            .iter()
+            .map(|proxy| {
+                (
+                    format!("Proxy {}", proxy.name),
+                    proxy.container_options(&self.defaults),
+                )
+            })
+            .chain(self.services.iter().map(|service| {
+                (
+                    format!("Service {}", service.name),
+                    service.container_options(&self.defaults),
+                )
+            }));
+        for (owner, options) in options {
+            if let Some(platform) = &options.platform
+                && !options.platforms.is_empty()
+                && !options.platforms.contains(platform)
+            {
+                return Err(CerberusError::validation(format!(
+                    "{owner} platform '{platform}' is not one of its platforms"
+                )));
+            }
+        }
+        if self.defaults.pull_policy == Some(PullPolicy::Build) {
+            return Err(CerberusError::validation(
+                "defaults.pull_policy cannot be \"build\"; set it on the services built from a Dockerfile",
+            ));
+        }
+        // Proxies are built from their Dockerfile only to bundle extra modules
+        if let Some(proxy) = self.proxies.iter().find(|proxy| {
+            proxy.pull_policy == Some(PullPolicy::Build) && !self.builds_proxy_image(proxy)
+        }) {
+            return Err(CerberusError::validation(format!(
+                "Proxy {} pull_policy = \"build\" requires the WAF, a Caddy cache or compression modules, which build its image",
+                proxy.name
+            )));
+        }
+
+        if let Some(strategy) = &self.deployment.strategy
+            && strategy.weight > 100
+        {
+            return Err(CerberusError::validation(
+                "deployment.strategy weight must be between 0 and 100",
+            ));
+        }
+
+        if self.waf.enabled {
+            if !(1..=4).contains(&self.waf.paranoia_level) {
+                return Err(CerberusError::validation(format!(
+                    "waf.paranoia_level must be between 1 and 4, got {}",
+                    self.waf.paranoia_level
+                )));
+            }
+            if !self.proxies.iter().any(|proxy| self.waf.applies_to(proxy)) {
+                return Err(CerberusError::validation(
+                    "waf.enabled requires a Caddy proxy or a layer-2 Nginx proxy",
+                ));
+            }
+        }
+
+        let crowdsec = &self.security.crowdsec;
+        if crowdsec.enabled
+            && (crowdsec.api_key_env.is_empty()
+                || crowdsec
+                    .api_key_env
+                    .starts_with(|c: char| c.is_ascii_digit())
+                || !crowdsec
+                    .api_key_env
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        {
+            return Err(CerberusError::validation(format!(
+                "security.crowdsec.api_key_env '{}' is not a valid environment variable name",
+                crowdsec.api_key_env
+            )));
+        }
+
+        validate_header_names(&self.security.header_scrub.request, "request")?;
+        validate_header_names(&self.security.header_scrub.response, "response")?;
+        validate_real_ip(&self.security.real_ip)?;
+        if self.security.hardening.enabled {
+            validate_hardening(self)?;
+        }
+        if self.security.profiles.enabled {
+            validate_profiles(self)?;
+        }
+
+        // Validate Anubis configuration
+        validate_anubis_protection(&self.anubis.protection)?;
+        validate_anubis_instances(self)?;
+        validate_anubis_topology(self)?;
+        if self.anubis.enabled && self.anubis.difficulty > 10 {
+            return Err(CerberusError::validation(
+                "Anubis difficulty must be between 1 and 10",
+            ));
+        }
+
+        validate_notifications(&self.notifications)?;
+        validate_dns(&self.dns)?;
+        validate_tls(&self.tls)?;
+        validate_acme(self)?;
+        if let Some(logs) = &self.observability.logs {
+            validate_log_shipping(logs, &self.proxies)?;
+        }
+        if let Some(tracing) = &self.observability.tracing {
+            validate_tracing(self, tracing)?;
+        }
+
+        if !(1..=10).contains(&self.lint.max_anubis_difficulty) {
+            return Err(CerberusError::validation(
+                "lint.max_anubis_difficulty must be between 1 and 10",
+            ));
+        }
+
+        if self.project.target == DeployTarget::Swarm
+            || self.outputs.contains_key(&DeployTarget::Swarm)
+        {
+            validate_swarm(self)?;
+        }
+        if (self.project.target == DeployTarget::Nomad
+            || self.outputs.contains_key(&DeployTarget::Nomad))
+            && let Some(service) = self
+                .services
+                .iter()
+                .find(|service| service.build.is_some() && service.image.is_none())
+        {
+            return Err(CerberusError::validation(format!(
+                "Service {} has a [services.build] section but no image, \
+                 which Nomad cannot build; push the image and set image instead",
+                service.name
+            )));
+        }
+
+        // Validate declared outputs
+        let mut output_paths = std::collections::HashSet::new();
+        for (target, output) in &self.outputs {
+            let path = output.path.trim_end_matches('/');
+            if path.trim().is_empty() {
+                return Err(CerberusError::validation(format!(
+                    "outputs.{} path cannot be empty",
+                    target.as_str()
+                )));
+            }
+            if !output_paths.insert(path) {
+                return Err(CerberusError::validation(format!(
+                    "outputs.{} path '{}' is already used by another output",
+                    target.as_str(),
+                    output.path
+                )));
+            }
+            if output.namespace.is_some() && *target != DeployTarget::Nomad {
+                return Err(CerberusError::validation(format!(
+                    "outputs.{} does not support namespace (only Nomad jobs are namespaced)",
+                    target.as_str()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Configuration for rendering one declared output
+    pub fn for_output(&self, target: DeployTarget, output: &OutputConfig) -> Config {
+        let mut config = self.clone();
+        config.project.target = target;
+        if let Some(namespace) = &output.namespace {
+            config.nomad.namespace = Some(namespace.clone());
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests;
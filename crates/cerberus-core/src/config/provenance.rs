@@ -0,0 +1,170 @@
+//! # Configuration provenance
+//!
+//! Renders the configuration as Cerberus uses it, for `cerberus config
+//! show`: includes merged, the file laid over the base it extends, and,
+//! with `--effective`, every default filled in. Each value of the effective
+//! configuration is followed by a comment naming where it was set.
+//!
+//! A value belongs to the main file or the included file that sets it
+//! (includes cannot set the same value twice), then to the base the
+//! configuration extends, and is a default otherwise. Entries of arrays of
+//! tables are matched by `name`, as `extends` merges them.
+
+use std::path::Path;
+
+use toml_edit::{DocumentMut, Item, Table};
+
+use super::{Config, deprecation, extends, include};
+use crate::{CerberusError, Result};
+
+/// Label of values no configuration file sets
+const DEFAULT_SOURCE: &str = "default";
+
+/// Step from a table to one of its values
+enum Segment {
+    /// Key of a table
+    Key(String),
+    /// Entry of an array of tables, by `name` when it has one
+    Entry { index: usize, name: Option<String> },
+}
+
+/// Configuration files in precedence order, each with its own table
+struct Sources(Vec<(String, toml::Table)>);
+
+impl Sources {
+    /// Read the main file, its includes, and the base it extends
+    fn read(path: &Path) -> Result<Self> {
+        let mut files = vec![path.to_path_buf()];
+        files.extend(include::included_files(path)?);
+
+        let mut sources = Vec::new();
+        for file in files {
+            let content =
+                std::fs::read_to_string(&file).map_err(|e| CerberusError::io(&file, e))?;
+            let table: toml::Table =
+                toml::from_str(&content).map_err(|e| CerberusError::toml_parse(&file, e))?;
+            sources.push((file.display().to_string(), table));
+        }
+        if let Some(base) = extends::base_table(path)? {
+            sources.push(base);
+        }
+
+        // Deprecated keys count where their replacements are used
+        for (_, table) in &mut sources {
+            table.remove(include::INCLUDE_KEY);
+            table.remove(extends::EXTENDS_KEY);
+            deprecation::upgrade(table);
+        }
+        Ok(Self(sources))
+    }
+
+    /// Where the value at `path` was set
+    fn origin(&self, path: &[Segment]) -> &str {
+        self.0
+            .iter()
+            .find(|(_, table)| lookup(table, path).is_some())
+            .map_or(DEFAULT_SOURCE, |(source, _)| source.as_str())
+    }
+}
+
+/// Value at a path of a configuration file's table
+fn lookup<'a>(table: &'a toml::Table, path: &[Segment]) -> Option<&'a toml::Value> {
+    let (Segment::Key(first), rest) = path.split_first()? else {
+        return None;
+    };
+    let mut value = table.get(first)?;
+    for segment in rest {
+        value = match (segment, value) {
+            (Segment::Key(key), toml::Value::Table(table)) => table.get(key)?,
+            (
+                Segment::Entry {
+                    name: Some(name), ..
+                },
+                toml::Value::Array(entries),
+            ) => entries
+                .iter()
+                .find(|entry| entry.get("name").and_then(toml::Value::as_str) == Some(name))?,
+            (Segment::Entry { index, .. }, toml::Value::Array(entries)) => entries.get(*index)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// The configuration file with includes and its base merged
+///
+/// Shows what the files set, without defaults, in the same form the
+/// configuration is deserialized from.
+///
+/// # Errors
+/// Returns error if a file cannot be read or parsed
+pub fn merged_toml(path: &Path) -> Result<String> {
+    let content = std::fs::read_to_string(path).map_err(|e| CerberusError::io(path, e))?;
+    let merged = include::merged(path, &content)?;
+    let mut table = match extends::based(path, &content, merged)? {
+        Some(table) => table,
+        None => toml::from_str(&content).map_err(|e| CerberusError::toml_parse(path, e))?,
+    };
+    deprecation::upgrade(&mut table);
+    toml::to_string(&table).map_err(|e| CerberusError::config(format!("{}: {e}", path.display())))
+}
+
+/// The configuration as it is used, each value annotated with its source
+///
+/// `config` is the configuration loaded from `path`. Keys are sorted, so
+/// the output is stable across runs.
+///
+/// # Errors
+/// Returns error if a file cannot be read or parsed
+pub fn effective_toml(path: &Path, config: &Config) -> Result<String> {
+    let sources = Sources::read(path)?;
+    let serialize_error = |e: &dyn std::fmt::Display| {
+        CerberusError::config(format!(
+            "{}: cannot serialize the configuration: {e}",
+            path.display()
+        ))
+    };
+    let value = toml::Value::try_from(config).map_err(|e| serialize_error(&e))?;
+    let rendered = toml::to_string(&value).map_err(|e| serialize_error(&e))?;
+    let mut document: DocumentMut = rendered.parse().map_err(|e| serialize_error(&e))?;
+
+    annotate(document.as_table_mut(), &mut Vec::new(), &sources);
+
+    let mut sources_list: Vec<&str> = sources
+        .0
+        .iter()
+        .map(|(source, _)| source.as_str())
+        .collect();
+    sources_list.push(DEFAULT_SOURCE);
+    Ok(format!(
+        "# Effective configuration of {}\n\
+         # Each value names where it was set: {}\n\n{document}",
+        path.display(),
+        sources_list.join(", ")
+    ))
+}
+
+/// Append the source of every value in a table as a comment
+fn annotate(table: &mut Table, path: &mut Vec<Segment>, sources: &Sources) {
+    for (key, item) in table.iter_mut() {
+        path.push(Segment::Key(key.get().to_string()));
+        match item {
+            Item::Table(table) => annotate(table, path, sources),
+            Item::ArrayOfTables(entries) => {
+                for (index, entry) in entries.iter_mut().enumerate() {
+                    let name = entry.get("name").and_then(Item::as_str).map(str::to_string);
+                    path.push(Segment::Entry { index, name });
+                    annotate(entry, path, sources);
+                    path.pop();
+                }
+            }
+            Item::Value(value) => {
+                value
+                    .decor_mut()
+                    .set_suffix(format!(" # {}", sources.origin(path)));
+            }
+            Item::None => {}
+        }
+        path.pop();
+    }
+}
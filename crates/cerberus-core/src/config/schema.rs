@@ -0,0 +1,39 @@
+//! # JSON Schema of the configuration file
+//!
+//! Derived from the [`Config`] structs, so editors (e.g. Even Better TOML)
+//! and CI can check `config.toml` against the same model `cerberus` loads.
+//! The directives resolved before deserialization, `extends` and `include`,
+//! are added by hand.
+
+use schemars::generate::SchemaSettings;
+use serde_json::{Value, json};
+
+use super::Config;
+use super::extends::EXTENDS_KEY;
+use super::include::INCLUDE_KEY;
+
+/// JSON Schema (draft 2020-12) describing a Cerberus configuration file
+pub fn json_schema() -> Value {
+    let generator = SchemaSettings::draft2020_12().into_generator();
+    let mut schema = generator.into_root_schema_for::<Config>().to_value();
+
+    schema["title"] = json!("Cerberus configuration");
+    if let Some(properties) = schema["properties"].as_object_mut() {
+        properties.insert(
+            EXTENDS_KEY.to_string(),
+            json!({
+                "description": "Base configuration this file is laid over: a local path, or host/owner/repo/path@ref",
+                "type": "string",
+            }),
+        );
+        properties.insert(
+            INCLUDE_KEY.to_string(),
+            json!({
+                "description": "Files merged into this configuration; wildcards are allowed in the file name",
+                "type": "array",
+                "items": { "type": "string" },
+            }),
+        );
+    }
+    schema
+}
@@ -0,0 +1,5614 @@
+//! # Tests for configuration management
+//!
+//! These tests verify that TOML configuration loading, parsing, and validation
+//! work correctly for all supported configuration patterns.
+
+use super::*;
+use pretty_assertions::assert_eq;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+/// Helper function to create a temporary TOML file with given content
+fn create_temp_config(content: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().expect("Failed to create temp file");
+    file.write_all(content.as_bytes())
+        .expect("Failed to write to temp file");
+    file
+}
+
+#[test]
+fn test_minimal_config_loading() {
+    let content = r#"
+[project]
+name = "test-project"
+
+[[proxies]]
+name = "simple-proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    assert_eq!(config.project.name, "test-project");
+    assert!(!config.project.scaling);
+    assert_eq!(config.proxies.len(), 1);
+    assert_eq!(config.proxies[0].name, "simple-proxy");
+    assert_eq!(config.proxies[0].proxy_type, ProxyType::Caddy);
+    assert_eq!(config.proxies[0].external_port, Some(80));
+    assert_eq!(config.services.len(), 1);
+    assert_eq!(config.services[0].name, "web-service");
+    assert_eq!(config.services[0].domain, "example.com");
+}
+
+#[test]
+fn test_full_featured_config_loading() {
+    let content = r#"
+[project]
+name = "full-featured-test"
+scaling = true
+
+[global]
+auto_https = "on"
+admin = "on"
+
+[tls]
+enabled = true
+
+[[tls.certificates]]
+domain = "*.example.com"
+cert_file = "/etc/ssl/wildcard.crt"
+key_file = "/etc/ssl/wildcard.key"
+
+[anubis]
+enabled = true
+bind = ":8080"
+target = "http://proxy-2:80"
+difficulty = 7
+metrics_bind = ":9090"
+
+[[proxies]]
+name = "proxy-layer1"
+type = "caddy"
+external_port = 80
+layer = 1
+instances = 2
+default_upstream = "http://anubis:8080"
+
+[[proxies.routes]]
+type = "direct"
+domain = "static.example.com"
+upstream = "http://proxy-2:80"
+
+[[proxies.routes]]
+type = "conditional"
+domain = "api.example.com"
+upstream = "http://proxy-2:80"
+bypass_paths = ["/health/*", "/metrics/*"]
+
+[[proxies]]
+name = "proxy-layer2"
+type = "caddy"
+external_port = 80
+layer = 2
+instances = 3
+
+[[services]]
+name = "main-app"
+domain = "api.example.com"
+upstream = "http://192.0.2.1:3000"
+websocket = true
+compression.enabled = true
+max_body_size = "500m"
+
+[[services]]
+name = "static-files"
+domain = "static.example.com"
+upstream = "https://cdn.example.com/"
+
+[services.headers.response.set]
+Cache-Control = "public, max-age=86400"
+
+[logging]
+level = "INFO"
+format = "json"
+output = "/var/log/cerberus.log"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    // Test project config
+    assert_eq!(config.project.name, "full-featured-test");
+    assert!(config.project.scaling);
+
+    // Test global config
+    assert_eq!(config.global.auto_https, "on");
+    assert_eq!(config.global.admin, "on");
+
+    // Test TLS config
+    assert!(config.tls.enabled);
+    assert_eq!(config.tls.certificates.len(), 1);
+    assert_eq!(config.tls.certificates[0].domain, "*.example.com");
+
+    // Test Anubis config
+    assert!(config.anubis.enabled);
+    assert_eq!(config.anubis.difficulty, 7);
+
+    // Test proxy configs
+    assert_eq!(config.proxies.len(), 2);
+    assert_eq!(config.proxies[0].instances, 2);
+    assert_eq!(config.proxies[0].routes.len(), 2);
+    assert_eq!(config.proxies[0].routes[0].route_type, RouteType::Direct);
+    assert_eq!(
+        config.proxies[0].routes[1].route_type,
+        RouteType::Conditional
+    );
+    assert_eq!(config.proxies[0].routes[1].bypass_paths.len(), 2);
+
+    // Test service configs
+    assert_eq!(config.services.len(), 2);
+    assert!(config.services[0].websocket);
+    assert_eq!(config.services[0].max_body_size, "500m");
+}
+
+#[test]
+fn test_anubis_disabled_config() {
+    let content = r#"
+[project]
+name = "no-anubis-test"
+
+[anubis]
+enabled = false
+
+[[proxies]]
+name = "simple-proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    assert!(!config.anubis.enabled);
+    // Default values should still be set
+    assert_eq!(config.anubis.bind, ":8080");
+    assert_eq!(config.anubis.difficulty, 5);
+}
+
+#[test]
+fn test_multi_proxy_config() {
+    let content = r#"
+[project]
+name = "multi-proxy-test"
+
+[[proxies]]
+name = "frontend-proxy"
+type = "caddy"
+external_port = 80
+
+[[proxies]]
+name = "backend-proxy"
+type = "nginx"
+external_port = 8080
+
+[[proxies]]
+name = "api-proxy"
+type = "haproxy"
+external_port = 9000
+algorithm = "roundrobin"
+max_connections = 1024
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    assert_eq!(config.proxies.len(), 3);
+    assert_eq!(config.proxies[0].proxy_type, ProxyType::Caddy);
+    assert_eq!(config.proxies[1].proxy_type, ProxyType::Nginx);
+    assert_eq!(config.proxies[2].proxy_type, ProxyType::HaProxy);
+    assert_eq!(config.proxies[2].algorithm, Some("roundrobin".to_string()));
+    assert_eq!(config.proxies[2].max_connections, Some(1024));
+}
+
+#[test]
+fn test_scaling_enabled_config() {
+    let content = r#"
+[project]
+name = "scaling-test"
+scaling = true
+
+[[proxies]]
+name = "load-balancer"
+type = "haproxy"
+external_port = 80
+instances = 3
+algorithm = "roundrobin"
+
+[[services]]
+name = "scalable-service"
+domain = "scale.example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    assert!(config.project.scaling);
+    assert_eq!(config.proxies[0].instances, 3);
+}
+
+#[test]
+fn test_headers_configuration() {
+    let content = r#"
+[project]
+name = "headers-test"
+
+[[proxies]]
+name = "header-proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "header-service"
+domain = "headers.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[services.headers.request.set]
+Authorization = "Bearer token123"
+
+[services.headers.request]
+add = { Forwarded = "for=edge" }
+remove = ["X-Debug"]
+
+[services.headers.response]
+set = { Cache-Control = "public, max-age=3600" }
+remove = ["X-Powered-By"]
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    let headers = &config.services[0].headers;
+    assert_eq!(
+        headers.request.set,
+        BTreeMap::from([("Authorization".to_string(), "Bearer token123".to_string())])
+    );
+    assert_eq!(headers.request.add["Forwarded"], "for=edge");
+    assert_eq!(headers.request.remove, vec!["X-Debug"]);
+    assert_eq!(
+        headers.response.set["Cache-Control"],
+        "public, max-age=3600"
+    );
+    assert_eq!(headers.response.remove, vec!["X-Powered-By"]);
+    assert!(config.deprecations.is_empty());
+
+    for (from, to, message) in [
+        (
+            "remove = [\"X-Debug\"]",
+            "remove = [\"Host\"]",
+            "cannot change the Host header",
+        ),
+        (
+            "remove = [\"X-Debug\"]",
+            "remove = [\"authorization\"]",
+            "lists authorization in both set and remove",
+        ),
+        (
+            "remove = [\"X-Debug\"]",
+            "remove = [\"X Debug\"]",
+            "contains invalid header name 'X Debug'",
+        ),
+        (
+            "\"for=edge\"",
+            "\"for=edge\\nX-Injected: 1\"",
+            "value of Forwarded contains a line break",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let error = Config::load(temp_file.path())
+            .expect_err("invalid headers should be rejected")
+            .to_string();
+        assert!(error.contains(message), "{error}");
+    }
+}
+
+#[test]
+fn test_legacy_header_keys() {
+    let content = r#"
+[project]
+name = "headers-test"
+
+[[services]]
+name = "header-service"
+domain = "headers.example.com"
+upstream = "http://192.0.2.1:3000"
+headers_request_x_tenant_id = "acme"
+headers_response_cache_control = "no-store"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    let headers = &config.services[0].headers;
+    assert_eq!(headers.request.set["X-Tenant-Id"], "acme");
+    assert_eq!(headers.response.set["Cache-Control"], "no-store");
+    let keys: Vec<(&str, &str)> = config
+        .deprecations
+        .iter()
+        .map(|deprecated| (deprecated.key.as_str(), deprecated.replacement.as_str()))
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            (
+                "services[0].headers_request_x_tenant_id",
+                "services[0].headers.request.set.X-Tenant-Id"
+            ),
+            (
+                "services[0].headers_response_cache_control",
+                "services[0].headers.response.set.Cache-Control"
+            ),
+        ]
+    );
+
+    // migrate writes the tables as dotted keys and the result loads cleanly
+    let (migrated, renamed) = migrate_content(content).unwrap();
+    assert_eq!(renamed, config.deprecations);
+    assert!(migrated.contains("headers.request.set.X-Tenant-Id = \"acme\"\n"));
+    assert!(migrated.contains("headers.response.set.Cache-Control = \"no-store\"\n"));
+    let temp_file = create_temp_config(&migrated);
+    let migrated_config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert!(migrated_config.deprecations.is_empty());
+    assert_eq!(migrated_config.services, config.services);
+
+    // An existing table receives the moved keys
+    let with_table = format!("{content}\n[services.headers.request.set]\nX-Region = \"eu\"\n");
+    let (migrated, _) = migrate_content(&with_table).unwrap();
+    let temp_file = create_temp_config(&migrated);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert_eq!(config.services[0].headers.request.set.len(), 2);
+
+    let both = format!("{content}\n[services.headers.request.set]\nX-Tenant-Id = \"other\"\n");
+    let temp_file = create_temp_config(&both);
+    assert!(
+        Config::load(temp_file.path())
+            .unwrap_err()
+            .to_string()
+            .contains("services[0].headers_request_x_tenant_id and services[0].headers.request.set.X-Tenant-Id are both set")
+    );
+}
+
+#[test]
+fn test_config_validation_empty_project_name() {
+    let content = r#"
+[project]
+name = ""
+
+[[proxies]]
+name = "test-proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "test-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let result = Config::load(temp_file.path());
+
+    assert!(result.is_err());
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("Project name cannot be empty")
+    );
+}
+
+#[test]
+fn test_config_validation_empty_proxy_name() {
+    let content = r#"
+[project]
+name = "test-project"
+
+[[proxies]]
+name = ""
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "test-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let result = Config::load(temp_file.path());
+
+    assert!(result.is_err());
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("name cannot be empty")
+    );
+}
+
+#[test]
+fn test_config_validation_zero_port() {
+    let content = r#"
+[project]
+name = "test-project"
+
+[[proxies]]
+name = "test-proxy"
+type = "caddy"
+external_port = 0
+
+[[services]]
+name = "test-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let result = Config::load(temp_file.path());
+
+    assert!(result.is_err());
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("external_port must be greater than 0")
+    );
+}
+
+#[test]
+fn test_anubis_protection_limits() {
+    let content = r#"
+[project]
+name = "protection-test"
+
+[anubis]
+enabled = true
+
+[anubis.protection]
+max_uri_length = 2048
+max_header_size = "4k"
+max_headers_size = "10k"
+challenge_body_size = "16k"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    // Nginx buffers cover the whole header block
+    assert_eq!(config.anubis.protection.header_buffers(), (3, 4 << 10));
+    assert_eq!(
+        AnubisProtectionConfig::default().header_buffers(),
+        (4, 8 << 10)
+    );
+
+    for (from, to, message) in [
+        (
+            "max_uri_length = 2048",
+            "max_uri_length = 4096",
+            "max_uri_length must be between 256 and max_header_size",
+        ),
+        (
+            "max_uri_length = 2048",
+            "max_uri_length = 100",
+            "max_uri_length must be between 256",
+        ),
+        (
+            "max_headers_size = \"10k\"",
+            "max_headers_size = \"2k\"",
+            "max_headers_size '2k' is smaller than max_header_size '4k'",
+        ),
+        (
+            "max_header_size = \"4k\"",
+            "max_header_size = \"512\"",
+            "max_header_size must be at least 1k",
+        ),
+        (
+            "challenge_body_size = \"16k\"",
+            "challenge_body_size = \"lots\"",
+            "anubis.protection.challenge_body_size 'lots' is not a valid size",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let error = Config::load(temp_file.path()).unwrap_err().to_string();
+        assert!(error.contains(message), "{error}");
+    }
+}
+
+#[test]
+fn test_anubis_instances_validation() {
+    let content = r#"
+[project]
+name = "instances-test"
+
+[anubis]
+enabled = true
+difficulty = 4
+
+[[anubis.instances]]
+name = "anubis-shop"
+domains = ["shop.example.com"]
+difficulty = 8
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    let containers = config.anubis.containers();
+    assert_eq!(containers.len(), 2);
+    assert_eq!(containers[1].difficulty, 8);
+    assert_eq!(containers[1].bind, config.anubis.bind);
+    assert_eq!(containers[1].config_dir(), "anubis/anubis-shop");
+    assert_eq!(
+        config.anubis.url_for("shop.example.com"),
+        "http://anubis-shop:8080"
+    );
+    assert_eq!(
+        config.anubis.url_for("blog.example.com"),
+        "http://anubis:8080"
+    );
+
+    for (from, to, message) in [
+        (
+            "enabled = true",
+            "enabled = false",
+            "anubis.instances requires anubis.enabled",
+        ),
+        (
+            "name = \"anubis-shop\"",
+            "name = \"Shop\"",
+            "Anubis instance name 'Shop' must contain only",
+        ),
+        (
+            "name = \"anubis-shop\"",
+            "name = \"web-app\"",
+            "Anubis instance name 'web-app' is already used by another container",
+        ),
+        (
+            "domains = [\"shop.example.com\"]",
+            "domains = []",
+            "Anubis instance anubis-shop needs at least one domain",
+        ),
+        (
+            "domains = [\"shop.example.com\"]",
+            "domains = [\"shop.example.com\", \"shop.example.com\"]",
+            "Domain 'shop.example.com' is assigned to more than one Anubis instance",
+        ),
+        (
+            "domains = [\"shop.example.com\"]",
+            "domains = [\"app.example.com\"]",
+            "domain 'app.example.com' is served by service web-app and bypasses Anubis",
+        ),
+        (
+            "difficulty = 8",
+            "difficulty = 0",
+            "Anubis instance anubis-shop difficulty must be between 1 and 10",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let error = Config::load(temp_file.path()).unwrap_err().to_string();
+        assert!(error.contains(message), "{error}");
+    }
+}
+
+#[test]
+fn test_anubis_topology_validation() {
+    let content = r#"
+[project]
+name = "topology-test"
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "edge"
+type = "caddy"
+external_port = 80
+
+[[proxies]]
+name = "proxy-2"
+type = "haproxy"
+layer = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    // Anubis sits behind a layer-1 proxy of any type
+    assert!(config.sends_to_anubis(&config.proxies[0]));
+    assert!(!config.sends_to_anubis(&config.proxies[1]));
+    assert_eq!(
+        config.default_upstream(&config.proxies[0]).as_deref(),
+        Some("http://anubis:8080")
+    );
+
+    for (from, to, message) in [
+        (
+            "external_port = 80",
+            "external_port = 80\nlayer = 2",
+            "anubis.enabled needs a layer-1 proxy to pass requests to Anubis",
+        ),
+        (
+            "enabled = true",
+            "enabled = true\n\n[[anubis.instances]]\nname = \"anubis-shop\"\ndomains = [\"shop.example.com\"]",
+            "anubis.instances need a layer-1 Nginx proxy to send their domains to them; \
+             caddy proxy edge would send every domain to the global Anubis",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let error = Config::load(temp_file.path()).unwrap_err().to_string();
+        assert!(error.contains(message), "{error}");
+    }
+}
+
+#[test]
+fn test_config_validation_high_anubis_difficulty() {
+    let content = r#"
+[project]
+name = "test-project"
+
+[anubis]
+enabled = true
+difficulty = 15
+
+[[proxies]]
+name = "test-proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "test-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let result = Config::load(temp_file.path());
+
+    assert!(result.is_err());
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("difficulty must be between 1 and 10")
+    );
+}
+
+#[test]
+fn test_config_defaults() {
+    let content = r#"
+[project]
+name = "defaults-test"
+
+[[proxies]]
+name = "test-proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "test-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    // Test default values
+    assert!(!config.project.scaling);
+    assert_eq!(config.global.auto_https, "off");
+    assert_eq!(config.global.admin, "off");
+    assert!(!config.tls.enabled);
+    assert!(!config.anubis.enabled);
+    assert_eq!(config.anubis.bind, ":8080");
+    assert_eq!(config.anubis.difficulty, 5);
+    assert_eq!(config.proxies[0].internal_port, 80);
+    assert_eq!(config.proxies[0].instances, 1);
+    assert!(!config.services[0].websocket);
+    assert!(config.services[0].compression.enabled);
+    assert_eq!(
+        config.services[0].compression.algorithms,
+        [CompressionAlgorithm::Gzip]
+    );
+    assert_eq!(config.services[0].max_body_size, "1m");
+    assert_eq!(config.logging.level, "INFO");
+    assert_eq!(config.logging.format, "json");
+}
+
+#[test]
+fn test_invalid_toml_syntax() {
+    let content = r#"
+[project
+name = "invalid-toml"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let result = Config::load(temp_file.path());
+
+    assert!(result.is_err());
+    // Should be a TOML parsing error
+    match result.unwrap_err() {
+        CerberusError::TomlParse { .. } => {}
+        _ => panic!("Expected TomlParse error"),
+    }
+}
+
+#[test]
+fn test_file_not_found() {
+    let result = Config::load(std::path::Path::new("/nonexistent/config.toml"));
+
+    assert!(result.is_err());
+    // Should be an I/O error
+    match result.unwrap_err() {
+        CerberusError::Io { .. } => {}
+        _ => panic!("Expected Io error"),
+    }
+}
+
+#[test]
+fn test_secrets_configuration() {
+    let content = r#"
+[project]
+name = "secrets-test"
+
+[secrets.db_password]
+file = "./secrets/db_password.txt"
+
+[secrets.oauth_token]
+environment = "OAUTH_TOKEN"
+
+[secrets.api_key]
+content = "super-secret-key"
+
+[secrets.external_secret]
+external = true
+name = "production-secret"
+
+[[proxies]]
+name = "test-proxy"
+type = "nginx"
+external_port = 80
+secrets = [
+    "db_password",
+    { source = "api_key", target = "/run/secrets/api_key", mode = 400 }
+]
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    // Check secrets configuration
+    assert_eq!(config.secrets.len(), 4);
+
+    // Test file-based secret
+    if let Some(crate::config::SecretConfig::File { file }) = config.secrets.get("db_password") {
+        assert_eq!(file, "./secrets/db_password.txt");
+    } else {
+        panic!("Expected file-based secret");
+    }
+
+    // Test environment secret
+    if let Some(crate::config::SecretConfig::Environment { environment }) =
+        config.secrets.get("oauth_token")
+    {
+        assert_eq!(environment, "OAUTH_TOKEN");
+    } else {
+        panic!("Expected environment secret");
+    }
+
+    // Test content secret
+    if let Some(crate::config::SecretConfig::Content { content }) = config.secrets.get("api_key") {
+        assert_eq!(content, "super-secret-key");
+    } else {
+        panic!("Expected content secret");
+    }
+
+    // Check proxy secrets references
+    let proxy = &config.proxies[0];
+    assert_eq!(proxy.secrets.len(), 2);
+
+    if let crate::config::ServiceSecretRef::Simple(name) = &proxy.secrets[0] {
+        assert_eq!(name, "db_password");
+    } else {
+        panic!("Expected simple secret reference");
+    }
+
+    if let crate::config::ServiceSecretRef::Detailed {
+        source,
+        target,
+        mode,
+        ..
+    } = &proxy.secrets[1]
+    {
+        assert_eq!(source, "api_key");
+        assert_eq!(target.as_ref().unwrap(), "/run/secrets/api_key");
+        assert_eq!(*mode, Some(400));
+    } else {
+        panic!("Expected detailed secret reference");
+    }
+}
+
+#[test]
+fn test_configs_configuration() {
+    let content = r#"
+[project]
+name = "configs-test"
+
+[configs.app_config]
+file = "./configs/app.conf"
+
+[configs.dynamic_config]
+content = '''
+debug=true
+log_level=info
+'''
+
+[[proxies]]
+name = "test-proxy"
+type = "nginx"
+external_port = 80
+configs = [
+    "app_config",
+    { source = "dynamic_config", target = "/etc/app/config.ini" }
+]
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    // Check configs configuration
+    assert_eq!(config.configs.len(), 2);
+
+    // Test file-based config
+    if let Some(crate::config::ConfigFileConfig::File { file }) = config.configs.get("app_config") {
+        assert_eq!(file, "./configs/app.conf");
+    } else {
+        panic!("Expected file-based config");
+    }
+
+    // Test content config
+    if let Some(crate::config::ConfigFileConfig::Content { content }) =
+        config.configs.get("dynamic_config")
+    {
+        assert!(content.contains("debug=true"));
+        assert!(content.contains("log_level=info"));
+    } else {
+        panic!("Expected content config");
+    }
+
+    // Check proxy configs references
+    let proxy = &config.proxies[0];
+    assert_eq!(proxy.configs.len(), 2);
+}
+
+#[test]
+fn test_networks_configuration() {
+    let content = r#"
+[project]
+name = "networks-test"
+ipv6 = true
+
+[networks.frontend]
+driver = "bridge"
+enable_ipv6 = true
+
+[networks.backend]
+driver = "overlay"
+
+[networks.backend.ipam]
+driver = "default"
+
+[[networks.backend.ipam.config]]
+subnet = "172.20.0.0/16"
+gateway = "172.20.0.1"
+
+[networks.external_net]
+external = true
+name = "existing-network"
+
+[[proxies]]
+name = "test-proxy"
+type = "nginx"
+external_port = 80
+networks = ["frontend", "backend"]
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    // Check networks configuration
+    assert_eq!(config.networks.len(), 3);
+
+    // Test frontend network
+    let frontend = config.networks.get("frontend").unwrap();
+    assert_eq!(frontend.driver, "bridge");
+    assert!(frontend.enable_ipv6);
+
+    // Test backend network with IPAM
+    let backend = config.networks.get("backend").unwrap();
+    assert_eq!(backend.driver, "overlay");
+    assert!(backend.ipam.is_some());
+
+    if let Some(ipam) = &backend.ipam {
+        assert_eq!(ipam.config.len(), 1);
+        assert_eq!(ipam.config[0].subnet.as_ref().unwrap(), "172.20.0.0/16");
+        assert_eq!(ipam.config[0].gateway.as_ref().unwrap(), "172.20.0.1");
+    }
+
+    // Test external network
+    let external = config.networks.get("external_net").unwrap();
+    assert!(external.external);
+    assert_eq!(external.name.as_ref().unwrap(), "existing-network");
+
+    // Check proxy networks
+    let proxy = &config.proxies[0];
+    assert_eq!(proxy.networks, vec!["frontend", "backend"]);
+}
+
+#[test]
+fn test_volumes_configuration() {
+    let content = r#"
+[project]
+name = "volumes-test"
+
+[volumes.db_data]
+driver = "local"
+
+[volumes.shared_storage]
+external = true
+name = "shared-volume"
+
+[volumes.cache_data.driver_opts]
+type = "tmpfs"
+device = "tmpfs"
+
+[[proxies]]
+name = "test-proxy"
+type = "nginx"
+external_port = 80
+volumes = [
+    "db_data:/var/lib/mysql",
+    "shared_storage:/mnt/shared:ro"
+]
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    // Check volumes configuration
+    assert_eq!(config.volumes.len(), 3);
+
+    // Test local volume
+    let db_data = config.volumes.get("db_data").unwrap();
+    assert_eq!(db_data.driver.as_ref().unwrap(), "local");
+
+    // Test external volume
+    let shared = config.volumes.get("shared_storage").unwrap();
+    assert!(shared.external);
+    assert_eq!(shared.name.as_ref().unwrap(), "shared-volume");
+
+    // Test volume with driver options
+    let cache = config.volumes.get("cache_data").unwrap();
+    assert_eq!(cache.driver_opts.get("type").unwrap(), "tmpfs");
+    assert_eq!(cache.driver_opts.get("device").unwrap(), "tmpfs");
+
+    // Check proxy volumes
+    let proxy = &config.proxies[0];
+    assert_eq!(proxy.volumes.len(), 2);
+    assert_eq!(proxy.volumes[0], "db_data:/var/lib/mysql");
+    assert_eq!(proxy.volumes[1], "shared_storage:/mnt/shared:ro");
+}
+
+#[test]
+fn test_comprehensive_docker_features() {
+    let content = r#"
+[project]
+name = "comprehensive-test"
+
+# Secrets
+[secrets.db_password]
+file = "./secrets/db_password"
+
+# Configs
+[configs.nginx_conf]
+file = "./configs/nginx.conf"
+
+# Networks
+[networks.web]
+driver = "bridge"
+
+[networks.db]
+driver = "bridge"
+
+# Volumes
+[volumes.mysql_data]
+driver = "local"
+
+[[proxies]]
+name = "web-proxy"
+type = "nginx"
+external_port = 80
+internal_port = 80
+build_context = "./nginx"
+build_dockerfile = "Dockerfile"
+restart = "unless-stopped"
+networks = ["web", "db"]
+volumes = ["./nginx.conf:/etc/nginx/nginx.conf:ro"]
+secrets = ["db_password"]
+configs = ["nginx_conf"]
+
+[proxies.environment]
+NGINX_HOST = "localhost"
+NGINX_PORT = "80"
+
+[proxies.healthcheck]
+test = ["CMD", "curl", "-f", "http://localhost/health"]
+interval = "30s"
+timeout = "10s"
+retries = 3
+
+[proxies.deploy]
+replicas = 2
+
+[proxies.deploy.resources.limits]
+cpus = "0.5"
+memory = "512M"
+
+[proxies.deploy.update_config]
+parallelism = 1
+delay = "10s"
+failure_action = "rollback"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    // Basic checks
+    assert_eq!(config.project.name, "comprehensive-test");
+    assert_eq!(config.secrets.len(), 1);
+    assert_eq!(config.configs.len(), 1);
+    assert_eq!(config.networks.len(), 2);
+    assert_eq!(config.volumes.len(), 1);
+    assert_eq!(config.proxies.len(), 1);
+
+    let proxy = &config.proxies[0];
+
+    // Check proxy configuration
+    assert_eq!(proxy.name, "web-proxy");
+    assert_eq!(proxy.external_port, Some(80));
+    assert_eq!(proxy.build_context.as_ref().unwrap(), "./nginx");
+    assert_eq!(proxy.restart.as_ref().unwrap(), "unless-stopped");
+
+    // Check networks and volumes
+    assert_eq!(proxy.networks, vec!["web", "db"]);
+    assert_eq!(proxy.volumes.len(), 1);
+
+    // Check secrets and configs
+    assert_eq!(proxy.secrets.len(), 1);
+    assert_eq!(proxy.configs.len(), 1);
+
+    // Check environment
+    assert_eq!(proxy.environment.get("NGINX_HOST").unwrap(), "localhost");
+    assert_eq!(proxy.environment.get("NGINX_PORT").unwrap(), "80");
+
+    // Check healthcheck
+    assert!(proxy.healthcheck.is_some());
+    if let Some(hc) = &proxy.healthcheck {
+        assert_eq!(
+            hc.test,
+            vec!["CMD", "curl", "-f", "http://localhost/health"]
+        );
+        assert_eq!(hc.interval, "30s");
+        assert_eq!(hc.retries, 3);
+    }
+
+    // Check deploy configuration
+    assert!(proxy.deploy.is_some());
+    if let Some(deploy) = &proxy.deploy {
+        assert_eq!(deploy.replicas, Some(2));
+
+        if let Some(resources) = &deploy.resources
+            && let Some(limits) = &resources.limits
+        {
+            assert_eq!(limits.cpus.as_ref().unwrap(), "0.5");
+            assert_eq!(limits.memory.as_ref().unwrap(), "512M");
+        }
+
+        if let Some(update_config) = &deploy.update_config {
+            assert_eq!(update_config.parallelism, Some(1));
+            assert_eq!(update_config.delay.as_ref().unwrap(), "10s");
+            assert_eq!(update_config.failure_action.as_ref().unwrap(), "rollback");
+        }
+    }
+}
+
+#[test]
+fn test_route_difficulty_override() {
+    let content = r#"
+[project]
+name = "route-difficulty-test"
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "proxy-layer1"
+type = "nginx"
+external_port = 80
+
+[[proxies.routes]]
+type = "conditional"
+domain = "api.example.com"
+upstream = "http://anubis:8080"
+bypass_paths = ["/api/*"]
+difficulty = 8
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    let route = &config.proxies[0].routes[0];
+    assert_eq!(route.difficulty, Some(8));
+    assert_eq!(route.bypass_paths, vec!["/api/*"]);
+}
+
+#[test]
+fn test_config_validation_route_difficulty() {
+    let content = r#"
+[project]
+name = "route-difficulty-test"
+
+[[proxies]]
+name = "proxy-layer1"
+type = "nginx"
+external_port = 80
+
+[[proxies.routes]]
+type = "direct"
+domain = "static.example.com"
+upstream = "http://proxy-2:80"
+difficulty = 3
+"#;
+
+    let temp_file = create_temp_config(content);
+    let result = Config::load(temp_file.path());
+
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("bypasses Anubis"));
+
+    let content = content
+        .replace("type = \"direct\"", "type = \"conditional\"")
+        .replace("difficulty = 3", "difficulty = 12");
+    let temp_file = create_temp_config(&content);
+    let result = Config::load(temp_file.path());
+
+    assert!(result.is_err());
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("difficulty must be between 1 and 10")
+    );
+}
+
+#[test]
+fn test_config_validation_route_paths() {
+    let content = r#"
+[project]
+name = "route-paths-test"
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "proxy-layer1"
+type = "nginx"
+external_port = 80
+
+[[proxies.routes]]
+type = "direct"
+domain = "app.example.com"
+upstream = "http://proxy-2:80"
+
+[[proxies.routes.paths]]
+prefix = "/login"
+protect = true
+
+[[proxies.routes.paths]]
+prefix = "/api/"
+protect = false
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    let route = &config.proxies[0].routes[0];
+    assert!(!route.protected_by_default());
+    assert_eq!(
+        route
+            .path_rules()
+            .iter()
+            .map(|path| path.prefix.as_str())
+            .collect::<Vec<_>>(),
+        vec!["/login", "/api/"]
+    );
+    assert_eq!(config.anubis.url(), "http://anubis:8080");
+
+    for (from, to, message) in [
+        (
+            "prefix = \"/api/\"",
+            "prefix = \"api/*\"",
+            "must start with /",
+        ),
+        (
+            "prefix = \"/api/\"",
+            "prefix = \"/login\"",
+            "more than once",
+        ),
+        ("type = \"nginx\"", "type = \"haproxy\"", "nginx and caddy"),
+        (
+            "proxy-2:80",
+            "anubis:8080",
+            "upstream must be the next layer",
+        ),
+        ("enabled = true", "enabled = false", "Anubis is not enabled"),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let result = Config::load(temp_file.path());
+        assert!(
+            result.unwrap_err().to_string().contains(message),
+            "{message}"
+        );
+    }
+}
+
+#[test]
+fn test_config_validation_streams() {
+    let content = r#"
+[project]
+name = "streams-test"
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+
+[[proxies.routes]]
+type = "direct"
+domain = "game.example.com"
+upstream = "udp://192.0.2.20:27015"
+protocol = "udp"
+listen_port = 27015
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+layer = 2
+
+[[services]]
+name = "postgres"
+domain = "db.example.com"
+upstream = "192.0.2.10:5432"
+protocol = "tcp"
+listen_port = 5432
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert_eq!(
+        config.streams(&config.proxies[0]),
+        vec![
+            StreamRoute {
+                id: "stream_postgres".to_string(),
+                name: "postgres".to_string(),
+                protocol: Protocol::Tcp,
+                listen_port: 5432,
+                address: "192.0.2.10:5432".to_string(),
+                server_names: vec!["db.example.com".to_string()],
+            },
+            StreamRoute {
+                id: "stream_game_example_com".to_string(),
+                name: "game.example.com".to_string(),
+                protocol: Protocol::Udp,
+                listen_port: 27015,
+                address: "192.0.2.20:27015".to_string(),
+                server_names: vec!["game.example.com".to_string()],
+            },
+        ]
+    );
+    // Only the edge proxy receives the tcp and udp services
+    assert!(config.streams(&config.proxies[1]).is_empty());
+
+    for (from, to, message) in [
+        ("listen_port = 5432\n", "", "needs a listen_port"),
+        (
+            "upstream = \"192.0.2.10:5432\"",
+            "upstream = \"http://192.0.2.10/\"",
+            "must be host:port",
+        ),
+        (
+            "listen_port = 5432",
+            "listen_port = 5432\nallowed_methods = [\"GET\"]",
+            "does not support allowed_methods",
+        ),
+        (
+            "protocol = \"tcp\"\n",
+            "",
+            "listen_port, which only applies to tcp and udp",
+        ),
+        (
+            "listen_port = 5432",
+            "listen_port = 80",
+            "already uses for HTTP",
+        ),
+        (
+            "type = \"nginx\"\nexternal_port",
+            "type = \"caddy\"\nexternal_port",
+            "cannot forward tcp stream postgres",
+        ),
+        (
+            "type = \"nginx\"\nlayer = 2",
+            "type = \"nginx\"\nlayer = 2\nexternal_port = 8080",
+            "exactly one proxy with an external_port",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let result = Config::load(temp_file.path());
+        assert!(
+            result.unwrap_err().to_string().contains(message),
+            "{message}"
+        );
+    }
+
+    // tcp and udp can share a port; tcp streams only with distinct server names
+    let shared = content.replace("listen_port = 27015", "listen_port = 5432");
+    let temp_file = create_temp_config(&shared);
+    assert!(Config::load(temp_file.path()).is_ok());
+    let tcp = shared.replace("protocol = \"udp\"", "protocol = \"tcp\"");
+    let temp_file = create_temp_config(&tcp);
+    assert!(Config::load(temp_file.path()).is_ok());
+    let temp_file = create_temp_config(&tcp.replace(
+        "domain = \"db.example.com\"",
+        "domain = \"db.example.com\"\ndomains = [\"*.example.com\"]",
+    ));
+    assert!(
+        Config::load(temp_file.path())
+            .unwrap_err()
+            .to_string()
+            .contains("share port 5432 but both match server name game.example.com")
+    );
+    let udp = content
+        .replace("listen_port = 5432", "listen_port = 27015")
+        .replace("protocol = \"tcp\"", "protocol = \"udp\"");
+    let temp_file = create_temp_config(&udp);
+    assert!(
+        Config::load(temp_file.path())
+            .unwrap_err()
+            .to_string()
+            .contains("both listen on port 27015")
+    );
+}
+
+#[test]
+fn test_config_validation_container_options() {
+    let content = r#"
+[project]
+name = "defaults-test"
+
+[defaults]
+pull_policy = "always"
+stop_grace_period = "15s"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+platform = "linux/arm64"
+
+[proxies.drain]
+timeout = "30s"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+stop_grace_period = "1m"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    // The drain timeout outranks the default grace period
+    assert_eq!(
+        config.proxies[0].container_options(&config.defaults),
+        ContainerOptions {
+            pull_policy: Some(PullPolicy::Always),
+            platform: Some("linux/arm64".to_string()),
+            platforms: Vec::new(),
+            stop_grace_period_secs: Some(35),
+        }
+    );
+    assert_eq!(
+        config.services[0]
+            .container_options(&config.defaults)
+            .stop_grace_period_secs,
+        Some(60)
+    );
+
+    for (from, to, message) in [
+        (
+            "platform = \"linux/arm64\"",
+            "platform = \"arm64\"",
+            "must be os/arch",
+        ),
+        (
+            "stop_grace_period = \"1m\"",
+            "stop_grace_period = \"soon\"",
+            "'soon' is not a valid duration",
+        ),
+        (
+            "platform = \"linux/arm64\"",
+            "stop_grace_period = \"10s\"",
+            "must be longer than the drain timeout",
+        ),
+        (
+            "pull_policy = \"always\"",
+            "pull_policy = \"build\"",
+            "defaults.pull_policy cannot be",
+        ),
+        (
+            "stop_grace_period = \"1m\"",
+            "pull_policy = \"build\"",
+            "requires a [services.build] section",
+        ),
+        (
+            "platform = \"linux/arm64\"",
+            "pull_policy = \"build\"",
+            "requires the WAF",
+        ),
+        (
+            "stop_grace_period = \"1m\"",
+            "platforms = [\"linux/amd64\", \"arm64\"]",
+            "platforms 'arm64' must be os/arch",
+        ),
+        (
+            "stop_grace_period = \"1m\"",
+            "platforms = [\"linux/amd64\", \"linux/amd64\"]",
+            "lists platform 'linux/amd64' twice",
+        ),
+        (
+            "pull_policy = \"always\"",
+            "platforms = [\"linux/amd64\"]",
+            "Proxy proxy platform 'linux/arm64' is not one of its platforms",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let result = Config::load(temp_file.path());
+        assert!(
+            result.unwrap_err().to_string().contains(message),
+            "{message}"
+        );
+    }
+
+    let both = content.replace("platform = \"linux/arm64\"", "stop_grace_period = \"40s\"");
+    let both = both.replace(
+        "timeout = \"30s\"",
+        "timeout = \"30s\"\nstop_grace_period = \"45s\"",
+    );
+    let temp_file = create_temp_config(&both);
+    assert!(
+        Config::load(temp_file.path())
+            .unwrap_err()
+            .to_string()
+            .contains("sets both stop_grace_period and drain.stop_grace_period")
+    );
+}
+
+#[test]
+fn test_config_validation_log_shipping() {
+    let content = r#"
+[project]
+name = "logs-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+
+[observability.logs]
+shipper = "promtail"
+loki_port = 3100
+retention = "14d"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    let logs = config.observability.logs.as_ref().unwrap();
+    assert_eq!(logs.shipper, LogShipper::Promtail);
+    assert_eq!(logs.shipper_image(), "grafana/promtail:latest");
+    assert!(logs.bundles_loki());
+    assert_eq!(logs.loki_url(), "http://loki:3100");
+
+    for (from, to, message) in [
+        (
+            "retention = \"14d\"",
+            "retention = \"12h\"",
+            "must be whole hours of at least 24h",
+        ),
+        (
+            "loki_port = 3100",
+            "loki_port = 80",
+            "already published by proxy 'proxy'",
+        ),
+        (
+            "retention = \"14d\"",
+            "loki_url = \"loki.example.com\"",
+            "must be an http:// or https:// URL",
+        ),
+        (
+            "retention = \"14d\"",
+            "loki_url = \"https://loki.example.com/\"",
+            "loki_port only applies to the bundled Loki",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let result = Config::load(temp_file.path());
+        assert!(
+            result.unwrap_err().to_string().contains(message),
+            "{message}"
+        );
+    }
+}
+
+#[test]
+fn test_config_validation_tracing() {
+    let content = r#"
+[project]
+name = "tracing-test"
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+
+[[proxies]]
+name = "cache"
+type = "varnish"
+layer = 2
+default_upstream = "http://192.0.2.1:3000"
+
+[observability.tracing]
+endpoint = "http://tempo:4317"
+sample_rate = 0.25
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    let tracing = config.observability.tracing.as_ref().unwrap();
+    assert_eq!(tracing.protocol, OtlpProtocol::Grpc);
+    assert!(tracing.insecure());
+    assert!(config.tracing(&config.proxies[0]).is_some());
+    assert!(config.tracing(&config.proxies[1]).is_none());
+    assert!(config.generates_nginx_main(&config.proxies[0]));
+
+    for (from, to, message) in [
+        (
+            "sample_rate = 0.25",
+            "sample_rate = 1.5",
+            "must be between 0.0 and 1.0",
+        ),
+        (
+            "endpoint = \"http://tempo:4317\"",
+            "endpoint = \"tempo:4317\"",
+            "must be an http:// or https:// URL",
+        ),
+        (
+            "name = \"tracing-test\"",
+            "name = \"tracing-test\"\ntarget = \"nomad\"",
+            "only supported by the compose and swarm targets",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let result = Config::load(temp_file.path());
+        assert!(
+            result.unwrap_err().to_string().contains(message),
+            "{message}"
+        );
+    }
+
+    // The WAF runs on layer-2 Nginx, whose image has no OpenTelemetry module
+    let waf =
+        content.replace("type = \"varnish\"", "type = \"nginx\"") + "\n[waf]\nenabled = true\n";
+    let temp_file = create_temp_config(&waf);
+    assert!(
+        Config::load(temp_file.path())
+            .unwrap_err()
+            .to_string()
+            .contains("Proxy cache runs the WAF")
+    );
+}
+
+#[test]
+fn test_config_validation_content() {
+    let content = r#"
+[project]
+name = "content-test"
+
+[[proxies]]
+name = "edge"
+type = "caddy"
+external_port = 80
+
+[content.robots]
+sitemaps = ["https://example.com/sitemap.xml"]
+
+[[content.robots.agents]]
+user_agent = "*"
+disallow = ["/admin/"]
+
+[content.security_txt]
+contact = ["mailto:security@example.com"]
+expires = "2027-01-01T00:00:00Z"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert_eq!(
+        config.content.files(),
+        vec![
+            ("/robots.txt", "robots.txt"),
+            ("/.well-known/security.txt", "security.txt"),
+        ]
+    );
+    assert!(config.serves_content(&config.proxies[0]));
+
+    for (from, to, message) in [
+        (
+            "sitemaps = [\"https://example.com/sitemap.xml\"]",
+            "file = \"robots.txt\"",
+            "sets both file and agents or sitemaps",
+        ),
+        (
+            "disallow = [\"/admin/\"]",
+            "disallow = [\"admin/\"]",
+            "must start with / or *",
+        ),
+        (
+            "https://example.com/sitemap.xml",
+            "/sitemap.xml",
+            "must be an absolute http:// or https:// URL",
+        ),
+        (
+            "mailto:security@example.com",
+            "security@example.com",
+            "must be a mailto:, tel: or https:// URI",
+        ),
+        (
+            "2027-01-01T00:00:00Z",
+            "next year",
+            "must be an RFC 3339 timestamp",
+        ),
+        (
+            "type = \"caddy\"",
+            "type = \"traefik\"",
+            "content cannot be served by traefik proxy edge at layer 1",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let result = Config::load(temp_file.path());
+        assert!(
+            result.unwrap_err().to_string().contains(message),
+            "{message}"
+        );
+    }
+}
+
+#[test]
+fn test_config_validation_https() {
+    let content = r#"
+[project]
+name = "https-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+
+[tls.redirect_http]
+status = 308
+
+[tls.hsts]
+max_age = 63072000
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    let redirect = config.tls.redirect_http.as_ref().unwrap();
+    assert_eq!(redirect.https_port, 443);
+    assert_eq!(redirect.port_suffix(), "");
+    assert!(redirect.is_permanent());
+    assert_eq!(
+        config.tls.hsts.header_value().as_deref(),
+        Some("max-age=63072000; includeSubDomains; preload")
+    );
+    assert_eq!(
+        HstsConfig::default().header_value().as_deref(),
+        Some("max-age=31536000; includeSubDomains; preload")
+    );
+
+    for (from, to, message) in [
+        (
+            "status = 308",
+            "status = 200",
+            "must be 301, 302, 307 or 308",
+        ),
+        (
+            "status = 308",
+            "https_port = 0",
+            "https_port must be greater than 0",
+        ),
+        (
+            "max_age = 63072000",
+            "includeSubdomains = false",
+            "preload requires includeSubdomains = true",
+        ),
+        (
+            "max_age = 63072000",
+            "max_age = 86400",
+            "preload requires max_age of at least 31536000",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let result = Config::load(temp_file.path());
+        assert!(
+            result.unwrap_err().to_string().contains(message),
+            "{message}"
+        );
+    }
+}
+
+#[test]
+fn test_config_validation_acme() {
+    let content = r#"
+[project]
+name = "acme-test"
+
+[secrets.aws_key_id]
+file = "./secrets/aws_key_id.txt"
+
+[secrets.aws_secret]
+file = "./secrets/aws_secret.txt"
+
+[tls.acme]
+email = "ops@example.com"
+provider = "route53"
+domains = ["*.example.com"]
+credentials = { AWS_ACCESS_KEY_ID = "aws_key_id", AWS_SECRET_ACCESS_KEY = "aws_secret" }
+
+[[proxies]]
+name = "proxy"
+type = "traefik"
+external_port = 80
+internal_port = 8080
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    let acme = config.acme(&config.proxies[0]).unwrap();
+    assert_eq!(acme.https_port, 443);
+    assert_eq!(acme.listen_port, 8443);
+    let envs: Vec<&str> = acme
+        .secret_credentials()
+        .iter()
+        .map(|(credential, _)| credential.env)
+        .collect();
+    assert_eq!(envs, ["AWS_ACCESS_KEY_ID", "AWS_SECRET_ACCESS_KEY"]);
+
+    for (from, to, message) in [
+        (
+            "email = \"ops@example.com\"",
+            "email = \"ops\"",
+            "tls.acme.email 'ops' is not an email address",
+        ),
+        (
+            "domains = [\"*.example.com\"]",
+            "domains = []",
+            "tls.acme.domains must list at least one domain",
+        ),
+        (
+            ", AWS_SECRET_ACCESS_KEY = \"aws_secret\"",
+            "",
+            "tls.acme.credentials must set AWS_SECRET_ACCESS_KEY for the route53 provider",
+        ),
+        (
+            "AWS_SECRET_ACCESS_KEY = \"aws_secret\"",
+            "AWS_SECRET_ACCESS_KEY = \"missing\"",
+            "tls.acme credential AWS_SECRET_ACCESS_KEY secret missing is not defined in [secrets]",
+        ),
+        (
+            "provider = \"route53\"",
+            "provider = \"cloudflare\"",
+            "tls.acme credential AWS_ACCESS_KEY_ID is not read by the cloudflare provider",
+        ),
+        (
+            "type = \"traefik\"",
+            "type = \"nginx\"",
+            "tls.acme is supported on caddy and traefik edge proxies, but proxy is nginx",
+        ),
+        (
+            "internal_port = 8080",
+            "internal_port = 8080\n\n[proxies.deploy]\nreplicas = 2",
+            "Proxy proxy runs several containers",
+        ),
+        (
+            "[tls.acme]",
+            "[security.hardening]\nenabled = true\n\n[tls.acme]\nlisten_port = 443",
+            "Proxy proxy serves HTTPS on privileged port 443",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let error = Config::load(temp_file.path()).unwrap_err().to_string();
+        assert!(error.contains(message), "{error}");
+    }
+}
+
+#[test]
+fn test_config_validation_tuning() {
+    let content = r#"
+[project]
+name = "tuning-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+
+[proxies.tuning]
+worker_processes = "auto"
+keepalive_timeout = "30s"
+client_max_body_size = "10m"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    let tuning = config.proxies[0].tuning.as_ref().unwrap();
+    assert_eq!(
+        tuning.worker_processes,
+        Some(WorkerProcesses::Keyword("auto".to_string()))
+    );
+    assert_eq!(
+        tuning.set_keys(),
+        [
+            "worker_processes",
+            "keepalive_timeout",
+            "client_max_body_size"
+        ]
+    );
+    assert!(config.generates_nginx_main(&config.proxies[0]));
+
+    for (from, to, message) in [
+        (
+            "type = \"nginx\"",
+            "type = \"haproxy\"",
+            "tuning.worker_processes does not apply to haproxy proxies",
+        ),
+        (
+            "type = \"nginx\"",
+            "type = \"caddy\"",
+            "tuning.worker_processes does not apply to caddy proxies",
+        ),
+        (
+            "worker_processes = \"auto\"",
+            "worker_processes = \"max\"",
+            "must be \"auto\" or a number",
+        ),
+        (
+            "worker_processes = \"auto\"",
+            "worker_connections = 0",
+            "tuning.worker_connections must be greater than 0",
+        ),
+        (
+            "keepalive_timeout = \"30s\"",
+            "keepalive_timeout = \"30x\"",
+            "tuning.keepalive_timeout '30x' must be a duration",
+        ),
+        (
+            "client_max_body_size = \"10m\"",
+            "client_max_body_size = \"huge\"",
+            "tuning.client_max_body_size 'huge' must be a size",
+        ),
+        (
+            "external_port = 80",
+            "layer = 2",
+            "client_max_body_size only applies to layer 1",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let result = Config::load(temp_file.path());
+        assert!(
+            result.unwrap_err().to_string().contains(message),
+            "{message}"
+        );
+    }
+}
+
+#[test]
+fn test_config_validation_snippets() {
+    let content = r#"
+[project]
+name = "snippets-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+layer = 1
+
+[proxies.snippets]
+http = "lua_shared_dict limits 10m;"
+server = "limit_req zone=edge burst=20;"
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+layer = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[services.extra_config]
+location = "proxy_read_timeout 300s;"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    let snippets = config.proxies[0].snippets.as_ref().unwrap();
+    assert_eq!(
+        snippets.set_scopes(),
+        [
+            ("http", "lua_shared_dict limits 10m;"),
+            ("server", "limit_req zone=edge burst=20;")
+        ]
+    );
+    assert_eq!(
+        config.services[0]
+            .snippets
+            .as_ref()
+            .unwrap()
+            .location
+            .as_deref(),
+        Some("proxy_read_timeout 300s;")
+    );
+
+    for (from, to, message) in [
+        (
+            "type = \"nginx\"\nexternal_port",
+            "type = \"traefik\"\nexternal_port",
+            "Proxy proxy snippets.http is not supported by traefik proxies",
+        ),
+        (
+            "type = \"nginx\"\nexternal_port = 80\nlayer = 1\n\n[proxies.snippets]\n",
+            "type = \"caddy\"\nexternal_port = 80\nlayer = 1\n\n[proxies.snippets]\nlocation = \"encode gzip\"\n",
+            "Proxy proxy snippets.location is not supported by caddy proxies",
+        ),
+        (
+            "server = \"limit_req zone=edge burst=20;\"",
+            "server = \" \"",
+            "Proxy proxy snippets.server cannot be empty",
+        ),
+        (
+            "type = \"nginx\"\nlayer = 2",
+            "type = \"traefik\"\nlayer = 2",
+            "Service web-app snippets.location is not supported by traefik proxy proxy-2, which routes it",
+        ),
+        (
+            "[services.extra_config]",
+            "[services.snippets]\nlua = \"init_by_lua_block {}\"",
+            "unknown field `lua`",
+        ),
+        (
+            "upstream = \"http://192.0.2.1:3000\"",
+            "upstream = \"192.0.2.1:5432\"\nprotocol = \"tcp\"\nlisten_port = 5432",
+            "Service web-app sets snippets, but tcp services are forwarded without HTTP routing",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let result = Config::load(temp_file.path());
+        assert!(
+            result.unwrap_err().to_string().contains(message),
+            "{message}"
+        );
+    }
+}
+
+#[test]
+fn test_deprecated_keys() {
+    let content = r#"
+[project]
+name = "deprecation-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+# Anubis sits behind the edge
+upstream = "http://anubis:8080"
+external_port = 80
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert_eq!(
+        config.proxies[0].default_upstream.as_deref(),
+        Some("http://anubis:8080")
+    );
+    assert_eq!(config.deprecations.len(), 1);
+    assert_eq!(config.deprecations[0].key, "proxies[0].upstream");
+    assert_eq!(
+        config.deprecations[0].replacement,
+        "proxies[0].default_upstream"
+    );
+
+    let (migrated, renamed) = migrate_content(content).unwrap();
+    assert_eq!(renamed, config.deprecations);
+    assert!(migrated.contains(
+        "# Anubis sits behind the edge\ndefault_upstream = \"http://anubis:8080\"\nexternal_port = 80"
+    ));
+    let temp_file = create_temp_config(&migrated);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert!(config.deprecations.is_empty());
+    assert_eq!(migrate_content(&migrated).unwrap().1, []);
+
+    let both = content.replace(
+        "external_port = 80",
+        "external_port = 80\ndefault_upstream = \"http://anubis:8080\"",
+    );
+    let temp_file = create_temp_config(&both);
+    assert!(
+        Config::load(temp_file.path())
+            .unwrap_err()
+            .to_string()
+            .contains("proxies[0].upstream and proxies[0].default_upstream are both set")
+    );
+
+    // Replacements stay in the same table or below it so migrate can
+    // rewrite them in place
+    for deprecation in DEPRECATIONS {
+        let parent = deprecation
+            .key
+            .rsplit_once('.')
+            .map_or("", |(parent, _)| parent);
+        assert!(deprecation.replacement.starts_with(parent));
+    }
+}
+
+#[test]
+fn test_schema_version_migration() {
+    let content = r#"# Legacy configuration
+
+[project]
+name = "schema-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+upstream = "http://anubis:8080"
+external_port = 80
+
+[[services]]
+name = "web"
+domain = "web.example.com"
+upstream = "http://192.0.2.1:3000"
+compress = true
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert_eq!(config.schema_version, None);
+
+    // Unversioned files run every migration and record the current version
+    let (migrated, renamed) = migrate_content(content).unwrap();
+    assert_eq!(renamed, config.deprecations);
+    assert!(migrated.starts_with("# Legacy configuration\n\nschema_version = 3\n\n[project]"));
+    assert!(migrated.contains("default_upstream = \"http://anubis:8080\""));
+    assert!(migrated.contains("compression.enabled = true"));
+    let temp_file = create_temp_config(&migrated);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert_eq!(config.schema_version, Some(SCHEMA_VERSION));
+    assert!(config.deprecations.is_empty());
+    assert_eq!(
+        migrate_content(&migrated).unwrap(),
+        (migrated.clone(), vec![])
+    );
+
+    // A version 2 file only runs the later migrations
+    let version_2 = format!(
+        "schema_version = 2\n{}",
+        content.replace(
+            "upstream = \"http://anubis",
+            "default_upstream = \"http://anubis"
+        )
+    );
+    let (migrated, renamed) = migrate_content(&version_2).unwrap();
+    assert_eq!(renamed.len(), 1);
+    assert_eq!(renamed[0].key, "services[0].compress");
+    assert!(migrated.starts_with("schema_version = 3\n"));
+
+    for (versioned, message) in [
+        (
+            format!("schema_version = 2\n{content}"),
+            "proxies[0].upstream was replaced by proxies[0].default_upstream in schema version 2",
+        ),
+        (
+            format!("schema_version = 9\n{content}"),
+            "schema_version 9 is newer than this cerberus supports (3)",
+        ),
+        (
+            format!("schema_version = 0\n{content}"),
+            "schema_version must be at least 1",
+        ),
+    ] {
+        let temp_file = create_temp_config(&versioned);
+        let error = Config::load(temp_file.path()).unwrap_err().to_string();
+        assert!(error.contains(message), "{error}");
+    }
+
+    // Included files follow the main file and do not record a version
+    let dir = tempfile::TempDir::new().unwrap();
+    let main = dir.path().join("config.toml");
+    std::fs::write(
+        &main,
+        "include = [\"services.toml\"]\n\n[project]\nname = \"schema-test\"\n",
+    )
+    .unwrap();
+    let services = content[content.find("[[services]]").unwrap()..].to_string();
+    std::fs::write(dir.path().join("services.toml"), &services).unwrap();
+    let report = migrate(&main).unwrap();
+    assert_eq!((report.from, report.to), (1, SCHEMA_VERSION));
+    assert_eq!(report.renamed.len(), 1);
+    assert_eq!(report.renamed[0].0, dir.path().join("services.toml"));
+    let included = std::fs::read_to_string(dir.path().join("services.toml")).unwrap();
+    assert!(!included.contains("schema_version"));
+    assert!(included.contains("compression.enabled = true"));
+    let config = Config::load(&main).expect("Failed to load config");
+    assert_eq!(config.schema_version, Some(SCHEMA_VERSION));
+
+    // Every deprecated key is renamed by a migration
+    for deprecation in DEPRECATIONS {
+        assert!(
+            MIGRATIONS
+                .iter()
+                .any(|migration| migration.version == deprecation.schema_version)
+        );
+    }
+    assert_eq!(MIGRATIONS.last().unwrap().version, SCHEMA_VERSION);
+}
+
+#[test]
+fn test_compression_configuration() {
+    let content = r#"
+[project]
+name = "compression-test"
+
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+
+[services.compression]
+algorithms = ["brotli", "gzip"]
+min_size = "512"
+types = ["text/html", "image/svg+xml"]
+
+[services.compression.levels]
+brotli = 11
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    let compression = &config.services[0].compression;
+    assert!(compression.uses(CompressionAlgorithm::Brotli));
+    assert!(!compression.uses(CompressionAlgorithm::Zstd));
+    assert_eq!(compression.level(CompressionAlgorithm::Brotli), 11);
+    assert_eq!(compression.level(CompressionAlgorithm::Gzip), 6);
+    assert_eq!(compression.min_size_bytes(), 512);
+    assert!(compression.is_customized());
+    assert!(!CompressionConfig::default().is_customized());
+
+    // The boolean flag moved into the block
+    let deprecated = r#"
+[project]
+name = "compression-test"
+
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+compress = false
+"#;
+    let temp_file = create_temp_config(deprecated);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert!(!config.services[0].compression.enabled);
+    assert_eq!(
+        config.deprecations[0].replacement,
+        "services[0].compression.enabled"
+    );
+
+    for (from, to, message) in [
+        (
+            r#"algorithms = ["brotli", "gzip"]"#,
+            "algorithms = []",
+            "compression.algorithms cannot be empty",
+        ),
+        (
+            r#"algorithms = ["brotli", "gzip"]"#,
+            r#"algorithms = ["gzip", "gzip"]"#,
+            "compression.algorithms lists 'gzip' twice",
+        ),
+        (
+            "brotli = 11",
+            "brotli = 12",
+            "compression.levels.brotli must be between 0 and 11, got 12",
+        ),
+        (
+            "brotli = 11",
+            "gzip = 0",
+            "compression.levels.gzip must be between 1 and 9, got 0",
+        ),
+        (
+            r#"min_size = "512""#,
+            r#"min_size = "tiny""#,
+            "compression.min_size must be a size",
+        ),
+        (
+            r#"types = ["text/html", "image/svg+xml"]"#,
+            r#"types = ["html"]"#,
+            "compression.types entry 'html' is not a MIME type",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let error = Config::load(temp_file.path()).unwrap_err().to_string();
+        assert!(error.contains(message), "{error}");
+    }
+
+    // Disabled compression is not checked
+    let temp_file = create_temp_config(
+        &content
+            .replace(
+                "[services.compression]\n",
+                "[services.compression]\nenabled = false\n",
+            )
+            .replace("brotli = 11", "brotli = 12"),
+    );
+    assert!(Config::load(temp_file.path()).is_ok());
+}
+
+#[test]
+fn test_cache_configuration() {
+    let content = r#"
+[project]
+name = "cache-test"
+
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+
+[services.cache]
+enabled = true
+stale_while_revalidate = "30s"
+stale_if_error = "1d"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    let cache = config.services[0]
+        .cache
+        .as_ref()
+        .expect("cache should be set");
+    assert!(cache.enabled);
+    assert_eq!(cache.ttl, "10m");
+    assert_eq!(cache.retention(), "1d");
+    assert_eq!(
+        cache.stale_cache_control().as_deref(),
+        Some("stale-while-revalidate=30, stale-if-error=86400")
+    );
+    assert!(config.services[0].headers.is_empty());
+}
+
+#[test]
+fn test_config_validation_invalid_cache_duration() {
+    let content = r#"
+[project]
+name = "cache-test"
+
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+
+[services.cache]
+enabled = true
+stale_if_error = "forever"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let result = Config::load(temp_file.path());
+
+    assert!(result.is_err());
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("stale_if_error 'forever' is not a valid duration")
+    );
+}
+
+#[test]
+fn test_cache_policy_validation() {
+    let content = r#"
+[project]
+name = "cache-test"
+
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+
+[services.cache]
+enabled = true
+methods = ["GET"]
+bypass_cookies = ["session", "wordpress_logged_in"]
+cache_key = ["host", "uri", "header:Accept-Language"]
+max_size = "5g"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    let cache = config.services[0].cache.as_ref().unwrap();
+    assert_eq!(
+        cache.key_parts(),
+        vec![
+            CacheKeyPart::Host,
+            CacheKeyPart::Uri,
+            CacheKeyPart::Header("Accept-Language".to_string()),
+        ]
+    );
+    assert_eq!(cache.keys_zone_size(), "51m");
+    assert_eq!(CacheConfig::default().methods, vec!["GET", "HEAD"]);
+    assert_eq!(CacheConfig::default().keys_zone_size(), "10m");
+
+    for (from, to, message) in [
+        (
+            "methods = [\"GET\"]",
+            "methods = []",
+            "must list at least one method",
+        ),
+        (
+            "methods = [\"GET\"]",
+            "methods = [\"DELETE\"]",
+            "cache methods contains 'DELETE'",
+        ),
+        (
+            "\"session\"",
+            "\"my-session\"",
+            "bypass cookie 'my-session' may only contain",
+        ),
+        (
+            "\"header:Accept-Language\"",
+            "\"cookie:lang\"",
+            "cache_key part 'cookie:lang' must be",
+        ),
+        (
+            "max_size = \"5g\"",
+            "max_size = \"lots\"",
+            "max_size 'lots'",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let error = Config::load(temp_file.path()).unwrap_err().to_string();
+        assert!(error.contains(message), "{error}");
+    }
+
+    // Varnish hashes the URL only, so it cannot tell POST bodies apart
+    let content = content.replace("methods = [\"GET\"]", "methods = [\"GET\", \"POST\"]")
+        + r#"
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+default_upstream = "http://cache:80"
+
+[[proxies]]
+name = "cache"
+type = "varnish"
+layer = 2
+"#;
+    let temp_file = create_temp_config(&content);
+    let error = Config::load(temp_file.path()).unwrap_err().to_string();
+    assert!(error.contains("caches POST responses"), "{error}");
+}
+
+#[test]
+fn test_config_validation_unknown_method() {
+    let content = r#"
+[project]
+name = "methods-test"
+
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+allowed_methods = ["GET", "get"]
+"#;
+
+    let temp_file = create_temp_config(content);
+    let result = Config::load(temp_file.path());
+
+    assert!(result.is_err());
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("Service web-service allowed_methods contains unknown method 'get'")
+    );
+}
+
+#[test]
+fn test_config_validation_service_domains() {
+    let content = r#"
+[project]
+name = "domains-test"
+
+[[services]]
+name = "web"
+domains = ["example.com", "www.example.com"]
+upstream = "http://192.0.2.1:3000"
+
+[[services]]
+name = "apps"
+domain = "apps.example.com"
+domains = ["*.apps.example.com"]
+upstream = "http://192.0.2.2:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert_eq!(
+        config.services[0].hosts(),
+        vec!["example.com", "www.example.com"]
+    );
+    assert_eq!(config.services[0].primary_domain(), "example.com");
+    assert_eq!(
+        config.services[1].hosts(),
+        vec!["apps.example.com", "*.apps.example.com"]
+    );
+
+    for (from, to, message) in [
+        (
+            "domains = [\"example.com\", \"www.example.com\"]",
+            "domains = []",
+            "Service web domain cannot be empty",
+        ),
+        (
+            "\"www.example.com\"",
+            "\"www.*.example.com\"",
+            "domain 'www.*.example.com' is not a host name",
+        ),
+        (
+            "\"www.example.com\"",
+            "\"APPS.example.com\"",
+            "Domain APPS.example.com is served by both services web and apps",
+        ),
+        (
+            "\"www.example.com\"",
+            "\"api.apps.example.com\"",
+            "Domain api.apps.example.com of service web overlaps wildcard *.apps.example.com of service apps",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let result = Config::load(temp_file.path());
+        assert!(
+            result.unwrap_err().to_string().contains(message),
+            "{message}"
+        );
+    }
+
+    assert!(host_matches("*.apps.example.com", "API.apps.example.com"));
+    assert!(!host_matches("*.apps.example.com", "apps.example.com"));
+    assert!(!host_matches("*.apps.example.com", "a.b.apps.example.com"));
+}
+
+#[test]
+fn test_header_scrub_configuration() {
+    let content = r#"
+[project]
+name = "scrub-test"
+
+[security.header_scrub]
+request = ["X-Forwarded-For", "X-Internal-Token"]
+response = ["Server", "X-Powered-By"]
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    let scrub = &config.security.header_scrub;
+    assert_eq!(scrub.request, vec!["X-Forwarded-For", "X-Internal-Token"]);
+    assert_eq!(scrub.response, vec!["Server", "X-Powered-By"]);
+}
+
+#[test]
+fn test_config_validation_header_scrub() {
+    let content = r#"
+[project]
+name = "scrub-test"
+
+[security.header_scrub]
+request = ["Host"]
+"#;
+
+    let temp_file = create_temp_config(content);
+    let result = Config::load(temp_file.path());
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("cannot strip the Host header")
+    );
+
+    let content = r#"
+[project]
+name = "scrub-test"
+
+[security.header_scrub]
+response = ["X-Bad Header"]
+"#;
+
+    let temp_file = create_temp_config(content);
+    let result = Config::load(temp_file.path());
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("invalid header name 'X-Bad Header'")
+    );
+}
+
+#[test]
+fn test_real_ip_configuration() {
+    let content = r#"
+[project]
+name = "real-ip-test"
+
+[security.real_ip]
+provider = "cloudflare"
+trusted = ["198.51.100.0/24"]
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    let real_ip = &config.security.real_ip;
+    assert!(real_ip.is_enabled());
+    assert_eq!(real_ip.header(), "CF-Connecting-IP");
+    let ranges = real_ip.trusted_ranges();
+    assert_eq!(ranges.len(), CLOUDFLARE_RANGES.len() + 1);
+    assert_eq!(ranges.last().unwrap(), "198.51.100.0/24");
+
+    for (from, to, message) in [
+        (
+            "provider = \"cloudflare\"",
+            "provider = \"custom\"\nheader = \"X Client\"",
+            "security.real_ip.header 'X Client' is not a valid header name",
+        ),
+        (
+            "\"198.51.100.0/24\"",
+            "\"198.51.100.0/40\"",
+            "security.real_ip.trusted entry '198.51.100.0/40'",
+        ),
+        (
+            "provider = \"cloudflare\"\ntrusted = [\"198.51.100.0/24\"]",
+            "provider = \"custom\"",
+            "provider 'custom' must list its trusted ranges",
+        ),
+        (
+            "provider = \"cloudflare\"",
+            "",
+            "trusted and header require security.real_ip.provider",
+        ),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let error = Config::load(temp_file.path()).unwrap_err().to_string();
+        assert!(error.contains(message), "{error}");
+    }
+}
+
+#[test]
+fn test_include_merges_files_in_order() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("conf.d")).unwrap();
+    std::fs::write(
+        dir.path().join("config.toml"),
+        r#"
+include = ["proxies.toml", "conf.d/*.toml"]
+
+[project]
+name = "include-test"
+
+[[services]]
+name = "main"
+domain = "main.example.com"
+upstream = "http://192.0.2.1:3000"
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("proxies.toml"),
+        "[[proxies]]\nname = \"proxy\"\ntype = \"caddy\"\nexternal_port = 80\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("conf.d/20-blog.toml"),
+        "[[services]]\nname = \"blog\"\ndomain = \"blog.example.com\"\nupstream = \"http://192.0.2.2:3000\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("conf.d/10-api.toml"),
+        "[[services]]\nname = \"api\"\ndomain = \"api.example.com\"\nupstream = \"http://192.0.2.3:3000\"\n\n[anubis]\nenabled = true\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("conf.d/notes.txt"), "not toml").unwrap();
+
+    let config_path = dir.path().join("config.toml");
+    let config = Config::load(&config_path).expect("Failed to load config");
+
+    assert_eq!(config.proxies[0].name, "proxy");
+    let names: Vec<&str> = config.services.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["main", "api", "blog"]);
+    assert!(config.anubis.enabled);
+
+    let included = included_files(&config_path).unwrap();
+    assert_eq!(
+        included,
+        vec![
+            dir.path().join("proxies.toml"),
+            dir.path().join("conf.d/10-api.toml"),
+            dir.path().join("conf.d/20-blog.toml"),
+        ]
+    );
+}
+
+#[test]
+fn test_include_errors_name_the_file() {
+    let cases = [
+        (
+            "[project]\nname = \"other\"\n",
+            "extra.toml: project.name is already set by an earlier configuration file",
+        ),
+        ("[[services]\n", "TOML parsing error in"),
+        (
+            "include = [\"more.toml\"]\n",
+            "extra.toml: include is only supported in the main configuration file",
+        ),
+    ];
+
+    for (extra, expected) in cases {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("config.toml"),
+            "include = [\"extra.toml\"]\n\n[project]\nname = \"include-test\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("extra.toml"), extra).unwrap();
+
+        let error = Config::load(&dir.path().join("config.toml"))
+            .expect_err("include should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+        // Every error names the included file
+        assert!(error.contains("extra.toml"));
+    }
+
+    let temp_file = create_temp_config("include = [\"missing.toml\"]\n[project]\nname = \"x\"\n");
+    assert!(
+        Config::load(temp_file.path())
+            .unwrap_err()
+            .to_string()
+            .contains("missing.toml does not exist")
+    );
+}
+
+/// Base configuration published by a platform team
+const BASE_CONFIG: &str = r#"
+[project]
+name = "platform-base"
+
+[anubis]
+enabled = true
+difficulty = 4
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+default_upstream = "http://anubis:8080"
+
+[security.header_scrub]
+response = ["Server", "X-Powered-By"]
+"#;
+
+#[test]
+fn test_extends_local_base() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("base")).unwrap();
+    std::fs::write(dir.path().join("base/config.toml"), BASE_CONFIG).unwrap();
+    std::fs::write(
+        dir.path().join("config.toml"),
+        r#"
+extends = "base/config.toml"
+
+[project]
+name = "product"
+
+[anubis]
+difficulty = 6
+
+[[proxies]]
+name = "proxy"
+external_port = 8080
+
+[[services]]
+name = "app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[security.header_scrub]
+response = ["Server"]
+"#,
+    )
+    .unwrap();
+
+    let config_path = dir.path().join("config.toml");
+    let config = Config::load(&config_path).expect("Failed to load config");
+    assert_eq!(config.project.name, "product");
+    assert!(config.anubis.enabled);
+    assert_eq!(config.anubis.difficulty, 6);
+    // Entries with the same name are merged, not duplicated
+    assert_eq!(config.proxies.len(), 1);
+    assert_eq!(config.proxies[0].external_port, Some(8080));
+    assert_eq!(
+        config.proxies[0].default_upstream.as_deref(),
+        Some("http://anubis:8080")
+    );
+    assert_eq!(config.services[0].name, "app");
+    assert_eq!(config.security.header_scrub.response, ["Server"]);
+
+    assert_eq!(
+        local_base(&config_path).unwrap(),
+        Some(dir.path().join("base/config.toml"))
+    );
+    // Local bases are read as they are; there is nothing to pin
+    assert_eq!(update_lock(&config_path).unwrap(), None);
+    assert!(!dir.path().join(LOCK_FILE).exists());
+}
+
+#[test]
+fn test_config_provenance() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("base")).unwrap();
+    std::fs::create_dir(dir.path().join("conf.d")).unwrap();
+    std::fs::write(dir.path().join("base/config.toml"), BASE_CONFIG).unwrap();
+    std::fs::write(
+        dir.path().join("config.toml"),
+        r#"
+extends = "base/config.toml"
+include = ["conf.d/*.toml"]
+
+[project]
+name = "product"
+
+[[proxies]]
+name = "proxy"
+external_port = 8080
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("conf.d/app.toml"),
+        r#"
+[[services]]
+name = "app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#,
+    )
+    .unwrap();
+
+    let config_path = dir.path().join("config.toml");
+    let config = Config::load(&config_path).expect("Failed to load config");
+    let main = config_path.display().to_string();
+    let include = dir.path().join("conf.d/app.toml").display().to_string();
+    let base = dir.path().join("base/config.toml").display().to_string();
+
+    let effective = effective_toml(&config_path, &config).expect("Should render");
+    for line in [
+        format!("name = \"product\" # {main}"),
+        format!("external_port = 8080 # {main}"),
+        format!("default_upstream = \"http://anubis:8080\" # {base}"),
+        format!("difficulty = 4 # {base}"),
+        format!("upstream = \"http://192.0.2.1:3000\" # {include}"),
+        "max_body_size = \"1m\" # default".to_string(),
+    ] {
+        assert!(
+            effective.contains(&format!("{line}\n")),
+            "{effective} should contain {line}"
+        );
+    }
+    // The effective configuration loads back to the same configuration
+    let reloaded: Config = toml::from_str(&effective).expect("Effective config should parse");
+    assert_eq!(reloaded, config);
+
+    let merged = merged_toml(&config_path).expect("Should render");
+    assert!(!merged.contains("extends"));
+    assert!(!merged.contains("max_body_size"));
+    assert!(merged.contains("upstream = \"http://192.0.2.1:3000\""));
+}
+
+#[test]
+fn test_extends_remote_base_from_lock() {
+    let source = "github.com/ourorg/cerberus-base/config.toml@v3";
+    let sha256 = crate::checksum::sha256_hex(BASE_CONFIG.as_bytes());
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("config.toml"),
+        format!("extends = \"{source}\"\n\n[project]\nname = \"product\"\n"),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join(LOCK_FILE),
+        format!(
+            "[extends]\nsource = \"{source}\"\nrepository = \"https://github.com/ourorg/cerberus-base\"\n\
+revision = \"0123456789abcdef0123456789abcdef01234567\"\nsha256 = \"{sha256}\"\n"
+        ),
+    )
+    .unwrap();
+    let cache = dir.path().join(".cerberus/extends");
+    std::fs::create_dir_all(&cache).unwrap();
+    std::fs::write(cache.join(format!("{sha256}.toml")), BASE_CONFIG).unwrap();
+
+    // The pinned base is served from the cache without contacting the repository
+    let config = Config::load(&dir.path().join("config.toml")).expect("Failed to load config");
+    assert_eq!(config.project.name, "product");
+    assert_eq!(config.proxies[0].name, "proxy");
+    assert_eq!(config.anubis.difficulty, 4);
+}
+
+#[test]
+fn test_extends_errors() {
+    let cases = [
+        ("extends = \"missing.toml\"\n", "base configuration"),
+        (
+            "extends = \"github.com/ourorg@v3\"\n",
+            "must be a local path or host/owner/repo/path/to/config.toml@ref",
+        ),
+        (
+            "extends = 3\n",
+            "extends must be a path or repository reference",
+        ),
+    ];
+    for (extends, expected) in cases {
+        let temp_file = create_temp_config(&format!("{extends}[project]\nname = \"x\"\n"));
+        let error = Config::load(temp_file.path()).unwrap_err().to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("base.toml"), "extends = \"other.toml\"\n").unwrap();
+    std::fs::write(
+        dir.path().join("config.toml"),
+        "extends = \"./base.toml\"\n[project]\nname = \"x\"\n",
+    )
+    .unwrap();
+    assert!(
+        Config::load(&dir.path().join("config.toml"))
+            .unwrap_err()
+            .to_string()
+            .contains("a base configuration cannot use extends or include")
+    );
+}
+
+#[test]
+fn test_outputs_configuration() {
+    let content = r#"
+[project]
+name = "outputs-test"
+
+[outputs.compose]
+path = "built/"
+
+[outputs.nomad]
+path = "nomad/"
+namespace = "edge"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    assert_eq!(config.outputs.len(), 2);
+    let nomad = &config.outputs[&DeployTarget::Nomad];
+    // Relative paths are resolved against the configuration file
+    let config_dir = temp_file.path().parent().unwrap();
+    assert_eq!(nomad.path, config_dir.join("nomad/").display().to_string());
+
+    let rendered = config.for_output(DeployTarget::Nomad, nomad);
+    assert_eq!(rendered.project.target, DeployTarget::Nomad);
+    assert_eq!(rendered.nomad.namespace.as_deref(), Some("edge"));
+}
+
+#[test]
+fn test_output_paths_resolve_against_config_file() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("deploy")).unwrap();
+    let stack_dir = dir.path().join("stack");
+    let config_path = dir.path().join("deploy/config.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            r#"
+[project]
+name = "outputs-test"
+
+[outputs.compose]
+path = "built"
+
+[outputs.swarm]
+path = "{}"
+"#,
+            stack_dir.display()
+        ),
+    )
+    .unwrap();
+
+    let config = Config::load(&config_path).expect("Failed to load config");
+    assert_eq!(
+        config.outputs[&DeployTarget::Compose].path,
+        dir.path().join("deploy/built").display().to_string()
+    );
+    // Absolute paths are kept as they are
+    assert_eq!(
+        config.outputs[&DeployTarget::Swarm].path,
+        stack_dir.display().to_string()
+    );
+}
+
+#[test]
+fn test_config_validation_swarm() {
+    let content = r#"
+[project]
+name = "swarm-test"
+target = "swarm"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web"
+domain = "app.example.com"
+upstream = "http://web:3000"
+image = "nginx:alpine"
+
+[networks.front-net]
+driver = "bridge"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert_eq!(config.project.target, DeployTarget::Swarm);
+
+    for (from, to, message) in [
+        (
+            "image = \"nginx:alpine\"",
+            "[services.build]\ncontext = \"./web\"",
+            "Service web has a [services.build] section",
+        ),
+        (
+            "image = \"nginx:alpine\"",
+            "image = \"nginx:alpine\"\nplatform = \"linux/arm64\"",
+            "Service web sets platform",
+        ),
+        (
+            "external_port = 80",
+            "external_port = 80\nplatforms = [\"linux/amd64\", \"linux/arm64\"]",
+            "Proxy proxy sets platform",
+        ),
+        (
+            "external_port = 80",
+            "external_port = 80\npull_policy = \"always\"",
+            "Proxy proxy sets pull_policy",
+        ),
+        (
+            "image = \"nginx:alpine\"",
+            "image = \"nginx:alpine\"\ndepends_on = { proxy = { condition = \"service_started\" } }",
+            "Service web sets depends_on conditions",
+        ),
+        (
+            "image = \"nginx:alpine\"",
+            "image = \"nginx:alpine\"\nenvironment = { TOKEN = \"vault://kv/web#token\" }",
+            "Service web reads vault:// environment values",
+        ),
+        (
+            "driver = \"bridge\"",
+            "driver = \"macvlan\"",
+            "Network front-net uses the macvlan driver",
+        ),
+        (
+            "target = \"swarm\"",
+            "[waf]\nenabled = true",
+            "Proxy proxy builds its image for the WAF",
+        ),
+    ] {
+        let content = content.replace(from, to);
+        // Declaring a swarm output checks the same options
+        let content = content.replace("target = \"swarm\"", "").replace(
+            "[[proxies]]",
+            "[outputs.swarm]\npath = \"stack\"\n\n[[proxies]]",
+        );
+        let temp_file = create_temp_config(&content);
+        let result = Config::load(temp_file.path());
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains(message), "{message}: {error}");
+    }
+}
+
+#[test]
+fn test_config_validation_outputs() {
+    let cases = [
+        (
+            "[outputs.compose]\npath = \"\"",
+            "outputs.compose path cannot be empty",
+        ),
+        (
+            "[outputs.compose]\npath = \"built\"\n[outputs.nomad]\npath = \"built/\"",
+            "is already used by another output",
+        ),
+        (
+            "[outputs.compose]\npath = \"built\"\nnamespace = \"edge\"",
+            "outputs.compose does not support namespace",
+        ),
+        ("[outputs.k8s]\npath = \"k8s\"", "unknown variant `k8s`"),
+    ];
+
+    for (outputs, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "outputs-test"
+
+{outputs}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("outputs should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_crowdsec_configuration() {
+    let content = r#"
+[project]
+name = "crowdsec-test"
+
+[security.crowdsec]
+enabled = true
+collections = ["crowdsecurity/http-cve", "crowdsecurity/nginx"]
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+
+[[proxies]]
+name = "proxy-2"
+type = "haproxy"
+internal_port = 8080
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    let crowdsec = &config.security.crowdsec;
+    assert!(crowdsec.enabled);
+    assert_eq!(crowdsec.image, "crowdsecurity/crowdsec:latest");
+    assert_eq!(crowdsec.api_key_env, "CROWDSEC_BOUNCER_KEY");
+    assert_eq!(
+        crowdsec.collections_for(&config.proxies),
+        vec![
+            "crowdsecurity/nginx",
+            "crowdsecurity/haproxy",
+            "crowdsecurity/http-cve"
+        ]
+    );
+
+    let content = r#"
+[project]
+name = "crowdsec-test"
+
+[security.crowdsec]
+enabled = true
+api_key_env = "1BAD-KEY"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let result = Config::load(temp_file.path());
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("'1BAD-KEY' is not a valid environment variable name")
+    );
+}
+
+#[test]
+fn test_service_docker_options() {
+    let content = r#"
+[project]
+name = "backend-test"
+
+[[services]]
+name = "app"
+domain = "app.example.com"
+upstream = "http://app:3000"
+image = "ghcr.io/example/app:latest"
+ports = ["127.0.0.1:3000:3000"]
+volumes = ["app_data:/data"]
+depends_on = ["db"]
+replicas = 2
+
+[services.environment]
+RUST_LOG = "info"
+
+[services.healthcheck]
+test = ["CMD", "true"]
+
+[services.build]
+context = "./app"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    let service = &config.services[0];
+    assert!(service.has_container());
+    assert_eq!(service.image.as_deref(), Some("ghcr.io/example/app:latest"));
+    assert_eq!(service.build.as_ref().unwrap().context, "./app");
+    assert_eq!(service.ports, vec!["127.0.0.1:3000:3000"]);
+    assert_eq!(service.volumes, vec!["app_data:/data"]);
+    assert_eq!(service.environment["RUST_LOG"], "info");
+    assert_eq!(
+        service.depends_on,
+        Some(DependsOn::Simple(vec!["db".to_string()]))
+    );
+    assert_eq!(service.healthcheck.as_ref().unwrap().retries, 3);
+    assert_eq!(service.replicas, Some(2));
+    assert!(service.headers.is_empty());
+}
+
+#[test]
+fn test_proxy_entrypoint_and_command() {
+    let content = r#"
+[project]
+name = "command-test"
+
+[[proxies]]
+name = "proxy"
+type = "haproxy"
+external_port = 80
+entrypoint = ["haproxy"]
+command = "-f '/usr/local/etc/haproxy/haproxy.cfg' -db"
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+command = ["nginx-debug", "-g", "daemon off;"]
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    let proxy = &config.proxies[0];
+    assert_eq!(
+        proxy.entrypoint,
+        Some(CommandConfig::Exec(vec!["haproxy".to_string()]))
+    );
+    assert_eq!(
+        proxy.command.as_ref().unwrap().args(),
+        vec!["-f", "/usr/local/etc/haproxy/haproxy.cfg", "-db"]
+    );
+    assert_eq!(
+        config.proxies[1].command.as_ref().unwrap().args(),
+        vec!["nginx-debug", "-g", "daemon off;"]
+    );
+}
+
+#[test]
+fn test_config_validation_proxy_command() {
+    let cases = [
+        (
+            "haproxy",
+            "entrypoint = \"\"",
+            "Proxy proxy entrypoint cannot be empty",
+        ),
+        (
+            "nginx",
+            "command = []",
+            "Proxy proxy command cannot be empty",
+        ),
+        (
+            "haproxy",
+            "entrypoint = [\"haproxy\", \"-db\"]",
+            "overrides the haproxy entrypoint/command without the config path",
+        ),
+        (
+            "caddy",
+            "command = \"caddy run\"",
+            "pass a file under /etc/caddy",
+        ),
+    ];
+
+    for (proxy_type, override_line, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "command-test"
+
+[[proxies]]
+name = "proxy"
+type = "{proxy_type}"
+{override_line}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("override should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_detailed_depends_on() {
+    let content = r#"
+[project]
+name = "depends-test"
+
+[[services]]
+name = "app"
+domain = "app.example.com"
+upstream = "http://app:3000"
+image = "ghcr.io/example/app:latest"
+
+[services.depends_on.db]
+condition = "service_healthy"
+restart = true
+
+[[services]]
+name = "db"
+domain = "db.internal"
+upstream = "http://db:5432"
+image = "postgres:16"
+
+[services.healthcheck]
+test = ["CMD", "pg_isready"]
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+
+    let Some(DependsOn::Detailed(conditions)) = &config.services[0].depends_on else {
+        panic!("depends_on should be detailed");
+    };
+    assert_eq!(conditions["db"].condition, "service_healthy");
+    assert_eq!(conditions["db"].restart, Some(true));
+}
+
+#[test]
+fn test_config_validation_depends_on() {
+    let cases = [
+        (
+            "service_healthy",
+            "image = \"postgres:16\"",
+            "but db defines no healthcheck",
+        ),
+        (
+            "service_ready",
+            "image = \"postgres:16\"",
+            "depends_on.db has unknown condition 'service_ready'",
+        ),
+    ];
+
+    for (condition, db, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "depends-test"
+
+[[services]]
+name = "app"
+domain = "app.example.com"
+upstream = "http://app:3000"
+depends_on = {{ db = {{ condition = "{condition}" }} }}
+
+[[services]]
+name = "db"
+domain = "db.internal"
+upstream = "http://db:5432"
+{db}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("depends_on should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+
+    let temp_file = create_temp_config(
+        r#"
+[project]
+name = "depends-test"
+
+[[services]]
+name = "app"
+domain = "app.example.com"
+upstream = "http://app:3000"
+depends_on = { redis = { condition = "service_healthy" } }
+"#,
+    );
+    assert!(
+        Config::load(temp_file.path())
+            .unwrap_err()
+            .to_string()
+            .contains("no service named redis is defined")
+    );
+}
+
+#[test]
+fn test_config_validation_service_replicas() {
+    let content = r#"
+[project]
+name = "backend-test"
+
+[[services]]
+name = "app"
+domain = "app.example.com"
+upstream = "http://app:3000"
+replicas = 0
+"#;
+
+    let temp_file = create_temp_config(content);
+    let result = Config::load(temp_file.path());
+
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("Service app replicas must be greater than 0")
+    );
+}
+
+#[test]
+fn test_parse_duration_secs() {
+    for (value, secs) in [
+        ("30", Some(30)),
+        ("30s", Some(30)),
+        ("10m", Some(600)),
+        ("1h", Some(3600)),
+        ("7d", Some(604_800)),
+        ("1.5h", None),
+        ("soon", None),
+        // Overflowing u64 is rejected rather than wrapped
+        ("300000000000000d", None),
+    ] {
+        assert_eq!(parse_duration_secs(value), secs, "{value}");
+    }
+}
+
+#[test]
+fn test_max_body_size() {
+    for (value, bytes) in [
+        ("0", Some(0)),
+        ("512", Some(512)),
+        ("512k", Some(512 * 1024)),
+        ("1m", Some(1024 * 1024)),
+        ("100MB", Some(100 * 1024 * 1024)),
+        ("10G", Some(10 * 1024 * 1024 * 1024)),
+        ("1.5m", None),
+        ("lots", None),
+    ] {
+        assert_eq!(parse_size_bytes(value), bytes, "{value}");
+    }
+
+    let content = r#"
+[project]
+name = "backend-test"
+
+[[services]]
+name = "app"
+domain = "app.example.com"
+upstream = "http://app:3000"
+max_body_size = "ten megabytes"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let error = Config::load(temp_file.path()).unwrap_err().to_string();
+    assert!(
+        error.contains("Service app max_body_size must be a size"),
+        "{error}"
+    );
+}
+
+#[test]
+fn test_config_validation_schedule() {
+    let base = r#"
+[project]
+name = "schedule-test"
+
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+
+[[services.schedules]]
+"#;
+
+    let cases = [
+        ("active_hours = \"9am-5pm\"", "must be \"HH:MM-HH:MM\""),
+        ("active_days = [\"monday\"]", "active_days must be one of"),
+        ("active_from = \"2025-13-01\"", "is not a YYYY-MM-DD date"),
+        (
+            "active_from = \"2025-02-01\"\nactive_until = \"2025-01-01\"",
+            "active_from must not be after active_until",
+        ),
+        ("path = \"/signup\"", "at least one of active_hours"),
+        (
+            "path = \"signup\"\nactive_hours = \"09:00-17:00\"",
+            "path must start with '/'",
+        ),
+    ];
+
+    for (schedule, expected) in cases {
+        let temp_file = create_temp_config(&format!("{base}{schedule}\n"));
+        let error = Config::load(temp_file.path())
+            .expect_err("Schedule should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_deployment_strategy_configuration() {
+    let content = r#"
+[project]
+name = "deploy-test"
+
+[deployment.strategy]
+type = "canary"
+weight = 25
+
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://web-stable:3000"
+candidate_upstream = "http://web-canary:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).unwrap();
+
+    let strategy = config.deployment.strategy.unwrap();
+    assert_eq!(strategy.strategy_type, StrategyType::Canary);
+    assert_eq!(strategy.pool_labels(), ("stable", "canary"));
+    assert_eq!(strategy.candidate_weight(), 25);
+    assert_eq!(
+        config.services[0].candidate_upstream.as_deref(),
+        Some("http://web-canary:3000")
+    );
+}
+
+#[test]
+fn test_config_validation_deployment_strategy() {
+    let service = r#"
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://web-blue:3000"
+candidate_upstream = "http://web-green:3000"
+"#;
+
+    let cases = [
+        ("", "candidate_upstream requires [deployment.strategy]"),
+        (
+            "[deployment.strategy]\ntype = \"canary\"\nweight = 150",
+            "weight must be between 0 and 100",
+        ),
+    ];
+
+    for (deployment, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            "[project]\nname = \"deploy-test\"\n\n{deployment}\n{service}"
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("Deployment should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_config_validation_observability() {
+    let base = r#"
+[project]
+name = "observability-test"
+
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+
+[services.observability]
+"#;
+
+    let temp_file = create_temp_config(&format!("{base}team = \"web\"\nslo_target = 99.95\n"));
+    let config = Config::load(temp_file.path()).unwrap();
+    assert_eq!(
+        config.services[0].observability.as_ref().unwrap().labels(),
+        vec![
+            ("team", "web".to_string()),
+            ("slo_target", "99.95".to_string())
+        ]
+    );
+
+    for (observability, expected) in [
+        ("slo_target = 120.0", "slo_target must be a percentage"),
+        (
+            "team = \"\"",
+            "observability.team is not a valid label value",
+        ),
+    ] {
+        let temp_file = create_temp_config(&format!("{base}{observability}\n"));
+        let error = Config::load(temp_file.path())
+            .expect_err("Observability should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_waf_configuration() {
+    let content = r#"
+[project]
+name = "waf-test"
+
+[waf]
+enabled = true
+paranoia_level = 2
+mode = "detect"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+
+[services.waf]
+exclude_rules = [942100, 920350]
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).unwrap();
+
+    assert_eq!(config.waf.paranoia_level, 2);
+    assert_eq!(config.waf.rule_engine(), "DetectionOnly");
+    assert!(config.waf.audit_log);
+    assert!(config.waf.applies_to(&config.proxies[0]));
+    assert!(config.services[0].waf.enabled);
+    assert_eq!(config.services[0].waf.exclude_rules, vec![942100, 920350]);
+}
+
+#[test]
+fn test_config_validation_waf() {
+    let cases = [
+        (
+            "caddy",
+            1,
+            "paranoia_level = 5",
+            "waf.paranoia_level must be between 1 and 4",
+        ),
+        (
+            "haproxy",
+            2,
+            "",
+            "waf.enabled requires a Caddy proxy or a layer-2 Nginx proxy",
+        ),
+        (
+            "nginx",
+            1,
+            "",
+            "waf.enabled requires a Caddy proxy or a layer-2 Nginx proxy",
+        ),
+    ];
+
+    for (proxy_type, layer, waf, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "waf-test"
+
+[waf]
+enabled = true
+{waf}
+
+[[proxies]]
+name = "proxy"
+type = "{proxy_type}"
+layer = {layer}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("WAF config should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_config_validation_instances_and_replicas() {
+    let config_for = |target: &str, instances: u8, replicas: u32| {
+        format!(
+            r#"
+[project]
+name = "scaling-test"
+scaling = true
+target = "{target}"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+instances = {instances}
+
+[proxies.deploy]
+replicas = {replicas}
+"#
+        )
+    };
+
+    // Compose would multiply duplicated containers by replicas
+    let temp_file = create_temp_config(&config_for("compose", 3, 3));
+    let error = Config::load(temp_file.path()).unwrap_err().to_string();
+    assert!(error.contains("sets both instances = 3 and deploy.replicas = 3"));
+
+    // Nomad has a single count, so matching values are reconciled
+    let temp_file = create_temp_config(&config_for("nomad", 3, 3));
+    let config = Config::load(temp_file.path()).unwrap();
+    assert_eq!(config.proxies[0].container_count(true), 3);
+    assert_eq!(config.proxies[0].duplicated_instances(true), 1);
+
+    let temp_file = create_temp_config(&config_for("nomad", 3, 2));
+    assert!(Config::load(temp_file.path()).is_err());
+
+    let temp_file = create_temp_config(&config_for("compose", 1, 0));
+    let error = Config::load(temp_file.path()).unwrap_err().to_string();
+    assert!(error.contains("deploy.replicas must be greater than 0"));
+}
+
+#[test]
+fn test_config_validation_ipv6() {
+    let cases = [
+        (
+            "ipv6 = true",
+            "[networks.front-net]",
+            "project.ipv6 is set, but proxy edge publishes port 80 without joining \
+             a network with enable_ipv6",
+        ),
+        (
+            "",
+            "[networks.front-net]\nenable_ipv6 = true",
+            "Network front-net enables IPv6, but proxy edge listens on IPv4 only",
+        ),
+        (
+            "ipv6 = true",
+            "[networks.front-net]\nenable_ipv6 = true\n\n[networks.front-net.ipam]\nconfig = [{ subnet = \"10.100.0.0/16\" }]",
+            "Network front-net enables IPv6, but its ipam.config has no IPv6 subnet",
+        ),
+        (
+            "",
+            "[networks.back-net]\n\n[networks.back-net.ipam]\nconfig = [{ subnet = \"fd00:1::/64\" }]",
+            "Network back-net has an IPv6 subnet but enable_ipv6 is false",
+        ),
+    ];
+
+    for (project, networks, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "ipv6-test"
+{project}
+
+{networks}
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+networks = ["front-net"]
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("mismatched IPv6 networks should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+
+    let temp_file = create_temp_config(
+        r#"
+[project]
+name = "ipv6-test"
+ipv6 = true
+
+[networks.front-net]
+enable_ipv6 = true
+
+[networks.front-net.ipam]
+config = [{ subnet = "10.100.0.0/16" }, { subnet = "fd00:1::/64" }]
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+networks = ["front-net"]
+"#,
+    );
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert!(config.project.ipv6);
+}
+
+#[test]
+fn test_config_validation_error_pages() {
+    let cases = [
+        (
+            "nginx",
+            "[error_pages.pages.302]\nhtml = \"<h1>Moved</h1>\"",
+            "error_pages.pages key '302' must be an HTTP status code from 400 to 599",
+        ),
+        (
+            "nginx",
+            "[error_pages.pages.404]\nfile = \"404.html\"\nhtml = \"<h1>Not found</h1>\"",
+            "error_pages.pages.404 sets both file and html",
+        ),
+        (
+            "nginx",
+            "[error_pages.pages.404]",
+            "error_pages.pages.404 needs a file or html",
+        ),
+        (
+            "haproxy",
+            "[error_pages.pages.418]\nhtml = \"<h1>Teapot</h1>\"",
+            "error_pages.pages.418 cannot be served by haproxy proxy edge",
+        ),
+    ];
+
+    for (proxy_type, error_pages, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "error-pages-test"
+
+{error_pages}
+
+[[proxies]]
+name = "edge"
+type = "{proxy_type}"
+external_port = 80
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("invalid error pages should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+
+    let temp_file = create_temp_config(
+        r#"
+[project]
+name = "error-pages-test"
+
+[error_pages]
+maintenance = true
+
+[error_pages.pages.404]
+html = "<h1>Not found</h1>"
+
+[error_pages.pages.503]
+file = "errors/503.html"
+
+[[proxies]]
+name = "edge"
+type = "haproxy"
+external_port = 80
+"#,
+    );
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert_eq!(config.error_pages.statuses(), vec![404, 502, 503, 504]);
+    assert!(config.error_pages.page(502).is_none());
+}
+
+#[test]
+fn test_config_validation_internal_networks() {
+    let cases = [
+        (
+            "[networks.back-net]\ninternal = true\nexternal = true",
+            "",
+            "Network back-net cannot be both external and internal",
+        ),
+        (
+            "[networks.back-net]\ninternal = true",
+            "[[proxies]]\nname = \"edge\"\ntype = \"caddy\"\nexternal_port = 80\nnetworks = [\"back-net\"]",
+            "only joins internal networks",
+        ),
+        (
+            "[networks.front-net]\n[networks.back-net]\ninternal = true",
+            "[[proxies]]\nname = \"tier-2\"\ntype = \"nginx\"\nexternal_port = 8080\nlayer = 2",
+            "only layer 1 may publish host ports",
+        ),
+        (
+            "[networks.back-net]\ninternal = true",
+            "[[services]]\nname = \"app\"\ndomain = \"app.example.com\"\nupstream = \"http://app:3000\"\nimage = \"app:latest\"\nports = [\"3000:3000\"]",
+            "Service app publishes ports but back-net is internal",
+        ),
+    ];
+
+    for (networks, component, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "internal-test"
+
+{networks}
+
+{component}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("internal network misuse should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+
+    let temp_file = create_temp_config(
+        r#"
+[project]
+name = "internal-test"
+
+[networks.front-net]
+
+[networks.back-net]
+internal = true
+
+[[proxies]]
+name = "edge"
+type = "caddy"
+external_port = 80
+layer = 1
+
+[[proxies]]
+name = "tier-2"
+type = "nginx"
+layer = 2
+expose = ["80"]
+"#,
+    );
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert!(config.is_internal_network("back-net"));
+    assert!(!config.is_internal_network("front-net"));
+    assert_eq!(config.proxies[1].expose, vec!["80"]);
+}
+
+#[test]
+fn test_config_validation_proxy_stats() {
+    let cases = [
+        (
+            "caddy",
+            "port = 9100",
+            "stats is only supported for haproxy",
+        ),
+        ("haproxy", "port = 80", "stats port 80 must be non-zero"),
+        ("haproxy", "port = 8405", "stats port 8405 must be non-zero"),
+        (
+            "haproxy",
+            "credentials = \"ops\"",
+            "stats credentials must be user:password",
+        ),
+        (
+            "haproxy",
+            "credentials = \"ops:s3 cret\"",
+            "stats credentials must be user:password",
+        ),
+    ];
+
+    for (proxy_type, stats, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "stats-test"
+
+[[proxies]]
+name = "proxy"
+type = "{proxy_type}"
+external_port = 80
+
+[proxies.stats]
+{stats}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("stats should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_ops_route() {
+    let content = r#"
+[project]
+name = "ops-test"
+
+[ops]
+domain = "ops.example.com"
+users = ['admin:$2y$10$abcdefghijklmnopqrstuuJ0Mtr3mpdwQEH7ETPZ6WWfxyIS5ZH.q']
+allow = ["10.0.0.0/8"]
+
+[[proxies]]
+name = "edge"
+type = "caddy"
+external_port = 80
+
+[[proxies]]
+name = "lb"
+type = "haproxy"
+layer = 2
+
+[proxies.stats]
+port = 9100
+
+[[services]]
+name = "web"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+
+    let temp_file = create_temp_config(content);
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert_eq!(
+        config.ops_dashboards(),
+        vec![OpsDashboard {
+            proxy: "lb".to_string(),
+            address: "lb:9100".to_string(),
+            path: "/stats",
+        }]
+    );
+    // The route replaces the published statistics port
+    assert!(config.published_stats(&config.proxies[1]).is_none());
+
+    for (from, to, message) in [
+        (
+            "domain = \"ops.example.com\"",
+            "domain = \"*.example.com\"",
+            "must be a host name",
+        ),
+        (
+            "domain = \"ops.example.com\"",
+            "domain = \"example.com\"",
+            "already served by service web",
+        ),
+        (
+            "domain = \"ops.example.com\"",
+            "",
+            "ops users and allow require ops.domain",
+        ),
+        ("users = ['admin:", "users = []\n#", "at least one user"),
+        (
+            "users = ['admin:$2y$",
+            "users = ['admin:plain",
+            "ops user 'admin'",
+        ),
+        (
+            "\"10.0.0.0/8\"",
+            "\"10.0.0.0/33\"",
+            "ops allow entry '10.0.0.0/33'",
+        ),
+        ("port = 9100", "enabled = false", "no dashboards to serve"),
+        ("external_port = 80", "layer = 1", "requires an edge proxy"),
+    ] {
+        let temp_file = create_temp_config(&content.replace(from, to));
+        let error = Config::load(temp_file.path()).unwrap_err().to_string();
+        assert!(error.contains(message), "{error}");
+    }
+}
+
+#[test]
+fn test_config_builder_matches_toml_defaults() {
+    let built = ConfigBuilder::new("builder-test")
+        .proxy(
+            ProxyConfigBuilder::new("edge", ProxyType::Caddy)
+                .external_port(80)
+                .layer(1)
+                .build()
+                .expect("Proxy should build"),
+        )
+        .service(
+            ServiceConfigBuilder::new("web-app", "app.example.com", "http://192.0.2.1:3000")
+                .websocket(true)
+                .build()
+                .expect("Service should build"),
+        )
+        .build()
+        .expect("Config should build");
+
+    let temp_file = create_temp_config(
+        r#"
+[project]
+name = "builder-test"
+
+[[proxies]]
+name = "edge"
+type = "caddy"
+external_port = 80
+layer = 1
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+websocket = true
+"#,
+    );
+    let loaded = Config::load(temp_file.path()).expect("Failed to load config");
+
+    assert_eq!(built, loaded);
+}
+
+#[test]
+fn test_config_builder_normalizes_upstreams() {
+    let built = ConfigBuilder::new("builder-test")
+        .proxy(
+            ProxyConfigBuilder::new("edge", ProxyType::Caddy)
+                .external_port(80)
+                .build()
+                .expect("Proxy should build"),
+        )
+        .service(
+            ServiceConfigBuilder::new("web-app", "app.example.com", "HTTP://Web-App:3000")
+                .build()
+                .expect("Service should build"),
+        )
+        .build()
+        .expect("Config should build");
+
+    assert_eq!(built.services[0].upstream, "http://web-app:3000");
+}
+
+#[test]
+fn test_config_builder_validation() {
+    let error = ProxyConfigBuilder::new("edge", ProxyType::Caddy)
+        .instances(0)
+        .build()
+        .expect_err("zero instances should be rejected")
+        .to_string();
+    assert!(error.contains("Proxy edge instances must be greater than 0"));
+
+    let error = ServiceConfigBuilder::new("web-app", "", "http://192.0.2.1:3000")
+        .build()
+        .expect_err("empty domain should be rejected")
+        .to_string();
+    assert!(error.contains("Service web-app domain cannot be empty"));
+
+    let error = ConfigBuilder::new(" ")
+        .build()
+        .expect_err("empty project name should be rejected")
+        .to_string();
+    assert!(error.contains("Project name cannot be empty"));
+}
+
+#[test]
+fn test_notifications_config_parsing() {
+    let temp_file = create_temp_config(
+        r#"
+[project]
+name = "notify-test"
+
+[notifications.channels.ops]
+type = "discord"
+webhook_url = "https://discord.com/api/webhooks/1/abc"
+
+[notifications.channels.security]
+type = "smtp"
+url = "smtps://mail.example.com:465"
+from = "cerberus@example.com"
+to = ["security@example.com"]
+username = "cerberus"
+password_env = "SMTP_PASSWORD"
+
+[notifications.routes]
+scaling = ["ops"]
+cert_expiry = ["security", "ops"]
+"#,
+    );
+
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    let notifications = &config.notifications;
+
+    assert_eq!(notifications.channels.len(), 2);
+    assert_eq!(
+        notifications.channels["ops"],
+        ChannelConfig::Discord {
+            webhook_url: "https://discord.com/api/webhooks/1/abc".to_string()
+        }
+    );
+    assert_eq!(
+        notifications.routes[&NotificationEvent::CertExpiry],
+        vec!["security".to_string(), "ops".to_string()]
+    );
+    assert!(
+        !notifications
+            .routes
+            .contains_key(&NotificationEvent::Deploy)
+    );
+}
+
+#[test]
+fn test_invalid_notifications_rejected() {
+    let cases = [
+        (
+            "type = \"webhook\"\nurl = \"ftp://example.com/hook\"",
+            "scaling = [\"ops\"]",
+            "must start with http:// or https://",
+        ),
+        (
+            "type = \"smtp\"\nurl = \"https://mail.example.com\"\nfrom = \"a@example.com\"\nto = [\"b@example.com\"]",
+            "",
+            "must start with smtp:// or smtps://",
+        ),
+        (
+            "type = \"smtp\"\nurl = \"smtp://mail.example.com\"\nfrom = \"a@example.com\"\nto = []",
+            "",
+            "at least one to address",
+        ),
+        (
+            "type = \"matrix\"\nhomeserver = \"https://matrix.org\"\nroom_id = \"ops\"\naccess_token_env = \"MATRIX_TOKEN\"",
+            "",
+            "must look like !room:server",
+        ),
+        (
+            "type = \"telegram\"\nbot_token_env = \"TG_TOKEN\"\nchat_id = \"42\"",
+            "deploy = [\"missing\"]",
+            "notifications.routes.deploy references unknown channel 'missing'",
+        ),
+    ];
+
+    for (channel, routes, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "notify-test"
+
+[notifications.channels.ops]
+{channel}
+
+[notifications.routes]
+{routes}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("notifications should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_invalid_varnish_rejected() {
+    let cases = [
+        (
+            "layer = 1",
+            "",
+            "http://web:3000",
+            "must sit behind another proxy",
+        ),
+        (
+            "layer = 3",
+            "[proxies.varnish]\nstorage = \"lots\"",
+            "http://web:3000",
+            "storage 'lots' must be a size",
+        ),
+        (
+            "layer = 3",
+            "[proxies.varnish]\npurge_acl = [\"10.0.0.0/129\"]",
+            "http://web:3000",
+            "purge_acl entry '10.0.0.0/129'",
+        ),
+        (
+            "layer = 3",
+            "",
+            "https://web:3000",
+            "uses HTTPS, which varnish proxy cache cannot connect to",
+        ),
+    ];
+
+    for (layer, varnish, upstream, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "varnish-test"
+
+[[proxies]]
+name = "cache"
+type = "varnish"
+{layer}
+
+{varnish}
+
+[[services]]
+name = "web"
+domain = "web.example.com"
+upstream = "{upstream}"
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("varnish should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_invalid_dns_rejected() {
+    let cases = [
+        (r#"hosts = ["lb.example.net", "203.0.113.10"]"#, "not both"),
+        (
+            r#"hosts = ["not a host"]"#,
+            "'not a host' is neither an IP address nor a hostname",
+        ),
+        (
+            "hosts = [\"203.0.113.10\"]\nttl = 0",
+            "dns.ttl must be greater than 0",
+        ),
+    ];
+
+    for (dns, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "dns-test"
+
+[dns]
+{dns}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("dns should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_invalid_drain_rejected() {
+    let cases = [
+        (
+            "timeout = \"soon\"",
+            "drain timeout 'soon' is not a valid duration",
+        ),
+        (
+            "timeout = \"30s\"\nstop_grace_period = \"30s\"",
+            "stop_grace_period must be longer than the drain timeout",
+        ),
+    ];
+
+    for (drain, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "drain-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[proxies.drain]
+{drain}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("drain should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+
+    let temp_file = create_temp_config(
+        r#"
+[project]
+name = "drain-test"
+
+[[proxies]]
+name = "proxy"
+type = "haproxy"
+external_port = 80
+entrypoint = ["haproxy", "-f", "/usr/local/etc/haproxy/haproxy.cfg"]
+
+[proxies.drain]
+"#,
+    );
+    let error = Config::load(temp_file.path())
+        .expect_err("an entrypoint override should conflict")
+        .to_string();
+    assert!(error.contains("drain.entrypoint = false"), "{error}");
+}
+
+#[test]
+fn test_invalid_upstream_health_rejected() {
+    let cases = [
+        ("path = \"ready\"", "health path 'ready' must start with /"),
+        (
+            "interval = \"often\"",
+            "health interval 'often' is not a valid duration",
+        ),
+        (
+            "interval = \"5s\"\ntimeout = \"10s\"",
+            "health timeout must not be longer than the interval",
+        ),
+        (
+            "expected_status = 42",
+            "expected_status 42 is not an HTTP status code",
+        ),
+        (
+            "unhealthy_threshold = 0",
+            "unhealthy_threshold must be greater than 0",
+        ),
+    ];
+
+    for (health, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "health-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+
+[services.health]
+{health}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("health check should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_exec_secrets() {
+    let temp_file = create_temp_config(
+        r#"
+[project]
+name = "secret-test"
+
+[secrets.db_password]
+command = "op read op://infra/db/password"
+
+[secrets.tls_cert]
+file = "./certs/tls.pem"
+"#,
+    );
+    let config = Config::load(temp_file.path()).expect("Config should load");
+    assert_eq!(
+        config.secrets["db_password"],
+        SecretConfig::Exec {
+            command: "op read op://infra/db/password".to_string()
+        }
+    );
+    assert!(config.secrets["db_password"].is_resolved_locally());
+    assert!(!config.secrets["tls_cert"].is_resolved_locally());
+
+    let cases = [
+        (
+            "[secrets.db_password]\ncommand = \" \"",
+            "Secret db_password command cannot be empty",
+        ),
+        (
+            "[secrets.\"../escape\"]\ncommand = \"true\"",
+            "Secret name '../escape'",
+        ),
+    ];
+    for (secret, expected) in cases {
+        let temp_file =
+            create_temp_config(&format!("[project]\nname = \"secret-test\"\n\n{secret}\n"));
+        let error = Config::load(temp_file.path())
+            .expect_err("secret should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_vault_references() {
+    let temp_file = create_temp_config(
+        r#"
+[project]
+name = "secret-test"
+
+[secrets.db_password]
+vault = "vault://secret/data/db#password"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+
+[services.environment]
+LOG_LEVEL = "info"
+API_KEY = "vault:///kv/web/#api_key"
+"#,
+    );
+    let config = Config::load(temp_file.path()).expect("Config should load");
+    assert!(config.uses_vault());
+    assert!(config.secrets["db_password"].is_resolved_locally());
+    let references = config.services[0]
+        .vault_environment()
+        .expect("References should parse");
+    assert_eq!(
+        references.into_iter().collect::<Vec<_>>(),
+        vec![(
+            "API_KEY",
+            VaultRef {
+                path: "kv/web".to_string(),
+                key: "api_key".to_string()
+            }
+        )]
+    );
+
+    let cases = [
+        (
+            "[secrets.db]\nvault = \"secret/data/db#password\"",
+            "Secret db vault must be a vault://<path>#<key> reference",
+        ),
+        (
+            "[secrets.db]\nvault = \"vault://secret/data/db\"",
+            "missing #<key>",
+        ),
+        (
+            "[secrets.db]\nvault = \"vault://secret/../sys#key\"",
+            "malformed path",
+        ),
+        (
+            "[[services]]\nname = \"web\"\ndomain = \"web.example.com\"\nupstream = \"http://web:80\"\n\n[services.environment]\nTOKEN = \"vault://#token\"",
+            "Service web environment TOKEN: invalid Vault reference",
+        ),
+    ];
+    for (section, expected) in cases {
+        let temp_file =
+            create_temp_config(&format!("[project]\nname = \"secret-test\"\n\n{section}\n"));
+        let error = Config::load(temp_file.path())
+            .expect_err("reference should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_encrypted_secrets() {
+    let temp_file = create_temp_config(
+        r#"
+[project]
+name = "secret-test"
+
+[secrets.db_password]
+file = "./secrets/db_password.enc.yaml"
+format = "sops"
+
+[secrets.tls_cert]
+file = "./certs/tls.pem"
+"#,
+    );
+    let config = Config::load(temp_file.path()).expect("Config should load");
+    assert_eq!(
+        config.secrets["db_password"],
+        SecretConfig::Encrypted {
+            file: "./secrets/db_password.enc.yaml".to_string(),
+            format: EncryptedFormat::Sops,
+        }
+    );
+    assert!(matches!(
+        config.secrets["tls_cert"],
+        SecretConfig::File { .. }
+    ));
+
+    let temp_file = create_temp_config(
+        "[project]\nname = \"secret-test\"\n\n[secrets.key]\nfile = \"\"\nformat = \"age\"\n",
+    );
+    let error = Config::load(temp_file.path())
+        .expect_err("empty encrypted file should be rejected")
+        .to_string();
+    assert!(error.contains("Secret key file cannot be empty"), "{error}");
+}
+
+#[test]
+fn test_history_config() {
+    let temp_file = create_temp_config("[project]\nname = \"history-test\"\n");
+    let config = Config::load(temp_file.path()).expect("Config should load");
+    assert_eq!(config.history, HistoryConfig::default());
+    assert!(config.history.enabled);
+    assert_eq!(config.history.keep, 50);
+
+    let temp_file =
+        create_temp_config("[project]\nname = \"history-test\"\n\n[history]\nkeep = 0\n");
+    let error = Config::load(temp_file.path())
+        .expect_err("keeping no generations should be rejected")
+        .to_string();
+    assert!(
+        error.contains("history.keep must be greater than 0"),
+        "{error}"
+    );
+
+    let temp_file = create_temp_config(
+        "[project]\nname = \"history-test\"\n\n[history]\nenabled = false\nkeep = 0\n",
+    );
+    assert!(Config::load(temp_file.path()).is_ok());
+}
+
+#[test]
+fn test_project_timezone() {
+    for timezone in [
+        "UTC",
+        "Asia/Tokyo",
+        "America/Argentina/Buenos_Aires",
+        "Etc/GMT+9",
+    ] {
+        let temp_file = create_temp_config(&format!(
+            "[project]\nname = \"tz-test\"\ntimezone = \"{timezone}\"\n"
+        ));
+        let config = Config::load(temp_file.path()).expect("Config should load");
+        assert_eq!(config.project.timezone.as_deref(), Some(timezone));
+    }
+
+    for timezone in ["", "Asia//Tokyo", "/etc/localtime", "JST 9"] {
+        let temp_file = create_temp_config(&format!(
+            "[project]\nname = \"tz-test\"\ntimezone = \"{timezone}\"\n"
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("invalid time zone should be rejected")
+            .to_string();
+        assert!(error.contains("is not a time zone name"), "{error}");
+    }
+}
+
+#[cfg(feature = "schema")]
+#[test]
+fn test_json_schema_describes_the_config_file() {
+    let schema = json_schema();
+    assert_eq!(schema["required"], serde_json::json!(["project"]));
+
+    let properties = schema["properties"].as_object().unwrap();
+    for key in [
+        "project", "proxies", "services", "outputs", "extends", "include",
+    ] {
+        assert!(properties.contains_key(key), "missing {key}");
+    }
+    // Filled in while loading, never read from the file
+    assert!(!properties.contains_key("deprecations"));
+
+    let compression = &schema["$defs"]["CompressionConfig"]["properties"];
+    assert_eq!(compression["enabled"]["default"], true);
+    assert_eq!(
+        compression["algorithms"]["default"],
+        serde_json::json!(["gzip"])
+    );
+    let targets: Vec<&str> = schema["$defs"]["DeployTarget"]["oneOf"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|variant| variant["const"].as_str())
+        .collect();
+    assert_eq!(targets, ["compose", "nomad", "swarm"]);
+}
+
+#[test]
+fn test_hardening_validation() {
+    let base = r#"
+[project]
+name = "hardening-test"
+
+[security.hardening]
+enabled = true
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+internal_port = 8080
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+    let temp_file = create_temp_config(base);
+    let config = Config::load(temp_file.path()).expect("Config should load");
+    assert!(config.security.hardening.enabled);
+    assert_eq!(config.security.hardening.tmpfs_size, "64m");
+
+    for (from, to, message) in [
+        (
+            "internal_port = 8080",
+            "internal_port = 80",
+            "Proxy proxy listens on privileged port 80",
+        ),
+        (
+            "enabled = true",
+            "enabled = true\ntmpfs = [\"cache\"]",
+            "tmpfs entry 'cache' must be an absolute container path",
+        ),
+        (
+            "enabled = true",
+            "enabled = true\ntmpfs_size = \"lots\"",
+            "tmpfs_size 'lots' must be a size such as 64m",
+        ),
+        (
+            "name = \"hardening-test\"",
+            "name = \"hardening-test\"\ntarget = \"nomad\"",
+            "security.hardening is only supported by the compose and swarm targets",
+        ),
+        (
+            "upstream = \"http://192.0.2.1:3000\"",
+            "upstream = \"192.0.2.1:53\"\nprotocol = \"udp\"\nlisten_port = 53",
+            "forwards web-app on privileged port 53",
+        ),
+    ] {
+        let temp_file = create_temp_config(&base.replace(from, to));
+        let error = Config::load(temp_file.path())
+            .expect_err("invalid hardening should be rejected")
+            .to_string();
+        assert!(error.contains(message), "{error}");
+    }
+}
+
+#[test]
+fn test_profiles_validation() {
+    let base = r#"
+[project]
+name = "profiles-test"
+
+[security.profiles]
+enabled = true
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+"#;
+    let temp_file = create_temp_config(base);
+    let config = Config::load(temp_file.path()).expect("Config should load");
+    let profiles = &config.security.profiles;
+    assert!(profiles.enabled && profiles.seccomp && profiles.apparmor);
+    assert_eq!(profiles.selinux_type, None);
+
+    for (from, to, message) in [
+        (
+            "name = \"profiles-test\"",
+            "name = \"profiles-test\"\ntarget = \"swarm\"",
+            "security.profiles is only supported by the compose target",
+        ),
+        (
+            "enabled = true",
+            "enabled = true\nallow_syscalls = [\"io_uring_setup\", \"../mount\"]",
+            "allow_syscalls entry '../mount' is not a system call name",
+        ),
+        (
+            "enabled = true",
+            "enabled = true\nselinux_type = \"system_u:system_r:container_t\"",
+            "selinux_type 'system_u:system_r:container_t' must be an SELinux type",
+        ),
+    ] {
+        let temp_file = create_temp_config(&base.replace(from, to));
+        let error = Config::load(temp_file.path())
+            .expect_err("invalid profiles should be rejected")
+            .to_string();
+        assert!(error.contains(message), "{error}");
+    }
+}
+
+#[test]
+fn test_service_auth_validation() {
+    let base = r#"
+[project]
+name = "auth-test"
+
+[[proxies]]
+name = "proxy"
+type = "traefik"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[services.auth]
+type = "basic"
+users = ["alice:$2y$05$6yTcwDuTOTYDZFHeMi3Du.7SxmSxm9wyiRaeDjQwPmCqk9Emjp6Hy"]
+
+[secrets.web_users]
+file = "./web.htpasswd"
+"#;
+    let temp_file = create_temp_config(base);
+    let config = Config::load(temp_file.path()).expect("Config should load");
+    let auth = config.services[0].auth.as_ref().unwrap();
+    assert_eq!(auth.auth_type, AuthType::Basic);
+    assert_eq!(auth.realm, "Restricted");
+    assert_eq!(auth.credentials()[0].0, "alice");
+
+    let users = "users = [\"alice:$2y$05$6yTcwDuTOTYDZFHeMi3Du.7SxmSxm9wyiRaeDjQwPmCqk9Emjp6Hy\"]";
+    for (from, to, message) in [
+        (
+            users,
+            "",
+            "Service web-app auth must list users or set users_secret",
+        ),
+        (
+            users,
+            "users = [\"alice:secret\"]",
+            "Service web-app auth user 'alice' must be name:bcrypt-hash",
+        ),
+        (
+            users,
+            "users_secret = \"missing\"",
+            "auth.users_secret missing is not defined in [secrets]",
+        ),
+        (
+            "type = \"basic\"",
+            "type = \"forward\"",
+            "Service web-app uses forward auth, but [security.forward_auth] sets no provider",
+        ),
+        (
+            "type = \"traefik\"",
+            "type = \"nginx\"",
+            "Service web-app sets [services.auth], but no proxy routes services itself",
+        ),
+        (
+            "upstream = \"http://192.0.2.1:3000\"",
+            "upstream = \"192.0.2.1:5432\"\nprotocol = \"tcp\"\nlisten_port = 5432",
+            "auth",
+        ),
+    ] {
+        let temp_file = create_temp_config(&base.replace(from, to));
+        let error = Config::load(temp_file.path())
+            .expect_err("invalid auth should be rejected")
+            .to_string();
+        assert!(error.contains(message), "{error}");
+    }
+
+    // Caddy only takes users written into its config
+    let from_secret = base.replace(users, "users_secret = \"web_users\"");
+    let temp_file = create_temp_config(&from_secret);
+    Config::load(temp_file.path()).expect("Traefik reads users from a secret");
+    let temp_file =
+        create_temp_config(&from_secret.replace("type = \"traefik\"", "type = \"caddy\""));
+    let error = Config::load(temp_file.path()).unwrap_err().to_string();
+    assert!(
+        error.contains("Service web-app auth.users_secret cannot be read by caddy proxy proxy"),
+        "{error}"
+    );
+
+    // Forward auth needs a provider HAProxy cannot query
+    let forward = format!(
+        "{}\n[security.forward_auth]\nprovider = \"oauth2-proxy\"\n",
+        base.replace(users, "")
+            .replace("type = \"basic\"", "type = \"forward\"")
+    );
+    let temp_file = create_temp_config(&forward);
+    Config::load(temp_file.path()).expect("forward auth should be valid");
+    for (from, to, message) in [
+        (
+            "type = \"traefik\"",
+            "type = \"haproxy\"",
+            "Service web-app uses forward auth, which HAProxy proxy proxy cannot do",
+        ),
+        (
+            "provider = \"oauth2-proxy\"",
+            "provider = \"authelia\"",
+            "security.forward_auth.config_dir is required for the authelia sidecar",
+        ),
+        (
+            "provider = \"oauth2-proxy\"",
+            "provider = \"oauth2-proxy\"\naddress = \"auth.internal\"",
+            "security.forward_auth.address 'auth.internal' must be an http:// or https:// URL",
+        ),
+        (
+            "provider = \"oauth2-proxy\"",
+            "provider = \"oauth2-proxy\"\nsecrets = [\"missing\"]",
+            "security.forward_auth secret missing is not defined in [secrets]",
+        ),
+        (
+            "name = \"auth-test\"",
+            "name = \"auth-test\"\ntarget = \"nomad\"",
+            "security.forward_auth sidecar is only generated for the compose and swarm targets",
+        ),
+    ] {
+        let temp_file = create_temp_config(&forward.replace(from, to));
+        let error = Config::load(temp_file.path())
+            .expect_err("invalid forward auth should be rejected")
+            .to_string();
+        assert!(error.contains(message), "{error}");
+    }
+}
+
+#[test]
+fn test_app_templates() {
+    for template in AppTemplate::ALL {
+        let upstream = template.default_upstream();
+        let temp_file = create_temp_config(&starter_config(template, "app.example.com", &upstream));
+        let config = Config::load(temp_file.path())
+            .unwrap_or_else(|e| panic!("{template} starter config should load: {e}"));
+        assert_eq!(config.schema_version, Some(SCHEMA_VERSION));
+        assert_eq!(config.project.name, template.as_str());
+        assert_eq!(
+            config.services,
+            vec![
+                template
+                    .service(template.as_str(), "app.example.com", &upstream)
+                    .unwrap()
+            ]
+        );
+        assert_eq!(config.services[0].template, Some(template));
+    }
+
+    let misskey = AppTemplate::Misskey
+        .service("misskey", "mi.example.com", "http://misskey:3000")
+        .unwrap();
+    assert!(misskey.websocket);
+    assert_eq!(misskey.max_body_size, "256m");
+    assert_eq!(misskey.cache.unwrap().bypass_cookies, ["token"]);
+
+    let mastodon = AppTemplate::Mastodon
+        .service("social", "social.example.com", "http://web:3000")
+        .unwrap();
+    assert!(
+        mastodon
+            .snippets
+            .unwrap()
+            .server
+            .unwrap()
+            .contains("    proxy_pass http://web:4000;\n")
+    );
+
+    let gitea = AppTemplate::Gitea
+        .service("git", "git.example.com", "http://gitea:3000")
+        .unwrap();
+    assert!(!gitea.websocket);
+    assert_eq!(gitea.cache, None);
+}
+
+#[test]
+fn test_write_starter_config() {
+    let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+    let path = temp_dir.path().join("config.toml");
+    write_starter_config(
+        &path,
+        AppTemplate::Nextcloud,
+        "cloud.example.com",
+        "http://nextcloud:80",
+        false,
+    )
+    .expect("Starter config should be written");
+    let config = Config::load(&path).expect("Written config should load");
+    assert_eq!(config.services[0].domain, "cloud.example.com");
+
+    let error = write_starter_config(
+        &path,
+        AppTemplate::Gitea,
+        "git.example.com",
+        "http://gitea:3000",
+        false,
+    )
+    .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("already exists; rerun with --force")
+    );
+    write_starter_config(
+        &path,
+        AppTemplate::Gitea,
+        "git.example.com",
+        "http://gitea:3000",
+        true,
+    )
+    .expect("--force should overwrite");
+    assert_eq!(
+        Config::load(&path).unwrap().services[0].template,
+        Some(AppTemplate::Gitea)
+    );
+
+    // Nothing is written for a domain the configuration rejects
+    let path = temp_dir.path().join("invalid.toml");
+    assert!(
+        write_starter_config(
+            &path,
+            AppTemplate::Gitea,
+            "not a domain",
+            "http://gitea:3000",
+            false
+        )
+        .is_err()
+    );
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_failover_validation() {
+    let cases = [
+        (
+            "backup_upstreams = [\"http://standby:3000/app\"]",
+            "backup upstream 'http://standby:3000/app' must be an address",
+        ),
+        (
+            "backup_upstreams = [\"https://standby:3000\"]",
+            "must use the http scheme of its upstream",
+        ),
+        (
+            "backup_upstreams = [\"http://web-app:3000\"]",
+            "lists upstream 'http://web-app:3000' more than once",
+        ),
+        (
+            "backup_upstreams = [\"http://a:3000\", \"http://a:3000\"]",
+            "lists upstream 'http://a:3000' more than once",
+        ),
+        (
+            "[services.failover]\ntimeout = \"soon\"",
+            "failover timeout 'soon' is not a valid duration",
+        ),
+        (
+            "[services.failover]\nnext_upstream = []",
+            "next_upstream must list at least one condition",
+        ),
+    ];
+
+    for (failover, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "failover-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+{failover}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("failover should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+
+    let temp_file = create_temp_config(
+        r#"
+[project]
+name = "failover-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+backup_upstreams = ["http://standby:3000"]
+
+[services.failover]
+retries = 3
+next_upstream = ["error", "http_500"]
+"#,
+    );
+    let config = Config::load(temp_file.path()).expect("failover should be accepted");
+    let failover = config.services[0].failover_policy().unwrap();
+    assert_eq!(failover.retries, 3);
+    assert_eq!(failover.timeout, "5s");
+    assert_eq!(
+        failover.next_upstream,
+        [FailoverCondition::Error, FailoverCondition::Http500]
+    );
+}
+
+#[test]
+fn test_timeouts_validation() {
+    let cases = [
+        (
+            "[services.timeouts]\nread = \"forever\"",
+            "Service web-app timeouts.read 'forever' is not a valid duration",
+        ),
+        (
+            "[services.timeouts]\nconnect = \"0s\"",
+            "Service web-app timeouts.connect '0s' is not a valid duration",
+        ),
+        (
+            "[services.timeouts]\nkeepalive = \"60s\"",
+            "unknown field `keepalive`",
+        ),
+        (
+            "[[proxies.routes]]\ntype = \"direct\"\ndomain = \"app.example.com\"\nupstream = \"http://web-app:3000\"\n\n[proxies.routes.timeouts]\nread = \"2m\"",
+            "sets timeouts, which apply to its paths",
+        ),
+        (
+            "[[proxies.routes]]\ntype = \"direct\"\ndomain = \"game.example.com\"\nupstream = \"192.0.2.20:27015\"\nprotocol = \"udp\"\nlisten_port = 27015\n\n[proxies.routes.timeouts]\nidle = \"1m\"",
+            "uses udp, which does not support timeouts",
+        ),
+    ];
+
+    for (timeouts, expected) in cases {
+        // Route cases replace the service's table with the proxy's
+        let (proxy_extra, service_extra) = if timeouts.starts_with("[[proxies.routes]]") {
+            (timeouts, "")
+        } else {
+            ("", timeouts)
+        };
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "timeouts-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+{proxy_extra}
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+{service_extra}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("timeouts should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+
+    let temp_file = create_temp_config(
+        r#"
+[project]
+name = "timeouts-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+
+[services.timeouts]
+connect = "5s"
+read = "2m"
+retries = 0
+"#,
+    );
+    let config = Config::load(temp_file.path()).expect("timeouts should be accepted");
+    let timeouts = &config.services[0].timeouts;
+    assert_eq!(timeouts.durations(), [("connect", "5s"), ("read", "2m")]);
+    assert_eq!(timeouts.retries, Some(0));
+    assert!(!timeouts.is_empty());
+    assert!(TimeoutsConfig::default().is_empty());
+}
+
+#[test]
+fn test_upstream_pool_validation() {
+    let pool = |servers: &str, extra: &str| {
+        format!("[[upstreams]]\nname = \"app-cluster\"\nservers = [{servers}]\n{extra}")
+    };
+    let one = "{ url = \"http://192.0.2.1:3000\" }";
+    let cases = [
+        (
+            "[[upstreams]]\nname = \"App\"\nservers = [{ url = \"http://a:3000\" }]".to_string(),
+            "",
+            "Upstream pool name 'App' must contain only lowercase letters",
+        ),
+        (
+            "[[upstreams]]\nname = \"web-app\"\nservers = [{ url = \"http://a:3000\" }]"
+                .to_string(),
+            "",
+            "Upstream pool name 'web-app' is already used by a proxy or service",
+        ),
+        (
+            format!("{}\n{}", pool(one, ""), pool(one, "")),
+            "",
+            "Upstream pool app-cluster is defined more than once",
+        ),
+        (
+            pool("", ""),
+            "",
+            "Upstream pool app-cluster needs at least one server",
+        ),
+        (
+            pool("{ url = \"192.0.2.1:3000\" }", ""),
+            "",
+            "server '192.0.2.1:3000' must be an address such as http://app-1:3000",
+        ),
+        (
+            pool(
+                "{ url = \"http://a:3000\" }, { url = \"https://b:3000\" }",
+                "",
+            ),
+            "",
+            "server 'https://b:3000' must use the http scheme of the first server",
+        ),
+        (
+            pool(
+                "{ url = \"http://a:3000\" }, { url = \"http://a:3000/\" }",
+                "",
+            ),
+            "",
+            "lists server 'http://a:3000/' more than once",
+        ),
+        (
+            pool("{ url = \"http://a:3000\", weight = 0 }", ""),
+            "",
+            "weight must be between 1 and 256",
+        ),
+        (
+            pool(one, "keepalive = 0"),
+            "",
+            "keepalive must be greater than 0",
+        ),
+        (
+            pool(one, "lb_policy = \"fastest\""),
+            "",
+            "unknown variant `fastest`",
+        ),
+        (
+            pool(one, ""),
+            "backup_upstreams = [\"http://standby:3000\"]",
+            "Service web-app uses upstream pool app-cluster, which cannot be combined with backup_upstreams",
+        ),
+    ];
+
+    for (upstreams, service, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "upstream-pool-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+
+{upstreams}
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "app-cluster"
+{service}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("upstream pool should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+
+    let temp_file = create_temp_config(&format!(
+        r#"
+[project]
+name = "upstream-pool-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+
+{}
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "app-cluster"
+"#,
+        pool(
+            "{ url = \"http://a:3000\", weight = 2 }, { url = \"http://b:3000\" }",
+            "lb_policy = \"ip_hash\"\nkeepalive = 32"
+        )
+    ));
+    let config = Config::load(temp_file.path()).expect("upstream pool should be accepted");
+    let pool = config.upstream_pool("app-cluster").unwrap();
+    assert_eq!(pool.lb_policy, LbPolicy::IpHash);
+    assert_eq!(pool.keepalive, Some(32));
+    assert_eq!(pool.servers[1].weight, 1);
+    assert!(pool.is_weighted());
+    assert!(config.upstream_pool("http://a:3000").is_none());
+}
+
+#[test]
+fn test_instance_port_strategy() {
+    let config_for = |strategy: &str, extra: &str| {
+        format!(
+            r#"
+[project]
+name = "instance-port-test"
+scaling = true
+{extra}
+
+[[proxies]]
+name = "edge"
+type = "caddy"
+external_port = 8080
+instances = 3
+instance_port_strategy = "{strategy}"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#
+        )
+    };
+
+    let temp_file = create_temp_config(&config_for("internal-only", ""));
+    let config = Config::load(temp_file.path()).expect("Config should load");
+    assert_eq!(
+        config.proxies[0].instance_port_strategy,
+        InstancePortStrategy::InternalOnly
+    );
+    assert_eq!(
+        config.proxies[0].instance_balancer(true).as_deref(),
+        Some("edge-lb")
+    );
+    // A single container needs no balancer
+    assert_eq!(config.proxies[0].instance_balancer(false), None);
+
+    let cases = [
+        (
+            config_for("random", "target = \"swarm\""),
+            "sets instance_port_strategy = \"random\", which only the compose target supports",
+        ),
+        (config_for("nearest", ""), "unknown variant `nearest`"),
+        (
+            config_for("internal-only", "")
+                + "\n[[services]]\nname = \"edge-lb\"\ndomain = \"lb.example.com\"\nupstream = \"http://192.0.2.2:3000\"\n",
+            "gets a load balancer named edge-lb, which is already the name of a proxy or service",
+        ),
+    ];
+    for (content, expected) in cases {
+        let temp_file = create_temp_config(&content);
+        let error = Config::load(temp_file.path())
+            .expect_err("instance_port_strategy should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_upstream_url_validation() {
+    let cases = [
+        (
+            "upstream = \"htp://web-app:3000\"",
+            "upstream 'htp://web-app:3000' uses unsupported scheme 'htp'",
+        ),
+        (
+            "upstream = \"ftp://web-app\"",
+            "uses unsupported scheme 'ftp'; use http:// or https://",
+        ),
+        (
+            "upstream = \"http://web-app:\"",
+            "upstream 'http://web-app:' is missing the port after ':'",
+        ),
+        (
+            "upstream = \"http://web-app:99999\"",
+            "upstream 'http://web-app:99999' is not a valid URL: invalid port number",
+        ),
+        ("upstream = \"http://web app:3000\"", "is not a valid URL"),
+        (
+            "upstream = \"http://web-app:3000\"\ncandidate_upstream = \"htps://web-app-next:3000\"",
+            "upstream 'htps://web-app-next:3000' uses unsupported scheme 'htps'",
+        ),
+    ];
+
+    for (upstream, expected) in cases {
+        let temp_file = create_temp_config(&format!(
+            r#"
+[project]
+name = "upstream-test"
+
+[deployment.strategy]
+type = "canary"
+weight = 25
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+{upstream}
+"#
+        ));
+        let error = Config::load(temp_file.path())
+            .expect_err("upstream should be rejected")
+            .to_string();
+        assert!(
+            error.contains(expected),
+            "{error} should contain {expected}"
+        );
+    }
+
+    // Routes, default upstreams and pool servers are checked too
+    let mut config: Config = toml::from_str(
+        r#"
+[project]
+name = "upstream-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+default_upstream = "http://fallback:8080"
+
+[[proxies.routes]]
+type = "direct"
+domain = "api.example.com"
+upstream = "http://api:8080"
+"#,
+    )
+    .unwrap();
+    config.validate().expect("valid upstreams should pass");
+    config.proxies[0].routes[0].upstream = "htp://api:8080".to_string();
+    assert!(config.validate().unwrap_err().to_string().contains(
+        "Route api.example.com on proxy proxy upstream 'htp://api:8080' uses unsupported scheme"
+    ));
+    config.proxies[0].routes[0].upstream = "http://api:8080".to_string();
+    config.proxies[0].default_upstream = Some("http://fallback:0x50".to_string());
+    assert!(
+        config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("Proxy proxy upstream 'http://fallback:0x50' is not a valid URL")
+    );
+}
+
+#[test]
+fn test_upstream_addresses() {
+    for (upstream, host) in [
+        ("http://proxy-2:80", "proxy-2"),
+        ("https://cdn.example.com/bucket/", "cdn.example.com"),
+        ("http://[::1]", "[::1]"),
+        ("http://[2001:db8::1]:8080", "[2001:db8::1]"),
+        ("app-cluster", "app-cluster"),
+    ] {
+        assert_eq!(upstream_host(upstream), host, "{upstream}");
+    }
+
+    assert_eq!(
+        upstream_address("http://web:3000"),
+        ("web".to_string(), 3000)
+    );
+    assert_eq!(upstream_address("https://web"), ("web".to_string(), 443));
+    assert_eq!(upstream_address("http://[::1]"), ("[::1]".to_string(), 80));
+    assert_eq!(upstream_address("web"), ("web".to_string(), 80));
+
+    for (upstream, address) in [
+        ("db:5432", Some("db:5432")),
+        ("tcp://db:5432/", Some("db:5432")),
+        ("udp://[2001:db8::53]:53", Some("[2001:db8::53]:53")),
+        ("db:80", Some("db:80")),
+        ("db", None),
+        ("db:0", None),
+        ("db:5432/path", None),
+        (":5432", None),
+    ] {
+        assert_eq!(stream_address(upstream).as_deref(), address, "{upstream}");
+    }
+}
+
+#[test]
+fn test_upstream_normalization() {
+    for (upstream, normalized) in [
+        ("HTTP://Web-App:3000", "http://web-app:3000"),
+        (
+            "https://CDN.Example.com/Bucket/",
+            "https://cdn.example.com/Bucket/",
+        ),
+        ("http://web-app:80", "http://web-app:80"),
+        ("http://web-app:03000/", "http://web-app:3000/"),
+        ("http://[::1]:8080", "http://[::1]:8080"),
+        ("app-cluster", "app-cluster"),
+        ("tcp://DB:5432", "tcp://DB:5432"),
+        ("htp://web-app:3000", "htp://web-app:3000"),
+        ("http://Web-App:", "http://Web-App:"),
+    ] {
+        assert_eq!(normalize_upstream(upstream), normalized, "{upstream}");
+    }
+
+    let temp_file = create_temp_config(
+        r#"
+[project]
+name = "upstream-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+default_upstream = "HTTP://Fallback:8080"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "Http://Web-App:3000"
+backup_upstreams = ["http://Standby:3000"]
+
+[[upstreams]]
+name = "api-cluster"
+
+[[upstreams.servers]]
+url = "HTTP://API-1:8080"
+"#,
+    );
+    let config = Config::load(temp_file.path()).expect("Failed to load config");
+    assert_eq!(config.services[0].upstream, "http://web-app:3000");
+    assert_eq!(config.services[0].backup_upstreams, ["http://standby:3000"]);
+    assert_eq!(
+        config.proxies[0].default_upstream.as_deref(),
+        Some("http://fallback:8080")
+    );
+    assert_eq!(config.upstreams[0].servers[0].url, "http://api-1:8080");
+}
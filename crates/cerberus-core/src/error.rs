@@ -58,6 +58,26 @@ pub enum CerberusError {
     /// General validation errors
     #[error("Validation error: {message}")]
     Validation { message: String },
+
+    /// Notification delivery errors
+    #[error("Notification error for channel {channel}: {message}")]
+    Notification { channel: String, message: String },
+
+    /// Errors applying an upgrade to a running stack
+    #[error("Upgrade of service {service} failed: {message}")]
+    Upgrade { service: String, message: String },
+
+    /// Errors resolving a secret value
+    #[error("Secret {name} could not be resolved: {message}")]
+    Secret { name: String, message: String },
+
+    /// Errors querying the Docker daemon
+    #[error("Docker query failed: {message}")]
+    Docker { message: String },
+
+    /// Errors publishing generated files to remote storage
+    #[error("Publishing to {target} failed: {message}")]
+    Publish { target: String, message: String },
 }
 
 /// Result type alias for Cerberus operations
@@ -109,6 +129,45 @@ impl CerberusError {
             message: message.into(),
         }
     }
+
+    /// Create a new notification delivery error
+    pub fn notification(channel: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Notification {
+            channel: channel.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Create a new upgrade error
+    pub fn upgrade(service: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Upgrade {
+            service: service.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Create a new secret resolution error
+    pub fn secret(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Secret {
+            name: name.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Create a new Docker query error
+    pub fn docker(message: impl Into<String>) -> Self {
+        Self::Docker {
+            message: message.into(),
+        }
+    }
+
+    /// Create a new publishing error
+    pub fn publish(target: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Publish {
+            target: target.into(),
+            message: message.into(),
+        }
+    }
 }
 
 impl From<std::io::Error> for CerberusError {
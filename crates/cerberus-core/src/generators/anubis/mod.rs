@@ -0,0 +1,310 @@
+//! # Anubis configuration generator
+//!
+//! Generates Anubis DDoS protection configuration from Cerberus settings.
+
+use crate::{
+    Result,
+    config::{AnubisContainer, Config, RouteConfig, RouteType},
+};
+use serde_json::{Value, json};
+
+/// Generator for Anubis configurations
+pub struct AnubisGenerator<'a> {
+    config: &'a Config,
+    container: AnubisContainer,
+}
+
+impl<'a> AnubisGenerator<'a> {
+    /// Create a new Anubis configuration generator for the global Anubis
+    pub fn new(config: &'a Config) -> Self {
+        let container = config.anubis.containers().remove(0);
+        Self { config, container }
+    }
+
+    /// Create a generator for one of the containers of
+    /// [`AnubisConfig::containers`](crate::config::AnubisConfig::containers)
+    pub fn for_container(config: &'a Config, container: AnubisContainer) -> Self {
+        Self { config, container }
+    }
+
+    /// Generate Anubis bot policy JSON configuration
+    pub fn generate(&self) -> Result<String> {
+        // Default bot policy that allows legitimate crawlers and challenges suspicious traffic
+        let mut bot_policy = json!({
+            "ALLOW": [
+                {
+                    "path": "/favicon.ico",
+                    "description": "Allow favicon requests"
+                },
+                {
+                    "path": "/.well-known/*",
+                    "description": "Allow well-known paths for certificates, etc."
+                },
+                {
+                    "path": "/robots.txt",
+                    "description": "Allow robots.txt"
+                },
+                {
+                    "user-agent": "*Googlebot*",
+                    "description": "Allow Google crawlers"
+                },
+                {
+                    "user-agent": "*bingbot*",
+                    "description": "Allow Bing crawlers"
+                },
+                {
+                    "user-agent": "*facebookexternalhit*",
+                    "description": "Allow Facebook link previews"
+                },
+                {
+                    "user-agent": "*Twitterbot*",
+                    "description": "Allow Twitter link previews"
+                },
+                {
+                    "user-agent": "*LinkedInBot*",
+                    "description": "Allow LinkedIn link previews"
+                },
+                {
+                    "user-agent": "*Slackbot*",
+                    "description": "Allow Slack link previews"
+                }
+            ],
+            "CHALLENGE": [
+                {
+                    "user-agent": "Mozilla*",
+                    "description": "Challenge typical browser user agents"
+                },
+                {
+                    "user-agent": "*Chrome*",
+                    "description": "Challenge Chrome browsers"
+                },
+                {
+                    "user-agent": "*Firefox*",
+                    "description": "Challenge Firefox browsers"
+                },
+                {
+                    "user-agent": "*Safari*",
+                    "description": "Challenge Safari browsers"
+                },
+                {
+                    "user-agent": "*Edge*",
+                    "description": "Challenge Edge browsers"
+                },
+                {
+                    "path": "/*",
+                    "rate_limit": {
+                        "requests_per_minute": 60,
+                        "burst": 10
+                    },
+                    "description": "Rate limit all paths"
+                }
+            ],
+            "BLOCK": [
+                {
+                    "user-agent": "*bot*",
+                    "description": "Block generic bots"
+                },
+                {
+                    "user-agent": "*crawler*",
+                    "description": "Block generic crawlers"
+                },
+                {
+                    "user-agent": "*scraper*",
+                    "description": "Block scrapers"
+                },
+                {
+                    "user-agent": "*wget*",
+                    "description": "Block wget"
+                },
+                {
+                    "user-agent": "*curl*",
+                    "description": "Block curl"
+                },
+                {
+                    "user-agent": "*python*",
+                    "description": "Block Python requests"
+                },
+                {
+                    "path": "/admin*",
+                    "description": "Block admin paths"
+                },
+                {
+                    "path": "/.env*",
+                    "description": "Block environment files"
+                },
+                {
+                    "path": "/wp-*",
+                    "description": "Block WordPress paths"
+                }
+            ],
+            "config": {
+                "difficulty": self.container.difficulty,
+                "challenge_ttl": 3600,
+                "rate_limit_window": 60,
+                "max_challenge_attempts": 3,
+                "javascript_challenge": true,
+                "proof_of_work": true
+            },
+            "metadata": {
+                "generated_by": "cerberus-rust",
+                "version": "1.0.0",
+                "project_name": &self.config.project.name,
+                "anubis_enabled": self.config.anubis.enabled
+            }
+        });
+
+        // Route-specific rules take precedence over the generic defaults
+        if let Some(allow) = bot_policy["ALLOW"].as_array_mut() {
+            let mut rules = self.content_allow_rules();
+            allow.retain(|rule| !rules.iter().any(|content| content["path"] == rule["path"]));
+            rules.append(allow);
+            *allow = rules;
+            allow.extend(self.route_allow_rules());
+        }
+        if let Some(challenge) = bot_policy["CHALLENGE"].as_array_mut() {
+            let mut rules = self.route_challenge_rules();
+            rules.append(challenge);
+            *challenge = rules;
+        }
+
+        // Pretty print JSON for readability
+        Ok(serde_json::to_string_pretty(&bot_policy)?)
+    }
+
+    /// Whether this container challenges a domain
+    ///
+    /// The global Anubis takes every domain not assigned to an instance.
+    fn handles(&self, domain: &str) -> bool {
+        if self.container.domains.is_empty() {
+            !self
+                .config
+                .anubis
+                .instances
+                .iter()
+                .any(|instance| instance.domains.iter().any(|d| d == domain))
+        } else {
+            self.container.serves(domain)
+        }
+    }
+
+    /// Routes of this container
+    fn routes(&self) -> impl Iterator<Item = &RouteConfig> {
+        self.config
+            .proxies
+            .iter()
+            .flat_map(|proxy| proxy.routes.iter())
+            .filter(|route| self.handles(&route.domain))
+    }
+
+    /// Routes that pass through Anubis (direct and tcp/udp routes skip it entirely)
+    fn protected_routes(&self) -> impl Iterator<Item = &RouteConfig> {
+        self.routes()
+            .filter(|route| route.route_type != RouteType::Direct && !route.protocol.is_stream())
+    }
+
+    /// Routes with per-path protection, whatever their type
+    fn path_routes(&self) -> impl Iterator<Item = &RouteConfig> {
+        self.routes().filter(|route| !route.paths.is_empty())
+    }
+
+    /// Build ALLOW rules for the `[content]` files, which crawlers fetch
+    /// before anything else
+    fn content_allow_rules(&self) -> Vec<Value> {
+        self.config
+            .content
+            .files()
+            .into_iter()
+            .map(|(path, file)| {
+                json!({
+                    "path": path,
+                    "description": format!("Allow {file} from [content]")
+                })
+            })
+            .collect()
+    }
+
+    /// Build ALLOW rules from route bypass paths and unprotected path prefixes
+    fn route_allow_rules(&self) -> Vec<Value> {
+        let bypass_paths = self.protected_routes().flat_map(|route| {
+            route.bypass_paths.iter().map(move |path| {
+                json!({
+                    "host": &route.domain,
+                    "path": path,
+                    "description": format!("Bypass path for {}", route.domain)
+                })
+            })
+        });
+        let unprotected_paths = self.path_routes().flat_map(|route| {
+            route
+                .path_rules()
+                .into_iter()
+                .filter(|path| !path.protect)
+                .map(move |path| {
+                    json!({
+                        "host": &route.domain,
+                        "path": format!("{}*", path.prefix),
+                        "description": format!("Unprotected path {} for {}", path.prefix, route.domain)
+                    })
+                })
+        });
+        bypass_paths.chain(unprotected_paths).collect()
+    }
+
+    /// Build CHALLENGE rules from protected path prefixes and per-route
+    /// difficulty overrides
+    fn route_challenge_rules(&self) -> Vec<Value> {
+        let protected_paths = self.path_routes().flat_map(|route| {
+            route
+                .path_rules()
+                .into_iter()
+                .filter(|path| path.protect)
+                .map(move |path| {
+                    json!({
+                        "host": &route.domain,
+                        "path": format!("{}*", path.prefix),
+                        "difficulty": route.difficulty.unwrap_or(self.container.difficulty),
+                        "description": format!("Protected path {} for {}", path.prefix, route.domain)
+                    })
+                })
+        });
+        let difficulty_overrides = self.protected_routes().filter_map(|route| {
+            route.difficulty.map(|difficulty| {
+                json!({
+                    "host": &route.domain,
+                    "path": "/*",
+                    "difficulty": difficulty,
+                    "description": format!("Difficulty override for {}", route.domain)
+                })
+            })
+        });
+        protected_paths.chain(difficulty_overrides).collect()
+    }
+
+    /// Generate Anubis environment configuration for Docker
+    ///
+    /// Written to `.env` in the container's config directory, which the Compose service loads with
+    /// `env_file`.
+    pub fn generate_env_config(&self) -> Result<Vec<String>> {
+        Ok(self
+            .environment()
+            .into_iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect())
+    }
+
+    /// Environment variables of the Anubis container
+    pub fn environment(&self) -> Vec<(&'static str, String)> {
+        let anubis_config = &self.config.anubis;
+        vec![
+            ("BIND", self.container.bind.clone()),
+            ("TARGET", self.container.target.clone()),
+            ("DIFFICULTY", self.container.difficulty.to_string()),
+            ("METRICS_BIND", self.container.metrics_bind.clone()),
+            ("SERVE_ROBOTS_TXT", anubis_config.serve_robots_txt.clone()),
+            ("POLICY_FNAME", anubis_config.policy_fname.clone()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,182 @@
+//! # Tests for the Anubis configuration generator
+//!
+//! These tests verify the generated bot policy and the environment of the
+//! global Anubis and of per-domain instances.
+
+use super::*;
+use crate::test_support::parse_config;
+use pretty_assertions::assert_eq;
+
+/// Helper function to create a config with Anubis in front of two layers
+fn create_test_config() -> Config {
+    parse_config(
+        r#"
+[project]
+name = "anubis-test"
+
+[anubis]
+enabled = true
+target = "http://proxy-layer2:80"
+difficulty = 7
+
+[[anubis.instances]]
+name = "anubis-api"
+domains = ["api.example.com"]
+difficulty = 9
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+
+[[proxies]]
+name = "proxy-layer2"
+type = "nginx"
+layer = 2
+"#,
+    )
+}
+
+/// Helper function to render the bot policy of a generator
+fn policy(generator: &AnubisGenerator) -> Value {
+    serde_json::from_str(&generator.generate().expect("Failed to generate bot policy"))
+        .expect("Generated JSON is invalid")
+}
+
+#[test]
+fn test_generate_bot_policy_json() {
+    let config = create_test_config();
+    let policy = policy(&AnubisGenerator::new(&config));
+
+    assert!(policy["ALLOW"].is_array());
+    assert!(policy["CHALLENGE"].is_array());
+    assert!(policy["BLOCK"].is_array());
+
+    let allow = policy["ALLOW"].as_array().unwrap();
+    assert!(allow.iter().any(|rule| rule["path"] == "/favicon.ico"));
+    assert!(allow.iter().any(|rule| rule["user-agent"] == "*Googlebot*"));
+    let block = policy["BLOCK"].as_array().unwrap();
+    assert!(block.iter().any(|rule| rule["user-agent"] == "*bot*"));
+    // Legitimate crawlers are allowed, not blocked
+    assert!(!block.iter().any(|rule| rule["user-agent"] == "*Googlebot*"));
+
+    assert_eq!(policy["config"]["difficulty"], 7);
+    assert_eq!(policy["metadata"]["project_name"], "anubis-test");
+    assert_eq!(policy["metadata"]["anubis_enabled"], true);
+}
+
+#[test]
+fn test_generate_env_config() {
+    let config = create_test_config();
+    let env = AnubisGenerator::new(&config)
+        .generate_env_config()
+        .expect("Failed to generate env config");
+
+    assert_eq!(
+        env,
+        vec![
+            "BIND=:8080",
+            "TARGET=http://proxy-layer2:80",
+            "DIFFICULTY=7",
+            "METRICS_BIND=:9090",
+            "SERVE_ROBOTS_TXT=true",
+            "POLICY_FNAME=/data/cfg/botPolicy.json",
+        ]
+    );
+}
+
+#[test]
+fn test_instance_takes_its_own_settings() {
+    let config = create_test_config();
+    let instance = config
+        .anubis
+        .containers()
+        .into_iter()
+        .find(|container| container.name == "anubis-api")
+        .expect("Instance should exist");
+    let generator = AnubisGenerator::for_container(&config, instance);
+
+    assert_eq!(policy(&generator)["config"]["difficulty"], 9);
+    let environment = generator.environment();
+    assert!(environment.contains(&("DIFFICULTY", "9".to_string())));
+    // Unset instance settings fall back to [anubis]
+    assert!(environment.contains(&("TARGET", "http://proxy-layer2:80".to_string())));
+}
+
+#[test]
+fn test_route_rules_in_bot_policy() {
+    let config = parse_config(
+        r#"
+[project]
+name = "anubis-test"
+
+[anubis]
+enabled = true
+difficulty = 4
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+
+[[proxies.routes]]
+type = "conditional"
+domain = "app.example.com"
+upstream = "http://anubis:8080"
+bypass_paths = ["/health"]
+difficulty = 8
+
+[[proxies.routes]]
+type = "direct"
+domain = "api.example.com"
+upstream = "http://proxy-2:80"
+
+[[proxies.routes.paths]]
+prefix = "/api/"
+protect = false
+
+[[proxies.routes.paths]]
+prefix = "/api/admin/"
+protect = true
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+layer = 2
+"#,
+    );
+    let policy = policy(&AnubisGenerator::new(&config));
+
+    let allow = policy["ALLOW"].as_array().unwrap();
+    assert!(allow.contains(&json!({
+        "host": "app.example.com",
+        "path": "/health",
+        "description": "Bypass path for app.example.com"
+    })));
+    assert!(allow.contains(&json!({
+        "host": "api.example.com",
+        "path": "/api/*",
+        "description": "Unprotected path /api/ for api.example.com"
+    })));
+
+    // Route rules come before the generic browser challenges
+    let challenge = policy["CHALLENGE"].as_array().unwrap();
+    assert_eq!(
+        challenge[..2],
+        [
+            json!({
+                "host": "api.example.com",
+                "path": "/api/admin/*",
+                "difficulty": 4,
+                "description": "Protected path /api/admin/ for api.example.com"
+            }),
+            json!({
+                "host": "app.example.com",
+                "path": "/*",
+                "difficulty": 8,
+                "description": "Difficulty override for app.example.com"
+            }),
+        ]
+    );
+    assert_eq!(policy["config"]["difficulty"], 4);
+}
@@ -0,0 +1,132 @@
+//! Typed representation of generated files
+//!
+//! [`CerberusGenerator::plan`] returns every file of a generation as a
+//! [`GeneratedArtifact`] instead of writing it, so library users can filter
+//! or post-process the files and store them elsewhere, e.g. in object
+//! storage or Kubernetes ConfigMaps.
+//!
+//! ```
+//! use cerberus_core::config::{ConfigBuilder, ProxyConfigBuilder, ProxyType, ServiceConfigBuilder};
+//! use cerberus_core::generators::{ArtifactKind, CerberusGenerator};
+//!
+//! let config = ConfigBuilder::new("example")
+//!     .proxy(
+//!         ProxyConfigBuilder::new("edge", ProxyType::Caddy)
+//!             .external_port(80)
+//!             .build()?,
+//!     )
+//!     .service(ServiceConfigBuilder::new("app", "app.example.com", "http://app:3000").build()?)
+//!     .build()?;
+//! let plan = CerberusGenerator::new(&config, "built").plan()?;
+//! let proxy_configs: Vec<_> = plan
+//!     .iter()
+//!     .filter(|artifact| artifact.kind == ArtifactKind::ProxyConfig)
+//!     .collect();
+//! assert!(!proxy_configs.is_empty());
+//! # Ok::<(), cerberus_core::CerberusError>(())
+//! ```
+//!
+//! [`CerberusGenerator::plan`]: super::CerberusGenerator::plan
+
+use super::set_executable;
+use crate::Result;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Which generator produced an artifact
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtifactKind {
+    /// Docker Compose file, Swarm stack or Nomad jobspec
+    Deployment,
+    /// Proxy configuration, including Nginx `conf.d` files and entrypoints
+    ProxyConfig,
+    /// Proxy Dockerfiles, the multi-stage Dockerfile and the buildx bake file
+    Dockerfile,
+    /// Anubis bot policy and environment
+    Anubis,
+    /// CrowdSec acquisition and bouncer configs
+    CrowdSec,
+    /// Custom error pages
+    ErrorPages,
+    /// robots.txt and security.txt
+    Content,
+    /// Seccomp and AppArmor profiles of the proxies
+    SecurityProfiles,
+    /// DNS record plan
+    Dns,
+    /// Update script, runbook and Makefile
+    Operations,
+    /// vault-agent sidecar configuration
+    VaultAgent,
+    /// Time-based routing cron job
+    Schedule,
+    /// Routes report and monitoring configs
+    Observability,
+    /// File of a generator registered outside this crate, by its name
+    Custom(String),
+}
+
+impl ArtifactKind {
+    /// Name of the kind, matching the generator name of built-in kinds
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Deployment => "deployment",
+            Self::ProxyConfig => "proxy-configs",
+            Self::Dockerfile => "dockerfiles",
+            Self::Anubis => "anubis",
+            Self::CrowdSec => "crowdsec",
+            Self::ErrorPages => "error-pages",
+            Self::Content => "content",
+            Self::SecurityProfiles => "security-profiles",
+            Self::Dns => "dns",
+            Self::Operations => "operations",
+            Self::VaultAgent => "vault-agent",
+            Self::Schedule => "schedule",
+            Self::Observability => "observability",
+            Self::Custom(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A file produced by a generation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GeneratedArtifact {
+    /// Path relative to the output directory
+    pub path: PathBuf,
+    /// Generator that produced the file
+    pub kind: ArtifactKind,
+    /// File content, with LF line endings
+    pub content: String,
+}
+
+impl GeneratedArtifact {
+    /// Whether the file is a shell script written with the executable bit
+    pub fn is_executable(&self) -> bool {
+        self.path
+            .extension()
+            .is_some_and(|extension| extension == "sh")
+    }
+
+    /// Write the file below an output directory, creating parent directories
+    ///
+    /// # Errors
+    /// Returns error if the file cannot be written
+    pub fn write_to(&self, output_dir: &Path) -> Result<PathBuf> {
+        let file_path = output_dir.join(&self.path);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&file_path, &self.content)?;
+        set_executable(&file_path)?;
+        Ok(file_path)
+    }
+}
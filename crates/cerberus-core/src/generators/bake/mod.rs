@@ -0,0 +1,178 @@
+//! # Buildx bake file generator
+//!
+//! Writes [`BAKE_FILE`] with a target for every image the stack builds —
+//! proxies that need extra modules and services with a `[services.build]`
+//! section — so `docker buildx bake --push` builds them for all of their
+//! `platforms` at once. Compose keeps building for the host it runs on.
+
+use super::paths;
+use crate::config::{Config, ContainerOptions};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Bake file written to the output directory
+pub const BAKE_FILE: &str = "docker-bake.hcl";
+
+/// One image the bake file builds
+struct BakeTarget {
+    name: String,
+    context: String,
+    dockerfile: Option<String>,
+    target: Option<String>,
+    args: BTreeMap<String, String>,
+    platforms: Vec<String>,
+    tag: String,
+}
+
+/// Generator for the buildx bake file
+pub struct BakeGenerator<'a> {
+    config: &'a Config,
+}
+
+impl<'a> BakeGenerator<'a> {
+    /// Create a new bake file generator
+    pub fn new(config: &'a Config) -> Self {
+        Self { config }
+    }
+
+    /// The bake file, when any built image sets `platforms`
+    pub fn generate(&self) -> Option<String> {
+        let targets = self.targets();
+        if targets.iter().all(|target| target.platforms.is_empty()) {
+            return None;
+        }
+
+        let mut output = String::new();
+        writeln!(
+            output,
+            "# Multi-platform image builds for {}",
+            self.config.project.name
+        )
+        .unwrap();
+        writeln!(output, "# Generated by Cerberus Rust edition").unwrap();
+        writeln!(
+            output,
+            "# Build and push: REGISTRY=registry.example.com/ docker buildx bake --push"
+        )
+        .unwrap();
+        writeln!(output).unwrap();
+        writeln!(output, "variable \"REGISTRY\" {{\n  default = \"\"\n}}").unwrap();
+        writeln!(output).unwrap();
+        writeln!(output, "variable \"TAG\" {{\n  default = \"latest\"\n}}").unwrap();
+        writeln!(output).unwrap();
+        writeln!(output, "group \"default\" {{").unwrap();
+        writeln!(
+            output,
+            "  targets = {}",
+            list(targets.iter().map(|t| &t.name))
+        )
+        .unwrap();
+        writeln!(output, "}}").unwrap();
+
+        for target in &targets {
+            writeln!(output).unwrap();
+            writeln!(output, "target {} {{", string(&target.name)).unwrap();
+            writeln!(output, "  context = {}", string(&target.context)).unwrap();
+            if let Some(dockerfile) = &target.dockerfile {
+                writeln!(output, "  dockerfile = {}", string(dockerfile)).unwrap();
+            }
+            if let Some(stage) = &target.target {
+                writeln!(output, "  target = {}", string(stage)).unwrap();
+            }
+            if !target.args.is_empty() {
+                writeln!(output, "  args = {{").unwrap();
+                for (key, value) in &target.args {
+                    writeln!(output, "    {} = {}", string(key), string(value)).unwrap();
+                }
+                writeln!(output, "  }}").unwrap();
+            }
+            if !target.platforms.is_empty() {
+                writeln!(output, "  platforms = {}", list(&target.platforms)).unwrap();
+            }
+            writeln!(output, "  tags = [{}]", target.tag).unwrap();
+            writeln!(output, "}}").unwrap();
+        }
+        Some(output)
+    }
+
+    /// Built images, proxies first
+    fn targets(&self) -> Vec<BakeTarget> {
+        let defaults = &self.config.defaults;
+        let mut targets = Vec::new();
+        for proxy in &self.config.proxies {
+            if !self.config.builds_proxy_image(proxy) {
+                continue;
+            }
+            targets.push(BakeTarget {
+                name: proxy.name.clone(),
+                context: format!("./dockerfiles/{}", proxy.name),
+                dockerfile: None,
+                target: None,
+                args: BTreeMap::new(),
+                platforms: platforms(proxy.container_options(defaults)),
+                tag: self.default_tag(&proxy.name),
+            });
+        }
+        for service in &self.config.services {
+            let Some(build) = &service.build else {
+                continue;
+            };
+            targets.push(BakeTarget {
+                name: service.name.clone(),
+                context: paths::host_path(&build.context),
+                dockerfile: build.dockerfile.as_deref().map(paths::host_path),
+                target: build.target.clone(),
+                args: build.args.clone(),
+                platforms: platforms(service.container_options(defaults)),
+                tag: match &service.image {
+                    Some(image) => string(image),
+                    None => self.default_tag(&service.name),
+                },
+            });
+        }
+        targets
+    }
+
+    /// Tag of an image without an `image` name: `<project>-<name>`
+    fn default_tag(&self, name: &str) -> String {
+        format!(
+            "\"${{REGISTRY}}{}-{name}:${{TAG}}\"",
+            escape(&self.config.project.name)
+        )
+    }
+}
+
+/// Platforms an image is built for: its `platforms`, or its single `platform`
+fn platforms(options: ContainerOptions) -> Vec<String> {
+    if options.platforms.is_empty() {
+        options.platform.into_iter().collect()
+    } else {
+        options.platforms
+    }
+}
+
+/// HCL list of strings
+fn list<S: AsRef<str>>(items: impl IntoIterator<Item = S>) -> String {
+    let items: Vec<String> = items
+        .into_iter()
+        .map(|item| string(item.as_ref()))
+        .collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// HCL string literal, without interpolation
+fn string(value: &str) -> String {
+    format!("\"{}\"", escape(value))
+}
+
+/// Escape a value for an HCL string
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "$${")
+        .replace("%{", "%%{")
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,96 @@
+//! # Tests for bake file generation
+//!
+//! These tests verify the targets of the buildx bake file and when it is
+//! written at all.
+
+use super::*;
+use crate::test_support::parse_config;
+use pretty_assertions::assert_eq;
+
+/// Helper function to create a config with a WAF proxy and a built service
+fn create_config(defaults: &str, service: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "bake-test"
+
+[defaults]
+{defaults}
+
+[waf]
+enabled = true
+
+[[proxies]]
+name = "edge"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "api"
+domain = "api.example.com"
+upstream = "http://api:3000"
+{service}
+
+[services.build]
+context = "./api"
+dockerfile = "Dockerfile.prod"
+target = "runtime"
+args = {{ VERSION = "1.2", GREETING = "say \"hi\"" }}
+"#
+    ))
+}
+
+#[test]
+fn test_no_bake_file_without_platforms() {
+    let config = create_config("", "");
+    assert_eq!(BakeGenerator::new(&config).generate(), None);
+}
+
+#[test]
+fn test_bake_targets() {
+    let config = create_config(
+        "platforms = [\"linux/amd64\", \"linux/arm64\"]",
+        "image = \"registry.example.com/api:1.2\"\nplatforms = [\"linux/arm64\"]",
+    );
+    let bake = BakeGenerator::new(&config).generate().unwrap();
+    assert!(bake.contains("group \"default\" {\n  targets = [\"edge\", \"api\"]\n}\n"));
+    assert!(bake.contains(
+        "target \"edge\" {\n  \
+           context = \"./dockerfiles/edge\"\n  \
+           platforms = [\"linux/amd64\", \"linux/arm64\"]\n  \
+           tags = [\"${REGISTRY}bake-test-edge:${TAG}\"]\n\
+         }\n"
+    ));
+    assert!(bake.contains(
+        "target \"api\" {\n  \
+           context = \"./api\"\n  \
+           dockerfile = \"Dockerfile.prod\"\n  \
+           target = \"runtime\"\n  \
+           args = {\n    \
+             \"GREETING\" = \"say \\\"hi\\\"\"\n    \
+             \"VERSION\" = \"1.2\"\n  \
+           }\n  \
+           platforms = [\"linux/arm64\"]\n  \
+           tags = [\"registry.example.com/api:1.2\"]\n\
+         }\n"
+    ));
+}
+
+#[test]
+fn test_target_platforms() {
+    let config = create_config(
+        "",
+        "platform = \"linux/arm64\"\nplatforms = [\"linux/arm64\", \"linux/arm/v7\"]",
+    );
+    let bake = BakeGenerator::new(&config).generate().unwrap();
+    assert!(bake.contains("target \"edge\" {\n  context = \"./dockerfiles/edge\"\n  tags"));
+    assert!(bake.contains("  platforms = [\"linux/arm64\", \"linux/arm/v7\"]\n"));
+
+    // A fixed platform is built for when no platforms are listed
+    let config = create_config(
+        "platform = \"linux/amd64\"",
+        "platforms = [\"linux/amd64\", \"linux/arm64\"]",
+    );
+    let bake = BakeGenerator::new(&config).generate().unwrap();
+    assert!(bake.contains("  context = \"./dockerfiles/edge\"\n  platforms = [\"linux/amd64\"]\n"));
+}
@@ -0,0 +1,106 @@
+//! # Content generator
+//!
+//! Writes the files of `[content]` to [`CONTENT_DIR`] in the output
+//! directory: `robots.txt` from `[content.robots]` and `security.txt` from
+//! `[content.security_txt]`. The layer-1 proxies serve them from the
+//! read-only mount at `/robots.txt` and `/.well-known/security.txt`.
+
+use super::GeneratedFile;
+use crate::{
+    CerberusError, Result,
+    config::{CONTENT_DIR, Config, RobotsConfig, SecurityTxtConfig},
+};
+use std::fmt::Write;
+use std::path::Path;
+
+/// Generator for the `[content]` files
+pub struct ContentGenerator<'a> {
+    config: &'a Config,
+}
+
+impl<'a> ContentGenerator<'a> {
+    /// Create a new content generator
+    pub fn new(config: &'a Config) -> Self {
+        Self { config }
+    }
+
+    /// Files to write, relative to the output directory
+    ///
+    /// # Errors
+    /// Returns error if the robots.txt `file` cannot be read
+    pub fn files(&self) -> Result<Vec<GeneratedFile>> {
+        let dir = Path::new(CONTENT_DIR);
+        let content = &self.config.content;
+        let mut files = Vec::new();
+        if let Some(robots) = &content.robots {
+            files.push((dir.join("robots.txt"), self.robots_txt(robots)?));
+        }
+        if let Some(security_txt) = &content.security_txt {
+            files.push((dir.join("security.txt"), security_txt_fields(security_txt)));
+        }
+        Ok(files)
+    }
+
+    /// robots.txt, read from `file` or rendered from the rule groups
+    fn robots_txt(&self, robots: &RobotsConfig) -> Result<String> {
+        if let Some(file) = &robots.file {
+            return std::fs::read_to_string(file).map_err(|e| CerberusError::io(file, e));
+        }
+
+        let mut output = String::new();
+        writeln!(
+            output,
+            "# robots.txt for project: {}",
+            self.config.project.name
+        )
+        .unwrap();
+        writeln!(output, "# Generated by Cerberus Rust edition").unwrap();
+        for agent in &robots.agents {
+            writeln!(output).unwrap();
+            writeln!(output, "User-agent: {}", agent.user_agent).unwrap();
+            for path in &agent.allow {
+                writeln!(output, "Allow: {path}").unwrap();
+            }
+            for path in &agent.disallow {
+                writeln!(output, "Disallow: {path}").unwrap();
+            }
+            if let Some(delay) = agent.crawl_delay {
+                writeln!(output, "Crawl-delay: {delay}").unwrap();
+            }
+        }
+        if !robots.sitemaps.is_empty() {
+            writeln!(output).unwrap();
+            for sitemap in &robots.sitemaps {
+                writeln!(output, "Sitemap: {sitemap}").unwrap();
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// security.txt with its fields in the order of RFC 9116
+fn security_txt_fields(security_txt: &SecurityTxtConfig) -> String {
+    let mut output = String::new();
+    let mut field = |name: &str, values: &[String]| {
+        for value in values {
+            writeln!(output, "{name}: {value}").unwrap();
+        }
+    };
+    field("Contact", &security_txt.contact);
+    field("Expires", std::slice::from_ref(&security_txt.expires));
+    field("Encryption", &security_txt.encryption);
+    field("Acknowledgments", &security_txt.acknowledgments);
+    if !security_txt.preferred_languages.is_empty() {
+        field(
+            "Preferred-Languages",
+            &[security_txt.preferred_languages.join(", ")],
+        );
+    }
+    field("Canonical", &security_txt.canonical);
+    field("Policy", &security_txt.policy);
+    field("Hiring", &security_txt.hiring);
+    output
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,113 @@
+//! # Tests for content generation
+//!
+//! These tests verify that robots.txt is rendered from its rule groups or
+//! copied from a file, and that security.txt lists its fields in RFC 9116
+//! order.
+
+use super::*;
+use crate::test_support::parse_config;
+use std::io::Write;
+use std::path::PathBuf;
+use tempfile::NamedTempFile;
+
+/// Helper function to create a config with one proxy and the given content
+fn create_config(content: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "content-test"
+
+{content}
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+"#
+    ))
+}
+
+#[test]
+fn test_disabled_by_default() {
+    let config = create_config("");
+    assert!(
+        ContentGenerator::new(&config)
+            .files()
+            .expect("Generation should succeed")
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_robots_and_security_txt() {
+    let config = create_config(
+        r#"
+[content.robots]
+sitemaps = ["https://example.com/sitemap.xml"]
+
+[[content.robots.agents]]
+user_agent = "*"
+allow = ["/public/"]
+disallow = ["/admin/", "/search"]
+
+[[content.robots.agents]]
+user_agent = "GPTBot"
+disallow = ["/"]
+crawl_delay = 10
+
+[content.security_txt]
+contact = ["mailto:security@example.com", "https://example.com/report"]
+expires = "2027-01-01T00:00:00Z"
+preferred_languages = ["en", "ja"]
+policy = ["https://example.com/disclosure"]
+"#,
+    );
+    let files = ContentGenerator::new(&config)
+        .files()
+        .expect("Generation should succeed");
+    assert_eq!(
+        files
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>(),
+        vec![
+            PathBuf::from("content/robots.txt"),
+            PathBuf::from("content/security.txt"),
+        ]
+    );
+
+    let robots = &files[0].1;
+    assert!(
+        robots.contains("\nUser-agent: *\nAllow: /public/\nDisallow: /admin/\nDisallow: /search\n")
+    );
+    assert!(robots.contains("\nUser-agent: GPTBot\nDisallow: /\nCrawl-delay: 10\n"));
+    assert!(robots.ends_with("\nSitemap: https://example.com/sitemap.xml\n"));
+
+    assert_eq!(
+        files[1].1,
+        "Contact: mailto:security@example.com\n\
+         Contact: https://example.com/report\n\
+         Expires: 2027-01-01T00:00:00Z\n\
+         Preferred-Languages: en, ja\n\
+         Policy: https://example.com/disclosure\n"
+    );
+}
+
+#[test]
+fn test_robots_file() {
+    let mut file = NamedTempFile::new().expect("Failed to create temp file");
+    file.write_all(b"User-agent: *\nDisallow:\n")
+        .expect("Failed to write to temp file");
+    let config = create_config(&format!(
+        "[content.robots]\nfile = {:?}\n",
+        file.path().display().to_string()
+    ));
+    let files = ContentGenerator::new(&config)
+        .files()
+        .expect("Generation should succeed");
+    assert_eq!(files[0].1, "User-agent: *\nDisallow:\n");
+
+    let mut missing = config.clone();
+    missing.content.robots.as_mut().unwrap().file = Some("missing-robots.txt".to_string());
+    assert!(ContentGenerator::new(&missing).files().is_err());
+}
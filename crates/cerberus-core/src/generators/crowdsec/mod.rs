@@ -0,0 +1,226 @@
+//! # CrowdSec generator
+//!
+//! Generates the CrowdSec agent's log acquisition, the SPOA bouncer settings,
+//! and the HAProxy SPOE filter. The agent reads the access logs the proxies
+//! write to the shared log directory (HAProxy logs to stdout and is read
+//! through the Docker socket). Edge proxies ask a bouncer about every
+//! request: Nginx and Traefik through forward auth, HAProxy through SPOE.
+
+use super::DockerComposeGenerator;
+use crate::{
+    Result,
+    config::{Config, ProxyConfig, ProxyType},
+};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Directory the agent mounts the shared proxy logs at
+pub const LOG_DIR: &str = "/var/log/cerberus";
+
+/// Forward-auth endpoint of the bouncer used by Nginx and Traefik
+pub const FORWARD_AUTH_URL: &str = "http://crowdsec-bouncer:8080/api/v1/forwardAuth";
+
+/// How a proxy asks CrowdSec for a decision
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bouncer {
+    /// HTTP subrequest to the forward-auth bouncer
+    ForwardAuth,
+    /// HAProxy SPOE call to the SPOA bouncer
+    Spoa,
+}
+
+/// Generator for CrowdSec configuration
+pub struct CrowdSecGenerator<'a> {
+    config: &'a Config,
+}
+
+impl<'a> CrowdSecGenerator<'a> {
+    /// Create a new CrowdSec generator
+    pub fn new(config: &'a Config) -> Self {
+        Self { config }
+    }
+
+    /// Whether CrowdSec is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.config.security.crowdsec.enabled
+    }
+
+    /// Bouncer a proxy uses, if it is an edge proxy that supports one
+    ///
+    /// Inner layers only see the edge proxy's address, so they are not bounced.
+    pub fn bouncer(&self, proxy: &ProxyConfig) -> Option<Bouncer> {
+        if !self.is_enabled() || !DockerComposeGenerator::new(self.config).is_edge_proxy(proxy) {
+            return None;
+        }
+
+        match proxy.proxy_type {
+            ProxyType::Nginx | ProxyType::Traefik => Some(Bouncer::ForwardAuth),
+            ProxyType::HaProxy => Some(Bouncer::Spoa),
+            ProxyType::Caddy | ProxyType::Varnish => None,
+        }
+    }
+
+    /// Whether any proxy uses the given bouncer
+    pub fn uses_bouncer(&self, bouncer: Bouncer) -> bool {
+        self.config
+            .proxies
+            .iter()
+            .any(|proxy| self.bouncer(proxy) == Some(bouncer))
+    }
+
+    /// Whether the agent reads HAProxy logs from the Docker socket
+    pub fn reads_docker_logs(&self) -> bool {
+        self.haproxy_containers().next().is_some()
+    }
+
+    /// Write acquis.yaml, the SPOA bouncer config, and HAProxy SPOE filters
+    pub fn generate(&self, output_dir: &Path) -> Result<()> {
+        super::write_files(output_dir, self.files())
+    }
+
+    /// Files to write, relative to the output directory
+    pub fn files(&self) -> Vec<(PathBuf, String)> {
+        let mut files = vec![(
+            PathBuf::from("crowdsec/acquis.yaml"),
+            self.generate_acquis(),
+        )];
+
+        if self.uses_bouncer(Bouncer::Spoa) {
+            files.push((
+                PathBuf::from("crowdsec/spoa-bouncer.yaml"),
+                self.generate_spoa_config(),
+            ));
+
+            for proxy in &self.config.proxies {
+                if self.bouncer(proxy) == Some(Bouncer::Spoa) {
+                    files.push((
+                        Path::new("proxy-configs")
+                            .join(&proxy.name)
+                            .join("crowdsec-spoe.cfg"),
+                        self.generate_spoe(),
+                    ));
+                }
+            }
+        }
+
+        files
+    }
+
+    /// Generate the agent's log acquisition
+    pub fn generate_acquis(&self) -> String {
+        let mut acquis = String::new();
+        let mut write_files = |label: &str, files: Vec<String>| {
+            if files.is_empty() {
+                return;
+            }
+            if !acquis.is_empty() {
+                writeln!(acquis, "---").unwrap();
+            }
+            writeln!(acquis, "filenames:").unwrap();
+            for file in files {
+                writeln!(acquis, "  - {LOG_DIR}/{file}").unwrap();
+            }
+            writeln!(acquis, "labels:").unwrap();
+            writeln!(acquis, "  type: {label}").unwrap();
+        };
+
+        let proxies_of = |proxy_type: ProxyType| {
+            self.config
+                .proxies
+                .iter()
+                .filter(move |proxy| proxy.proxy_type == proxy_type)
+        };
+
+        let mut nginx_files: Vec<String> = proxies_of(ProxyType::Nginx)
+            .flat_map(|proxy| self.config.access_log_files(proxy))
+            .collect();
+        nginx_files.sort();
+        nginx_files.dedup();
+        write_files("nginx", nginx_files);
+
+        for proxy_type in [ProxyType::Traefik, ProxyType::Caddy] {
+            write_files(
+                proxy_type.as_str(),
+                proxies_of(proxy_type)
+                    .flat_map(|proxy| self.config.access_log_files(proxy))
+                    .collect(),
+            );
+        }
+
+        let haproxy: Vec<String> = self.haproxy_containers().collect();
+        if !haproxy.is_empty() {
+            if !acquis.is_empty() {
+                writeln!(acquis, "---").unwrap();
+            }
+            writeln!(acquis, "source: docker").unwrap();
+            writeln!(acquis, "container_name:").unwrap();
+            for container in haproxy {
+                writeln!(acquis, "  - {container}").unwrap();
+            }
+            writeln!(acquis, "labels:").unwrap();
+            writeln!(acquis, "  type: haproxy").unwrap();
+        }
+
+        acquis
+    }
+
+    /// Generate the SPOA bouncer configuration
+    pub fn generate_spoa_config(&self) -> String {
+        let mut spoa = String::new();
+        writeln!(
+            spoa,
+            "# CrowdSec SPOA bouncer for project: {}",
+            self.config.project.name
+        )
+        .unwrap();
+        writeln!(spoa, "log_mode: stdout").unwrap();
+        writeln!(spoa, "update_frequency: 10s").unwrap();
+        writeln!(spoa, "api_url: http://crowdsec:8080/").unwrap();
+        writeln!(
+            spoa,
+            "api_key: ${{{}}}",
+            self.config.security.crowdsec.api_key_env
+        )
+        .unwrap();
+        writeln!(spoa, "listen_tcp: 0.0.0.0:9000").unwrap();
+        spoa
+    }
+
+    /// Generate the HAProxy SPOE filter configuration
+    pub fn generate_spoe(&self) -> String {
+        let mut spoe = String::new();
+        writeln!(spoe, "# CrowdSec SPOE filter").unwrap();
+        writeln!(spoe, "[crowdsec]").unwrap();
+        writeln!(spoe, "spoe-agent crowdsec-agent").unwrap();
+        writeln!(spoe, "    messages crowdsec-http").unwrap();
+        writeln!(spoe, "    option var-prefix crowdsec").unwrap();
+        writeln!(spoe, "    option set-on-error error").unwrap();
+        writeln!(spoe, "    timeout hello 100ms").unwrap();
+        writeln!(spoe, "    timeout idle 30s").unwrap();
+        writeln!(spoe, "    timeout processing 500ms").unwrap();
+        writeln!(spoe, "    use-backend crowdsec_spoa").unwrap();
+        writeln!(spoe, "    log global").unwrap();
+        writeln!(spoe).unwrap();
+        writeln!(spoe, "spoe-message crowdsec-http").unwrap();
+        writeln!(
+            spoe,
+            "    args remediation=str(allow) src-ip=src host=req.hdr(host) method=method path=path"
+        )
+        .unwrap();
+        writeln!(spoe, "    event on-frontend-http-request").unwrap();
+        spoe
+    }
+
+    /// Container names of HAProxy proxies, including scaled instances
+    fn haproxy_containers(&self) -> impl Iterator<Item = String> {
+        let scaling = self.config.project.scaling;
+        self.config
+            .proxies
+            .iter()
+            .filter(move |proxy| self.is_enabled() && proxy.proxy_type == ProxyType::HaProxy)
+            .flat_map(move |proxy| proxy.container_names(scaling))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,185 @@
+//! # Tests for CrowdSec generation
+//!
+//! These tests verify that enabling CrowdSec adds the agent and bouncer
+//! services, shares the proxy logs with the agent, and wires each edge
+//! proxy to its bouncer.
+
+use super::*;
+use crate::generators::{DockerComposeGenerator, ProxyConfigGenerator};
+use crate::test_support::parse_config;
+
+/// Helper function to create a two-layer config with CrowdSec enabled
+///
+/// Anubis sits between the layers so only the first layer is published.
+fn create_crowdsec_config(edge_type: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "crowdsec-test"
+
+[anubis]
+enabled = true
+
+[security.crowdsec]
+enabled = true
+
+[[proxies]]
+name = "proxy"
+type = "{edge_type}"
+external_port = 80
+default_upstream = "http://proxy-2:80"
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+layer = 2
+
+[[services]]
+name = "web-service"
+domain = "example.com"
+upstream = "http://192.0.2.1:3000"
+"#
+    ))
+}
+
+#[test]
+fn test_disabled_by_default() {
+    let config = parse_config(
+        r#"
+[project]
+name = "crowdsec-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+"#,
+    );
+
+    assert!(!CrowdSecGenerator::new(&config).is_enabled());
+    assert_eq!(
+        CrowdSecGenerator::new(&config).bouncer(&config.proxies[0]),
+        None
+    );
+
+    let compose = DockerComposeGenerator::new(&config).generate().unwrap();
+    assert!(!compose.contains("crowdsec:"));
+    assert!(!compose.contains("crowdsec_data"));
+}
+
+#[test]
+fn test_only_edge_proxies_are_bounced() {
+    let config = create_crowdsec_config("nginx");
+    let generator = CrowdSecGenerator::new(&config);
+
+    assert_eq!(
+        generator.bouncer(&config.proxies[0]),
+        Some(Bouncer::ForwardAuth)
+    );
+    assert_eq!(generator.bouncer(&config.proxies[1]), None);
+    assert!(!generator.uses_bouncer(Bouncer::Spoa));
+}
+
+#[test]
+fn test_acquis_reads_shared_logs() {
+    let config = create_crowdsec_config("nginx");
+    let acquis = CrowdSecGenerator::new(&config).generate_acquis();
+
+    assert!(acquis.contains("- /var/log/cerberus/access.log"));
+    assert!(acquis.contains("- /var/log/cerberus/web-service_access.log"));
+    assert!(acquis.contains("type: nginx"));
+    assert!(!acquis.contains("source: docker"));
+    assert!(serde_yaml::from_str::<serde_yaml::Value>(&acquis).is_ok());
+}
+
+#[test]
+fn test_compose_forward_auth_services() {
+    let config = create_crowdsec_config("nginx");
+    let compose = DockerComposeGenerator::new(&config).generate().unwrap();
+
+    assert!(compose.contains("  crowdsec:\n"));
+    assert!(compose.contains("- COLLECTIONS=crowdsecurity/nginx"));
+    assert!(compose.contains("- BOUNCER_KEY_cerberus=${CROWDSEC_BOUNCER_KEY}"));
+    assert!(compose.contains("- ./built/logs:/var/log/cerberus:ro"));
+    assert!(compose.contains("  crowdsec-bouncer:\n"));
+    assert!(compose.contains("- CROWDSEC_BOUNCER_API_KEY=${CROWDSEC_BOUNCER_KEY}"));
+    assert!(!compose.contains("crowdsec-spoa"));
+    assert!(!compose.contains("docker.sock"));
+    assert!(compose.contains("  crowdsec_data:\n"));
+    assert!(serde_yaml::from_str::<serde_yaml::Value>(&compose).is_ok());
+}
+
+#[test]
+fn test_nginx_auth_request() {
+    let config = create_crowdsec_config("nginx");
+    let generator = ProxyConfigGenerator::new(&config);
+
+    let edge = generator
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    assert!(edge["crowdsec.inc"].contains("auth_request /_crowdsec;"));
+    assert!(edge["crowdsec.inc"].contains(FORWARD_AUTH_URL));
+    assert!(edge["default.conf"].contains("include /etc/nginx/conf.d/crowdsec.inc;"));
+
+    let inner = generator
+        .generate_nginx_configs(&config.proxies[1])
+        .unwrap();
+    assert!(!inner.contains_key("crowdsec.inc"));
+    assert!(!inner["web_service.conf"].contains("crowdsec.inc"));
+}
+
+#[test]
+fn test_traefik_forward_auth_middleware() {
+    let config = create_crowdsec_config("traefik");
+    let files = ProxyConfigGenerator::new(&config)
+        .generate_traefik_configs(&config.proxies[0])
+        .unwrap();
+    for (path, content) in &files {
+        assert!(
+            serde_yaml::from_str::<serde_yaml::Value>(content).is_ok(),
+            "{} should be valid YAML",
+            path.display()
+        );
+    }
+    let traefik: String = files.into_iter().map(|(_, content)| content).collect();
+
+    assert!(traefik.contains("crowdsec-bouncer:\n      forwardAuth:"));
+    assert!(traefik.contains("- crowdsec-bouncer"));
+
+    let acquis = CrowdSecGenerator::new(&config).generate_acquis();
+    assert!(acquis.contains("- /var/log/cerberus/proxy_access.log"));
+    assert!(acquis.contains("type: traefik"));
+}
+
+#[test]
+fn test_haproxy_spoe_bouncer() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config = create_crowdsec_config("haproxy");
+    let generator = CrowdSecGenerator::new(&config);
+
+    generator.generate(temp_dir.path()).unwrap();
+    assert!(temp_dir.path().join("crowdsec/acquis.yaml").exists());
+    assert!(temp_dir.path().join("crowdsec/spoa-bouncer.yaml").exists());
+    let spoe = std::fs::read_to_string(
+        temp_dir
+            .path()
+            .join("proxy-configs/proxy/crowdsec-spoe.cfg"),
+    )
+    .unwrap();
+    assert!(spoe.contains("use-backend crowdsec_spoa"));
+
+    let acquis = generator.generate_acquis();
+    assert!(acquis.contains("source: docker"));
+    assert!(acquis.contains("  - proxy\n"));
+
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(haproxy.contains("filter spoe engine crowdsec"));
+    assert!(haproxy.contains("backend crowdsec_spoa"));
+
+    let compose = DockerComposeGenerator::new(&config).generate().unwrap();
+    assert!(compose.contains("  crowdsec-spoa:\n"));
+    assert!(compose.contains("- /var/run/docker.sock:/var/run/docker.sock:ro"));
+    assert!(!compose.contains("crowdsec-bouncer:"));
+}
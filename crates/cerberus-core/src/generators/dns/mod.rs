@@ -0,0 +1,275 @@
+//! # DNS record plan generator
+//!
+//! Lists the records every service and route domain needs so that it points
+//! at the deployment hosts from `[dns]`: `A`/`AAAA` records for addresses, or
+//! a `CNAME` to a single host name. The plan is written as a zone-file
+//! fragment or as Cloudflare/Route 53 API payloads, and [`DnsGenerator::check`]
+//! compares it with what live DNS returns.
+
+use crate::{
+    Result,
+    config::{Config, DnsProvider},
+    report::FindingStatus,
+};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+
+/// A DNS record required by the deployment
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DnsRecord {
+    /// Fully qualified name without the trailing dot
+    pub name: String,
+    /// Record type (`A`, `AAAA` or `CNAME`)
+    pub record_type: &'static str,
+    /// Address or alias target
+    pub value: String,
+}
+
+/// Result of checking one domain against live DNS
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DnsCheck {
+    /// Domain that was resolved
+    pub domain: String,
+    /// Whether live DNS matches the plan
+    pub status: FindingStatus,
+    /// Addresses the plan points the domain at
+    pub expected: Vec<String>,
+    /// Addresses live DNS returned
+    pub found: Vec<String>,
+    /// What matched, or why the check failed
+    pub message: String,
+}
+
+/// Generator for the DNS record plan
+pub struct DnsGenerator<'a> {
+    config: &'a Config,
+}
+
+impl<'a> DnsGenerator<'a> {
+    /// Create a new DNS generator
+    pub fn new(config: &'a Config) -> Self {
+        Self { config }
+    }
+
+    /// Whether deployment hosts are configured
+    pub fn is_needed(&self) -> bool {
+        !self.config.dns.hosts.is_empty()
+    }
+
+    /// Service and route domains, sorted and without duplicates
+    pub fn domains(&self) -> Vec<String> {
+        let services = self
+            .config
+            .services
+            .iter()
+            .flat_map(|service| service.hosts());
+        let routes = self
+            .config
+            .proxies
+            .iter()
+            .flat_map(|proxy| proxy.routes.iter().map(|route| route.domain.as_str()));
+
+        services
+            .chain(routes)
+            .map(|domain| domain.trim_end_matches('.').to_lowercase())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Records required for every domain
+    pub fn records(&self) -> Vec<DnsRecord> {
+        let mut records = Vec::new();
+        for domain in self.domains() {
+            for host in &self.config.dns.hosts {
+                let record_type = match host.parse::<IpAddr>() {
+                    Ok(IpAddr::V4(_)) => "A",
+                    Ok(IpAddr::V6(_)) => "AAAA",
+                    Err(_) => "CNAME",
+                };
+                records.push(DnsRecord {
+                    name: domain.clone(),
+                    record_type,
+                    value: host.trim_end_matches('.').to_string(),
+                });
+            }
+        }
+        records
+    }
+
+    /// Files to write, relative to the output directory
+    ///
+    /// # Errors
+    /// Returns error if a JSON plan cannot be serialized
+    pub fn files(&self) -> Result<Vec<(PathBuf, String)>> {
+        let (file, content) = match self.config.dns.provider {
+            DnsProvider::Zone => ("records.zone", self.generate_zone()),
+            DnsProvider::Cloudflare => ("cloudflare.json", self.generate_cloudflare()?),
+            DnsProvider::Route53 => ("route53.json", self.generate_route53()?),
+        };
+        Ok(vec![(Path::new("dns").join(file), content)])
+    }
+
+    /// Write the record plan
+    pub fn generate(&self, output_dir: &Path) -> Result<()> {
+        super::write_files(output_dir, self.files()?)
+    }
+
+    /// BIND zone-file fragment with fully qualified names
+    pub fn generate_zone(&self) -> String {
+        let mut zone = String::new();
+        writeln!(zone, "; DNS records for {}", self.config.project.name).unwrap();
+        writeln!(zone, "; Generated by Cerberus").unwrap();
+        writeln!(zone, "$TTL {}", self.config.dns.ttl).unwrap();
+        for record in self.records() {
+            let value = if record.record_type == "CNAME" {
+                format!("{}.", record.value)
+            } else {
+                record.value
+            };
+            writeln!(
+                zone,
+                "{}.\t{}\tIN\t{}\t{}",
+                record.name, self.config.dns.ttl, record.record_type, value
+            )
+            .unwrap();
+        }
+        zone
+    }
+
+    /// Cloudflare "create DNS record" request bodies
+    pub fn generate_cloudflare(&self) -> Result<String> {
+        let records: Vec<_> = self
+            .records()
+            .into_iter()
+            .map(|record| {
+                json!({
+                    "type": record.record_type,
+                    "name": record.name,
+                    "content": record.value,
+                    "ttl": self.config.dns.ttl,
+                    "proxied": false,
+                })
+            })
+            .collect();
+        Ok(serde_json::to_string_pretty(&records)?)
+    }
+
+    /// Route 53 `ChangeResourceRecordSets` change batch
+    pub fn generate_route53(&self) -> Result<String> {
+        let mut record_sets: BTreeMap<(String, &str), Vec<String>> = BTreeMap::new();
+        for record in self.records() {
+            record_sets
+                .entry((record.name, record.record_type))
+                .or_default()
+                .push(record.value);
+        }
+
+        let changes: Vec<_> = record_sets
+            .into_iter()
+            .map(|((name, record_type), values)| {
+                json!({
+                    "Action": "UPSERT",
+                    "ResourceRecordSet": {
+                        "Name": format!("{name}."),
+                        "Type": record_type,
+                        "TTL": self.config.dns.ttl,
+                        "ResourceRecords": values
+                            .into_iter()
+                            .map(|value| json!({ "Value": value }))
+                            .collect::<Vec<_>>(),
+                    },
+                })
+            })
+            .collect();
+
+        Ok(serde_json::to_string_pretty(&json!({
+            "Comment": format!("Cerberus records for {}", self.config.project.name),
+            "Changes": changes,
+        }))?)
+    }
+
+    /// Resolve every domain and compare it with the plan
+    ///
+    /// A `CNAME` plan is satisfied when the domain resolves to the same
+    /// addresses as the alias target. Wildcard domains cannot be resolved
+    /// and are reported as errors.
+    pub fn check(&self) -> Vec<DnsCheck> {
+        let mut expected: BTreeSet<String> = BTreeSet::new();
+        let mut alias = None;
+        for host in &self.config.dns.hosts {
+            match host.parse::<IpAddr>() {
+                Ok(address) => {
+                    expected.insert(address.to_string());
+                }
+                Err(_) => alias = Some(host.trim_end_matches('.')),
+            }
+        }
+        if let Some(alias) = alias {
+            expected = resolve(alias).unwrap_or_default();
+        }
+        let expected: Vec<String> = expected.into_iter().collect();
+
+        let mut checks = Vec::new();
+        for domain in self.domains() {
+            let check = |status, found: Vec<String>, message: String| DnsCheck {
+                domain: domain.clone(),
+                status,
+                expected: expected.clone(),
+                found,
+                message,
+            };
+
+            if domain.starts_with('*') {
+                checks.push(check(
+                    FindingStatus::Error,
+                    Vec::new(),
+                    "Wildcard domains cannot be checked; resolve a name below it".to_string(),
+                ));
+                continue;
+            }
+
+            let check = match resolve(&domain) {
+                Err(e) => check(
+                    FindingStatus::Error,
+                    Vec::new(),
+                    format!("Does not resolve: {e}"),
+                ),
+                Ok(found) => {
+                    let found: Vec<String> = found.into_iter().collect();
+                    if found == expected {
+                        check(FindingStatus::Ok, found, "Matches the plan".to_string())
+                    } else {
+                        let message = format!(
+                            "Resolves to {} instead of {}",
+                            found.join(", "),
+                            if expected.is_empty() {
+                                "the unresolvable alias target".to_string()
+                            } else {
+                                expected.join(", ")
+                            }
+                        );
+                        check(FindingStatus::Error, found, message)
+                    }
+                }
+            };
+            checks.push(check);
+        }
+        checks
+    }
+}
+
+/// Addresses a name resolves to through the system resolver
+fn resolve(name: &str) -> std::io::Result<BTreeSet<String>> {
+    Ok((name, 0)
+        .to_socket_addrs()?
+        .map(|address| address.ip().to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,149 @@
+//! # Tests for the DNS record plan generator
+//!
+//! These tests verify the records derived from the service and route domains
+//! and the zone-file, Cloudflare and Route 53 output formats.
+
+use super::*;
+use crate::test_support::parse_config;
+use pretty_assertions::assert_eq;
+
+/// Helper function to create a config with two services and one route
+fn create_dns_config(dns: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "dns-test"
+
+[dns]
+{dns}
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[proxies.routes]]
+type = "direct"
+domain = "status.example.com"
+upstream = "http://status:8080"
+
+[[services]]
+name = "web-app"
+domain = "App.example.com"
+upstream = "http://web:3000"
+
+[[services]]
+name = "api"
+domain = "api.example.com"
+upstream = "http://api:3000"
+"#
+    ))
+}
+
+#[test]
+fn test_address_records() {
+    let config = create_dns_config(r#"hosts = ["203.0.113.10", "2001:db8::10"]"#);
+    let generator = DnsGenerator::new(&config);
+
+    assert!(generator.is_needed());
+    assert_eq!(
+        generator.domains(),
+        vec!["api.example.com", "app.example.com", "status.example.com"]
+    );
+
+    let records = generator.records();
+    assert_eq!(records.len(), 6);
+    assert_eq!(
+        records[0],
+        DnsRecord {
+            name: "api.example.com".to_string(),
+            record_type: "A",
+            value: "203.0.113.10".to_string(),
+        }
+    );
+    assert_eq!(records[1].record_type, "AAAA");
+
+    let zone = generator.generate_zone();
+    assert!(zone.contains("$TTL 300\n"));
+    assert!(zone.contains("app.example.com.\t300\tIN\tA\t203.0.113.10\n"));
+    assert!(zone.contains("app.example.com.\t300\tIN\tAAAA\t2001:db8::10\n"));
+}
+
+#[test]
+fn test_alias_record() {
+    let config = create_dns_config(
+        r#"hosts = ["lb.example.net."]
+ttl = 60"#,
+    );
+    let zone = DnsGenerator::new(&config).generate_zone();
+
+    assert!(zone.contains("api.example.com.\t60\tIN\tCNAME\tlb.example.net.\n"));
+    assert!(!zone.contains("\tA\t"));
+}
+
+#[test]
+fn test_provider_formats() {
+    let config = create_dns_config(
+        r#"hosts = ["203.0.113.10", "203.0.113.11"]
+provider = "cloudflare""#,
+    );
+    let generator = DnsGenerator::new(&config);
+
+    let files = generator.files().expect("Files should render");
+    assert_eq!(files[0].0, Path::new("dns/cloudflare.json"));
+    let cloudflare: serde_json::Value =
+        serde_json::from_str(&files[0].1).expect("Should be valid JSON");
+    assert_eq!(cloudflare.as_array().map(Vec::len), Some(6));
+    assert_eq!(cloudflare[0]["type"], "A");
+    assert_eq!(cloudflare[0]["name"], "api.example.com");
+    assert_eq!(cloudflare[0]["content"], "203.0.113.10");
+    assert_eq!(cloudflare[0]["proxied"], false);
+
+    // Route 53 groups the addresses of a name into one record set
+    let route53: serde_json::Value = serde_json::from_str(
+        &generator
+            .generate_route53()
+            .expect("Route 53 plan should render"),
+    )
+    .expect("Should be valid JSON");
+    let changes = route53["Changes"]
+        .as_array()
+        .expect("Changes should be a list");
+    assert_eq!(changes.len(), 3);
+    assert_eq!(changes[0]["Action"], "UPSERT");
+    assert_eq!(changes[0]["ResourceRecordSet"]["Name"], "api.example.com.");
+    assert_eq!(
+        changes[0]["ResourceRecordSet"]["ResourceRecords"],
+        json!([{ "Value": "203.0.113.10" }, { "Value": "203.0.113.11" }])
+    );
+}
+
+#[test]
+fn test_no_plan_without_hosts() {
+    let config = create_dns_config("");
+    assert!(!DnsGenerator::new(&config).is_needed());
+}
+
+#[test]
+fn test_check_rejects_wildcard_domains() {
+    let config = parse_config(
+        r#"
+[project]
+name = "dns-test"
+
+[dns]
+hosts = ["203.0.113.10"]
+
+[[services]]
+name = "tenants"
+domain = "*.example.com"
+upstream = "http://tenants:3000"
+"#,
+    );
+
+    let checks = DnsGenerator::new(&config).check();
+    assert_eq!(checks.len(), 1);
+    assert_eq!(checks[0].status, FindingStatus::Error);
+    assert_eq!(checks[0].expected, vec!["203.0.113.10"]);
+    assert!(checks[0].message.contains("Wildcard"));
+}
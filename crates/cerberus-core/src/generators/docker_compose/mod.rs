@@ -0,0 +1,2163 @@
+//! # Docker Compose generator
+//!
+//! Generates docker-compose.yaml files from Cerberus configuration.
+
+use crate::{
+    CerberusError, Result,
+    config::{
+        AnubisContainer, AuthProvider, AuthType, CONTAINER_CONTENT_DIR, CONTAINER_ERROR_PAGES_DIR,
+        CONTENT_DIR, CommandConfig, Config, ContainerOptions, DependencyCondition, DependsOn,
+        DeployTarget, ERROR_PAGES_DIR, HealthCheckType, InstancePortStrategy, LogShipper,
+        LogShippingConfig, OTEL_COLLECTOR_ENDPOINT, ProxyConfig, ProxyType, ResourcesConfig,
+        SecretConfig, ServiceConfig, ServiceSecretRef, TracingConfig, VAULT_SCHEME,
+        parse_duration_secs, parse_size_bytes, upstream_address,
+    },
+    generators::{
+        ErrorPagesGenerator, NetworkResolver, ObservabilityGenerator, SecurityProfilesGenerator,
+        crowdsec::{self, Bouncer, CrowdSecGenerator},
+        dockerfile::NGINX_OTEL_IMAGE,
+        error_pages, paths,
+        proxy_config::ENTRYPOINT_PATH,
+    },
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::process::Command;
+
+/// File the Compose target renders into the output directory
+pub const COMPOSE_FILE: &str = "docker-compose.yaml";
+
+/// File a Swarm target renders instead of [`COMPOSE_FILE`]
+pub const STACK_FILE: &str = "docker-stack.yaml";
+
+/// Host side of a port a proxy container publishes
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum HostPort {
+    /// A host port, or a range of them for replicas
+    Fixed(String),
+    /// A free port Docker picks when the container starts
+    Random,
+}
+
+impl HostPort {
+    /// Compose port mapping to a container port
+    fn mapping(&self, container_port: u16) -> String {
+        match self {
+            HostPort::Fixed(host) => format!("{host}:{container_port}"),
+            HostPort::Random => container_port.to_string(),
+        }
+    }
+}
+
+/// Generator for Docker Compose configurations
+///
+/// With the `swarm` target the same services are rendered as a stack file
+/// for `docker stack deploy`.
+pub struct DockerComposeGenerator<'a> {
+    config: &'a Config,
+    networks: NetworkResolver<'a>,
+    vault_agent: bool,
+}
+
+impl<'a> DockerComposeGenerator<'a> {
+    /// Create a new Docker Compose generator
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            config,
+            networks: NetworkResolver::new(config),
+            vault_agent: false,
+        }
+    }
+
+    /// Render `vault://` references with a vault-agent sidecar
+    ///
+    /// Without it, Cerberus writes the values into `secrets/` itself.
+    pub fn vault_agent(mut self, vault_agent: bool) -> Self {
+        self.vault_agent = vault_agent;
+        self
+    }
+
+    /// Generate Docker Compose YAML content
+    pub fn generate(&self) -> Result<String> {
+        let mut output = String::new();
+
+        // Add header
+        writeln!(output).unwrap();
+        writeln!(output, "# Generated by Cerberus").unwrap();
+        writeln!(output, "# Project: {}", self.config.project.name).unwrap();
+        writeln!(output, "# Configuration: config.toml").unwrap();
+        writeln!(output).unwrap();
+        if self.swarm() {
+            // docker stack deploy still requires a version 3 file
+            writeln!(output, "version: \"3.8\"").unwrap();
+            writeln!(output).unwrap();
+        }
+
+        // Generate services section
+        writeln!(output, "services:").unwrap();
+
+        // Generate proxy services
+        for (index, proxy) in self.config.proxies.iter().enumerate() {
+            if !self.includes_proxy(proxy) {
+                continue;
+            }
+            self.generate_proxy_service(&mut output, proxy, index)?;
+
+            // Generate scaled instances if needed
+            let instances = proxy.duplicated_instances(self.config.project.scaling);
+            if instances > 1 {
+                for instance in 2..=instances {
+                    self.generate_scaled_proxy_instance(&mut output, proxy, index, instance)?;
+                }
+            }
+
+            // internal-only instances sit behind a load balancer publishing the port
+            if let Some(balancer) = proxy.instance_balancer(self.config.project.scaling) {
+                self.generate_instance_balancer(&mut output, proxy, index, &balancer);
+            }
+        }
+
+        // Anubis sits between layer 1 and the next layer, whatever the proxy types
+        if self.config.anubis.enabled {
+            for container in self.config.anubis.containers() {
+                self.generate_anubis_service(&mut output, &container)?;
+            }
+        }
+
+        // Generate the forward auth provider checking protected services
+        if self.config.security.forward_auth.has_sidecar() {
+            self.generate_forward_auth_service(&mut output);
+        }
+
+        // Generate CrowdSec agent and bouncers if enabled
+        if self.config.security.crowdsec.enabled {
+            self.generate_crowdsec_services(&mut output)?;
+        }
+
+        // Generate the server Traefik fetches the error pages from
+        if ErrorPagesGenerator::new(self.config).needs_server() {
+            self.generate_error_pages_service(&mut output);
+        }
+
+        // Generate Loki and the shipper of the proxies' access logs
+        if let Some(logs) = ObservabilityGenerator::new(self.config).log_shipping() {
+            self.generate_log_services(&mut output, logs);
+        }
+
+        // Generate the OpenTelemetry Collector exporting the proxies' spans
+        if let Some(tracing) = ObservabilityGenerator::new(self.config).tracing() {
+            self.generate_otel_collector_service(&mut output, tracing);
+        }
+
+        // Generate the vault-agent sidecar rendering vault:// references
+        if self.vault_agent && self.config.uses_vault() && !self.swarm() {
+            self.generate_vault_agent_service(&mut output);
+        }
+
+        // Generate backend services
+        for service in &self.config.services {
+            if self.includes_service(service) {
+                self.generate_backend_service(&mut output, service)?;
+            }
+        }
+
+        // Generate networks section
+        self.generate_networks(&mut output)?;
+
+        // Generate volumes section
+        self.generate_volumes(&mut output)?;
+
+        // Generate secrets section
+        self.generate_secrets(&mut output);
+
+        Ok(output)
+    }
+
+    /// Generate the vault-agent sidecar
+    ///
+    /// The agent writes into `./secrets`, where the compose secrets and env
+    /// files are read from, so it has to be started before the services
+    /// that use them.
+    fn generate_vault_agent_service(&self, output: &mut String) {
+        let vault = &self.config.vault;
+
+        writeln!(output).unwrap();
+        writeln!(
+            output,
+            "  # Vault agent (start first: docker compose up -d vault-agent)"
+        )
+        .unwrap();
+        writeln!(output, "  vault-agent:").unwrap();
+        writeln!(output, "    image: {}", vault.agent_image).unwrap();
+        self.write_container_name(output, "vault-agent");
+        self.write_restart(output, "unless-stopped");
+        writeln!(
+            output,
+            "    command: [\"agent\", \"-config=/vault/config/agent.hcl\"]"
+        )
+        .unwrap();
+        writeln!(output, "    environment:").unwrap();
+        writeln!(output, "      - VAULT_ADDR=${{VAULT_ADDR}}").unwrap();
+        writeln!(output, "      - SKIP_SETCAP=true").unwrap();
+        self.write_timezone_env(output);
+        writeln!(output, "    volumes:").unwrap();
+        writeln!(
+            output,
+            "      - ./vault-agent/agent.hcl:/vault/config/agent.hcl:ro"
+        )
+        .unwrap();
+        writeln!(
+            output,
+            "      - {}:/vault/token:ro",
+            paths::host_path(&vault.token_file)
+        )
+        .unwrap();
+        writeln!(output, "      - ./{}:/vault/secrets:rw", paths::SECRETS_DIR).unwrap();
+        self.write_localtime_volume(output);
+        // No networks: the default network reaches Vault outside the stack
+        writeln!(output, "    labels:").unwrap();
+        writeln!(output, "      - \"cerberus.service=vault-agent\"").unwrap();
+    }
+
+    /// Generate a proxy service definition
+    fn generate_proxy_service(
+        &self,
+        output: &mut String,
+        proxy: &ProxyConfig,
+        index: usize,
+    ) -> Result<()> {
+        writeln!(output).unwrap();
+        writeln!(
+            output,
+            "  # Proxy Layer: {} ({})",
+            proxy.name,
+            proxy.proxy_type.to_string()
+        )
+        .unwrap();
+        writeln!(output, "  {}:", proxy.name).unwrap();
+        self.generate_proxy_image(output, proxy);
+        // Replicated proxies cannot share a fixed container name
+        let replicas = proxy.deploy_replicas().unwrap_or(1);
+        if replicas == 1 {
+            self.write_container_name(output, &proxy.name);
+        }
+        // Swarm's routing mesh publishes one port for every replica
+        let port_ranges = replicas > 1 && !self.swarm();
+        self.write_restart(output, "unless-stopped");
+        self.write_proxy_hardening(output, proxy);
+        self.generate_proxy_command(output, proxy);
+
+        // ポート設定（external_portがある場合のみ）
+        let published = self
+            .published_port(proxy, index)
+            .and_then(|port| self.host_port(proxy, port, 0))
+            .map(|host| host.mapping(proxy.internal_port));
+        let stats = self.config.published_stats(proxy).and_then(|stats| {
+            self.host_port(proxy, stats.port, 0)
+                .map(|host| host.mapping(stats.port))
+        });
+        let streams = self.config.streams(proxy);
+        let acme = self.config.acme(proxy);
+        if published.is_some() || stats.is_some() || !streams.is_empty() || acme.is_some() {
+            writeln!(output, "    ports:").unwrap();
+        }
+        if let Some(acme) = acme {
+            writeln!(
+                output,
+                "      - \"{}:{}\"",
+                acme.https_port, acme.listen_port
+            )
+            .unwrap();
+        }
+        for mapping in published.iter().chain(&stats) {
+            writeln!(output, "      - \"{mapping}\"").unwrap();
+        }
+        // TCP and UDP streams keep their port on the host; tcp streams
+        // routed by SNI share theirs
+        let mut stream_ports = Vec::new();
+        for stream in &streams {
+            if stream_ports.contains(&(stream.listen_port, stream.protocol)) {
+                continue;
+            }
+            stream_ports.push((stream.listen_port, stream.protocol));
+            let protocol = stream.protocol.as_str();
+            if port_ranges {
+                writeln!(
+                    output,
+                    "      - \"{}-{}:{}/{protocol}\"",
+                    stream.listen_port,
+                    stream.listen_port as u32 + replicas - 1,
+                    stream.listen_port
+                )
+                .unwrap();
+            } else {
+                writeln!(output, "      - \"{0}:{0}/{protocol}\"", stream.listen_port).unwrap();
+            }
+        }
+        self.generate_proxy_expose(output, proxy);
+        writeln!(output, "    volumes:").unwrap();
+        match proxy.proxy_type {
+            ProxyType::Nginx => {
+                writeln!(
+                    output,
+                    "      - ./proxy-configs/{}/conf.d:/etc/nginx/conf.d",
+                    proxy.name
+                )
+                .unwrap();
+                // stream {} blocks and worker tuning need the main configuration
+                if self.config.generates_nginx_main(proxy) {
+                    writeln!(
+                        output,
+                        "      - ./proxy-configs/{}/nginx.conf:/etc/nginx/nginx.conf:ro",
+                        proxy.name
+                    )
+                    .unwrap();
+                }
+            }
+            _ => {
+                writeln!(
+                    output,
+                    "      - ./proxy-configs/{}:{}:ro",
+                    proxy.name,
+                    self.get_proxy_config_dir(&proxy.proxy_type)
+                )
+                .unwrap();
+            }
+        }
+        writeln!(
+            output,
+            "      - ./built/logs:{}:rw",
+            proxy.proxy_type.log_dir()
+        )
+        .unwrap();
+        if proxy.uses_drain_entrypoint() {
+            writeln!(
+                output,
+                "      - ./proxy-configs/{}/entrypoint.sh:{ENTRYPOINT_PATH}:ro",
+                proxy.name
+            )
+            .unwrap();
+        }
+        if proxy.enabled_stats().is_some() {
+            writeln!(output, "      - {}-admin-socket:/run/haproxy", proxy.name).unwrap();
+        }
+        if self.config.waf.applies_to(proxy) && self.config.waf.audit_log {
+            writeln!(output, "      - waf_audit:/var/log/waf:rw").unwrap();
+        }
+        if proxy.proxy_type == ProxyType::Nginx && self.config.edge_caches(proxy) {
+            writeln!(output, "      - {}-cache:/var/cache/nginx", proxy.name).unwrap();
+        }
+        if self.config.error_pages.is_enabled() {
+            writeln!(
+                output,
+                "      - ./{ERROR_PAGES_DIR}:{CONTAINER_ERROR_PAGES_DIR}:ro"
+            )
+            .unwrap();
+        }
+        if self.config.serves_content(proxy) {
+            writeln!(output, "      - ./{CONTENT_DIR}:{CONTAINER_CONTENT_DIR}:ro").unwrap();
+        }
+        if acme.is_some() {
+            writeln!(output, "      - {}-acme:/data", proxy.name).unwrap();
+        }
+        self.write_localtime_volume(output);
+        self.write_networks(output, &self.networks.proxy_networks(proxy));
+        self.write_secrets(output, &self.proxy_secrets(proxy));
+
+        // Add dependencies if needed
+        self.generate_proxy_dependencies(output, proxy)?;
+
+        // Add environment variables
+        writeln!(output, "    environment:").unwrap();
+        writeln!(output, "      - PROXY_LAYER={}", proxy.layer.unwrap_or(0)).unwrap();
+        writeln!(
+            output,
+            "      - UPSTREAM={}",
+            proxy.default_upstream.as_deref().unwrap_or("")
+        )
+        .unwrap();
+        writeln!(
+            output,
+            "      - MAX_CONNECTIONS={}",
+            proxy.max_connections.unwrap_or(1024)
+        )
+        .unwrap();
+        for (key, value) in proxy.image_environment() {
+            writeln!(output, "      - {}", yaml_string(&format!("{key}={value}"))).unwrap();
+        }
+        self.write_tracing_env(output, proxy);
+        // Traefik's DNS providers read each credential from the file in VAR_FILE
+        if let Some(acme) = acme.filter(|_| proxy.proxy_type == ProxyType::Traefik) {
+            for (credential, secret) in acme.secret_credentials() {
+                writeln!(
+                    output,
+                    "      - {}_FILE={}/{secret}",
+                    credential.env,
+                    paths::CONTAINER_SECRETS_DIR
+                )
+                .unwrap();
+            }
+        }
+        self.write_timezone_env(output);
+
+        // Add labels
+        writeln!(output, "    labels:").unwrap();
+        writeln!(output, "      - \"cerberus.service=proxy\"").unwrap();
+        writeln!(
+            output,
+            "      - \"cerberus.layer={}\"",
+            proxy.layer.unwrap_or(0)
+        )
+        .unwrap();
+        writeln!(
+            output,
+            "      - \"cerberus.type={}\"",
+            proxy.proxy_type.to_string()
+        )
+        .unwrap();
+
+        let resources = proxy.resources();
+        if replicas > 1 || resources.is_some() {
+            writeln!(output, "    deploy:").unwrap();
+            if replicas > 1 {
+                writeln!(output, "      replicas: {replicas}").unwrap();
+            }
+            if let Some(resources) = resources {
+                self.write_resources(output, resources);
+            }
+        }
+
+        self.write_proxy_healthcheck(output, proxy);
+
+        Ok(())
+    }
+
+    /// Generate scaled proxy instance
+    fn generate_scaled_proxy_instance(
+        &self,
+        output: &mut String,
+        proxy: &ProxyConfig,
+        _index: usize,
+        instance: u8,
+    ) -> Result<()> {
+        writeln!(output).unwrap();
+        writeln!(output, "  # Scaled instance {} of {}", instance, proxy.name).unwrap();
+        writeln!(output, "  {}-{}:", proxy.name, instance).unwrap();
+        self.generate_proxy_image(output, proxy);
+        self.write_container_name(output, &format!("{}-{}", proxy.name, instance));
+        self.write_restart(output, "unless-stopped");
+        self.write_proxy_hardening(output, proxy);
+        self.generate_proxy_command(output, proxy);
+
+        // ポート設定（external_portがある場合のみ）
+        let published = proxy
+            .external_port
+            .and_then(|port| self.host_port(proxy, port, instance - 1))
+            .map(|host| host.mapping(proxy.internal_port));
+        let stats = self.config.published_stats(proxy).and_then(|stats| {
+            self.host_port(proxy, stats.port, instance - 1)
+                .map(|host| host.mapping(stats.port))
+        });
+        if published.is_some() || stats.is_some() {
+            writeln!(output, "    ports:").unwrap();
+        }
+        for mapping in published.iter().chain(&stats) {
+            writeln!(output, "      - \"{mapping}\"").unwrap();
+        }
+        self.generate_proxy_expose(output, proxy);
+        writeln!(output, "    volumes:").unwrap();
+        match proxy.proxy_type {
+            ProxyType::Nginx => {
+                writeln!(
+                    output,
+                    "      - ./proxy-configs/{}/conf.d:/etc/nginx/conf.d",
+                    proxy.name
+                )
+                .unwrap();
+                // stream {} blocks and worker tuning need the main configuration
+                if self.config.generates_nginx_main(proxy) {
+                    writeln!(
+                        output,
+                        "      - ./proxy-configs/{}/nginx.conf:/etc/nginx/nginx.conf:ro",
+                        proxy.name
+                    )
+                    .unwrap();
+                }
+            }
+            _ => {
+                writeln!(
+                    output,
+                    "      - ./proxy-configs/{}:{}:ro",
+                    proxy.name,
+                    self.get_proxy_config_dir(&proxy.proxy_type)
+                )
+                .unwrap();
+            }
+        }
+        writeln!(
+            output,
+            "      - ./built/logs:{}:rw",
+            proxy.proxy_type.log_dir()
+        )
+        .unwrap();
+        if proxy.uses_drain_entrypoint() {
+            writeln!(
+                output,
+                "      - ./proxy-configs/{}/entrypoint.sh:{ENTRYPOINT_PATH}:ro",
+                proxy.name
+            )
+            .unwrap();
+        }
+        if proxy.enabled_stats().is_some() {
+            writeln!(output, "      - {}-admin-socket:/run/haproxy", proxy.name).unwrap();
+        }
+        if self.config.waf.applies_to(proxy) && self.config.waf.audit_log {
+            writeln!(output, "      - waf_audit:/var/log/waf:rw").unwrap();
+        }
+        if proxy.proxy_type == ProxyType::Nginx && self.config.edge_caches(proxy) {
+            writeln!(output, "      - {}-cache:/var/cache/nginx", proxy.name).unwrap();
+        }
+        if self.config.error_pages.is_enabled() {
+            writeln!(
+                output,
+                "      - ./{ERROR_PAGES_DIR}:{CONTAINER_ERROR_PAGES_DIR}:ro"
+            )
+            .unwrap();
+        }
+        if self.config.serves_content(proxy) {
+            writeln!(output, "      - ./{CONTENT_DIR}:{CONTAINER_CONTENT_DIR}:ro").unwrap();
+        }
+        self.write_localtime_volume(output);
+        self.write_networks(output, &self.networks.proxy_networks(proxy));
+        self.write_secrets(output, &self.proxy_secrets(proxy));
+        self.generate_proxy_dependencies(output, proxy)?;
+        writeln!(output, "    environment:").unwrap();
+        writeln!(output, "      - PROXY_LAYER={}", proxy.layer.unwrap_or(0)).unwrap();
+        writeln!(output, "      - INSTANCE_ID={instance}").unwrap();
+        writeln!(
+            output,
+            "      - MAX_CONNECTIONS={}",
+            proxy.max_connections.unwrap_or(1024)
+        )
+        .unwrap();
+        for (key, value) in proxy.image_environment() {
+            writeln!(output, "      - {}", yaml_string(&format!("{key}={value}"))).unwrap();
+        }
+        self.write_tracing_env(output, proxy);
+        self.write_timezone_env(output);
+        writeln!(output, "    labels:").unwrap();
+        writeln!(output, "      - \"cerberus.service=proxy\"").unwrap();
+        writeln!(
+            output,
+            "      - \"cerberus.layer={}\"",
+            proxy.layer.unwrap_or(0)
+        )
+        .unwrap();
+        writeln!(
+            output,
+            "      - \"cerberus.type={}\"",
+            proxy.proxy_type.to_string()
+        )
+        .unwrap();
+        writeln!(output, "      - \"cerberus.instance={instance}\"").unwrap();
+        if let Some(resources) = proxy.resources() {
+            writeln!(output, "    deploy:").unwrap();
+            self.write_resources(output, resources);
+        }
+        self.write_proxy_healthcheck(output, proxy);
+
+        Ok(())
+    }
+
+    /// Generate the load balancer publishing the port of internal-only instances
+    ///
+    /// HAProxy balances the connections over the containers and adds
+    /// `X-Forwarded-For`; its configuration is written to
+    /// `proxy-configs/<balancer>/haproxy.cfg`.
+    fn generate_instance_balancer(
+        &self,
+        output: &mut String,
+        proxy: &ProxyConfig,
+        index: usize,
+        balancer: &str,
+    ) {
+        writeln!(output).unwrap();
+        writeln!(
+            output,
+            "  # Load balancer for the instances of {}",
+            proxy.name
+        )
+        .unwrap();
+        writeln!(output, "  {balancer}:").unwrap();
+        writeln!(
+            output,
+            "    image: {}",
+            self.get_proxy_image(&ProxyType::HaProxy)
+        )
+        .unwrap();
+        self.write_container_name(output, balancer);
+        self.write_restart(output, "unless-stopped");
+        if let Some(port) = self.published_port(proxy, index) {
+            writeln!(output, "    ports:").unwrap();
+            writeln!(output, "      - \"{port}:{}\"", proxy.internal_port).unwrap();
+        }
+        writeln!(output, "    volumes:").unwrap();
+        writeln!(
+            output,
+            "      - ./proxy-configs/{balancer}/haproxy.cfg:{}/haproxy.cfg:ro",
+            self.get_proxy_config_dir(&ProxyType::HaProxy)
+        )
+        .unwrap();
+        self.write_localtime_volume(output);
+        self.write_networks(output, &self.networks.proxy_networks(proxy));
+        let mut dependencies = Vec::new();
+        for name in self.proxy_services(proxy) {
+            push_dependency(&mut dependencies, name, true);
+        }
+        self.write_depends_on(output, &dependencies);
+        self.write_timezone_environment(output);
+        writeln!(output, "    labels:").unwrap();
+        writeln!(output, "      - \"cerberus.service=instance-balancer\"").unwrap();
+        writeln!(
+            output,
+            "      - \"cerberus.layer={}\"",
+            proxy.layer.unwrap_or(0)
+        )
+        .unwrap();
+        let port = proxy.internal_port.to_string();
+        self.write_healthcheck(output, &["CMD", "nc", "-z", "127.0.0.1", &port]);
+    }
+
+    /// Generate proxy dependencies section
+    ///
+    /// A proxy starts once the next hop of the chain is healthy: Anubis for
+    /// the layer-1 proxies protected by it, the next proxy layer otherwise,
+    /// and the backend containers for the proxies routing services.
+    fn generate_proxy_dependencies(&self, output: &mut String, proxy: &ProxyConfig) -> Result<()> {
+        let mut dependencies = Vec::new();
+
+        // Layer-1 proxies passing requests to Anubis start after it
+        if self.config.sends_to_anubis(proxy) {
+            for container in self.config.anubis.containers() {
+                push_dependency(&mut dependencies, container.name, true);
+            }
+        } else {
+            for name in self.next_layer_services(proxy.layer.unwrap_or(1)) {
+                push_dependency(&mut dependencies, name, true);
+            }
+        }
+
+        if proxy.renders_services() {
+            for (name, healthy) in self.backend_services() {
+                push_dependency(&mut dependencies, name, healthy);
+            }
+        }
+
+        // Proxies routing services query the forward auth provider
+        let forward_auth = &self.config.security.forward_auth;
+        if let Some(provider) = forward_auth.provider
+            && forward_auth.has_sidecar()
+            && proxy.renders_services()
+            && self.config.services.iter().any(|service| {
+                service
+                    .auth
+                    .as_ref()
+                    .is_some_and(|auth| auth.auth_type == AuthType::Forward)
+            })
+        {
+            push_dependency(&mut dependencies, provider.as_str(), false);
+        }
+
+        // Edge proxies query their CrowdSec bouncer
+        match CrowdSecGenerator::new(self.config).bouncer(proxy) {
+            Some(Bouncer::ForwardAuth) => {
+                push_dependency(&mut dependencies, "crowdsec-bouncer", false)
+            }
+            Some(Bouncer::Spoa) => push_dependency(&mut dependencies, "crowdsec-spoa", false),
+            None => {}
+        }
+
+        // Spans are exported from the first request on
+        if self.config.tracing(proxy).is_some()
+            && ObservabilityGenerator::new(self.config).tracing().is_some()
+        {
+            push_dependency(&mut dependencies, "otel-collector", false);
+        }
+
+        self.write_depends_on(output, &dependencies);
+
+        Ok(())
+    }
+
+    /// Compose services of the proxies in the layer after `layer`
+    fn next_layer_services(&self, layer: u8) -> Vec<String> {
+        let next = layer.checked_add(1);
+        self.config
+            .proxies
+            .iter()
+            .filter(|proxy| proxy.layer.or(Some(1)) == next && self.includes_proxy(proxy))
+            .flat_map(|proxy| self.proxy_services(proxy))
+            .collect()
+    }
+
+    /// Backend containers, with whether they check their health
+    fn backend_services(&self) -> Vec<(&str, bool)> {
+        self.config
+            .services
+            .iter()
+            .filter(|service| self.includes_service(service))
+            .map(|service| (service.name.as_str(), service.has_healthcheck()))
+            .collect()
+    }
+
+    /// Write the `depends_on` section of a container
+    ///
+    /// Swarm ignores the startup order and its stack files only take the
+    /// names, so the conditions are left out there.
+    fn write_depends_on(
+        &self,
+        output: &mut String,
+        dependencies: &[(String, DependencyCondition)],
+    ) {
+        if dependencies.is_empty() {
+            return;
+        }
+        writeln!(output, "    depends_on:").unwrap();
+        for (name, condition) in dependencies {
+            if self.swarm() {
+                writeln!(output, "      - {name}").unwrap();
+                continue;
+            }
+            // Long form keeps the startup condition and restart flag
+            writeln!(output, "      {name}:").unwrap();
+            writeln!(output, "        condition: {}", condition.condition).unwrap();
+            if let Some(restart) = condition.restart {
+                writeln!(output, "        restart: {restart}").unwrap();
+            }
+        }
+    }
+
+    /// Write the healthcheck dependent containers wait for
+    fn write_healthcheck(&self, output: &mut String, test: &[&str]) {
+        let test: Vec<String> = test.iter().map(|part| format!("\"{part}\"")).collect();
+        writeln!(output, "    healthcheck:").unwrap();
+        writeln!(output, "      test: [{}]", test.join(", ")).unwrap();
+        writeln!(output, "      interval: 10s").unwrap();
+        writeln!(output, "      timeout: 5s").unwrap();
+        writeln!(output, "      retries: 3").unwrap();
+        writeln!(output, "      start_period: 10s").unwrap();
+    }
+
+    /// Write the healthcheck of a proxy container
+    ///
+    /// Traefik answers its ping endpoint and Varnish its management port;
+    /// the other proxies are ready once they listen on their port.
+    fn write_proxy_healthcheck(&self, output: &mut String, proxy: &ProxyConfig) {
+        let port = proxy.internal_port.to_string();
+        match proxy.proxy_type {
+            ProxyType::Traefik => {
+                self.write_healthcheck(output, &["CMD", "traefik", "healthcheck", "--ping"])
+            }
+            ProxyType::Varnish => self.write_healthcheck(output, &["CMD", "varnishadm", "ping"]),
+            _ => self.write_healthcheck(output, &["CMD", "nc", "-z", "127.0.0.1", &port]),
+        }
+    }
+
+    /// Generate the forward auth provider sidecar
+    ///
+    /// oauth2-proxy is set up to trust the proxies' `X-Forwarded-*` headers
+    /// and to answer checks on `/` with 202 once the user is signed in;
+    /// `environment` adds the identity provider settings.
+    fn generate_forward_auth_service(&self, output: &mut String) {
+        let forward_auth = &self.config.security.forward_auth;
+        let (Some(provider), Some(image)) = (forward_auth.provider, forward_auth.image()) else {
+            return;
+        };
+        let name = provider.as_str();
+
+        writeln!(output).unwrap();
+        writeln!(output, "  # Forward auth provider ({name})").unwrap();
+        writeln!(output, "  {name}:").unwrap();
+        writeln!(output, "    image: {image}").unwrap();
+        self.write_container_name(output, name);
+        self.write_restart(output, "unless-stopped");
+
+        let mut environment: BTreeMap<&str, &str> = BTreeMap::new();
+        if provider == AuthProvider::Oauth2Proxy {
+            environment.insert("OAUTH2_PROXY_HTTP_ADDRESS", "0.0.0.0:4180");
+            environment.insert("OAUTH2_PROXY_REVERSE_PROXY", "true");
+            environment.insert("OAUTH2_PROXY_SET_XAUTHREQUEST", "true");
+            environment.insert("OAUTH2_PROXY_UPSTREAMS", "static://202");
+        }
+        for (key, value) in &forward_auth.environment {
+            environment.insert(key, value);
+        }
+        if environment.is_empty() {
+            self.write_timezone_environment(output);
+        } else {
+            writeln!(output, "    environment:").unwrap();
+            for (key, value) in environment {
+                writeln!(output, "      - {}", yaml_string(&format!("{key}={value}"))).unwrap();
+            }
+            self.write_timezone_env(output);
+        }
+
+        if forward_auth.config_dir.is_some() || self.config.project.timezone.is_some() {
+            writeln!(output, "    volumes:").unwrap();
+            if let Some(dir) = &forward_auth.config_dir {
+                writeln!(output, "      - {}:/config", paths::host_path(dir)).unwrap();
+            }
+            self.write_localtime_volume(output);
+        }
+        self.write_networks(output, &self.networks.backend_networks());
+        let secrets: Vec<ServiceSecretRef> = forward_auth
+            .secrets
+            .iter()
+            .cloned()
+            .map(ServiceSecretRef::Simple)
+            .collect();
+        self.write_secrets(output, &secrets);
+        writeln!(output, "    labels:").unwrap();
+        writeln!(output, "      - \"cerberus.service={name}\"").unwrap();
+    }
+
+    /// Generate the static server of the error pages for Traefik's `errors` middleware
+    fn generate_error_pages_service(&self, output: &mut String) {
+        let name = error_pages::SERVICE_NAME;
+        writeln!(output).unwrap();
+        writeln!(output, "  # Custom error pages for Traefik").unwrap();
+        writeln!(output, "  {name}:").unwrap();
+        writeln!(output, "    image: {}", error_pages::SERVICE_IMAGE).unwrap();
+        self.write_container_name(output, name);
+        self.write_restart(output, "unless-stopped");
+        self.write_hardening(output, None, &["/tmp"], false, &[]);
+        writeln!(output, "    volumes:").unwrap();
+        writeln!(
+            output,
+            "      - ./{ERROR_PAGES_DIR}:{}:ro",
+            error_pages::SERVICE_ROOT
+        )
+        .unwrap();
+        let mut networks = Vec::new();
+        for proxy in &self.config.proxies {
+            if proxy.proxy_type == ProxyType::Traefik && self.includes_proxy(proxy) {
+                for network in self.networks.proxy_networks(proxy) {
+                    if !networks.contains(&network) {
+                        networks.push(network);
+                    }
+                }
+            }
+        }
+        self.write_networks(output, &networks);
+        writeln!(output, "    labels:").unwrap();
+        writeln!(output, "      - \"cerberus.service={name}\"").unwrap();
+    }
+
+    /// Secrets mounted into a proxy
+    ///
+    /// Proxies routing services also read the htpasswd secrets of
+    /// `[services.auth]`.
+    fn proxy_secrets(&self, proxy: &ProxyConfig) -> Vec<ServiceSecretRef> {
+        let mut secrets = proxy.secrets.clone();
+        let acme_secrets = self
+            .config
+            .acme(proxy)
+            .map(|acme| acme.credentials.values().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let auth_secrets = self
+            .config
+            .services
+            .iter()
+            .filter(|_| proxy.renders_services())
+            .filter_map(|service| service.auth.as_ref()?.users_secret.as_ref());
+        for name in acme_secrets.into_iter().chain(auth_secrets) {
+            let mounted = secrets.iter().any(|secret| match secret {
+                ServiceSecretRef::Simple(source) | ServiceSecretRef::Detailed { source, .. } => {
+                    source == name
+                }
+            });
+            if !mounted {
+                secrets.push(ServiceSecretRef::Simple(name.clone()));
+            }
+        }
+        secrets
+    }
+
+    /// Generate the CrowdSec agent and the bouncers used by edge proxies
+    fn generate_crowdsec_services(&self, output: &mut String) -> Result<()> {
+        let crowdsec = &self.config.security.crowdsec;
+        let generator = CrowdSecGenerator::new(self.config);
+        let api_key = format!("${{{}}}", crowdsec.api_key_env);
+
+        writeln!(output).unwrap();
+        writeln!(output, "  # CrowdSec agent (parses the shared proxy logs)").unwrap();
+        writeln!(output, "  crowdsec:").unwrap();
+        writeln!(output, "    image: {}", crowdsec.image).unwrap();
+        self.write_container_name(output, "crowdsec");
+        self.write_restart(output, "unless-stopped");
+        writeln!(output, "    environment:").unwrap();
+        writeln!(
+            output,
+            "      - COLLECTIONS={}",
+            crowdsec.collections_for(&self.config.proxies).join(" ")
+        )
+        .unwrap();
+        writeln!(output, "      - BOUNCER_KEY_cerberus={api_key}").unwrap();
+        self.write_timezone_env(output);
+        writeln!(output, "    volumes:").unwrap();
+        writeln!(
+            output,
+            "      - ./crowdsec/acquis.yaml:/etc/crowdsec/acquis.yaml:ro"
+        )
+        .unwrap();
+        writeln!(output, "      - crowdsec_data:/var/lib/crowdsec/data").unwrap();
+        writeln!(output, "      - crowdsec_config:/etc/crowdsec").unwrap();
+        writeln!(output, "      - ./built/logs:{}:ro", crowdsec::LOG_DIR).unwrap();
+        if generator.reads_docker_logs() {
+            writeln!(
+                output,
+                "      - /var/run/docker.sock:/var/run/docker.sock:ro"
+            )
+            .unwrap();
+        }
+        self.write_localtime_volume(output);
+        self.write_networks(output, &self.networks.backend_networks());
+        writeln!(output, "    labels:").unwrap();
+        writeln!(output, "      - \"cerberus.service=crowdsec\"").unwrap();
+        self.write_healthcheck(output, &["CMD", "cscli", "lapi", "status"]);
+        let agent = [dependency("crowdsec", true)];
+
+        if generator.uses_bouncer(Bouncer::ForwardAuth) {
+            writeln!(output).unwrap();
+            writeln!(output, "  # CrowdSec forward-auth bouncer (Nginx, Traefik)").unwrap();
+            writeln!(output, "  crowdsec-bouncer:").unwrap();
+            writeln!(output, "    image: {}", crowdsec.bouncer_image).unwrap();
+            self.write_container_name(output, "crowdsec-bouncer");
+            self.write_restart(output, "unless-stopped");
+            writeln!(output, "    environment:").unwrap();
+            writeln!(output, "      - CROWDSEC_BOUNCER_API_KEY={api_key}").unwrap();
+            writeln!(output, "      - CROWDSEC_AGENT_HOST=crowdsec:8080").unwrap();
+            self.write_timezone_env(output);
+            if self.config.project.timezone.is_some() {
+                writeln!(output, "    volumes:").unwrap();
+                self.write_localtime_volume(output);
+            }
+            self.write_depends_on(output, &agent);
+            self.write_networks(output, &self.networks.backend_networks());
+            writeln!(output, "    labels:").unwrap();
+            writeln!(output, "      - \"cerberus.service=crowdsec-bouncer\"").unwrap();
+        }
+
+        if generator.uses_bouncer(Bouncer::Spoa) {
+            writeln!(output).unwrap();
+            writeln!(output, "  # CrowdSec SPOA bouncer (HAProxy)").unwrap();
+            writeln!(output, "  crowdsec-spoa:").unwrap();
+            writeln!(output, "    image: {}", crowdsec.spoa_image).unwrap();
+            self.write_container_name(output, "crowdsec-spoa");
+            self.write_restart(output, "unless-stopped");
+            writeln!(output, "    environment:").unwrap();
+            writeln!(output, "      - {}={api_key}", crowdsec.api_key_env).unwrap();
+            self.write_timezone_env(output);
+            writeln!(output, "    volumes:").unwrap();
+            writeln!(
+                output,
+                "      - ./crowdsec/spoa-bouncer.yaml:/etc/crowdsec/bouncers/crowdsec-spoa-bouncer.yaml:ro"
+            )
+            .unwrap();
+            self.write_localtime_volume(output);
+            self.write_depends_on(output, &agent);
+            self.write_networks(output, &self.networks.backend_networks());
+            writeln!(output, "    labels:").unwrap();
+            writeln!(output, "      - \"cerberus.service=crowdsec-bouncer\"").unwrap();
+        }
+
+        Ok(())
+    }
+
+    /// Generate the bundled Loki and the log shipper reading the shared proxy logs
+    fn generate_log_services(&self, output: &mut String, logs: &LogShippingConfig) {
+        let shipper = logs.shipper.as_str();
+
+        if logs.bundles_loki() {
+            writeln!(output).unwrap();
+            writeln!(output, "  # Loki (stores the shipped access logs)").unwrap();
+            writeln!(output, "  loki:").unwrap();
+            writeln!(output, "    image: {}", logs.loki_image).unwrap();
+            self.write_container_name(output, "loki");
+            self.write_restart(output, "unless-stopped");
+            writeln!(output, "    command: -config.file=/etc/loki/loki.yaml").unwrap();
+            if let Some(port) = logs.loki_port {
+                writeln!(output, "    ports:").unwrap();
+                writeln!(output, "      - \"{port}:3100\"").unwrap();
+            }
+            self.write_timezone_environment(output);
+            writeln!(output, "    volumes:").unwrap();
+            writeln!(
+                output,
+                "      - ./observability/loki.yaml:/etc/loki/loki.yaml:ro"
+            )
+            .unwrap();
+            writeln!(output, "      - loki_data:/loki").unwrap();
+            self.write_localtime_volume(output);
+            self.write_networks(output, &self.networks.backend_networks());
+            writeln!(output, "    labels:").unwrap();
+            writeln!(output, "      - \"cerberus.service=loki\"").unwrap();
+        }
+
+        writeln!(output).unwrap();
+        writeln!(output, "  # Log shipper (reads the shared proxy logs)").unwrap();
+        writeln!(output, "  {shipper}:").unwrap();
+        writeln!(output, "    image: {}", logs.shipper_image()).unwrap();
+        self.write_container_name(output, shipper);
+        self.write_restart(output, "unless-stopped");
+        let (config_path, data_path) = match logs.shipper {
+            LogShipper::Vector => ("/etc/vector/vector.yaml", "/var/lib/vector"),
+            LogShipper::Promtail => ("/etc/promtail/promtail.yaml", "/var/lib/promtail"),
+        };
+        match logs.shipper {
+            LogShipper::Vector => writeln!(output, "    command: --config {config_path}"),
+            LogShipper::Promtail => writeln!(output, "    command: -config.file={config_path}"),
+        }
+        .unwrap();
+        self.write_timezone_environment(output);
+        writeln!(output, "    volumes:").unwrap();
+        writeln!(
+            output,
+            "      - ./observability/{shipper}.yaml:{config_path}:ro"
+        )
+        .unwrap();
+        writeln!(output, "      - {shipper}_data:{data_path}").unwrap();
+        writeln!(output, "      - ./built/logs:{}:ro", crowdsec::LOG_DIR).unwrap();
+        if ObservabilityGenerator::new(self.config).reads_docker_logs() {
+            writeln!(
+                output,
+                "      - /var/run/docker.sock:/var/run/docker.sock:ro"
+            )
+            .unwrap();
+        }
+        self.write_localtime_volume(output);
+        self.write_networks(output, &self.networks.backend_networks());
+        writeln!(output, "    labels:").unwrap();
+        writeln!(output, "      - \"cerberus.service=log-shipper\"").unwrap();
+        if logs.bundles_loki() {
+            self.write_depends_on(output, &[dependency("loki", false)]);
+        }
+    }
+
+    /// Generate the OpenTelemetry Collector receiving the proxies' spans
+    ///
+    /// It joins every proxy network, so each tracing layer reaches it.
+    fn generate_otel_collector_service(&self, output: &mut String, tracing: &TracingConfig) {
+        let mut networks: Vec<String> = Vec::new();
+        for proxy in &self.config.proxies {
+            if self.config.tracing(proxy).is_none() || !self.includes_proxy(proxy) {
+                continue;
+            }
+            for network in self.networks.proxy_networks(proxy) {
+                if !networks.contains(&network) {
+                    networks.push(network);
+                }
+            }
+        }
+
+        writeln!(output).unwrap();
+        writeln!(
+            output,
+            "  # OpenTelemetry Collector (exports the proxies' spans)"
+        )
+        .unwrap();
+        writeln!(output, "  otel-collector:").unwrap();
+        writeln!(output, "    image: {}", tracing.collector_image).unwrap();
+        self.write_container_name(output, "otel-collector");
+        self.write_restart(output, "unless-stopped");
+        writeln!(output, "    command: --config=/etc/otelcol/config.yaml").unwrap();
+        self.write_timezone_environment(output);
+        writeln!(output, "    volumes:").unwrap();
+        writeln!(
+            output,
+            "      - ./observability/otel-collector.yaml:/etc/otelcol/config.yaml:ro"
+        )
+        .unwrap();
+        self.write_localtime_volume(output);
+        if !networks.is_empty() {
+            self.write_networks(output, &networks);
+        }
+        writeln!(output, "    labels:").unwrap();
+        writeln!(output, "      - \"cerberus.service=otel-collector\"").unwrap();
+    }
+
+    /// Generate an Anubis DDoS protection service
+    fn generate_anubis_service(
+        &self,
+        output: &mut String,
+        container: &AnubisContainer,
+    ) -> Result<()> {
+        let name = &container.name;
+        let dir = container.config_dir();
+        writeln!(output).unwrap();
+        if container.domains.is_empty() {
+            writeln!(output, "  # DDoS Protection Layer").unwrap();
+        } else {
+            writeln!(
+                output,
+                "  # DDoS Protection Layer: {}",
+                container.domains.join(", ")
+            )
+            .unwrap();
+        }
+        writeln!(output, "  {name}:").unwrap();
+        writeln!(output, "    image: {}", self.config.anubis.image).unwrap();
+        self.write_container_name(output, name);
+        self.write_restart(output, &self.config.anubis.restart);
+        // The image already runs as an unprivileged user
+        self.write_hardening(output, None, &["/tmp"], false, &[]);
+        // Only the metrics port can be published; challenges stay behind the proxies.
+        // Instances would clash with the global Anubis on the host port.
+        if self.config.anubis.expose_metrics
+            && container.domains.is_empty()
+            && let Some(port) = container.metrics_port()
+        {
+            writeln!(output, "    ports:").unwrap();
+            writeln!(output, "      - \"{port}:{port}\"").unwrap();
+        }
+        writeln!(output, "    env_file:").unwrap();
+        writeln!(output, "      - ./{dir}/.env").unwrap();
+        self.write_timezone_environment(output);
+        writeln!(output, "    volumes:").unwrap();
+        writeln!(
+            output,
+            "      - ./{dir}/botPolicy.json:{}:ro",
+            self.config.anubis.policy_fname
+        )
+        .unwrap();
+        writeln!(output, "      - ./built/logs:/var/log/anubis:rw").unwrap();
+        for volume in &self.config.anubis.volumes {
+            writeln!(output, "      - {}", paths::volume(volume)).unwrap();
+        }
+        self.write_localtime_volume(output);
+        self.write_networks(output, &self.networks.anubis_networks());
+        writeln!(output, "    labels:").unwrap();
+        writeln!(output, "      - \"cerberus.service=ddos-protection\"").unwrap();
+        writeln!(output, "      - \"cerberus.layer=anubis\"").unwrap();
+        // The image has no shell; the binary probes its own metrics server
+        self.write_healthcheck(output, &["CMD", "anubis", "--healthcheck"]);
+
+        // Anubis passes the checked requests to the second proxy layer
+        let mut dependencies = Vec::new();
+        for name in self.next_layer_services(1) {
+            push_dependency(&mut dependencies, name, true);
+        }
+        self.write_depends_on(output, &dependencies);
+
+        Ok(())
+    }
+
+    /// Generate backend service definition
+    ///
+    /// Services with an `image` or `build` get a real container built from
+    /// their Docker options; others get a placeholder container.
+    fn generate_backend_service(
+        &self,
+        output: &mut String,
+        service: &crate::config::ServiceConfig,
+    ) -> Result<()> {
+        writeln!(output).unwrap();
+        writeln!(output, "  # Backend Service: {}", service.name).unwrap();
+        writeln!(output, "  {}:", service.name).unwrap();
+        if let Some(build) = &service.build {
+            writeln!(output, "    build:").unwrap();
+            writeln!(
+                output,
+                "      context: {}",
+                paths::host_path(&build.context)
+            )
+            .unwrap();
+            if let Some(dockerfile) = &build.dockerfile {
+                writeln!(output, "      dockerfile: {}", paths::host_path(dockerfile)).unwrap();
+            }
+            if let Some(target) = &build.target {
+                writeln!(output, "      target: {target}").unwrap();
+            }
+            if !build.args.is_empty() {
+                writeln!(output, "      args:").unwrap();
+                let args: BTreeMap<_, _> = build.args.iter().collect();
+                for (key, value) in args {
+                    writeln!(output, "        {key}: {}", yaml_string(value)).unwrap();
+                }
+            }
+        }
+        if let Some(image) = &service.image {
+            writeln!(output, "    image: {image}").unwrap();
+        } else if service.build.is_none() {
+            writeln!(output, "    image: alpine:latest").unwrap();
+        }
+        let options = service.container_options(&self.config.defaults);
+        self.write_image_options(output, &options);
+        // Replicated services cannot share a fixed container name
+        let replicas = service.replicas.unwrap_or(1);
+        if replicas == 1 {
+            self.write_container_name(output, &service.name);
+        }
+        self.write_restart(output, "unless-stopped");
+        if let Some(period) = options.stop_grace_period_secs {
+            writeln!(output, "    stop_grace_period: {period}s").unwrap();
+        }
+
+        if !service.ports.is_empty() {
+            writeln!(output, "    ports:").unwrap();
+            for port in &service.ports {
+                writeln!(output, "      - \"{port}\"").unwrap();
+            }
+        }
+
+        if service.has_container() {
+            if !service.volumes.is_empty() || self.config.project.timezone.is_some() {
+                writeln!(output, "    volumes:").unwrap();
+                for volume in &service.volumes {
+                    writeln!(output, "      - {}", paths::volume(volume)).unwrap();
+                }
+            }
+        } else {
+            writeln!(output, "    volumes:").unwrap();
+            writeln!(output, "      - ./{}/config:/app/config:ro", service.name).unwrap();
+            writeln!(output, "      - ./{}/data:/app/data:rw", service.name).unwrap();
+        }
+        self.write_localtime_volume(output);
+        self.write_networks(output, &self.networks.backend_networks());
+
+        let dependencies = match &service.depends_on {
+            // Plain names wait for the services checking their health
+            Some(DependsOn::Simple(names)) => {
+                let backends = self.backend_services();
+                let mut dependencies = Vec::new();
+                for name in names {
+                    let healthy = backends
+                        .iter()
+                        .any(|(backend, healthy)| backend == name && *healthy);
+                    push_dependency(&mut dependencies, name.as_str(), healthy);
+                }
+                dependencies
+            }
+            Some(DependsOn::Detailed(conditions)) => {
+                let conditions: BTreeMap<_, _> = conditions.iter().collect();
+                conditions
+                    .into_iter()
+                    .map(|(name, condition)| (name.clone(), condition.clone()))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        self.write_depends_on(output, &dependencies);
+
+        // vault:// values are read from the env file written next to the secrets
+        let environment: BTreeMap<_, _> = service
+            .environment
+            .iter()
+            .filter(|(_, value)| !value.starts_with(VAULT_SCHEME))
+            .collect();
+        // The service's own TZ wins over project.timezone
+        let timezone =
+            !service.environment.contains_key("TZ") && self.config.project.timezone.is_some();
+        // An empty mapping is null, which the stack file schema rejects
+        if !service.has_container() || !environment.is_empty() || timezone {
+            writeln!(output, "    environment:").unwrap();
+        }
+        if !service.has_container() {
+            writeln!(output, "      - SERVICE_NAME={}", service.name).unwrap();
+            writeln!(output, "      - DOMAIN={}", service.primary_domain()).unwrap();
+            writeln!(output, "      - UPSTREAM={}", service.upstream).unwrap();
+        }
+        for (key, value) in &environment {
+            writeln!(output, "      - {}", yaml_string(&format!("{key}={value}"))).unwrap();
+        }
+        if timezone {
+            self.write_timezone_env(output);
+        }
+        if service.environment.len() > environment.len() {
+            let env_file = paths::vault_env_file(&service.name);
+            writeln!(output, "    env_file:").unwrap();
+            if self.vault_agent {
+                // Rendered by vault-agent once it is running
+                writeln!(output, "      - path: ./{}", paths::posix(&env_file)).unwrap();
+                writeln!(output, "        required: false").unwrap();
+            } else {
+                writeln!(output, "      - ./{}", paths::posix(&env_file)).unwrap();
+            }
+        }
+        writeln!(output, "    labels:").unwrap();
+        writeln!(output, "      - \"cerberus.service=backend\"").unwrap();
+        writeln!(output, "      - \"cerberus.name={}\"", service.name).unwrap();
+        writeln!(
+            output,
+            "      - \"cerberus.domain={}\"",
+            service.primary_domain()
+        )
+        .unwrap();
+        if let Some(observability) = &service.observability {
+            for (name, value) in observability.labels() {
+                writeln!(output, "      - \"cerberus.{name}={value}\"").unwrap();
+            }
+        }
+
+        if let Some(healthcheck) = &service.healthcheck {
+            writeln!(output, "    healthcheck:").unwrap();
+            // A JSON array is a valid YAML flow sequence, with quotes escaped
+            let test = serde_json::to_string(&healthcheck.test).unwrap();
+            writeln!(output, "      test: {test}").unwrap();
+            writeln!(output, "      interval: {}", healthcheck.interval).unwrap();
+            writeln!(output, "      timeout: {}", healthcheck.timeout).unwrap();
+            writeln!(output, "      retries: {}", healthcheck.retries).unwrap();
+            if let Some(start_period) = &healthcheck.start_period {
+                writeln!(output, "      start_period: {start_period}").unwrap();
+            }
+            if let Some(start_interval) = &healthcheck.start_interval {
+                writeln!(output, "      start_interval: {start_interval}").unwrap();
+            }
+        } else if service.has_healthcheck() {
+            self.write_upstream_healthcheck(output, service);
+        }
+
+        if replicas > 1 {
+            writeln!(output, "    deploy:").unwrap();
+            writeln!(output, "      replicas: {replicas}").unwrap();
+        }
+
+        Ok(())
+    }
+
+    /// Write the healthcheck of a placeholder container from `[services.health]`
+    ///
+    /// Without the section the upstream's `/health` is requested.
+    fn write_upstream_healthcheck(&self, output: &mut String, service: &ServiceConfig) {
+        writeln!(output, "    healthcheck:").unwrap();
+        let Some(health) = &service.health else {
+            writeln!(
+                output,
+                "      test: [\"CMD\", \"curl\", \"-f\", \"{}/health\"]",
+                service.upstream
+            )
+            .unwrap();
+            writeln!(output, "      interval: 30s").unwrap();
+            writeln!(output, "      timeout: 10s").unwrap();
+            writeln!(output, "      retries: 3").unwrap();
+            writeln!(output, "      start_period: 60s").unwrap();
+            return;
+        };
+
+        let seconds = |value: &str| parse_duration_secs(value).unwrap_or(0);
+        if health.check_type == HealthCheckType::Tcp {
+            // BusyBox nc only opens the connection with -z
+            let (host, port) = upstream_address(&service.upstream);
+            writeln!(
+                output,
+                "      test: [\"CMD\", \"nc\", \"-z\", \"-w\", \"{}\", \"{host}\", \"{port}\"]",
+                seconds(&health.timeout)
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                output,
+                "      test: [\"CMD\", \"curl\", \"-f\", \"{}{}\"]",
+                service.upstream.trim_end_matches('/'),
+                health.path
+            )
+            .unwrap();
+        }
+        writeln!(output, "      interval: {}s", seconds(&health.interval)).unwrap();
+        writeln!(output, "      timeout: {}s", seconds(&health.timeout)).unwrap();
+        writeln!(output, "      retries: {}", health.unhealthy_threshold).unwrap();
+        writeln!(output, "      start_period: 60s").unwrap();
+    }
+
+    /// Generate networks section
+    fn generate_networks(&self, output: &mut String) -> Result<()> {
+        writeln!(output).unwrap();
+        writeln!(output, "networks:").unwrap();
+
+        // Generate networks from config
+        if !self.config.networks.is_empty() {
+            for (name, network) in &self.config.networks {
+                writeln!(output, "  {}:", name).unwrap();
+                writeln!(
+                    output,
+                    "    driver: {}",
+                    self.network_driver(&network.driver)
+                )
+                .unwrap();
+
+                if !network.external {
+                    writeln!(output, "    name: {}-{}", self.config.project.name, name).unwrap();
+                    if network.internal {
+                        writeln!(output, "    internal: true").unwrap();
+                    }
+                    if network.enable_ipv6 {
+                        writeln!(output, "    enable_ipv6: true").unwrap();
+                    }
+
+                    if let Some(ipam) = &network.ipam {
+                        writeln!(output, "    ipam:").unwrap();
+                        if let Some(driver) = &ipam.driver {
+                            writeln!(output, "      driver: {}", driver).unwrap();
+                        }
+                        if !ipam.config.is_empty() {
+                            writeln!(output, "      config:").unwrap();
+                            for config in &ipam.config {
+                                writeln!(output, "        - ").unwrap();
+                                if let Some(subnet) = &config.subnet {
+                                    writeln!(output, "          subnet: {}", subnet).unwrap();
+                                }
+                                if let Some(gateway) = &config.gateway {
+                                    writeln!(output, "          gateway: {}", gateway).unwrap();
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    writeln!(output, "    external: true").unwrap();
+                    if let Some(external_name) = &network.name {
+                        writeln!(output, "    name: {}", external_name).unwrap();
+                    }
+                }
+                writeln!(output).unwrap();
+            }
+        } else {
+            // Fallback to default networks, dual-stack with unique local subnets
+            let ipv6 = self.config.project.ipv6;
+            let defaults = [
+                ("front-net", "front", "10.100.0.0/16", "fd10:100::/64"),
+                ("back-net", "back", "10.101.0.0/16", "fd10:101::/64"),
+            ];
+            for (index, (key, suffix, subnet, subnet_v6)) in defaults.into_iter().enumerate() {
+                if index > 0 {
+                    writeln!(output).unwrap();
+                }
+                writeln!(output, "  {key}:").unwrap();
+                writeln!(output, "    driver: {}", self.network_driver("bridge")).unwrap();
+                writeln!(output, "    name: {}-{suffix}", self.config.project.name).unwrap();
+                if ipv6 {
+                    writeln!(output, "    enable_ipv6: true").unwrap();
+                }
+                writeln!(output, "    ipam:").unwrap();
+                writeln!(output, "      config:").unwrap();
+                writeln!(output, "        - subnet: {subnet}").unwrap();
+                if ipv6 {
+                    writeln!(output, "        - subnet: {subnet_v6}").unwrap();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Generate volumes section
+    fn generate_volumes(&self, output: &mut String) -> Result<()> {
+        writeln!(output).unwrap();
+        writeln!(output, "volumes:").unwrap();
+
+        // Generate volumes from config
+        if !self.config.volumes.is_empty() {
+            for (name, volume) in &self.config.volumes {
+                writeln!(output, "  {}:", name).unwrap();
+
+                if !volume.external {
+                    if let Some(driver) = &volume.driver {
+                        writeln!(output, "    driver: {}", driver).unwrap();
+                    }
+                    writeln!(output, "    name: {}-{}", self.config.project.name, name).unwrap();
+
+                    if !volume.driver_opts.is_empty() {
+                        writeln!(output, "    driver_opts:").unwrap();
+                        for (key, value) in &volume.driver_opts {
+                            writeln!(output, "      {}: {}", key, value).unwrap();
+                        }
+                    }
+                } else {
+                    writeln!(output, "    external: true").unwrap();
+                    if let Some(external_name) = &volume.name {
+                        writeln!(output, "    name: {}", external_name).unwrap();
+                    }
+                }
+
+                if !volume.labels.is_empty() {
+                    writeln!(output, "    labels:").unwrap();
+                    for (key, value) in &volume.labels {
+                        writeln!(output, "      {}: {}", key, value).unwrap();
+                    }
+                }
+                writeln!(output).unwrap();
+            }
+        } else {
+            // Fallback to default volumes
+            writeln!(output, "  # Persistent data volumes").unwrap();
+            writeln!(output, "  postgres_data:").unwrap();
+            writeln!(output, "    driver: local").unwrap();
+            writeln!(output, "    name: {}-postgres", self.config.project.name).unwrap();
+            writeln!(output).unwrap();
+            writeln!(output, "  redis_data:").unwrap();
+            writeln!(output, "    driver: local").unwrap();
+            writeln!(output, "    name: {}-redis", self.config.project.name).unwrap();
+            writeln!(output).unwrap();
+            writeln!(output, "  # Log volumes").unwrap();
+            writeln!(output, "  nginx_logs:").unwrap();
+            writeln!(output, "    driver: local").unwrap();
+            writeln!(output, "    name: {}-logs", self.config.project.name).unwrap();
+        }
+
+        if self.config.security.crowdsec.enabled {
+            writeln!(output).unwrap();
+            writeln!(output, "  # CrowdSec state").unwrap();
+            writeln!(output, "  crowdsec_data:").unwrap();
+            writeln!(output, "    driver: local").unwrap();
+            writeln!(
+                output,
+                "    name: {}-crowdsec-data",
+                self.config.project.name
+            )
+            .unwrap();
+            writeln!(output, "  crowdsec_config:").unwrap();
+            writeln!(output, "    driver: local").unwrap();
+            writeln!(
+                output,
+                "    name: {}-crowdsec-config",
+                self.config.project.name
+            )
+            .unwrap();
+        }
+
+        if let Some(logs) = ObservabilityGenerator::new(self.config).log_shipping() {
+            writeln!(output).unwrap();
+            writeln!(output, "  # Log shipping state").unwrap();
+            if logs.bundles_loki() {
+                writeln!(output, "  loki_data:").unwrap();
+                writeln!(output, "    driver: local").unwrap();
+                writeln!(output, "    name: {}-loki-data", self.config.project.name).unwrap();
+            }
+            writeln!(output, "  {}_data:", logs.shipper.as_str()).unwrap();
+            writeln!(output, "    driver: local").unwrap();
+            writeln!(
+                output,
+                "    name: {}-{}-data",
+                self.config.project.name,
+                logs.shipper.as_str()
+            )
+            .unwrap();
+        }
+
+        for proxy in &self.config.proxies {
+            if proxy.proxy_type == ProxyType::Nginx && self.config.edge_caches(proxy) {
+                writeln!(output).unwrap();
+                writeln!(output, "  # Edge cache of {}", proxy.name).unwrap();
+                writeln!(output, "  {}-cache:", proxy.name).unwrap();
+                writeln!(output, "    driver: local").unwrap();
+                writeln!(
+                    output,
+                    "    name: {}-{}-cache",
+                    self.config.project.name, proxy.name
+                )
+                .unwrap();
+            }
+            if self.config.acme(proxy).is_some() {
+                writeln!(output).unwrap();
+                writeln!(
+                    output,
+                    "  # ACME account and certificates of {}",
+                    proxy.name
+                )
+                .unwrap();
+                writeln!(output, "  {}-acme:", proxy.name).unwrap();
+                writeln!(output, "    driver: local").unwrap();
+                writeln!(
+                    output,
+                    "    name: {}-{}-acme",
+                    self.config.project.name, proxy.name
+                )
+                .unwrap();
+            }
+            if proxy.enabled_stats().is_some() {
+                writeln!(output).unwrap();
+                writeln!(output, "  # HAProxy admin sockets of {}", proxy.name).unwrap();
+                writeln!(output, "  {}-admin-socket:", proxy.name).unwrap();
+                writeln!(output, "    driver: local").unwrap();
+                writeln!(
+                    output,
+                    "    name: {}-{}-admin-socket",
+                    self.config.project.name, proxy.name
+                )
+                .unwrap();
+            }
+        }
+
+        if self.config.waf.audit_log
+            && self
+                .config
+                .proxies
+                .iter()
+                .any(|proxy| self.config.waf.applies_to(proxy))
+        {
+            writeln!(output).unwrap();
+            writeln!(output, "  # WAF audit logs").unwrap();
+            writeln!(output, "  waf_audit:").unwrap();
+            writeln!(output, "    driver: local").unwrap();
+            writeln!(output, "    name: {}-waf-audit", self.config.project.name).unwrap();
+        }
+
+        Ok(())
+    }
+
+    /// Generate secrets section
+    ///
+    /// Inline and command secrets are mounted from the files Cerberus
+    /// resolves into `secrets/` at generate time (or vault-agent renders
+    /// there), encrypted secrets from their decrypted copy on tmpfs.
+    /// A Swarm stack references every secret as external: create them with
+    /// `docker secret create` before deploying.
+    fn generate_secrets(&self, output: &mut String) {
+        if self.config.secrets.is_empty() {
+            return;
+        }
+
+        writeln!(output).unwrap();
+        writeln!(output, "secrets:").unwrap();
+        let secrets: BTreeMap<_, _> = self.config.secrets.iter().collect();
+        for (name, secret) in secrets {
+            writeln!(output, "  {name}:").unwrap();
+            if self.swarm() {
+                writeln!(output, "    external: true").unwrap();
+                if let SecretConfig::External {
+                    name: Some(name), ..
+                } = secret
+                {
+                    writeln!(output, "    name: {name}").unwrap();
+                }
+                continue;
+            }
+            match secret {
+                SecretConfig::File { file } => {
+                    writeln!(output, "    file: {}", paths::host_path(file)).unwrap();
+                }
+                SecretConfig::Environment { environment } => {
+                    writeln!(output, "    environment: {environment}").unwrap();
+                }
+                SecretConfig::External { external, name } => {
+                    writeln!(output, "    external: {external}").unwrap();
+                    if let Some(name) = name {
+                        writeln!(output, "    name: {name}").unwrap();
+                    }
+                }
+                SecretConfig::Content { .. }
+                | SecretConfig::Exec { .. }
+                | SecretConfig::Vault { .. } => {
+                    writeln!(output, "    file: ./{}/{name}", paths::SECRETS_DIR).unwrap()
+                }
+                SecretConfig::Encrypted { .. } => writeln!(
+                    output,
+                    "    file: {}",
+                    paths::posix(&paths::decrypted_path(&self.config.project.name, name))
+                )
+                .unwrap(),
+            }
+        }
+    }
+
+    /// Write the image of a proxy, building its Dockerfile when it needs extra modules
+    fn generate_proxy_image(&self, output: &mut String, proxy: &ProxyConfig) {
+        if self.config.builds_proxy_image(proxy) {
+            writeln!(output, "    build:").unwrap();
+            writeln!(output, "      context: ./dockerfiles/{}", proxy.name).unwrap();
+        } else {
+            let image =
+                if self.config.tracing(proxy).is_some() && proxy.proxy_type == ProxyType::Nginx {
+                    NGINX_OTEL_IMAGE
+                } else {
+                    self.get_proxy_image(&proxy.proxy_type)
+                };
+            writeln!(output, "    image: {image}").unwrap();
+        }
+        self.write_image_options(output, &proxy.container_options(&self.config.defaults));
+    }
+
+    /// Write a fixed container name
+    ///
+    /// Swarm names the tasks of a service itself and rejects the key.
+    fn write_container_name(&self, output: &mut String, name: &str) {
+        if !self.swarm() {
+            writeln!(output, "    container_name: {name}").unwrap();
+        }
+    }
+
+    /// Write the restart policy of a container
+    ///
+    /// Swarm ignores it and restarts failed tasks on its own.
+    fn write_restart(&self, output: &mut String, policy: &str) {
+        if !self.swarm() {
+            writeln!(output, "    restart: {policy}").unwrap();
+        }
+    }
+
+    /// Write the hardening options of a proxy container
+    fn write_proxy_hardening(&self, output: &mut String, proxy: &ProxyConfig) {
+        // Mounted volumes already provide the cache, admin socket and ACME
+        // storage directories
+        let runtime_dirs: Vec<&str> = proxy
+            .proxy_type
+            .runtime_dirs()
+            .iter()
+            .copied()
+            .filter(|dir| match *dir {
+                "/var/cache/nginx" => !self.config.edge_caches(proxy),
+                "/run/haproxy" => proxy.enabled_stats().is_none(),
+                "/data" => self.config.acme(proxy).is_none(),
+                _ => true,
+            })
+            .collect();
+        // varnishd loads the VCL it compiles into its working directory
+        let exec = proxy.proxy_type == ProxyType::Varnish;
+        self.write_hardening(
+            output,
+            proxy.proxy_type.hardened_user(),
+            &runtime_dirs,
+            exec,
+            &SecurityProfilesGenerator::new(self.config).security_opt(proxy),
+        );
+    }
+
+    /// Write the options of a hardened container when `[security.hardening]` is enabled
+    ///
+    /// The root filesystem becomes read-only, so the directories written at
+    /// runtime are mounted as tmpfs, writable by any user. `profiles` are
+    /// the `[security.profiles]` options of a proxy, written either way.
+    fn write_hardening(
+        &self,
+        output: &mut String,
+        user: Option<&str>,
+        runtime_dirs: &[&str],
+        exec: bool,
+        profiles: &[String],
+    ) {
+        let hardening = &self.config.security.hardening;
+        if !hardening.enabled {
+            if !profiles.is_empty() {
+                writeln!(output, "    security_opt:").unwrap();
+                for option in profiles {
+                    writeln!(output, "      - {option}").unwrap();
+                }
+            }
+            return;
+        }
+
+        if let Some(user) = user {
+            writeln!(output, "    user: \"{user}\"").unwrap();
+        }
+        writeln!(output, "    read_only: true").unwrap();
+        writeln!(output, "    security_opt:").unwrap();
+        writeln!(output, "      - no-new-privileges:true").unwrap();
+        for option in profiles {
+            writeln!(output, "      - {option}").unwrap();
+        }
+        writeln!(output, "    cap_drop:").unwrap();
+        writeln!(output, "      - ALL").unwrap();
+        writeln!(output, "    tmpfs:").unwrap();
+        let size = parse_size_bytes(&hardening.tmpfs_size).unwrap_or_default();
+        let mut dirs: Vec<&str> = runtime_dirs.to_vec();
+        for dir in &hardening.tmpfs {
+            if !dirs.contains(&dir.as_str()) {
+                dirs.push(dir);
+            }
+        }
+        for dir in dirs {
+            let exec = if exec { ",exec" } else { "" };
+            writeln!(output, "      - {dir}:mode=1777,size={size}{exec}").unwrap();
+        }
+    }
+
+    /// Write the OpenTelemetry SDK settings Caddy's `tracing` directive reads
+    ///
+    /// Nginx and Traefik take theirs from the generated configuration.
+    fn write_tracing_env(&self, output: &mut String, proxy: &ProxyConfig) {
+        let Some(tracing) = self
+            .config
+            .tracing(proxy)
+            .filter(|_| proxy.proxy_type == ProxyType::Caddy)
+        else {
+            return;
+        };
+        writeln!(
+            output,
+            "      - OTEL_EXPORTER_OTLP_ENDPOINT=http://{OTEL_COLLECTOR_ENDPOINT}"
+        )
+        .unwrap();
+        writeln!(output, "      - OTEL_EXPORTER_OTLP_PROTOCOL=grpc").unwrap();
+        writeln!(output, "      - OTEL_SERVICE_NAME={}", proxy.name).unwrap();
+        // Inner layers only trace requests sampled by the layer above
+        if proxy.layer.unwrap_or(1) == 1 {
+            writeln!(
+                output,
+                "      - OTEL_TRACES_SAMPLER=parentbased_traceidratio"
+            )
+            .unwrap();
+            writeln!(
+                output,
+                "      - OTEL_TRACES_SAMPLER_ARG={}",
+                tracing.sample_rate
+            )
+            .unwrap();
+        } else {
+            writeln!(output, "      - OTEL_TRACES_SAMPLER=parentbased_always_off").unwrap();
+        }
+    }
+
+    /// Write `TZ` into an `environment:` list when `project.timezone` is set
+    fn write_timezone_env(&self, output: &mut String) {
+        if let Some(timezone) = &self.config.project.timezone {
+            writeln!(output, "      - TZ={timezone}").unwrap();
+        }
+    }
+
+    /// Write an `environment:` list holding only `TZ`, for containers without one
+    fn write_timezone_environment(&self, output: &mut String) {
+        if self.config.project.timezone.is_some() {
+            writeln!(output, "    environment:").unwrap();
+            self.write_timezone_env(output);
+        }
+    }
+
+    /// Mount the host's `/etc/localtime` into a `volumes:` list
+    ///
+    /// Tools that ignore `TZ` read the zone from this file instead.
+    fn write_localtime_volume(&self, output: &mut String) {
+        if self.config.project.timezone.is_some() {
+            writeln!(output, "      - /etc/localtime:/etc/localtime:ro").unwrap();
+        }
+    }
+
+    /// Network driver, with bridge networks spanning the Swarm as overlays
+    fn network_driver<'d>(&self, driver: &'d str) -> &'d str {
+        if self.swarm() && driver == "bridge" {
+            "overlay"
+        } else {
+            driver
+        }
+    }
+
+    /// Whether the file is rendered for `docker stack deploy`
+    fn swarm(&self) -> bool {
+        self.config.project.target == DeployTarget::Swarm
+    }
+
+    /// Write the `pull_policy` and `platform` of a container's image
+    ///
+    /// Without a fixed `platform`, a multi-platform image runs on the
+    /// platform `DOCKER_DEFAULT_PLATFORM` of the host names, falling back to
+    /// the first of its `platforms`.
+    fn write_image_options(&self, output: &mut String, options: &ContainerOptions) {
+        if let Some(pull_policy) = options.pull_policy {
+            writeln!(output, "    pull_policy: {}", pull_policy.as_str()).unwrap();
+        }
+        if let Some(platform) = &options.platform {
+            writeln!(output, "    platform: {platform}").unwrap();
+        } else if let Some(first) = options.platforms.first() {
+            writeln!(
+                output,
+                "    platform: ${{DOCKER_DEFAULT_PLATFORM:-{first}}}"
+            )
+            .unwrap();
+        }
+    }
+
+    /// Generate entrypoint and command overrides for a proxy
+    fn generate_proxy_command(&self, output: &mut String, proxy: &ProxyConfig) {
+        if proxy.uses_drain_entrypoint() {
+            writeln!(
+                output,
+                "    entrypoint: [\"/bin/sh\", \"{ENTRYPOINT_PATH}\"]"
+            )
+            .unwrap();
+            // The entrypoint translates SIGTERM into the proxy's graceful stop
+            writeln!(output, "    stop_signal: SIGTERM").unwrap();
+        }
+        if let Some(period) = proxy
+            .container_options(&self.config.defaults)
+            .stop_grace_period_secs
+        {
+            writeln!(output, "    stop_grace_period: {period}s").unwrap();
+        }
+
+        for (field, value) in [
+            ("entrypoint", &proxy.entrypoint),
+            ("command", &proxy.command),
+        ] {
+            // JSON strings and arrays are valid YAML flow scalars/sequences
+            let rendered = match value {
+                Some(CommandConfig::Shell(command)) => serde_json::to_string(command),
+                Some(CommandConfig::Exec(args)) => serde_json::to_string(args),
+                None => continue,
+            };
+            writeln!(output, "    {field}: {}", rendered.unwrap()).unwrap();
+        }
+    }
+
+    /// Whether a proxy receives traffic directly from clients
+    pub(crate) fn is_edge_proxy(&self, proxy: &ProxyConfig) -> bool {
+        self.published_port(proxy, 0).is_some()
+    }
+
+    /// Write the `networks` list of a service
+    fn write_networks(&self, output: &mut String, networks: &[String]) {
+        writeln!(output, "    networks:").unwrap();
+        for network in networks {
+            writeln!(output, "      - {network}").unwrap();
+        }
+    }
+
+    /// Write the `resources` block of a service's `deploy` section
+    fn write_resources(&self, output: &mut String, resources: &ResourcesConfig) {
+        writeln!(output, "      resources:").unwrap();
+        for (section, limits) in [
+            ("limits", &resources.limits),
+            ("reservations", &resources.reservations),
+        ] {
+            let Some(limits) = limits else {
+                continue;
+            };
+            writeln!(output, "        {section}:").unwrap();
+            if let Some(cpus) = &limits.cpus {
+                writeln!(output, "          cpus: \"{cpus}\"").unwrap();
+            }
+            if let Some(memory) = &limits.memory {
+                writeln!(output, "          memory: {memory}").unwrap();
+            }
+            if let Some(pids) = limits.pids {
+                writeln!(output, "          pids: {pids}").unwrap();
+            }
+        }
+    }
+
+    /// Write the `secrets` list of a service
+    fn write_secrets(&self, output: &mut String, secrets: &[ServiceSecretRef]) {
+        if secrets.is_empty() {
+            return;
+        }
+
+        writeln!(output, "    secrets:").unwrap();
+        for secret in secrets {
+            match secret {
+                ServiceSecretRef::Simple(name) => {
+                    writeln!(output, "      - {name}").unwrap();
+                }
+                ServiceSecretRef::Detailed {
+                    source,
+                    target,
+                    mode,
+                    uid,
+                    gid,
+                } => {
+                    writeln!(output, "      - source: {source}").unwrap();
+                    if let Some(target) = target {
+                        writeln!(output, "        target: {target}").unwrap();
+                    }
+                    if let Some(uid) = uid {
+                        writeln!(output, "        uid: \"{uid}\"").unwrap();
+                    }
+                    if let Some(gid) = gid {
+                        writeln!(output, "        gid: \"{gid}\"").unwrap();
+                    }
+                    if let Some(mode) = mode {
+                        writeln!(output, "        mode: 0{mode:o}").unwrap();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Generate the container-only `expose` ports of a proxy
+    fn generate_proxy_expose(&self, output: &mut String, proxy: &ProxyConfig) {
+        if !proxy.expose.is_empty() {
+            writeln!(output, "    expose:").unwrap();
+            for port in &proxy.expose {
+                writeln!(output, "      - \"{port}\"").unwrap();
+            }
+        }
+    }
+
+    /// Host port published for a proxy, if any
+    pub(crate) fn published_port(&self, proxy: &ProxyConfig, index: usize) -> Option<u16> {
+        // Without an external port the proxy is reachable only inside Docker
+        proxy.external_port.map(|external_port| {
+            // ポート重複を避けるために、インデックスベースで自動調整
+            if index == 0 {
+                external_port
+            } else {
+                external_port + index as u16 * 10
+            }
+        })
+    }
+
+    /// Host side of a port a proxy container publishes, if any
+    ///
+    /// `offset` counts the containers of the proxy from 0. The containers of
+    /// an internal-only proxy publish nothing; its load balancer does.
+    pub(crate) fn host_port(&self, proxy: &ProxyConfig, port: u16, offset: u8) -> Option<HostPort> {
+        let scaling = self.config.project.scaling;
+        if proxy.instance_balancer(scaling).is_some() {
+            return None;
+        }
+        if proxy.instance_port_strategy == InstancePortStrategy::Random
+            && proxy.container_count(scaling) > 1
+        {
+            return Some(HostPort::Random);
+        }
+        let replicas = proxy.deploy_replicas().unwrap_or(1);
+        // Each replica binds the next host port of the range; Swarm's
+        // routing mesh publishes one port for every replica
+        Some(HostPort::Fixed(if replicas > 1 && !self.swarm() {
+            format!("{port}-{}", u32::from(port) + replicas - 1)
+        } else {
+            (port + u16::from(offset)).to_string()
+        }))
+    }
+
+    /// Compose service names of a proxy, including scaled instances
+    pub(crate) fn proxy_services(&self, proxy: &ProxyConfig) -> Vec<String> {
+        let instances = proxy.duplicated_instances(self.config.project.scaling);
+        std::iter::once(proxy.name.clone())
+            .chain((2..=instances).map(|instance| format!("{}-{instance}", proxy.name)))
+            .collect()
+    }
+
+    /// Whether the proxy gets a container
+    pub(crate) fn includes_proxy(&self, proxy: &ProxyConfig) -> bool {
+        // Skip proxy-1 if anubis is disabled AND proxy is nginx (no DDoS protection needed)
+        // Other proxy types (Caddy, HAProxy, Traefik) always generate as simple reverse proxies
+        // unless it forwards tcp or udp streams, which bypass Anubis anyway
+        !(proxy.layer.unwrap_or(1) == 1
+            && !self.config.anubis.enabled
+            && proxy.proxy_type == ProxyType::Nginx
+            && self.config.streams(proxy).is_empty())
+    }
+
+    /// Whether the backend service gets a container
+    pub(crate) fn includes_service(&self, service: &crate::config::ServiceConfig) -> bool {
+        // Generate a container if one is defined or the upstream is not an
+        // external IP; the servers of an upstream pool run elsewhere
+        service.has_container()
+            || (self.config.upstream_pool(&service.upstream).is_none()
+                && !self.is_external_upstream(&service.upstream))
+    }
+
+    /// Get Docker image for proxy type
+    pub(crate) fn get_proxy_image(&self, proxy_type: &ProxyType) -> &'static str {
+        match proxy_type {
+            ProxyType::Caddy => "caddy:alpine",
+            ProxyType::Nginx => "nginx:alpine",
+            ProxyType::HaProxy => "haproxy:alpine",
+            ProxyType::Traefik => "traefik:v3.0",
+            ProxyType::Varnish => "varnish:stable",
+        }
+    }
+
+    /// Get configuration directory for proxy type
+    pub(crate) fn get_proxy_config_dir(&self, proxy_type: &ProxyType) -> &'static str {
+        proxy_type.config_dir()
+    }
+
+    /// Check if upstream is an external IP/hostname
+    pub(crate) fn is_external_upstream(&self, upstream: &str) -> bool {
+        // Simple check: if it contains IP pattern or external domains
+        upstream.contains("192.")
+            || upstream.contains("10.")
+            || upstream.contains("172.")
+            || upstream.contains("100.")  // Tailscale IP range
+            || upstream.contains(".com")
+            || upstream.contains(".net")
+            || upstream.contains(".org")
+            || (upstream.contains("://")
+                && !upstream.contains("://internal-")
+                && !upstream.contains("http://internal-service"))
+    }
+
+    /// Validate a Docker Compose file
+    pub async fn validate_file(path: &std::path::Path) -> Result<()> {
+        // Run docker-compose config to validate
+        let output = Command::new("docker-compose")
+            .arg("-f")
+            .arg(path)
+            .arg("config")
+            .output()
+            .map_err(|e| CerberusError::io(path, e))?;
+
+        if !output.status.success() {
+            let error_msg = String::from_utf8_lossy(&output.stderr);
+            return Err(CerberusError::DockerComposeValidation {
+                message: error_msg.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl ProxyType {
+    /// Convert ProxyType to string for display
+    pub(crate) fn to_string(&self) -> &'static str {
+        match self {
+            ProxyType::Caddy => "caddy",
+            ProxyType::Nginx => "nginx",
+            ProxyType::HaProxy => "haproxy",
+            ProxyType::Traefik => "traefik",
+            ProxyType::Varnish => "varnish",
+        }
+    }
+}
+
+/// `depends_on` entry waiting for a container to start, or to be healthy
+fn dependency(name: impl Into<String>, healthy: bool) -> (String, DependencyCondition) {
+    let condition = if healthy {
+        "service_healthy"
+    } else {
+        "service_started"
+    };
+    (
+        name.into(),
+        DependencyCondition {
+            condition: condition.to_string(),
+            restart: None,
+        },
+    )
+}
+
+/// Add a `depends_on` entry unless the container is already listed
+fn push_dependency(
+    dependencies: &mut Vec<(String, DependencyCondition)>,
+    name: impl Into<String>,
+    healthy: bool,
+) {
+    let (name, condition) = dependency(name, healthy);
+    if !dependencies.iter().any(|(listed, _)| *listed == name) {
+        dependencies.push((name, condition));
+    }
+}
+
+/// Double-quoted YAML scalar of a user-supplied value
+///
+/// Keeps `: `, ` #`, leading indicators such as `*` or `&`, and line breaks
+/// part of the value instead of the document's structure.
+fn yaml_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests;
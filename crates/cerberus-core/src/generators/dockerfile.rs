@@ -2,14 +2,77 @@
 //!
 //! Generates Dockerfiles for proxy services from Cerberus configuration.
 
+use super::proxy_config::waf_template_data;
 use crate::{
     Result,
-    config::{Config, ProxyConfig},
+    config::{CompressionAlgorithm, Config, ProxyConfig},
 };
 use handlebars::Handlebars;
 use serde_json::json;
 use std::collections::HashMap;
 
+/// Nginx image bundling ModSecurity and the OWASP Core Rule Set
+const NGINX_WAF_IMAGE: &str = "owasp/modsecurity-crs:nginx-alpine";
+
+/// Nginx image shipping the OpenTelemetry module
+pub(crate) const NGINX_OTEL_IMAGE: &str = "nginx:alpine-otel";
+
+/// Caddy module providing the Coraza WAF (OWASP Core Rule Set embedded)
+const CADDY_WAF_MODULE: &str = "github.com/corazawaf/coraza-caddy/v2";
+
+/// Caddy module providing the `cache` directive
+const CADDY_CACHE_MODULE: &str = "github.com/caddyserver/cache-handler";
+
+/// Caddy module providing the `br` encoder of the `encode` directive
+const CADDY_BROTLI_MODULE: &str = "github.com/ueffel/caddy-brotli";
+
+/// Dynamic Nginx module providing a compression algorithm
+pub(crate) struct NginxModule {
+    /// Algorithm the module adds
+    pub algorithm: CompressionAlgorithm,
+    /// Directory the source is cloned into
+    pub name: &'static str,
+    /// Git repository of the source
+    pub repository: &'static str,
+    /// Alpine packages needed to build it
+    pub build_packages: &'static str,
+    /// Alpine packages needed to run it
+    pub runtime_packages: &'static str,
+    /// Module file loaded by `load_module`
+    pub file: &'static str,
+}
+
+/// Nginx modules for the algorithms the stock image lacks
+///
+/// ngx_brotli builds its bundled brotli submodule statically.
+pub(crate) const NGINX_COMPRESSION_MODULES: &[NginxModule] = &[
+    NginxModule {
+        algorithm: CompressionAlgorithm::Brotli,
+        name: "ngx_brotli",
+        repository: "https://github.com/google/ngx_brotli",
+        build_packages: "",
+        runtime_packages: "",
+        file: "ngx_http_brotli_filter_module.so",
+    },
+    NginxModule {
+        algorithm: CompressionAlgorithm::Zstd,
+        name: "zstd-nginx-module",
+        repository: "https://github.com/tokers/zstd-nginx-module",
+        build_packages: "zstd-dev",
+        runtime_packages: "zstd-libs",
+        file: "ngx_http_zstd_filter_module.so",
+    },
+];
+
+/// Nginx modules a proxy loads for its services' compression
+pub(crate) fn nginx_modules(config: &Config, proxy: &ProxyConfig) -> Vec<&'static NginxModule> {
+    let algorithms = config.compression_modules(proxy);
+    NGINX_COMPRESSION_MODULES
+        .iter()
+        .filter(|module| algorithms.contains(&module.algorithm))
+        .collect()
+}
+
 /// Generator for Dockerfiles
 pub struct DockerfileGenerator<'a> {
     config: &'a Config,
@@ -50,6 +113,13 @@ impl<'a> DockerfileGenerator<'a> {
             )
             .expect("Failed to register Traefik Dockerfile template");
 
+        handlebars
+            .register_template_string(
+                "varnish_dockerfile",
+                include_str!("../templates/Dockerfile.varnish.hbs"),
+            )
+            .expect("Failed to register Varnish Dockerfile template");
+
         Self { config, handlebars }
     }
 
@@ -60,6 +130,7 @@ impl<'a> DockerfileGenerator<'a> {
             "nginx" => self.generate_nginx_dockerfile(proxy),
             "haproxy" => self.generate_haproxy_dockerfile(proxy),
             "traefik" => self.generate_traefik_dockerfile(proxy),
+            "varnish" => self.generate_varnish_dockerfile(proxy),
             _ => Err(crate::CerberusError::config(format!(
                 "Unsupported proxy type for Dockerfile: {}",
                 proxy.proxy_type
@@ -69,7 +140,7 @@ impl<'a> DockerfileGenerator<'a> {
 
     /// Generate Caddy Dockerfile
     fn generate_caddy_dockerfile(&self, proxy: &ProxyConfig) -> Result<String> {
-        let template_data = json!({
+        let mut template_data = json!({
             "proxy": proxy,
             "project_name": &self.config.project.name,
             "services": &self.config.services,
@@ -78,25 +149,69 @@ impl<'a> DockerfileGenerator<'a> {
             "config_file": "Caddyfile",
             "config_path": "/etc/caddy/Caddyfile",
             "log_path": "/var/log/caddy",
-            "port": proxy.external_port.unwrap_or(proxy.internal_port),
+            "port": proxy.internal_port,
         });
 
+        template_data["waf"] = waf_template_data(&self.config.waf, proxy);
+
+        let mut modules = Vec::new();
+        if self.config.waf.applies_to(proxy) {
+            modules.push(CADDY_WAF_MODULE);
+        }
+        if self.config.edge_caches(proxy) {
+            modules.push(CADDY_CACHE_MODULE);
+        }
+        if self
+            .config
+            .compression_modules(proxy)
+            .contains(&CompressionAlgorithm::Brotli)
+        {
+            modules.push(CADDY_BROTLI_MODULE);
+        }
+        if let Some(acme) = self.config.acme(proxy) {
+            modules.push(acme.provider.caddy_module());
+        }
+        template_data["modules"] = json!(modules);
+
         let dockerfile = self.handlebars.render("caddy_dockerfile", &template_data)?;
         Ok(dockerfile)
     }
 
     /// Generate Nginx Dockerfile
     fn generate_nginx_dockerfile(&self, proxy: &ProxyConfig) -> Result<String> {
+        let waf = waf_template_data(&self.config.waf, proxy);
+        let modules = nginx_modules(self.config, proxy);
+        let packages = |field: fn(&NginxModule) -> &'static str| {
+            modules
+                .iter()
+                .map(|module| field(module))
+                .filter(|packages| !packages.is_empty())
+                .collect::<Vec<_>>()
+        };
         let template_data = json!({
             "proxy": proxy,
             "project_name": &self.config.project.name,
             "services": &self.config.services,
             "has_anubis": self.config.anubis.enabled,
-            "base_image": "nginx:alpine",
+            "base_image": if !waf.is_null() {
+                NGINX_WAF_IMAGE
+            } else if self.config.tracing(proxy).is_some() {
+                NGINX_OTEL_IMAGE
+            } else {
+                "nginx:alpine"
+            },
+            "waf": waf,
             "config_file": "nginx.conf",
             "config_path": "/etc/nginx/nginx.conf",
             "log_path": "/var/log/nginx",
-            "port": proxy.external_port.unwrap_or(proxy.internal_port),
+            "port": proxy.internal_port,
+            "hardened": self.config.security.hardening.enabled,
+            "modules": modules
+                .iter()
+                .map(|module| json!({ "name": module.name, "repository": module.repository }))
+                .collect::<Vec<_>>(),
+            "build_packages": packages(|module| module.build_packages),
+            "runtime_packages": packages(|module| module.runtime_packages),
         });
 
         let dockerfile = self.handlebars.render("nginx_dockerfile", &template_data)?;
@@ -114,7 +229,7 @@ impl<'a> DockerfileGenerator<'a> {
             "config_file": "haproxy.cfg",
             "config_path": "/usr/local/etc/haproxy/haproxy.cfg",
             "log_path": "/var/log/haproxy",
-            "port": proxy.external_port.unwrap_or(proxy.internal_port),
+            "port": proxy.internal_port,
         });
 
         let dockerfile = self
@@ -134,7 +249,7 @@ impl<'a> DockerfileGenerator<'a> {
             "config_file": "traefik.yml",
             "config_path": "/etc/traefik/traefik.yml",
             "log_path": "/var/log/traefik",
-            "port": proxy.external_port.unwrap_or(proxy.internal_port),
+            "port": proxy.internal_port,
         });
 
         let dockerfile = self
@@ -143,6 +258,24 @@ impl<'a> DockerfileGenerator<'a> {
         Ok(dockerfile)
     }
 
+    /// Generate Varnish Dockerfile
+    fn generate_varnish_dockerfile(&self, proxy: &ProxyConfig) -> Result<String> {
+        let template_data = json!({
+            "proxy": proxy,
+            "project_name": &self.config.project.name,
+            "base_image": "varnish:stable",
+            "config_file": "default.vcl",
+            "config_path": "/etc/varnish/default.vcl",
+            "port": proxy.internal_port,
+            "storage": proxy.varnish.clone().unwrap_or_default().storage,
+        });
+
+        let dockerfile = self
+            .handlebars
+            .render("varnish_dockerfile", &template_data)?;
+        Ok(dockerfile)
+    }
+
     /// Generate all Dockerfiles for the project
     pub fn generate_all(&self) -> Result<HashMap<String, String>> {
         let mut dockerfiles = HashMap::new();
@@ -190,6 +323,7 @@ impl<'a> DockerfileGenerator<'a> {
                 "nginx" => "nginx:alpine",
                 "haproxy" => "haproxy:alpine",
                 "traefik" => "traefik:v3.0",
+                "varnish" => "varnish:stable",
                 _ => "alpine:latest",
             };
 
@@ -200,6 +334,7 @@ impl<'a> DockerfileGenerator<'a> {
                 "nginx" => "/etc/nginx/",
                 "haproxy" => "/usr/local/etc/haproxy/",
                 "traefik" => "/etc/traefik/",
+                "varnish" => "/etc/varnish/",
                 _ => "/etc/",
             };
 
@@ -207,20 +342,12 @@ impl<'a> DockerfileGenerator<'a> {
                 "COPY --from=config /config/{}/ {}\n",
                 proxy.name, config_path
             ));
-            if let Some(port) = proxy.external_port {
-                dockerfile.push_str(&format!("EXPOSE {}\n", port));
-                dockerfile.push_str("HEALTHCHECK --interval=30s --timeout=10s --retries=3 \\\n");
-                dockerfile.push_str(&format!(
-                    "  CMD curl -f http://localhost:{}/health || exit 1\n",
-                    port
-                ));
-            } else {
-                dockerfile.push_str("HEALTHCHECK --interval=30s --timeout=10s --retries=3 \\\n");
-                dockerfile.push_str(&format!(
-                    "  CMD curl -f http://localhost:{}/health || exit 1\n",
-                    proxy.internal_port
-                ));
-            }
+            dockerfile.push_str(&format!("EXPOSE {}\n", proxy.internal_port));
+            dockerfile.push_str("HEALTHCHECK --interval=30s --timeout=10s --retries=3 \\\n");
+            dockerfile.push_str(&format!(
+                "  CMD curl -f http://localhost:{}/health || exit 1\n",
+                proxy.internal_port
+            ));
             dockerfile.push('\n');
         }
 
@@ -232,6 +359,7 @@ impl<'a> DockerfileGenerator<'a> {
                 "nginx" => "nginx:alpine",
                 "haproxy" => "haproxy:alpine",
                 "traefik" => "traefik:v3.0",
+                "varnish" => "varnish:stable",
                 _ => "alpine:latest",
             };
             dockerfile.push_str(&format!("FROM {base_image}\n"));
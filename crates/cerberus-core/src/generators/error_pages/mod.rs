@@ -0,0 +1,135 @@
+//! # Error pages generator
+//!
+//! Writes the pages of `[error_pages]` to [`ERROR_PAGES_DIR`] in the output
+//! directory: `<status>.html` for every proxy type, and `<status>.http`,
+//! the complete response HAProxy's `errorfile` expects, when a proxy is
+//! HAProxy. Traefik cannot serve files itself, so its `errors` middleware
+//! fetches the pages from the [`SERVICE_NAME`] container.
+
+use super::GeneratedFile;
+use crate::{
+    CerberusError, Result,
+    config::{Config, ERROR_PAGES_DIR, ProxyType},
+};
+use std::path::Path;
+
+/// Compose service serving the pages to Traefik
+pub const SERVICE_NAME: &str = "error-pages";
+
+/// Image of the [`SERVICE_NAME`] container, which runs unprivileged
+pub const SERVICE_IMAGE: &str = "nginxinc/nginx-unprivileged:alpine";
+
+/// Port the [`SERVICE_NAME`] container serves the pages on
+pub const SERVICE_PORT: u16 = 8080;
+
+/// Directory the [`SERVICE_NAME`] container serves
+pub const SERVICE_ROOT: &str = "/usr/share/nginx/html";
+
+/// Built-in page for `[error_pages] maintenance = true`
+const MAINTENANCE_PAGE: &str = include_str!("../../templates/maintenance.html");
+
+/// Generator for the custom error pages
+pub struct ErrorPagesGenerator<'a> {
+    config: &'a Config,
+}
+
+impl<'a> ErrorPagesGenerator<'a> {
+    /// Create a new error pages generator
+    pub fn new(config: &'a Config) -> Self {
+        Self { config }
+    }
+
+    /// Whether any error page is served
+    pub fn is_enabled(&self) -> bool {
+        self.config.error_pages.is_enabled()
+    }
+
+    /// Whether the Compose file needs the [`SERVICE_NAME`] container
+    pub fn needs_server(&self) -> bool {
+        self.is_enabled() && self.uses(ProxyType::Traefik)
+    }
+
+    /// Files to write, relative to the output directory
+    ///
+    /// # Errors
+    /// Returns error if a page's `file` cannot be read
+    pub fn files(&self) -> Result<Vec<GeneratedFile>> {
+        let dir = Path::new(ERROR_PAGES_DIR);
+        let haproxy = self.uses(ProxyType::HaProxy);
+        let mut files = Vec::new();
+        for status in self.config.error_pages.statuses() {
+            let html = self.html(status)?;
+            if haproxy {
+                files.push((
+                    dir.join(format!("{status}.http")),
+                    haproxy_response(status, &html),
+                ));
+            }
+            files.push((dir.join(format!("{status}.html")), html));
+        }
+        Ok(files)
+    }
+
+    /// HTML of the page for a status code
+    fn html(&self, status: u16) -> Result<String> {
+        let Some(page) = self.config.error_pages.page(status) else {
+            return Ok(MAINTENANCE_PAGE.to_string());
+        };
+        match (&page.file, &page.html) {
+            (Some(file), _) => {
+                std::fs::read_to_string(file).map_err(|e| CerberusError::io(file, e))
+            }
+            (None, Some(html)) => Ok(html.clone()),
+            (None, None) => Err(CerberusError::validation(format!(
+                "error_pages.pages.{status} needs a file or html"
+            ))),
+        }
+    }
+
+    /// Whether any proxy is of a type
+    fn uses(&self, proxy_type: ProxyType) -> bool {
+        self.config
+            .proxies
+            .iter()
+            .any(|proxy| proxy.proxy_type == proxy_type)
+    }
+}
+
+/// Complete HTTP response HAProxy sends for an `errorfile`
+pub fn haproxy_response(status: u16, html: &str) -> String {
+    format!(
+        "HTTP/1.0 {status} {}\n\
+         Cache-Control: no-cache\n\
+         Connection: close\n\
+         Content-Type: text/html; charset=utf-8\n\
+         \n\
+         {html}",
+        reason_phrase(status)
+    )
+}
+
+/// Reason phrase of a status code, for the HAProxy responses
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        407 => "Proxy Authentication Required",
+        408 => "Request Timeout",
+        410 => "Gone",
+        413 => "Payload Too Large",
+        425 => "Too Early",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Error",
+    }
+}
+
+#[cfg(test)]
+mod tests;
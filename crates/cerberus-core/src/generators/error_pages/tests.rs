@@ -0,0 +1,134 @@
+//! # Tests for error page generation
+//!
+//! These tests verify that the configured pages and the maintenance page
+//! are written for every status code, with HAProxy responses next to them
+//! when a proxy needs them.
+
+use super::*;
+use crate::test_support::parse_config;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use tempfile::NamedTempFile;
+
+/// Helper function to create a config with one proxy and the given pages
+fn create_config(proxy_type: &str, error_pages: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "error-pages-test"
+
+{error_pages}
+
+[[proxies]]
+name = "edge"
+type = "{proxy_type}"
+external_port = 80
+"#
+    ))
+}
+
+#[test]
+fn test_disabled_by_default() {
+    let config = create_config("nginx", "");
+    let generator = ErrorPagesGenerator::new(&config);
+    assert!(!generator.is_enabled());
+    assert!(!generator.needs_server());
+    assert!(
+        generator
+            .files()
+            .expect("Generation should succeed")
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_pages_and_maintenance() {
+    let mut file = NamedTempFile::new().expect("Failed to create temp file");
+    file.write_all(b"<h1>Gone</h1>")
+        .expect("Failed to write to temp file");
+    let config = create_config(
+        "nginx",
+        &format!(
+            r#"
+[error_pages]
+maintenance = true
+
+[error_pages.pages.404]
+html = "<h1>Not found</h1>"
+
+[error_pages.pages.410]
+file = "{}"
+
+[error_pages.pages.503]
+html = "<h1>Back soon</h1>"
+"#,
+            file.path().display()
+        ),
+    );
+    let files: BTreeMap<PathBuf, String> = ErrorPagesGenerator::new(&config)
+        .files()
+        .expect("Generation should succeed")
+        .into_iter()
+        .collect();
+
+    assert_eq!(
+        files
+            .keys()
+            .map(|path| path.to_string_lossy())
+            .collect::<Vec<_>>(),
+        [
+            "error-pages/404.html",
+            "error-pages/410.html",
+            "error-pages/502.html",
+            "error-pages/503.html",
+            "error-pages/504.html",
+        ]
+    );
+    assert_eq!(
+        files[Path::new("error-pages/404.html")],
+        "<h1>Not found</h1>"
+    );
+    assert_eq!(files[Path::new("error-pages/410.html")], "<h1>Gone</h1>");
+    assert_eq!(
+        files[Path::new("error-pages/503.html")],
+        "<h1>Back soon</h1>"
+    );
+    assert!(files[Path::new("error-pages/502.html")].contains("Under maintenance"));
+}
+
+#[test]
+fn test_haproxy_responses() {
+    let config = create_config(
+        "haproxy",
+        "[error_pages.pages.503]\nhtml = \"<h1>Back soon</h1>\"",
+    );
+    let files = ErrorPagesGenerator::new(&config)
+        .files()
+        .expect("Generation should succeed");
+    let (_, response) = files
+        .iter()
+        .find(|(path, _)| path == Path::new("error-pages/503.http"))
+        .expect("HAProxy needs a complete response");
+    assert!(response.starts_with("HTTP/1.0 503 Service Unavailable\n"));
+    assert!(response.contains("Content-Type: text/html; charset=utf-8\n\n<h1>Back soon</h1>"));
+}
+
+#[test]
+fn test_missing_file() {
+    let config = create_config(
+        "nginx",
+        "[error_pages.pages.404]\nfile = \"does-not-exist/404.html\"",
+    );
+    let error = ErrorPagesGenerator::new(&config)
+        .files()
+        .expect_err("A missing page file should fail generation")
+        .to_string();
+    assert!(error.contains("does-not-exist/404.html"), "{error}");
+}
+
+#[test]
+fn test_traefik_needs_server() {
+    let config = create_config("traefik", "[error_pages]\nmaintenance = true");
+    assert!(ErrorPagesGenerator::new(&config).needs_server());
+}
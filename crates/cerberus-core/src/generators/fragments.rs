@@ -0,0 +1,110 @@
+//! Cache of rendered template fragments
+//!
+//! Per-service fragments, such as an nginx `conf.d` file or a Traefik
+//! dynamic file, only depend on their template and the data rendered into
+//! it. The cache keys each fragment by a hash of the Cerberus version, the
+//! template source, and that data, so a fragment is rendered once per run
+//! however many proxies share it. With a directory, fragments also survive
+//! between runs: `watch` then re-renders only the services that changed.
+
+use crate::checksum::sha256_hex;
+use crate::{CerberusError, Result};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Rendered fragments by key, in memory and optionally on disk
+#[derive(Debug, Default)]
+pub struct FragmentCache {
+    dir: Option<PathBuf>,
+    rendered: Mutex<HashMap<String, String>>,
+    used: Mutex<HashSet<String>>,
+}
+
+impl FragmentCache {
+    /// Cache fragments for the current run only
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Cache fragments in a directory as well, for later runs
+    pub fn on_disk(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: Some(dir.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Return the cached fragment, or render and cache it
+    ///
+    /// A cache directory that cannot be written only costs the reuse in
+    /// later runs; the fragment is still returned.
+    pub fn render(
+        &self,
+        template: &str,
+        source: &str,
+        data: &Value,
+        render: impl FnOnce() -> Result<String>,
+    ) -> Result<String> {
+        let key = fragment_key(template, source, data)?;
+        self.used.lock().unwrap().insert(key.clone());
+
+        if let Some(fragment) = self.rendered.lock().unwrap().get(&key) {
+            return Ok(fragment.clone());
+        }
+        let path = self.dir.as_ref().map(|dir| dir.join(&key));
+        if let Some(fragment) = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+        {
+            self.rendered.lock().unwrap().insert(key, fragment.clone());
+            return Ok(fragment);
+        }
+
+        let fragment = render()?;
+        if let Some(path) = &path {
+            let written = path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::write(path, &fragment));
+            if let Err(e) = written {
+                tracing::warn!("Could not cache fragment in {}: {e}", path.display());
+            }
+        }
+        self.rendered.lock().unwrap().insert(key, fragment.clone());
+        Ok(fragment)
+    }
+
+    /// Remove cached fragments on disk that this run did not use
+    ///
+    /// # Errors
+    /// Returns error if the cache directory cannot be read or cleaned
+    pub fn prune(&self) -> Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        if !dir.exists() {
+            return Ok(());
+        }
+
+        let used = self.used.lock().unwrap();
+        for entry in std::fs::read_dir(dir).map_err(|e| CerberusError::io(dir, e))? {
+            let path = entry.map_err(|e| CerberusError::io(dir, e))?.path();
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned());
+            if path.is_file() && !name.is_some_and(|name| used.contains(&name)) {
+                std::fs::remove_file(&path).map_err(|e| CerberusError::io(&path, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Key of a fragment: its inputs, hashed
+fn fragment_key(template: &str, source: &str, data: &Value) -> Result<String> {
+    let mut input = format!("{}\0{template}\0{source}\0", env!("CARGO_PKG_VERSION")).into_bytes();
+    input.extend(serde_json::to_vec(data)?);
+    Ok(sha256_hex(&input))
+}
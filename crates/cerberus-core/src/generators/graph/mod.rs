@@ -0,0 +1,400 @@
+//! # Topology graph generator
+//!
+//! Renders the configured architecture for `cerberus graph`: the path from
+//! the internet through each proxy layer and Anubis to the services, the
+//! networks every container joins, and the service dependencies. The same
+//! graph is written as Graphviz DOT or as a Mermaid flowchart, so it can be
+//! embedded in runbooks and diffed in reviews of routing changes.
+
+use super::{DockerComposeGenerator, NetworkResolver};
+use crate::config::{AnubisContainer, Config, DependsOn, ProxyConfig};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Output syntax of the topology graph
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum GraphStyle {
+    /// Mermaid flowchart, rendered inline by GitHub and GitLab
+    #[default]
+    Mermaid,
+    /// Graphviz DOT, for `dot -Tsvg`
+    Dot,
+}
+
+/// Kind of a node, which decides its shape
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NodeKind {
+    Internet,
+    Proxy,
+    Anubis,
+    Service,
+    Network,
+}
+
+/// A component of the topology
+#[derive(Debug, Clone, PartialEq)]
+struct Node {
+    id: String,
+    label: String,
+    kind: NodeKind,
+}
+
+/// Kind of an edge, which decides its line style
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EdgeKind {
+    /// Requests flowing towards the services
+    Traffic,
+    /// A container attached to a network
+    Network,
+    /// A `depends_on` entry of a service
+    Dependency,
+}
+
+/// A connection between two components
+#[derive(Debug, Clone, PartialEq)]
+struct Edge {
+    from: String,
+    to: String,
+    label: Option<String>,
+    kind: EdgeKind,
+}
+
+/// Generator for the topology graph
+pub struct GraphGenerator<'a> {
+    config: &'a Config,
+    compose: DockerComposeGenerator<'a>,
+    networks: NetworkResolver<'a>,
+}
+
+impl<'a> GraphGenerator<'a> {
+    /// Create a new graph generator
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            config,
+            compose: DockerComposeGenerator::new(config),
+            networks: NetworkResolver::new(config),
+        }
+    }
+
+    /// Render the graph in the given syntax
+    pub fn render(&self, style: GraphStyle) -> String {
+        match style {
+            GraphStyle::Mermaid => self.mermaid(),
+            GraphStyle::Dot => self.dot(),
+        }
+    }
+
+    /// Render the graph as a Mermaid flowchart
+    pub fn mermaid(&self) -> String {
+        let (nodes, edges) = self.build();
+        let mut output = String::new();
+
+        writeln!(output, "flowchart LR").unwrap();
+        for node in &nodes {
+            let label = node.label.replace('"', "#quot;").replace('\n', "<br/>");
+            let (open, close) = match node.kind {
+                NodeKind::Internet => ("((", "))"),
+                NodeKind::Proxy => ("[", "]"),
+                NodeKind::Anubis => ("{{", "}}"),
+                NodeKind::Service => ("([", "])"),
+                NodeKind::Network => ("[(", ")]"),
+            };
+            writeln!(output, "    {}{open}\"{label}\"{close}", node.id).unwrap();
+        }
+        for edge in &edges {
+            let arrow = match edge.kind {
+                EdgeKind::Traffic => "-->",
+                EdgeKind::Network => "---",
+                EdgeKind::Dependency => "-.->",
+            };
+            match &edge.label {
+                Some(label) => writeln!(
+                    output,
+                    "    {} {arrow}|\"{}\"| {}",
+                    edge.from,
+                    label.replace('"', "#quot;"),
+                    edge.to
+                ),
+                None => writeln!(output, "    {} {arrow} {}", edge.from, edge.to),
+            }
+            .unwrap();
+        }
+        output
+    }
+
+    /// Render the graph as Graphviz DOT
+    pub fn dot(&self) -> String {
+        let (nodes, edges) = self.build();
+        let mut output = String::new();
+
+        writeln!(
+            output,
+            "digraph {} {{",
+            dot_string(&self.config.project.name)
+        )
+        .unwrap();
+        writeln!(output, "    rankdir=LR;").unwrap();
+        for node in &nodes {
+            let shape = match node.kind {
+                NodeKind::Internet => "ellipse",
+                NodeKind::Proxy => "box",
+                NodeKind::Anubis => "hexagon",
+                NodeKind::Service => "component",
+                NodeKind::Network => "cylinder",
+            };
+            writeln!(
+                output,
+                "    {} [label={}, shape={shape}];",
+                node.id,
+                dot_string(&node.label)
+            )
+            .unwrap();
+        }
+        for edge in &edges {
+            let mut attributes = Vec::new();
+            if let Some(label) = &edge.label {
+                attributes.push(format!("label={}", dot_string(label)));
+            }
+            match edge.kind {
+                EdgeKind::Traffic => {}
+                EdgeKind::Network => attributes.push("dir=none, style=dotted".to_string()),
+                EdgeKind::Dependency => attributes.push("style=dashed".to_string()),
+            }
+            if attributes.is_empty() {
+                writeln!(output, "    {} -> {};", edge.from, edge.to).unwrap();
+            } else {
+                writeln!(
+                    output,
+                    "    {} -> {} [{}];",
+                    edge.from,
+                    edge.to,
+                    attributes.join(", ")
+                )
+                .unwrap();
+            }
+        }
+        writeln!(output, "}}").unwrap();
+        output
+    }
+
+    /// Proxies that run a container, ordered by layer
+    fn proxies(&self) -> Vec<&'a ProxyConfig> {
+        let mut proxies: Vec<&ProxyConfig> = self
+            .config
+            .proxies
+            .iter()
+            .filter(|proxy| self.compose.includes_proxy(proxy))
+            .collect();
+        proxies.sort_by_key(|proxy| proxy.layer.unwrap_or(1));
+        proxies
+    }
+
+    /// Collect the nodes and edges of the topology
+    fn build(&self) -> (Vec<Node>, Vec<Edge>) {
+        let mut nodes = vec![Node {
+            id: "internet".to_string(),
+            label: "Internet".to_string(),
+            kind: NodeKind::Internet,
+        }];
+        let mut edges = Vec::new();
+        let mut networks = BTreeSet::new();
+        let traffic = |from: &str, to: &str, label: Option<String>| Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            label,
+            kind: EdgeKind::Traffic,
+        };
+
+        let proxies = self.proxies();
+        let layers: BTreeSet<u8> = proxies
+            .iter()
+            .map(|proxy| proxy.layer.unwrap_or(1))
+            .collect();
+        let anubis = self.config.anubis.enabled;
+
+        for proxy in &proxies {
+            let id = node_id("proxy", &proxy.name);
+            let layer = proxy.layer.unwrap_or(1);
+            let instances = self.compose.proxy_services(proxy).len();
+            let mut label = format!(
+                "{}\nlayer {layer} {}",
+                proxy.name,
+                proxy.proxy_type.as_str()
+            );
+            if instances > 1 {
+                write!(label, " x{instances}").unwrap();
+            }
+            nodes.push(Node {
+                id: id.clone(),
+                label,
+                kind: NodeKind::Proxy,
+            });
+
+            if let Some(port) = proxy.external_port {
+                edges.push(traffic("internet", &id, Some(format!(":{port}"))));
+            }
+            // Anubis challenges everything the first layer forwards
+            if self.config.sends_to_anubis(proxy) {
+                for container in self.config.anubis.containers() {
+                    edges.push(traffic(&id, &anubis_node_id(&container), None));
+                }
+            } else if let Some(next) = layers.range(layer + 1..).next() {
+                for target in proxies
+                    .iter()
+                    .filter(|target| target.layer.unwrap_or(1) == *next)
+                {
+                    edges.push(traffic(&id, &node_id("proxy", &target.name), None));
+                }
+            }
+            for network in self.networks.proxy_networks(proxy) {
+                edges.push(network_edge(&id, &network));
+                networks.insert(network);
+            }
+        }
+
+        if anubis {
+            for container in self.config.anubis.containers() {
+                let id = anubis_node_id(&container);
+                let mut label = format!("Anubis\ndifficulty {}", container.difficulty);
+                if !container.domains.is_empty() {
+                    write!(label, "\n{}", container.domains.join(", ")).unwrap();
+                }
+                nodes.push(Node {
+                    id: id.clone(),
+                    label,
+                    kind: NodeKind::Anubis,
+                });
+                // The target names the proxy that receives passed challenges
+                let target = container
+                    .target
+                    .split("://")
+                    .last()
+                    .and_then(|address| address.split(':').next())
+                    .unwrap_or_default();
+                let named = proxies.iter().any(|proxy| proxy.name == target);
+                let next_layer = layers.range(2..).next().copied();
+                for proxy in proxies.iter().filter(|proxy| match named {
+                    true => proxy.name == target,
+                    false => proxy.layer.or(Some(1)) == next_layer,
+                }) {
+                    edges.push(traffic(
+                        &id,
+                        &node_id("proxy", &proxy.name),
+                        Some("passed".to_string()),
+                    ));
+                }
+                for network in self.networks.anubis_networks() {
+                    edges.push(network_edge(&id, &network));
+                    networks.insert(network);
+                }
+            }
+        }
+
+        // HTTP services are routed by the innermost layer, streams by the edge
+        let last_layer = layers.last().copied();
+        for service in &self.config.services {
+            let id = node_id("service", &service.name);
+            let label = match service.protocol.is_stream() {
+                true => format!(
+                    "{}\n{}:{}",
+                    service.name,
+                    service.protocol.as_str(),
+                    service.listen_port.unwrap_or_default()
+                ),
+                false => format!("{}\n{}", service.name, service.hosts().join(", ")),
+            };
+            nodes.push(Node {
+                id: id.clone(),
+                label,
+                kind: NodeKind::Service,
+            });
+
+            for proxy in &proxies {
+                let routes = match service.protocol.is_stream() {
+                    true => proxy.external_port.is_some(),
+                    false => Some(proxy.layer.unwrap_or(1)) == last_layer,
+                };
+                if routes {
+                    edges.push(traffic(&node_id("proxy", &proxy.name), &id, None));
+                }
+            }
+            if self.compose.includes_service(service) {
+                for network in self.networks.backend_networks() {
+                    edges.push(network_edge(&id, &network));
+                    networks.insert(network);
+                }
+            }
+
+            let dependencies: Vec<&String> = match &service.depends_on {
+                Some(DependsOn::Simple(names)) => names.iter().collect(),
+                Some(DependsOn::Detailed(conditions)) => {
+                    let mut names: Vec<&String> = conditions.keys().collect();
+                    names.sort();
+                    names
+                }
+                None => Vec::new(),
+            };
+            for dependency in dependencies {
+                edges.push(Edge {
+                    from: id.clone(),
+                    to: node_id("service", dependency),
+                    label: Some("depends on".to_string()),
+                    kind: EdgeKind::Dependency,
+                });
+            }
+        }
+
+        for network in networks {
+            nodes.push(Node {
+                id: node_id("network", &network),
+                label: self.networks.docker_name(&network),
+                kind: NodeKind::Network,
+            });
+        }
+
+        (nodes, edges)
+    }
+}
+
+/// Edge attaching a node to a network
+fn network_edge(from: &str, network: &str) -> Edge {
+    Edge {
+        from: from.to_string(),
+        to: node_id("network", network),
+        label: None,
+        kind: EdgeKind::Network,
+    }
+}
+
+/// Identifier valid in both DOT and Mermaid
+/// Node id of an Anubis container, `anubis` for the global one
+fn anubis_node_id(container: &AnubisContainer) -> String {
+    if container.domains.is_empty() {
+        "anubis".to_string()
+    } else {
+        node_id("anubis", &container.name)
+    }
+}
+
+fn node_id(prefix: &str, name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{prefix}_{name}")
+}
+
+/// Quoted DOT string
+fn dot_string(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,120 @@
+//! # Tests for topology graph generation
+//!
+//! These tests verify that both renderings follow requests from the internet
+//! through each layer and Anubis to the services, and show networks and
+//! dependencies.
+
+use super::*;
+use crate::test_support::parse_config;
+
+/// Helper function to create a protected two-layer stack
+fn create_anubis_config() -> Config {
+    parse_config(
+        r#"
+[project]
+name = "graph-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+
+[[proxies]]
+name = "proxy-2"
+type = "caddy"
+layer = 2
+
+[anubis]
+enabled = true
+target = "http://proxy-2:80"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+image = "example/web:1.0"
+depends_on = ["db"]
+
+[[services]]
+name = "db"
+domain = "db.example.com"
+upstream = "http://db:5432"
+image = "postgres:16"
+"#,
+    )
+}
+
+#[test]
+fn test_mermaid_follows_layers_through_anubis() {
+    let config = create_anubis_config();
+    let graph = GraphGenerator::new(&config).mermaid();
+
+    assert!(graph.starts_with("flowchart LR\n"));
+    assert!(graph.contains("    internet((\"Internet\"))\n"));
+    assert!(graph.contains("    proxy_proxy[\"proxy<br/>layer 1 nginx\"]\n"));
+    assert!(graph.contains("    anubis{{\"Anubis<br/>difficulty 5\"}}\n"));
+    assert!(graph.contains("    service_web_app([\"web-app<br/>app.example.com\"])\n"));
+    assert!(graph.contains("    internet -->|\":80\"| proxy_proxy\n"));
+    assert!(graph.contains("    proxy_proxy --> anubis\n"));
+    assert!(graph.contains("    anubis -->|\"passed\"| proxy_proxy_2\n"));
+    assert!(graph.contains("    proxy_proxy_2 --> service_web_app\n"));
+    // Nothing reaches the inner layer without the challenge
+    assert!(!graph.contains("proxy_proxy --> proxy_proxy_2"));
+    assert!(!graph.contains("proxy_proxy --> service_web_app"));
+}
+
+#[test]
+fn test_graph_shows_networks_and_dependencies() {
+    let config = create_anubis_config();
+    let generator = GraphGenerator::new(&config);
+
+    let mermaid = generator.mermaid();
+    assert!(mermaid.contains("    network_back_net[(\"graph-test-back\")]\n"));
+    assert!(mermaid.contains("    anubis --- network_front_net\n"));
+    assert!(mermaid.contains("    service_web_app --- network_back_net\n"));
+    assert!(mermaid.contains("    service_web_app -.->|\"depends on\"| service_db\n"));
+
+    let dot = generator.dot();
+    assert!(dot.starts_with("digraph \"graph-test\" {\n    rankdir=LR;\n"));
+    assert!(dot.contains("    proxy_proxy [label=\"proxy\\nlayer 1 nginx\", shape=box];\n"));
+    assert!(dot.contains("    internet -> proxy_proxy [label=\":80\"];\n"));
+    assert!(dot.contains("    proxy_proxy -> anubis;\n"));
+    assert!(dot.contains("    service_web_app -> network_back_net [dir=none, style=dotted];\n"));
+    assert!(
+        dot.contains("    service_web_app -> service_db [label=\"depends on\", style=dashed];\n")
+    );
+    assert!(dot.ends_with("}\n"));
+}
+
+#[test]
+fn test_graph_without_anubis_skips_layer_one_nginx() {
+    let config = parse_config(
+        r#"
+[project]
+name = "simple"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+layer = 2
+external_port = 8080
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+"#,
+    );
+    let graph = GraphGenerator::new(&config).render(GraphStyle::Mermaid);
+
+    // Layer-1 nginx has no container without Anubis
+    assert!(!graph.contains("proxy_proxy["));
+    assert!(!graph.contains("anubis"));
+    assert!(graph.contains("    internet -->|\":8080\"| proxy_proxy_2\n"));
+    assert!(graph.contains("    proxy_proxy_2 --> service_web_app\n"));
+}
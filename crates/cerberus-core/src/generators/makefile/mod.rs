@@ -0,0 +1,379 @@
+//! # Makefile generator
+//!
+//! Generates a `Makefile` (or a `justfile` with `project.task_runner =
+//! "just"`) next to `docker-compose.yaml` with the day-to-day operations of
+//! the stack: `up`, `down`, `validate`, `reload-<proxy>`, `cert-renew`,
+//! `logs-<service>`, and `scale-<proxy> n=3`. The targets are written for
+//! the concrete services of this deployment, so hosts without the cerberus
+//! binary can still be operated with `make` (or `just`) and Docker alone.
+
+use super::DockerComposeGenerator;
+use super::runbook::reload_commands;
+use crate::config::{Config, ProxyConfig, TaskRunner};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Generator for the operations Makefile
+pub struct MakefileGenerator<'a> {
+    config: &'a Config,
+    compose: DockerComposeGenerator<'a>,
+    runner: TaskRunner,
+}
+
+impl<'a> MakefileGenerator<'a> {
+    /// Create a new Makefile generator
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            config,
+            compose: DockerComposeGenerator::new(config),
+            runner: config.project.task_runner,
+        }
+    }
+
+    /// Files to write, relative to the output directory
+    pub fn files(&self) -> Vec<(PathBuf, String)> {
+        vec![(PathBuf::from(self.runner.file_name()), self.generate())]
+    }
+
+    /// Generate the Makefile, or the justfile for `just`
+    pub fn generate(&self) -> String {
+        let proxies: Vec<&ProxyConfig> = self
+            .config
+            .proxies
+            .iter()
+            .filter(|proxy| self.compose.includes_proxy(proxy))
+            .collect();
+        let services = self.logged_services(&proxies);
+
+        let mut makefile = String::new();
+        writeln!(makefile, "# Operations for {}", self.config.project.name).unwrap();
+        match self.runner {
+            TaskRunner::Make => {
+                let mut targets = vec!["help", "up", "down", "ps", "logs", "reload"]
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect::<Vec<_>>();
+                targets.extend(proxies.iter().map(|proxy| format!("reload-{}", proxy.name)));
+                targets.extend(proxies.iter().map(|proxy| format!("scale-{}", proxy.name)));
+                targets.extend(services.iter().map(|service| format!("logs-{service}")));
+                targets.extend(["validate".to_string(), "cert-renew".to_string()]);
+
+                writeln!(
+                    makefile,
+                    "# Generated by Cerberus Rust edition. Run `make help` from this directory."
+                )
+                .unwrap();
+                writeln!(makefile).unwrap();
+                writeln!(makefile, "COMPOSE ?= docker compose").unwrap();
+                writeln!(makefile).unwrap();
+                writeln!(makefile, ".DEFAULT_GOAL := help").unwrap();
+                writeln!(makefile, ".PHONY: {}", targets.join(" ")).unwrap();
+                writeln!(makefile).unwrap();
+
+                self.write_help(&mut makefile, &proxies, &services);
+            }
+            TaskRunner::Just => {
+                writeln!(
+                    makefile,
+                    "# Generated by Cerberus Rust edition. Run `just` from this directory."
+                )
+                .unwrap();
+                writeln!(makefile).unwrap();
+                writeln!(
+                    makefile,
+                    "compose := env_var_or_default(\"COMPOSE\", \"docker compose\")"
+                )
+                .unwrap();
+                writeln!(makefile).unwrap();
+                // just runs the first recipe without arguments
+                self.write_recipe(&mut makefile, "help", None, &[], "List the recipes");
+                writeln!(makefile, "    @just --list --unsorted").unwrap();
+                writeln!(makefile).unwrap();
+            }
+        }
+
+        self.write_lifecycle(&mut makefile);
+        self.write_validate(&mut makefile, &proxies);
+        self.write_reload(&mut makefile, &proxies);
+        self.write_cert_renew(&mut makefile, &proxies);
+        self.write_scale(&mut makefile, &proxies);
+        self.write_logs(&mut makefile, &services);
+
+        makefile
+    }
+
+    /// Compose services with their own logs target
+    fn logged_services(&self, proxies: &[&ProxyConfig]) -> Vec<String> {
+        let mut services: Vec<String> = proxies
+            .iter()
+            .flat_map(|proxy| self.compose.proxy_services(proxy))
+            .collect();
+        if self.config.anubis.enabled {
+            services.extend(
+                self.config
+                    .anubis
+                    .containers()
+                    .into_iter()
+                    .map(|container| container.name),
+            );
+        }
+        services.extend(
+            self.config
+                .services
+                .iter()
+                .filter(|service| self.compose.includes_service(service))
+                .map(|service| service.name.clone()),
+        );
+        services
+    }
+
+    /// Write the first line of a target; just lists the description
+    fn write_recipe(
+        &self,
+        makefile: &mut String,
+        name: &str,
+        parameter: Option<&str>,
+        dependencies: &[String],
+        description: &str,
+    ) {
+        let mut line = name.to_string();
+        if let (TaskRunner::Just, Some(parameter)) = (self.runner, parameter) {
+            line = format!("{line} {parameter}");
+        }
+        line.push(':');
+        if !dependencies.is_empty() {
+            line = format!("{line} {}", dependencies.join(" "));
+        }
+        if self.runner == TaskRunner::Just {
+            writeln!(makefile, "# {description}").unwrap();
+        }
+        writeln!(makefile, "{line}").unwrap();
+    }
+
+    /// Write a recipe line running a shell command
+    fn write_command(&self, makefile: &mut String, command: &str) {
+        match self.runner {
+            TaskRunner::Make => writeln!(makefile, "\t{command}").unwrap(),
+            TaskRunner::Just => writeln!(makefile, "    {command}").unwrap(),
+        }
+    }
+
+    /// Write a recipe line running `docker compose`
+    fn write_compose(&self, makefile: &mut String, arguments: &str) {
+        let compose = match self.runner {
+            TaskRunner::Make => "$(COMPOSE)",
+            TaskRunner::Just => "{{compose}}",
+        };
+        self.write_command(makefile, &format!("{compose} {arguments}"));
+    }
+
+    /// Reference to a recipe parameter
+    fn parameter(&self, name: &str) -> String {
+        match self.runner {
+            TaskRunner::Make => format!("$({name})"),
+            TaskRunner::Just => format!("{{{{{name}}}}}"),
+        }
+    }
+
+    fn write_help(&self, makefile: &mut String, proxies: &[&ProxyConfig], services: &[String]) {
+        let mut lines = vec![
+            ("up", "Start or update the whole stack".to_string()),
+            ("down", "Stop and remove the stack".to_string()),
+            ("ps", "Show container status".to_string()),
+            ("logs", "Follow the logs of every service".to_string()),
+            (
+                "validate",
+                "Check the Compose file and every proxy's configuration".to_string(),
+            ),
+            ("reload", "Reload every proxy".to_string()),
+            (
+                "cert-renew",
+                "Renew due certificates and load them into the proxies".to_string(),
+            ),
+        ];
+        let proxy_names = proxies
+            .iter()
+            .map(|proxy| proxy.name.as_str())
+            .collect::<Vec<_>>()
+            .join("|");
+        if !proxies.is_empty() {
+            lines.push((
+                "reload-<proxy>",
+                format!("Reload one proxy ({proxy_names})"),
+            ));
+            lines.push((
+                "scale-<proxy> n=N",
+                format!("Run N containers of a replicated proxy ({proxy_names})"),
+            ));
+        }
+        if !services.is_empty() {
+            lines.push((
+                "logs-<service>",
+                format!("Follow one service ({})", services.join("|")),
+            ));
+        }
+
+        writeln!(makefile, "help:").unwrap();
+        for (target, description) in lines {
+            writeln!(makefile, "\t@echo \"  {target:<18} {description}\"").unwrap();
+        }
+        writeln!(makefile).unwrap();
+    }
+
+    fn write_lifecycle(&self, makefile: &mut String) {
+        for (target, arguments, description) in [
+            ("up", "up -d", "Start or update the whole stack"),
+            ("down", "down", "Stop and remove the stack"),
+            ("ps", "ps", "Show container status"),
+            ("logs", "logs -f", "Follow the logs of every service"),
+        ] {
+            self.write_recipe(makefile, target, None, &[], description);
+            self.write_compose(makefile, arguments);
+            writeln!(makefile).unwrap();
+        }
+    }
+
+    /// Check the Compose file, then let each running proxy parse its files
+    ///
+    /// Traefik has no check command; it reports dynamic files it cannot
+    /// load in its log and keeps the last valid routes.
+    fn write_validate(&self, makefile: &mut String, proxies: &[&ProxyConfig]) {
+        self.write_recipe(
+            makefile,
+            "validate",
+            None,
+            &[],
+            "Check the Compose file and every proxy's configuration",
+        );
+        self.write_compose(makefile, "config --quiet");
+        for proxy in proxies {
+            if let Some(command) = proxy.proxy_type.check_command() {
+                self.write_compose(makefile, &format!("exec {} {command}", proxy.name));
+            }
+        }
+        writeln!(makefile).unwrap();
+    }
+
+    fn write_reload(&self, makefile: &mut String, proxies: &[&ProxyConfig]) {
+        let reload_targets: Vec<String> = proxies
+            .iter()
+            .map(|proxy| format!("reload-{}", proxy.name))
+            .collect();
+        self.write_recipe(
+            makefile,
+            "reload",
+            None,
+            &reload_targets,
+            "Reload every proxy",
+        );
+        writeln!(makefile).unwrap();
+
+        for proxy in proxies {
+            self.write_recipe(
+                makefile,
+                &format!("reload-{}", proxy.name),
+                None,
+                &[],
+                &format!("Reload {}", proxy.name),
+            );
+            let services = self.compose.proxy_services(proxy);
+            for command in reload_commands(proxy, &services) {
+                self.write_compose(makefile, &command);
+            }
+            writeln!(makefile).unwrap();
+        }
+    }
+
+    /// Renew certificates and load them
+    ///
+    /// Caddy and Traefik renew their ACME certificates themselves and check
+    /// which are due when they start, so those proxies are restarted.
+    /// Certificate files from `[tls]` are renewed outside the stack; the
+    /// proxies only need a reload to pick them up.
+    fn write_cert_renew(&self, makefile: &mut String, proxies: &[&ProxyConfig]) {
+        let tls = &self.config.tls;
+        let files = tls.enabled && !tls.certificates.is_empty();
+        let acme: Vec<&&ProxyConfig> = proxies
+            .iter()
+            .filter(|proxy| self.config.acme(proxy).is_some())
+            .collect();
+        let dependencies = if files {
+            vec!["reload".to_string()]
+        } else {
+            Vec::new()
+        };
+
+        self.write_recipe(
+            makefile,
+            "cert-renew",
+            None,
+            &dependencies,
+            "Renew due certificates and load them into the proxies",
+        );
+        for proxy in &acme {
+            self.write_compose(makefile, &format!("restart {}", proxy.name));
+        }
+        if acme.is_empty() && !files {
+            self.write_command(
+                makefile,
+                "@echo \"No certificates are configured; TLS terminates in front of this stack.\"",
+            );
+        }
+        writeln!(makefile).unwrap();
+    }
+
+    fn write_scale(&self, makefile: &mut String, proxies: &[&ProxyConfig]) {
+        for proxy in proxies {
+            let name = format!("scale-{}", proxy.name);
+            let description = format!("Run n containers of {}", proxy.name);
+            // Fixed container names cannot be scaled by Compose
+            if proxy.deploy_replicas().is_none() {
+                self.write_recipe(makefile, &name, None, &[], &description);
+                self.write_command(
+                    makefile,
+                    &format!(
+                        "@echo \"{} runs fixed containers; set deploy.replicas in config.toml and regenerate\" >&2",
+                        proxy.name
+                    ),
+                );
+                self.write_command(makefile, "@exit 1");
+                writeln!(makefile).unwrap();
+                continue;
+            }
+            self.write_recipe(makefile, &name, Some("n"), &[], &description);
+            // just refuses to run the recipe without its parameter
+            if self.runner == TaskRunner::Make {
+                self.write_command(
+                    makefile,
+                    &format!("$(if $(n),,$(error Usage: make {name} n=<containers>))"),
+                );
+            }
+            self.write_compose(
+                makefile,
+                &format!(
+                    "up -d --no-deps --no-recreate --scale {name}={n} {name}",
+                    name = proxy.name,
+                    n = self.parameter("n")
+                ),
+            );
+            writeln!(makefile).unwrap();
+        }
+    }
+
+    fn write_logs(&self, makefile: &mut String, services: &[String]) {
+        for service in services {
+            self.write_recipe(
+                makefile,
+                &format!("logs-{service}"),
+                None,
+                &[],
+                &format!("Follow the logs of {service}"),
+            );
+            self.write_compose(makefile, &format!("logs -f {service}"));
+            writeln!(makefile).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
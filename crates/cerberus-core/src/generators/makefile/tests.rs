@@ -0,0 +1,136 @@
+//! # Tests for Makefile generation
+//!
+//! These tests verify that the Makefile targets use the real Compose
+//! service names and the right reload and scaling commands for each proxy.
+
+use super::*;
+use crate::test_support::parse_config;
+
+/// Helper function to create a stack with a replicated edge and a scaled inner layer
+fn create_stack_config() -> Config {
+    parse_config(
+        r#"
+[project]
+name = "makefile-test"
+scaling = true
+
+[[proxies]]
+name = "edge"
+type = "haproxy"
+external_port = 80
+
+[proxies.deploy]
+replicas = 2
+
+[[proxies]]
+name = "inner"
+type = "nginx"
+layer = 2
+instances = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+image = "example/web:1.0"
+"#,
+    )
+}
+
+#[test]
+fn test_makefile_lifecycle_and_logs_targets() {
+    let makefile = MakefileGenerator::new(&create_stack_config()).generate();
+
+    assert!(makefile.starts_with("# Operations for makefile-test\n"));
+    assert!(makefile.contains("COMPOSE ?= docker compose\n"));
+    assert!(makefile.contains("up:\n\t$(COMPOSE) up -d\n"));
+    assert!(makefile.contains("down:\n\t$(COMPOSE) down\n"));
+    assert!(makefile.contains("logs-inner-2:\n\t$(COMPOSE) logs -f inner-2\n"));
+    assert!(makefile.contains("logs-web-app:\n\t$(COMPOSE) logs -f web-app\n"));
+    assert!(makefile.contains(".PHONY: help up down ps logs reload reload-edge reload-inner"));
+    // Recipes must be indented with tabs
+    assert!(!makefile.contains("\n    $(COMPOSE)"));
+}
+
+#[test]
+fn test_makefile_reload_targets() {
+    let makefile = MakefileGenerator::new(&create_stack_config()).generate();
+
+    assert!(makefile.contains("reload: reload-edge reload-inner\n"));
+    assert!(makefile.contains("reload-edge:\n\t$(COMPOSE) kill -s USR2 edge\n"));
+    assert!(makefile.contains(
+        "reload-inner:\n\t$(COMPOSE) exec inner nginx -t\n\t$(COMPOSE) kill -s HUP inner\n\t$(COMPOSE) kill -s HUP inner-2\n"
+    ));
+}
+
+#[test]
+fn test_makefile_scale_targets() {
+    let makefile = MakefileGenerator::new(&create_stack_config()).generate();
+
+    assert!(makefile.contains(
+        "scale-edge:\n\t$(if $(n),,$(error Usage: make scale-edge n=<containers>))\n\t$(COMPOSE) up -d --no-deps --no-recreate --scale edge=$(n) edge\n"
+    ));
+    // Instances are separately named containers
+    assert!(makefile.contains("scale-inner:\n\t@echo \"inner runs fixed containers;"));
+}
+
+#[test]
+fn test_makefile_validate_and_cert_renew_targets() {
+    let makefile = MakefileGenerator::new(&create_stack_config()).generate();
+
+    assert!(makefile.contains(
+        "validate:\n\t$(COMPOSE) config --quiet\n\t$(COMPOSE) exec edge haproxy -c -q -f /usr/local/etc/haproxy/haproxy.cfg\n\t$(COMPOSE) exec inner nginx -t -q\n"
+    ));
+    assert!(makefile.contains(
+        "cert-renew:\n\t@echo \"No certificates are configured; TLS terminates in front of this stack.\"\n"
+    ));
+
+    // ACME proxies are restarted to renew due certificates
+    let config = parse_config(
+        r#"
+[project]
+name = "makefile-test"
+
+[[proxies]]
+name = "edge"
+type = "caddy"
+external_port = 80
+
+[secrets.cloudflare_token]
+file = "./secrets/cloudflare_token.txt"
+
+[tls.acme]
+email = "ops@example.com"
+provider = "cloudflare"
+domains = ["example.com"]
+credentials = { CF_DNS_API_TOKEN = "cloudflare_token" }
+"#,
+    );
+    let makefile = MakefileGenerator::new(&config).generate();
+    assert!(makefile.contains("cert-renew:\n\t$(COMPOSE) restart edge\n\n"));
+    assert!(makefile.contains(
+        "\t$(COMPOSE) exec edge caddy validate --config /etc/caddy/Caddyfile --adapter caddyfile\n"
+    ));
+}
+
+#[test]
+fn test_justfile() {
+    let mut config = create_stack_config();
+    config.project.task_runner = TaskRunner::Just;
+    let generator = MakefileGenerator::new(&config);
+    let files = generator.files();
+    assert_eq!(files[0].0, PathBuf::from("justfile"));
+    let justfile = &files[0].1;
+
+    assert!(justfile.contains("compose := env_var_or_default(\"COMPOSE\", \"docker compose\")\n"));
+    assert!(justfile.contains("# List the recipes\nhelp:\n    @just --list --unsorted\n"));
+    assert!(justfile.contains("# Start or update the whole stack\nup:\n    {{compose}} up -d\n"));
+    assert!(justfile.contains("reload: reload-edge reload-inner\n"));
+    assert!(justfile.contains(
+        "# Run n containers of edge\nscale-edge n:\n    {{compose}} up -d --no-deps --no-recreate --scale edge={{n}} edge\n"
+    ));
+    assert!(justfile.contains("validate:\n    {{compose}} config --quiet\n"));
+    // No make syntax leaks into the justfile
+    assert!(!justfile.contains("$("));
+    assert!(!justfile.contains('\t'));
+}
@@ -0,0 +1,110 @@
+//! Checksum manifest of generated files
+//!
+//! Each generation records the SHA-256 of every file it wrote in
+//! `.cerberus-manifest.json` inside the output directory. Before the next
+//! generation replaces the directory, tracked files are compared against the
+//! manifest so that edits made by hand are not silently overwritten.
+
+use crate::{CerberusError, Result, checksum::sha256_hex};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File name of the manifest inside the output directory
+pub const MANIFEST_FILE: &str = ".cerberus-manifest.json";
+
+/// Checksums of the files written by the last generation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct Manifest {
+    /// SHA-256 of each file, keyed by its path relative to the output directory
+    pub files: BTreeMap<PathBuf, String>,
+}
+
+impl Manifest {
+    /// Manifest for rendered files
+    pub fn from_files(files: &BTreeMap<PathBuf, Vec<u8>>) -> Self {
+        Self {
+            files: files
+                .iter()
+                .map(|(path, content)| (path.clone(), sha256_hex(content)))
+                .collect(),
+        }
+    }
+
+    /// Load the manifest of an output directory, if it has one
+    ///
+    /// # Errors
+    /// Returns error if the manifest exists but cannot be read or parsed
+    pub fn load(output_dir: &Path) -> Result<Option<Self>> {
+        let path = output_dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read(&path).map_err(|e| CerberusError::io(&path, e))?;
+        serde_json::from_slice(&content)
+            .map(Some)
+            .map_err(|e| CerberusError::config(format!("Invalid manifest {}: {e}", path.display())))
+    }
+
+    /// Write the manifest into an output directory
+    ///
+    /// # Errors
+    /// Returns error if the manifest cannot be written
+    pub fn write(&self, output_dir: &Path) -> Result<()> {
+        let path = output_dir.join(MANIFEST_FILE);
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, content).map_err(|e| CerberusError::io(&path, e))
+    }
+
+    /// Tracked files whose content changed since they were generated
+    ///
+    /// Deleted files are not reported; regenerating them loses nothing.
+    ///
+    /// # Errors
+    /// Returns error if a tracked file cannot be read
+    pub fn modified_files(&self, output_dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut modified = Vec::new();
+        for (path, checksum) in &self.files {
+            let file_path = output_dir.join(path);
+            if !file_path.exists() {
+                continue;
+            }
+            let content =
+                std::fs::read(&file_path).map_err(|e| CerberusError::io(&file_path, e))?;
+            if sha256_hex(&content) != *checksum {
+                modified.push(path.clone());
+            }
+        }
+        Ok(modified)
+    }
+}
+
+/// Read every generated file under `dir` keyed by its path relative to `dir`
+///
+/// The checksum manifest is bookkeeping, not output, and is left out, as
+/// are resolved secret values.
+pub fn snapshot(dir: &Path) -> Result<BTreeMap<PathBuf, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    if dir.exists() {
+        collect_files(dir, dir, &mut files)?;
+    }
+    Ok(files)
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut BTreeMap<PathBuf, Vec<u8>>) -> Result<()> {
+    for entry in std::fs::read_dir(dir).map_err(|e| CerberusError::io(dir, e))? {
+        let path = entry.map_err(|e| CerberusError::io(dir, e))?.path();
+        if path.is_dir() {
+            // Resolved secret values are not generated files
+            if path != root.join(super::paths::SECRETS_DIR) {
+                collect_files(root, &path, files)?;
+            }
+        } else if path.file_name() != Some(MANIFEST_FILE.as_ref()) {
+            let content = std::fs::read(&path).map_err(|e| CerberusError::io(&path, e))?;
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            files.insert(relative, content);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,526 @@
+//! # Configuration generators for Cerberus
+//!
+//! This module contains generators for various configuration files including
+//! Docker Compose, proxy configurations, Dockerfiles, and Anubis settings.
+//!
+//! ## Available Generators
+//!
+//! - **DockerComposeGenerator**: Generates docker-compose.yaml files
+//! - **NomadGenerator**: Generates Nomad jobspecs (`project.target = "nomad"`)
+//! - **ProxyConfigGenerator**: Generates proxy configuration files (Caddy, Nginx, etc.)
+//! - **DockerfileGenerator**: Generates custom Dockerfiles
+//! - **BakeGenerator**: Generates the buildx bake file for multi-platform images
+//! - **AnubisGenerator**: Generates Anubis DDoS protection policies
+//! - **UpdateScriptGenerator**: Generates automated deployment shell scripts
+//! - **ScheduleGenerator**: Generates the cron job for time-based routing
+//! - **ObservabilityGenerator**: Generates the ROUTES report and monitoring configs
+//! - **CrowdSecGenerator**: Generates CrowdSec log acquisition and bouncer configs
+//! - **ErrorPagesGenerator**: Writes the custom error pages the proxies serve
+//! - **ContentGenerator**: Writes the robots.txt and security.txt the edge serves
+//! - **SecurityProfilesGenerator**: Generates seccomp and AppArmor profiles of the proxies
+//! - **DnsGenerator**: Generates the DNS record plan for the service domains
+//! - **GraphGenerator**: Renders the topology as Graphviz DOT or Mermaid (`cerberus graph`)
+//! - **RunbookGenerator**: Generates the operational runbook for the Compose stack
+//! - **MakefileGenerator**: Generates the operations Makefile for the Compose stack
+//! - **VaultAgentGenerator**: Generates the vault-agent sidecar configuration
+//!
+//! `CerberusGenerator` renders every [`Generator`] of a [`GeneratorRegistry`];
+//! the built-in registry wraps the generators above, and downstream crates
+//! register their own next to them. [`CerberusGenerator::plan`] returns the
+//! rendered files as [`GeneratedArtifact`]s for callers that store them
+//! somewhere other than the output directory. Generators render as the
+//! tasks of a [`TaskGraph`]; with the `concurrent` feature, the
+//! `*_concurrent` methods render independent generators at the same time.
+
+pub mod anubis;
+pub mod artifact;
+pub mod bake;
+pub mod content;
+pub mod crowdsec;
+pub mod dns;
+pub mod docker_compose;
+pub mod dockerfile;
+pub mod error_pages;
+pub mod fragments;
+pub mod graph;
+pub mod makefile;
+pub mod manifest;
+pub mod network;
+pub mod nomad;
+pub mod observability;
+pub mod paths;
+pub mod proxy_config;
+pub mod registry;
+pub mod runbook;
+pub mod schedule;
+pub mod security_profiles;
+pub mod tasks;
+pub mod update_script;
+pub mod vault_agent;
+
+pub use anubis::AnubisGenerator;
+pub use artifact::{ArtifactKind, GeneratedArtifact};
+pub use bake::{BAKE_FILE, BakeGenerator};
+pub use content::ContentGenerator;
+pub use crowdsec::CrowdSecGenerator;
+pub use dns::DnsGenerator;
+pub use docker_compose::{COMPOSE_FILE, DockerComposeGenerator};
+pub use dockerfile::DockerfileGenerator;
+pub use error_pages::ErrorPagesGenerator;
+pub use fragments::FragmentCache;
+pub use graph::{GraphGenerator, GraphStyle};
+pub use makefile::MakefileGenerator;
+pub use manifest::{MANIFEST_FILE, Manifest, snapshot};
+pub use network::NetworkResolver;
+pub use nomad::NomadGenerator;
+pub use observability::ObservabilityGenerator;
+pub use proxy_config::ProxyConfigGenerator;
+pub use registry::{GenerateContext, GeneratedFile, Generator, GeneratorRegistry};
+pub use runbook::RunbookGenerator;
+pub use schedule::ScheduleGenerator;
+pub use security_profiles::SecurityProfilesGenerator;
+pub use tasks::{RenderedFiles, TaskGraph, TaskOutputs};
+pub use update_script::UpdateScriptGenerator;
+pub use vault_agent::VaultAgentGenerator;
+
+use crate::{
+    CerberusError, Result,
+    config::Config,
+    report::{Finding, FindingStatus},
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Master generator that orchestrates all sub-generators
+pub struct CerberusGenerator<'a> {
+    config: &'a Config,
+    output_dir: String,
+    force: bool,
+    vault_agent: bool,
+    fragments: Arc<FragmentCache>,
+    registry: GeneratorRegistry,
+}
+
+impl<'a> CerberusGenerator<'a> {
+    /// Create a new master generator
+    pub fn new(config: &'a Config, output_dir: impl Into<String>) -> Self {
+        Self {
+            config,
+            output_dir: output_dir.into(),
+            force: false,
+            vault_agent: false,
+            fragments: Arc::new(FragmentCache::in_memory()),
+            registry: GeneratorRegistry::builtin(),
+        }
+    }
+
+    /// Overwrite generated files even if they were edited by hand
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Leave `vault://` references to a vault-agent sidecar instead of
+    /// reading Vault at generate time
+    pub fn vault_agent(mut self, vault_agent: bool) -> Self {
+        self.vault_agent = vault_agent;
+        self
+    }
+
+    /// Keep rendered per-service fragments in a directory between runs
+    ///
+    /// Fragments are always reused within one run. With a directory, a
+    /// later generation only renders the fragments whose inputs changed,
+    /// and fragments no longer used are removed after each generation.
+    pub fn fragment_cache(mut self, dir: Option<PathBuf>) -> Self {
+        self.fragments = Arc::new(match dir {
+            Some(dir) => FragmentCache::on_disk(dir),
+            None => FragmentCache::in_memory(),
+        });
+        self
+    }
+
+    /// Render with the generators of a registry instead of the built-in ones
+    pub fn registry(mut self, registry: GeneratorRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Generate all configurations into the output directory
+    ///
+    /// Secret values are not written; the `cerberus` CLI resolves them into
+    /// [`paths::SECRETS_DIR`] afterwards.
+    ///
+    /// # Errors
+    /// Returns error if a file recorded in the manifest was edited since the
+    /// last generation (unless forced), or if any generation step fails
+    pub fn generate_all(&self) -> Result<()> {
+        self.write_all(&self.render()?)
+    }
+
+    /// Generate all configurations into the output directory, rendering
+    /// independent generators concurrently
+    ///
+    /// Writes the same files as [`generate_all`](Self::generate_all).
+    ///
+    /// # Errors
+    /// Returns error if a file recorded in the manifest was edited since the
+    /// last generation (unless forced), or if any generation step fails
+    #[cfg(feature = "concurrent")]
+    pub async fn generate_all_concurrent(&self) -> Result<()> {
+        self.write_all(&self.render_concurrent().await?)
+    }
+
+    /// Replace the output directory with rendered files and their manifest
+    fn write_all(&self, files: &BTreeMap<PathBuf, Vec<u8>>) -> Result<()> {
+        let output_dir = Path::new(&self.output_dir);
+
+        if !self.force
+            && let Some(manifest) = Manifest::load(output_dir)?
+        {
+            let modified = manifest.modified_files(output_dir)?;
+            if !modified.is_empty() {
+                return Err(CerberusError::validation(format!(
+                    "Refusing to overwrite files edited since the last generation: {}; \
+                     rerun with --force to discard the changes",
+                    modified
+                        .iter()
+                        .map(|path| output_dir.join(path).display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )));
+            }
+        }
+
+        // Clean and create output directories
+        self.clean_directories()?;
+        self.create_directories()?;
+
+        // Files are written in path order, whichever generator finished first
+        for (path, content) in files {
+            let file_path = output_dir.join(path);
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&file_path, content)?;
+            set_executable(&file_path)?;
+            tracing::info!("Generated {}", file_path.display());
+        }
+        Manifest::from_files(files).write(output_dir)?;
+        self.fragments.prune()?;
+
+        tracing::info!("All configurations generated successfully");
+        Ok(())
+    }
+
+    /// Render every generated file as an artifact without touching the disk
+    ///
+    /// Artifacts are sorted by path, which is relative to the output
+    /// directory.
+    ///
+    /// # Errors
+    /// Returns error if a generator fails, if two generators write the same
+    /// path, or if generator dependencies are missing or circular
+    pub fn plan(&self) -> Result<Vec<GeneratedArtifact>> {
+        let rendered = TaskGraph::new(&self.registry)?.run(|generator, dependencies| {
+            let context = GenerateContext {
+                output_dir: Path::new(&self.output_dir),
+                vault_agent: self.vault_agent,
+                fragments: &self.fragments,
+                dependencies: &dependencies,
+            };
+            generator.generate(self.config, &context)
+        })?;
+        merge_artifacts(rendered)
+    }
+
+    /// Render every generated file as an artifact, rendering independent
+    /// generators concurrently
+    ///
+    /// Returns the same artifacts as [`plan`](Self::plan).
+    ///
+    /// # Errors
+    /// Returns error if a generator fails, if two generators write the same
+    /// path, or if generator dependencies are missing or circular
+    #[cfg(feature = "concurrent")]
+    pub async fn plan_concurrent(&self) -> Result<Vec<GeneratedArtifact>> {
+        let graph = TaskGraph::new(&self.registry)?;
+        let config = Arc::new(self.config.clone());
+        let output_dir = PathBuf::from(&self.output_dir);
+        let fragments = Arc::clone(&self.fragments);
+        let vault_agent = self.vault_agent;
+        let rendered = graph
+            .run_concurrent(move |generator, dependencies| {
+                let context = GenerateContext {
+                    output_dir: &output_dir,
+                    vault_agent,
+                    fragments: &fragments,
+                    dependencies: &dependencies,
+                };
+                generator.generate(&config, &context)
+            })
+            .await?;
+        merge_artifacts(rendered)
+    }
+
+    /// Render every generated file without touching the disk
+    ///
+    /// Paths are relative to the output directory.
+    pub fn render(&self) -> Result<BTreeMap<PathBuf, Vec<u8>>> {
+        Ok(self
+            .plan()?
+            .into_iter()
+            .map(|artifact| (artifact.path, artifact.content.into_bytes()))
+            .collect())
+    }
+
+    /// Render every generated file concurrently without touching the disk
+    ///
+    /// Returns the same files as [`render`](Self::render).
+    ///
+    /// # Errors
+    /// Returns error if any generation step fails
+    #[cfg(feature = "concurrent")]
+    pub async fn render_concurrent(&self) -> Result<BTreeMap<PathBuf, Vec<u8>>> {
+        Ok(self
+            .plan_concurrent()
+            .await?
+            .into_iter()
+            .map(|artifact| (artifact.path, artifact.content.into_bytes()))
+            .collect())
+    }
+
+    /// Clean output directories
+    fn clean_directories(&self) -> Result<()> {
+        if Path::new(&self.output_dir).exists() {
+            fs::remove_dir_all(&self.output_dir)?;
+            tracing::info!("Cleaned output directory: {}", self.output_dir);
+        }
+        Ok(())
+    }
+
+    /// Create necessary output directories
+    fn create_directories(&self) -> Result<()> {
+        let dirs = [
+            &self.output_dir,
+            &format!("{}/proxy-configs", self.output_dir),
+            &format!("{}/dockerfiles", self.output_dir),
+            &format!("{}/anubis", self.output_dir),
+            &format!("{}/logs", self.output_dir),
+        ];
+
+        for dir in dirs {
+            if !Path::new(dir).exists() {
+                fs::create_dir_all(dir)?;
+                tracing::debug!("Created directory: {}", dir);
+            }
+        }
+
+        // Create subdirectories for each proxy
+        for proxy in &self.config.proxies {
+            let proxy_dir = format!("{}/proxy-configs/{}", self.output_dir, proxy.name);
+            let dockerfile_dir = format!("{}/dockerfiles/{}", self.output_dir, proxy.name);
+
+            fs::create_dir_all(&proxy_dir)?;
+            fs::create_dir_all(&dockerfile_dir)?;
+            tracing::debug!(
+                "Created proxy directories: {}, {}",
+                proxy_dir,
+                dockerfile_dir
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Validate all generated configurations
+    pub fn validate_generated(&self) -> Result<()> {
+        tracing::info!("Validating generated configurations...");
+
+        for finding in self.check_generated()? {
+            match finding.status {
+                FindingStatus::Ok => tracing::info!("{}", finding.message),
+                FindingStatus::Error => {
+                    tracing::error!("{}", finding.message);
+                    return Err(crate::CerberusError::config(finding.message));
+                }
+            }
+        }
+
+        tracing::info!("All generated configurations validated successfully");
+        Ok(())
+    }
+
+    /// Check the syntax of every generated file that can be parsed
+    pub fn check_generated(&self) -> Result<Vec<Finding>> {
+        let mut findings = Vec::new();
+        for (file, label, syntax) in self.syntax_checks() {
+            let path = Path::new(&self.output_dir).join(file);
+            if !path.exists() {
+                continue;
+            }
+
+            let content = fs::read(&path)?;
+            findings.push(check_syntax(path, label, syntax, &content));
+        }
+
+        Ok(findings)
+    }
+
+    /// Check the syntax of rendered files, as returned by [`render`]
+    ///
+    /// Finding paths include the output directory, as for files on disk.
+    ///
+    /// [`render`]: CerberusGenerator::render
+    pub fn check_rendered(&self, files: &BTreeMap<PathBuf, Vec<u8>>) -> Vec<Finding> {
+        self.syntax_checks()
+            .into_iter()
+            .filter_map(|(file, label, syntax)| {
+                let content = files.get(Path::new(&file))?;
+                let path = Path::new(&self.output_dir).join(file);
+                Some(check_syntax(path, label, syntax, content))
+            })
+            .collect()
+    }
+
+    /// Generated files whose syntax can be checked
+    fn syntax_checks(&self) -> Vec<(String, &'static str, Syntax)> {
+        let mut checks = vec![
+            (
+                COMPOSE_FILE.to_string(),
+                "Docker Compose YAML",
+                Syntax::Yaml,
+            ),
+            (
+                docker_compose::STACK_FILE.to_string(),
+                "Docker Swarm stack YAML",
+                Syntax::Yaml,
+            ),
+            (
+                NomadGenerator::new(self.config).file_name(),
+                "Nomad jobspec JSON",
+                Syntax::Json,
+            ),
+        ];
+        checks.extend(
+            self.config
+                .anubis
+                .containers()
+                .into_iter()
+                .map(|container| {
+                    (
+                        format!("{}/botPolicy.json", container.config_dir()),
+                        "Anubis bot policy JSON",
+                        Syntax::Json,
+                    )
+                }),
+        );
+        checks.extend(
+            SecurityProfilesGenerator::new(self.config)
+                .files()
+                .into_iter()
+                .filter(|(path, _)| path.extension().is_some_and(|ext| ext == "json"))
+                .map(|(path, _)| (paths::posix(&path), "Seccomp profile JSON", Syntax::Json)),
+        );
+        checks
+    }
+
+    /// Clean generated files
+    pub fn clean(&self) -> Result<()> {
+        if Path::new(&self.output_dir).exists() {
+            fs::remove_dir_all(&self.output_dir)?;
+            tracing::info!("Cleaned output directory: {}", self.output_dir);
+        }
+        Ok(())
+    }
+}
+
+/// Artifacts of the files rendered by each generator, sorted by path
+///
+/// Generators are visited in registry order, so a path written twice is
+/// reported for the same pair of generators however the tasks were run.
+fn merge_artifacts(rendered: TaskOutputs) -> Result<Vec<GeneratedArtifact>> {
+    let mut artifacts = BTreeMap::new();
+    let mut owners: BTreeMap<PathBuf, &str> = BTreeMap::new();
+    for (generator, files) in &rendered {
+        for (path, content) in files.iter() {
+            if let Some(owner) = owners.insert(path.clone(), generator.name()) {
+                return Err(CerberusError::validation(format!(
+                    "Generators '{owner}' and '{}' both write {}",
+                    generator.name(),
+                    path.display()
+                )));
+            }
+            artifacts.insert(
+                path.clone(),
+                GeneratedArtifact {
+                    path: path.clone(),
+                    kind: generator.kind(),
+                    content: paths::lf(content.clone()),
+                },
+            );
+        }
+    }
+    Ok(artifacts.into_values().collect())
+}
+
+/// Write files relative to an output directory, creating parent directories
+pub(crate) fn write_files(output_dir: &Path, files: Vec<(PathBuf, String)>) -> Result<()> {
+    for (path, content) in files {
+        let file_path = output_dir.join(path);
+        if let Some(parent) = file_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&file_path, paths::lf(content))?;
+        set_executable(&file_path)?;
+    }
+    Ok(())
+}
+
+/// Make generated shell scripts executable
+pub fn set_executable(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    if path.extension().is_some_and(|extension| extension == "sh") {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Parser used to check a generated file
+#[derive(Clone, Copy)]
+enum Syntax {
+    Yaml,
+    Json,
+}
+
+/// Check that a generated file parses
+fn check_syntax(path: PathBuf, label: &str, syntax: Syntax, content: &[u8]) -> Finding {
+    let result = match syntax {
+        Syntax::Yaml => serde_yaml::from_slice::<serde_yaml::Value>(content)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        Syntax::Json => serde_json::from_slice::<serde_json::Value>(content)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+    };
+    match result {
+        Ok(()) => Finding {
+            file: path,
+            status: FindingStatus::Ok,
+            message: format!("{label} is valid"),
+        },
+        Err(e) => Finding {
+            file: path,
+            status: FindingStatus::Error,
+            message: format!("Invalid {label}: {e}"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,65 @@
+//! Network resolver shared by the generators
+//!
+//! Every generator attaches components to the networks the Docker Compose
+//! output declares: the `[networks]` keys from the configuration, or the
+//! default `front-net`/`back-net` topology. This module is the single place
+//! that decides which networks a component joins and the Docker name each
+//! network is created with.
+
+use crate::config::{Config, ProxyConfig};
+
+/// Network every backend component joins
+pub const BACKEND_NETWORK: &str = "back-net";
+
+/// Resolves the networks generated components attach to
+pub struct NetworkResolver<'a> {
+    config: &'a Config,
+}
+
+impl<'a> NetworkResolver<'a> {
+    /// Create a new network resolver
+    pub fn new(config: &'a Config) -> Self {
+        Self { config }
+    }
+
+    /// Compose network keys a proxy joins
+    pub fn proxy_networks(&self, proxy: &ProxyConfig) -> Vec<String> {
+        proxy
+            .network_names()
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Compose network keys Anubis joins
+    pub fn anubis_networks(&self) -> Vec<String> {
+        if self.config.anubis.networks.is_empty() {
+            vec!["front-net".to_string(), BACKEND_NETWORK.to_string()]
+        } else {
+            self.config.anubis.networks.clone()
+        }
+    }
+
+    /// Compose network keys backend services and sidecars join
+    pub fn backend_networks(&self) -> Vec<String> {
+        vec![BACKEND_NETWORK.to_string()]
+    }
+
+    /// Name Docker creates a network with
+    ///
+    /// External networks keep their own name; networks Cerberus creates are
+    /// prefixed with the project name, as in the Compose `networks` section.
+    pub fn docker_name(&self, key: &str) -> String {
+        match self.config.networks.get(key) {
+            Some(network) if network.external => {
+                network.name.clone().unwrap_or_else(|| key.to_string())
+            }
+            Some(_) => format!("{}-{}", self.config.project.name, key),
+            None => match key {
+                "front-net" => format!("{}-front", self.config.project.name),
+                "back-net" => format!("{}-back", self.config.project.name),
+                _ => format!("{}-{}", self.config.project.name, key),
+            },
+        }
+    }
+}
@@ -0,0 +1,447 @@
+//! # Nomad jobspec generator
+//!
+//! Generates a HashiCorp Nomad job (JSON format) from Cerberus configuration.
+//! Each proxy, Anubis, and internal backend service becomes a task group
+//! running on the Docker driver. Tasks join the same Docker networks as the
+//! Compose output and are reachable by their service name through network
+//! aliases, so the generated proxy configs work unchanged.
+
+use super::{
+    AnubisGenerator, DockerComposeGenerator,
+    network::{BACKEND_NETWORK, NetworkResolver},
+    proxy_config::ENTRYPOINT_PATH,
+};
+use crate::{
+    CerberusError, Result,
+    config::{
+        AnubisContainer, CONTAINER_CONTENT_DIR, CONTAINER_ERROR_PAGES_DIR, CONTENT_DIR,
+        CommandConfig, Config, ContainerOptions, ERROR_PAGES_DIR, ProxyConfig, ProxyType,
+        PullPolicy, ServiceConfig, VAULT_SCHEME,
+    },
+};
+use serde_json::{Map, Value, json};
+
+/// Generator for Nomad jobspecs
+pub struct NomadGenerator<'a> {
+    config: &'a Config,
+    compose: DockerComposeGenerator<'a>,
+}
+
+impl<'a> NomadGenerator<'a> {
+    /// Create a new Nomad jobspec generator
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            config,
+            compose: DockerComposeGenerator::new(config),
+        }
+    }
+
+    /// File name of the generated jobspec
+    pub fn file_name(&self) -> String {
+        format!("{}.nomad.json", self.config.project.name)
+    }
+
+    /// Generate the Nomad jobspec as JSON
+    pub fn generate(&self) -> Result<String> {
+        let mut task_groups = Vec::new();
+
+        // Same component selection as the Docker Compose output
+        for (index, proxy) in self.config.proxies.iter().enumerate() {
+            if proxy.layer.unwrap_or(1) == 1
+                && !self.config.anubis.enabled
+                && proxy.proxy_type == ProxyType::Nginx
+            {
+                continue;
+            }
+            task_groups.push(self.proxy_group(proxy, index));
+        }
+
+        if self.config.anubis.enabled {
+            for container in self.config.anubis.containers() {
+                task_groups.push(self.anubis_group(container));
+            }
+        }
+
+        for service in &self.config.services {
+            if self.compose.includes_service(service) {
+                task_groups.push(self.backend_group(service)?);
+            }
+        }
+
+        let mut job = json!({
+            "ID": self.config.project.name,
+            "Name": self.config.project.name,
+            "Type": "service",
+            "Datacenters": self.config.nomad.datacenters,
+            "Meta": {
+                "cerberus.project": self.config.project.name,
+            },
+            "TaskGroups": task_groups,
+        });
+
+        if let Some(region) = &self.config.nomad.region {
+            job["Region"] = json!(region);
+        }
+        if let Some(namespace) = &self.config.nomad.namespace {
+            job["Namespace"] = json!(namespace);
+        }
+
+        Ok(serde_json::to_string_pretty(&json!({ "Job": job }))?)
+    }
+
+    /// Build the task group for a proxy layer
+    fn proxy_group(&self, proxy: &ProxyConfig, index: usize) -> Value {
+        let config_dir = &self.config.nomad.config_dir;
+        let layer = proxy.layer.unwrap_or(0);
+
+        let config_volume = match proxy.proxy_type {
+            ProxyType::Nginx => format!(
+                "{config_dir}/proxy-configs/{}/conf.d:/etc/nginx/conf.d",
+                proxy.name
+            ),
+            _ => format!(
+                "{config_dir}/proxy-configs/{}:{}:ro",
+                proxy.name,
+                self.compose.get_proxy_config_dir(&proxy.proxy_type)
+            ),
+        };
+        let log_path = match proxy.proxy_type {
+            ProxyType::Caddy => "/var/log/caddy",
+            ProxyType::Nginx => "/var/log/nginx",
+            ProxyType::Traefik => "/var/log/traefik",
+            _ => "/var/log/proxy",
+        };
+
+        let count = proxy.container_count(self.config.project.scaling);
+
+        let mut volumes = vec![config_volume, format!("{config_dir}/logs:{log_path}:rw")];
+        if proxy.uses_drain_entrypoint() {
+            volumes.push(format!(
+                "{config_dir}/proxy-configs/{}/entrypoint.sh:{ENTRYPOINT_PATH}:ro",
+                proxy.name
+            ));
+        }
+        if self.config.error_pages.is_enabled() {
+            volumes.push(format!(
+                "{config_dir}/{ERROR_PAGES_DIR}:{CONTAINER_ERROR_PAGES_DIR}:ro"
+            ));
+        }
+        if self.config.serves_content(proxy) {
+            volumes.push(format!(
+                "{config_dir}/{CONTENT_DIR}:{CONTAINER_CONTENT_DIR}:ro"
+            ));
+        }
+
+        let mut docker_config = self.docker_config(
+            self.compose.get_proxy_image(&proxy.proxy_type),
+            &proxy.name,
+            &NetworkResolver::new(self.config).proxy_networks(proxy),
+            volumes,
+            json!({
+                "cerberus.service": "proxy",
+                "cerberus.layer": layer.to_string(),
+                "cerberus.type": proxy.proxy_type.to_string(),
+            }),
+        );
+        if proxy.uses_drain_entrypoint() {
+            docker_config["entrypoint"] = json!(["/bin/sh", ENTRYPOINT_PATH]);
+        } else if let Some(entrypoint) = &proxy.entrypoint {
+            docker_config["entrypoint"] = json!(entrypoint.args());
+        }
+        // The Docker driver splits the command into a binary and its args
+        if let Some((command, args)) = proxy
+            .command
+            .as_ref()
+            .map(CommandConfig::args)
+            .as_deref()
+            .and_then(<[String]>::split_first)
+        {
+            docker_config["command"] = json!(command);
+            docker_config["args"] = json!(args);
+        }
+
+        let mut task = json!({
+            "Name": proxy.name,
+            "Driver": "docker",
+            "Config": docker_config,
+            "Env": {
+                "PROXY_LAYER": layer.to_string(),
+                "UPSTREAM": proxy.default_upstream.as_deref().unwrap_or(""),
+                "MAX_CONNECTIONS": proxy.max_connections.unwrap_or(1024).to_string(),
+            },
+        });
+        for (key, value) in proxy.image_environment() {
+            task["Env"][key] = json!(value);
+        }
+        if proxy.uses_drain_entrypoint() {
+            // The entrypoint translates SIGTERM into the proxy's graceful stop
+            task["KillSignal"] = json!("SIGTERM");
+        }
+        apply_container_options(&mut task, &proxy.container_options(&self.config.defaults));
+
+        self.task_group(
+            &proxy.name,
+            count,
+            task,
+            self.compose
+                .published_port(proxy, index)
+                .map(|port| (port, proxy.internal_port)),
+            vec!["cerberus".to_string(), format!("layer-{layer}")],
+        )
+    }
+
+    /// Build the task group for an Anubis container
+    fn anubis_group(&self, container: AnubisContainer) -> Value {
+        let anubis = &self.config.anubis;
+        let config_dir = &self.config.nomad.config_dir;
+        let name = container.name.clone();
+        let policy_dir = container.config_dir();
+
+        let task = json!({
+            "Name": &name,
+            "Driver": "docker",
+            "Config": self.docker_config(
+                &anubis.image,
+                &name,
+                &NetworkResolver::new(self.config).anubis_networks(),
+                vec![
+                    format!(
+                        "{config_dir}/{policy_dir}/botPolicy.json:{}:ro",
+                        anubis.policy_fname
+                    ),
+                    format!("{config_dir}/logs:/var/log/anubis:rw"),
+                ],
+                json!({
+                    "cerberus.service": "ddos-protection",
+                    "cerberus.layer": "anubis",
+                }),
+            ),
+            "Env": AnubisGenerator::for_container(self.config, container)
+                .environment()
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), Value::String(value)))
+                .collect::<Map<_, _>>(),
+        });
+
+        self.task_group(
+            &name,
+            1,
+            task,
+            None,
+            vec!["cerberus".to_string(), "anubis".to_string()],
+        )
+    }
+
+    /// Build the task group for a backend service
+    ///
+    /// # Errors
+    /// Returns error if the service is only built, as Nomad cannot build images
+    fn backend_group(&self, service: &ServiceConfig) -> Result<Value> {
+        let config_dir = &self.config.nomad.config_dir;
+
+        let (image, volumes, mut env) = if service.has_container() {
+            let image = service.image.as_deref().ok_or_else(|| {
+                CerberusError::validation(format!(
+                    "Service {} has a [services.build] section but no image, \
+                         which Nomad cannot build; push the image and set image instead",
+                    service.name
+                ))
+            })?;
+            (image, service.volumes.clone(), Map::new())
+        } else {
+            let mut env = Map::new();
+            env.insert("SERVICE_NAME".to_string(), json!(service.name));
+            env.insert("DOMAIN".to_string(), json!(service.primary_domain()));
+            env.insert("UPSTREAM".to_string(), json!(service.upstream));
+            (
+                "alpine:latest",
+                vec![
+                    format!("{config_dir}/{}/config:/app/config:ro", service.name),
+                    format!("{config_dir}/{}/data:/app/data:rw", service.name),
+                ],
+                env,
+            )
+        };
+        for (key, value) in &service.environment {
+            if !value.starts_with(VAULT_SCHEME) {
+                env.insert(key.clone(), json!(value));
+            }
+        }
+
+        let mut labels = json!({
+            "cerberus.service": "backend",
+            "cerberus.name": service.name,
+            "cerberus.domain": service.primary_domain(),
+        });
+        if let Some(observability) = &service.observability {
+            for (name, value) in observability.labels() {
+                labels[format!("cerberus.{name}")] = json!(value);
+            }
+        }
+
+        let mut task = json!({
+            "Name": service.name,
+            "Driver": "docker",
+            "Config": self.docker_config(
+                image,
+                &service.name,
+                &NetworkResolver::new(self.config).backend_networks(),
+                volumes,
+                labels,
+            ),
+            "Env": env,
+        });
+
+        apply_container_options(&mut task, &service.container_options(&self.config.defaults));
+
+        // Nomad renders vault:// values itself; references were validated on load
+        let references = service.vault_environment().unwrap_or_default();
+        if !references.is_empty() {
+            let template: String = references
+                .iter()
+                .map(|(key, reference)| format!("{key}={}\n", reference.template()))
+                .collect();
+            task["Vault"] = json!({ "ChangeMode": "restart" });
+            task["Templates"] = json!([{
+                "EmbeddedTmpl": template,
+                "DestPath": "secrets/vault.env",
+                "Envvars": true,
+                "ChangeMode": "restart",
+            }]);
+        }
+
+        Ok(self.task_group(
+            &service.name,
+            service.replicas.unwrap_or(1),
+            task,
+            None,
+            vec!["cerberus".to_string(), "backend".to_string()],
+        ))
+    }
+
+    /// Wrap a task in a task group, optionally publishing a static port
+    fn task_group(
+        &self,
+        name: &str,
+        count: u32,
+        mut task: Value,
+        port: Option<(u16, u16)>,
+        tags: Vec<String>,
+    ) -> Value {
+        let mut group = json!({
+            "Name": name,
+            "Count": count,
+            "RestartPolicy": {
+                "Attempts": 3,
+                "Interval": 300_000_000_000u64,
+                "Delay": 15_000_000_000u64,
+                "Mode": "delay",
+            },
+        });
+
+        if let Some((host_port, container_port)) = port {
+            task["Config"]["ports"] = json!(["http"]);
+            group["Networks"] = json!([{
+                "ReservedPorts": [{
+                    "Label": "http",
+                    "Value": host_port,
+                    "To": container_port,
+                }],
+            }]);
+            group["Services"] = json!([{
+                "Name": name,
+                "PortLabel": "http",
+                "Provider": "nomad",
+                "Tags": tags,
+            }]);
+        }
+
+        if let Some(timezone) = &self.config.project.timezone {
+            // A service's own TZ wins over project.timezone
+            if task["Env"].get("TZ").is_none() {
+                task["Env"]["TZ"] = json!(timezone);
+            }
+            if let Some(volumes) = task["Config"]["volumes"].as_array_mut() {
+                volumes.push(json!("/etc/localtime:/etc/localtime:ro"));
+            }
+        }
+
+        group["Tasks"] = json!([task]);
+        group
+    }
+
+    /// Docker driver configuration shared by all tasks
+    fn docker_config(
+        &self,
+        image: &str,
+        alias: &str,
+        networks: &[String],
+        volumes: Vec<String>,
+        labels: Value,
+    ) -> Value {
+        let mut config = Map::new();
+        config.insert("image".to_string(), json!(image));
+        config.insert(
+            "network_mode".to_string(),
+            json!(self.docker_network(networks)),
+        );
+        config.insert("network_aliases".to_string(), json!([alias]));
+        config.insert("volumes".to_string(), json!(volumes));
+        config.insert("labels".to_string(), labels);
+        Value::Object(config)
+    }
+
+    /// Docker network a task joins
+    ///
+    /// The Docker driver attaches a task to a single network, so the last
+    /// configured network is used. With the default topology this is
+    /// `back-net`, which every component shares.
+    fn docker_network(&self, networks: &[String]) -> String {
+        let name = networks
+            .last()
+            .map(String::as_str)
+            .unwrap_or(BACKEND_NETWORK);
+        NetworkResolver::new(self.config).docker_name(name)
+    }
+}
+
+/// Apply a container's pull policy, platform, and stop grace period to its task
+///
+/// The platform's architecture becomes a constraint, so the task is only
+/// placed on matching clients; a multi-platform image may run on any
+/// architecture of its `platforms`.
+fn apply_container_options(task: &mut Value, options: &ContainerOptions) {
+    if options.pull_policy == Some(PullPolicy::Always) {
+        task["Config"]["force_pull"] = json!(true);
+    }
+    let arch = |platform: &str| platform.split('/').nth(1).map(str::to_string);
+    if let Some(arch) = options.platform.as_deref().and_then(arch) {
+        task["Constraints"] = json!([{
+            "LTarget": "${attr.cpu.arch}",
+            "RTarget": arch,
+            "Operand": "=",
+        }]);
+    } else if !options.platforms.is_empty() {
+        let mut archs: Vec<String> = Vec::new();
+        for arch in options
+            .platforms
+            .iter()
+            .filter_map(|platform| arch(platform))
+        {
+            if !archs.contains(&arch) {
+                archs.push(arch);
+            }
+        }
+        task["Constraints"] = json!([{
+            "LTarget": "${attr.cpu.arch}",
+            "RTarget": format!("^({})$", archs.join("|")),
+            "Operand": "regexp",
+        }]);
+    }
+    if let Some(period) = options.stop_grace_period_secs {
+        task["KillTimeout"] = json!(period * 1_000_000_000);
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,291 @@
+//! # Tests for Nomad jobspec generation
+//!
+//! These tests verify that the Nomad job mirrors the Docker Compose topology:
+//! the same components, networks, published ports, and mounted configs.
+
+use super::*;
+use crate::config::{DeployTarget, NomadConfig};
+use crate::test_support::parse_config;
+
+/// Helper function to generate the jobspec and parse it back
+fn generate_job(config: &Config) -> Value {
+    let content = NomadGenerator::new(config)
+        .generate()
+        .expect("Generation should succeed");
+    let spec: Value = serde_json::from_str(&content).expect("Jobspec should be valid JSON");
+    spec["Job"].clone()
+}
+
+/// Helper function to find a task group by name
+fn find_group<'v>(job: &'v Value, name: &str) -> &'v Value {
+    job["TaskGroups"]
+        .as_array()
+        .expect("TaskGroups should be an array")
+        .iter()
+        .find(|group| group["Name"] == name)
+        .unwrap_or_else(|| panic!("Task group {name} should exist"))
+}
+
+const LAYERED_CONFIG: &str = r#"
+[project]
+name = "edge"
+target = "nomad"
+
+[nomad]
+datacenters = ["tokyo"]
+region = "ap"
+config_dir = "/srv/edge"
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+layer = 1
+default_upstream = "http://anubis:8080"
+
+[[proxies]]
+name = "proxy-2"
+type = "caddy"
+layer = 2
+default_upstream = "http://192.0.2.1:3000"
+
+[[services]]
+name = "web"
+domain = "web.example.com"
+upstream = "http://internal-web:3000"
+"#;
+
+#[test]
+fn test_nomad_target_parsing() {
+    let config = parse_config(LAYERED_CONFIG);
+
+    assert_eq!(config.project.target, DeployTarget::Nomad);
+    assert_eq!(config.nomad.datacenters, vec!["tokyo"]);
+
+    let config = parse_config("[project]\nname = \"compose\"\n");
+    assert_eq!(config.project.target, DeployTarget::Compose);
+    assert_eq!(config.nomad, NomadConfig::default());
+}
+
+#[test]
+fn test_nomad_job_topology() {
+    let config = parse_config(LAYERED_CONFIG);
+    let job = generate_job(&config);
+
+    assert_eq!(job["ID"], "edge");
+    assert_eq!(job["Region"], "ap");
+    assert_eq!(job["Datacenters"], json!(["tokyo"]));
+
+    let names: Vec<&str> = job["TaskGroups"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|group| group["Name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["proxy", "proxy-2", "anubis", "web"]);
+
+    // Every task joins the shared back network under its service name
+    let anubis = &find_group(&job, "anubis")["Tasks"][0];
+    assert_eq!(anubis["Config"]["network_mode"], "edge-back");
+    assert_eq!(anubis["Config"]["network_aliases"], json!(["anubis"]));
+    assert_eq!(anubis["Env"]["TARGET"], config.anubis.target);
+}
+
+#[test]
+fn test_nomad_proxy_ports_and_volumes() {
+    let config = parse_config(LAYERED_CONFIG);
+    let job = generate_job(&config);
+
+    let proxy = find_group(&job, "proxy");
+    assert_eq!(
+        proxy["Networks"][0]["ReservedPorts"][0],
+        json!({"Label": "http", "Value": 80, "To": 80})
+    );
+    assert_eq!(proxy["Services"][0]["Name"], "proxy");
+
+    let task = &proxy["Tasks"][0];
+    assert_eq!(task["Driver"], "docker");
+    assert_eq!(task["Config"]["image"], "nginx:alpine");
+    assert_eq!(task["Config"]["ports"], json!(["http"]));
+    assert_eq!(
+        task["Config"]["volumes"][0],
+        "/srv/edge/proxy-configs/proxy/conf.d:/etc/nginx/conf.d"
+    );
+
+    // Layer 2 behind Anubis is not published
+    let proxy_2 = find_group(&job, "proxy-2");
+    assert!(proxy_2.get("Networks").is_none());
+    assert_eq!(
+        proxy_2["Tasks"][0]["Config"]["volumes"][0],
+        "/srv/edge/proxy-configs/proxy-2:/etc/caddy:ro"
+    );
+}
+
+#[test]
+fn test_nomad_scaling_count() {
+    let config = parse_config(
+        r#"
+[project]
+name = "scaled"
+scaling = true
+target = "nomad"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+instances = 3
+"#,
+    );
+    let job = generate_job(&config);
+
+    assert_eq!(find_group(&job, "proxy")["Count"], 3);
+    assert!(job.get("Region").is_none());
+}
+
+#[test]
+fn test_nomad_output_namespace() {
+    let config = parse_config(
+        r#"
+[project]
+name = "edge"
+
+[outputs.compose]
+path = "built"
+
+[outputs.nomad]
+path = "nomad"
+namespace = "edge"
+"#,
+    );
+    assert_eq!(config.project.target, DeployTarget::Compose);
+
+    let output = &config.outputs[&DeployTarget::Nomad];
+    let job = generate_job(&config.for_output(DeployTarget::Nomad, output));
+    assert_eq!(job["Namespace"], "edge");
+
+    assert!(generate_job(&config).get("Namespace").is_none());
+}
+
+#[test]
+fn test_nomad_proxy_command() {
+    let config = parse_config(
+        r#"
+[project]
+name = "edge"
+target = "nomad"
+
+[[proxies]]
+name = "proxy"
+type = "haproxy"
+external_port = 80
+command = "haproxy -f /usr/local/etc/haproxy/haproxy.cfg -db"
+"#,
+    );
+    let job = generate_job(&config);
+    let task = &find_group(&job, "proxy")["Tasks"][0];
+
+    assert_eq!(task["Config"]["command"], "haproxy");
+    assert_eq!(
+        task["Config"]["args"],
+        json!(["-f", "/usr/local/etc/haproxy/haproxy.cfg", "-db"])
+    );
+    assert!(task["Config"].get("entrypoint").is_none());
+}
+
+#[test]
+fn test_nomad_vault_environment_template() {
+    let config = parse_config(
+        r#"
+[project]
+name = "edge"
+target = "nomad"
+
+[[services]]
+name = "web"
+domain = "web.example.com"
+upstream = "http://web:3000"
+image = "example/web:1.0"
+
+[services.environment]
+LOG_LEVEL = "info"
+DB_PASSWORD = "vault://secret/data/web#db_password"
+"#,
+    );
+    let job = generate_job(&config);
+    let task = &find_group(&job, "web")["Tasks"][0];
+
+    assert_eq!(task["Env"], json!({ "LOG_LEVEL": "info" }));
+    assert_eq!(task["Templates"][0]["Envvars"], true);
+    assert_eq!(
+        task["Templates"][0]["EmbeddedTmpl"],
+        "DB_PASSWORD={{ with secret \"secret/data/web\" }}{{ if .Data.metadata }}{{ index .Data.data \"db_password\" }}{{ else }}{{ index .Data \"db_password\" }}{{ end }}{{ end }}\n"
+    );
+}
+
+#[test]
+fn test_nomad_container_options() {
+    let config = parse_config(
+        &LAYERED_CONFIG
+            .replace(
+                "[anubis]",
+                "[defaults]\nplatform = \"linux/arm64\"\nstop_grace_period = \"20s\"\n\n[anubis]",
+            )
+            .replace(
+                "type = \"caddy\"",
+                "type = \"caddy\"\npull_policy = \"always\"\nplatform = \"linux/amd64\"",
+            ),
+    );
+    let job = generate_job(&config);
+
+    let task = &find_group(&job, "proxy-2")["Tasks"][0];
+    assert_eq!(task["Config"]["force_pull"], true);
+    assert_eq!(task["Constraints"][0]["RTarget"], "amd64");
+    assert_eq!(task["KillTimeout"], 20_000_000_000u64);
+
+    let task = &find_group(&job, "web")["Tasks"][0];
+    assert_eq!(task["Config"]["force_pull"], Value::Null);
+    assert_eq!(task["Constraints"][0]["LTarget"], "${attr.cpu.arch}");
+    assert_eq!(task["Constraints"][0]["RTarget"], "arm64");
+}
+
+#[test]
+fn test_nomad_multi_platform_constraint() {
+    let config = parse_config(&LAYERED_CONFIG.replace(
+        "[anubis]",
+        "[defaults]\nplatforms = [\"linux/amd64\", \"linux/arm/v6\", \"linux/arm/v7\"]\n\n[anubis]",
+    ));
+    let job = generate_job(&config);
+
+    let task = &find_group(&job, "web")["Tasks"][0];
+    assert_eq!(task["Constraints"][0]["Operand"], "regexp");
+    assert_eq!(task["Constraints"][0]["RTarget"], "^(amd64|arm)$");
+}
+
+#[test]
+fn test_nomad_build_needs_image() {
+    let built = LAYERED_CONFIG.replace(
+        "upstream = \"http://internal-web:3000\"",
+        "upstream = \"http://web:3000\"\n\n[services.build]\ncontext = \"./web\"",
+    );
+    let config: Config = toml::from_str(&built).expect("Failed to parse config");
+    let error = config
+        .validate()
+        .expect_err("Build-only services should be rejected")
+        .to_string();
+    assert!(error.contains("Service web has a [services.build] section but no image"));
+    // The generator refuses instead of running a placeholder image
+    assert!(NomadGenerator::new(&config).generate().is_err());
+
+    // The pushed image of a built service is deployed
+    let config = parse_config(&built.replace(
+        "[services.build]",
+        "image = \"registry.example.com/web:1.0\"\n\n[services.build]",
+    ));
+    let job = generate_job(&config);
+    let task = &find_group(&job, "web")["Tasks"][0];
+    assert_eq!(task["Config"]["image"], "registry.example.com/web:1.0");
+}
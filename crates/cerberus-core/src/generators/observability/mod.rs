@@ -0,0 +1,557 @@
+//! # Observability generator
+//!
+//! Generates the routing report and, when services carry
+//! `[services.observability]` metadata, a Prometheus scrape config and an
+//! Alertmanager routing tree labelled with each service's team, tier, and
+//! SLO target. With `[observability.logs]`, it also generates the Vector
+//! or Promtail pipeline shipping the proxies' access logs to Loki, and the
+//! bundled Loki's configuration. With `[observability.tracing]`, it
+//! generates the OpenTelemetry Collector pipeline exporting the proxies'
+//! spans.
+
+use super::crowdsec::LOG_DIR;
+use crate::{
+    Result,
+    config::{
+        Config, DeployTarget, LogShipper, LogShippingConfig, OtlpProtocol, ProxyType,
+        ServiceConfig, TracingConfig, parse_duration_secs,
+    },
+};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Fields of an Nginx `combined` access log line
+const NGINX_PATTERN: &str = r#"^(?P<remote_addr>\S+) \S+ (?P<remote_user>\S+) \[(?P<time_local>[^\]]+)\] "(?P<method>\S+) (?P<path>\S+) [^"]*" (?P<status>\d{3}) (?P<bytes>\d+)"#;
+
+/// Fields of an HAProxy `option httplog` line
+const HAPROXY_PATTERN: &str = r#"(?P<client>[^\s:]+):\d+ \[(?P<accept_date>[^\]]+)\] (?P<frontend>\S+) (?P<backend>[^\s/]+)/(?P<server>\S+) \S+ (?P<status>\d{3}) (?P<bytes>\d+) .*"(?P<method>\S+) (?P<path>\S+) [^"]*"$"#;
+
+/// Generator for the ROUTES report and monitoring configs
+pub struct ObservabilityGenerator<'a> {
+    config: &'a Config,
+}
+
+impl<'a> ObservabilityGenerator<'a> {
+    /// Create a new observability generator
+    pub fn new(config: &'a Config) -> Self {
+        Self { config }
+    }
+
+    /// Whether any service carries observability metadata
+    pub fn has_metadata(&self) -> bool {
+        self.config
+            .services
+            .iter()
+            .any(|service| service.observability.is_some())
+    }
+
+    /// Log shipping settings, when the stack is generated
+    ///
+    /// The services are only generated in docker-compose.yaml and the
+    /// Swarm stack file.
+    pub fn log_shipping(&self) -> Option<&LogShippingConfig> {
+        self.config.observability.logs.as_ref().filter(|_| {
+            matches!(
+                self.config.project.target,
+                DeployTarget::Compose | DeployTarget::Swarm
+            )
+        })
+    }
+
+    /// Tracing settings, when the collector is generated
+    pub fn tracing(&self) -> Option<&TracingConfig> {
+        self.config.observability.tracing.as_ref().filter(|_| {
+            matches!(
+                self.config.project.target,
+                DeployTarget::Compose | DeployTarget::Swarm
+            )
+        })
+    }
+
+    /// Whether the shipper reads HAProxy logs from the Docker socket
+    pub fn reads_docker_logs(&self) -> bool {
+        !self.haproxy_containers().is_empty()
+    }
+
+    /// Write ROUTES.md and, with metadata, the observability/ configs
+    pub fn generate(&self, output_dir: &Path) -> Result<()> {
+        super::write_files(output_dir, self.files())
+    }
+
+    /// Files to write, relative to the output directory
+    pub fn files(&self) -> Vec<(PathBuf, String)> {
+        let mut files = vec![(PathBuf::from("ROUTES.md"), self.generate_routes_report())];
+
+        if self.has_metadata() {
+            files.push((
+                PathBuf::from("observability/prometheus.yml"),
+                self.generate_prometheus(),
+            ));
+            files.push((
+                PathBuf::from("observability/alertmanager.yml"),
+                self.generate_alertmanager(),
+            ));
+        }
+
+        if let Some(logs) = self.log_shipping() {
+            let pipeline = match logs.shipper {
+                LogShipper::Vector => self.generate_vector(logs),
+                LogShipper::Promtail => self.generate_promtail(logs),
+            };
+            files.push((
+                PathBuf::from(format!("observability/{}.yaml", logs.shipper.as_str())),
+                pipeline,
+            ));
+            if logs.bundles_loki() {
+                files.push((
+                    PathBuf::from("observability/loki.yaml"),
+                    self.generate_loki(logs),
+                ));
+            }
+        }
+
+        if let Some(tracing) = self.tracing() {
+            files.push((
+                PathBuf::from("observability/otel-collector.yaml"),
+                self.generate_otel_collector(tracing),
+            ));
+        }
+
+        files
+    }
+
+    /// Generate a Markdown table of every routed domain
+    pub fn generate_routes_report(&self) -> String {
+        let mut report = String::new();
+
+        writeln!(report, "# Routes for {}", self.config.project.name).unwrap();
+        writeln!(report).unwrap();
+        writeln!(report, "Generated by Cerberus Rust edition.").unwrap();
+        writeln!(report).unwrap();
+        writeln!(
+            report,
+            "| Domain | Service | Upstream | Team | Tier | SLO target |"
+        )
+        .unwrap();
+        writeln!(report, "|---|---|---|---|---|---|").unwrap();
+
+        for service in &self.config.services {
+            let observability = service.observability.clone().unwrap_or_default();
+            writeln!(
+                report,
+                "| {} | {} | {} | {} | {} | {} |",
+                service.hosts().join(", "),
+                service.name,
+                service.upstream,
+                observability.team.as_deref().unwrap_or("-"),
+                observability.tier.as_deref().unwrap_or("-"),
+                observability
+                    .slo_target
+                    .map_or_else(|| "-".to_string(), |target| format!("{target}%"))
+            )
+            .unwrap();
+        }
+
+        report
+    }
+
+    /// Generate Prometheus scrape jobs for each service's `/metrics` endpoint
+    pub fn generate_prometheus(&self) -> String {
+        let mut prometheus = String::new();
+
+        writeln!(
+            prometheus,
+            "# Prometheus scrape config for project: {}",
+            self.config.project.name
+        )
+        .unwrap();
+        writeln!(prometheus, "scrape_configs:").unwrap();
+
+        for service in &self.config.services {
+            writeln!(prometheus, "  - job_name: \"{}\"", service.name).unwrap();
+            writeln!(prometheus, "    metrics_path: /metrics").unwrap();
+            writeln!(prometheus, "    static_configs:").unwrap();
+            writeln!(prometheus, "      - targets:").unwrap();
+            for target in self.targets(service) {
+                writeln!(prometheus, "          - \"{target}\"").unwrap();
+            }
+            writeln!(prometheus, "        labels:").unwrap();
+            writeln!(prometheus, "          service: \"{}\"", service.name).unwrap();
+            writeln!(
+                prometheus,
+                "          domain: \"{}\"",
+                service.primary_domain()
+            )
+            .unwrap();
+            if let Some(observability) = &service.observability {
+                for (name, value) in observability.labels() {
+                    writeln!(prometheus, "          {name}: \"{value}\"").unwrap();
+                }
+            }
+        }
+
+        if self.config.anubis.enabled {
+            writeln!(prometheus, "  - job_name: \"anubis\"").unwrap();
+            writeln!(prometheus, "    static_configs:").unwrap();
+            writeln!(prometheus, "      - targets:").unwrap();
+            for container in self.config.anubis.containers() {
+                let port = container.metrics_port().unwrap_or(9090);
+                writeln!(prometheus, "          - \"{}:{port}\"", container.name).unwrap();
+            }
+        }
+
+        prometheus
+    }
+
+    /// Generate an Alertmanager routing tree sending alerts to each owning team
+    pub fn generate_alertmanager(&self) -> String {
+        let teams: BTreeSet<&str> = self
+            .config
+            .services
+            .iter()
+            .filter_map(|service| service.observability.as_ref()?.team.as_deref())
+            .collect();
+
+        let mut alertmanager = String::new();
+        writeln!(
+            alertmanager,
+            "# Alertmanager routing for project: {}",
+            self.config.project.name
+        )
+        .unwrap();
+        writeln!(
+            alertmanager,
+            "# Fill in the receiver integrations (email, Slack, ...) for each team"
+        )
+        .unwrap();
+        writeln!(alertmanager, "route:").unwrap();
+        writeln!(alertmanager, "  receiver: default").unwrap();
+        writeln!(
+            alertmanager,
+            "  group_by: [\"alertname\", \"service\", \"tier\"]"
+        )
+        .unwrap();
+        if !teams.is_empty() {
+            writeln!(alertmanager, "  routes:").unwrap();
+            for team in &teams {
+                writeln!(alertmanager, "    - receiver: team-{team}").unwrap();
+                writeln!(alertmanager, "      matchers:").unwrap();
+                writeln!(alertmanager, "        - team=\"{team}\"").unwrap();
+            }
+        }
+        writeln!(alertmanager, "receivers:").unwrap();
+        writeln!(alertmanager, "  - name: default").unwrap();
+        for team in &teams {
+            writeln!(alertmanager, "  - name: team-{team}").unwrap();
+        }
+
+        alertmanager
+    }
+
+    /// Generate the Vector pipeline parsing each proxy type's access log
+    pub fn generate_vector(&self, logs: &LogShippingConfig) -> String {
+        let mut vector = String::new();
+        writeln!(
+            vector,
+            "# Vector log pipeline for project: {}",
+            self.config.project.name
+        )
+        .unwrap();
+        writeln!(vector, "data_dir: /var/lib/vector").unwrap();
+        writeln!(vector).unwrap();
+
+        let log_files = self.log_files();
+        let haproxy = self.haproxy_containers();
+
+        writeln!(vector, "sources:").unwrap();
+        for (proxy_type, files) in &log_files {
+            writeln!(vector, "  {}_access:", proxy_type.as_str()).unwrap();
+            writeln!(vector, "    type: file").unwrap();
+            writeln!(vector, "    include:").unwrap();
+            for file in files {
+                writeln!(vector, "      - {LOG_DIR}/{file}").unwrap();
+            }
+        }
+        if !haproxy.is_empty() {
+            writeln!(vector, "  haproxy_access:").unwrap();
+            writeln!(vector, "    type: docker_logs").unwrap();
+            writeln!(vector, "    include_containers:").unwrap();
+            for container in &haproxy {
+                writeln!(vector, "      - {container}").unwrap();
+            }
+        }
+
+        let mut parsed = Vec::new();
+        writeln!(vector).unwrap();
+        writeln!(vector, "transforms:").unwrap();
+        let proxy_types = log_files
+            .iter()
+            .map(|(proxy_type, _)| proxy_type.clone())
+            .chain((!haproxy.is_empty()).then_some(ProxyType::HaProxy));
+        for proxy_type in proxy_types {
+            let name = proxy_type.as_str();
+            writeln!(vector, "  {name}_parsed:").unwrap();
+            writeln!(vector, "    type: remap").unwrap();
+            writeln!(vector, "    inputs: [\"{name}_access\"]").unwrap();
+            writeln!(vector, "    source: |").unwrap();
+            let parser = match proxy_type {
+                ProxyType::Nginx => "parse_nginx_log(.message, \"combined\")".to_string(),
+                ProxyType::HaProxy => format!("parse_regex(.message, r'{HAPROXY_PATTERN}')"),
+                _ => "parse_json(.message)".to_string(),
+            };
+            writeln!(vector, "      parsed, err = {parser}").unwrap();
+            writeln!(
+                vector,
+                "      if err == null && is_object(parsed) {{ . = merge(., object!(parsed)) }}"
+            )
+            .unwrap();
+            writeln!(vector, "      .proxy_type = \"{name}\"").unwrap();
+            parsed.push(format!("\"{name}_parsed\""));
+        }
+
+        writeln!(vector).unwrap();
+        writeln!(vector, "sinks:").unwrap();
+        writeln!(vector, "  loki:").unwrap();
+        writeln!(vector, "    type: loki").unwrap();
+        writeln!(vector, "    inputs: [{}]", parsed.join(", ")).unwrap();
+        writeln!(vector, "    endpoint: {}", logs.loki_url()).unwrap();
+        writeln!(vector, "    encoding:").unwrap();
+        writeln!(vector, "      codec: json").unwrap();
+        writeln!(vector, "    labels:").unwrap();
+        writeln!(vector, "      project: \"{}\"", self.config.project.name).unwrap();
+        writeln!(vector, "      proxy_type: \"{{{{ proxy_type }}}}\"").unwrap();
+
+        vector
+    }
+
+    /// Generate the Promtail scrape jobs parsing each proxy type's access log
+    pub fn generate_promtail(&self, logs: &LogShippingConfig) -> String {
+        let mut promtail = String::new();
+        writeln!(
+            promtail,
+            "# Promtail log pipeline for project: {}",
+            self.config.project.name
+        )
+        .unwrap();
+        writeln!(promtail, "server:").unwrap();
+        writeln!(promtail, "  http_listen_port: 9080").unwrap();
+        writeln!(promtail, "  grpc_listen_port: 0").unwrap();
+        writeln!(promtail, "positions:").unwrap();
+        writeln!(promtail, "  filename: /var/lib/promtail/positions.yaml").unwrap();
+        writeln!(promtail, "clients:").unwrap();
+        writeln!(promtail, "  - url: {}/loki/api/v1/push", logs.loki_url()).unwrap();
+        writeln!(promtail, "scrape_configs:").unwrap();
+
+        let write_stages = |promtail: &mut String, proxy_type: ProxyType| {
+            writeln!(promtail, "    pipeline_stages:").unwrap();
+            match proxy_type {
+                ProxyType::Nginx => {
+                    writeln!(promtail, "      - regex:").unwrap();
+                    writeln!(promtail, "          expression: '{NGINX_PATTERN}'").unwrap();
+                }
+                ProxyType::HaProxy => {
+                    writeln!(promtail, "      - regex:").unwrap();
+                    writeln!(promtail, "          expression: '{HAPROXY_PATTERN}'").unwrap();
+                }
+                ProxyType::Caddy => {
+                    writeln!(promtail, "      - json:").unwrap();
+                    writeln!(promtail, "          expressions:").unwrap();
+                    writeln!(promtail, "            status: status").unwrap();
+                    writeln!(promtail, "            method: request.method").unwrap();
+                }
+                _ => {
+                    writeln!(promtail, "      - json:").unwrap();
+                    writeln!(promtail, "          expressions:").unwrap();
+                    writeln!(promtail, "            status: DownstreamStatus").unwrap();
+                    writeln!(promtail, "            method: RequestMethod").unwrap();
+                }
+            }
+            writeln!(promtail, "      - labels:").unwrap();
+            writeln!(promtail, "          status:").unwrap();
+        };
+
+        for (proxy_type, files) in self.log_files() {
+            writeln!(promtail, "  - job_name: {}", proxy_type.as_str()).unwrap();
+            writeln!(promtail, "    static_configs:").unwrap();
+            for file in files {
+                writeln!(promtail, "      - targets: [localhost]").unwrap();
+                writeln!(promtail, "        labels:").unwrap();
+                writeln!(
+                    promtail,
+                    "          project: \"{}\"",
+                    self.config.project.name
+                )
+                .unwrap();
+                writeln!(promtail, "          proxy_type: {}", proxy_type.as_str()).unwrap();
+                writeln!(promtail, "          __path__: {LOG_DIR}/{file}").unwrap();
+            }
+            write_stages(&mut promtail, proxy_type);
+        }
+
+        let haproxy = self.haproxy_containers();
+        if !haproxy.is_empty() {
+            writeln!(promtail, "  - job_name: haproxy").unwrap();
+            writeln!(promtail, "    docker_sd_configs:").unwrap();
+            writeln!(promtail, "      - host: unix:///var/run/docker.sock").unwrap();
+            writeln!(promtail, "        filters:").unwrap();
+            writeln!(promtail, "          - name: name").unwrap();
+            writeln!(promtail, "            values:").unwrap();
+            for container in &haproxy {
+                writeln!(promtail, "              - ^{container}$").unwrap();
+            }
+            writeln!(promtail, "    relabel_configs:").unwrap();
+            writeln!(promtail, "      - target_label: project").unwrap();
+            writeln!(
+                promtail,
+                "        replacement: \"{}\"",
+                self.config.project.name
+            )
+            .unwrap();
+            writeln!(promtail, "      - target_label: proxy_type").unwrap();
+            writeln!(promtail, "        replacement: haproxy").unwrap();
+            write_stages(&mut promtail, ProxyType::HaProxy);
+        }
+
+        promtail
+    }
+
+    /// Generate the bundled Loki's single-binary configuration
+    pub fn generate_loki(&self, logs: &LogShippingConfig) -> String {
+        let retention = parse_duration_secs(&logs.retention).unwrap_or_default() / 3600;
+
+        let mut loki = String::new();
+        writeln!(
+            loki,
+            "# Loki configuration for project: {}",
+            self.config.project.name
+        )
+        .unwrap();
+        writeln!(loki, "auth_enabled: false").unwrap();
+        writeln!(loki, "server:").unwrap();
+        writeln!(loki, "  http_listen_port: 3100").unwrap();
+        writeln!(loki, "common:").unwrap();
+        writeln!(loki, "  path_prefix: /loki").unwrap();
+        writeln!(loki, "  replication_factor: 1").unwrap();
+        writeln!(loki, "  storage:").unwrap();
+        writeln!(loki, "    filesystem:").unwrap();
+        writeln!(loki, "      chunks_directory: /loki/chunks").unwrap();
+        writeln!(loki, "      rules_directory: /loki/rules").unwrap();
+        writeln!(loki, "  ring:").unwrap();
+        writeln!(loki, "    kvstore:").unwrap();
+        writeln!(loki, "      store: inmemory").unwrap();
+        writeln!(loki, "schema_config:").unwrap();
+        writeln!(loki, "  configs:").unwrap();
+        writeln!(loki, "    - from: 2024-01-01").unwrap();
+        writeln!(loki, "      store: tsdb").unwrap();
+        writeln!(loki, "      object_store: filesystem").unwrap();
+        writeln!(loki, "      schema: v13").unwrap();
+        writeln!(loki, "      index:").unwrap();
+        writeln!(loki, "        prefix: index_").unwrap();
+        writeln!(loki, "        period: 24h").unwrap();
+        writeln!(loki, "limits_config:").unwrap();
+        writeln!(loki, "  retention_period: {retention}h").unwrap();
+        writeln!(loki, "compactor:").unwrap();
+        writeln!(loki, "  working_directory: /loki/compactor").unwrap();
+        writeln!(loki, "  retention_enabled: true").unwrap();
+        writeln!(loki, "  delete_request_store: filesystem").unwrap();
+
+        loki
+    }
+
+    /// Generate the OpenTelemetry Collector's trace pipeline
+    ///
+    /// The proxies send OTLP over gRPC or HTTP; the spans are batched and
+    /// exported to the configured endpoint.
+    pub fn generate_otel_collector(&self, tracing: &TracingConfig) -> String {
+        let exporter = match tracing.protocol {
+            OtlpProtocol::Grpc => "otlp",
+            OtlpProtocol::Http => "otlphttp",
+        };
+
+        let mut collector = String::new();
+        writeln!(
+            collector,
+            "# OpenTelemetry Collector configuration for project: {}",
+            self.config.project.name
+        )
+        .unwrap();
+        writeln!(collector, "receivers:").unwrap();
+        writeln!(collector, "  otlp:").unwrap();
+        writeln!(collector, "    protocols:").unwrap();
+        writeln!(collector, "      grpc:").unwrap();
+        writeln!(collector, "        endpoint: 0.0.0.0:4317").unwrap();
+        writeln!(collector, "      http:").unwrap();
+        writeln!(collector, "        endpoint: 0.0.0.0:4318").unwrap();
+        writeln!(collector, "processors:").unwrap();
+        writeln!(collector, "  batch: {{}}").unwrap();
+        writeln!(collector, "exporters:").unwrap();
+        writeln!(collector, "  {exporter}:").unwrap();
+        writeln!(collector, "    endpoint: {}", tracing.endpoint).unwrap();
+        if tracing.insecure() {
+            writeln!(collector, "    tls:").unwrap();
+            writeln!(collector, "      insecure: true").unwrap();
+        }
+        writeln!(collector, "service:").unwrap();
+        writeln!(collector, "  pipelines:").unwrap();
+        writeln!(collector, "    traces:").unwrap();
+        writeln!(collector, "      receivers: [otlp]").unwrap();
+        writeln!(collector, "      processors: [batch]").unwrap();
+        writeln!(collector, "      exporters: [{exporter}]").unwrap();
+
+        collector
+    }
+
+    /// Access log files in the shared log directory, grouped by proxy type
+    fn log_files(&self) -> Vec<(ProxyType, Vec<String>)> {
+        [ProxyType::Nginx, ProxyType::Caddy, ProxyType::Traefik]
+            .into_iter()
+            .filter_map(|proxy_type| {
+                let mut files: Vec<String> = self
+                    .config
+                    .proxies
+                    .iter()
+                    .filter(|proxy| proxy.proxy_type == proxy_type)
+                    .flat_map(|proxy| self.config.access_log_files(proxy))
+                    .collect();
+                files.sort();
+                files.dedup();
+                (!files.is_empty()).then_some((proxy_type, files))
+            })
+            .collect()
+    }
+
+    /// Container names of HAProxy proxies, which log to stdout
+    fn haproxy_containers(&self) -> Vec<String> {
+        self.config
+            .proxies
+            .iter()
+            .filter(|proxy| proxy.proxy_type == ProxyType::HaProxy)
+            .flat_map(|proxy| proxy.container_names(self.config.project.scaling))
+            .collect()
+    }
+
+    /// Scrape targets (host:port) of a service's upstream, one per server
+    /// of an upstream pool
+    fn targets<'s>(&'s self, service: &'s ServiceConfig) -> Vec<&'s str> {
+        let target = |upstream: &'s str| {
+            upstream
+                .split_once("://")
+                .map_or(upstream, |(_, rest)| rest)
+                .split('/')
+                .next()
+                .unwrap_or_default()
+        };
+        match self.config.upstream_pool(&service.upstream) {
+            Some(pool) => pool
+                .servers
+                .iter()
+                .map(|server| target(&server.url))
+                .collect(),
+            None => vec![target(&service.upstream)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
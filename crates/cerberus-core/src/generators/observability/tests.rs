@@ -0,0 +1,219 @@
+//! # Tests for observability generation
+//!
+//! These tests verify that service ownership metadata reaches the routes
+//! report, Prometheus labels, Alertmanager routes, and container labels.
+
+use super::*;
+use crate::generators::{DockerComposeGenerator, NomadGenerator};
+use crate::test_support::parse_config;
+
+/// Helper function to create a config with one annotated and one plain service
+fn create_annotated_config() -> Config {
+    parse_config(
+        r#"
+[project]
+name = "observability-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "checkout"
+domain = "shop.example.com"
+upstream = "http://checkout:8080"
+image = "example/checkout:1.4"
+
+[services.observability]
+team = "payments"
+tier = "critical"
+slo_target = 99.9
+
+[[services]]
+name = "blog"
+domain = "blog.example.com"
+upstream = "http://192.0.2.1:3000/"
+"#,
+    )
+}
+
+#[test]
+fn test_routes_report() {
+    let config = create_annotated_config();
+    let report = ObservabilityGenerator::new(&config).generate_routes_report();
+
+    assert!(report.contains(
+        "| shop.example.com | checkout | http://checkout:8080 | payments | critical | 99.9% |"
+    ));
+    assert!(report.contains("| blog.example.com | blog | http://192.0.2.1:3000/ | - | - | - |"));
+}
+
+#[test]
+fn test_prometheus_scrape_labels() {
+    let config = create_annotated_config();
+    let prometheus = ObservabilityGenerator::new(&config).generate_prometheus();
+
+    assert!(prometheus.contains("- \"checkout:8080\""));
+    assert!(prometheus.contains("- \"192.0.2.1:3000\""));
+    assert!(prometheus.contains("team: \"payments\""));
+    assert!(prometheus.contains("tier: \"critical\""));
+    assert!(prometheus.contains("slo_target: \"99.9\""));
+    assert!(serde_yaml::from_str::<serde_yaml::Value>(&prometheus).is_ok());
+}
+
+#[test]
+fn test_alertmanager_routes_by_team() {
+    let config = create_annotated_config();
+    let alertmanager = ObservabilityGenerator::new(&config).generate_alertmanager();
+
+    assert!(alertmanager.contains("- receiver: team-payments"));
+    assert!(alertmanager.contains("- team=\"payments\""));
+    assert!(alertmanager.contains("- name: team-payments"));
+    assert!(serde_yaml::from_str::<serde_yaml::Value>(&alertmanager).is_ok());
+}
+
+#[test]
+fn test_container_labels() {
+    let config = create_annotated_config();
+
+    let compose = DockerComposeGenerator::new(&config).generate().unwrap();
+    assert!(compose.contains("- \"cerberus.team=payments\""));
+    assert!(compose.contains("- \"cerberus.slo_target=99.9\""));
+
+    let job = NomadGenerator::new(&config).generate().unwrap();
+    assert!(job.contains("\"cerberus.tier\": \"critical\""));
+}
+
+#[test]
+fn test_monitoring_configs_only_with_metadata() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config = parse_config(
+        r#"
+[project]
+name = "observability-test"
+
+[[services]]
+name = "blog"
+domain = "blog.example.com"
+upstream = "http://192.0.2.1:3000"
+"#,
+    );
+
+    ObservabilityGenerator::new(&config)
+        .generate(temp_dir.path())
+        .unwrap();
+
+    assert!(temp_dir.path().join("ROUTES.md").exists());
+    assert!(!temp_dir.path().join("observability").exists());
+}
+
+/// Helper function to create a config shipping the logs of every proxy type
+fn create_log_shipping_config(shipper: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "logs-test"
+
+[[proxies]]
+name = "edge"
+type = "haproxy"
+external_port = 80
+
+[[proxies]]
+name = "front"
+type = "nginx"
+layer = 2
+
+[[proxies]]
+name = "api-gw"
+type = "traefik"
+layer = 2
+
+[[services]]
+name = "blog"
+domain = "blog.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[observability.logs]
+shipper = "{shipper}"
+"#
+    ))
+}
+
+#[test]
+fn test_vector_pipeline_per_proxy_type() {
+    let config = create_log_shipping_config("vector");
+    let generator = ObservabilityGenerator::new(&config);
+    let vector = generator.generate_vector(config.observability.logs.as_ref().unwrap());
+
+    assert!(vector.contains(
+        "  nginx_access:\n    type: file\n    include:\n      - /var/log/cerberus/blog_access.log"
+    ));
+    assert!(vector.contains("      - /var/log/cerberus/api-gw_access.log"));
+    assert!(vector.contains("    type: docker_logs\n    include_containers:\n      - edge"));
+    assert!(vector.contains("parse_nginx_log(.message, \"combined\")"));
+    assert!(vector.contains("parse_json(.message)"));
+    assert!(vector.contains(
+        "    inputs: [\"nginx_parsed\", \"traefik_parsed\", \"haproxy_parsed\"]\n    endpoint: http://loki:3100"
+    ));
+    assert!(generator.reads_docker_logs());
+}
+
+#[test]
+fn test_promtail_pipeline_and_loki() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config = create_log_shipping_config("promtail");
+    ObservabilityGenerator::new(&config)
+        .generate(temp_dir.path())
+        .unwrap();
+
+    let promtail =
+        std::fs::read_to_string(temp_dir.path().join("observability/promtail.yaml")).unwrap();
+    assert!(promtail.contains("  - url: http://loki:3100/loki/api/v1/push"));
+    assert!(promtail.contains("          __path__: /var/log/cerberus/blog_access.log"));
+    assert!(promtail.contains("            status: DownstreamStatus"));
+    assert!(promtail.contains("    docker_sd_configs:"));
+    assert!(!temp_dir.path().join("observability/vector.yaml").exists());
+
+    let loki = std::fs::read_to_string(temp_dir.path().join("observability/loki.yaml")).unwrap();
+    assert!(loki.contains("  retention_period: 168h"));
+
+    let mut external = config.clone();
+    external.observability.logs.as_mut().unwrap().loki_url =
+        Some("https://loki.example.com/".to_string());
+    let files = ObservabilityGenerator::new(&external).files();
+    assert!(
+        !files
+            .iter()
+            .any(|(path, _)| path.ends_with("observability/loki.yaml"))
+    );
+    assert!(files.iter().any(|(_, content)| {
+        content.contains("  - url: https://loki.example.com/loki/api/v1/push")
+    }));
+}
+
+#[test]
+fn test_otel_collector_pipeline() {
+    let mut config = create_log_shipping_config("vector");
+    config.observability.tracing = Some(TracingConfig {
+        endpoint: "https://otlp.example.com".to_string(),
+        protocol: OtlpProtocol::Http,
+        sample_rate: 0.5,
+        collector_image: "otel/opentelemetry-collector:latest".to_string(),
+    });
+    config.validate().expect("Config should be valid");
+
+    let files = ObservabilityGenerator::new(&config).files();
+    let (_, collector) = files
+        .iter()
+        .find(|(path, _)| path.ends_with("observability/otel-collector.yaml"))
+        .expect("collector config should be generated");
+    assert!(collector.contains("      grpc:\n        endpoint: 0.0.0.0:4317"));
+    assert!(collector.contains("  otlphttp:\n    endpoint: https://otlp.example.com\n"));
+    assert!(!collector.contains("insecure"));
+    assert!(collector.contains("      exporters: [otlphttp]"));
+
+    config.project.target = DeployTarget::Nomad;
+    assert!(ObservabilityGenerator::new(&config).tracing().is_none());
+}
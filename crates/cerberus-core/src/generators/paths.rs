@@ -0,0 +1,103 @@
+//! Host path normalization shared by the generators
+//!
+//! Generated files are consumed by Docker and Linux containers, whatever OS
+//! Cerberus runs on. Docker Desktop rejects mount sources with backslashes
+//! or `C:` drive prefixes, so every path written into a generated file goes
+//! through this module: separators become `/`, drive letters become the
+//! `/c/...` form Docker Desktop mounts, and file contents use LF line endings.
+//!
+//! It also names the paths secret values are written to, which the rendered
+//! files mount but whose contents the CLI resolves after rendering.
+
+use std::path::{Component, Path, PathBuf};
+
+/// Directory in the output directory holding resolved secret values
+pub const SECRETS_DIR: &str = "secrets";
+
+/// Directory in the output directory the proxies, Anubis and the WAF log to
+pub const LOGS_DIR: &str = "built/logs";
+
+/// Directory Docker mounts secrets at inside containers
+pub const CONTAINER_SECRETS_DIR: &str = "/run/secrets";
+
+/// tmpfs directory decrypted secrets are written to, one subdirectory per project
+pub const RUNTIME_DIR: &str = "/dev/shm/cerberus";
+
+/// Env file holding a service's `vault://` environment values, relative to
+/// the output directory
+pub fn vault_env_file(service: &str) -> PathBuf {
+    Path::new(SECRETS_DIR).join(format!("{service}.env"))
+}
+
+/// Path a decrypted secret is written to and mounted from
+pub fn decrypted_path(project: &str, name: &str) -> PathBuf {
+    Path::new(RUNTIME_DIR).join(project).join(name)
+}
+
+/// Path relative to the output directory, with `/` separators on every OS
+pub fn posix(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            Component::ParentDir => Some("..".to_string()),
+            Component::RootDir => Some(String::new()),
+            Component::CurDir | Component::Prefix(_) => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Host path from the configuration, as Docker expects it
+///
+/// `.\data` becomes `./data` and `C:\srv\certs` becomes `/c/srv/certs`;
+/// POSIX paths are returned unchanged.
+pub fn host_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    match drive_letter(&path) {
+        Some(drive) => format!("/{}{}", drive.to_ascii_lowercase(), &path[2..]),
+        None => path,
+    }
+}
+
+/// Short-syntax volume (`source:target[:mode]`) with a normalized source
+///
+/// Named volumes are left alone; only bind mount sources are rewritten.
+pub fn volume(spec: &str) -> String {
+    // The colon after a drive letter does not separate the source
+    let split_at = if drive_letter(&spec.replace('\\', "/")).is_some() {
+        spec[2..].find(':').map(|index| index + 2)
+    } else {
+        spec.find(':')
+    };
+    let Some(index) = split_at else {
+        return host_path(spec);
+    };
+
+    let (source, rest) = spec.split_at(index);
+    if source.contains(['/', '\\']) || source.starts_with(['.', '~']) {
+        format!("{}{rest}", host_path(source))
+    } else {
+        spec.to_string()
+    }
+}
+
+/// File content with LF line endings
+///
+/// Templates checked out on Windows may carry CRLF, which breaks shell
+/// scripts and configs read inside Linux containers.
+pub fn lf(content: String) -> String {
+    if content.contains('\r') {
+        content.replace("\r\n", "\n")
+    } else {
+        content
+    }
+}
+
+/// Drive letter of an absolute Windows path (`C:/...`)
+fn drive_letter(path: &str) -> Option<char> {
+    let mut chars = path.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(drive), Some(':'), Some('/')) if drive.is_ascii_alphabetic() => Some(drive),
+        _ => None,
+    }
+}
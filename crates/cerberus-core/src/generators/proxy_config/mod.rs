@@ -0,0 +1,2546 @@
+//! # Proxy configuration generator
+//!
+//! Generates proxy configuration files (Caddy, Nginx, HAProxy, Traefik, Varnish) from Cerberus configuration.
+
+use super::DockerComposeGenerator;
+use super::NetworkResolver;
+use super::crowdsec::{self, Bouncer, CrowdSecGenerator};
+use super::dockerfile::nginx_modules;
+use super::error_pages;
+use super::fragments::FragmentCache;
+use super::paths;
+use crate::{
+    Result,
+    config::{
+        ACME_CHALLENGE_PATH, AppTemplate, AuthProvider, AuthType, CONTAINER_CONTENT_DIR,
+        CONTAINER_ERROR_PAGES_DIR, CacheConfig, CacheKeyPart, CompressionAlgorithm,
+        CompressionConfig, Config, DeploymentStrategy, FailoverConfig, HeaderRules, HeadersConfig,
+        HealthCheckType, LbPolicy, MISSKEY_FEDERATION_PATHS, OTEL_COLLECTOR_ENDPOINT, Protocol,
+        ProxyConfig, ProxyType, ServiceConfig, SnippetsConfig, StreamRoute, TimeoutsConfig,
+        UpstreamHealthConfig, UpstreamPoolConfig, WafConfig, parse_duration_secs, parse_size_bytes,
+        upstream_address,
+    },
+};
+use handlebars::Handlebars;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Request headers nginx always overwrites in proxy_params.conf
+const NGINX_REQUEST_HEADERS: &[&str] = &[
+    "X-Real-IP",
+    "X-Forwarded-For",
+    "X-Forwarded-Proto",
+    "X-Forwarded-Host",
+    "X-Forwarded-Server",
+    "X-Forwarded-Port",
+];
+
+/// Response headers nginx always hides in proxy_params.conf
+const NGINX_HIDDEN_HEADERS: &[&str] = &["Server", "X-Powered-By"];
+
+/// Internal location Nginx serves the error pages under
+const NGINX_ERROR_PAGES_LOCATION: &str = "/.cerberus-errors/";
+
+/// Private ranges a TLS terminator in front of Traefik may connect from
+const PRIVATE_RANGES: &[&str] = &[
+    "127.0.0.1/32",
+    "10.0.0.0/8",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+];
+
+/// Indentation of the `http`, `server` and `location` snippets in Nginx files
+const NGINX_SNIPPET_INDENTS: [&str; 3] = ["", "    ", "        "];
+
+/// Indentation of the snippets in the Caddyfile
+const CADDY_SNIPPET_INDENTS: [&str; 3] = ["\t", "\t", "\t\t"];
+
+/// Indentation of the snippets in haproxy.cfg
+const HAPROXY_SNIPPET_INDENTS: [&str; 3] = ["    ", "    ", "    "];
+
+/// Container path the draining entrypoint script is mounted at
+pub const ENTRYPOINT_PATH: &str = "/usr/local/bin/cerberus-entrypoint.sh";
+
+/// Upstream health check settings with durations normalised to seconds
+fn upstream_health_data(service: &ServiceConfig, health: &UpstreamHealthConfig) -> Value {
+    let seconds = |value: &str| format!("{}s", parse_duration_secs(value).unwrap_or(0));
+    let (host, port) = upstream_address(&service.upstream);
+
+    json!({
+        "http": health.check_type == HealthCheckType::Http,
+        "path": health.path,
+        "interval": seconds(&health.interval),
+        "timeout": seconds(&health.timeout),
+        "expected_status": health.expected_status,
+        "unhealthy_threshold": health.unhealthy_threshold,
+        "upstream_id": format!("{}_upstream", service.name.replace('-', "_")),
+        "upstream_server": format!("{host}:{port}"),
+        "scheme": service.upstream.split_once("://").map_or("http", |(scheme, _)| scheme),
+    })
+}
+
+/// Failover settings of a service for the proxy templates
+///
+/// Nginx and HAProxy list the backups in the service's upstream block or
+/// backend, Caddy after the primary under `lb_policy first`, and Traefik in
+/// the fallback of a failover service.
+fn failover_data(service: &ServiceConfig, failover: &FailoverConfig) -> Value {
+    let server = |upstream: &str| {
+        let (host, port) = upstream_address(upstream);
+        format!("{host}:{port}")
+    };
+    let prefix = service.name.replace('-', "_");
+    let backups: Vec<Value> = service
+        .backup_upstreams
+        .iter()
+        .enumerate()
+        .map(|(index, backup)| {
+            json!({
+                "id": format!("{prefix}_backup_{}", index + 1),
+                "url": backup.trim_end_matches('/'),
+                "server": server(backup),
+            })
+        })
+        .collect();
+    let conditions = &failover.next_upstream;
+    let statuses: Vec<String> = conditions
+        .iter()
+        .filter_map(|condition| condition.status())
+        .map(|status| status.to_string())
+        .collect();
+
+    // [services.timeouts] takes precedence over the failover policy
+    let retries = service.timeouts.retries.unwrap_or(failover.retries);
+    let timeout = service
+        .timeouts
+        .connect
+        .as_deref()
+        .unwrap_or(&failover.timeout);
+
+    json!({
+        "backups": backups,
+        "retries": retries,
+        "tries": retries + 1,
+        "timeout": format!("{}s", parse_duration_secs(timeout).unwrap_or(0)),
+        "upstream_id": format!("{prefix}_upstream"),
+        "upstream_server": server(&service.upstream),
+        "scheme": service.upstream.split_once("://").map_or("http", |(scheme, _)| scheme),
+        "nginx_next_upstream": conditions
+            .iter()
+            .map(|condition| condition.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+        "haproxy_retry_on": conditions
+            .iter()
+            .map(|condition| condition.haproxy_retry_on())
+            .collect::<Vec<_>>()
+            .join(" "),
+        "unhealthy_status": statuses.join(" "),
+    })
+}
+
+/// An `[[upstreams]]` pool for the proxy templates
+///
+/// Nginx renders it as a shared `upstream` block named `id`; HAProxy,
+/// Caddy and Traefik list its servers in each backend that uses it.
+fn upstream_pool_data(pool: &UpstreamPoolConfig) -> Value {
+    let id = pool_id(pool);
+    let servers: Vec<Value> = pool
+        .servers
+        .iter()
+        .enumerate()
+        .map(|(index, server)| {
+            let (host, port) = upstream_address(&server.url);
+            let weight = match server.weight {
+                1 => String::new(),
+                weight => format!(" weight={weight}"),
+            };
+            json!({
+                "id": format!("{id}_{}", index + 1),
+                "label": format!("server-{}", index + 1),
+                "url": server.url.trim_end_matches('/'),
+                "server": format!("{host}:{port}"),
+                "weight": server.weight,
+                "nginx": format!("{host}:{port}{weight}"),
+            })
+        })
+        .collect();
+
+    // Caddy only weighs servers under its weighted_round_robin policy
+    let caddy_lb_policy = if pool.lb_policy == LbPolicy::RoundRobin && pool.is_weighted() {
+        let weights: Vec<String> = pool
+            .servers
+            .iter()
+            .map(|server| server.weight.to_string())
+            .collect();
+        format!("weighted_round_robin {}", weights.join(" "))
+    } else {
+        pool.lb_policy.as_str().to_string()
+    };
+
+    json!({
+        "name": pool.name,
+        "id": id,
+        "scheme": pool.scheme(),
+        "servers": servers,
+        "weighted": pool.is_weighted(),
+        "keepalive": pool.keepalive,
+        "nginx_method": pool.lb_policy.nginx(),
+        "haproxy_balance": pool.lb_policy.haproxy(),
+        "caddy_upstreams": servers
+            .iter()
+            .map(|server| server["url"].as_str().unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(" "),
+        "caddy_lb_policy": caddy_lb_policy,
+    })
+}
+
+/// Name of the nginx `upstream` block of a pool
+fn pool_id(pool: &UpstreamPoolConfig) -> String {
+    format!("pool_{}", pool.name.replace('-', "_"))
+}
+
+/// Add a pool to the shared nginx `upstream` blocks unless it is there already
+fn add_nginx_pool(pools: &mut Vec<Value>, pool: &Value) {
+    if !pool.is_null() && !pools.iter().any(|other| other["id"] == pool["id"]) {
+        pools.push(pool.clone());
+    }
+}
+
+/// Upstream timeouts with durations normalised to seconds, or null when
+/// none is set
+///
+/// `transport` tells whether any timeout is set, `forwarding` whether one
+/// of Traefik's forwarding timeouts is, and `server_timeout` is HAProxy's
+/// single `timeout server` for both directions.
+fn timeouts_data(timeouts: &TimeoutsConfig) -> Value {
+    if timeouts.is_empty() {
+        return Value::Null;
+    }
+    let seconds = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(|value| format!("{}s", parse_duration_secs(value).unwrap_or(0)))
+    };
+    let (connect, read, send, idle) = (
+        seconds(&timeouts.connect),
+        seconds(&timeouts.read),
+        seconds(&timeouts.send),
+        seconds(&timeouts.idle),
+    );
+
+    json!({
+        "transport": connect.is_some() || read.is_some() || send.is_some() || idle.is_some(),
+        "forwarding": connect.is_some() || read.is_some() || idle.is_some(),
+        "server_timeout": read.clone().or_else(|| send.clone()),
+        "connect": connect,
+        "read": read,
+        "send": send,
+        "idle": idle,
+        "has_retries": timeouts.retries.is_some(),
+        "retries": timeouts.retries,
+        "tries": timeouts.retries.map(|retries| retries + 1),
+    })
+}
+
+/// Attempts of Traefik's retry middleware, or null when nothing is retried
+fn retry_attempts(failover: &Value, timeouts: &TimeoutsConfig) -> Value {
+    match timeouts.retries {
+        _ if !failover.is_null() => failover["tries"].clone(),
+        Some(retries) if retries > 0 => json!(retries + 1),
+        _ => Value::Null,
+    }
+}
+
+/// Header changes in one direction, with each value quoted for the proxies
+///
+/// `value` is a double-quoted string that Caddy, Nginx and YAML read
+/// verbatim, `haproxy` additionally escapes `%` for HAProxy's log-format
+/// strings, and `plain` is the bare value for VCL's `{"..."}` strings.
+fn header_rules_data(rules: &HeaderRules) -> Value {
+    let entries = |headers: &std::collections::BTreeMap<String, String>| -> Vec<Value> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let quoted = json!(value).to_string();
+                json!({
+                    "name": name,
+                    "value": quoted,
+                    "haproxy": quoted.replace('%', "%%"),
+                    "plain": value,
+                })
+            })
+            .collect()
+    };
+    json!({
+        "set": entries(&rules.set),
+        "add": entries(&rules.add),
+        "remove": rules.remove,
+    })
+}
+
+/// Compression settings of a service, or null when it is not compressed
+///
+/// Nginx always compresses `text/html` and warns when it is listed again.
+fn compression_data(compression: &CompressionConfig) -> Value {
+    if !compression.enabled {
+        return Value::Null;
+    }
+    let level = |algorithm| {
+        compression
+            .uses(algorithm)
+            .then(|| json!({ "level": compression.level(algorithm) }))
+    };
+    let caddy_encoders: Vec<String> = compression
+        .algorithms
+        .iter()
+        .map(|&algorithm| match algorithm {
+            CompressionAlgorithm::Gzip => format!("gzip {}", compression.level(algorithm)),
+            // Caddy only takes a numeric level for gzip
+            CompressionAlgorithm::Zstd => "zstd".to_string(),
+            CompressionAlgorithm::Brotli => "br".to_string(),
+        })
+        .collect();
+    json!({
+        "gzip": level(CompressionAlgorithm::Gzip),
+        "brotli": level(CompressionAlgorithm::Brotli),
+        "zstd": level(CompressionAlgorithm::Zstd),
+        "min_size_bytes": compression.min_size_bytes(),
+        "types": compression.types,
+        "types_space": compression.types.join(" "),
+        "nginx_types": compression
+            .types
+            .iter()
+            .filter(|mime| !mime.eq_ignore_ascii_case("text/html"))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" "),
+        "caddy_encoders": caddy_encoders,
+        "encodings": compression
+            .algorithms
+            .iter()
+            .map(|algorithm| algorithm.encoding())
+            .collect::<Vec<_>>(),
+        "customized": compression.is_customized(),
+    })
+}
+
+/// Header changes of a service, or null when it changes none
+fn headers_data(headers: &HeadersConfig) -> Value {
+    if headers.is_empty() {
+        return Value::Null;
+    }
+    json!({
+        "request": header_rules_data(&headers.request),
+        "response": header_rules_data(&headers.response),
+    })
+}
+
+/// Replace the host of an upstream (`http://proxy-2:80` or `proxy-2:80`)
+fn with_host(upstream: &str, host: &str) -> String {
+    let (scheme, address) = match upstream.split_once("://") {
+        Some((scheme, address)) => (format!("{scheme}://"), address),
+        None => (String::new(), upstream),
+    };
+    let rest = address
+        .find([':', '/'])
+        .map_or("", |index| &address[index..]);
+    format!("{scheme}{host}{rest}")
+}
+
+/// Escape a literal path for use in a VCL regular expression
+/// Longest time a stale cached response may still be served, in seconds
+fn cache_grace_secs(cache: &CacheConfig) -> Option<u64> {
+    [&cache.stale_while_revalidate, &cache.stale_if_error]
+        .into_iter()
+        .flatten()
+        .filter_map(|value| parse_duration_secs(value))
+        .max()
+}
+
+/// Regex matching a `Cookie` header that carries any of the named cookies
+fn cookie_pattern(cookies: &[String]) -> String {
+    format!(r"(^|;\s*)({})=", cookies.join("|"))
+}
+
+/// Express a service's cache key for each proxy
+///
+/// nginx joins variables, Varnish hashes VCL expressions, and the Caddy
+/// cache handler always keys on the path and drops the parts not listed.
+fn cache_key_data(cache: &CacheConfig) -> Value {
+    let parts = cache.key_parts();
+    let mut nginx = Vec::new();
+    let mut varnish = Vec::new();
+    let mut headers = Vec::new();
+    for part in &parts {
+        match part {
+            CacheKeyPart::Host => {
+                nginx.push("$host".to_string());
+                varnish.push("req.http.X-Cerberus-Host".to_string());
+            }
+            CacheKeyPart::Method => {
+                nginx.push("$request_method".to_string());
+                varnish.push("req.method".to_string());
+            }
+            CacheKeyPart::Uri => {
+                nginx.push("$request_uri".to_string());
+                varnish.push("req.url".to_string());
+            }
+            CacheKeyPart::Header(name) => {
+                nginx.push(format!("$http_{}", name.to_lowercase().replace('-', "_")));
+                varnish.push(format!("req.http.{name}"));
+                headers.push(name.as_str());
+            }
+        }
+    }
+
+    json!({
+        "nginx": nginx.join("|"),
+        "varnish": varnish,
+        "caddy": {
+            "disable_host": !parts.contains(&CacheKeyPart::Host),
+            "disable_method": !parts.contains(&CacheKeyPart::Method),
+            "disable_query": !parts.contains(&CacheKeyPart::Uri),
+            "headers": headers.join(" "),
+        },
+    })
+}
+
+fn escape_regex(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        if "\\.^$*+?()[]{}|".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Unanchored regular expression for a domain; a leading `*.` matches one label
+fn host_pattern(domain: &str) -> String {
+    match domain.strip_prefix("*.") {
+        Some(suffix) => format!("[^.]+\\.{}", escape_regex(suffix)),
+        None => escape_regex(domain),
+    }
+}
+
+/// Nginx `server_name` or `map` key for a domain, wildcards as regexes
+fn nginx_host(domain: &str) -> String {
+    if domain.starts_with("*.") {
+        format!("~^{}$", host_pattern(domain))
+    } else {
+        domain.to_string()
+    }
+}
+
+/// Traefik rule matching any of the domains with `Host` or `HostSNI`
+///
+/// Wildcards use the `Regexp` variant of the matcher; backslashes are
+/// doubled for the quoted YAML string the rule is written into.
+fn traefik_rule(hosts: &[&str], matcher: &str) -> String {
+    hosts
+        .iter()
+        .map(|host| {
+            if host.starts_with("*.") {
+                let pattern = host_pattern(host).replace('\\', "\\\\");
+                format!("{matcher}Regexp(`^{pattern}$`)")
+            } else {
+                format!("{matcher}(`{host}`)")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" || ")
+}
+
+/// Nginx size with the largest unit that divides it, e.g. "100m"
+/// Serialize snippets for templates, each line indented for its block
+///
+/// Returns `null` when no scope is set.
+fn snippets_data(snippets: Option<&SnippetsConfig>, indents: [&str; 3]) -> Value {
+    let Some(snippets) = snippets else {
+        return Value::Null;
+    };
+    let mut data = serde_json::Map::new();
+    for (scope, directives) in snippets.set_scopes() {
+        let indent = match scope {
+            "http" => indents[0],
+            "server" => indents[1],
+            _ => indents[2],
+        };
+        let lines: Vec<String> = directives
+            .trim_end()
+            .trim_start_matches('\n')
+            .lines()
+            .map(|line| {
+                if line.trim().is_empty() {
+                    String::new()
+                } else {
+                    format!("{indent}{line}")
+                }
+            })
+            .collect();
+        data.insert(scope.to_string(), json!(lines.join("\n")));
+    }
+    if data.is_empty() {
+        Value::Null
+    } else {
+        Value::Object(data)
+    }
+}
+
+fn nginx_size(bytes: u64) -> String {
+    [("g", 1 << 30), ("m", 1 << 20), ("k", 1 << 10)]
+        .into_iter()
+        .find(|(_, unit)| bytes >= *unit && bytes.is_multiple_of(*unit))
+        .map_or(bytes.to_string(), |(suffix, unit)| {
+            format!("{}{suffix}", bytes / unit)
+        })
+}
+
+/// Nginx `[proxies.tuning]` knobs, falling back to the stock values
+///
+/// `client_max_body_size` stays null when unset; each template keeps its
+/// own default.
+fn nginx_tuning(proxy: &ProxyConfig) -> Value {
+    let tuning = proxy.tuning.clone().unwrap_or_default();
+    json!({
+        "worker_processes": tuning
+            .worker_processes
+            .map_or_else(|| "auto".to_string(), |workers| workers.to_string()),
+        "worker_connections": tuning.worker_connections.unwrap_or(1024),
+        "keepalive_timeout": format!(
+            "{}s",
+            tuning.keepalive_timeout.as_deref().and_then(parse_duration_secs).unwrap_or(65)
+        ),
+        "client_max_body_size": tuning
+            .client_max_body_size
+            .as_deref()
+            .and_then(parse_size_bytes)
+            .map(nginx_size),
+    })
+}
+
+/// HAProxy `[proxies.tuning]` knobs, falling back to the stock values
+///
+/// Durations are written with an explicit unit: a bare HAProxy timeout is
+/// read as milliseconds.
+fn haproxy_tuning(proxy: &ProxyConfig) -> Value {
+    let tuning = proxy.tuning.clone().unwrap_or_default();
+    let seconds = |value: &Option<String>, default: u64| {
+        format!(
+            "{}s",
+            value
+                .as_deref()
+                .and_then(parse_duration_secs)
+                .unwrap_or(default)
+        )
+    };
+    json!({
+        "maxconn": tuning.maxconn.unwrap_or(4096),
+        "timeout_connect": seconds(&tuning.timeout_connect, 5),
+        "timeout_client": seconds(&tuning.timeout_client, 50),
+        "timeout_server": seconds(&tuning.timeout_server, 50),
+        "keepalive_timeout": seconds(&tuning.keepalive_timeout, 2),
+    })
+}
+
+/// Templates by the name they are rendered with
+const TEMPLATES: &[(&str, &str)] = &[
+    ("caddy", include_str!("../../templates/Caddyfile.hbs")),
+    (
+        "nginx_default",
+        include_str!("../../templates/nginx/default.conf.hbs"),
+    ),
+    (
+        "nginx_proxy2",
+        include_str!("../../templates/nginx/proxy2.conf.hbs"),
+    ),
+    (
+        "nginx_main",
+        include_str!("../../templates/nginx/nginx.conf.hbs"),
+    ),
+    (
+        "nginx_service",
+        include_str!("../../templates/nginx/service.conf.hbs"),
+    ),
+    (
+        "nginx_proxy_params",
+        include_str!("../../templates/nginx/proxy_params.conf.hbs"),
+    ),
+    (
+        "nginx_timeouts",
+        include_str!("../../templates/nginx/timeouts.conf.hbs"),
+    ),
+    (
+        "nginx_tracing",
+        include_str!("../../templates/nginx/tracing.conf.hbs"),
+    ),
+    (
+        "nginx_upstreams",
+        include_str!("../../templates/nginx/upstreams.conf.hbs"),
+    ),
+    (
+        "nginx_crowdsec",
+        include_str!("../../templates/nginx/crowdsec.inc.hbs"),
+    ),
+    (
+        "nginx_https",
+        include_str!("../../templates/nginx/https.inc.hbs"),
+    ),
+    (
+        "nginx_auth",
+        include_str!("../../templates/nginx/auth.inc.hbs"),
+    ),
+    (
+        "nginx_error_pages",
+        include_str!("../../templates/nginx/error_pages.inc.hbs"),
+    ),
+    (
+        "nginx_content",
+        include_str!("../../templates/nginx/content.inc.hbs"),
+    ),
+    (
+        "nginx_real_ip",
+        include_str!("../../templates/nginx/real_ip.conf.hbs"),
+    ),
+    ("haproxy", include_str!("../../templates/haproxy.cfg.hbs")),
+    ("traefik", include_str!("../../templates/traefik.yml.hbs")),
+    (
+        "traefik_middlewares",
+        include_str!("../../templates/traefik/middlewares.yml.hbs"),
+    ),
+    (
+        "traefik_default",
+        include_str!("../../templates/traefik/default.yml.hbs"),
+    ),
+    (
+        "traefik_streams",
+        include_str!("../../templates/traefik/streams.yml.hbs"),
+    ),
+    (
+        "traefik_service",
+        include_str!("../../templates/traefik/service.yml.hbs"),
+    ),
+    (
+        "traefik_ops",
+        include_str!("../../templates/traefik/ops.yml.hbs"),
+    ),
+    ("varnish", include_str!("../../templates/varnish.vcl.hbs")),
+    (
+        "instance_balancer",
+        include_str!("../../templates/instance_balancer.cfg.hbs"),
+    ),
+];
+
+/// Generator for proxy configurations
+pub struct ProxyConfigGenerator<'a> {
+    config: &'a Config,
+    handlebars: Handlebars<'static>,
+    fragments: Option<&'a FragmentCache>,
+}
+
+impl<'a> ProxyConfigGenerator<'a> {
+    /// Create a new proxy configuration generator
+    pub fn new(config: &'a Config) -> Self {
+        let mut handlebars = Handlebars::new();
+
+        // Register helper for string equality
+        handlebars.register_helper(
+            "eq",
+            Box::new(
+                |h: &handlebars::Helper,
+                 _: &Handlebars,
+                 _: &handlebars::Context,
+                 _: &mut handlebars::RenderContext,
+                 out: &mut dyn handlebars::Output|
+                 -> handlebars::HelperResult {
+                    let param0 = h.param(0).and_then(|v| v.value().as_str()).unwrap_or("");
+                    let param1 = h.param(1).and_then(|v| v.value().as_str()).unwrap_or("");
+                    let result = param0 == param1;
+                    out.write(if result { "true" } else { "" })?;
+                    Ok(())
+                },
+            ),
+        );
+
+        // Register helper for string starts_with
+        handlebars.register_helper(
+            "starts_with",
+            Box::new(
+                |h: &handlebars::Helper,
+                 _: &Handlebars,
+                 _: &handlebars::Context,
+                 _: &mut handlebars::RenderContext,
+                 out: &mut dyn handlebars::Output|
+                 -> handlebars::HelperResult {
+                    let param0 = h.param(0).and_then(|v| v.value().as_str()).unwrap_or("");
+                    let param1 = h.param(1).and_then(|v| v.value().as_str()).unwrap_or("");
+                    let result = param0.starts_with(param1);
+                    out.write(if result { "true" } else { "" })?;
+                    Ok(())
+                },
+            ),
+        );
+
+        for (name, source) in TEMPLATES {
+            handlebars
+                .register_template_string(name, *source)
+                .unwrap_or_else(|e| panic!("Failed to register {name} template: {e}"));
+        }
+
+        Self {
+            config,
+            handlebars,
+            fragments: None,
+        }
+    }
+
+    /// Reuse rendered per-service fragments through a cache
+    ///
+    /// Without one, every fragment is rendered from its template.
+    pub fn fragment_cache(mut self, fragments: &'a FragmentCache) -> Self {
+        self.fragments = Some(fragments);
+        self
+    }
+
+    /// Render a per-service fragment, through the cache when there is one
+    fn render_fragment(&self, template: &str, data: &Value) -> Result<String> {
+        let Some(fragments) = self.fragments else {
+            return Ok(self.handlebars.render(template, data)?);
+        };
+        let source = TEMPLATES
+            .iter()
+            .find(|(name, _)| *name == template)
+            .map_or("", |(_, source)| source);
+        fragments.render(template, source, data, || {
+            Ok(self.handlebars.render(template, data)?)
+        })
+    }
+
+    /// Generate configuration for a specific proxy
+    pub fn generate_for_proxy(&self, proxy: &ProxyConfig) -> Result<String> {
+        match proxy.proxy_type.as_str() {
+            "caddy" => self.generate_caddy_config(proxy),
+            "nginx" => self.generate_nginx_config(proxy),
+            "haproxy" => self.generate_haproxy_config(proxy),
+            "traefik" => self.generate_traefik_config(proxy),
+            "varnish" => self.generate_varnish_config(proxy),
+            _ => Err(crate::CerberusError::config(format!(
+                "Unsupported proxy type: {}",
+                proxy.proxy_type
+            ))),
+        }
+    }
+
+    /// Generate multiple Nginx configuration files
+    pub fn generate_nginx_configs(&self, proxy: &ProxyConfig) -> Result<HashMap<String, String>> {
+        let mut configs = HashMap::new();
+
+        let services = self.get_services_for_proxy(proxy);
+
+        // Check proxy layer to determine configuration type
+        let is_proxy_layer_1 = proxy.layer.unwrap_or(1) == 1;
+
+        // Edge Nginx proxies check each request with the CrowdSec bouncer
+        let crowdsec =
+            CrowdSecGenerator::new(self.config).bouncer(proxy) == Some(Bouncer::ForwardAuth);
+        if crowdsec {
+            let crowdsec_data = json!({
+                "project_name": &self.config.project.name,
+                "forward_auth_url": crowdsec::FORWARD_AUTH_URL,
+            });
+            let crowdsec_inc = self.handlebars.render("nginx_crowdsec", &crowdsec_data)?;
+            configs.insert("crowdsec.inc".to_string(), crowdsec_inc);
+        }
+
+        // Only the edge sends HSTS; inner layers would repeat the header
+        let redirect = self.https_redirect_data(proxy);
+        let hsts = if self.is_edge_proxy(proxy) {
+            self.config.tls.hsts.header_value()
+        } else {
+            None
+        };
+        let https = redirect.is_some() || hsts.is_some();
+        if https {
+            let https_data = json!({
+                "project_name": &self.config.project.name,
+                "redirect": redirect,
+                "acme_pattern": escape_regex(ACME_CHALLENGE_PATH),
+                "hsts": hsts,
+            });
+            let https_inc = self.handlebars.render("nginx_https", &https_data)?;
+            configs.insert("https.inc".to_string(), https_inc);
+        }
+
+        let error_pages = self.error_pages_data();
+        if !error_pages.is_null() {
+            let error_pages_data = json!({
+                "project_name": &self.config.project.name,
+                "error_pages": error_pages,
+            });
+            let error_pages_inc = self
+                .handlebars
+                .render("nginx_error_pages", &error_pages_data)?;
+            configs.insert("error_pages.inc".to_string(), error_pages_inc);
+        }
+        let error_pages = !error_pages.is_null();
+
+        let content = self.content_data(proxy);
+        if !content.is_null() {
+            let content_inc = self.handlebars.render(
+                "nginx_content",
+                &json!({
+                    "project_name": &self.config.project.name,
+                    "content": content,
+                }),
+            )?;
+            configs.insert("content.inc".to_string(), content_inc);
+        }
+        let content = !content.is_null();
+
+        // [[upstreams]] pools are shared blocks in upstreams.conf
+        let mut shared_pools = Vec::new();
+
+        if is_proxy_layer_1 {
+            // Proxy Layer 1: Domain routing to anubis or proxy-2
+            let special_service = match &proxy.special_routing_service {
+                Some(name) => services.iter().find(|s| &s.name == name),
+                None => services.iter().find(|s| {
+                    s.template
+                        .is_some_and(|template| template.federation_paths().is_some())
+                }),
+            };
+            let regular_services: Vec<_> = services
+                .iter()
+                .filter(|s| special_service.is_none_or(|special| special.name != s.name))
+                .map(|s| self.service_template_data(s))
+                .collect();
+            // Federation endpoints other servers call skip the challenge
+            let special_paths = special_service.map(|s| {
+                s.template
+                    .and_then(AppTemplate::federation_paths)
+                    .unwrap_or(MISSKEY_FEDERATION_PATHS)
+            });
+
+            let mut upstream_pools = Vec::new();
+            let mut template_data = json!({
+                "proxy": proxy,
+                "services": regular_services,
+                "special_service": special_service.map(|s| self.service_template_data(s)),
+                "special_paths": special_paths,
+                "project_name": &self.config.project.name,
+                "listen_port": proxy.internal_port,
+                "ipv6": self.config.project.ipv6,
+                "default_upstream": self.nginx_upstream(
+                    &self
+                        .config
+                        .default_upstream(proxy)
+                        .unwrap_or_else(|| "proxy-2:80".to_string()),
+                    &mut upstream_pools,
+                ),
+                "next_layer": self.nginx_upstream("proxy-2:80", &mut upstream_pools),
+                "has_services": !regular_services.is_empty(),
+                "anubis_enabled": self.config.anubis.enabled,
+                "anubis_instances": self.anubis_instances_data(),
+                "method_restrictions": self.method_restrictions(proxy, &services),
+                "route_paths": self.route_paths_data(proxy),
+                "ops": self.ops_data(proxy),
+                "crowdsec": crowdsec,
+                "https": https,
+                "error_pages": error_pages,
+                "content": content,
+                "tuning": nginx_tuning(proxy),
+                "snippets": snippets_data(proxy.snippets.as_ref(), NGINX_SNIPPET_INDENTS),
+            });
+
+            template_data["upstream_pools"] = json!(upstream_pools);
+            template_data["protection"] = self.anubis_protection_data();
+            for route in template_data["route_paths"]
+                .as_array()
+                .into_iter()
+                .flatten()
+            {
+                for location in route["locations"].as_array().into_iter().flatten() {
+                    add_nginx_pool(&mut shared_pools, &location["pool"]);
+                }
+            }
+
+            // Generate default.conf for proxy-1
+            let default_conf = self.handlebars.render("nginx_default", &template_data)?;
+            configs.insert("default.conf".to_string(), default_conf);
+
+            // Users of the ops route, read by auth_basic
+            if !template_data["ops"].is_null() {
+                let htpasswd: String = self
+                    .config
+                    .ops
+                    .users
+                    .iter()
+                    .map(|user| format!("{user}\n"))
+                    .collect();
+                configs.insert("ops.htpasswd".to_string(), htpasswd);
+            }
+        } else {
+            // Proxy Layer 2: Generate individual config files for each service
+            for service in &services {
+                let auth = self.auth_data(service);
+                if !auth.is_null() {
+                    // Nginx runs a single auth_request per location
+                    if crowdsec && auth["forward"] == json!(true) {
+                        return Err(crate::CerberusError::config(format!(
+                            "Service {} uses forward auth on proxy {}, which already checks requests with the CrowdSec bouncer; \
+                             route the service through a layer behind the edge",
+                            service.name, proxy.name
+                        )));
+                    }
+                    let auth_data = json!({
+                        "project_name": &self.config.project.name,
+                        "service_name": &service.name,
+                        "auth": auth,
+                    });
+                    configs.insert(
+                        auth["include"].as_str().unwrap_or_default().to_string(),
+                        self.handlebars.render("nginx_auth", &auth_data)?,
+                    );
+                    // Users listed inline, read by auth_basic
+                    if let Some(basic) = service.auth.as_ref().filter(|auth| {
+                        auth.auth_type == AuthType::Basic && auth.users_secret.is_none()
+                    }) {
+                        let htpasswd: String =
+                            basic.users.iter().map(|user| format!("{user}\n")).collect();
+                        configs.insert(
+                            auth["htpasswd"].as_str().unwrap_or_default().to_string(),
+                            htpasswd,
+                        );
+                    }
+                }
+
+                let mut service_data = self.routed_service_data(proxy, service);
+                service_data["snippets"] =
+                    snippets_data(service.snippets.as_ref(), NGINX_SNIPPET_INDENTS);
+                add_nginx_pool(&mut shared_pools, &service_data["upstream_pool"]);
+                let template_data = json!({
+                    "service": service_data,
+                    "project_name": &self.config.project.name,
+                    "listen_port": proxy.internal_port,
+                    "ipv6": self.config.project.ipv6,
+                    "waf": waf_template_data(&self.config.waf, proxy),
+                    "crowdsec": crowdsec,
+                    "https": https,
+                    "error_pages": error_pages,
+                    "hsts": hsts,
+                    "snippets": snippets_data(proxy.snippets.as_ref(), NGINX_SNIPPET_INDENTS),
+                });
+
+                let service_conf = self.render_fragment("nginx_service", &template_data)?;
+                let filename = format!("{}.conf", service.name.replace("-", "_"));
+                configs.insert(filename, service_conf);
+            }
+        }
+
+        // Generate proxy_params.conf (shared for all proxy types)
+        let mut header_scrub =
+            self.header_scrub(proxy, NGINX_REQUEST_HEADERS, NGINX_HIDDEN_HEADERS);
+        header_scrub["reset_forwarded_for"] = json!(
+            self.is_edge_proxy(proxy)
+                && self
+                    .config
+                    .security
+                    .header_scrub
+                    .request
+                    .iter()
+                    .any(|header| header.eq_ignore_ascii_case("X-Forwarded-For"))
+        );
+        let proxy_params_data = json!({
+            "project_name": &self.config.project.name,
+            "header_scrub": header_scrub,
+        });
+        let proxy_params_conf = self
+            .handlebars
+            .render("nginx_proxy_params", &proxy_params_data)?;
+        configs.insert("proxy_params.conf".to_string(), proxy_params_conf);
+
+        // Included at the http level, below the per-server timeouts
+        let timeouts_conf = self.handlebars.render(
+            "nginx_timeouts",
+            &json!({ "project_name": &self.config.project.name }),
+        )?;
+        configs.insert("timeouts.conf".to_string(), timeouts_conf);
+
+        let tracing = self.tracing_data(proxy);
+        if !tracing.is_null() {
+            let tracing_conf = self.handlebars.render(
+                "nginx_tracing",
+                &json!({
+                    "project_name": &self.config.project.name,
+                    "tracing": tracing,
+                }),
+            )?;
+            configs.insert("tracing.conf".to_string(), tracing_conf);
+        }
+
+        if !shared_pools.is_empty() {
+            let upstreams_data = json!({
+                "project_name": &self.config.project.name,
+                "pools": shared_pools,
+            });
+            let upstreams_conf = self.handlebars.render("nginx_upstreams", &upstreams_data)?;
+            configs.insert("upstreams.conf".to_string(), upstreams_conf);
+        }
+
+        // conf.d is included inside http {}, which is where http snippets go
+        let mut http_snippets = Vec::new();
+        if let Some(http) = proxy.snippets.as_ref().and_then(|s| s.http.as_deref()) {
+            http_snippets.push((format!("proxy {}", proxy.name), http));
+        }
+        if proxy.renders_services() {
+            for service in &services {
+                if let Some(http) = service.snippets.as_ref().and_then(|s| s.http.as_deref()) {
+                    http_snippets.push((format!("service {}", service.name), http));
+                }
+            }
+        }
+        if !http_snippets.is_empty() {
+            let mut snippets_conf = format!(
+                "# Raw http directives from [proxies.snippets] and [services.snippets]\n\
+                 # Generated by Cerberus Rust edition\n\
+                 # Project: {}\n",
+                self.config.project.name
+            );
+            for (owner, http) in http_snippets {
+                snippets_conf.push_str(&format!("\n# From {owner}\n{}\n", http.trim()));
+            }
+            configs.insert("snippets.conf".to_string(), snippets_conf);
+        }
+
+        // Included at the http level, so it also covers the access log
+        let real_ip = self.real_ip_data(proxy);
+        if !real_ip.is_null() {
+            let real_ip_data = json!({
+                "project_name": &self.config.project.name,
+                "real_ip": real_ip,
+            });
+            let real_ip_conf = self.handlebars.render("nginx_real_ip", &real_ip_data)?;
+            configs.insert("real_ip.conf".to_string(), real_ip_conf);
+        }
+
+        Ok(configs)
+    }
+
+    /// Generate Caddy configuration
+    fn generate_caddy_config(&self, proxy: &ProxyConfig) -> Result<String> {
+        let services = self.get_services_for_proxy(proxy);
+
+        let mut services_data = self.services_template_data(proxy, &services);
+        for (data, service) in services_data.iter_mut().zip(&services) {
+            data["snippets"] = snippets_data(service.snippets.as_ref(), CADDY_SNIPPET_INDENTS);
+        }
+
+        let default_upstream = self
+            .config
+            .default_upstream(proxy)
+            .unwrap_or_else(|| "http://localhost:3000".to_string());
+        let mut template_data = json!({
+            "proxy": proxy,
+            "services": services_data,
+            "project_name": &self.config.project.name,
+            "listen_port": proxy.internal_port,
+            // Caddy balances across every address listed after reverse_proxy
+            "upstream": self
+                .upstream_pool(&default_upstream)
+                .join(" "),
+            "has_services": !services.is_empty(),
+            "has_anubis": self.config.anubis.enabled,
+            "anubis_target": if self.config.anubis.enabled { &self.config.anubis.target } else { "" },
+            "route_paths": self.route_paths_data(proxy),
+            "ops": self.ops_data(proxy),
+        });
+
+        template_data["header_scrub"] = self.header_scrub(proxy, &[], &["Server"]);
+        template_data["real_ip"] = self.real_ip_data(proxy);
+        template_data["crowdsec"] =
+            json!(CrowdSecGenerator::new(self.config).bouncer(proxy) == Some(Bouncer::Spoa));
+        template_data["waf"] = waf_template_data(&self.config.waf, proxy);
+        template_data["edge_cache"] = json!(self.config.edge_caches(proxy));
+        template_data["drain_timeout"] = json!(proxy.drain_timeout_secs());
+        template_data["https_redirect"] = json!(self.https_redirect_data(proxy));
+        template_data["hsts"] = json!(self.config.tls.hsts.header_value());
+        template_data["acme"] = json!(self.acme_data(proxy));
+        template_data["snippets"] = snippets_data(proxy.snippets.as_ref(), CADDY_SNIPPET_INDENTS);
+        template_data["error_pages"] = self.error_pages_data();
+        template_data["content"] = self.content_data(proxy);
+
+        let config = self.handlebars.render("caddy", &template_data)?;
+        Ok(config)
+    }
+
+    /// Generate Nginx configuration
+    fn generate_nginx_config(&self, proxy: &ProxyConfig) -> Result<String> {
+        let services = self.get_services_for_proxy(proxy);
+
+        let default_upstream = self
+            .config
+            .default_upstream(proxy)
+            .unwrap_or_else(|| "http://localhost:3000".to_string());
+        let mut template_data = json!({
+            "proxy": proxy,
+            "services": self.services_template_data(proxy, &services),
+            "project_name": &self.config.project.name,
+            "listen_port": proxy.internal_port,
+            "ipv6": self.config.project.ipv6,
+            "upstream": &default_upstream,
+            "has_services": !services.is_empty(),
+            "hsts": self.config.tls.hsts.header_value(),
+        });
+        let tuning = nginx_tuning(proxy);
+        for key in [
+            "worker_processes",
+            "worker_connections",
+            "keepalive_timeout",
+        ] {
+            template_data[key] = tuning[key].clone();
+        }
+        template_data["client_max_body_size"] = match &tuning["client_max_body_size"] {
+            Value::Null => json!("100M"),
+            size => size.clone(),
+        };
+
+        let config = self.handlebars.render("nginx", &template_data)?;
+        Ok(config)
+    }
+
+    /// Generate HAProxy configuration
+    fn generate_haproxy_config(&self, proxy: &ProxyConfig) -> Result<String> {
+        let services = self.get_services_for_proxy(proxy);
+        let mut services_data = self.services_template_data(proxy, &services);
+        for (data, service) in services_data.iter_mut().zip(&services) {
+            data["snippets"] = snippets_data(service.snippets.as_ref(), HAPROXY_SNIPPET_INDENTS);
+        }
+
+        let default_upstream = self
+            .config
+            .default_upstream(proxy)
+            .unwrap_or_else(|| "http://localhost:3000".to_string());
+        let mut template_data = json!({
+            "proxy": proxy,
+            "services": services_data,
+            "project_name": &self.config.project.name,
+            "listen_port": proxy.internal_port,
+            "ipv6": self.config.project.ipv6,
+            "upstream": &default_upstream,
+            "has_services": !services.is_empty(),
+            "tuning": haproxy_tuning(proxy),
+            "snippets": snippets_data(proxy.snippets.as_ref(), HAPROXY_SNIPPET_INDENTS),
+        });
+
+        template_data["default_servers"] = json!(
+            self.upstream_pool(&default_upstream)
+                .iter()
+                .enumerate()
+                .map(|(index, address)| json!({ "id": format!("default_{}", index + 1), "address": address }))
+                .collect::<Vec<_>>()
+        );
+        template_data["header_scrub"] = self.header_scrub(proxy, &[], &["Server"]);
+        template_data["real_ip"] = self.real_ip_data(proxy);
+        template_data["crowdsec"] =
+            json!(CrowdSecGenerator::new(self.config).bouncer(proxy) == Some(Bouncer::Spoa));
+        // Without [proxies.stats] the unauthenticated page stays container-internal
+        template_data["stats"] = json!(proxy.enabled_stats());
+        template_data["default_stats"] = json!(proxy.stats.is_none());
+        template_data["ops"] = self.ops_data(proxy);
+        template_data["drain_timeout"] = json!(proxy.drain_timeout_secs());
+        template_data["streams"] = json!(self.streams_data(proxy));
+        template_data["https_redirect"] = json!(self.https_redirect_data(proxy));
+        template_data["hsts"] = json!(self.config.tls.hsts.header_value());
+        template_data["hardened"] = json!(self.config.security.hardening.enabled);
+        template_data["error_pages"] = self.error_pages_data();
+        template_data["content"] = self.content_data(proxy);
+
+        let config = self.handlebars.render("haproxy", &template_data)?;
+        Ok(config)
+    }
+
+    /// Generate the static Traefik configuration (`traefik.yml`)
+    fn generate_traefik_config(&self, proxy: &ProxyConfig) -> Result<String> {
+        let https_redirect = self.https_redirect_data(proxy).is_some();
+        let real_ip = self.real_ip_data(proxy);
+        let mut trusted_ips: Vec<Value> = Vec::new();
+        if https_redirect {
+            trusted_ips.extend(PRIVATE_RANGES.iter().map(|range| json!(range)));
+        }
+        if let Some(trusted) = real_ip["trusted"].as_array() {
+            trusted_ips.extend(trusted.iter().cloned());
+        }
+
+        let template_data = json!({
+            "proxy": proxy,
+            "project_name": &self.config.project.name,
+            "listen_port": proxy.internal_port,
+            "drain_timeout": proxy.drain_timeout_secs(),
+            "streams": self.streams_data(proxy),
+            "https_redirect": https_redirect,
+            "real_ip": real_ip,
+            "trusted_ips": trusted_ips,
+            "acme": self.acme_data(proxy),
+            "tracing": self.tracing_data(proxy),
+        });
+
+        let config = self.handlebars.render("traefik", &template_data)?;
+        Ok(config)
+    }
+
+    /// Generate the static Traefik configuration and its dynamic directory
+    ///
+    /// `dynamic/` holds the shared middlewares, the default routes, and one
+    /// file per service with its router, service, and own middlewares.
+    /// Traefik's file provider watches the directory, so routes change
+    /// without a restart. Paths are relative to the proxy's config directory.
+    pub fn generate_traefik_configs(&self, proxy: &ProxyConfig) -> Result<Vec<(PathBuf, String)>> {
+        let services = self.get_services_for_proxy(proxy);
+        let dynamic = Path::new("dynamic");
+
+        let header_scrub = self.header_scrub(proxy, &[], &[]);
+        let crowdsec = (CrowdSecGenerator::new(self.config).bouncer(proxy)
+            == Some(Bouncer::ForwardAuth))
+        .then(|| json!({ "forward_auth_url": crowdsec::FORWARD_AUTH_URL }));
+
+        // Middlewares every router applies, in order; error pages come first
+        // so they also replace the other middlewares' rejections
+        let error_pages = self.error_pages_data();
+        let mut shared = Vec::new();
+        if !error_pages.is_null() {
+            shared.push("error-pages".to_string());
+        }
+        if crowdsec.is_some() {
+            shared.push("crowdsec-bouncer".to_string());
+        }
+        if header_scrub["enabled"] == json!(true) {
+            shared.push("header-scrub".to_string());
+        }
+        shared.push("security-headers".to_string());
+        shared.push("rate-limit".to_string());
+
+        let mut files = vec![(
+            PathBuf::from("traefik.yml"),
+            self.generate_traefik_config(proxy)?,
+        )];
+
+        let hsts = &self.config.tls.hsts;
+        let https_redirect = self.https_redirect_data(proxy);
+        // Routers also serve the HTTPS entry point
+        let acme = self.config.acme(proxy).is_some();
+        let middleware_data = json!({
+            "proxy": proxy,
+            "project_name": &self.config.project.name,
+            "header_scrub": header_scrub,
+            "crowdsec": crowdsec,
+            "hsts": hsts.enabled.then_some(hsts),
+            "https_redirect": https_redirect,
+            "error_pages": error_pages,
+        });
+        files.push((
+            dynamic.join("middlewares.yml"),
+            self.handlebars
+                .render("traefik_middlewares", &middleware_data)?,
+        ));
+
+        let default_upstream = self
+            .config
+            .default_upstream(proxy)
+            .unwrap_or_else(|| "http://localhost:3000".to_string());
+        let mut default_middlewares = shared.clone();
+        default_middlewares.push("compression".to_string());
+        let default_data = json!({
+            "proxy": proxy,
+            "project_name": &self.config.project.name,
+            "upstreams": self.upstream_pool(
+                &default_upstream
+            ),
+            "middlewares": default_middlewares,
+            "https_redirect": https_redirect,
+            "acme": acme,
+        });
+        files.push((
+            dynamic.join("default.yml"),
+            self.handlebars.render("traefik_default", &default_data)?,
+        ));
+
+        let ops = self.ops_data(proxy);
+        if !ops.is_null() {
+            let ops_data = json!({
+                "proxy": proxy,
+                "project_name": &self.config.project.name,
+                "ops": ops,
+                "acme": acme,
+            });
+            files.push((
+                dynamic.join("ops.yml"),
+                self.handlebars.render("traefik_ops", &ops_data)?,
+            ));
+        }
+
+        // TCP and UDP routers, each bound to its own entry point
+        let streams = self.streams_data(proxy);
+        if !streams.is_empty() {
+            let (udp, tcp): (Vec<Value>, Vec<Value>) = streams
+                .into_iter()
+                .partition(|stream| stream["udp"] == json!(true));
+            let stream_data = json!({
+                "proxy": proxy,
+                "project_name": &self.config.project.name,
+                "tcp": tcp,
+                "udp": udp,
+            });
+            files.push((
+                dynamic.join("streams.yml"),
+                self.handlebars.render("traefik_streams", &stream_data)?,
+            ));
+        }
+
+        for service in &services {
+            let service_data = self.routed_service_data(proxy, service);
+            let has_headers = !service_data["headers"].is_null();
+
+            // Buffering a WebSocket upgrade would break the stream
+            let body_limit = parse_size_bytes(&service.max_body_size)
+                .filter(|&bytes| bytes > 0 && !service.websocket);
+
+            // Settings other than the defaults need their own middleware
+            let compression = &service.compression;
+            let own_compression = compression.enabled && compression.is_customized();
+
+            let has_auth = !service_data["auth"].is_null();
+
+            let mut middlewares = shared.clone();
+            if has_auth {
+                middlewares.push(format!("{}-auth", service.name));
+            }
+            if has_headers {
+                middlewares.push(format!("{}-headers", service.name));
+            }
+            if body_limit.is_some() {
+                middlewares.push(format!("{}-body-limit", service.name));
+            }
+            if own_compression {
+                middlewares.push(format!("{}-compression", service.name));
+            } else if compression.enabled {
+                middlewares.push("compression".to_string());
+            }
+            // Closest to the service, so only the proxying is retried
+            let retry = !service_data["retry_attempts"].is_null();
+            if retry {
+                middlewares.push(format!("{}-retry", service.name));
+            }
+
+            let template_data = json!({
+                "proxy": proxy,
+                "service": service_data,
+                "rule": traefik_rule(&service.hosts(), "Host"),
+                "project_name": &self.config.project.name,
+                "middlewares": middlewares,
+                "shared_middlewares": shared,
+                "has_headers": has_headers,
+                "body_limit": body_limit,
+                "own_compression": own_compression,
+                "has_own_middlewares": has_auth
+                    || has_headers
+                    || body_limit.is_some()
+                    || own_compression
+                    || retry,
+                "acme": acme,
+            });
+            files.push((
+                dynamic.join(format!("{}.yml", service.name)),
+                self.render_fragment("traefik_service", &template_data)?,
+            ));
+        }
+
+        Ok(files)
+    }
+
+    /// Generate the HAProxy configuration balancing internal-only instances
+    ///
+    /// Written for proxies with a [`ProxyConfig::instance_balancer`].
+    pub fn generate_instance_balancer(&self, proxy: &ProxyConfig) -> Result<String> {
+        let data = json!({
+            "project_name": &self.config.project.name,
+            "proxy": proxy,
+            "port": proxy.internal_port,
+            "replicas": proxy.deploy_replicas(),
+            "servers": DockerComposeGenerator::new(self.config).proxy_services(proxy),
+        });
+        Ok(self.handlebars.render("instance_balancer", &data)?)
+    }
+
+    /// Generate the draining entrypoint script of a proxy
+    ///
+    /// The script runs the proxy as a child so Docker's SIGTERM can be
+    /// translated into the proxy's graceful stop signal, and SIGHUP into its
+    /// reload signal. Arguments passed to the script replace the default
+    /// command. Returns `None` unless `[proxies.drain]` enables it.
+    pub fn generate_entrypoint(&self, proxy: &ProxyConfig) -> Option<String> {
+        if !proxy.uses_drain_entrypoint() {
+            return None;
+        }
+        let timeout = proxy.drain_timeout_secs().unwrap_or(30);
+
+        // (default command, graceful stop signal, reload signal)
+        let (command, stop, reload) = match proxy.proxy_type {
+            ProxyType::Nginx => (
+                format!("nginx -g 'daemon off; worker_shutdown_timeout {timeout}s;'"),
+                "QUIT",
+                Some("HUP"),
+            ),
+            ProxyType::HaProxy => (
+                "haproxy -W -db -f /usr/local/etc/haproxy/haproxy.cfg".to_string(),
+                "USR1",
+                Some("USR2"),
+            ),
+            ProxyType::Caddy => (
+                "caddy run --config /etc/caddy/Caddyfile --adapter caddyfile".to_string(),
+                "TERM",
+                None,
+            ),
+            ProxyType::Traefik => (
+                "traefik --configFile=/etc/traefik/traefik.yml".to_string(),
+                "TERM",
+                None,
+            ),
+            ProxyType::Varnish => return None,
+        };
+
+        let reload = match reload {
+            Some(signal) => format!("trap 'kill -{signal} \"$pid\" 2>/dev/null' HUP\n"),
+            // Caddy's admin API is disabled and Traefik watches its files
+            None => "trap '' HUP\n".to_string(),
+        };
+
+        Some(format!(
+            r#"#!/bin/sh
+# Draining entrypoint for {name} ({proxy_type})
+# Generated by Cerberus Rust edition
+# Project: {project}
+#
+# SIGTERM stops accepting connections and lets in-flight requests finish for
+# up to {timeout}s; SIGHUP reloads the configuration without dropping them.
+
+if [ "$#" -eq 0 ]; then
+    set -- {command}
+fi
+
+"$@" &
+pid=$!
+
+trap 'kill -{stop} "$pid" 2>/dev/null' TERM INT
+{reload}
+# wait returns early whenever a trapped signal arrives
+status=0
+while kill -0 "$pid" 2>/dev/null; do
+    wait "$pid"
+    status=$?
+done
+exit "$status"
+"#,
+            name = proxy.name,
+            proxy_type = proxy.proxy_type,
+            project = self.config.project.name,
+        ))
+    }
+
+    /// Generate Varnish configuration
+    fn generate_varnish_config(&self, proxy: &ProxyConfig) -> Result<String> {
+        let services = self.get_services_for_proxy(proxy);
+        let varnish = proxy.varnish.clone().unwrap_or_default();
+
+        let services_data: Vec<Value> = services
+            .iter()
+            .enumerate()
+            .map(|(index, service)| {
+                let (host, port) = upstream_address(&service.upstream);
+                let cache = service.cache.as_ref().filter(|cache| cache.enabled);
+                let grace = cache.and_then(cache_grace_secs);
+                let condition = service
+                    .hosts()
+                    .iter()
+                    .map(|host| {
+                        let host = host.to_lowercase();
+                        if host.starts_with("*.") {
+                            format!("req.http.X-Cerberus-Host ~ \"^{}$\"", host_pattern(&host))
+                        } else {
+                            format!("req.http.X-Cerberus-Host == \"{host}\"")
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" || ");
+                json!({
+                    "name": service.name,
+                    "condition": condition,
+                    "backend": format!("{}_backend", service.name.replace('-', "_")),
+                    "branch": if index == 0 { "if" } else { "} elsif" },
+                    "host": host,
+                    "port": port,
+                    "headers": headers_data(&service.headers),
+                    "cached": cache.is_some(),
+                    "ttl": cache.and_then(|cache| parse_duration_secs(&cache.ttl)),
+                    "grace": grace,
+                    "methods": cache
+                        .map(|cache| {
+                            cache
+                                .methods
+                                .iter()
+                                .map(|method| format!("req.method != \"{method}\""))
+                                .collect::<Vec<_>>()
+                                .join(" && ")
+                        }),
+                    "bypass_cookies": cache
+                        .filter(|cache| !cache.bypass_cookies.is_empty())
+                        .map(|cache| cookie_pattern(&cache.bypass_cookies)),
+                    "hash": cache
+                        .filter(|cache| !cache.cache_key.is_empty())
+                        .map(|cache| cache_key_data(cache)["varnish"].clone()),
+                    "bypass_paths": cache
+                        .map(|cache| {
+                            cache
+                                .bypass_paths
+                                .iter()
+                                .map(|path| escape_regex(path))
+                                .collect::<Vec<_>>()
+                        })
+                        .unwrap_or_default(),
+                })
+            })
+            .collect();
+
+        // A scaled upstream gets one backend per instance behind a director
+        let default_backends: Vec<Value> = proxy
+            .default_upstream
+            .as_deref()
+            .map(|upstream| self.upstream_pool(upstream))
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+            .map(|(index, upstream)| {
+                let (host, port) = upstream_address(upstream);
+                json!({ "name": format!("default_backend_{}", index + 1), "host": host, "port": port })
+            })
+            .collect();
+        let default_backend = match default_backends.as_slice() {
+            [] => Value::Null,
+            [backend] => {
+                json!({ "host": backend["host"], "port": backend["port"], "hint": "default_backend" })
+            }
+            _ => json!({ "pool": default_backends, "hint": "default_director.backend()" }),
+        };
+
+        // VCL ACL entries are a quoted address with an optional unquoted mask
+        let purge_acl: Vec<String> = varnish
+            .purge_acl
+            .iter()
+            .map(|entry| match entry.split_once('/') {
+                Some((host, mask)) => format!("\"{host}\"/{mask}"),
+                None => format!("\"{entry}\""),
+            })
+            .collect();
+
+        let template_data = json!({
+            "proxy": proxy,
+            "services": services_data,
+            "project_name": &self.config.project.name,
+            "has_services": !services.is_empty(),
+            "default_backend": default_backend,
+            "purge_acl": purge_acl,
+            "error_pages": self.error_pages_data(),
+        });
+
+        let config = self.handlebars.render("varnish", &template_data)?;
+        Ok(config)
+    }
+
+    /// Serialize a service for templates, adding values derived from its config
+    fn service_template_data(&self, service: &ServiceConfig) -> Value {
+        let mut data = json!(service);
+
+        // Exact domains match verbatim; wildcards as anchored regexes
+        let hosts = service.hosts();
+        let (wildcards, exact): (Vec<&str>, Vec<&str>) =
+            hosts.iter().partition(|host| host.starts_with("*."));
+        data["domain"] = json!(service.primary_domain());
+        data["hosts"] = json!(hosts);
+        data["hosts_space"] = json!(hosts.join(" "));
+        data["exact_hosts"] = json!(exact.join(" "));
+        let wildcard_patterns: Vec<String> = wildcards
+            .iter()
+            .map(|host| format!("^{}$", host_pattern(host)))
+            .collect();
+        let nginx_hosts: Vec<String> = hosts.iter().map(|host| nginx_host(host)).collect();
+        data["wildcard_hosts"] = json!(wildcard_patterns);
+        data["server_names"] = json!(nginx_hosts.join(" "));
+        data["nginx_hosts"] = json!(nginx_hosts);
+
+        data["headers"] = headers_data(&service.headers);
+        data["compression"] = compression_data(&service.compression);
+        data["auth"] = self.auth_data(service);
+
+        if !service.allowed_methods.is_empty() {
+            data["methods_space"] = json!(service.allowed_methods.join(" "));
+            data["methods_pattern"] = json!(service.allowed_methods.join("|"));
+        }
+
+        if let Some(pool) = self.config.upstream_pool(&service.upstream) {
+            data["upstream_pool"] = upstream_pool_data(pool);
+        }
+
+        if let (Some(strategy), Some(candidate)) = (
+            &self.config.deployment.strategy,
+            &service.candidate_upstream,
+        ) {
+            self.add_pool_data(&mut data, service, strategy, candidate);
+        }
+
+        if service.waf.enabled && !service.waf.exclude_rules.is_empty() {
+            data["waf_exclusions"] = json!(
+                service
+                    .waf
+                    .exclude_rules
+                    .iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+        }
+
+        if !service.schedules.is_empty() {
+            data["schedules"] = json!(self.schedule_template_data(service));
+        }
+
+        match &service.health {
+            Some(health) if health.is_enabled() => {
+                data["health"] = upstream_health_data(service, health);
+            }
+            // Without it the proxies fall back to requesting /health
+            Some(_) => {
+                data["health"] = Value::Null;
+                data["health_disabled"] = json!(true);
+            }
+            None => {}
+        }
+
+        if let Some(failover) = service.failover_policy() {
+            data["failover"] = failover_data(service, &failover);
+        }
+        data["timeouts"] = timeouts_data(&service.timeouts);
+        data["retry_attempts"] = retry_attempts(&data["failover"], &service.timeouts);
+
+        if let Some(cache) = service.cache.as_ref().filter(|cache| cache.enabled) {
+            data["cache_zone"] = json!(service.name.replace('-', "_"));
+            data["cache_retention"] = json!(cache.retention());
+            data["stale_cache_control"] = json!(cache.stale_cache_control());
+            data["cache_zone_size"] = json!(cache.keys_zone_size());
+            data["cache_methods"] = json!(cache.methods.join(" "));
+            data["cache_ttl_secs"] = json!(parse_duration_secs(&cache.ttl));
+            data["cache_stale_secs"] = json!(cache_grace_secs(cache));
+            data["cache_key"] = cache_key_data(cache);
+            if !cache.bypass_cookies.is_empty() {
+                let variables: Vec<String> = cache
+                    .bypass_cookies
+                    .iter()
+                    .map(|cookie| format!("$cookie_{cookie}"))
+                    .collect();
+                data["cache_bypass"] = json!(variables.join(" "));
+                data["cache_bypass_pattern"] = json!(cookie_pattern(&cache.bypass_cookies));
+            }
+
+            let mut use_stale = Vec::new();
+            if cache.stale_if_error.is_some() {
+                use_stale.push("error timeout http_500 http_502 http_503 http_504");
+            }
+            if cache.stale_while_revalidate.is_some() {
+                use_stale.push("updating");
+            }
+            if !use_stale.is_empty() {
+                data["cache_use_stale"] = json!(use_stale.join(" "));
+            }
+        }
+
+        data
+    }
+
+    /// Add the current and candidate upstream pools of a service
+    fn add_pool_data(
+        &self,
+        data: &mut Value,
+        service: &ServiceConfig,
+        strategy: &DeploymentStrategy,
+        candidate: &str,
+    ) {
+        let prefix = service.name.replace('-', "_");
+        let (current_label, candidate_label) = strategy.pool_labels();
+        let candidate_weight = strategy.candidate_weight();
+        let pool = |label: &str, upstream: &str, weight: u8| {
+            json!({
+                "id": format!("{prefix}_{label}"),
+                "label": label,
+                "url": upstream,
+                "server": upstream
+                    .split_once("://")
+                    .map_or(upstream, |(_, rest)| rest)
+                    .trim_end_matches('/'),
+                "weight": weight,
+            })
+        };
+        let pools = [
+            pool(current_label, &service.upstream, 100 - candidate_weight),
+            pool(candidate_label, candidate, candidate_weight),
+        ];
+        let active: Vec<&Value> = pools.iter().filter(|pool| pool["weight"] != 0).collect();
+
+        // nginx split_clients: explicit share for the candidate, rest to the current pool
+        let split = match candidate_weight {
+            0 => json!([{ "share": "*", "pool": pools[0]["id"] }]),
+            100 => json!([{ "share": "*", "pool": pools[1]["id"] }]),
+            weight => json!([
+                { "share": format!("{weight}%"), "pool": pools[1]["id"] },
+                { "share": "*", "pool": pools[0]["id"] },
+            ]),
+        };
+
+        data["pools"] = json!(pools);
+        data["pool_var"] = json!(format!("{prefix}_pool"));
+        data["pool_scheme"] = json!(
+            service
+                .upstream
+                .split_once("://")
+                .map_or("http", |(scheme, _)| scheme)
+        );
+        data["pool_split"] = split;
+        data["caddy_upstreams"] = json!(
+            active
+                .iter()
+                .map(|pool| pool["url"].as_str().unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        if active.len() > 1 {
+            data["caddy_weights"] = json!(
+                active
+                    .iter()
+                    .map(|pool| pool["weight"].to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+        }
+    }
+
+    /// Describe a service's schedules for templates
+    ///
+    /// HAProxy evaluates the window natively; the other proxies check a flag
+    /// file maintained by the generated `schedule.sh`.
+    fn schedule_template_data(&self, service: &ServiceConfig) -> Vec<Value> {
+        let service_acl = format!("is_{}", service.name);
+
+        service
+            .schedules
+            .iter()
+            .enumerate()
+            .map(|(index, schedule)| {
+                let id = service.schedule_id(index);
+                let is_root = schedule.path == "/";
+
+                let mut acls = Vec::new();
+                let mut window = Vec::new();
+                if !is_root {
+                    acls.push(format!("{id}_path path_beg {}", schedule.path));
+                }
+                let ranges = schedule.clock_ranges();
+                if !ranges.is_empty() {
+                    let ranges: Vec<String> = ranges
+                        .iter()
+                        .map(|(start, end)| format!("{start}:{end}"))
+                        .collect();
+                    acls.push(format!(
+                        "{id}_hours date,ltime(%H%M) -m int {}",
+                        ranges.join(" ")
+                    ));
+                    window.push(format!("{id}_hours"));
+                }
+                let days = schedule.day_numbers();
+                if !days.is_empty() {
+                    let days: Vec<String> = days.iter().map(u8::to_string).collect();
+                    acls.push(format!(
+                        "{id}_days date,ltime(%u) -m int {}",
+                        days.join(" ")
+                    ));
+                    window.push(format!("{id}_days"));
+                }
+                let (from, until) = schedule.date_range();
+                if from.is_some() || until.is_some() {
+                    acls.push(format!(
+                        "{id}_dates date,ltime(%Y%m%d) -m int {}:{}",
+                        from.unwrap_or(0),
+                        until.unwrap_or(99991231)
+                    ));
+                    window.push(format!("{id}_dates"));
+                }
+
+                // Closed when any part of the window does not match
+                let scope = if is_root {
+                    service_acl.clone()
+                } else {
+                    format!("{service_acl} {id}_path")
+                };
+                let closed: Vec<String> =
+                    window.iter().map(|acl| format!("{scope} !{acl}")).collect();
+
+                let upstream = match self.config.upstream_pool(&service.upstream) {
+                    Some(pool) => format!("{}://{}", pool.scheme(), pool_id(pool)),
+                    None if service.upstream.starts_with("http") => service.upstream.clone(),
+                    None => format!("http://{}", service.upstream),
+                };
+                let fallback_server = schedule.fallback_upstream.as_deref().map(|upstream| {
+                    upstream
+                        .split_once("://")
+                        .map_or(upstream, |(_, rest)| rest)
+                        .trim_end_matches('/')
+                        .to_string()
+                });
+
+                json!({
+                    "id": id,
+                    "flag": format!("{id}.closed"),
+                    "path": schedule.path,
+                    "is_root": is_root,
+                    "upstream": upstream,
+                    "fallback_upstream": schedule.fallback_upstream,
+                    "fallback_server": fallback_server,
+                    "fallback_status": schedule.fallback_status,
+                    "haproxy_acls": acls,
+                    "haproxy_closed": closed.join(" || "),
+                })
+            })
+            .collect()
+    }
+
+    /// Every container an upstream reaches
+    ///
+    /// An upstream naming a proxy with duplicated instances (`proxy-2`,
+    /// `proxy-2-2`, ...) expands to one entry per instance, in the same
+    /// format. Other upstreams, including `deploy.replicas` proxies that
+    /// Docker balances by service name, are returned unchanged.
+    fn upstream_pool(&self, upstream: &str) -> Vec<String> {
+        let (host, _) = upstream_address(upstream);
+        match self.config.proxies.iter().find(|proxy| proxy.name == host) {
+            Some(proxy) if proxy.duplicated_instances(self.config.project.scaling) > 1 => {
+                DockerComposeGenerator::new(self.config)
+                    .proxy_services(proxy)
+                    .iter()
+                    .map(|service| with_host(upstream, service))
+                    .collect()
+            }
+            _ => vec![upstream.to_string()],
+        }
+    }
+
+    /// Address nginx proxies an upstream to
+    ///
+    /// A scaled upstream is replaced by the name of an `upstream` block
+    /// listing every instance, which is added to `pools` once.
+    fn nginx_upstream(&self, upstream: &str, pools: &mut Vec<Value>) -> String {
+        let instances = self.upstream_pool(upstream);
+        if instances.len() == 1 {
+            return upstream.to_string();
+        }
+
+        let (host, _) = upstream_address(upstream);
+        let name = format!("{}_pool", host.replace('-', "_"));
+        if !pools.iter().any(|pool| pool["name"] == name) {
+            let servers: Vec<String> = instances
+                .iter()
+                .map(|instance| {
+                    let (host, port) = upstream_address(instance);
+                    format!("{host}:{port}")
+                })
+                .collect();
+            pools.push(json!({ "name": name, "servers": servers }));
+        }
+
+        // The block carries the ports; keep the scheme and path
+        let (scheme, address) = upstream.split_once("://").unwrap_or(("", upstream));
+        let path = address.find('/').map_or("", |index| &address[index..]);
+        match scheme {
+            "" => format!("{name}{path}"),
+            scheme => format!("{scheme}://{name}{path}"),
+        }
+    }
+
+    /// Serialize the `[tls.redirect_http]` settings for an edge proxy
+    ///
+    /// Inner layers always see plain HTTP from the layer in front, so only
+    /// proxies receiving traffic from clients redirect.
+    fn https_redirect_data(&self, proxy: &ProxyConfig) -> Option<Value> {
+        let redirect = self.config.tls.redirect_http.as_ref()?;
+        self.is_edge_proxy(proxy).then(|| {
+            json!({
+                "status": redirect.status,
+                "permanent": redirect.is_permanent(),
+                "https_port": redirect.https_port,
+                "port_suffix": redirect.port_suffix(),
+                "acme_path": ACME_CHALLENGE_PATH,
+            })
+        })
+    }
+
+    /// Serialize the `[tls.acme]` settings for an edge proxy
+    ///
+    /// Credentials are read from the Compose secrets mounted in the proxy
+    /// container, never from the configuration itself.
+    fn acme_data(&self, proxy: &ProxyConfig) -> Option<Value> {
+        let acme = self.config.acme(proxy)?;
+        let credentials: Vec<Value> = acme
+            .secret_credentials()
+            .into_iter()
+            .map(|(credential, secret)| {
+                json!({
+                    "env": credential.env,
+                    "caddy_option": credential.caddy_option,
+                    "file": format!("{}/{secret}", paths::CONTAINER_SECRETS_DIR),
+                })
+            })
+            .collect();
+        Some(json!({
+            "email": acme.email,
+            "provider": acme.provider.as_str(),
+            "domains": acme.domains,
+            "ca": acme.ca,
+            "listen_port": acme.listen_port,
+            "credentials": credentials,
+        }))
+    }
+
+    /// Whether a proxy receives traffic directly from clients
+    fn is_edge_proxy(&self, proxy: &ProxyConfig) -> bool {
+        DockerComposeGenerator::new(self.config).is_edge_proxy(proxy)
+    }
+
+    /// Headers to scrub at a proxy, minus those its template already handles
+    ///
+    /// Request headers are only stripped at edge proxies so inner layers keep
+    /// the values set by the layer in front of them.
+    fn header_scrub(&self, proxy: &ProxyConfig, set_request: &[&str], hidden: &[&str]) -> Value {
+        let scrub = &self.config.security.header_scrub;
+        let without = |headers: &[String], builtin: &[&str]| -> Vec<String> {
+            headers
+                .iter()
+                .filter(|header| !builtin.iter().any(|b| header.eq_ignore_ascii_case(b)))
+                .cloned()
+                .collect()
+        };
+
+        let request = if self.is_edge_proxy(proxy) {
+            without(&scrub.request, set_request)
+        } else {
+            Vec::new()
+        };
+        let response = without(&scrub.response, hidden);
+
+        json!({
+            "enabled": !request.is_empty() || !response.is_empty(),
+            "request": request,
+            "response": response,
+        })
+    }
+
+    /// Anubis instances and the domains layer-1 Nginx sends to them
+    fn anubis_instances_data(&self) -> Vec<Value> {
+        if !self.config.anubis.enabled {
+            return Vec::new();
+        }
+        self.config
+            .anubis
+            .containers()
+            .into_iter()
+            .filter(|container| !container.domains.is_empty())
+            .map(|container| {
+                json!({
+                    "name": &container.name,
+                    "domains": &container.domains,
+                    "url": container.url(),
+                })
+            })
+            .collect()
+    }
+
+    /// Limits layer-1 Nginx applies before requests reach Anubis
+    fn anubis_protection_data(&self) -> Value {
+        let anubis = &self.config.anubis;
+        if !anubis.enabled {
+            return Value::Null;
+        }
+        let protection = &anubis.protection;
+        let (buffers, buffer_size) = protection.header_buffers();
+        // Instances answer the challenges of their own domains
+        let upstream = if anubis.instances.is_empty() {
+            anubis.url()
+        } else {
+            "$anubis_upstream".to_string()
+        };
+        json!({
+            "anubis": upstream,
+            "default_anubis": anubis.url(),
+            // Anubis serves its challenge and pass-challenge API below this path
+            "challenge_path": "/.within.website/",
+            "uri_pattern": format!("^.{{{}}}", protection.max_uri_length + 1),
+            "header_buffers": buffers,
+            "header_buffer_size": nginx_size(buffer_size),
+            "challenge_body_size": parse_size_bytes(&protection.challenge_body_size).map(nginx_size),
+        })
+    }
+
+    /// OpenTelemetry settings of a proxy that emits spans, or null
+    ///
+    /// Layer-1 proxies sample the requests entering the stack; Nginx at
+    /// inner layers follows the decision carried in `traceparent`.
+    fn tracing_data(&self, proxy: &ProxyConfig) -> Value {
+        let Some(tracing) = self.config.tracing(proxy) else {
+            return Value::Null;
+        };
+        json!({
+            "collector": OTEL_COLLECTOR_ENDPOINT,
+            "service_name": proxy.name,
+            "edge": proxy.layer.unwrap_or(1) == 1,
+            "sample_rate": tracing.sample_rate,
+            "sample_all": tracing.sample_rate >= 1.0,
+            // split_clients takes percentages with two decimals
+            "percent": (tracing.sample_rate * 10000.0).round() / 100.0,
+        })
+    }
+
+    /// Trusted ranges and client address header of an edge proxy
+    ///
+    /// Inner layers already receive the client address from the edge in
+    /// front of them, so only edge proxies get `[security.real_ip]`.
+    fn real_ip_data(&self, proxy: &ProxyConfig) -> Value {
+        let real_ip = &self.config.security.real_ip;
+        let Some(provider) = real_ip.provider else {
+            return Value::Null;
+        };
+        if !self.is_edge_proxy(proxy) {
+            return Value::Null;
+        }
+        json!({
+            "provider": provider.as_str(),
+            "header": real_ip.header(),
+            "trusted": real_ip.trusted_ranges(),
+        })
+    }
+
+    /// Files of `[content]` a layer-1 proxy serves, null for other proxies
+    fn content_data(&self, proxy: &ProxyConfig) -> Value {
+        if !self.config.serves_content(proxy) {
+            return Value::Null;
+        }
+        json!({
+            "dir": CONTAINER_CONTENT_DIR,
+            "files": self
+                .config
+                .content
+                .files()
+                .into_iter()
+                .map(|(path, file)| json!({ "path": path, "file": file }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    /// Status codes and mounted location of `[error_pages]`, null without any
+    fn error_pages_data(&self) -> Value {
+        let error_pages = &self.config.error_pages;
+        if !error_pages.is_enabled() {
+            return Value::Null;
+        }
+        let statuses = error_pages.statuses();
+        json!({
+            "statuses": statuses,
+            "statuses_space": statuses
+                .iter()
+                .map(u16::to_string)
+                .collect::<Vec<_>>()
+                .join(" "),
+            "dir": CONTAINER_ERROR_PAGES_DIR,
+            "location": NGINX_ERROR_PAGES_LOCATION,
+            "service": error_pages::SERVICE_NAME,
+            "server": format!(
+                "http://{}:{}",
+                error_pages::SERVICE_NAME,
+                error_pages::SERVICE_PORT
+            ),
+        })
+    }
+
+    /// Serialize the routes of a layer 1 proxy that protect single paths
+    ///
+    /// Each route lists its path prefixes, longest first, followed by a `/`
+    /// catch-all that follows the route type unless a rule covers it.
+    fn route_paths_data(&self, proxy: &ProxyConfig) -> Vec<Value> {
+        proxy
+            .routes
+            .iter()
+            .filter(|route| !route.paths.is_empty())
+            .map(|route| {
+                let target = |protect: bool| {
+                    if protect {
+                        self.config.anubis.url_for(&route.domain)
+                    } else {
+                        route.upstream.clone()
+                    }
+                };
+                // Bypassed requests may go to an upstream pool
+                let pool = self
+                    .config
+                    .upstream_pool(&route.upstream)
+                    .map(upstream_pool_data);
+                let location = |prefix: &str, protect: bool| {
+                    json!({
+                        "prefix": prefix,
+                        "protect": protect,
+                        "upstream": target(protect),
+                        "pool": pool.as_ref().filter(|_| !protect),
+                    })
+                };
+                let mut locations: Vec<Value> = route
+                    .path_rules()
+                    .into_iter()
+                    .map(|path| location(&path.prefix, path.protect))
+                    .collect();
+                if !route.paths.iter().any(|path| path.prefix == "/") {
+                    locations.push(location("/", route.protected_by_default()));
+                }
+
+                json!({
+                    "id": format!("route_{}", route.domain.replace(['.', '-', '*'], "_")),
+                    "domain": &route.domain,
+                    "locations": locations,
+                    "timeouts": timeouts_data(&route.timeouts),
+                })
+            })
+            .collect()
+    }
+
+    /// Serialize the `[ops]` route an edge proxy serves, if any
+    fn ops_data(&self, proxy: &ProxyConfig) -> Value {
+        let ops = &self.config.ops;
+        let Some(domain) = ops.domain.as_deref().filter(|_| self.is_edge_proxy(proxy)) else {
+            return Value::Null;
+        };
+
+        let dashboards: Vec<Value> = self
+            .config
+            .ops_dashboards()
+            .iter()
+            .map(|dashboard| {
+                json!({
+                    "id": format!("ops_{}", dashboard.proxy.replace('-', "_")),
+                    "name": dashboard.proxy,
+                    "prefix": format!("/{}", dashboard.proxy),
+                    "address": dashboard.address,
+                    "upstream": format!("http://{}", dashboard.address),
+                    "path": dashboard.path,
+                })
+            })
+            .collect();
+        let prefixes: Vec<String> = dashboards
+            .iter()
+            .map(|dashboard| format!("{}/", dashboard["prefix"].as_str().unwrap_or_default()))
+            .collect();
+
+        json!({
+            "domain": domain,
+            "users": ops
+                .credentials()
+                .iter()
+                .map(|(user, hash)| json!({ "user": user, "hash": hash }))
+                .collect::<Vec<_>>(),
+            "allow": ops.allow,
+            "allow_space": ops.allow.join(" "),
+            "dashboards": dashboards,
+            "prefixes": prefixes.join(" "),
+        })
+    }
+
+    /// Serialize the `[services.auth]` of a service for templates
+    fn auth_data(&self, service: &ServiceConfig) -> Value {
+        let Some(auth) = &service.auth else {
+            return Value::Null;
+        };
+        let id = service.name.replace('-', "_");
+
+        match auth.auth_type {
+            AuthType::Basic => json!({
+                "basic": true,
+                "include": format!("{id}_auth.inc"),
+                "realm": auth.realm,
+                "users": auth
+                    .credentials()
+                    .iter()
+                    .map(|(user, hash)| json!({ "user": user, "hash": hash }))
+                    .collect::<Vec<_>>(),
+                "users_file": match &auth.users_secret {
+                    Some(secret) => format!("{}/{secret}", paths::CONTAINER_SECRETS_DIR),
+                    None => format!("/etc/nginx/conf.d/{id}.htpasswd"),
+                },
+                "users_secret": auth.users_secret,
+                "htpasswd": format!("{id}.htpasswd"),
+                "userlist": format!("{id}_users"),
+            }),
+            AuthType::Forward => {
+                let forward_auth = &self.config.security.forward_auth;
+                let (Some(provider), Some(base_url)) =
+                    (forward_auth.provider, forward_auth.base_url())
+                else {
+                    return Value::Null;
+                };
+                let user_headers: Vec<Value> = provider
+                    .user_headers()
+                    .iter()
+                    .map(|name| {
+                        let upstream_var = name.to_ascii_lowercase().replace('-', "_");
+                        json!({
+                            "name": name,
+                            "var": format!("auth_{upstream_var}"),
+                            "upstream_var": upstream_var,
+                        })
+                    })
+                    .collect();
+                json!({
+                    "forward": true,
+                    "include": format!("{id}_auth.inc"),
+                    "provider": provider.as_str(),
+                    "oauth2_proxy": provider == AuthProvider::Oauth2Proxy,
+                    "base_url": base_url,
+                    "auth_request_url": format!("{base_url}{}", provider.auth_request_path()),
+                    "forward_auth_url": format!("{base_url}{}", provider.forward_auth_path()),
+                    "forward_auth_path": provider.forward_auth_path(),
+                    "scheme_var": format!("{id}_auth_scheme"),
+                    "user_headers": user_headers,
+                    "user_headers_space": provider.user_headers().join(" "),
+                })
+            }
+        }
+    }
+
+    /// Collect host-based method restrictions for nginx `map` blocks
+    fn method_restrictions(&self, proxy: &ProxyConfig, services: &[&ServiceConfig]) -> Vec<Value> {
+        let services = services.iter().flat_map(|service| {
+            service
+                .hosts()
+                .into_iter()
+                .map(|host| (host, &service.allowed_methods))
+        });
+        let routes = proxy
+            .routes
+            .iter()
+            .map(|route| (route.domain.as_str(), &route.allowed_methods));
+
+        services
+            .chain(routes)
+            .filter(|(_, methods)| !methods.is_empty())
+            .map(|(domain, methods)| {
+                json!({
+                    "domain": domain,
+                    "host_pattern": host_pattern(domain),
+                    "methods_pattern": methods.join("|"),
+                })
+            })
+            .collect()
+    }
+
+    /// Serialize the services a proxy routes for templates
+    fn services_template_data(
+        &self,
+        proxy: &ProxyConfig,
+        services: &[&ServiceConfig],
+    ) -> Vec<Value> {
+        services
+            .iter()
+            .map(|service| self.routed_service_data(proxy, service))
+            .collect()
+    }
+
+    /// Serialize a service as routed by a proxy
+    ///
+    /// When a Varnish layer sits directly behind the proxy, the service is
+    /// sent to the cache instead of its upstream, and the cache (not this
+    /// proxy) stores its responses.
+    fn routed_service_data(&self, proxy: &ProxyConfig, service: &ServiceConfig) -> Value {
+        let mut data = self.service_template_data(service);
+
+        if let Some(cache) = self.config.cache_layer(proxy) {
+            let upstream = format!("http://{}:{}", cache.name, cache.internal_port);
+            if let Some(schedules) = data["schedules"].as_array_mut() {
+                for schedule in schedules {
+                    schedule["upstream"] = json!(upstream);
+                }
+            }
+            data["upstream"] = json!(upstream);
+            if let Some(data) = data.as_object_mut() {
+                // The cache layer sits between this proxy and the upstream
+                for key in [
+                    "headers",
+                    "health",
+                    "health_disabled",
+                    "failover",
+                    "cache",
+                    "cache_zone",
+                    "cache_retention",
+                    "cache_use_stale",
+                    "stale_cache_control",
+                    "cache_zone_size",
+                    "cache_methods",
+                    "cache_ttl_secs",
+                    "cache_stale_secs",
+                    "cache_key",
+                    "cache_bypass",
+                    "cache_bypass_pattern",
+                ] {
+                    data.remove(key);
+                }
+            }
+            data["retry_attempts"] = retry_attempts(&Value::Null, &service.timeouts);
+        }
+
+        data
+    }
+
+    /// Get services that should be routed through this proxy
+    fn get_services_for_proxy(&self, _proxy: &ProxyConfig) -> Vec<&ServiceConfig> {
+        // For now, return all HTTP services. In the future, this could be filtered
+        // based on proxy layer or other criteria; tcp and udp services are streams
+        self.config
+            .services
+            .iter()
+            .filter(|service| !service.protocol.is_stream())
+            .collect()
+    }
+
+    /// Serialize the layer-4 streams a proxy forwards for templates
+    ///
+    /// The first stream on a port is its listener. When tcp streams share a
+    /// port, the listener lists every stream's server names so the proxy can
+    /// route on the TLS SNI and pass the encrypted connection through.
+    fn streams_data(&self, proxy: &ProxyConfig) -> Vec<Value> {
+        let streams = self.config.streams(proxy);
+        let server_names = |stream: &StreamRoute| {
+            let names: Vec<&str> = stream.server_names.iter().map(String::as_str).collect();
+            let (wildcards, exact): (Vec<&str>, Vec<&str>) =
+                names.iter().partition(|name| name.starts_with("*."));
+            json!({
+                "id": stream.id,
+                "exact": exact.join(" "),
+                "wildcards": wildcards
+                    .iter()
+                    .map(|name| format!("^{}$", host_pattern(name)))
+                    .collect::<Vec<_>>(),
+                "nginx": names.iter().map(|name| nginx_host(name)).collect::<Vec<_>>(),
+                "traefik_rule": traefik_rule(&names, "HostSNI"),
+            })
+        };
+
+        streams
+            .iter()
+            .map(|stream| {
+                let shared: Vec<&StreamRoute> = streams
+                    .iter()
+                    .filter(|other| {
+                        other.listen_port == stream.listen_port && other.protocol == stream.protocol
+                    })
+                    .collect();
+                json!({
+                    "id": stream.id,
+                    "name": stream.name,
+                    "protocol": stream.protocol.as_str(),
+                    "udp": stream.protocol == Protocol::Udp,
+                    "listen_port": stream.listen_port,
+                    "address": stream.address,
+                    "entry_point": shared[0].id,
+                    "listener": shared[0].id == stream.id,
+                    "sni": shared.len() > 1,
+                    "server_names": server_names(stream),
+                    "routes": shared.iter().map(|other| server_names(other)).collect::<Vec<_>>(),
+                })
+            })
+            .collect()
+    }
+
+    /// Generate the main `nginx.conf` of an Nginx proxy forwarding streams
+    ///
+    /// `stream {}` blocks cannot live in `conf.d`, which the stock
+    /// configuration includes inside `http {}`, and neither can the worker
+    /// settings of `[proxies.tuning]` or the `load_module` lines of
+    /// compression modules. Returns `None` when the proxy needs none of them
+    /// and the image's own `nginx.conf` is kept.
+    pub fn generate_nginx_main(&self, proxy: &ProxyConfig) -> Result<Option<String>> {
+        if !self.config.generates_nginx_main(proxy) {
+            return Ok(None);
+        }
+
+        let template_data = json!({
+            "proxy": proxy,
+            "project_name": &self.config.project.name,
+            "streams": self.streams_data(proxy),
+            "ipv6": self.config.project.ipv6,
+            "tuning": nginx_tuning(proxy),
+            "hardened": self.config.security.hardening.enabled,
+            "modules": nginx_modules(self.config, proxy)
+                .iter()
+                .map(|module| module.file)
+                .chain(
+                    self.config
+                        .tracing(proxy)
+                        .map(|_| "ngx_otel_module.so"),
+                )
+                .collect::<Vec<_>>(),
+        });
+        Ok(Some(self.handlebars.render("nginx_main", &template_data)?))
+    }
+
+    /// Generate all proxy configurations
+    pub fn generate_all(&self) -> Result<HashMap<String, String>> {
+        let mut configs = HashMap::new();
+
+        for proxy in &self.config.proxies {
+            let config = self.generate_for_proxy(proxy)?;
+            configs.insert(proxy.name.clone(), config);
+        }
+
+        Ok(configs)
+    }
+
+    /// Get the appropriate file extension for a proxy type
+    pub fn get_file_extension(proxy_type: &str) -> &'static str {
+        match proxy_type {
+            "caddy" => "Caddyfile",
+            "nginx" => "nginx.conf",
+            "haproxy" => "haproxy.cfg",
+            "traefik" => "traefik.yml",
+            "varnish" => "default.vcl",
+            _ => "conf",
+        }
+    }
+
+    /// Generate Docker service configuration for proxy
+    pub fn generate_docker_service(&self, proxy: &ProxyConfig) -> Result<serde_yaml::Value> {
+        let docker_image = match proxy.proxy_type.as_str() {
+            "caddy" => "caddy:2-alpine",
+            "nginx" => "nginx:alpine",
+            "haproxy" => "haproxy:alpine",
+            "traefik" => "traefik:v3.0",
+            "varnish" => "varnish:stable",
+            _ => {
+                return Err(crate::CerberusError::config(format!(
+                    "Unsupported proxy type for Docker: {}",
+                    proxy.proxy_type
+                )));
+            }
+        };
+
+        let config_path = format!("./built/proxy-configs/{}/", proxy.name);
+        let config_file = Self::get_file_extension(proxy.proxy_type.as_str());
+
+        let volumes = match proxy.proxy_type.as_str() {
+            "caddy" => vec![
+                format!("{}{}:/etc/caddy/Caddyfile:ro", config_path, config_file),
+                "./built/logs:/var/log/caddy".to_string(),
+            ],
+            "nginx" => vec![
+                format!("{}{}:/etc/nginx/nginx.conf:ro", config_path, config_file),
+                "./built/logs:/var/log/nginx".to_string(),
+            ],
+            "haproxy" => vec![
+                format!(
+                    "{}{}:/usr/local/etc/haproxy/haproxy.cfg:ro",
+                    config_path, config_file
+                ),
+                "./built/logs:/var/log/haproxy".to_string(),
+            ],
+            "traefik" => vec![
+                format!("{}{}:/etc/traefik/traefik.yml:ro", config_path, config_file),
+                "./built/logs:/var/log/traefik".to_string(),
+            ],
+            "varnish" => vec![format!(
+                "{}{}:/etc/varnish/default.vcl:ro",
+                config_path, config_file
+            )],
+            _ => vec![],
+        };
+
+        // Only a configured external port is published
+        let ports: Vec<String> = proxy
+            .external_port
+            .map(|port| format!("{port}:{}", proxy.internal_port))
+            .into_iter()
+            .collect();
+
+        let service = serde_yaml::to_value(json!({
+            "image": docker_image,
+            "container_name": &proxy.name,
+            "restart": "unless-stopped",
+            "ports": ports,
+            "volumes": volumes,
+            "networks": NetworkResolver::new(self.config).proxy_networks(proxy),
+            "healthcheck": {
+                "test": format!("wget --quiet --tries=1 --spider http://localhost:{}/health || exit 1", proxy.internal_port),
+                "interval": "30s",
+                "timeout": "10s",
+                "retries": 3,
+                "start_period": "10s"
+            },
+            "labels": {
+                "cerberus.component": "proxy",
+                "cerberus.proxy": &proxy.name,
+                "cerberus.proxy_type": &proxy.proxy_type
+            },
+            "depends_on": self.get_dependencies_for_proxy(proxy)
+        }))?;
+
+        Ok(service)
+    }
+
+    /// Get Docker service dependencies for a proxy
+    fn get_dependencies_for_proxy(&self, proxy: &ProxyConfig) -> Vec<String> {
+        let mut deps = Vec::new();
+
+        // If this proxy routes to Anubis, add Anubis as dependency
+        if self.config.sends_to_anubis(proxy) {
+            deps.push("anubis".to_string());
+        }
+
+        // Add other proxy dependencies based on upstream configuration
+        for other_proxy in &self.config.proxies {
+            if other_proxy.name != proxy.name
+                && let Some(upstream) = &proxy.default_upstream
+                && upstream.contains(&other_proxy.name)
+            {
+                deps.push(other_proxy.name.clone());
+            }
+        }
+
+        deps
+    }
+}
+
+/// WAF settings for a proxy's templates, or null when it runs no WAF
+pub(crate) fn waf_template_data(waf: &WafConfig, proxy: &ProxyConfig) -> Value {
+    if !waf.applies_to(proxy) {
+        return Value::Null;
+    }
+
+    json!({
+        "paranoia_level": waf.paranoia_level,
+        "rule_engine": waf.rule_engine(),
+        "audit_engine": if waf.audit_log { "RelevantOnly" } else { "Off" },
+    })
+}
+
+#[cfg(test)]
+mod tests;
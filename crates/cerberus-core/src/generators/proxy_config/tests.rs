@@ -0,0 +1,3237 @@
+//! # Tests for proxy configuration generation
+//!
+//! These tests verify that the proxy templates render the configuration
+//! options exposed in `config.toml` for each supported proxy type.
+
+use super::*;
+use crate::config::Config;
+use crate::test_support::parse_config;
+
+/// Helper function to create a two-layer config with a single service
+fn create_layered_config(proxy_type: &str, service_extra: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "proxy-config-test"
+
+[[proxies]]
+name = "proxy-2"
+type = "{proxy_type}"
+layer = 2
+default_upstream = "http://192.0.2.1:3000"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+{service_extra}
+"#
+    ))
+}
+
+/// Helper function to render the per-service nginx config for the first service
+fn render_nginx_service(config: &Config) -> String {
+    let generator = ProxyConfigGenerator::new(config);
+    let configs = generator
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    configs
+        .get("web_app.conf")
+        .expect("Service config should be generated")
+        .clone()
+}
+
+/// Helper function to render a Traefik proxy's dynamic files as one string
+fn render_traefik_dynamic(config: &Config, proxy: usize) -> String {
+    ProxyConfigGenerator::new(config)
+        .generate_traefik_configs(&config.proxies[proxy])
+        .expect("Generation should succeed")
+        .into_iter()
+        .filter(|(path, _)| path.starts_with("dynamic"))
+        .map(|(_, content)| content)
+        .collect()
+}
+
+#[test]
+fn test_nginx_stale_cache_policy() {
+    let config = create_layered_config(
+        "nginx",
+        r#"
+[services.cache]
+enabled = true
+ttl = "5m"
+stale_while_revalidate = "30s"
+stale_if_error = "1d"
+"#,
+    );
+
+    let result = render_nginx_service(&config);
+
+    assert!(result.contains("keys_zone=web_app:10m"));
+    assert!(result.contains("inactive=1d"));
+    assert!(result.contains("proxy_cache web_app;"));
+    assert!(result.contains("proxy_cache_valid 200 301 302 5m;"));
+    assert!(result.contains(
+        "proxy_cache_use_stale error timeout http_500 http_502 http_503 http_504 updating;"
+    ));
+    assert!(result.contains("proxy_cache_background_update on;"));
+}
+
+#[test]
+fn test_nginx_cache_disabled() {
+    let config = create_layered_config(
+        "nginx",
+        r#"
+[services.cache]
+enabled = false
+stale_if_error = "1d"
+"#,
+    );
+
+    let result = render_nginx_service(&config);
+
+    assert!(!result.contains("proxy_cache"));
+}
+
+#[test]
+fn test_stale_cache_control_headers() {
+    let cache = r#"
+[services.cache]
+enabled = true
+stale_while_revalidate = "1m"
+stale_if_error = "1h"
+"#;
+
+    let config = create_layered_config("caddy", cache);
+    let generator = ProxyConfigGenerator::new(&config);
+    let caddyfile = generator
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(
+        caddyfile.contains(
+            "header_down +Cache-Control \"stale-while-revalidate=60, stale-if-error=3600\""
+        )
+    );
+
+    let config = create_layered_config("haproxy", cache);
+    let generator = ProxyConfigGenerator::new(&config);
+    let haproxy_cfg = generator
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(haproxy_cfg.contains(
+        "http-response add-header Cache-Control \"stale-while-revalidate=60, stale-if-error=3600\""
+    ));
+}
+
+#[test]
+fn test_service_cache_policy_all_proxies() {
+    let cache = r#"
+[services.cache]
+enabled = true
+ttl = "5m"
+stale_if_error = "1h"
+methods = ["GET"]
+bypass_cookies = ["session", "logged_in"]
+cache_key = ["host", "uri", "header:Accept-Language"]
+max_size = "5g"
+"#;
+
+    let config = create_layered_config("nginx", cache);
+    let service_conf = render_nginx_service(&config);
+    for line in [
+        "keys_zone=web_app:51m max_size=5g inactive=1h",
+        "proxy_cache_key \"$host|$request_uri|$http_accept_language\";",
+        "proxy_cache_methods GET;",
+        "proxy_cache_bypass $cookie_session $cookie_logged_in;",
+        "proxy_no_cache $cookie_session $cookie_logged_in;",
+    ] {
+        assert!(service_conf.contains(line), "{line}");
+    }
+
+    let config = create_layered_config("caddy", cache);
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate Caddyfile");
+    for line in [
+        "\torder cache before rewrite\n\tcache\n",
+        "\t\t@web-app_cacheable not header_regexp Cookie `(^|;\\s*)(session|logged_in)=`\n",
+        "\t\tcache @web-app_cacheable {\n\t\t\tttl 300s\n\t\t\tstale 3600s\n\t\t\tallowed_http_verbs GET\n",
+        "\t\t\tkey {\n\t\t\t\tdisable_method\n\t\t\t\theaders Accept-Language\n\t\t\t}\n",
+    ] {
+        assert!(caddyfile.contains(line), "{line}");
+    }
+    let dockerfile = crate::generators::DockerfileGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(dockerfile.contains("xcaddy build --with github.com/caddyserver/cache-handler"));
+    assert!(!dockerfile.contains("COPY Caddyfile"));
+
+    let config = create_layered_config("varnish", cache);
+    let vcl = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate VCL");
+    for line in [
+        "        if (req.method != \"GET\") {\n            return (pass);\n        }\n",
+        "        if (req.http.Cookie ~ \"(^|;\\s*)(session|logged_in)=\") {\n            return (pass);\n        }\n        unset req.http.Cookie;\n",
+        "    if (req.backend_hint == web_app_backend) {\n        hash_data(req.http.X-Cerberus-Host);\n        hash_data(req.url);\n        hash_data(req.http.Accept-Language);\n        return (lookup);\n    }\n",
+    ] {
+        assert!(vcl.contains(line), "{line}");
+    }
+
+    // Without a cached service Caddy keeps the stock image
+    let config = create_layered_config("caddy", "");
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(!caddyfile.contains("order cache"));
+    let dockerfile = crate::generators::DockerfileGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(!dockerfile.contains("xcaddy"));
+}
+
+#[test]
+fn test_compression_settings() {
+    let compression = r#"
+[services.compression]
+algorithms = ["zstd", "brotli", "gzip"]
+min_size = "2k"
+types = ["text/html", "application/json"]
+
+[services.compression.levels]
+gzip = 4
+brotli = 5
+"#;
+
+    let config = create_layered_config("nginx", compression);
+    let service_conf = render_nginx_service(&config);
+    for line in [
+        "    gzip_comp_level 4;\n",
+        "    gzip_min_length 2048;\n",
+        "    gzip_types application/json;\n",
+        "    brotli on;\n    brotli_comp_level 5;\n    brotli_min_length 2048;\n",
+        "    zstd on;\n    zstd_comp_level 3;\n",
+    ] {
+        assert!(service_conf.contains(line), "{line}");
+    }
+    let main = ProxyConfigGenerator::new(&config)
+        .generate_nginx_main(&config.proxies[0])
+        .unwrap()
+        .expect("Modules are loaded from the main nginx.conf");
+    assert!(main.contains(
+        "load_module modules/ngx_http_brotli_filter_module.so;\nload_module modules/ngx_http_zstd_filter_module.so;\n"
+    ));
+    assert!(config.builds_proxy_image(&config.proxies[0]));
+    let dockerfile = crate::generators::DockerfileGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    for line in [
+        "FROM nginx:alpine AS modules\n",
+        "git clone --depth 1 --recurse-submodules https://github.com/google/ngx_brotli /tmp/ngx_brotli",
+        "--add-dynamic-module=/tmp/ngx_brotli --add-dynamic-module=/tmp/zstd-nginx-module",
+        "    logrotate \\\n    zstd-libs\n",
+        "COPY --from=modules /modules/ /usr/lib/nginx/modules/\n",
+    ] {
+        assert!(dockerfile.contains(line), "{line}");
+    }
+
+    let config = create_layered_config("caddy", compression);
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(caddyfile.contains(
+        "\t\tencode {\n\t\t\tzstd\n\t\t\tbr\n\t\t\tgzip 4\n\t\t\tminimum_length 2048\n\t\t\tmatch {\n\t\t\t\theader Content-Type text/html*\n"
+    ));
+    assert!(caddyfile.contains(
+        "\t@default_compression not host app.example.com\n\tencode @default_compression gzip zstd\n"
+    ));
+    let dockerfile = crate::generators::DockerfileGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(dockerfile.contains("xcaddy build --with github.com/ueffel/caddy-brotli"));
+
+    let config = create_layered_config("haproxy", compression);
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(
+        haproxy.contains(
+            "    compression algo gzip\n    compression type text/html application/json\n"
+        )
+    );
+
+    let config = create_layered_config("traefik", compression);
+    let service = render_traefik_dynamic(&config, 0);
+    assert!(service.contains("        - web-app-compression\n"));
+    assert!(service.contains(
+        "      compress:\n        encodings:\n          - zstd\n          - br\n          - gzip\n        minResponseBodyBytes: 2048\n"
+    ));
+
+    // Disabled compression needs no modules either
+    let config = create_layered_config("nginx", "compression.enabled = false");
+    let service_conf = render_nginx_service(&config);
+    assert!(service_conf.contains("    gzip off;\n"));
+    assert!(!service_conf.contains("brotli"));
+    assert!(!config.builds_proxy_image(&config.proxies[0]));
+    let config = create_layered_config("haproxy", "compression.enabled = false");
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert_eq!(haproxy.matches("compression algo gzip").count(), 1);
+}
+
+#[test]
+fn test_service_basic_auth() {
+    let auth = r#"
+[services.auth]
+type = "basic"
+realm = "Staff only"
+users = ["alice:$2y$05$6yTcwDuTOTYDZFHeMi3Du.7SxmSxm9wyiRaeDjQwPmCqk9Emjp6Hy"]
+"#;
+    let user = "alice $2y$05$6yTcwDuTOTYDZFHeMi3Du.7SxmSxm9wyiRaeDjQwPmCqk9Emjp6Hy";
+
+    let config = create_layered_config("nginx", auth);
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    assert!(configs["web_app.conf"].contains("    include /etc/nginx/conf.d/web_app_auth.inc;\n"));
+    assert!(configs["web_app_auth.inc"].contains(
+        "auth_basic \"Staff only\";\nauth_basic_user_file /etc/nginx/conf.d/web_app.htpasswd;\n"
+    ));
+    assert_eq!(
+        configs["web_app.htpasswd"],
+        format!("{}\n", user.replace(' ', ":"))
+    );
+
+    let config = create_layered_config("caddy", auth);
+    let caddy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(caddy.contains(&format!(
+        "\t\tbasic_auth bcrypt \"Staff only\" {{\n\t\t\t{user}\n\t\t}}\n"
+    )));
+
+    let config = create_layered_config("haproxy", auth);
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(haproxy.contains(&format!(
+        "userlist web_app_users\n    user {}\n",
+        user.replace(' ', " password ")
+    )));
+    assert!(haproxy.contains(
+        "    http-request auth realm \"Staff only\" if is_web-app !{ http_auth(web_app_users) }\n"
+    ));
+
+    let config = create_layered_config("traefik", auth);
+    let traefik = render_traefik_dynamic(&config, 0);
+    assert!(traefik.contains("        - web-app-auth\n"));
+    assert!(traefik.contains(&format!(
+        "    web-app-auth:\n      basicAuth:\n        realm: \"Staff only\"\n        users:\n          - \"{}\"\n",
+        user.replace(' ', ":")
+    )));
+
+    // A secret is read at runtime instead
+    let config = parse_config(
+        r#"
+[project]
+name = "proxy-config-test"
+
+[[proxies]]
+name = "proxy-2"
+type = "traefik"
+layer = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[services.auth]
+type = "basic"
+users_secret = "web_users"
+
+[secrets.web_users]
+file = "./web.htpasswd"
+"#,
+    );
+    let traefik = render_traefik_dynamic(&config, 0);
+    assert!(traefik.contains("        usersFile: \"/run/secrets/web_users\"\n"));
+}
+
+#[test]
+fn test_service_forward_auth() {
+    let auth = r#"
+[services.auth]
+type = "forward"
+
+[security.forward_auth]
+provider = "oauth2-proxy"
+"#;
+
+    let config = create_layered_config("nginx", auth);
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    assert!(configs["web_app.conf"].contains("map $http_x_forwarded_proto $web_app_auth_scheme {"));
+    let include = &configs["web_app_auth.inc"];
+    assert!(include.contains("auth_request /_cerberus_auth;\n"));
+    assert!(include.contains(
+        "auth_request_set $auth_x_auth_request_user $upstream_http_x_auth_request_user;\n\
+         proxy_set_header X-Auth-Request-User $auth_x_auth_request_user;\n"
+    ));
+    assert!(include.contains("error_page 401 =403 /oauth2/sign_in;\n"));
+    assert!(include.contains("    proxy_pass http://oauth2-proxy:4180/oauth2/auth;\n"));
+    assert!(include.contains(
+        "location /oauth2/ {\n    auth_request off;\n    proxy_pass http://oauth2-proxy:4180;\n"
+    ));
+
+    let config = create_layered_config("caddy", auth);
+    let caddy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(
+        caddy.contains(
+            "\t\thandle /oauth2/* {\n\t\t\treverse_proxy http://oauth2-proxy:4180\n\t\t}\n"
+        )
+    );
+    assert!(caddy.contains(
+        "\t\tforward_auth @web-app_auth http://oauth2-proxy:4180 {\n\t\t\turi /\n\t\t\tcopy_headers X-Auth-Request-User X-Auth-Request-Email\n"
+    ));
+
+    let config = create_layered_config("traefik", auth);
+    let traefik = render_traefik_dynamic(&config, 0);
+    assert!(traefik.contains(
+        "    web-app-auth:\n      forwardAuth:\n        address: \"http://oauth2-proxy:4180/\"\n"
+    ));
+    assert!(
+        traefik.contains("      rule: \"(Host(`app.example.com`)) && PathPrefix(`/oauth2/`)\"\n")
+    );
+    assert!(traefik.contains("    web-app-oauth2:\n      loadBalancer:\n"));
+
+    // Authelia redirects from its own portal
+    let authelia = auth.replace(
+        "provider = \"oauth2-proxy\"",
+        "provider = \"authelia\"\naddress = \"http://auth.internal:9091\"",
+    );
+    let config = create_layered_config("nginx", &authelia);
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    let include = &configs["web_app_auth.inc"];
+    assert!(include.contains("    proxy_pass http://auth.internal:9091/api/authz/auth-request;\n"));
+    assert!(include.contains("error_page 401 =302 $auth_redirect;\n"));
+    assert!(!include.contains("/oauth2/"));
+}
+
+#[test]
+fn test_method_restrictions_layer1_nginx() {
+    let config = parse_config(
+        r#"
+[project]
+name = "proxy-config-test"
+
+[[proxies]]
+name = "proxy-1"
+type = "nginx"
+layer = 1
+
+[[proxies.routes]]
+type = "conditional"
+domain = "api.example.com"
+upstream = "http://anubis:8080"
+allowed_methods = ["GET", "POST"]
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+allowed_methods = ["GET", "HEAD"]
+"#,
+    );
+
+    let generator = ProxyConfigGenerator::new(&config);
+    let configs = generator
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    let default_conf = &configs["default.conf"];
+
+    assert!(default_conf.contains("map \"$host:$request_method\" $method_not_allowed"));
+    assert!(default_conf.contains(r#""~^app\.example\.com:(?!(GET|HEAD)$)" 1;"#));
+    assert!(default_conf.contains(r#""~^api\.example\.com:(?!(GET|POST)$)" 1;"#));
+    assert!(default_conf.contains("return 405;"));
+}
+
+#[test]
+fn test_nginx_maps_key_on_host_without_port() {
+    // Behind a non-default port clients send `Host: App.example.com:8443`;
+    // `$http_host` keeps the port and the casing, `$host` does not
+    let config = parse_config(
+        r#"
+[project]
+name = "proxy-config-test"
+
+[anubis]
+enabled = true
+
+[[anubis.instances]]
+name = "anubis-api"
+domains = ["api.example.com"]
+
+[[proxies]]
+name = "proxy-1"
+type = "nginx"
+layer = 1
+external_port = 8443
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+layer = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+allowed_methods = ["GET", "HEAD"]
+"#,
+    );
+
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    let default_conf = &configs["default.conf"];
+    assert!(default_conf.contains("map $host $proxy_destination {"));
+    assert!(default_conf.contains("map $host $anubis_upstream {"));
+    assert!(default_conf.contains("map \"$host:$request_method\" $method_not_allowed {"));
+    assert!(!default_conf.contains("$http_host"));
+}
+
+#[test]
+fn test_method_restrictions_per_proxy_type() {
+    let methods = r#"allowed_methods = ["GET", "HEAD"]"#;
+
+    let config = create_layered_config("nginx", methods);
+    assert!(render_nginx_service(&config).contains("if ($request_method !~ ^(GET|HEAD)$)"));
+
+    let config = create_layered_config("caddy", methods);
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(caddyfile.contains("@web-app_method_not_allowed not method GET HEAD"));
+    assert!(caddyfile.contains("respond @web-app_method_not_allowed 405"));
+
+    let config = create_layered_config("haproxy", methods);
+    let haproxy_cfg = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(
+        haproxy_cfg
+            .contains("http-request deny deny_status 405 if is_web-app !{ method GET HEAD }")
+    );
+}
+
+#[test]
+fn test_no_method_restrictions_by_default() {
+    let config = create_layered_config("nginx", "");
+
+    assert!(!render_nginx_service(&config).contains("return 405"));
+}
+
+/// Helper function to create an edge proxy and an inner proxy (behind Anubis) with header scrubbing
+fn create_scrub_config(proxy_type: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "proxy-config-test"
+
+[security.header_scrub]
+request = ["X-Forwarded-For", "X-Internal-Token"]
+response = ["Server", "X-Powered-By"]
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "proxy"
+type = "{proxy_type}"
+external_port = 80
+layer = 1
+default_upstream = "http://proxy-2:80"
+
+[[proxies]]
+name = "proxy-2"
+type = "{proxy_type}"
+layer = 2
+default_upstream = "http://192.0.2.1:3000"
+"#
+    ))
+}
+
+#[test]
+fn test_header_scrub_nginx() {
+    let config = create_scrub_config("nginx");
+    let generator = ProxyConfigGenerator::new(&config);
+
+    let edge = generator
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    let params = &edge["proxy_params.conf"];
+    assert!(params.contains("proxy_set_header X-Forwarded-For $remote_addr;"));
+    assert!(params.contains("proxy_set_header X-Internal-Token \"\";"));
+    assert_eq!(params.matches("proxy_hide_header Server;").count(), 1);
+
+    // Inner layers keep the forwarded chain from the edge
+    let inner = generator
+        .generate_nginx_configs(&config.proxies[1])
+        .unwrap();
+    let params = &inner["proxy_params.conf"];
+    assert!(params.contains("proxy_set_header X-Forwarded-For $proxy_add_x_forwarded_for;"));
+    assert!(!params.contains("X-Internal-Token"));
+}
+
+#[test]
+fn test_header_scrub_other_proxies() {
+    let config = create_scrub_config("caddy");
+    let generator = ProxyConfigGenerator::new(&config);
+    let edge = generator.generate_for_proxy(&config.proxies[0]).unwrap();
+    assert!(edge.contains("request_header -X-Forwarded-For"));
+    assert!(edge.contains("request_header -X-Internal-Token"));
+    assert!(edge.contains("-X-Powered-By"));
+    let inner = generator.generate_for_proxy(&config.proxies[1]).unwrap();
+    assert!(!inner.contains("request_header"));
+    assert!(inner.contains("-X-Powered-By"));
+
+    let config = create_scrub_config("haproxy");
+    let generator = ProxyConfigGenerator::new(&config);
+    let edge = generator.generate_for_proxy(&config.proxies[0]).unwrap();
+    assert!(edge.contains("http-request del-header X-Forwarded-For"));
+    assert!(edge.contains("http-response del-header X-Powered-By"));
+    assert_eq!(edge.matches("http-response del-header Server").count(), 1);
+
+    let config = create_scrub_config("traefik");
+    let edge = render_traefik_dynamic(&config, 0);
+    assert!(edge.contains("header-scrub:"));
+    assert!(edge.contains("X-Internal-Token: \"\""));
+    assert!(edge.contains("- header-scrub"));
+    let inner = render_traefik_dynamic(&config, 1);
+    assert!(!inner.contains("customRequestHeaders:\n          X-Internal-Token"));
+    assert!(inner.contains("X-Powered-By: \"\""));
+}
+
+/// Helper function to create an edge proxy and an inner proxy behind a CDN
+fn create_real_ip_config(proxy_type: &str, real_ip: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "proxy-config-test"
+
+[security.real_ip]
+{real_ip}
+
+[[proxies]]
+name = "proxy"
+type = "{proxy_type}"
+external_port = 80
+layer = 1
+default_upstream = "http://proxy-2:80"
+
+[[proxies]]
+name = "proxy-2"
+type = "{proxy_type}"
+layer = 2
+default_upstream = "http://192.0.2.1:3000"
+"#
+    ))
+}
+
+#[test]
+fn test_real_ip_at_every_edge() {
+    let cloudflare = "provider = \"cloudflare\"\ntrusted = [\"198.51.100.0/24\"]";
+
+    let config = create_real_ip_config("nginx", cloudflare);
+    let generator = ProxyConfigGenerator::new(&config);
+    let edge = generator
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    let real_ip = &edge["real_ip.conf"];
+    assert!(real_ip.contains("set_real_ip_from 173.245.48.0/20;\n"));
+    assert!(real_ip.contains("set_real_ip_from 2400:cb00::/32;\n"));
+    assert!(real_ip.contains("set_real_ip_from 198.51.100.0/24;\n"));
+    assert!(real_ip.contains("real_ip_header CF-Connecting-IP;\n"));
+    // Inner layers trust the forwarded chain of the edge instead
+    let inner = generator
+        .generate_nginx_configs(&config.proxies[1])
+        .unwrap();
+    assert!(!inner.contains_key("real_ip.conf"));
+
+    let config = create_real_ip_config("caddy", cloudflare);
+    let generator = ProxyConfigGenerator::new(&config);
+    let edge = generator.generate_for_proxy(&config.proxies[0]).unwrap();
+    assert!(edge.contains("\t\ttrusted_proxies static 173.245.48.0/20 "));
+    assert!(edge.contains(" 2c0f:f248::/32 198.51.100.0/24\n"));
+    assert!(edge.contains("\t\tclient_ip_headers CF-Connecting-IP\n"));
+    let inner = generator.generate_for_proxy(&config.proxies[1]).unwrap();
+    assert!(!inner.contains("trusted_proxies"));
+
+    let config = create_real_ip_config("haproxy", cloudflare);
+    let edge = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(edge.contains("    acl real_ip_trusted src 103.21.244.0/22\n"));
+    assert!(edge.contains(
+        "    http-request set-src req.hdr_ip(CF-Connecting-IP,-1) if real_ip_trusted { req.hdr(CF-Connecting-IP) -m found }\n"
+    ));
+
+    let config = create_real_ip_config("traefik", cloudflare);
+    let generator = ProxyConfigGenerator::new(&config);
+    let edge = generator.generate_for_proxy(&config.proxies[0]).unwrap();
+    assert!(
+        edge.contains("    forwardedHeaders:\n      trustedIPs:\n        - \"173.245.48.0/20\"\n")
+    );
+    assert!(!edge.contains("192.168.0.0/16"));
+    let inner = generator.generate_for_proxy(&config.proxies[1]).unwrap();
+    assert!(!inner.contains("forwardedHeaders"));
+}
+
+#[test]
+fn test_real_ip_custom_ranges() {
+    let config = create_real_ip_config(
+        "nginx",
+        "provider = \"custom\"\ntrusted = [\"10.10.0.0/16\"]",
+    );
+    let edge = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    let real_ip = &edge["real_ip.conf"];
+    assert!(real_ip.contains("set_real_ip_from 10.10.0.0/16;\nreal_ip_header X-Forwarded-For;\n"));
+    assert!(!real_ip.contains("173.245.48.0/20"));
+
+    let config = create_real_ip_config(
+        "haproxy",
+        "provider = \"custom\"\ntrusted = [\"10.10.0.0/16\"]\nheader = \"True-Client-IP\"",
+    );
+    let edge = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(edge.contains("    acl real_ip_trusted src 10.10.0.0/16\n"));
+    assert!(edge.contains("set-src req.hdr_ip(True-Client-IP,-1)"));
+}
+
+#[test]
+fn test_anubis_protection_at_layer_one() {
+    let mut config = create_scrub_config("nginx");
+    config.anubis.protection.max_uri_length = 2048;
+    config.anubis.protection.max_headers_size = "20k".to_string();
+    let generator = ProxyConfigGenerator::new(&config);
+
+    let edge = generator
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    let default_conf = &edge["default.conf"];
+    assert!(default_conf.contains("\nlarge_client_header_buffers 3 8k;\n"));
+    assert!(default_conf.contains("    \"~^.{2049}\" 1;\n"));
+    assert!(default_conf.contains("    if ($uri_too_long) {\n        return 414;\n    }\n"));
+    assert!(default_conf.contains(
+        "    location ^~ /.within.website/ {\n        client_max_body_size 64k;\n        proxy_request_buffering off;\n        proxy_pass http://anubis:8080;\n"
+    ));
+
+    // Without Anubis there is nothing to protect
+    config.anubis.enabled = false;
+    let edge = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    assert!(!edge["default.conf"].contains("large_client_header_buffers"));
+    assert!(!edge["default.conf"].contains("$uri_too_long"));
+}
+
+#[test]
+fn test_no_header_scrub_by_default() {
+    let config = create_layered_config("traefik", "");
+    let traefik = render_traefik_dynamic(&config, 0);
+
+    assert!(!traefik.contains("header-scrub"));
+}
+
+#[test]
+fn test_traefik_dynamic_directory() {
+    let config = create_layered_config("traefik", "");
+    let files = ProxyConfigGenerator::new(&config)
+        .generate_traefik_configs(&config.proxies[0])
+        .unwrap();
+
+    let paths: Vec<String> = files
+        .iter()
+        .map(|(path, _)| path.to_string_lossy().replace('\\', "/"))
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "traefik.yml",
+            "dynamic/middlewares.yml",
+            "dynamic/default.yml",
+            "dynamic/web-app.yml"
+        ]
+    );
+
+    let static_config = &files[0].1;
+    assert!(static_config.contains("directory: /etc/traefik/dynamic\n    watch: true"));
+    assert!(!static_config.contains("routers:"));
+    for (path, content) in &files {
+        assert!(
+            serde_yaml::from_str::<serde_yaml::Value>(content).is_ok(),
+            "{} should be valid YAML",
+            path.display()
+        );
+    }
+
+    // Defaults: compression on, 1m body limit
+    let service = &files[3].1;
+    assert!(service.contains("web-app-router:\n      rule: \"Host(`app.example.com`)\""));
+    assert!(
+        service.contains(
+            "        - rate-limit\n        - web-app-body-limit\n        - compression\n"
+        )
+    );
+    assert!(service.contains("maxRequestBodyBytes: 1048576"));
+}
+
+#[test]
+fn test_traefik_service_middlewares() {
+    let config = create_layered_config(
+        "traefik",
+        r#"
+compression.enabled = false
+max_body_size = "100MB"
+
+[services.headers.request.set]
+X-Tenant = "acme"
+X-Quote = 'say "hi"'
+
+[services.headers.response.set]
+Cache-Control = "no-store"
+"#,
+    );
+    let service = render_traefik_dynamic(&config, 0);
+
+    assert!(service.contains("        - web-app-headers\n        - web-app-body-limit\n\n"));
+    assert!(
+        service.contains("          X-Quote: \"say \\\"hi\\\"\"\n          X-Tenant: \"acme\"\n")
+    );
+    assert!(service.contains("customResponseHeaders:\n          Cache-Control: \"no-store\"\n"));
+    assert!(service.contains("maxRequestBodyBytes: 104857600"));
+
+    let parsed: serde_yaml::Value = serde_yaml::from_str(
+        &ProxyConfigGenerator::new(&config)
+            .generate_traefik_configs(&config.proxies[0])
+            .unwrap()[3]
+            .1,
+    )
+    .unwrap();
+    assert_eq!(
+        parsed["http"]["middlewares"]["web-app-headers"]["headers"]["customRequestHeaders"]["X-Quote"],
+        "say \"hi\""
+    );
+
+    // WebSocket upgrades are never buffered
+    let config = create_layered_config("traefik", "websocket = true\nmax_body_size = \"10m\"");
+    let service = render_traefik_dynamic(&config, 0);
+    assert!(!service.contains("body-limit"));
+    assert!(service.contains("- compression"));
+}
+
+/// Helper function to create a layer 1 proxy in front of three proxy-2 instances
+fn create_scaled_config(proxy_type: &str, default_upstream: &str) -> Config {
+    // Varnish cannot sit at the edge
+    let layer = if proxy_type == "varnish" { 2 } else { 1 };
+    parse_config(&format!(
+        r#"
+[project]
+name = "proxy-config-test"
+scaling = true
+
+[[proxies]]
+name = "edge"
+type = "{proxy_type}"
+layer = {layer}
+external_port = 80
+default_upstream = "{default_upstream}"
+
+[[proxies]]
+name = "proxy-2"
+type = "caddy"
+layer = {}
+instances = 3
+"#,
+        layer + 1
+    ))
+}
+
+#[test]
+fn test_scaled_layer_becomes_upstream_pool() {
+    let config = create_scaled_config("caddy", "http://proxy-2:80");
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(
+        caddyfile
+            .contains("reverse_proxy http://proxy-2:80 http://proxy-2-2:80 http://proxy-2-3:80 {")
+    );
+
+    let config = create_scaled_config("haproxy", "proxy-2:80");
+    let haproxy_cfg = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(haproxy_cfg.contains("    server default_1 proxy-2:80 check"));
+    assert!(haproxy_cfg.contains("    server default_3 proxy-2-3:80 check"));
+
+    let config = create_scaled_config("traefik", "http://proxy-2:80");
+    let traefik = render_traefik_dynamic(&config, 0);
+    assert!(traefik.contains(
+        "          - url: \"http://proxy-2:80\"\n          - url: \"http://proxy-2-2:80\"\n          - url: \"http://proxy-2-3:80\"\n"
+    ));
+
+    let config = create_scaled_config("varnish", "http://proxy-2:80");
+    let vcl = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(vcl.contains("import directors;"));
+    assert!(vcl.contains("backend default_backend_2 {\n    .host = \"proxy-2-2\";"));
+    assert!(vcl.contains("    default_director.add_backend(default_backend_3);\n"));
+    assert!(vcl.contains("set req.backend_hint = default_director.backend();"));
+}
+
+#[test]
+fn test_scaled_layer_nginx_upstream_block() {
+    let config = parse_config(
+        r#"
+[project]
+name = "proxy-config-test"
+scaling = true
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+default_upstream = "http://anubis:8080"
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+layer = 2
+instances = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+"#,
+    );
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    let default_conf = &configs["default.conf"];
+
+    assert!(default_conf.contains(
+        "upstream proxy_2_pool {\n    server proxy-2:80;\n    server proxy-2-2:80;\n}\n"
+    ));
+    assert!(default_conf.contains("    app.example.com http://proxy_2_pool;\n"));
+    assert!(default_conf.contains("    default http://anubis:8080;\n"));
+    assert!(!default_conf.contains("http://proxy-2:80"));
+
+    // Without scaling, the single container is addressed directly
+    let config = create_layered_config("varnish", "");
+    let vcl = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(!vcl.contains("directors"));
+    assert!(vcl.contains("set req.backend_hint = default_backend;"));
+}
+
+/// Helper function to create a layered config with a canary release
+fn create_canary_config(proxy_type: &str, weight: u8) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "proxy-config-test"
+
+[deployment.strategy]
+type = "canary"
+weight = {weight}
+
+[[proxies]]
+name = "proxy-2"
+type = "{proxy_type}"
+layer = 2
+default_upstream = "http://192.0.2.1:3000"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+candidate_upstream = "http://192.0.2.2:3000"
+"#
+    ))
+}
+
+#[test]
+fn test_canary_pools_nginx() {
+    let result = render_nginx_service(&create_canary_config("nginx", 20));
+
+    assert!(result.contains("upstream web_app_stable {\n    server 192.0.2.1:3000;"));
+    assert!(result.contains("upstream web_app_canary {\n    server 192.0.2.2:3000;"));
+    assert!(result.contains("split_clients \"${remote_addr}${http_user_agent}\" $web_app_pool"));
+    assert!(result.contains("20% web_app_canary;"));
+    assert!(result.contains("* web_app_stable;"));
+    assert!(result.contains("proxy_pass http://$web_app_pool;"));
+}
+
+#[test]
+fn test_canary_pools_other_proxies() {
+    let config = create_canary_config("caddy", 20);
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(caddyfile.contains("reverse_proxy http://192.0.2.1:3000 http://192.0.2.2:3000 {"));
+    assert!(caddyfile.contains("lb_policy weighted_round_robin 80 20"));
+
+    let config = create_canary_config("haproxy", 20);
+    let haproxy_cfg = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(haproxy_cfg.contains("server web_app_stable 192.0.2.1:3000 weight 80"));
+    assert!(haproxy_cfg.contains("server web_app_canary 192.0.2.2:3000 weight 20"));
+
+    let config = create_canary_config("traefik", 20);
+    let traefik = render_traefik_dynamic(&config, 0);
+    assert!(traefik.contains("weighted:"));
+    assert!(traefik.contains("- name: \"web-app-canary\"\n            weight: 20"));
+    assert!(traefik.contains("web-app-stable:\n      loadBalancer:"));
+}
+
+#[test]
+fn test_blue_green_routes_only_active_pool() {
+    let config = parse_config(
+        r#"
+[project]
+name = "proxy-config-test"
+
+[deployment.strategy]
+type = "blue_green"
+active = "green"
+
+[[proxies]]
+name = "proxy-2"
+type = "caddy"
+layer = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+candidate_upstream = "http://192.0.2.2:3000"
+"#,
+    );
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+
+    assert!(caddyfile.contains("reverse_proxy http://192.0.2.2:3000 {"));
+    assert!(caddyfile.contains("lb_policy round_robin"));
+
+    let config = Config {
+        proxies: vec![ProxyConfig {
+            proxy_type: crate::config::ProxyType::Nginx,
+            ..config.proxies[0].clone()
+        }],
+        ..config
+    };
+    let result = render_nginx_service(&config);
+    assert!(result.contains("* web_app_green;"));
+    assert!(!result.contains("% web_app"));
+}
+
+/// Helper function to create a layered config with the WAF enabled
+fn create_waf_config(proxy_type: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "proxy-config-test"
+
+[waf]
+enabled = true
+paranoia_level = 3
+
+[[proxies]]
+name = "proxy-2"
+type = "{proxy_type}"
+layer = 2
+default_upstream = "http://192.0.2.1:3000"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[services.waf]
+exclude_rules = [942100, 920350]
+
+[[services]]
+name = "uploads"
+domain = "uploads.example.com"
+upstream = "http://192.0.2.2:3000"
+
+[services.waf]
+enabled = false
+"#
+    ))
+}
+
+#[test]
+fn test_waf_nginx() {
+    let config = create_waf_config("nginx");
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+
+    let web_app = &configs["web_app.conf"];
+    assert!(web_app.contains("modsecurity on;"));
+    assert!(web_app.contains("modsecurity_rules_file /etc/modsecurity.d/setup.conf;"));
+    assert!(web_app.contains("modsecurity_rules 'SecRuleRemoveById 942100 920350';"));
+    assert!(!configs["uploads.conf"].contains("modsecurity"));
+
+    let dockerfile = crate::generators::DockerfileGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(dockerfile.contains("FROM owasp/modsecurity-crs:nginx-alpine"));
+    assert!(dockerfile.contains("BLOCKING_PARANOIA=3"));
+    assert!(dockerfile.contains("MODSEC_RULE_ENGINE=On"));
+    assert!(!dockerfile.contains("COPY nginx.conf"));
+}
+
+#[test]
+fn test_waf_caddy() {
+    let config = create_waf_config("caddy");
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+
+    assert!(caddyfile.contains("order coraza_waf first"));
+    assert_eq!(caddyfile.matches("coraza_waf {").count(), 1);
+    assert!(caddyfile.contains("setvar:tx.blocking_paranoia_level=3"));
+    assert!(caddyfile.contains("SecRuleEngine On"));
+    assert!(caddyfile.contains("SecAuditLog /var/log/waf/audit.log"));
+    assert!(caddyfile.contains("SecRuleRemoveById 942100 920350"));
+
+    let dockerfile = crate::generators::DockerfileGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(dockerfile.contains("xcaddy build --with github.com/corazawaf/coraza-caddy/v2"));
+    assert!(dockerfile.contains("COPY --from=builder /usr/bin/caddy /usr/bin/caddy"));
+}
+
+#[test]
+fn test_no_waf_by_default() {
+    let config = create_layered_config("caddy", "");
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+
+    assert!(!caddyfile.contains("coraza"));
+}
+
+/// Helper function to create an edge proxy serving the ops route to an
+/// HAProxy statistics page and a Traefik dashboard
+fn create_ops_config(edge: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "ops-test"
+
+[anubis]
+enabled = true
+
+[ops]
+domain = "ops.example.com"
+users = ['admin:$2y$10$abcdefghijklmnopqrstuuJ0Mtr3mpdwQEH7ETPZ6WWfxyIS5ZH.q']
+allow = ["10.0.0.0/8"]
+
+[[proxies]]
+name = "edge"
+type = "{edge}"
+layer = 1
+external_port = 80
+default_upstream = "http://anubis:8080"
+
+[[proxies]]
+name = "lb"
+type = "haproxy"
+layer = 2
+
+[proxies.stats]
+port = 8404
+
+[[proxies]]
+name = "router"
+type = "traefik"
+layer = 3
+
+[[services]]
+name = "web"
+domain = "example.com"
+upstream = "http://web:3000"
+"#
+    ))
+}
+
+#[test]
+fn test_ops_route_at_every_edge() {
+    const HASH: &str = "$2y$10$abcdefghijklmnopqrstuuJ0Mtr3mpdwQEH7ETPZ6WWfxyIS5ZH.q";
+
+    let config = create_ops_config("caddy");
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    for line in [
+        "\t@ops_denied {\n\t\thost ops.example.com\n\t\tnot remote_ip 10.0.0.0/8\n\t}\n",
+        &format!("\t\tbasic_auth {{\n\t\t\tadmin {HASH}\n\t\t}}\n"),
+        "\t\thandle_path /lb/* {\n\t\t\treverse_proxy http://lb:8404\n\t\t}\n",
+        "\t\thandle_path /router/* {\n\t\t\treverse_proxy http://router:8080\n\t\t}\n",
+    ] {
+        assert!(caddyfile.contains(line), "{line}");
+    }
+
+    let config = create_ops_config("nginx");
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    assert_eq!(configs["ops.htpasswd"], format!("admin:{HASH}\n"));
+    for line in [
+        "    server_name ops.example.com;\n",
+        "    allow 10.0.0.0/8;\n    deny all;\n",
+        "    auth_basic_user_file /etc/nginx/conf.d/ops.htpasswd;\n",
+        "    location ^~ /lb/ {\n        set $ops_upstream http://lb:8404;\n        rewrite ^/lb(/.*)$ $1 break;\n",
+    ] {
+        assert!(configs["default.conf"].contains(line), "{line}");
+    }
+
+    let config = create_ops_config("haproxy");
+    let haproxy_cfg = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    for line in [
+        &format!("userlist ops_users\n    user admin password {HASH}\n"),
+        "    http-request deny deny_status 403 if is_ops !{ src 10.0.0.0/8 }\n",
+        "    http-request auth realm cerberus-ops if is_ops !{ http_auth(ops_users) }\n",
+        "    http-request deny deny_status 404 if is_ops !{ path_beg /lb/ /router/ }\n",
+        "    use_backend ops_router_backend if is_ops { path_beg /router/ }\n",
+        "backend ops_lb_backend\n    http-request replace-path /lb(/.*) \\1\n    server ops_lb lb:8404\n",
+    ] {
+        assert!(haproxy_cfg.contains(line), "{line}");
+    }
+    // Inner layers do not serve the route
+    let inner = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[1])
+        .unwrap();
+    assert!(!inner.contains("is_ops"));
+
+    let config = create_ops_config("traefik");
+    let ops = ProxyConfigGenerator::new(&config)
+        .generate_traefik_configs(&config.proxies[0])
+        .unwrap()
+        .into_iter()
+        .find(|(path, _)| path.ends_with("ops.yml"))
+        .map(|(_, content)| content)
+        .expect("ops.yml should be generated");
+    for line in [
+        "      rule: \"Host(`ops.example.com`) && PathPrefix(`/lb/`)\"\n",
+        "        - ops-allow\n        - ops-auth\n        - ops_lb-strip\n",
+        &format!("          - \"admin:{HASH}\"\n"),
+        "          - url: \"http://edge:8080\"\n",
+    ] {
+        assert!(ops.contains(line), "{line}");
+    }
+}
+
+#[test]
+fn test_haproxy_stats_frontend_and_socket() {
+    let render = |stats: &str| {
+        let config = parse_config(&format!(
+            r#"
+[project]
+name = "stats-test"
+
+[[proxies]]
+name = "proxy-2"
+type = "haproxy"
+layer = 2
+default_upstream = "http://192.0.2.1:3000"
+{stats}
+"#
+        ));
+        ProxyConfigGenerator::new(&config)
+            .generate_for_proxy(&config.proxies[0])
+            .expect("Generation should succeed")
+    };
+
+    let haproxy_cfg = render("[proxies.stats]\nport = 9100\ncredentials = \"ops:s3cret\"");
+    assert!(haproxy_cfg.contains("stats socket \"/run/haproxy/${HOSTNAME}.sock\""));
+    assert!(haproxy_cfg.contains("listen stats\n    bind *:9100\n"));
+    assert!(haproxy_cfg.contains("    stats auth ops:s3cret\n    stats admin if TRUE\n"));
+
+    // Without credentials the page is read-only
+    let haproxy_cfg = render("[proxies.stats]");
+    assert!(haproxy_cfg.contains("bind *:8404"));
+    assert!(!haproxy_cfg.contains("stats admin"));
+
+    let haproxy_cfg = render("[proxies.stats]\nenabled = false");
+    assert!(!haproxy_cfg.contains("listen stats"));
+    assert!(haproxy_cfg.contains("stats socket /run/haproxy/admin.sock"));
+
+    // Unconfigured proxies keep the built-in stats page
+    let haproxy_cfg = render("");
+    assert!(haproxy_cfg.contains("listen stats\n    bind *:8404\n"));
+    assert!(haproxy_cfg.contains("stats socket /run/haproxy/admin.sock"));
+}
+
+#[test]
+fn test_docker_service_uses_declared_networks() {
+    let config = parse_config(
+        r#"
+[project]
+name = "network-test"
+
+[networks.dmz]
+
+[[proxies]]
+name = "edge"
+type = "caddy"
+external_port = 80
+networks = ["dmz"]
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+layer = 2
+"#,
+    );
+    let generator = ProxyConfigGenerator::new(&config);
+
+    let edge = generator
+        .generate_docker_service(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert_eq!(edge["networks"], serde_yaml::to_value(["dmz"]).unwrap());
+
+    // Without networks the proxy joins the default topology
+    let proxy_2 = generator
+        .generate_docker_service(&config.proxies[1])
+        .expect("Generation should succeed");
+    assert_eq!(
+        proxy_2["networks"],
+        serde_yaml::to_value(["front-net", "back-net"]).unwrap()
+    );
+
+    let resolver = NetworkResolver::new(&config);
+    assert_eq!(resolver.docker_name("dmz"), "network-test-dmz");
+    assert_eq!(resolver.docker_name("back-net"), "network-test-back");
+}
+
+#[test]
+fn test_proxies_listen_on_internal_port() {
+    for proxy_type in ["caddy", "haproxy", "traefik", "nginx"] {
+        let config = parse_config(&format!(
+            r#"
+[project]
+name = "listen-test"
+
+[[proxies]]
+name = "edge"
+type = "{proxy_type}"
+external_port = 8443
+internal_port = 8080
+layer = 2
+default_upstream = "http://192.0.2.1:3000"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#
+        ));
+        let generator = ProxyConfigGenerator::new(&config);
+        let rendered = if proxy_type == "nginx" {
+            render_nginx_service(&config)
+        } else {
+            generator
+                .generate_for_proxy(&config.proxies[0])
+                .expect("Generation should succeed")
+        };
+
+        assert!(
+            rendered.contains("8080"),
+            "{proxy_type} should listen on 8080"
+        );
+        assert!(
+            !rendered.contains("8443"),
+            "{proxy_type} should not use 8443"
+        );
+
+        let service = generator
+            .generate_docker_service(&config.proxies[0])
+            .expect("Generation should succeed");
+        assert_eq!(service["ports"][0], "8443:8080");
+    }
+}
+
+/// Helper function to create a config with a Varnish layer behind proxy-2
+fn create_varnish_config(proxy_type: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "varnish-test"
+
+[[proxies]]
+name = "proxy-2"
+type = "{proxy_type}"
+layer = 2
+default_upstream = "http://cache:6081"
+
+[[proxies]]
+name = "cache"
+type = "varnish"
+layer = 3
+internal_port = 6081
+
+[proxies.varnish]
+purge_acl = ["localhost", "172.16.0.0/12"]
+
+[[services]]
+name = "web-app"
+domain = "App.example.com"
+upstream = "http://web:3000"
+
+[services.cache]
+enabled = true
+ttl = "5m"
+stale_if_error = "1h"
+bypass_paths = ["/account", "/api/v1.0"]
+
+[[services]]
+name = "admin"
+domain = "admin.example.com"
+upstream = "admin"
+"#
+    ))
+}
+
+#[test]
+fn test_varnish_vcl() {
+    let config = create_varnish_config("caddy");
+    let generator = ProxyConfigGenerator::new(&config);
+    let vcl = generator
+        .generate_for_proxy(&config.proxies[1])
+        .expect("Should generate Varnish config");
+
+    assert!(vcl.starts_with("vcl 4.1;"));
+    assert!(vcl.contains("backend web_app_backend {\n    .host = \"web\";\n    .port = \"3000\";"));
+    assert!(vcl.contains("backend admin_backend {\n    .host = \"admin\";\n    .port = \"80\";"));
+    assert!(vcl.contains("    \"localhost\";\n    \"172.16.0.0\"/12;\n"));
+
+    // Services are routed on the client's host in one if/elsif chain
+    assert!(vcl.contains("    if (req.http.X-Cerberus-Host == \"app.example.com\") {"));
+    assert!(vcl.contains("    } elsif (req.http.X-Cerberus-Host == \"admin.example.com\") {"));
+    assert!(vcl.contains("return (synth(404, \"Unknown host\"));"));
+
+    // Authenticated paths bypass the cache; uncached services always pass
+    assert!(vcl.contains("if (req.url ~ \"^/account\") {"));
+    assert!(vcl.contains("if (req.url ~ \"^/api/v1\\.0\") {"));
+    assert!(vcl.contains("        set req.backend_hint = admin_backend;\n        return (pass);"));
+
+    // Per-service TTL with grace from the stale settings
+    assert!(
+        vcl.contains("    if (bereq.backend == web_app_backend) {\n        set beresp.ttl = 300s;")
+    );
+    assert!(vcl.contains("set beresp.grace = 3600s;"));
+    assert!(!vcl.contains("bereq.backend == admin_backend"));
+
+    assert_eq!(
+        ProxyConfigGenerator::get_file_extension("varnish"),
+        "default.vcl"
+    );
+}
+
+#[test]
+fn test_proxy_routes_services_through_varnish_layer() {
+    let config = create_varnish_config("caddy");
+    let generator = ProxyConfigGenerator::new(&config);
+    let caddyfile = generator
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate Caddy config");
+
+    assert!(caddyfile.contains("reverse_proxy http://cache:6081"));
+    assert!(!caddyfile.contains("reverse_proxy http://web:3000"));
+
+    let config = create_varnish_config("nginx");
+    let service_conf = render_nginx_service(&config);
+    assert!(service_conf.contains("proxy_pass http://cache:6081;"));
+    assert!(
+        !service_conf.contains("proxy_cache "),
+        "The cache layer, not proxy-2, stores responses"
+    );
+}
+
+/// Helper function to create a single edge proxy that drains on shutdown
+fn create_drain_config(proxy_type: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "drain-test"
+
+[[proxies]]
+name = "proxy"
+type = "{proxy_type}"
+external_port = 80
+default_upstream = "http://web:3000"
+
+[proxies.drain]
+timeout = "1m"
+"#
+    ))
+}
+
+#[test]
+fn test_drain_timeout_in_proxy_configs() {
+    let expected = [
+        ("caddy", "\tgrace_period 60s\n"),
+        ("haproxy", "    hard-stop-after 60s\n"),
+        ("traefik", "      lifeCycle:\n        graceTimeOut: 60s\n"),
+    ];
+
+    for (proxy_type, directive) in expected {
+        let config = create_drain_config(proxy_type);
+        let rendered = ProxyConfigGenerator::new(&config)
+            .generate_for_proxy(&config.proxies[0])
+            .expect("Should generate config");
+        assert!(
+            rendered.contains(directive),
+            "{proxy_type} config should contain {directive:?}"
+        );
+    }
+
+    // Without [proxies.drain] the proxies keep their defaults
+    let config = create_layered_config("haproxy", "");
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate HAProxy config");
+    assert!(!haproxy.contains("hard-stop-after"));
+}
+
+/// Helper function to create a single edge proxy with `[proxies.tuning]`
+fn create_tuning_config(proxy_type: &str, tuning: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "tuning-test"
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "proxy"
+type = "{proxy_type}"
+external_port = 80
+default_upstream = "http://web:3000"
+
+[proxies.tuning]
+{tuning}
+"#
+    ))
+}
+
+#[test]
+fn test_tuning_in_proxy_configs() {
+    let config = create_tuning_config(
+        "nginx",
+        "worker_processes = 4\nworker_connections = 4096\nkeepalive_timeout = \"2m\"\nclient_max_body_size = \"100MB\"",
+    );
+    let generator = ProxyConfigGenerator::new(&config);
+    let main = generator
+        .generate_nginx_main(&config.proxies[0])
+        .expect("Generation should succeed")
+        .expect("Worker tuning needs the main configuration");
+    assert!(main.contains("worker_processes 4;\n"));
+    assert!(main.contains("    worker_connections 4096;\n"));
+    assert!(main.contains("    keepalive_timeout 120s;\n"));
+    assert!(!main.contains("stream {"));
+    let configs = generator
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(configs["default.conf"].contains("\nclient_max_body_size 100m;\n"));
+
+    // Only the body size lives in conf.d; the image's nginx.conf is kept
+    let config = create_tuning_config("nginx", "client_max_body_size = \"1g\"");
+    let generator = ProxyConfigGenerator::new(&config);
+    assert!(
+        generator
+            .generate_nginx_main(&config.proxies[0])
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        generator
+            .generate_nginx_configs(&config.proxies[0])
+            .unwrap()["default.conf"]
+            .contains("\nclient_max_body_size 1g;\n")
+    );
+
+    let config = create_tuning_config(
+        "haproxy",
+        "maxconn = 20000\ntimeout_connect = \"3\"\ntimeout_client = \"5m\"\nkeepalive_timeout = \"10s\"",
+    );
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate HAProxy config");
+    assert!(haproxy.contains("    maxconn 20000\n"));
+    assert!(
+        haproxy
+            .contains("    timeout connect 3s\n    timeout client 300s\n    timeout server 50s\n")
+    );
+    assert!(haproxy.contains("    timeout http-keep-alive 10s\n"));
+
+    // Without [proxies.tuning] the stock values are kept
+    let config = create_layered_config("haproxy", "");
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate HAProxy config");
+    assert!(haproxy.contains("    maxconn 4096\n"));
+    assert!(
+        haproxy
+            .contains("    timeout connect 5s\n    timeout client 50s\n    timeout server 50s\n")
+    );
+    assert!(haproxy.contains("    timeout http-keep-alive 2s\n"));
+}
+
+#[test]
+fn test_drain_entrypoint_signals() {
+    let config = create_drain_config("nginx");
+    let generator = ProxyConfigGenerator::new(&config);
+    let script = generator
+        .generate_entrypoint(&config.proxies[0])
+        .expect("Drain should generate an entrypoint");
+
+    assert!(script.starts_with("#!/bin/sh\n"));
+    assert!(script.contains("    set -- nginx -g 'daemon off; worker_shutdown_timeout 60s;'\n"));
+    assert!(script.contains("trap 'kill -QUIT \"$pid\" 2>/dev/null' TERM INT\n"));
+    assert!(script.contains("trap 'kill -HUP \"$pid\" 2>/dev/null' HUP\n"));
+
+    let config = create_drain_config("haproxy");
+    let script = ProxyConfigGenerator::new(&config)
+        .generate_entrypoint(&config.proxies[0])
+        .expect("Drain should generate an entrypoint");
+    assert!(script.contains("trap 'kill -USR1 \"$pid\" 2>/dev/null' TERM INT\n"));
+    assert!(script.contains("trap 'kill -USR2 \"$pid\" 2>/dev/null' HUP\n"));
+
+    let config = create_layered_config("nginx", "");
+    assert!(
+        ProxyConfigGenerator::new(&config)
+            .generate_entrypoint(&config.proxies[0])
+            .is_none()
+    );
+}
+
+/// Service settings for the upstream health check tests
+const UPSTREAM_HEALTH: &str = r#"
+[services.health]
+path = "/ready"
+interval = "15s"
+timeout = "2s"
+expected_status = 204
+unhealthy_threshold = 4
+"#;
+
+#[test]
+fn test_upstream_health_checks_per_proxy_type() {
+    let expected = [
+        (
+            "haproxy",
+            vec![
+                "    option httpchk GET /ready\n",
+                "    http-check expect status 204\n",
+                "    timeout check 2s\n",
+                "check inter 15s rise 2 fall 4 maxconn 300",
+            ],
+        ),
+        (
+            "caddy",
+            vec![
+                "\t\t\thealth_uri /ready\n",
+                "\t\t\thealth_interval 15s\n",
+                "\t\t\thealth_timeout 2s\n",
+                "\t\t\thealth_status 204\n",
+                "\t\t\tmax_fails 4\n",
+            ],
+        ),
+        (
+            "traefik",
+            vec![
+                "        healthCheck:\n          path: \"/ready\"\n          interval: \"15s\"\n          timeout: \"2s\"\n          status: 204\n",
+            ],
+        ),
+    ];
+
+    for (proxy_type, directives) in expected {
+        let config = create_layered_config(proxy_type, UPSTREAM_HEALTH);
+        let rendered = match proxy_type {
+            "traefik" => render_traefik_dynamic(&config, 0),
+            _ => ProxyConfigGenerator::new(&config)
+                .generate_for_proxy(&config.proxies[0])
+                .expect("Should generate config"),
+        };
+        for directive in directives {
+            assert!(
+                rendered.contains(directive),
+                "{proxy_type} config should contain {directive:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_upstream_health_nginx() {
+    let config = create_layered_config("nginx", UPSTREAM_HEALTH);
+    let service_conf = render_nginx_service(&config);
+
+    assert!(service_conf.contains(
+        "upstream web_app_upstream {\n    server 192.0.2.1:3000 max_fails=4 fail_timeout=15s;\n}"
+    ));
+    assert!(service_conf.contains("proxy_connect_timeout 2s;"));
+    assert!(service_conf.contains("proxy_pass http://web_app_upstream;"));
+
+    // Without [services.health] nginx proxies straight to the upstream
+    let config = create_layered_config("nginx", "");
+    let service_conf = render_nginx_service(&config);
+    assert!(!service_conf.contains("max_fails"));
+    assert!(service_conf.contains("proxy_pass http://192.0.2.1:3000;"));
+}
+
+#[test]
+fn test_no_upstream_health_keeps_defaults() {
+    let config = create_layered_config("haproxy", "");
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate HAProxy config");
+    assert!(haproxy.contains("    option httpchk GET /health\n"));
+    assert!(haproxy.contains("check inter 5s rise 2 fall 3 maxconn 300"));
+
+    let config = create_layered_config("caddy", "");
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate Caddyfile");
+    assert!(caddyfile.contains("# health_uri /health"));
+}
+
+const FAILOVER: &str = r#"backup_upstreams = ["http://192.0.2.2:3000", "http://standby:8080/"]
+
+[services.failover]
+retries = 2
+timeout = "3s"
+next_upstream = ["error", "timeout", "http_503"]
+"#;
+
+#[test]
+fn test_failover_per_proxy_type() {
+    let expected = [
+        (
+            "nginx",
+            vec![
+                "upstream web_app_upstream {\n    server 192.0.2.1:3000;\n    server 192.0.2.2:3000 backup;\n    server standby:8080 backup;\n}",
+                "proxy_connect_timeout 3s;",
+                "proxy_next_upstream error timeout http_503;",
+                "proxy_next_upstream_tries 3;",
+                "proxy_pass http://web_app_upstream;",
+            ],
+        ),
+        (
+            "haproxy",
+            vec![
+                "    server web_app_backup_1 192.0.2.2:3000 backup check inter 5s rise 2 fall 3 maxconn 300\n",
+                "    server web_app_backup_2 standby:8080 backup check inter 5s rise 2 fall 3 maxconn 300\n",
+                "    retries 2\n    option redispatch 1\n",
+                "    retry-on conn-failure empty-response response-timeout 503\n",
+                "    timeout connect 3s\n",
+            ],
+        ),
+        (
+            "caddy",
+            vec![
+                "reverse_proxy http://192.0.2.1:3000 http://192.0.2.2:3000 http://standby:8080 {",
+                "\t\t\tlb_policy first\n\t\t\tlb_retries 2\n\t\t\tfail_duration 30s\n",
+                "\t\t\tunhealthy_status 503\n",
+                "\t\t\t\tdial_timeout 3s\n",
+            ],
+        ),
+        (
+            "traefik",
+            vec![
+                "        - web-app-retry\n",
+                "      retry:\n        attempts: 3\n",
+                "      failover:\n        service: \"web-app-primary\"\n        fallback: \"web-app-backup\"\n",
+                "          - url: \"http://192.0.2.2:3000\"\n          - url: \"http://standby:8080\"\n",
+                "        dialTimeout: \"3s\"\n",
+            ],
+        ),
+    ];
+
+    for (proxy_type, directives) in expected {
+        let config = create_layered_config(proxy_type, FAILOVER);
+        let rendered = match proxy_type {
+            "nginx" => render_nginx_service(&config),
+            "traefik" => render_traefik_dynamic(&config, 0),
+            _ => ProxyConfigGenerator::new(&config)
+                .generate_for_proxy(&config.proxies[0])
+                .expect("Should generate config"),
+        };
+        for directive in directives {
+            assert!(
+                rendered.contains(directive),
+                "{proxy_type} config should contain {directive:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_failover_defaults() {
+    // Backups alone fail over with the default policy
+    let config = create_layered_config(
+        "nginx",
+        &format!("backup_upstreams = [\"http://192.0.2.2:3000\"]\n{UPSTREAM_HEALTH}"),
+    );
+    let service_conf = render_nginx_service(&config);
+    assert!(service_conf.contains(
+        "    server 192.0.2.1:3000 max_fails=4 fail_timeout=15s;\n    server 192.0.2.2:3000 backup;\n"
+    ));
+    assert!(service_conf.contains("proxy_next_upstream error timeout http_502 http_503 http_504;"));
+    assert!(service_conf.contains("proxy_next_upstream_tries 2;"));
+
+    // Without either, nothing fails over
+    let config = create_layered_config("haproxy", "");
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate HAProxy config");
+    assert!(!haproxy.contains("retry-on"));
+    assert!(!haproxy.contains(" backup "));
+
+    let config = create_layered_config("caddy", "");
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate Caddyfile");
+    assert!(caddyfile.contains("lb_policy round_robin"));
+    assert!(!caddyfile.contains("lb_policy first"));
+}
+
+const TIMEOUTS: &str = r#"
+[services.timeouts]
+connect = "5s"
+read = "2m"
+send = "90"
+idle = "75s"
+retries = 2
+"#;
+
+#[test]
+fn test_timeouts_per_proxy_type() {
+    let expected = [
+        (
+            "nginx",
+            vec![
+                "    proxy_connect_timeout 5s;\n    proxy_send_timeout 90s;\n    proxy_read_timeout 120s;\n    keepalive_timeout 75s;\n",
+            ],
+        ),
+        (
+            "haproxy",
+            vec![
+                "    timeout connect 5s\n    retries 2\n    timeout server 120s\n    timeout http-keep-alive 75s\n",
+            ],
+        ),
+        (
+            "caddy",
+            vec![
+                "\t\t\tlb_policy round_robin\n\t\t\tlb_retries 2\n\t\t\ttransport http {\n\t\t\t\tdial_timeout 5s\n\t\t\t\tread_timeout 120s\n\t\t\t\twrite_timeout 90s\n\t\t\t\tkeepalive 75s\n\t\t\t}\n",
+            ],
+        ),
+        (
+            "traefik",
+            vec![
+                "        - web-app-retry\n",
+                "      retry:\n        attempts: 3\n",
+                "          - url: \"http://192.0.2.1:3000\"\n        serversTransport: \"web-app-transport\"\n",
+                "  serversTransports:\n    web-app-transport:\n      forwardingTimeouts:\n        dialTimeout: \"5s\"\n        responseHeaderTimeout: \"120s\"\n        idleConnTimeout: \"75s\"\n",
+            ],
+        ),
+    ];
+
+    for (proxy_type, directives) in expected {
+        let config = create_layered_config(proxy_type, TIMEOUTS);
+        let rendered = match proxy_type {
+            "nginx" => render_nginx_service(&config),
+            "traefik" => render_traefik_dynamic(&config, 0),
+            _ => ProxyConfigGenerator::new(&config)
+                .generate_for_proxy(&config.proxies[0])
+                .expect("Should generate config"),
+        };
+        for directive in directives {
+            assert!(
+                rendered.contains(directive),
+                "{proxy_type} config should contain {directive:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_timeouts_defaults() {
+    // Nginx keeps its 30s defaults at the http level, outside proxy_params.conf
+    let config = create_layered_config("nginx", "");
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(configs["timeouts.conf"].contains(
+        "proxy_connect_timeout 30s;\nproxy_send_timeout 30s;\nproxy_read_timeout 30s;\n"
+    ));
+    assert!(!configs["proxy_params.conf"].contains("timeout"));
+    assert!(!configs["web_app.conf"].contains("proxy_read_timeout"));
+
+    let config = create_layered_config("caddy", "");
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate Caddyfile");
+    assert!(!caddyfile.contains("transport http"));
+    assert!(!caddyfile.contains("lb_retries"));
+
+    let config = create_layered_config("traefik", "");
+    let dynamic = render_traefik_dynamic(&config, 0);
+    assert!(!dynamic.contains("serversTransport"));
+    assert!(!dynamic.contains("retry"));
+}
+
+#[test]
+fn test_timeouts_override_failover() {
+    let config = create_layered_config(
+        "nginx",
+        &format!("{FAILOVER}\n[services.timeouts]\nconnect = \"1s\"\nretries = 4\n"),
+    );
+    let service_conf = render_nginx_service(&config);
+    assert!(service_conf.contains("        proxy_connect_timeout 1s;\n"));
+    assert!(service_conf.contains("proxy_next_upstream_tries 5;"));
+    assert!(!service_conf.contains("3s"));
+
+    let config = create_layered_config(
+        "haproxy",
+        &format!("{FAILOVER}\n[services.timeouts]\nconnect = \"1s\"\nretries = 4\n"),
+    );
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate HAProxy config");
+    assert!(haproxy.contains("    retries 4\n    option redispatch 1\n"));
+    assert_eq!(haproxy.matches("    timeout connect 1s\n").count(), 1);
+    assert_eq!(haproxy.matches("    retries 4\n").count(), 1);
+}
+
+#[test]
+fn test_upstream_health_types() {
+    let tcp = UPSTREAM_HEALTH.replace("[services.health]", "[services.health]\ntype = \"tcp\"");
+    let config = create_layered_config("haproxy", &tcp);
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate HAProxy config");
+    // A bare check opens a connection once the default httpchk is dropped
+    assert!(
+        haproxy.contains("    # TCP connect checks\n    no option httpchk\n    timeout check 2s\n")
+    );
+    assert!(!haproxy.contains("GET /ready"));
+    assert!(haproxy.contains("check inter 15s rise 2 fall 4 maxconn 300"));
+
+    let config = create_layered_config("caddy", &tcp);
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate Caddyfile");
+    assert!(!caddyfile.contains("health_uri /ready"));
+    assert!(caddyfile.contains("\t\t\tmax_fails 4\n"));
+
+    // Only the default route keeps its HTTP check
+    let config = create_layered_config("traefik", &tcp);
+    assert_eq!(
+        render_traefik_dynamic(&config, 0)
+            .matches("healthCheck:")
+            .count(),
+        1
+    );
+
+    let none = "[services.health]\ntype = \"none\"";
+    let config = create_layered_config("haproxy", none);
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate HAProxy config");
+    assert!(haproxy.contains("    balance roundrobin\n    no option httpchk\n"));
+    assert!(haproxy.contains("    server web-app_1 http://192.0.2.1:3000 maxconn 300\n"));
+
+    let config = create_layered_config("traefik", none);
+    assert_eq!(
+        render_traefik_dynamic(&config, 0)
+            .matches("healthCheck:")
+            .count(),
+        1
+    );
+
+    let config = create_layered_config("nginx", none);
+    assert!(!render_nginx_service(&config).contains("max_fails"));
+}
+
+#[test]
+fn test_route_paths_at_layer_one() {
+    let toml = |proxy_type: &str| {
+        format!(
+            r#"
+[project]
+name = "route-paths-test"
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "proxy"
+type = "{proxy_type}"
+external_port = 80
+layer = 1
+
+[[proxies.routes]]
+type = "conditional"
+domain = "app.example.com"
+upstream = "http://proxy-2:80"
+
+[[proxies.routes.paths]]
+prefix = "/api/"
+protect = false
+
+[[proxies.routes.paths]]
+prefix = "/api/admin/"
+protect = true
+
+[proxies.routes.timeouts]
+read = "5m"
+retries = 1
+
+[[proxies]]
+name = "proxy-2"
+type = "caddy"
+layer = 2
+"#
+        )
+    };
+
+    let config = parse_config(&toml("nginx"));
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    let default_conf = &configs["default.conf"];
+    assert!(default_conf.contains("    server_name app.example.com;\n"));
+    let admin = default_conf
+        .find("location ^~ /api/admin/ {\n        proxy_pass http://anubis:8080;")
+        .expect("Protected prefix should go to Anubis");
+    let api = default_conf
+        .find("location ^~ /api/ {\n        proxy_pass http://proxy-2:80;")
+        .expect("Unprotected prefix should bypass Anubis");
+    let root = default_conf
+        .find("location ^~ / {\n        proxy_pass http://anubis:8080;")
+        .expect("Conditional route should challenge other paths");
+    assert!(admin < api && api < root);
+    assert!(default_conf.contains(
+        "    resolver 127.0.0.11 valid=30s;\n\n    # Upstream timeouts\n    proxy_read_timeout 300s;\n"
+    ));
+
+    let config = parse_config(&toml("caddy"));
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate Caddyfile");
+    assert!(caddyfile.contains("\t@route_app_example_com host app.example.com\n"));
+    assert!(caddyfile.contains(
+        "\t\t# Bypasses Anubis\n\t\thandle /api/* {\n\t\t\treverse_proxy http://proxy-2:80 {\n\t\t\t\tlb_retries 1\n\t\t\t\ttransport http {\n\t\t\t\t\tread_timeout 300s\n\t\t\t\t}\n\t\t\t}\n\t\t}\n"
+    ));
+    assert!(caddyfile.contains("\t\thandle /* {\n\t\t\treverse_proxy http://anubis:8080 {\n"));
+}
+
+#[test]
+fn test_streams_at_edge_proxy() {
+    let toml = |proxy_type: &str, game: &str| {
+        format!(
+            r#"
+[project]
+name = "streams-test"
+
+[[proxies]]
+name = "edge"
+type = "{proxy_type}"
+external_port = 80
+{game}
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[[services]]
+name = "postgres"
+domain = "db.example.com"
+upstream = "tcp://192.0.2.10:5432"
+protocol = "tcp"
+listen_port = 5432
+"#
+        )
+    };
+    let game = r#"
+[[proxies.routes]]
+type = "direct"
+domain = "game.example.com"
+upstream = "192.0.2.20:27015"
+protocol = "udp"
+listen_port = 27015
+"#;
+
+    let config = parse_config(&toml("nginx", game));
+    let generator = ProxyConfigGenerator::new(&config);
+    let main = generator
+        .generate_nginx_main(&config.proxies[0])
+        .expect("Generation should succeed")
+        .expect("Streams need the main configuration");
+    assert!(
+        main.contains("    include /etc/nginx/conf.d/*.conf;\n}\n\n# Layer-4 streams\nstream {")
+    );
+    assert!(main.contains(
+        "    upstream stream_postgres {\n        server 192.0.2.10:5432;\n    }\n\n    server {\n        listen 5432;\n        proxy_pass stream_postgres;\n    }"
+    ));
+    assert!(
+        main.contains("        listen 27015 udp;\n        proxy_pass stream_game_example_com;")
+    );
+    // The tcp service is not routed by host
+    let configs = generator
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(!configs["default.conf"].contains("db.example.com"));
+
+    let config = parse_config(&toml("haproxy", ""));
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate HAProxy config");
+    assert!(haproxy.contains(
+        "frontend stream_postgres_frontend\n    mode tcp\n    option tcplog\n    bind *:5432\n    default_backend stream_postgres_backend\n"
+    ));
+    assert!(
+        haproxy.contains(
+            "    server stream_postgres_1 192.0.2.10:5432 check inter 5s rise 2 fall 3\n"
+        )
+    );
+    assert!(!haproxy.contains("acl is_postgres"));
+
+    let config = parse_config(&toml("traefik", game));
+    let files: HashMap<PathBuf, String> = ProxyConfigGenerator::new(&config)
+        .generate_traefik_configs(&config.proxies[0])
+        .expect("Should generate Traefik configs")
+        .into_iter()
+        .collect();
+    assert!(files[Path::new("traefik.yml")].contains(
+        "  stream_postgres:\n    address: \":5432\"\n\n  # Layer-4 stream for game.example.com\n  stream_game_example_com:\n    address: \":27015/udp\"\n"
+    ));
+    let streams = &files[Path::new("dynamic/streams.yml")];
+    assert!(streams.contains(
+        "tcp:\n  routers:\n    # postgres\n    stream_postgres:\n      entryPoints:\n        - stream_postgres\n      rule: \"HostSNI(`*`)\"\n"
+    ));
+    assert!(
+        streams
+            .contains("udp:\n  routers:\n    # game.example.com\n    stream_game_example_com:\n")
+    );
+    assert!(streams.contains("          - address: \"192.0.2.20:27015\"\n"));
+    assert!(!files.contains_key(Path::new("dynamic/postgres.yml")));
+}
+
+#[test]
+fn test_streams_routed_by_sni() {
+    let config = parse_config(
+        r#"
+[project]
+name = "sni-test"
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+
+[[services]]
+name = "postgres"
+domain = "db.example.com"
+upstream = "192.0.2.10:5432"
+protocol = "tcp"
+listen_port = 5432
+
+[[services]]
+name = "tenants"
+domain = "tenant.example.com"
+domains = ["*.tenants.example.com"]
+upstream = "192.0.2.11:5432"
+protocol = "tcp"
+listen_port = 5432
+"#,
+    );
+    let main = ProxyConfigGenerator::new(&config)
+        .generate_nginx_main(&config.proxies[0])
+        .expect("Generation should succeed")
+        .expect("Streams need the main configuration");
+    assert!(main.contains(
+        "    map $ssl_preread_server_name $stream_postgres_sni {\n        db.example.com stream_postgres;\n        tenant.example.com stream_tenants;\n        ~^[^.]+\\.tenants\\.example\\.com$ stream_tenants;\n    }\n\n    server {\n        listen 5432;\n        ssl_preread on;\n        proxy_pass $stream_postgres_sni;\n    }"
+    ));
+    assert_eq!(main.matches("listen 5432").count(), 1);
+
+    let mut config = config;
+    config.proxies[0].proxy_type = ProxyType::HaProxy;
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate HAProxy config");
+    assert!(haproxy.contains(
+        "    tcp-request content accept if { req_ssl_hello_type 1 }\n    use_backend stream_postgres_backend if { req_ssl_sni -i db.example.com }\n    use_backend stream_tenants_backend if { req_ssl_sni -i tenant.example.com }\n    use_backend stream_tenants_backend if { req_ssl_sni -m reg -i ^[^.]+\\.tenants\\.example\\.com$ }\n"
+    ));
+    assert_eq!(haproxy.matches("bind *:5432").count(), 1);
+
+    config.proxies[0].proxy_type = ProxyType::Traefik;
+    let files: HashMap<PathBuf, String> = ProxyConfigGenerator::new(&config)
+        .generate_traefik_configs(&config.proxies[0])
+        .expect("Should generate Traefik configs")
+        .into_iter()
+        .collect();
+    assert_eq!(
+        files[Path::new("traefik.yml")]
+            .matches("address: \":5432\"")
+            .count(),
+        1
+    );
+    assert!(files[Path::new("dynamic/streams.yml")].contains(
+        "    stream_tenants:\n      entryPoints:\n        - stream_postgres\n      rule: \"HostSNI(`tenant.example.com`) || HostSNIRegexp(`^[^.]+\\\\.tenants\\\\.example\\\\.com$`)\"\n      service: stream_tenants\n      tls:\n        passthrough: true\n"
+    ));
+}
+
+#[test]
+fn test_multi_domain_services() {
+    let service = "domains = [\"www.example.com\", \"*.apps.example.com\"]";
+
+    let config = create_layered_config("nginx", service);
+    assert!(
+        render_nginx_service(&config).contains(
+            "server_name app.example.com www.example.com ~^[^.]+\\.apps\\.example\\.com$;"
+        )
+    );
+
+    let config = create_layered_config("haproxy", service);
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate HAProxy config");
+    assert!(haproxy.contains(
+        "    acl is_web-app hdr(host) -i app.example.com www.example.com\n    acl is_web-app hdr_reg(host) -i ^[^.]+\\.apps\\.example\\.com$\n"
+    ));
+
+    let config = create_layered_config("caddy", service);
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate Caddyfile");
+    assert!(
+        caddyfile.contains("\t@web-app host app.example.com www.example.com *.apps.example.com\n")
+    );
+
+    let config = create_layered_config("traefik", service);
+    assert!(render_traefik_dynamic(&config, 0).contains(
+        "      rule: \"Host(`app.example.com`) || Host(`www.example.com`) || HostRegexp(`^[^.]+\\\\.apps\\\\.example\\\\.com$`)\"\n"
+    ));
+
+    let config = create_layered_config("varnish", service);
+    let vcl = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate VCL");
+    assert!(vcl.contains(
+        "    if (req.http.X-Cerberus-Host == \"app.example.com\" || req.http.X-Cerberus-Host == \"www.example.com\" || req.http.X-Cerberus-Host ~ \"^[^.]+\\.apps\\.example\\.com$\") {\n"
+    ));
+
+    // Layer 1 maps every domain to the next layer
+    let mut config = create_layered_config("nginx", service);
+    config.proxies[0].layer = Some(1);
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(configs["default.conf"].contains(
+        "    www.example.com http://proxy-2:80;\n    ~^[^.]+\\.apps\\.example\\.com$ http://proxy-2:80;\n"
+    ));
+}
+
+/// Helper function to create an edge proxy and an inner proxy with `[tls]` settings
+fn create_https_config(proxy_type: &str, tls: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "proxy-config-test"
+
+{tls}
+
+[[proxies]]
+name = "proxy"
+type = "{proxy_type}"
+external_port = 80
+layer = 1
+default_upstream = "http://proxy-2:80"
+
+[[proxies]]
+name = "proxy-2"
+type = "{proxy_type}"
+layer = 2
+default_upstream = "http://192.0.2.1:3000"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#
+    ))
+}
+
+#[test]
+fn test_https_redirect_nginx() {
+    let config = create_https_config(
+        "nginx",
+        "[tls.redirect_http]\nhttps_port = 8443\nstatus = 308",
+    );
+    let generator = ProxyConfigGenerator::new(&config);
+
+    let edge = generator
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    let https = &edge["https.inc"];
+    assert!(https.contains("if ($http_x_forwarded_proto != \"https\")"));
+    assert!(https.contains("if ($uri ~ \"^/\\.well-known/acme-challenge/\")"));
+    assert!(https.contains("return 308 https://$host:8443$request_uri;"));
+    assert!(https.contains(
+        "add_header Strict-Transport-Security \"max-age=31536000; includeSubDomains; preload\" always;"
+    ));
+    assert!(edge["default.conf"].contains("include /etc/nginx/conf.d/https.inc;"));
+
+    // Inner layers only ever see plain HTTP from the edge
+    let inner = generator
+        .generate_nginx_configs(&config.proxies[1])
+        .unwrap();
+    assert!(!inner.contains_key("https.inc"));
+    assert!(!inner["web_app.conf"].contains("https.inc"));
+}
+
+#[test]
+fn test_https_redirect_other_proxies() {
+    let tls = "[tls.redirect_http]";
+
+    let config = create_https_config("caddy", tls);
+    let generator = ProxyConfigGenerator::new(&config);
+    let edge = generator.generate_for_proxy(&config.proxies[0]).unwrap();
+    assert!(edge.contains("not header X-Forwarded-Proto https"));
+    assert!(edge.contains("not path /.well-known/acme-challenge/* /health"));
+    assert!(edge.contains("redir @http_to_https https://{host}{uri} 301"));
+    let inner = generator.generate_for_proxy(&config.proxies[1]).unwrap();
+    assert!(!inner.contains("redir"));
+
+    let config = create_https_config("haproxy", tls);
+    let edge = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(edge.contains(
+        "http-request redirect location https://%[req.hdr(host),field(1,:)]%[capture.req.uri] code 301 \
+if !{ req.hdr(X-Forwarded-Proto) -i https } !{ path_beg /.well-known/acme-challenge/ }"
+    ));
+
+    let config = create_https_config("traefik", tls);
+    let files = ProxyConfigGenerator::new(&config)
+        .generate_traefik_configs(&config.proxies[0])
+        .unwrap();
+    let file = |name: &str| {
+        files
+            .iter()
+            .find(|(path, _)| path.ends_with(name))
+            .map(|(_, content)| content.clone())
+            .unwrap()
+    };
+    assert!(file("traefik.yml").contains("forwardedHeaders:"));
+    assert!(
+        file("middlewares.yml")
+            .contains("redirectScheme:\n        scheme: https\n        permanent: true")
+    );
+    assert!(file("default.yml").contains(
+        "rule: \"!Header(`X-Forwarded-Proto`, `https`) && !PathPrefix(`/.well-known/acme-challenge/`) && !Path(`/health`)\""
+    ));
+    let inner = render_traefik_dynamic(&config, 1);
+    assert!(!inner.contains("https-redirect"));
+}
+
+#[test]
+fn test_hsts_options() {
+    let tls = "[tls.hsts]\nmax_age = 86400\nincludeSubdomains = false\npreload = false";
+
+    let config = create_https_config("caddy", tls);
+    let caddy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(caddy.contains("Strict-Transport-Security \"max-age=86400\""));
+    assert!(!caddy.contains("redir"));
+
+    let config = create_https_config("traefik", tls);
+    let traefik = render_traefik_dynamic(&config, 0);
+    assert!(traefik.contains("stsIncludeSubdomains: false"));
+    assert!(traefik.contains("stsSeconds: 86400"));
+
+    let config = create_https_config("haproxy", "[tls.hsts]\nenabled = false");
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(!haproxy.contains("Strict-Transport-Security"));
+
+    let config = create_https_config("nginx", "[tls.hsts]\nenabled = false");
+    let nginx = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    assert!(!nginx.contains_key("https.inc"));
+    assert!(!nginx["default.conf"].contains("https.inc"));
+}
+
+#[test]
+fn test_service_headers_all_proxies() {
+    let headers = r#"
+[services.headers.request]
+set = { X-Tenant = "acme" }
+add = { Forwarded = "for=edge" }
+remove = ["X-Debug"]
+
+[services.headers.response]
+set = { Cache-Control = "no-store" }
+add = { Vary = "Accept-Language" }
+remove = ["X-Powered-By"]
+"#;
+
+    let config = create_layered_config("caddy", headers);
+    let caddy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    for line in [
+        "header_up X-Tenant \"acme\"",
+        "header_up +Forwarded \"for=edge\"",
+        "header_up -X-Debug",
+        "header_down Cache-Control \"no-store\"",
+        "header_down +Vary \"Accept-Language\"",
+        "header_down -X-Powered-By",
+    ] {
+        assert!(caddy.contains(line), "{line}");
+    }
+
+    let config = create_layered_config("nginx", headers);
+    let nginx = render_nginx_service(&config);
+    for line in [
+        "    proxy_set_header X-Tenant \"acme\";",
+        "    proxy_set_header Forwarded \"for=edge\";",
+        "    proxy_set_header X-Debug \"\";",
+        "    proxy_hide_header Cache-Control;\n    add_header Cache-Control \"no-store\" always;",
+        "    add_header Vary \"Accept-Language\" always;",
+        "    proxy_hide_header X-Powered-By;",
+    ] {
+        assert!(nginx.contains(line), "{line}");
+    }
+
+    let config = create_layered_config("haproxy", headers);
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    for line in [
+        "    http-request set-header X-Tenant \"acme\"",
+        "    http-request add-header Forwarded \"for=edge\"",
+        "    http-request del-header X-Debug",
+        "    http-response set-header Cache-Control \"no-store\"",
+        "    http-response add-header Vary \"Accept-Language\"",
+        "    http-response del-header X-Powered-By",
+    ] {
+        assert!(haproxy.contains(line), "{line}");
+    }
+
+    let config = create_layered_config("varnish", headers);
+    let varnish = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    for line in [
+        "        set bereq.http.X-Tenant = {\"acme\"};",
+        "            set bereq.http.Forwarded = bereq.http.Forwarded + {\", for=edge\"};",
+        "        unset bereq.http.X-Debug;",
+        "        set beresp.http.Cache-Control = {\"no-store\"};",
+        "        unset beresp.http.X-Powered-By;",
+    ] {
+        assert!(varnish.contains(line), "{line}");
+    }
+
+    let config = create_layered_config("traefik", headers);
+    let files = ProxyConfigGenerator::new(&config)
+        .generate_traefik_configs(&config.proxies[0])
+        .unwrap();
+    let parsed: serde_yaml::Value = serde_yaml::from_str(&files[3].1).unwrap();
+    let middleware = &parsed["http"]["middlewares"]["web-app-headers"]["headers"];
+    assert_eq!(middleware["customRequestHeaders"]["X-Tenant"], "acme");
+    assert_eq!(middleware["customRequestHeaders"]["Forwarded"], "for=edge");
+    assert_eq!(middleware["customRequestHeaders"]["X-Debug"], "");
+    assert_eq!(middleware["customResponseHeaders"]["X-Powered-By"], "");
+
+    // HAProxy reads % as a log-format variable
+    let config = create_layered_config(
+        "haproxy",
+        "\n[services.headers.response.set]\nX-Share = \"50%\"\n",
+    );
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(haproxy.contains("http-response set-header X-Share \"50%%\""));
+}
+
+#[test]
+fn test_hardened_proxies_do_not_switch_users() {
+    let mut config = create_layered_config("haproxy", "");
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(haproxy.contains("    chroot /var/lib/haproxy\n"));
+    assert!(haproxy.contains("    user haproxy\n"));
+
+    // A non-root HAProxy can neither chroot nor change its user
+    config.security.hardening.enabled = true;
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(!haproxy.contains("chroot"));
+    assert!(!haproxy.contains("user haproxy"));
+
+    let mut config = create_layered_config(
+        "nginx",
+        "[services.compression]\nalgorithms = [\"brotli\", \"gzip\"]",
+    );
+    config.security.hardening.enabled = true;
+    let main = ProxyConfigGenerator::new(&config)
+        .generate_nginx_main(&config.proxies[0])
+        .unwrap()
+        .expect("Modules need the main configuration");
+    assert!(!main.contains("user nginx;"));
+    let dockerfile = crate::generators::DockerfileGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(dockerfile.contains("\nUSER nginx\n"));
+}
+
+/// Helper function to create a config whose proxy and service inject snippets
+fn create_snippets_config(
+    proxy_type: &str,
+    proxy_snippets: &str,
+    service_snippets: &str,
+) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "snippets-test"
+
+[[proxies]]
+name = "proxy-2"
+type = "{proxy_type}"
+layer = 2
+default_upstream = "http://192.0.2.1:3000"
+
+[proxies.snippets]
+{proxy_snippets}
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[services.snippets]
+{service_snippets}
+"#
+    ))
+}
+
+#[test]
+fn test_snippets_in_proxy_configs() {
+    let config = create_snippets_config(
+        "nginx",
+        "http = \"lua_shared_dict limits 10m;\"\nserver = \"access_by_lua_block {\\n    ngx.exit(ngx.OK)\\n}\"\nlocation = \"proxy_buffering off;\"",
+        "http = \"map $http_x_tenant $tenant { default main; }\"\nserver = \"error_page 404 /404.html;\"\nlocation = \"proxy_read_timeout 300s;\"",
+    );
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    let snippets = &configs["snippets.conf"];
+    assert!(snippets.contains("# From proxy proxy-2\nlua_shared_dict limits 10m;\n"));
+    assert!(
+        snippets.contains("# From service web-app\nmap $http_x_tenant $tenant { default main; }\n")
+    );
+    let service = &configs["web_app.conf"];
+    assert!(service.contains("    access_by_lua_block {\n        ngx.exit(ngx.OK)\n    }\n"));
+    assert!(service.contains("    error_page 404 /404.html;\n"));
+    assert!(
+        service
+            .contains("        proxy_buffering off;\n        proxy_read_timeout 300s;\n    }\n}")
+    );
+
+    // Layer 1 routes every service onwards, so only its own snippets apply
+    let config = parse_config(
+        r#"
+[project]
+name = "snippets-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+default_upstream = "http://proxy-2:80"
+
+[proxies.snippets]
+server = "limit_req zone=edge burst=20;"
+location = "proxy_buffering off;"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[services.snippets]
+http = "limit_req_zone $binary_remote_addr zone=edge:10m rate=10r/s;"
+"#,
+    );
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(!configs.contains_key("snippets.conf"));
+    let default = &configs["default.conf"];
+    assert!(default.contains(
+        "    # Raw directives from [proxies.snippets]\n    limit_req zone=edge burst=20;\n"
+    ));
+    assert!(default.contains(
+        "        include /etc/nginx/conf.d/proxy_params.conf;\n        proxy_buffering off;\n    }"
+    ));
+
+    let config = create_snippets_config(
+        "caddy",
+        "http = \"debug\"\nserver = \"request_body {\\n\\tmax_size 10MB\\n}\"",
+        "location = \"header X-Tenant main\"",
+    );
+    let caddy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate Caddy config");
+    assert!(caddy.contains("\t# Raw directives from [proxies.snippets]\n\tdebug\n}\n"));
+    assert!(caddy.contains("\trequest_body {\n\t\tmax_size 10MB\n\t}\n"));
+    assert!(caddy.contains("\t\theader X-Tenant main\n\n\t\treverse_proxy "));
+
+    let config = create_snippets_config(
+        "haproxy",
+        "http = \"tune.bufsize 32768\"\nserver = \"http-request set-var(txn.edge) str(proxy-2)\"",
+        "location = \"http-response set-header X-Tenant main\"",
+    );
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate HAProxy config");
+    assert!(haproxy.contains("    tune.ssl.default-dh-param 2048\n\n    # Raw directives from [proxies.snippets]\n    tune.bufsize 32768\n"));
+    assert!(haproxy.contains("    http-request set-var(txn.edge) str(proxy-2)\n"));
+    let backend = haproxy.split("\nbackend web-app_backend").nth(1).unwrap();
+    assert!(backend.contains("    http-response set-header X-Tenant main\n"));
+}
+
+#[test]
+fn test_fediverse_template_routing_layer1_nginx() {
+    let config_for = |service: &str| {
+        parse_config(&format!(
+            r#"
+[project]
+name = "proxy-config-test"
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+layer = 1
+default_upstream = "http://anubis:8080"
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+layer = 2
+
+{service}
+"#
+        ))
+    };
+
+    let config = config_for(
+        r#"[[services]]
+name = "social"
+template = "mastodon"
+domain = "social.example.com"
+upstream = "http://web:3000""#,
+    );
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    let default_conf = &configs["default.conf"];
+    assert!(default_conf.contains("# Federation endpoints of social skip the challenge"));
+    assert!(default_conf.contains(&format!(
+        "location ~ ^/({}) {{",
+        AppTemplate::Mastodon.federation_paths().unwrap()
+    )));
+
+    // A service is no longer special just because it is named misskey
+    let config = config_for(
+        r#"[[services]]
+name = "misskey"
+domain = "mi.example.com"
+upstream = "http://misskey:3000""#,
+    );
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(!configs["default.conf"].contains("# Federation endpoints"));
+
+    let config = config_for(
+        r#"[[services]]
+name = "git"
+template = "gitea"
+domain = "git.example.com"
+upstream = "http://gitea:3000""#,
+    );
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(!configs["default.conf"].contains("# Federation endpoints"));
+}
+
+#[test]
+fn test_layer1_default_upstream_is_anubis() {
+    for proxy_type in ["caddy", "haproxy", "traefik"] {
+        let config = parse_config(&format!(
+            r#"
+[project]
+name = "anubis-edge-test"
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "edge"
+type = "{proxy_type}"
+external_port = 80
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+layer = 2
+default_upstream = "http://192.0.2.1:3000"
+"#
+        ));
+        let generator = ProxyConfigGenerator::new(&config);
+        let edge = match proxy_type {
+            "traefik" => render_traefik_dynamic(&config, 0),
+            _ => generator
+                .generate_for_proxy(&config.proxies[0])
+                .expect("Generation should succeed"),
+        };
+        assert!(edge.contains("anubis:8080"), "{proxy_type}:\n{edge}");
+        assert!(!edge.contains("localhost:3000"), "{proxy_type}:\n{edge}");
+
+        let service = generator
+            .generate_docker_service(&config.proxies[0])
+            .expect("Generation should succeed");
+        assert_eq!(service["depends_on"][0], "anubis", "{proxy_type}");
+    }
+}
+
+#[test]
+fn test_ipv6_listeners() {
+    let content = r#"
+[project]
+name = "ipv6-test"
+ipv6 = true
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+
+[[proxies]]
+name = "proxy-2"
+type = "haproxy"
+layer = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+    let config = parse_config(content);
+    let generator = ProxyConfigGenerator::new(&config);
+    let configs = generator
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(
+        configs["default.conf"]
+            .contains("    listen 80 default_server;\n    listen [::]:80 default_server;\n")
+    );
+    let haproxy = generator
+        .generate_for_proxy(&config.proxies[1])
+        .expect("Generation should succeed");
+    assert!(haproxy.contains("    bind :::80 v4v6\n"));
+    assert!(!haproxy.contains("bind *:"));
+
+    let config = parse_config(&content.replace("ipv6 = true", ""));
+    let generator = ProxyConfigGenerator::new(&config);
+    let configs = generator
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(!configs["default.conf"].contains("[::]"));
+    let haproxy = generator
+        .generate_for_proxy(&config.proxies[1])
+        .expect("Generation should succeed");
+    assert!(haproxy.contains("    bind *:80\n"));
+}
+
+#[test]
+fn test_error_pages_handlers() {
+    let error_pages = r#"
+[error_pages]
+maintenance = true
+
+[error_pages.pages.404]
+html = "<h1>Not found</h1>"
+"#;
+
+    let config = create_layered_config("nginx", error_pages);
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    let include = &configs["error_pages.inc"];
+    assert!(include.contains("proxy_intercept_errors on;\n"));
+    assert!(include.contains("error_page 404 /.cerberus-errors/404.html;\n"));
+    assert!(include.contains("error_page 504 /.cerberus-errors/504.html;\n"));
+    assert!(include.contains("    alias /usr/share/cerberus/error-pages/;\n"));
+    assert!(configs["web_app.conf"].contains("    include /etc/nginx/conf.d/error_pages.inc;\n"));
+
+    let config = create_layered_config("haproxy", error_pages);
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(haproxy.contains("    errorfile 404 /usr/share/cerberus/error-pages/404.http\n"));
+    assert!(haproxy.contains("    errorfile 503 /usr/share/cerberus/error-pages/503.http\n"));
+
+    let config = create_layered_config("caddy", error_pages);
+    let caddy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(caddy.contains("\thandle_errors 404 502 503 504 {\n"));
+    assert!(caddy.contains("\t\troot * /usr/share/cerberus/error-pages\n"));
+
+    let config = create_layered_config("varnish", error_pages);
+    let varnish = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(varnish.contains("sub vcl_backend_error {\n"));
+    assert!(
+        varnish.contains("synthetic(std.fileread(\"/usr/share/cerberus/error-pages/503.html\"));")
+    );
+
+    let config = create_layered_config("traefik", error_pages);
+    let dynamic = render_traefik_dynamic(&config, 0);
+    let parsed: serde_yaml::Value = serde_yaml::from_str(
+        &ProxyConfigGenerator::new(&config)
+            .generate_traefik_configs(&config.proxies[0])
+            .expect("Generation should succeed")
+            .into_iter()
+            .find(|(path, _)| path.ends_with("middlewares.yml"))
+            .expect("Middlewares should be generated")
+            .1,
+    )
+    .expect("Middlewares should be valid YAML");
+    let errors = &parsed["http"]["middlewares"]["error-pages"]["errors"];
+    assert_eq!(errors["status"][0], "404");
+    assert_eq!(errors["query"], "/{status}.html");
+    assert_eq!(
+        parsed["http"]["services"]["error-pages"]["loadBalancer"]["servers"][0]["url"],
+        "http://error-pages:8080"
+    );
+    assert!(dynamic.contains("- error-pages\n"));
+
+    let config = create_layered_config("nginx", "");
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(!configs.contains_key("error_pages.inc"));
+    assert!(!configs["web_app.conf"].contains("error_pages.inc"));
+}
+
+/// Helper function to create a config whose two services share an upstream pool
+fn create_pool_config(proxy_type: &str, weight: u32, pool_extra: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "upstream-pool-test"
+
+[[proxies]]
+name = "proxy-2"
+type = "{proxy_type}"
+layer = 2
+
+[[upstreams]]
+name = "app-cluster"
+{pool_extra}
+servers = [
+    {{ url = "http://192.0.2.1:3000", weight = {weight} }},
+    {{ url = "http://192.0.2.2:3000/" }},
+]
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "app-cluster"
+
+[[services]]
+name = "admin"
+domain = "admin.example.com"
+upstream = "app-cluster"
+"#
+    ))
+}
+
+#[test]
+fn test_upstream_pool_nginx() {
+    let config = create_pool_config("nginx", 3, "lb_policy = \"least_conn\"\nkeepalive = 16");
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+
+    // One block, shared by both services
+    let upstreams = &configs["upstreams.conf"];
+    assert_eq!(upstreams.matches("upstream pool_app_cluster {").count(), 1);
+    assert!(upstreams.contains(
+        "upstream pool_app_cluster {\n    least_conn;\n    server 192.0.2.1:3000 weight=3;\n    server 192.0.2.2:3000;\n    keepalive 16;\n}\n"
+    ));
+    for file in ["web_app.conf", "admin.conf"] {
+        let service = &configs[file];
+        assert!(service.contains("        proxy_pass http://pool_app_cluster;\n"));
+        assert!(service.contains("    proxy_set_header Connection \"\";\n"));
+        assert!(!service.contains("upstream pool_app_cluster"));
+    }
+
+    // Round robin is nginx's default and needs no directive
+    let config = create_pool_config("nginx", 3, "");
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(
+        configs["upstreams.conf"].contains("upstream pool_app_cluster {\n    server 192.0.2.1")
+    );
+    assert!(!configs["web_app.conf"].contains("Connection \"\""));
+
+    let config = create_layered_config("nginx", "");
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(!configs.contains_key("upstreams.conf"));
+}
+
+#[test]
+fn test_upstream_pool_other_proxies() {
+    let config = create_pool_config("haproxy", 3, "lb_policy = \"ip_hash\"\nkeepalive = 16");
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate config");
+    assert_eq!(haproxy.matches("    balance source\n").count(), 2);
+    assert!(haproxy.contains(
+        "    server pool_app_cluster_1 192.0.2.1:3000 weight 3 check inter 5s rise 2 fall 3 maxconn 300 pool-max-conn 16\n"
+    ));
+    assert!(haproxy.contains("    server pool_app_cluster_2 192.0.2.2:3000 weight 1 check"));
+
+    let config = create_pool_config("caddy", 3, "keepalive = 16");
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate Caddyfile");
+    assert_eq!(
+        caddyfile
+            .matches("reverse_proxy http://192.0.2.1:3000 http://192.0.2.2:3000 {")
+            .count(),
+        2
+    );
+    assert!(caddyfile.contains("\t\t\tlb_policy weighted_round_robin 3 1\n"));
+    assert!(caddyfile.contains("\t\t\t\tkeepalive_idle_conns_per_host 16\n"));
+
+    let config = create_pool_config("caddy", 3, "lb_policy = \"least_conn\"");
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate Caddyfile");
+    assert!(caddyfile.contains("\t\t\tlb_policy least_conn\n"));
+    assert!(!caddyfile.contains("transport http"));
+
+    // Traefik weighs servers through a weighted service
+    let config = create_pool_config("traefik", 3, "");
+    let dynamic = render_traefik_dynamic(&config, 0);
+    assert!(dynamic.contains(
+        "      weighted:\n        services:\n          - name: \"web-app-server-1\"\n            weight: 3\n          - name: \"web-app-server-2\"\n            weight: 1\n"
+    ));
+    assert!(dynamic.contains(
+        "    admin-server-2:\n      loadBalancer:\n        servers:\n          - url: \"http://192.0.2.2:3000\"\n"
+    ));
+
+    let config = create_pool_config("traefik", 1, "");
+    let dynamic = render_traefik_dynamic(&config, 0);
+    assert!(dynamic.contains(
+        "        servers:\n          - url: \"http://192.0.2.1:3000\"\n          - url: \"http://192.0.2.2:3000\"\n"
+    ));
+}
+
+#[test]
+fn test_upstream_pool_route_paths() {
+    let toml = |proxy_type: &str| {
+        format!(
+            r#"
+[project]
+name = "route-pool-test"
+
+[anubis]
+enabled = true
+
+[[upstreams]]
+name = "api-cluster"
+keepalive = 8
+servers = [{{ url = "http://192.0.2.1:8080" }}, {{ url = "http://192.0.2.2:8080" }}]
+
+[[proxies]]
+name = "proxy"
+type = "{proxy_type}"
+external_port = 80
+layer = 1
+
+[[proxies.routes]]
+type = "conditional"
+domain = "app.example.com"
+upstream = "api-cluster"
+
+[[proxies.routes.paths]]
+prefix = "/api/"
+protect = false
+
+[[proxies]]
+name = "proxy-2"
+type = "caddy"
+layer = 2
+"#
+        )
+    };
+
+    let config = parse_config(&toml("nginx"));
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(configs["upstreams.conf"].contains(
+        "upstream pool_api_cluster {\n    server 192.0.2.1:8080;\n    server 192.0.2.2:8080;\n    keepalive 8;\n}\n"
+    ));
+    let default_conf = &configs["default.conf"];
+    assert!(default_conf.contains(
+        "location ^~ /api/ {\n        proxy_pass http://pool_api_cluster;\n        include /etc/nginx/conf.d/proxy_params.conf;\n        proxy_set_header Connection \"\";\n"
+    ));
+    // Challenged requests still go to Anubis
+    assert!(default_conf.contains("location ^~ / {\n        proxy_pass http://anubis:8080;"));
+
+    let config = parse_config(&toml("caddy"));
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Should generate Caddyfile");
+    assert!(caddyfile.contains(
+        "\t\thandle /api/* {\n\t\t\treverse_proxy http://192.0.2.1:8080 http://192.0.2.2:8080 {\n\t\t\t\tlb_policy round_robin\n\t\t\t\ttransport http {\n\t\t\t\t\tkeepalive_idle_conns_per_host 8\n\t\t\t\t}\n\t\t\t}\n\t\t}\n"
+    ));
+    assert!(caddyfile.contains("\t\thandle /* {\n\t\t\treverse_proxy http://anubis:8080\n"));
+}
+
+#[test]
+fn test_instance_balancer_config() {
+    let config = parse_config(
+        r#"
+[project]
+name = "instance-balancer-test"
+scaling = true
+
+[[proxies]]
+name = "edge"
+type = "caddy"
+external_port = 80
+internal_port = 8080
+instances = 2
+instance_port_strategy = "internal-only"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#,
+    );
+    let generator = ProxyConfigGenerator::new(&config);
+    let balancer = generator
+        .generate_instance_balancer(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(balancer.contains("    bind :8080\n    # The proxies see the balancer"));
+    assert!(balancer.contains("    option forwardfor\n"));
+    assert!(balancer.contains(
+        "    server edge edge:8080 check resolvers docker init-addr last,libc,none\n    server edge-2 edge-2:8080 check"
+    ));
+    assert!(!balancer.contains("server-template"));
+
+    let mut config = config;
+    config.project.scaling = false;
+    config.proxies[0].deploy = Some(crate::config::DeployConfig {
+        replicas: Some(3),
+        ..Default::default()
+    });
+    let balancer = ProxyConfigGenerator::new(&config)
+        .generate_instance_balancer(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(balancer.contains(
+        "    server-template edge 1-3 edge:8080 check resolvers docker init-addr none\n"
+    ));
+}
+
+#[test]
+fn test_tracing_configs() {
+    let config = parse_config(
+        r#"
+[project]
+name = "tracing-test"
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+
+[[proxies]]
+name = "inner"
+type = "nginx"
+layer = 2
+
+[[proxies]]
+name = "gateway"
+type = "traefik"
+layer = 3
+
+[[services]]
+name = "app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[observability.tracing]
+endpoint = "http://tempo:4317"
+sample_rate = 0.07
+"#,
+    );
+    let generator = ProxyConfigGenerator::new(&config);
+
+    let edge = generator
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    let tracing = &edge["tracing.conf"];
+    assert!(tracing.contains("otel_exporter {\n    endpoint otel-collector:4317;\n}\n"));
+    assert!(tracing.contains("otel_service_name edge;\n"));
+    assert!(tracing.contains("    7.0% on;\n    * off;\n}\notel_trace $otel_sampled;\n"));
+    let main = generator
+        .generate_nginx_main(&config.proxies[0])
+        .unwrap()
+        .expect("The module is loaded from the main nginx.conf");
+    assert!(main.contains("load_module modules/ngx_otel_module.so;\n"));
+
+    let inner = generator
+        .generate_nginx_configs(&config.proxies[1])
+        .unwrap();
+    assert!(inner["tracing.conf"].contains("otel_trace $otel_parent_sampled;\n"));
+    assert!(inner["tracing.conf"].contains("otel_trace_context propagate;\n"));
+
+    let files: HashMap<PathBuf, String> = generator
+        .generate_traefik_configs(&config.proxies[2])
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert!(files[Path::new("traefik.yml")].contains(
+        "tracing:\n  serviceName: gateway\n  sampleRate: 0.07\n  otlp:\n    grpc:\n      endpoint: otel-collector:4317\n      insecure: true\n"
+    ));
+}
+
+#[test]
+fn test_content_served_at_layer_1() {
+    let toml = |proxy_type: &str| {
+        format!(
+            r#"
+[project]
+name = "content-test"
+
+[[proxies]]
+name = "edge"
+type = "{proxy_type}"
+external_port = 80
+default_upstream = "http://proxy-2:80"
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+layer = 2
+
+[[services]]
+name = "app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[content.robots]
+sitemaps = ["https://example.com/sitemap.xml"]
+
+[content.security_txt]
+contact = ["mailto:security@example.com"]
+expires = "2027-01-01T00:00:00Z"
+"#
+        )
+    };
+
+    let config = parse_config(&toml("nginx"));
+    let generator = ProxyConfigGenerator::new(&config);
+    let edge = generator
+        .generate_nginx_configs(&config.proxies[0])
+        .unwrap();
+    assert!(edge["content.inc"].contains(
+        "location = /.well-known/security.txt {\n    alias /usr/share/cerberus/content/security.txt;\n"
+    ));
+    assert!(edge["default.conf"].contains("    include /etc/nginx/conf.d/content.inc;\n"));
+    let inner = generator
+        .generate_nginx_configs(&config.proxies[1])
+        .unwrap();
+    assert!(!inner.contains_key("content.inc"));
+
+    let config = parse_config(&toml("caddy"));
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(caddyfile.contains(
+        "\thandle /robots.txt {\n\t\troot * /usr/share/cerberus/content\n\t\trewrite * /robots.txt\n"
+    ));
+
+    let config = parse_config(&toml("haproxy"));
+    let haproxy = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .unwrap();
+    assert!(haproxy.contains(
+        "    http-request return status 200 content-type \"text/plain; charset=utf-8\" file /usr/share/cerberus/content/robots.txt if { path /robots.txt }\n"
+    ));
+}
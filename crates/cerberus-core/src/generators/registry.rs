@@ -0,0 +1,492 @@
+//! Generator trait and registry
+//!
+//! Every file Cerberus writes comes from a [`Generator`] in a
+//! [`GeneratorRegistry`]. The built-in registry holds the generators of
+//! this crate; downstream crates register their own, e.g. for
+//! company-specific configs, and [`CerberusGenerator`] renders them
+//! alongside the built-in files.
+//!
+//! ```
+//! use cerberus_core::Result;
+//! use cerberus_core::config::Config;
+//! use cerberus_core::generators::{GenerateContext, GeneratedFile, Generator, GeneratorRegistry};
+//! use std::path::PathBuf;
+//!
+//! struct Inventory;
+//!
+//! impl Generator for Inventory {
+//!     fn name(&self) -> &str {
+//!         "inventory"
+//!     }
+//!
+//!     fn generate(&self, config: &Config, _: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+//!         let services: Vec<&str> = config.services.iter().map(|s| s.name.as_str()).collect();
+//!         Ok(vec![(PathBuf::from("inventory.txt"), services.join("\n"))])
+//!     }
+//! }
+//!
+//! let mut registry = GeneratorRegistry::builtin();
+//! registry.register(Inventory);
+//! assert!(registry.names().contains(&"inventory"));
+//! ```
+//!
+//! [`CerberusGenerator`]: super::CerberusGenerator
+
+use super::{
+    AnubisGenerator, ArtifactKind, BAKE_FILE, BakeGenerator, ContentGenerator, CrowdSecGenerator,
+    DnsGenerator, DockerComposeGenerator, DockerfileGenerator, ErrorPagesGenerator, FragmentCache,
+    MakefileGenerator, NomadGenerator, ObservabilityGenerator, ProxyConfigGenerator, RenderedFiles,
+    RunbookGenerator, ScheduleGenerator, SecurityProfilesGenerator, UpdateScriptGenerator,
+    VaultAgentGenerator,
+};
+use crate::Result;
+use crate::config::{Config, DeployTarget};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A generated file: its path relative to the output directory, and content
+pub type GeneratedFile = (PathBuf, String);
+
+/// Options of the generation run a generator renders for
+pub struct GenerateContext<'a> {
+    /// Directory the files are written to
+    pub output_dir: &'a Path,
+    /// Whether a vault-agent sidecar renders `vault://` references
+    pub vault_agent: bool,
+    /// Cache of rendered per-service fragments
+    pub fragments: &'a FragmentCache,
+    /// Files of the generators named in [`Generator::depends_on`], by name
+    pub dependencies: &'a RenderedFiles,
+}
+
+/// A source of generated files
+pub trait Generator: Send + Sync {
+    /// Name used in logs and errors, e.g. `proxy-configs`
+    fn name(&self) -> &str;
+
+    /// Files to write for a configuration, relative to the output directory
+    ///
+    /// Generators with nothing to write for a configuration return no files.
+    ///
+    /// # Errors
+    /// Returns error if rendering fails
+    fn generate(&self, config: &Config, context: &GenerateContext) -> Result<Vec<GeneratedFile>>;
+
+    /// Names of the generators whose files this one reads
+    ///
+    /// The generator renders after them, with their files in
+    /// [`GenerateContext::dependencies`]. Generators without dependencies
+    /// render concurrently.
+    fn depends_on(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Kind of the artifacts the generated files become
+    ///
+    /// Generators outside this crate keep the default, a custom kind
+    /// named after the generator.
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::Custom(self.name().to_string())
+    }
+}
+
+/// Ordered list of the generators a run renders
+#[derive(Clone, Default)]
+pub struct GeneratorRegistry {
+    generators: Vec<Arc<dyn Generator>>,
+}
+
+impl GeneratorRegistry {
+    /// A registry without any generator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The generators of this crate
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register(Deployment)
+            .register(ProxyConfigs)
+            .register(Dockerfiles)
+            .register(Anubis)
+            .register(CrowdSec)
+            .register(ErrorPages)
+            .register(Content)
+            .register(SecurityProfiles)
+            .register(Dns)
+            .register(Operations)
+            .register(VaultAgent)
+            .register(Schedule)
+            .register(Observability);
+        registry
+    }
+
+    /// Add a generator after the ones already registered
+    pub fn register(&mut self, generator: impl Generator + 'static) -> &mut Self {
+        self.generators.push(Arc::new(generator));
+        self
+    }
+
+    /// Names of the registered generators, in order
+    pub fn names(&self) -> Vec<&str> {
+        self.generators
+            .iter()
+            .map(|generator| generator.name())
+            .collect()
+    }
+
+    /// Registered generators, in order
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Generator> {
+        self.generators.iter().map(|generator| generator.as_ref())
+    }
+
+    /// Shared handles of the registered generators, in order
+    pub(crate) fn generators(&self) -> impl Iterator<Item = &Arc<dyn Generator>> {
+        self.generators.iter()
+    }
+}
+
+impl fmt::Debug for GeneratorRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// The deployment definition for the selected target
+struct Deployment;
+
+impl Generator for Deployment {
+    fn name(&self) -> &str {
+        "deployment"
+    }
+
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::Deployment
+    }
+
+    fn generate(&self, config: &Config, context: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        Ok(vec![match config.project.target {
+            DeployTarget::Compose => (
+                PathBuf::from(super::docker_compose::COMPOSE_FILE),
+                DockerComposeGenerator::new(config)
+                    .vault_agent(context.vault_agent)
+                    .generate()?,
+            ),
+            DeployTarget::Swarm => (
+                PathBuf::from(super::docker_compose::STACK_FILE),
+                DockerComposeGenerator::new(config).generate()?,
+            ),
+            DeployTarget::Nomad => {
+                let generator = NomadGenerator::new(config);
+                (PathBuf::from(generator.file_name()), generator.generate()?)
+            }
+        }])
+    }
+}
+
+/// Proxy configurations
+struct ProxyConfigs;
+
+impl Generator for ProxyConfigs {
+    fn name(&self) -> &str {
+        "proxy-configs"
+    }
+
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::ProxyConfig
+    }
+
+    fn generate(&self, config: &Config, context: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        let generator = ProxyConfigGenerator::new(config).fragment_cache(context.fragments);
+        let mut files = Vec::new();
+        for proxy in &config.proxies {
+            let proxy_dir = Path::new("proxy-configs").join(&proxy.name);
+            match proxy.proxy_type.as_str() {
+                "nginx" => {
+                    // Multiple Nginx config files
+                    for (filename, content) in generator.generate_nginx_configs(proxy)? {
+                        files.push((proxy_dir.join("conf.d").join(filename), content));
+                    }
+                    if let Some(main) = generator.generate_nginx_main(proxy)? {
+                        files.push((proxy_dir.join("nginx.conf"), main));
+                    }
+                }
+                "traefik" => {
+                    // Static configuration plus the watched dynamic directory
+                    for (path, content) in generator.generate_traefik_configs(proxy)? {
+                        files.push((proxy_dir.join(path), content));
+                    }
+                }
+                _ => {
+                    // Single config file for other proxy types
+                    let config_file =
+                        ProxyConfigGenerator::get_file_extension(proxy.proxy_type.as_str());
+                    files.push((
+                        proxy_dir.join(config_file),
+                        generator.generate_for_proxy(proxy)?,
+                    ));
+                }
+            }
+            if let Some(entrypoint) = generator.generate_entrypoint(proxy) {
+                files.push((proxy_dir.join("entrypoint.sh"), entrypoint));
+            }
+            if let Some(balancer) = proxy.instance_balancer(config.project.scaling) {
+                files.push((
+                    Path::new("proxy-configs")
+                        .join(balancer)
+                        .join("haproxy.cfg"),
+                    generator.generate_instance_balancer(proxy)?,
+                ));
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Dockerfiles, plus the multi-stage Dockerfile and the buildx bake file
+struct Dockerfiles;
+
+impl Generator for Dockerfiles {
+    fn name(&self) -> &str {
+        "dockerfiles"
+    }
+
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::Dockerfile
+    }
+
+    fn generate(&self, config: &Config, _: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        let generator = DockerfileGenerator::new(config);
+        let mut files = Vec::new();
+        for proxy in &config.proxies {
+            files.push((
+                Path::new("dockerfiles")
+                    .join(&proxy.name)
+                    .join("Dockerfile"),
+                generator.generate_for_proxy(proxy)?,
+            ));
+        }
+        files.push((
+            PathBuf::from("Dockerfile.multi-stage"),
+            generator.generate_multi_stage()?,
+        ));
+        if let Some(bake) = BakeGenerator::new(config).generate() {
+            files.push((PathBuf::from(BAKE_FILE), bake));
+        }
+        Ok(files)
+    }
+}
+
+/// Anubis bot policy and environment if enabled
+struct Anubis;
+
+impl Generator for Anubis {
+    fn name(&self) -> &str {
+        "anubis"
+    }
+
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::Anubis
+    }
+
+    fn generate(&self, config: &Config, _: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        if !config.anubis.enabled {
+            return Ok(Vec::new());
+        }
+        let mut files = Vec::new();
+        for container in config.anubis.containers() {
+            let dir = PathBuf::from(container.config_dir());
+            let generator = AnubisGenerator::for_container(config, container);
+            files.push((dir.join("botPolicy.json"), generator.generate()?));
+            files.push((
+                dir.join(".env"),
+                generator.generate_env_config()?.join("\n") + "\n",
+            ));
+        }
+        Ok(files)
+    }
+}
+
+/// CrowdSec acquisition and bouncer configs if enabled
+struct CrowdSec;
+
+impl Generator for CrowdSec {
+    fn name(&self) -> &str {
+        "crowdsec"
+    }
+
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::CrowdSec
+    }
+
+    fn generate(&self, config: &Config, _: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        if !config.security.crowdsec.enabled {
+            return Ok(Vec::new());
+        }
+        Ok(CrowdSecGenerator::new(config).files())
+    }
+}
+
+/// Custom error pages if configured
+struct ErrorPages;
+
+impl Generator for ErrorPages {
+    fn name(&self) -> &str {
+        "error-pages"
+    }
+
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::ErrorPages
+    }
+
+    fn generate(&self, config: &Config, _: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        let generator = ErrorPagesGenerator::new(config);
+        if !generator.is_enabled() {
+            return Ok(Vec::new());
+        }
+        generator.files()
+    }
+}
+
+/// robots.txt and security.txt if `[content]` is configured
+struct Content;
+
+impl Generator for Content {
+    fn name(&self) -> &str {
+        "content"
+    }
+
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::Content
+    }
+
+    fn generate(&self, config: &Config, _: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        ContentGenerator::new(config).files()
+    }
+}
+
+/// Seccomp and AppArmor profiles if `[security.profiles]` is enabled
+struct SecurityProfiles;
+
+impl Generator for SecurityProfiles {
+    fn name(&self) -> &str {
+        "security-profiles"
+    }
+
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::SecurityProfiles
+    }
+
+    fn generate(&self, config: &Config, _: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        Ok(SecurityProfilesGenerator::new(config).files())
+    }
+}
+
+/// DNS record plan if deployment hosts are configured
+struct Dns;
+
+impl Generator for Dns {
+    fn name(&self) -> &str {
+        "dns"
+    }
+
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::Dns
+    }
+
+    fn generate(&self, config: &Config, _: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        let generator = DnsGenerator::new(config);
+        if !generator.is_needed() {
+            return Ok(Vec::new());
+        }
+        generator.files()
+    }
+}
+
+/// Update script (drives docker-compose), the runbook and Makefile for operating it
+struct Operations;
+
+impl Generator for Operations {
+    fn name(&self) -> &str {
+        "operations"
+    }
+
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::Operations
+    }
+
+    fn generate(&self, config: &Config, _: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        if config.project.target != DeployTarget::Compose {
+            return Ok(Vec::new());
+        }
+        let mut files = UpdateScriptGenerator::new(config).files()?;
+        files.extend(RunbookGenerator::new(config).files());
+        files.extend(MakefileGenerator::new(config).files());
+        Ok(files)
+    }
+}
+
+/// vault-agent configuration when it renders the vault:// references
+struct VaultAgent;
+
+impl Generator for VaultAgent {
+    fn name(&self) -> &str {
+        "vault-agent"
+    }
+
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::VaultAgent
+    }
+
+    fn generate(&self, config: &Config, context: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        if !context.vault_agent
+            || config.project.target != DeployTarget::Compose
+            || !config.uses_vault()
+        {
+            return Ok(Vec::new());
+        }
+        VaultAgentGenerator::new(config).files()
+    }
+}
+
+/// Schedule cron job for time-based routes
+struct Schedule;
+
+impl Generator for Schedule {
+    fn name(&self) -> &str {
+        "schedule"
+    }
+
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::Schedule
+    }
+
+    fn generate(&self, config: &Config, context: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        let generator = ScheduleGenerator::new(config);
+        if !generator.is_needed() {
+            return Ok(Vec::new());
+        }
+        let script_dir = std::fs::canonicalize(context.output_dir)
+            .unwrap_or_else(|_| context.output_dir.to_path_buf());
+        Ok(generator.files(&script_dir))
+    }
+}
+
+/// Routes report and monitoring configs
+struct Observability;
+
+impl Generator for Observability {
+    fn name(&self) -> &str {
+        "observability"
+    }
+
+    fn kind(&self) -> ArtifactKind {
+        ArtifactKind::Observability
+    }
+
+    fn generate(&self, config: &Config, _: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        Ok(ObservabilityGenerator::new(config).files())
+    }
+}
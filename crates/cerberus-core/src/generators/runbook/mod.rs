@@ -0,0 +1,348 @@
+//! # Runbook generator
+//!
+//! Generates `RUNBOOK.md`, the operating notes for one generated Compose
+//! stack: which containers run and what they publish, how to reload each
+//! proxy, where logs and certificates live, and how to scale each layer.
+//! Every command uses the real service names from `docker-compose.yaml`.
+
+use super::DockerComposeGenerator;
+use super::docker_compose::HostPort;
+use crate::config::{Config, ProxyConfig, ProxyType};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Generator for the operational runbook
+pub struct RunbookGenerator<'a> {
+    config: &'a Config,
+    compose: DockerComposeGenerator<'a>,
+}
+
+impl<'a> RunbookGenerator<'a> {
+    /// Create a new runbook generator
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            config,
+            compose: DockerComposeGenerator::new(config),
+        }
+    }
+
+    /// Files to write, relative to the output directory
+    pub fn files(&self) -> Vec<(PathBuf, String)> {
+        vec![(PathBuf::from("RUNBOOK.md"), self.generate())]
+    }
+
+    /// Generate the runbook
+    pub fn generate(&self) -> String {
+        let mut runbook = String::new();
+
+        writeln!(runbook, "# Runbook for {}", self.config.project.name).unwrap();
+        writeln!(runbook).unwrap();
+        writeln!(
+            runbook,
+            "Generated by Cerberus Rust edition for this deployment. Run the commands"
+        )
+        .unwrap();
+        writeln!(
+            runbook,
+            "from this directory, next to `docker-compose.yaml`. The `Makefile` here wraps"
+        )
+        .unwrap();
+        writeln!(
+            runbook,
+            "the same commands for hosts without cerberus; `make help` lists them."
+        )
+        .unwrap();
+        writeln!(runbook).unwrap();
+
+        self.write_containers(&mut runbook);
+        self.write_deploying(&mut runbook);
+        self.write_reloading(&mut runbook);
+        self.write_logs(&mut runbook);
+        self.write_certificates(&mut runbook);
+        self.write_scaling(&mut runbook);
+
+        runbook
+    }
+
+    /// Proxies that run a container, with their index in the configuration
+    fn proxies(&self) -> impl Iterator<Item = (usize, &'a ProxyConfig)> + '_ {
+        self.config
+            .proxies
+            .iter()
+            .enumerate()
+            .filter(|(_, proxy)| self.compose.includes_proxy(proxy))
+    }
+
+    fn write_containers(&self, runbook: &mut String) {
+        writeln!(runbook, "## Containers").unwrap();
+        writeln!(runbook).unwrap();
+        writeln!(runbook, "| Service | Role | Published ports |").unwrap();
+        writeln!(runbook, "|---|---|---|").unwrap();
+
+        for (index, proxy) in self.proxies() {
+            let stats = self.config.published_stats(proxy).map(|stats| stats.port);
+            let replicas = proxy.deploy_replicas().unwrap_or(1);
+            for (instance, name) in self.compose.proxy_services(proxy).iter().enumerate() {
+                // Matches the port mappings written to docker-compose.yaml
+                let published = match instance {
+                    0 => self.compose.published_port(proxy, index),
+                    _ => proxy.external_port,
+                };
+                let range = |port: u16| match replicas {
+                    1 => port.to_string(),
+                    _ => format!("{port}-{}", u32::from(port) + replicas - 1),
+                };
+                let mut ports: Vec<String> = published
+                    .into_iter()
+                    .chain(stats)
+                    .filter_map(|port| self.compose.host_port(proxy, port, instance as u8))
+                    .map(|host| match host {
+                        HostPort::Fixed(port) => port,
+                        HostPort::Random => "random".to_string(),
+                    })
+                    .collect();
+                // Only the first container publishes the tcp and udp streams
+                if instance == 0 {
+                    ports.extend(self.config.streams(proxy).into_iter().map(|stream| {
+                        format!("{}/{}", range(stream.listen_port), stream.protocol.as_str())
+                    }));
+                }
+                writeln!(
+                    runbook,
+                    "| {name} | Layer {} {} proxy | {} |",
+                    proxy.layer.unwrap_or(1),
+                    proxy.proxy_type.as_str(),
+                    if ports.is_empty() {
+                        "-".to_string()
+                    } else {
+                        ports.join(", ")
+                    }
+                )
+                .unwrap();
+            }
+            if let Some(balancer) = proxy.instance_balancer(self.config.project.scaling) {
+                writeln!(
+                    runbook,
+                    "| {balancer} | Load balancer for the {} instances | {} |",
+                    proxy.name,
+                    self.compose
+                        .published_port(proxy, index)
+                        .map_or_else(|| "-".to_string(), |port| port.to_string())
+                )
+                .unwrap();
+            }
+        }
+        if self.config.anubis.enabled {
+            for container in self.config.anubis.containers() {
+                let role = if container.domains.is_empty() {
+                    "Anubis bot challenge".to_string()
+                } else {
+                    format!("Anubis bot challenge for {}", container.domains.join(", "))
+                };
+                writeln!(runbook, "| {} | {role} | - |", container.name).unwrap();
+            }
+        }
+        for service in &self.config.services {
+            if self.compose.includes_service(service) {
+                let ports = if service.ports.is_empty() {
+                    "-".to_string()
+                } else {
+                    service.ports.join(", ")
+                };
+                writeln!(
+                    runbook,
+                    "| {} | Backend for {} | {ports} |",
+                    service.name,
+                    service.hosts().join(", ")
+                )
+                .unwrap();
+            }
+        }
+        writeln!(runbook).unwrap();
+    }
+
+    fn write_deploying(&self, runbook: &mut String) {
+        writeln!(runbook, "## Deploying changes").unwrap();
+        writeln!(runbook).unwrap();
+        writeln!(
+            runbook,
+            "Edit `config.toml`, then regenerate and apply only what changed:"
+        )
+        .unwrap();
+        writeln!(runbook).unwrap();
+        writeln!(runbook, "```sh").unwrap();
+        writeln!(runbook, "cerberus upgrade --dry-run").unwrap();
+        writeln!(runbook, "cerberus upgrade").unwrap();
+        writeln!(runbook, "```").unwrap();
+        writeln!(runbook).unwrap();
+        writeln!(
+            runbook,
+            "For a first deployment, or to recreate everything: `docker compose up -d`."
+        )
+        .unwrap();
+        writeln!(runbook).unwrap();
+    }
+
+    fn write_reloading(&self, runbook: &mut String) {
+        writeln!(runbook, "## Reloading proxies").unwrap();
+        writeln!(runbook).unwrap();
+
+        for (_, proxy) in self.proxies() {
+            let services = self.compose.proxy_services(proxy);
+            writeln!(
+                runbook,
+                "### {} ({})",
+                proxy.name,
+                proxy.proxy_type.as_str()
+            )
+            .unwrap();
+            writeln!(runbook).unwrap();
+            writeln!(runbook, "Configuration: `proxy-configs/{}/`", proxy.name).unwrap();
+            writeln!(runbook).unwrap();
+            writeln!(runbook, "```sh").unwrap();
+            for command in reload_commands(proxy, &services) {
+                writeln!(runbook, "docker compose {command}").unwrap();
+            }
+            writeln!(runbook, "```").unwrap();
+            writeln!(runbook).unwrap();
+            match proxy.reload_signal() {
+                Some(_) => writeln!(
+                    runbook,
+                    "The signal reloads the configuration in place without dropping connections."
+                )
+                .unwrap(),
+                None if proxy.proxy_type == ProxyType::Varnish => writeln!(
+                    runbook,
+                    "`varnishreload` loads the new VCL and keeps the cache."
+                )
+                .unwrap(),
+                None => writeln!(
+                    runbook,
+                    "{} cannot reload this configuration in place; the restart briefly drops connections.",
+                    proxy.proxy_type.as_str()
+                )
+                .unwrap(),
+            }
+            writeln!(runbook).unwrap();
+        }
+    }
+
+    fn write_logs(&self, runbook: &mut String) {
+        writeln!(runbook, "## Logs").unwrap();
+        writeln!(runbook).unwrap();
+        writeln!(
+            runbook,
+            "Proxy access and error logs are written to `built/logs/` in this directory:"
+        )
+        .unwrap();
+        writeln!(runbook).unwrap();
+        for (_, proxy) in self.proxies() {
+            writeln!(
+                runbook,
+                "- {}: mounted at `{}`",
+                proxy.name,
+                proxy.proxy_type.log_dir()
+            )
+            .unwrap();
+        }
+        writeln!(runbook).unwrap();
+        writeln!(runbook, "Container output:").unwrap();
+        writeln!(runbook).unwrap();
+        writeln!(runbook, "```sh").unwrap();
+        writeln!(runbook, "docker compose logs -f <service>").unwrap();
+        writeln!(runbook, "```").unwrap();
+        writeln!(runbook).unwrap();
+    }
+
+    fn write_certificates(&self, runbook: &mut String) {
+        writeln!(runbook, "## Certificates").unwrap();
+        writeln!(runbook).unwrap();
+
+        let tls = &self.config.tls;
+        if !tls.enabled || tls.certificates.is_empty() {
+            writeln!(
+                runbook,
+                "No certificates are configured; TLS terminates in front of this stack."
+            )
+            .unwrap();
+            writeln!(runbook).unwrap();
+            return;
+        }
+
+        writeln!(runbook, "| Domain | Certificate | Key |").unwrap();
+        writeln!(runbook, "|---|---|---|").unwrap();
+        for certificate in &tls.certificates {
+            writeln!(
+                runbook,
+                "| {} | `{}` | `{}` |",
+                certificate.domain, certificate.cert_file, certificate.key_file
+            )
+            .unwrap();
+        }
+        writeln!(runbook).unwrap();
+        writeln!(
+            runbook,
+            "After renewing a certificate, reload the proxies that serve it."
+        )
+        .unwrap();
+        writeln!(runbook).unwrap();
+    }
+
+    fn write_scaling(&self, runbook: &mut String) {
+        writeln!(runbook, "## Scaling").unwrap();
+        writeln!(runbook).unwrap();
+
+        for (_, proxy) in self.proxies() {
+            let containers = proxy.container_count(self.config.project.scaling);
+            let how = if proxy.deploy_replicas().is_some() {
+                "change `deploy.replicas`"
+            } else if self.config.project.scaling {
+                "change `instances`"
+            } else {
+                "enable `project.scaling` and set `instances`"
+            };
+            writeln!(
+                runbook,
+                "- {} (layer {}): {containers} container(s); {how} in `config.toml`, then run `cerberus upgrade`",
+                proxy.name,
+                proxy.layer.unwrap_or(1)
+            )
+            .unwrap();
+        }
+        for service in &self.config.services {
+            if !self.compose.includes_service(service) {
+                continue;
+            }
+            let replicas = service.replicas.unwrap_or(1);
+            writeln!(
+                runbook,
+                "- {}: {replicas} container(s); change `replicas` in `config.toml`, then run `cerberus upgrade`",
+                service.name
+            )
+            .unwrap();
+        }
+        writeln!(runbook).unwrap();
+    }
+}
+
+/// `docker compose` arguments that reload a proxy's containers
+///
+/// Nginx configurations are tested first. Proxies without a reload signal
+/// are restarted, except Varnish, which loads new VCL with `varnishreload`.
+pub(crate) fn reload_commands(proxy: &ProxyConfig, services: &[String]) -> Vec<String> {
+    let mut commands = Vec::new();
+    if proxy.proxy_type == ProxyType::Nginx {
+        commands.push(format!("exec {} nginx -t", proxy.name));
+    }
+    for service in services {
+        commands.push(match (&proxy.proxy_type, proxy.reload_signal()) {
+            (_, Some(signal)) => format!("kill -s {signal} {service}"),
+            (ProxyType::Varnish, None) => format!("exec {service} varnishreload"),
+            (_, None) => format!("restart {service}"),
+        });
+    }
+    commands
+}
+
+#[cfg(test)]
+mod tests;
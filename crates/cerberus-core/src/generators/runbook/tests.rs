@@ -0,0 +1,117 @@
+//! # Tests for runbook generation
+//!
+//! These tests verify that the runbook names the real Compose services and
+//! gives the right reload, port, and scaling instructions for each proxy.
+
+use super::*;
+use crate::test_support::parse_config;
+
+/// Helper function to create a scaled two-layer stack
+fn create_stack_config() -> Config {
+    parse_config(
+        r#"
+[project]
+name = "runbook-test"
+scaling = true
+
+[[proxies]]
+name = "edge"
+type = "haproxy"
+external_port = 80
+instances = 2
+default_upstream = "http://inner:80"
+
+[proxies.stats]
+port = 9000
+
+[[proxies]]
+name = "inner"
+type = "caddy"
+layer = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+image = "example/web:1.0"
+replicas = 3
+"#,
+    )
+}
+
+#[test]
+fn test_runbook_lists_containers_and_ports() {
+    let config = create_stack_config();
+    let runbook = RunbookGenerator::new(&config).generate();
+
+    assert!(runbook.starts_with("# Runbook for runbook-test\n"));
+    assert!(runbook.contains("| edge | Layer 1 haproxy proxy | 80, 9000 |\n"));
+    assert!(runbook.contains("| edge-2 | Layer 1 haproxy proxy | 81, 9001 |\n"));
+    assert!(runbook.contains("| inner | Layer 2 caddy proxy | - |\n"));
+    assert!(runbook.contains("| web-app | Backend for app.example.com | - |\n"));
+}
+
+#[test]
+fn test_runbook_reload_commands() {
+    let config = create_stack_config();
+    let runbook = RunbookGenerator::new(&config).generate();
+
+    assert!(runbook.contains(
+        "```sh\ndocker compose kill -s USR2 edge\ndocker compose kill -s USR2 edge-2\n```\n"
+    ));
+    assert!(runbook.contains("Configuration: `proxy-configs/inner/`\n"));
+    assert!(runbook.contains("docker compose restart inner\n"));
+    assert!(runbook.contains("caddy cannot reload this configuration in place"));
+}
+
+#[test]
+fn test_runbook_scaling_and_certificates() {
+    let config = create_stack_config();
+    let runbook = RunbookGenerator::new(&config).generate();
+
+    assert!(runbook.contains(
+        "- edge (layer 1): 2 container(s); change `instances` in `config.toml`, then run `cerberus upgrade`\n"
+    ));
+    assert!(runbook.contains("- web-app: 3 container(s); change `replicas`"));
+    assert!(runbook.contains("No certificates are configured"));
+    assert!(runbook.contains("- edge: mounted at `/var/log/proxy`\n"));
+}
+
+#[test]
+fn test_runbook_skips_nginx_edge_without_anubis() {
+    let config = parse_config(
+        r#"
+[project]
+name = "runbook-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+layer = 1
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+layer = 2
+
+[tls]
+enabled = true
+
+[[tls.certificates]]
+domain = "*.example.com"
+cert_file = "/etc/ssl/example.pem"
+key_file = "/etc/ssl/example.key"
+"#,
+    );
+    let runbook = RunbookGenerator::new(&config).generate();
+
+    // The compose file has no container for the layer 1 nginx proxy
+    assert!(!runbook.contains("| proxy |"));
+    assert!(runbook.contains(
+        "```sh\ndocker compose exec proxy-2 nginx -t\ndocker compose kill -s HUP proxy-2\n```\n"
+    ));
+    assert!(
+        runbook.contains("| *.example.com | `/etc/ssl/example.pem` | `/etc/ssl/example.key` |\n")
+    );
+}
@@ -0,0 +1,313 @@
+//! # Schedule generator
+//!
+//! Generates the cron-driven pieces of time-based routing. HAProxy evaluates
+//! schedules natively; Nginx and Caddy check a flag file per schedule, and
+//! Traefik picks up a closed-route file from its watched dynamic directory.
+//! `schedule.sh` creates or removes those files based on the current time,
+//! and `schedule.crontab` runs it at every window boundary.
+
+use crate::{
+    Result,
+    config::{Config, ProxyConfig, ProxyType, ScheduleConfig, ServiceConfig},
+};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Generator for time-based routing scripts
+pub struct ScheduleGenerator<'a> {
+    config: &'a Config,
+}
+
+impl<'a> ScheduleGenerator<'a> {
+    /// Create a new schedule generator
+    pub fn new(config: &'a Config) -> Self {
+        Self { config }
+    }
+
+    /// Whether any proxy needs the cron job
+    pub fn is_needed(&self) -> bool {
+        self.has_schedules() && self.toggled_proxies().next().is_some()
+    }
+
+    /// Write schedule.sh, schedule.crontab and Traefik closed-route files
+    pub fn generate(&self, output_dir: &Path) -> Result<()> {
+        let script_dir = fs::canonicalize(output_dir).unwrap_or_else(|_| output_dir.to_path_buf());
+        super::write_files(output_dir, self.files(&script_dir))
+    }
+
+    /// Files to write, relative to the output directory
+    ///
+    /// `script_dir` is the absolute directory the crontab runs schedule.sh from.
+    pub fn files(&self, script_dir: &Path) -> Vec<(PathBuf, String)> {
+        let mut files = vec![
+            (PathBuf::from("schedule.sh"), self.generate_script()),
+            (
+                PathBuf::from("schedule.crontab"),
+                self.generate_crontab(&script_dir.join("schedule.sh").to_string_lossy()),
+            ),
+        ];
+
+        for proxy in self.toggled_proxies() {
+            if proxy.proxy_type != ProxyType::Traefik {
+                continue;
+            }
+            let schedule_dir = Path::new("proxy-configs")
+                .join(&proxy.name)
+                .join("schedule");
+            let mut routes: Vec<_> = self.generate_traefik_routes().into_iter().collect();
+            routes.sort();
+            for (file_name, content) in routes {
+                files.push((schedule_dir.join(file_name), content));
+            }
+        }
+
+        files
+    }
+
+    /// Generate the script that toggles schedules for the current time
+    pub fn generate_script(&self) -> String {
+        let mut script = String::new();
+
+        writeln!(script, "#!/bin/bash").unwrap();
+        writeln!(script, "# Cerberus Schedule Script").unwrap();
+        writeln!(
+            script,
+            "# Generated by Cerberus Rust edition for project: {}",
+            self.config.project.name
+        )
+        .unwrap();
+        writeln!(
+            script,
+            "# Opens and closes time-based routes; run from cron (see schedule.crontab)"
+        )
+        .unwrap();
+        writeln!(script).unwrap();
+        writeln!(script, "set -e").unwrap();
+        writeln!(
+            script,
+            "SCRIPT_DIR=\"$(cd \"$(dirname \"${{BASH_SOURCE[0]}}\")\" && pwd)\""
+        )
+        .unwrap();
+        writeln!(script, "NOW_TIME=$((10#$(date +%H%M)))").unwrap();
+        writeln!(script, "NOW_DAY=$(date +%u)").unwrap();
+        writeln!(script, "NOW_DATE=$(date +%Y%m%d)").unwrap();
+        writeln!(script).unwrap();
+
+        script.push_str(
+            r#"# in_window RANGES DAYS FROM UNTIL
+in_window() {
+    local range
+    if [ -n "$3" ] && [ "$NOW_DATE" -lt "$3" ]; then return 1; fi
+    if [ -n "$4" ] && [ "$NOW_DATE" -gt "$4" ]; then return 1; fi
+    if [ -n "$2" ] && [[ " $2 " != *" $NOW_DAY "* ]]; then return 1; fi
+    [ -z "$1" ] && return 0
+    for range in $1; do
+        if [ "$NOW_TIME" -ge "${range%-*}" ] && [ "$NOW_TIME" -le "${range#*-}" ]; then
+            return 0
+        fi
+    done
+    return 1
+}
+
+# set_flag ACTIVE FLAG_FILE
+set_flag() {
+    if [ "$1" = 1 ]; then
+        rm -f "$2"
+    else
+        mkdir -p "$(dirname "$2")"
+        touch "$2"
+    fi
+}
+
+# install_route ACTIVE ROUTE_FILE DYNAMIC_FILE
+install_route() {
+    if [ "$1" = 1 ]; then
+        rm -f "$3"
+    else
+        mkdir -p "$(dirname "$3")"
+        cp "$2" "$3"
+    fi
+}
+
+"#,
+        );
+
+        for (service, index, schedule) in self.schedules() {
+            let id = service.schedule_id(index);
+            let ranges: Vec<String> = schedule
+                .clock_ranges()
+                .iter()
+                .map(|(start, end)| format!("{start}-{end}"))
+                .collect();
+            let days: Vec<String> = schedule.day_numbers().iter().map(u8::to_string).collect();
+            let (from, until) = schedule.date_range();
+
+            writeln!(script, "# {} {}", service.name, schedule.path).unwrap();
+            writeln!(
+                script,
+                "if in_window \"{}\" \"{}\" \"{}\" \"{}\"; then ACTIVE=1; else ACTIVE=0; fi",
+                ranges.join(" "),
+                days.join(" "),
+                from.map(|date| date.to_string()).unwrap_or_default(),
+                until.map(|date| date.to_string()).unwrap_or_default()
+            )
+            .unwrap();
+
+            for proxy in self.toggled_proxies() {
+                let proxy_dir = format!("$SCRIPT_DIR/proxy-configs/{}", proxy.name);
+                match proxy.proxy_type {
+                    ProxyType::Nginx => writeln!(
+                        script,
+                        "set_flag \"$ACTIVE\" \"{proxy_dir}/conf.d/schedule/{id}.closed\""
+                    )
+                    .unwrap(),
+                    ProxyType::Caddy => writeln!(
+                        script,
+                        "set_flag \"$ACTIVE\" \"{proxy_dir}/schedule/{id}.closed\""
+                    )
+                    .unwrap(),
+                    ProxyType::Traefik => writeln!(
+                        script,
+                        "install_route \"$ACTIVE\" \"{proxy_dir}/schedule/{id}.yml\" \"{proxy_dir}/dynamic/{id}.yml\""
+                    )
+                    .unwrap(),
+                    ProxyType::HaProxy | ProxyType::Varnish => {}
+                }
+            }
+            writeln!(script).unwrap();
+        }
+
+        script
+    }
+
+    /// Generate crontab entries running the script at every window boundary
+    pub fn generate_crontab(&self, script_path: &str) -> String {
+        let mut boundaries = BTreeSet::new();
+        let mut daily = false;
+
+        for (_, _, schedule) in self.schedules() {
+            if let Some((start, end)) = schedule.hours() {
+                for minutes in [start, end % (24 * 60)] {
+                    boundaries.insert((minutes / 60, minutes % 60));
+                }
+            }
+            if !schedule.active_days.is_empty()
+                || schedule.active_from.is_some()
+                || schedule.active_until.is_some()
+            {
+                daily = true;
+            }
+        }
+        if daily {
+            boundaries.insert((0, 0));
+        }
+
+        let mut crontab = String::new();
+        writeln!(
+            crontab,
+            "# Cerberus schedule for project: {}",
+            self.config.project.name
+        )
+        .unwrap();
+        writeln!(
+            crontab,
+            "# Install with `crontab schedule.crontab` and run schedule.sh once after deploying"
+        )
+        .unwrap();
+        writeln!(crontab, "@reboot {script_path}").unwrap();
+        for (hour, minute) in boundaries {
+            writeln!(crontab, "{minute} {hour} * * * {script_path}").unwrap();
+        }
+
+        crontab
+    }
+
+    /// Generate Traefik dynamic files routing closed schedules away from the service
+    pub fn generate_traefik_routes(&self) -> HashMap<String, String> {
+        let mut routes = HashMap::new();
+
+        for (service, index, schedule) in self.schedules() {
+            let id = service.schedule_id(index);
+            let mut route = String::new();
+
+            writeln!(
+                route,
+                "# Installed into dynamic/ by schedule.sh while {}{} is outside its window",
+                service.primary_domain(),
+                schedule.path
+            )
+            .unwrap();
+            writeln!(route, "http:").unwrap();
+            writeln!(route, "  routers:").unwrap();
+            writeln!(route, "    {id}-closed:").unwrap();
+            writeln!(
+                route,
+                "      rule: \"Host(`{}`) && PathPrefix(`{}`)\"",
+                service.primary_domain(),
+                schedule.path
+            )
+            .unwrap();
+            writeln!(route, "      entryPoints:").unwrap();
+            writeln!(route, "        - web").unwrap();
+            writeln!(route, "      priority: 1000").unwrap();
+
+            match &schedule.fallback_upstream {
+                Some(upstream) => {
+                    writeln!(route, "      service: {id}-fallback").unwrap();
+                    writeln!(route, "  services:").unwrap();
+                    writeln!(route, "    {id}-fallback:").unwrap();
+                    writeln!(route, "      loadBalancer:").unwrap();
+                    writeln!(route, "        servers:").unwrap();
+                    writeln!(route, "          - url: \"{upstream}\"").unwrap();
+                }
+                None => {
+                    // Traefik cannot answer with a fixed status; reject every client instead
+                    writeln!(route, "      service: noop@internal").unwrap();
+                    writeln!(route, "      middlewares:").unwrap();
+                    writeln!(route, "        - {id}-closed").unwrap();
+                    writeln!(route, "  middlewares:").unwrap();
+                    writeln!(route, "    {id}-closed:").unwrap();
+                    writeln!(route, "      ipAllowList:").unwrap();
+                    writeln!(route, "        sourceRange:").unwrap();
+                    writeln!(route, "          - \"255.255.255.255/32\"").unwrap();
+                }
+            }
+
+            routes.insert(format!("{id}.yml"), route);
+        }
+
+        routes
+    }
+
+    /// All schedules with their service and index
+    fn schedules(&self) -> impl Iterator<Item = (&ServiceConfig, usize, &ScheduleConfig)> {
+        self.config.services.iter().flat_map(|service| {
+            service
+                .schedules
+                .iter()
+                .enumerate()
+                .map(move |(index, schedule)| (service, index, schedule))
+        })
+    }
+
+    fn has_schedules(&self) -> bool {
+        self.schedules().next().is_some()
+    }
+
+    /// Proxies that rely on the script (HAProxy and layer-1 Nginx do not)
+    fn toggled_proxies(&self) -> impl Iterator<Item = &ProxyConfig> {
+        self.config
+            .proxies
+            .iter()
+            .filter(|proxy| match proxy.proxy_type {
+                ProxyType::HaProxy | ProxyType::Varnish => false,
+                ProxyType::Nginx => proxy.layer.unwrap_or(1) != 1,
+                ProxyType::Caddy | ProxyType::Traefik => true,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests;
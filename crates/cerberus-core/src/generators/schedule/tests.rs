@@ -0,0 +1,139 @@
+//! # Tests for time-based routing generation
+//!
+//! These tests verify the schedule script, crontab boundaries, and the
+//! per-proxy rendering of scheduled paths.
+
+use super::*;
+use crate::generators::ProxyConfigGenerator;
+use crate::test_support::parse_config;
+
+/// Helper function to create a config with scheduled paths behind one proxy
+fn create_scheduled_config(proxy_type: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "schedule-test"
+
+[[proxies]]
+name = "proxy-2"
+type = "{proxy_type}"
+layer = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[[services.schedules]]
+path = "/signup"
+active_hours = "09:00-18:00"
+active_days = ["mon", "tue", "wed", "thu", "fri"]
+
+[[services.schedules]]
+active_hours = "22:00-06:00"
+active_until = "2025-12-31"
+fallback_upstream = "http://maintenance:8080"
+"#
+    ))
+}
+
+#[test]
+fn test_clock_ranges() {
+    let config = create_scheduled_config("caddy");
+    let schedules = &config.services[0].schedules;
+
+    assert_eq!(schedules[0].clock_ranges(), vec![(900, 1759)]);
+    assert_eq!(schedules[0].day_numbers(), vec![1, 2, 3, 4, 5]);
+    // Windows past midnight are split in two
+    assert_eq!(schedules[1].clock_ranges(), vec![(2200, 2359), (0, 559)]);
+    assert_eq!(schedules[1].date_range(), (None, Some(20251231)));
+}
+
+#[test]
+fn test_schedule_script_and_crontab() {
+    let config = create_scheduled_config("nginx");
+    let generator = ScheduleGenerator::new(&config);
+    assert!(generator.is_needed());
+
+    let script = generator.generate_script();
+    assert!(script.contains("if in_window \"900-1759\" \"1 2 3 4 5\" \"\" \"\"; then"));
+    assert!(script.contains("if in_window \"2200-2359 0-559\" \"\" \"\" \"20251231\"; then"));
+    assert!(script.contains(
+        "set_flag \"$ACTIVE\" \"$SCRIPT_DIR/proxy-configs/proxy-2/conf.d/schedule/web_app_schedule_0.closed\""
+    ));
+
+    let crontab = generator.generate_crontab("/srv/built/schedule.sh");
+    assert!(crontab.contains("@reboot /srv/built/schedule.sh"));
+    for boundary in [
+        "0 9 * * *",
+        "0 18 * * *",
+        "0 22 * * *",
+        "0 6 * * *",
+        "0 0 * * *",
+    ] {
+        assert!(crontab.contains(&format!("{boundary} /srv/built/schedule.sh")));
+    }
+}
+
+#[test]
+fn test_haproxy_schedules_are_native() {
+    let config = create_scheduled_config("haproxy");
+    assert!(!ScheduleGenerator::new(&config).is_needed());
+
+    let haproxy_cfg = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Generation should succeed");
+
+    assert!(haproxy_cfg.contains("acl web_app_schedule_0_path path_beg /signup"));
+    assert!(haproxy_cfg.contains("acl web_app_schedule_0_hours date,ltime(%H%M) -m int 900:1759"));
+    assert!(haproxy_cfg.contains("acl web_app_schedule_0_days date,ltime(%u) -m int 1 2 3 4 5"));
+    assert!(haproxy_cfg.contains(
+        "http-request return status 503 if is_web-app web_app_schedule_0_path !web_app_schedule_0_hours || is_web-app web_app_schedule_0_path !web_app_schedule_0_days"
+    ));
+    assert!(haproxy_cfg.contains(
+        "use_backend web_app_schedule_1_fallback if is_web-app !web_app_schedule_1_hours || is_web-app !web_app_schedule_1_dates"
+    ));
+    assert!(haproxy_cfg.contains("server web_app_schedule_1_1 maintenance:8080"));
+}
+
+#[test]
+fn test_flag_file_schedules() {
+    let config = create_scheduled_config("nginx");
+    let configs = ProxyConfigGenerator::new(&config)
+        .generate_nginx_configs(&config.proxies[0])
+        .expect("Generation should succeed");
+    let service_conf = &configs["web_app.conf"];
+    assert!(service_conf.contains("location ^~ /signup {"));
+    assert!(service_conf.contains("if (-f /etc/nginx/conf.d/schedule/web_app_schedule_0.closed)"));
+    assert!(service_conf.contains("return 503;"));
+    assert!(service_conf.contains("proxy_pass http://maintenance:8080;"));
+
+    let config = create_scheduled_config("caddy");
+    let caddyfile = ProxyConfigGenerator::new(&config)
+        .generate_for_proxy(&config.proxies[0])
+        .expect("Generation should succeed");
+    assert!(caddyfile.contains("path /signup*"));
+    assert!(caddyfile.contains("try_files web_app_schedule_0.closed"));
+    assert!(caddyfile.contains("respond @web_app_schedule_0_closed 503"));
+    assert!(caddyfile.contains("handle @web_app_schedule_1_closed"));
+}
+
+#[test]
+fn test_traefik_closed_routes() {
+    let config = create_scheduled_config("traefik");
+    let generator = ScheduleGenerator::new(&config);
+    let routes = generator.generate_traefik_routes();
+
+    let signup = &routes["web_app_schedule_0.yml"];
+    assert!(signup.contains("rule: \"Host(`app.example.com`) && PathPrefix(`/signup`)\""));
+    assert!(signup.contains("ipAllowList:"));
+
+    let site = &routes["web_app_schedule_1.yml"];
+    assert!(site.contains("url: \"http://maintenance:8080\""));
+    let _parsed: serde_yaml::Value =
+        serde_yaml::from_str(site).expect("Route should be valid YAML");
+
+    assert!(generator.generate_script().contains(
+        "install_route \"$ACTIVE\" \"$SCRIPT_DIR/proxy-configs/proxy-2/schedule/web_app_schedule_1.yml\""
+    ));
+}
@@ -0,0 +1,419 @@
+//! # Security profiles generator
+//!
+//! Writes the confinement profiles of `[security.profiles]` to
+//! [`PROFILES_DIR`] in the output directory, for every proxy:
+//! `<proxy>.seccomp.json`, which Compose reads when it creates the
+//! container, and `<proxy>.apparmor`, which has to be loaded on the host
+//! with `apparmor_parser` before the stack starts. The Compose file
+//! references both through `security_opt`.
+
+use super::GeneratedFile;
+use crate::config::{Config, ProxyConfig, ProxyType};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+/// Directory in the output directory holding the profiles
+pub const PROFILES_DIR: &str = "security-profiles";
+
+/// System calls of the proxies, the Go runtime of Caddy and Traefik, and
+/// the shell entrypoints
+///
+/// Left out are the calls Docker's default profile also restricts, such as
+/// `mount`, `ptrace`, `bpf`, `keyctl`, `unshare`, `setns`, module loading
+/// and clock changes, and a few it allows that no proxy needs
+/// (`personality`, `userfaultfd`, `process_vm_readv`, `io_uring_*`).
+const SYSCALLS: &[&str] = &[
+    "accept",
+    "accept4",
+    "access",
+    "arch_prctl",
+    "bind",
+    "brk",
+    "capget",
+    "capset",
+    "chdir",
+    "chmod",
+    "chown",
+    "clock_getres",
+    "clock_gettime",
+    "clock_nanosleep",
+    "clone",
+    "clone3",
+    "close",
+    "close_range",
+    "connect",
+    "copy_file_range",
+    "dup",
+    "dup2",
+    "dup3",
+    "epoll_create",
+    "epoll_create1",
+    "epoll_ctl",
+    "epoll_pwait",
+    "epoll_pwait2",
+    "epoll_wait",
+    "eventfd",
+    "eventfd2",
+    "execve",
+    "execveat",
+    "exit",
+    "exit_group",
+    "faccessat",
+    "faccessat2",
+    "fadvise64",
+    "fallocate",
+    "fchdir",
+    "fchmod",
+    "fchmodat",
+    "fchown",
+    "fchownat",
+    "fcntl",
+    "fdatasync",
+    "flock",
+    "fork",
+    "fstat",
+    "fstatfs",
+    "fsync",
+    "ftruncate",
+    "futex",
+    "getcwd",
+    "getdents",
+    "getdents64",
+    "getegid",
+    "geteuid",
+    "getgid",
+    "getgroups",
+    "getitimer",
+    "getpeername",
+    "getpgid",
+    "getpgrp",
+    "getpid",
+    "getppid",
+    "getpriority",
+    "getrandom",
+    "getresgid",
+    "getresuid",
+    "getrlimit",
+    "getrusage",
+    "getsid",
+    "getsockname",
+    "getsockopt",
+    "gettid",
+    "gettimeofday",
+    "getuid",
+    "getxattr",
+    "inotify_add_watch",
+    "inotify_init",
+    "inotify_init1",
+    "inotify_rm_watch",
+    "io_destroy",
+    "io_getevents",
+    "io_setup",
+    "io_submit",
+    "ioctl",
+    "kill",
+    "lgetxattr",
+    "link",
+    "linkat",
+    "listen",
+    "lseek",
+    "lstat",
+    "madvise",
+    "membarrier",
+    "memfd_create",
+    "mincore",
+    "mkdir",
+    "mkdirat",
+    "mmap",
+    "mprotect",
+    "mremap",
+    "msync",
+    "munmap",
+    "nanosleep",
+    "newfstatat",
+    "open",
+    "openat",
+    "openat2",
+    "pipe",
+    "pipe2",
+    "poll",
+    "ppoll",
+    "prctl",
+    "pread64",
+    "preadv",
+    "prlimit64",
+    "pselect6",
+    "pwrite64",
+    "pwritev",
+    "read",
+    "readlink",
+    "readlinkat",
+    "readv",
+    "recvfrom",
+    "recvmmsg",
+    "recvmsg",
+    "rename",
+    "renameat",
+    "renameat2",
+    "restart_syscall",
+    "rmdir",
+    "rseq",
+    "rt_sigaction",
+    "rt_sigpending",
+    "rt_sigprocmask",
+    "rt_sigqueueinfo",
+    "rt_sigreturn",
+    "rt_sigsuspend",
+    "rt_sigtimedwait",
+    "rt_tgsigqueueinfo",
+    "sched_get_priority_max",
+    "sched_get_priority_min",
+    "sched_getaffinity",
+    "sched_getparam",
+    "sched_getscheduler",
+    "sched_setaffinity",
+    "sched_yield",
+    "select",
+    "sendfile",
+    "sendmmsg",
+    "sendmsg",
+    "sendto",
+    "set_robust_list",
+    "set_tid_address",
+    "setgid",
+    "setgroups",
+    "setitimer",
+    "setpgid",
+    "setpriority",
+    "setregid",
+    "setresgid",
+    "setresuid",
+    "setreuid",
+    "setrlimit",
+    "setsid",
+    "setsockopt",
+    "setuid",
+    "shutdown",
+    "sigaltstack",
+    "socket",
+    "socketpair",
+    "splice",
+    "stat",
+    "statfs",
+    "statx",
+    "symlink",
+    "symlinkat",
+    "sysinfo",
+    "tgkill",
+    "time",
+    "timer_create",
+    "timer_delete",
+    "timer_getoverrun",
+    "timer_gettime",
+    "timer_settime",
+    "timerfd_create",
+    "timerfd_gettime",
+    "timerfd_settime",
+    "tkill",
+    "truncate",
+    "umask",
+    "uname",
+    "unlink",
+    "unlinkat",
+    "utime",
+    "utimensat",
+    "utimes",
+    "vfork",
+    "wait4",
+    "waitid",
+    "write",
+    "writev",
+];
+
+/// Further system calls of one proxy type
+///
+/// Varnish locks its shared memory log in memory.
+fn proxy_syscalls(proxy_type: &ProxyType) -> &'static [&'static str] {
+    match proxy_type {
+        ProxyType::Varnish => &["mlock", "munlock"],
+        _ => &[],
+    }
+}
+
+/// Generator for the seccomp and AppArmor profiles
+pub struct SecurityProfilesGenerator<'a> {
+    config: &'a Config,
+}
+
+impl<'a> SecurityProfilesGenerator<'a> {
+    /// Create a new security profiles generator
+    pub fn new(config: &'a Config) -> Self {
+        Self { config }
+    }
+
+    /// Files to write, relative to the output directory
+    pub fn files(&self) -> Vec<GeneratedFile> {
+        let profiles = &self.config.security.profiles;
+        let mut files = Vec::new();
+        if !profiles.enabled {
+            return files;
+        }
+        for proxy in &self.config.proxies {
+            if profiles.seccomp {
+                files.push((seccomp_path(proxy), self.seccomp(proxy)));
+            }
+            if profiles.apparmor {
+                files.push((apparmor_path(proxy), self.apparmor(proxy)));
+            }
+        }
+        files
+    }
+
+    /// `security_opt` entries confining a proxy container
+    pub fn security_opt(&self, proxy: &ProxyConfig) -> Vec<String> {
+        let profiles = &self.config.security.profiles;
+        let mut options = Vec::new();
+        if !profiles.enabled {
+            return options;
+        }
+        if profiles.seccomp {
+            options.push(format!(
+                "seccomp=./{}",
+                super::paths::posix(&seccomp_path(proxy))
+            ));
+        }
+        if profiles.apparmor {
+            options.push(format!("apparmor={}", self.apparmor_name(proxy)));
+        }
+        if let Some(selinux_type) = &profiles.selinux_type {
+            options.push(format!("label=type:{selinux_type}"));
+        }
+        options
+    }
+
+    /// Name the AppArmor profile of a proxy is loaded under
+    pub fn apparmor_name(&self, proxy: &ProxyConfig) -> String {
+        format!("cerberus-{}-{}", self.config.project.name, proxy.name)
+    }
+
+    /// Seccomp profile of a proxy: every system call not listed fails with EPERM
+    fn seccomp(&self, proxy: &ProxyConfig) -> String {
+        let mut syscalls: Vec<&str> = SYSCALLS
+            .iter()
+            .chain(proxy_syscalls(&proxy.proxy_type))
+            .copied()
+            .chain(
+                self.config
+                    .security
+                    .profiles
+                    .allow_syscalls
+                    .iter()
+                    .map(String::as_str),
+            )
+            .collect();
+        syscalls.sort_unstable();
+        syscalls.dedup();
+
+        let profile = json!({
+            "defaultAction": "SCMP_ACT_ERRNO",
+            "defaultErrnoRet": 1,
+            "archMap": [
+                {
+                    "architecture": "SCMP_ARCH_X86_64",
+                    "subArchitectures": ["SCMP_ARCH_X86", "SCMP_ARCH_X32"],
+                },
+                {
+                    "architecture": "SCMP_ARCH_AARCH64",
+                    "subArchitectures": ["SCMP_ARCH_ARM"],
+                },
+            ],
+            "syscalls": [
+                {
+                    "names": syscalls,
+                    "action": "SCMP_ACT_ALLOW",
+                },
+            ],
+        });
+        let mut content = serde_json::to_string_pretty(&profile).unwrap_or_default();
+        content.push('\n');
+        content
+    }
+
+    /// AppArmor profile of a proxy
+    ///
+    /// Based on Docker's `docker-default` profile, without mounts or
+    /// ptrace, and with the mounted configuration read-only.
+    fn apparmor(&self, proxy: &ProxyConfig) -> String {
+        let name = self.apparmor_name(proxy);
+        let config_dir = proxy.proxy_type.config_dir();
+        format!(
+            "# AppArmor profile for proxy {proxy_name} of {project}\n\
+             # Generated by Cerberus Rust edition\n\
+             # Load before starting the stack:\n\
+             #   sudo apparmor_parser -r -W {path}\n\
+             \n\
+             #include <tunables/global>\n\
+             \n\
+             profile {name} flags=(attach_disconnected,mediate_deleted) {{\n  \
+               #include <abstractions/base>\n\
+             \n  \
+               network inet stream,\n  \
+               network inet6 stream,\n  \
+               network inet dgram,\n  \
+               network inet6 dgram,\n  \
+               network unix,\n  \
+               network netlink raw,\n\
+             \n  \
+               # Master processes bind low ports and drop to their worker user\n  \
+               capability chown,\n  \
+               capability dac_override,\n  \
+               capability fowner,\n  \
+               capability kill,\n  \
+               capability net_bind_service,\n  \
+               capability setgid,\n  \
+               capability setuid,\n\
+             \n  \
+               file,\n  \
+               signal (send, receive) peer={name},\n  \
+               signal (receive) peer=unconfined,\n\
+             \n  \
+               # The generated configuration is only read\n  \
+               deny {config_dir}/** wl,\n\
+             \n  \
+               deny mount,\n  \
+               deny umount,\n  \
+               deny pivot_root,\n  \
+               deny ptrace,\n  \
+               deny @{{PROC}}/* w,\n  \
+               deny @{{PROC}}/{{[^1-9],[^1-9][^0-9],[^1-9s][^0-9y][^0-9s],[^1-9][^0-9][^0-9][^0-9/]*}}/** w,\n  \
+               deny @{{PROC}}/sys/[^k]** w,\n  \
+               deny @{{PROC}}/sys/kernel/{{?,??,[^s][^h][^m]**}} w,\n  \
+               deny @{{PROC}}/sysrq-trigger rwklx,\n  \
+               deny @{{PROC}}/kcore rwklx,\n  \
+               deny /sys/[^f]*/** wklx,\n  \
+               deny /sys/f[^s]*/** wklx,\n  \
+               deny /sys/fs/[^c]*/** wklx,\n  \
+               deny /sys/fs/c[^g]*/** wklx,\n  \
+               deny /sys/fs/cg[^r]*/** wklx,\n  \
+               deny /sys/firmware/** rwklx,\n  \
+               deny /sys/kernel/security/** rwklx,\n\
+             }}\n",
+            proxy_name = proxy.name,
+            project = self.config.project.name,
+            path = super::paths::posix(&apparmor_path(proxy)),
+        )
+    }
+}
+
+/// Path of a proxy's seccomp profile, relative to the output directory
+fn seccomp_path(proxy: &ProxyConfig) -> PathBuf {
+    Path::new(PROFILES_DIR).join(format!("{}.seccomp.json", proxy.name))
+}
+
+/// Path of a proxy's AppArmor profile, relative to the output directory
+fn apparmor_path(proxy: &ProxyConfig) -> PathBuf {
+    Path::new(PROFILES_DIR).join(format!("{}.apparmor", proxy.name))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,114 @@
+//! # Tests for security profile generation
+//!
+//! These tests verify that every proxy gets a seccomp and an AppArmor
+//! profile, and the `security_opt` entries that apply them.
+
+use super::*;
+use crate::test_support::parse_config;
+use pretty_assertions::assert_eq;
+
+/// Helper function to create a config with two proxies and the given profiles
+fn create_config(profiles: &str) -> Config {
+    parse_config(&format!(
+        r#"
+[project]
+name = "profiles-test"
+
+[security.profiles]
+{profiles}
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+
+[[proxies]]
+name = "cache"
+type = "varnish"
+layer = 2
+default_upstream = "http://192.0.2.1:3000"
+"#
+    ))
+}
+
+#[test]
+fn test_disabled_by_default() {
+    let config = create_config("");
+    let generator = SecurityProfilesGenerator::new(&config);
+    assert!(generator.files().is_empty());
+    assert!(generator.security_opt(&config.proxies[0]).is_empty());
+}
+
+#[test]
+fn test_profiles_per_proxy() {
+    let config = create_config("enabled = true\nallow_syscalls = [\"io_uring_setup\"]");
+    let generator = SecurityProfilesGenerator::new(&config);
+    let files = generator.files();
+    let paths: Vec<String> = files
+        .iter()
+        .map(|(path, _)| super::super::paths::posix(path))
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "security-profiles/edge.seccomp.json",
+            "security-profiles/edge.apparmor",
+            "security-profiles/cache.seccomp.json",
+            "security-profiles/cache.apparmor",
+        ]
+    );
+
+    let seccomp: serde_json::Value = serde_json::from_str(&files[0].1).unwrap();
+    assert_eq!(seccomp["defaultAction"], "SCMP_ACT_ERRNO");
+    let allowed = |profile: &serde_json::Value, syscall: &str| {
+        profile["syscalls"][0]["names"]
+            .as_array()
+            .unwrap()
+            .contains(&json!(syscall))
+    };
+    assert!(allowed(&seccomp, "epoll_wait"));
+    assert!(allowed(&seccomp, "io_uring_setup"));
+    assert!(!allowed(&seccomp, "mount"));
+    assert!(!allowed(&seccomp, "ptrace"));
+    assert!(!allowed(&seccomp, "mlock"));
+    // Varnish locks its shared memory log
+    let varnish: serde_json::Value = serde_json::from_str(&files[2].1).unwrap();
+    assert!(allowed(&varnish, "mlock"));
+
+    let apparmor = &files[1].1;
+    assert!(apparmor.contains("#   sudo apparmor_parser -r -W security-profiles/edge.apparmor\n"));
+    assert!(apparmor.contains(
+        "profile cerberus-profiles-test-edge flags=(attach_disconnected,mediate_deleted) {\n"
+    ));
+    assert!(apparmor.contains("  deny /etc/nginx/** wl,\n"));
+    assert!(apparmor.contains("  deny mount,\n"));
+    assert!(files[3].1.contains("  deny /etc/varnish/** wl,\n"));
+
+    assert_eq!(
+        generator.security_opt(&config.proxies[0]),
+        [
+            "seccomp=./security-profiles/edge.seccomp.json",
+            "apparmor=cerberus-profiles-test-edge",
+        ]
+    );
+}
+
+#[test]
+fn test_profiles_selection() {
+    let config =
+        create_config("enabled = true\napparmor = false\nselinux_type = \"cerberus_proxy_t\"");
+    let generator = SecurityProfilesGenerator::new(&config);
+    assert!(
+        generator
+            .files()
+            .iter()
+            .all(|(path, _)| path.extension().is_some_and(|ext| ext == "json"))
+    );
+    assert_eq!(
+        generator.security_opt(&config.proxies[1]),
+        [
+            "seccomp=./security-profiles/cache.seccomp.json",
+            "label=type:cerberus_proxy_t",
+        ]
+    );
+}
@@ -0,0 +1,198 @@
+//! Dependency graph of the generators a run renders
+//!
+//! Every generator of a [`GeneratorRegistry`] is one task. A generator
+//! that needs the files of others names them in
+//! [`Generator::depends_on`] and receives their files in
+//! [`GenerateContext::dependencies`]. The graph groups the tasks into
+//! waves: each wave only depends on earlier ones, so the tasks of a wave
+//! can render at the same time.
+//!
+//! [`CerberusGenerator::plan`] renders the waves one task after the other;
+//! with the `concurrent` feature, `CerberusGenerator::plan_concurrent`
+//! renders each wave on a `tokio::task::JoinSet`. Both return the files of every task in
+//! registry order, so the output does not depend on which task finished
+//! first.
+//!
+//! [`GeneratorRegistry`]: super::GeneratorRegistry
+//! [`Generator::depends_on`]: super::Generator::depends_on
+//! [`GenerateContext::dependencies`]: super::GenerateContext::dependencies
+//! [`CerberusGenerator::plan`]: super::CerberusGenerator::plan
+
+use super::{GeneratedFile, Generator, GeneratorRegistry};
+use crate::{CerberusError, Result};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Files rendered by each generator, keyed by generator name
+pub type RenderedFiles = BTreeMap<String, Arc<Vec<GeneratedFile>>>;
+
+/// Every generator of a graph with the files it rendered, in registry order
+pub type TaskOutputs = Vec<(Arc<dyn Generator>, Arc<Vec<GeneratedFile>>)>;
+
+/// The generators of a registry, ordered into waves of independent tasks
+pub struct TaskGraph {
+    /// Generators in registry order
+    generators: Vec<Arc<dyn Generator>>,
+    /// Indexes of the generators each generator depends on
+    dependencies: Vec<Vec<usize>>,
+    /// Indexes of the generators of each wave, in registry order
+    waves: Vec<Vec<usize>>,
+}
+
+impl TaskGraph {
+    /// Build the graph of a registry
+    ///
+    /// # Errors
+    /// Returns error if a generator depends on one that is not registered,
+    /// or if dependencies form a cycle
+    pub fn new(registry: &GeneratorRegistry) -> Result<Self> {
+        let generators: Vec<Arc<dyn Generator>> = registry.generators().cloned().collect();
+        let index: BTreeMap<&str, usize> = generators
+            .iter()
+            .enumerate()
+            .map(|(i, generator)| (generator.name(), i))
+            .collect();
+
+        let mut dependencies = Vec::with_capacity(generators.len());
+        for generator in &generators {
+            let mut indexes = Vec::new();
+            for name in generator.depends_on() {
+                let dependency = *index.get(name.as_str()).ok_or_else(|| {
+                    CerberusError::validation(format!(
+                        "Generator '{}' depends on '{name}', which is not registered",
+                        generator.name()
+                    ))
+                })?;
+                indexes.push(dependency);
+            }
+            dependencies.push(indexes);
+        }
+
+        // Each wave takes the tasks whose dependencies are all in earlier waves
+        let mut wave_of: Vec<Option<usize>> = vec![None; generators.len()];
+        let mut waves: Vec<Vec<usize>> = Vec::new();
+        while wave_of.iter().any(Option::is_none) {
+            let wave: Vec<usize> = (0..generators.len())
+                .filter(|&task| {
+                    wave_of[task].is_none()
+                        && dependencies[task]
+                            .iter()
+                            .all(|&dependency| wave_of[dependency].is_some())
+                })
+                .collect();
+            if wave.is_empty() {
+                let cycle: Vec<&str> = (0..generators.len())
+                    .filter(|&task| wave_of[task].is_none())
+                    .map(|task| generators[task].name())
+                    .collect();
+                return Err(CerberusError::validation(format!(
+                    "Generators {} depend on each other",
+                    cycle.join(", ")
+                )));
+            }
+            for &task in &wave {
+                wave_of[task] = Some(waves.len());
+            }
+            waves.push(wave);
+        }
+
+        Ok(Self {
+            generators,
+            dependencies,
+            waves,
+        })
+    }
+
+    /// Names of the generators of each wave
+    pub fn waves(&self) -> Vec<Vec<&str>> {
+        self.waves
+            .iter()
+            .map(|wave| {
+                wave.iter()
+                    .map(|&task| self.generators[task].name())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Render every task one after the other, wave by wave
+    ///
+    /// `render` receives a generator and the files of its dependencies.
+    ///
+    /// # Errors
+    /// Returns the first error of a task
+    pub fn run<F>(&self, render: F) -> Result<TaskOutputs>
+    where
+        F: Fn(&dyn Generator, RenderedFiles) -> Result<Vec<GeneratedFile>>,
+    {
+        let mut rendered: Vec<Option<Arc<Vec<GeneratedFile>>>> = vec![None; self.generators.len()];
+        for wave in &self.waves {
+            for &task in wave {
+                let files = render(self.generators[task].as_ref(), self.inputs(task, &rendered))?;
+                rendered[task] = Some(Arc::new(files));
+            }
+        }
+        Ok(self.collect(rendered))
+    }
+
+    /// Render the tasks of each wave concurrently on blocking threads
+    ///
+    /// `render` receives a generator and the files of its dependencies.
+    ///
+    /// # Errors
+    /// Returns the error of the first task of a wave, in registry order, or
+    /// an error if a task panicked
+    #[cfg(feature = "concurrent")]
+    pub async fn run_concurrent<F>(&self, render: F) -> Result<TaskOutputs>
+    where
+        F: Fn(&dyn Generator, RenderedFiles) -> Result<Vec<GeneratedFile>> + Send + Sync + 'static,
+    {
+        let render = Arc::new(render);
+        let mut rendered: Vec<Option<Arc<Vec<GeneratedFile>>>> = vec![None; self.generators.len()];
+        for wave in &self.waves {
+            let mut tasks = tokio::task::JoinSet::new();
+            for &task in wave {
+                let generator = Arc::clone(&self.generators[task]);
+                let inputs = self.inputs(task, &rendered);
+                let render = Arc::clone(&render);
+                tasks.spawn_blocking(move || (task, render(generator.as_ref(), inputs)));
+            }
+
+            let mut results = BTreeMap::new();
+            while let Some(joined) = tasks.join_next().await {
+                let (task, result) = joined
+                    .map_err(|e| CerberusError::config(format!("Generation task failed: {e}")))?;
+                results.insert(task, result);
+            }
+            // Report the same error whichever task finished first
+            for (task, result) in results {
+                rendered[task] = Some(Arc::new(result?));
+            }
+        }
+        Ok(self.collect(rendered))
+    }
+
+    /// Files of a task's dependencies, keyed by generator name
+    fn inputs(&self, task: usize, rendered: &[Option<Arc<Vec<GeneratedFile>>>]) -> RenderedFiles {
+        self.dependencies[task]
+            .iter()
+            .filter_map(|&dependency| {
+                let files = rendered[dependency].as_ref()?;
+                Some((
+                    self.generators[dependency].name().to_string(),
+                    Arc::clone(files),
+                ))
+            })
+            .collect()
+    }
+
+    /// Files of every task, in registry order
+    fn collect(&self, rendered: Vec<Option<Arc<Vec<GeneratedFile>>>>) -> TaskOutputs {
+        self.generators
+            .iter()
+            .cloned()
+            .zip(rendered)
+            .filter_map(|(generator, files)| Some((generator, files?)))
+            .collect()
+    }
+}
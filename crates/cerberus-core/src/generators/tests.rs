@@ -0,0 +1,874 @@
+//! # Tests for the master generator
+//!
+//! These tests verify that rendering in memory matches the files written by
+//! a full generation, and that hand-edited files are protected.
+
+use super::*;
+use crate::test_support::parse_config;
+use tempfile::TempDir;
+
+#[test]
+fn test_render_matches_generated_files() {
+    let config = parse_config(
+        r#"
+[project]
+name = "render-test"
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+layer = 1
+default_upstream = "http://anubis:8080"
+
+[[proxies]]
+name = "proxy-2"
+type = "caddy"
+layer = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#,
+    );
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let output_dir = temp_dir.path().join("built");
+    let generator = CerberusGenerator::new(&config, output_dir.to_string_lossy().to_string());
+
+    let rendered = generator.render().expect("Rendering should succeed");
+    assert!(!output_dir.exists(), "Rendering must not touch the disk");
+    assert_eq!(
+        rendered,
+        generator.render().expect("Rendering should succeed")
+    );
+
+    generator.generate_all().expect("Generation should succeed");
+    let written = snapshot(&output_dir).expect("Snapshot should succeed");
+
+    assert_eq!(rendered, written);
+    assert_eq!(
+        Manifest::load(&output_dir).expect("Manifest should load"),
+        Some(Manifest::from_files(&rendered))
+    );
+    assert!(rendered.contains_key(Path::new("docker-compose.yaml")));
+    assert!(rendered.contains_key(Path::new("proxy-configs/proxy/conf.d/default.conf")));
+    assert!(rendered.contains_key(Path::new("anubis/botPolicy.json")));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(output_dir.join("update.sh"))
+            .expect("update.sh should exist")
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o111, 0o111);
+    }
+}
+
+#[test]
+fn test_hand_edited_files_need_force() {
+    let config = parse_config(
+        r#"
+[project]
+name = "manifest-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+"#,
+    );
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let output_dir = temp_dir.path().join("built");
+    let generator = CerberusGenerator::new(&config, output_dir.to_string_lossy().to_string());
+
+    generator.generate_all().expect("Generation should succeed");
+    generator
+        .generate_all()
+        .expect("Unchanged files should be regenerated");
+
+    // Deleted files are simply generated again
+    std::fs::remove_file(output_dir.join("ROUTES.md")).expect("Failed to remove file");
+    generator
+        .generate_all()
+        .expect("Deleted files should be regenerated");
+
+    let compose = output_dir.join("docker-compose.yaml");
+    let generated = std::fs::read_to_string(&compose).expect("Failed to read compose file");
+    std::fs::write(&compose, format!("{generated}# edited\n")).expect("Failed to edit file");
+
+    let error = generator
+        .generate_all()
+        .expect_err("Edited files should be protected")
+        .to_string();
+    assert!(error.contains("docker-compose.yaml"), "{error}");
+    assert!(error.contains("--force"), "{error}");
+    assert!(
+        std::fs::read_to_string(&compose)
+            .expect("Failed to read compose file")
+            .ends_with("# edited\n"),
+        "The edited file must be left untouched"
+    );
+
+    CerberusGenerator::new(&config, output_dir.to_string_lossy().to_string())
+        .force(true)
+        .generate_all()
+        .expect("Forced generation should succeed");
+    assert_eq!(
+        std::fs::read_to_string(&compose).expect("Failed to read compose file"),
+        generated
+    );
+}
+
+#[test]
+fn test_host_paths_are_posix() {
+    for (path, expected) in [
+        ("./certs/tls.pem", "./certs/tls.pem"),
+        (".\\certs\\tls.pem", "./certs/tls.pem"),
+        ("C:\\srv\\certs", "/c/srv/certs"),
+        ("d:/data", "/d/data"),
+        ("~/.vault-token", "~/.vault-token"),
+    ] {
+        assert_eq!(paths::host_path(path), expected, "{path}");
+    }
+
+    for (spec, expected) in [
+        ("C:\\data\\app:/app/data:ro", "/c/data/app:/app/data:ro"),
+        (".\\uploads:/uploads", "./uploads:/uploads"),
+        ("app_data:/var/lib/app", "app_data:/var/lib/app"),
+        ("/srv/app:/app", "/srv/app:/app"),
+        ("/var/cache", "/var/cache"),
+    ] {
+        assert_eq!(paths::volume(spec), expected, "{spec}");
+    }
+}
+
+#[test]
+fn test_rendered_files_use_lf() {
+    assert_eq!(
+        paths::lf("#!/bin/sh\r\nset -e\r\n".to_string()),
+        "#!/bin/sh\nset -e\n"
+    );
+
+    let config = parse_config(
+        r#"
+[project]
+name = "windows-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+image = "example/web:1.0"
+volumes = ["C:\\srv\\web:/srv/web:ro"]
+"#,
+    );
+    let files = CerberusGenerator::new(&config, "unused").render().unwrap();
+    for (path, content) in &files {
+        assert!(!content.contains(&b'\r'), "{} has CR", path.display());
+    }
+    let compose = String::from_utf8_lossy(&files[Path::new("docker-compose.yaml")]);
+    assert!(compose.contains("      - /c/srv/web:/srv/web:ro\n"));
+}
+
+#[cfg(windows)]
+#[test]
+fn test_windows_relative_paths_use_slashes() {
+    let path = Path::new("secrets").join("web-app.env");
+    assert_eq!(path.to_string_lossy(), "secrets\\web-app.env");
+    assert_eq!(paths::posix(&path), "secrets/web-app.env");
+    assert_eq!(
+        paths::posix(&paths::decrypted_path("stack", "db")),
+        "/dev/shm/cerberus/stack/db"
+    );
+}
+
+#[cfg(windows)]
+#[test]
+fn test_windows_compose_mounts() {
+    let config = parse_config(
+        r#"
+[project]
+name = "windows-test"
+
+[secrets.api_key]
+file = ".\\secrets\\api_key.txt"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+image = "example/web:1.0"
+
+[services.environment]
+API_KEY = "vault://kv/web#api_key"
+"#,
+    );
+    let compose = DockerComposeGenerator::new(&config).generate().unwrap();
+
+    assert!(compose.contains("    file: ./secrets/api_key.txt\n"));
+    assert!(compose.contains("      - ./secrets/web-app.env\n"));
+    assert!(!compose.contains('\\'));
+}
+
+#[test]
+fn test_route_paths_become_anubis_rules() {
+    let config = parse_config(
+        r#"
+[project]
+name = "route-paths-test"
+
+[anubis]
+enabled = true
+difficulty = 4
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+layer = 1
+
+[[proxies.routes]]
+type = "direct"
+domain = "app.example.com"
+upstream = "http://proxy-2:80"
+
+[[proxies.routes.paths]]
+prefix = "/api/"
+protect = false
+
+[[proxies.routes.paths]]
+prefix = "/login"
+protect = true
+
+[[proxies]]
+name = "proxy-2"
+type = "caddy"
+layer = 2
+"#,
+    );
+    let policy: serde_json::Value =
+        serde_json::from_str(&AnubisGenerator::new(&config).generate().unwrap()).unwrap();
+
+    assert!(
+        policy["ALLOW"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!({
+                "host": "app.example.com",
+                "path": "/api/*",
+                "description": "Unprotected path /api/ for app.example.com"
+            }))
+    );
+    assert_eq!(
+        policy["CHALLENGE"][0],
+        serde_json::json!({
+            "host": "app.example.com",
+            "path": "/login*",
+            "difficulty": 4,
+            "description": "Protected path /login for app.example.com"
+        })
+    );
+}
+
+#[test]
+fn test_content_skips_the_challenge() {
+    let config = parse_config(
+        r#"
+[project]
+name = "content-test"
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+
+[content.robots]
+sitemaps = ["https://example.com/sitemap.xml"]
+
+[content.security_txt]
+contact = ["mailto:security@example.com"]
+expires = "2027-01-01T00:00:00Z"
+"#,
+    );
+    let policy: serde_json::Value =
+        serde_json::from_str(&AnubisGenerator::new(&config).generate().unwrap()).unwrap();
+
+    let allow = policy["ALLOW"].as_array().unwrap();
+    assert_eq!(
+        allow[..2],
+        [
+            serde_json::json!({
+                "path": "/robots.txt",
+                "description": "Allow robots.txt from [content]"
+            }),
+            serde_json::json!({
+                "path": "/.well-known/security.txt",
+                "description": "Allow security.txt from [content]"
+            }),
+        ]
+    );
+    assert_eq!(
+        allow
+            .iter()
+            .filter(|rule| rule["path"] == "/robots.txt")
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn test_anubis_instances() {
+    let config = parse_config(
+        r#"
+[project]
+name = "anubis-instances-test"
+
+[anubis]
+enabled = true
+difficulty = 4
+
+[[anubis.instances]]
+name = "anubis-shop"
+domains = ["shop.example.com"]
+bind = ":8081"
+difficulty = 8
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+layer = 1
+default_upstream = "http://anubis:8080"
+
+[[proxies.routes]]
+type = "conditional"
+domain = "shop.example.com"
+upstream = "http://proxy-2:80"
+
+[[proxies.routes.paths]]
+prefix = "/checkout"
+protect = true
+
+[[proxies.routes]]
+type = "conditional"
+domain = "blog.example.com"
+upstream = "http://proxy-2:80"
+difficulty = 6
+
+[[proxies]]
+name = "proxy-2"
+type = "caddy"
+layer = 2
+"#,
+    );
+    let generator = CerberusGenerator::new(&config, "built");
+    let rendered = generator.render().expect("Rendering should succeed");
+    let file = |path: &str| {
+        String::from_utf8(rendered[Path::new(path)].clone()).expect("Files should be UTF-8")
+    };
+
+    let shop: serde_json::Value =
+        serde_json::from_str(&file("anubis/anubis-shop/botPolicy.json")).unwrap();
+    assert_eq!(shop["config"]["difficulty"], 8);
+    assert_eq!(shop["CHALLENGE"][0]["host"], "shop.example.com");
+    assert!(!file("anubis/anubis-shop/botPolicy.json").contains("blog.example.com"));
+    let global: serde_json::Value = serde_json::from_str(&file("anubis/botPolicy.json")).unwrap();
+    assert_eq!(global["config"]["difficulty"], 4);
+    assert_eq!(global["CHALLENGE"][0]["host"], "blog.example.com");
+    assert!(
+        file("anubis/anubis-shop/.env")
+            .contains("BIND=:8081\nTARGET=http://proxy-2:80\nDIFFICULTY=8\n")
+    );
+
+    let compose = file("docker-compose.yaml");
+    assert!(compose.contains("  anubis:\n"));
+    assert!(compose.contains("  # DDoS Protection Layer: shop.example.com\n  anubis-shop:\n"));
+    assert!(compose.contains("      - ./anubis/anubis-shop/.env\n"));
+    assert!(compose.contains(
+        "      anubis:\n        condition: service_healthy\n      anubis-shop:\n        condition: service_healthy\n"
+    ));
+
+    let nginx = file("proxy-configs/proxy/conf.d/default.conf");
+    assert!(nginx.contains(
+        "    default http://anubis:8080;\n    shop.example.com http://anubis-shop:8081;\n"
+    ));
+    assert!(nginx.contains("map $host $anubis_upstream {"));
+    assert!(nginx.contains("        proxy_pass $anubis_upstream;\n"));
+    assert!(
+        nginx.contains("    location ^~ /checkout {\n        proxy_pass http://anubis-shop:8081;")
+    );
+}
+
+#[test]
+fn test_acme_dns_challenge() {
+    let render = |proxy_type: &str| {
+        let config = parse_config(&format!(
+            r#"
+[project]
+name = "acme-test"
+
+[secrets.cloudflare_token]
+file = "./secrets/cloudflare_token.txt"
+
+[tls.acme]
+email = "ops@example.com"
+provider = "cloudflare"
+domains = ["example.com", "*.example.com"]
+credentials = {{ CF_DNS_API_TOKEN = "cloudflare_token" }}
+
+[tls.redirect_http]
+
+[[proxies]]
+name = "proxy"
+type = "{proxy_type}"
+external_port = 80
+internal_port = 8080
+"#
+        ));
+        CerberusGenerator::new(&config, "built")
+            .render()
+            .expect("Rendering should succeed")
+    };
+    let file = |rendered: &std::collections::BTreeMap<std::path::PathBuf, Vec<u8>>, path: &str| {
+        String::from_utf8(rendered[Path::new(path)].clone()).expect("Files should be UTF-8")
+    };
+
+    let caddy = render("caddy");
+    let caddyfile = file(&caddy, "proxy-configs/proxy/Caddyfile");
+    assert!(caddyfile.contains("\tauto_https disable_redirects\n\temail ops@example.com\n"));
+    assert!(caddyfile.contains(
+        "\tacme_dns cloudflare {\n\t\tapi_token {file./run/secrets/cloudflare_token}\n\t}\n"
+    ));
+    assert!(caddyfile.contains(":8080, example.com, *.example.com {"));
+    assert!(caddyfile.contains("\t\tnot protocol https\n"));
+    assert!(
+        file(&caddy, "dockerfiles/proxy/Dockerfile")
+            .contains("--with github.com/caddy-dns/cloudflare")
+    );
+    let compose = file(&caddy, "docker-compose.yaml");
+    assert!(compose.contains("      - \"443:8443\"\n      - \"80:8080\"\n"));
+    assert!(compose.contains("      - proxy-acme:/data\n"));
+    assert!(compose.contains("    secrets:\n      - cloudflare_token\n"));
+    assert!(compose.contains("  proxy-acme:\n    driver: local\n    name: acme-test-proxy-acme\n"));
+    assert!(!compose.contains("CF_DNS_API_TOKEN"));
+
+    let traefik = render("traefik");
+    let static_config = file(&traefik, "proxy-configs/proxy/traefik.yml");
+    assert!(static_config.contains("  websecure:\n    address: \":8443\"\n"));
+    assert!(static_config.contains(
+        "        certResolver: acme\n        domains:\n          - main: \"example.com\"\n"
+    ));
+    assert!(static_config.contains("      dnsChallenge:\n        provider: cloudflare\n"));
+    assert!(
+        file(&traefik, "proxy-configs/proxy/dynamic/default.yml")
+            .contains("      entryPoints:\n        - web\n        - websecure\n")
+    );
+    let compose = file(&traefik, "docker-compose.yaml");
+    assert!(compose.contains("      - CF_DNS_API_TOKEN_FILE=/run/secrets/cloudflare_token\n"));
+    assert!(compose.contains("      - proxy-acme:/data\n"));
+}
+
+#[test]
+fn test_fragment_cache_reuses_renders() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let cache_dir = temp_dir.path().join("fragments");
+    let data = serde_json::json!({ "service": { "name": "web-app" } });
+    let renders = std::cell::Cell::new(0);
+    let render = || {
+        renders.set(renders.get() + 1);
+        Ok("rendered".to_string())
+    };
+
+    let cache = FragmentCache::on_disk(&cache_dir);
+    assert_eq!(
+        cache.render("nginx_service", "v1", &data, render).unwrap(),
+        "rendered"
+    );
+    assert_eq!(
+        cache.render("nginx_service", "v1", &data, render).unwrap(),
+        "rendered"
+    );
+    assert_eq!(renders.get(), 1);
+
+    // A later run reads the fragment from disk; a changed template does not
+    let cache = FragmentCache::on_disk(&cache_dir);
+    cache.render("nginx_service", "v1", &data, render).unwrap();
+    assert_eq!(renders.get(), 1);
+    cache.render("nginx_service", "v2", &data, render).unwrap();
+    assert_eq!(renders.get(), 2);
+    assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 2);
+
+    let cache = FragmentCache::on_disk(&cache_dir);
+    cache.render("nginx_service", "v2", &data, render).unwrap();
+    cache.prune().unwrap();
+    assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 1);
+    assert_eq!(renders.get(), 2);
+}
+
+#[test]
+fn test_cached_generation_matches_render() {
+    let config = parse_config(
+        r#"
+[project]
+name = "fragment-test"
+
+[[proxies]]
+name = "proxy"
+type = "traefik"
+external_port = 80
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+layer = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+
+[[services]]
+name = "api"
+domain = "api.example.com"
+upstream = "http://192.0.2.2:3000"
+"#,
+    );
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let output_dir = temp_dir.path().join("built");
+    let cache_dir = temp_dir.path().join("fragments");
+    let expected = CerberusGenerator::new(&config, output_dir.to_string_lossy().to_string())
+        .render()
+        .unwrap();
+
+    for _ in 0..2 {
+        CerberusGenerator::new(&config, output_dir.to_string_lossy().to_string())
+            .fragment_cache(Some(cache_dir.clone()))
+            .generate_all()
+            .expect("Generation should succeed");
+        let written = snapshot(&output_dir).expect("Snapshot should succeed");
+        assert_eq!(written, expected);
+    }
+    // Two Traefik dynamic files and two nginx conf.d files
+    assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 4);
+}
+
+/// Custom generator writing a list of the service domains
+struct Domains {
+    path: &'static str,
+}
+
+impl Generator for Domains {
+    fn name(&self) -> &str {
+        "domains"
+    }
+
+    fn generate(&self, config: &Config, _: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        let domains: Vec<&str> = config.services.iter().map(|s| s.domain.as_str()).collect();
+        Ok(vec![(PathBuf::from(self.path), domains.join("\n"))])
+    }
+}
+
+#[test]
+fn test_registered_generators_are_rendered() {
+    let config = parse_config(
+        r#"
+[project]
+name = "registry-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#,
+    );
+    let builtin = CerberusGenerator::new(&config, "built").render().unwrap();
+
+    let mut registry = GeneratorRegistry::builtin();
+    registry.register(Domains {
+        path: "custom/domains.txt",
+    });
+    assert_eq!(registry.names().first(), Some(&"deployment"));
+    assert_eq!(registry.names().last(), Some(&"domains"));
+    let mut files = CerberusGenerator::new(&config, "built")
+        .registry(registry)
+        .render()
+        .unwrap();
+    assert_eq!(
+        files.remove(Path::new("custom/domains.txt")),
+        Some(b"app.example.com".to_vec())
+    );
+    assert_eq!(files, builtin);
+
+    // Custom generators may not overwrite another generator's files
+    let mut registry = GeneratorRegistry::builtin();
+    registry.register(Domains {
+        path: "docker-compose.yaml",
+    });
+    let error = CerberusGenerator::new(&config, "built")
+        .registry(registry)
+        .render()
+        .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("Generators 'deployment' and 'domains' both write docker-compose.yaml")
+    );
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let output_dir = temp_dir.path().join("built");
+    let mut registry = GeneratorRegistry::builtin();
+    registry.register(Domains {
+        path: "custom/domains.txt",
+    });
+    CerberusGenerator::new(&config, output_dir.to_string_lossy().to_string())
+        .registry(registry)
+        .generate_all()
+        .expect("Generation should succeed");
+    assert_eq!(
+        std::fs::read_to_string(output_dir.join("custom/domains.txt")).unwrap(),
+        "app.example.com"
+    );
+}
+
+/// Custom generator counting the lines written by the generators it reads
+struct LineCount {
+    name: &'static str,
+    reads: Vec<&'static str>,
+}
+
+impl Generator for LineCount {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn depends_on(&self) -> Vec<String> {
+        self.reads.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn generate(&self, _: &Config, context: &GenerateContext) -> Result<Vec<GeneratedFile>> {
+        let lines: Vec<String> = context
+            .dependencies
+            .iter()
+            .map(|(name, files)| {
+                let count: usize = files
+                    .iter()
+                    .map(|(_, content)| content.lines().count())
+                    .sum();
+                format!("{name} {count}")
+            })
+            .collect();
+        Ok(vec![(
+            PathBuf::from(format!("custom/{}.txt", self.name)),
+            lines.join("\n"),
+        )])
+    }
+}
+
+#[cfg(feature = "concurrent")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_concurrent_generation_matches_sequential() {
+    let config = parse_config(
+        r#"
+[project]
+name = "concurrent-test"
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+layer = 1
+default_upstream = "http://anubis:8080"
+
+[[proxies]]
+name = "proxy-2"
+type = "traefik"
+layer = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#,
+    );
+    let generator = CerberusGenerator::new(&config, "built");
+    let expected = generator.render().unwrap();
+    for _ in 0..3 {
+        assert_eq!(generator.render_concurrent().await.unwrap(), expected);
+    }
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let output_dir = temp_dir.path().join("built");
+    CerberusGenerator::new(&config, output_dir.to_string_lossy().to_string())
+        .generate_all_concurrent()
+        .await
+        .expect("Generation should succeed");
+    assert_eq!(snapshot(&output_dir).unwrap(), expected);
+}
+
+#[cfg(feature = "concurrent")]
+#[tokio::test]
+async fn test_generators_receive_their_dependencies() {
+    let config = parse_config(
+        r#"
+[project]
+name = "dependency-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#,
+    );
+    let mut registry = GeneratorRegistry::builtin();
+    // Registered before the generator it reads
+    registry.register(LineCount {
+        name: "summary",
+        reads: vec!["domains"],
+    });
+    registry.register(Domains {
+        path: "custom/domains.txt",
+    });
+
+    let graph = TaskGraph::new(&registry).unwrap();
+    let waves = graph.waves();
+    assert_eq!(waves.len(), 2);
+    assert!(waves[0].contains(&"deployment") && waves[0].contains(&"domains"));
+    assert_eq!(waves[1], vec!["summary"]);
+
+    let generator = CerberusGenerator::new(&config, "built").registry(registry);
+    let files = generator.render().unwrap();
+    assert_eq!(
+        files.get(Path::new("custom/summary.txt")),
+        Some(&b"domains 1".to_vec())
+    );
+    assert_eq!(generator.render_concurrent().await.unwrap(), files);
+}
+
+#[test]
+fn test_task_graph_rejects_bad_dependencies() {
+    let mut registry = GeneratorRegistry::builtin();
+    registry.register(LineCount {
+        name: "summary",
+        reads: vec!["missing"],
+    });
+    let error = TaskGraph::new(&registry).err().unwrap().to_string();
+    assert!(error.contains("Generator 'summary' depends on 'missing', which is not registered"));
+
+    let mut registry = GeneratorRegistry::builtin();
+    registry.register(LineCount {
+        name: "first",
+        reads: vec!["second"],
+    });
+    registry.register(LineCount {
+        name: "second",
+        reads: vec!["first"],
+    });
+    let error = TaskGraph::new(&registry).err().unwrap().to_string();
+    assert!(error.contains("Generators first, second depend on each other"));
+}
+
+#[test]
+fn test_plan_returns_typed_artifacts() {
+    let config = parse_config(
+        r#"
+[project]
+name = "plan-test"
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 80
+default_upstream = "http://anubis:8080"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#,
+    );
+    let mut registry = GeneratorRegistry::builtin();
+    registry.register(Domains {
+        path: "custom/domains.txt",
+    });
+    let generator = CerberusGenerator::new(&config, "built").registry(registry);
+    let plan = generator.plan().expect("Planning should succeed");
+
+    // The plan holds the rendered files, sorted by path
+    let rendered = generator.render().unwrap();
+    assert_eq!(
+        plan.iter()
+            .map(|artifact| &artifact.path)
+            .collect::<Vec<_>>(),
+        rendered.keys().collect::<Vec<_>>()
+    );
+    for artifact in &plan {
+        assert_eq!(artifact.content.as_bytes(), rendered[&artifact.path]);
+    }
+
+    let kind_of = |path: &str| {
+        plan.iter()
+            .find(|artifact| artifact.path == Path::new(path))
+            .map(|artifact| artifact.kind.clone())
+    };
+    assert_eq!(
+        kind_of("docker-compose.yaml"),
+        Some(ArtifactKind::Deployment)
+    );
+    assert_eq!(
+        kind_of("proxy-configs/proxy/conf.d/proxy_params.conf"),
+        Some(ArtifactKind::ProxyConfig)
+    );
+    assert_eq!(
+        kind_of("dockerfiles/proxy/Dockerfile"),
+        Some(ArtifactKind::Dockerfile)
+    );
+    assert_eq!(kind_of("anubis/botPolicy.json"), Some(ArtifactKind::Anubis));
+    assert_eq!(
+        kind_of("custom/domains.txt"),
+        Some(ArtifactKind::Custom("domains".to_string()))
+    );
+    assert!(
+        plan.iter()
+            .find(|artifact| artifact.path == Path::new("update.sh"))
+            .is_some_and(GeneratedArtifact::is_executable)
+    );
+
+    // Artifacts can be written anywhere, one by one
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let artifact = plan
+        .iter()
+        .find(|artifact| artifact.kind == ArtifactKind::Dockerfile)
+        .unwrap();
+    let written = artifact.write_to(temp_dir.path()).unwrap();
+    assert_eq!(written, temp_dir.path().join(&artifact.path));
+    assert_eq!(std::fs::read_to_string(written).unwrap(), artifact.content);
+}
@@ -5,8 +5,7 @@
 
 use crate::config::Config;
 use crate::error::Result;
-use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Generator for update/deployment shell scripts
 pub struct UpdateScriptGenerator<'a> {
@@ -21,21 +20,15 @@ impl<'a> UpdateScriptGenerator<'a> {
 
     /// Generate update.sh script in the built directory
     pub fn generate(&self, output_dir: &Path) -> Result<()> {
-        let script_content = self.generate_update_script()?;
-        let script_path = output_dir.join("update.sh");
-
-        fs::write(&script_path, script_content)?;
-
-        // Make script executable
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&script_path)?.permissions();
-            perms.set_mode(0o755);
-            fs::set_permissions(&script_path, perms)?;
-        }
+        super::write_files(output_dir, self.files()?)
+    }
 
-        Ok(())
+    /// Files to write, relative to the output directory
+    pub fn files(&self) -> Result<Vec<(PathBuf, String)>> {
+        Ok(vec![(
+            PathBuf::from("update.sh"),
+            self.generate_update_script()?,
+        )])
     }
 
     /// Generate the update script content
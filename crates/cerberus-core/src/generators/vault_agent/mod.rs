@@ -0,0 +1,116 @@
+//! # vault-agent configuration generator
+//!
+//! With `--no-resolve`, Cerberus leaves `vault://` references to a vault-agent
+//! sidecar instead of reading Vault itself. This generator writes the agent's
+//! `agent.hcl`: one template per Vault secret, rendered to `secrets/<name>`,
+//! and one env file per service with `vault://` environment values, rendered
+//! to the same paths Cerberus would have written.
+
+use crate::{
+    Result,
+    config::{Config, SecretConfig, VaultRef},
+    generators::paths,
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Directory the output directory's `secrets/` is mounted at in the agent
+const AGENT_SECRETS_DIR: &str = "/vault/secrets";
+
+/// Generator for the vault-agent configuration
+pub struct VaultAgentGenerator<'a> {
+    config: &'a Config,
+}
+
+impl<'a> VaultAgentGenerator<'a> {
+    /// Create a new vault-agent generator
+    pub fn new(config: &'a Config) -> Self {
+        Self { config }
+    }
+
+    /// Files to write, relative to the output directory
+    ///
+    /// # Errors
+    /// Returns error if a Vault reference is malformed
+    pub fn files(&self) -> Result<Vec<(PathBuf, String)>> {
+        Ok(vec![(
+            Path::new("vault-agent").join("agent.hcl"),
+            self.generate()?,
+        )])
+    }
+
+    /// Generate `agent.hcl`
+    ///
+    /// # Errors
+    /// Returns error if a Vault reference is malformed
+    pub fn generate(&self) -> Result<String> {
+        let mut hcl = String::new();
+
+        writeln!(hcl, "# Generated by Cerberus").unwrap();
+        writeln!(hcl, "# Project: {}", self.config.project.name).unwrap();
+        writeln!(hcl, "# Vault address: $VAULT_ADDR").unwrap();
+        writeln!(hcl).unwrap();
+        writeln!(hcl, "pid_file = \"/tmp/vault-agent.pid\"").unwrap();
+        writeln!(hcl).unwrap();
+        writeln!(hcl, "auto_auth {{").unwrap();
+        writeln!(hcl, "  method \"token_file\" {{").unwrap();
+        writeln!(hcl, "    config = {{").unwrap();
+        writeln!(hcl, "      token_file_path = \"/vault/token\"").unwrap();
+        writeln!(hcl, "    }}").unwrap();
+        writeln!(hcl, "  }}").unwrap();
+        writeln!(hcl, "}}").unwrap();
+        writeln!(hcl).unwrap();
+        writeln!(hcl, "template_config {{").unwrap();
+        writeln!(hcl, "  exit_on_retry_failure = true").unwrap();
+        writeln!(hcl, "}}").unwrap();
+
+        let secrets: BTreeMap<_, _> = self.config.secrets.iter().collect();
+        for (name, secret) in secrets {
+            let SecretConfig::Vault { vault } = secret else {
+                continue;
+            };
+            let Ok(Some(reference)) = VaultRef::parse(vault) else {
+                continue;
+            };
+            writeln!(hcl).unwrap();
+            writeln!(hcl, "# Secret {name} ({reference})").unwrap();
+            writeln!(hcl, "template {{").unwrap();
+            // A quoted string, so the file gets no trailing newline
+            writeln!(
+                hcl,
+                "  contents    = \"{}\"",
+                reference.template().replace('"', "\\\"")
+            )
+            .unwrap();
+            writeln!(hcl, "  destination = \"{AGENT_SECRETS_DIR}/{name}\"").unwrap();
+            writeln!(hcl, "  perms       = \"0600\"").unwrap();
+            writeln!(hcl, "}}").unwrap();
+        }
+
+        for service in &self.config.services {
+            let references = service.vault_environment()?;
+            if references.is_empty() {
+                continue;
+            }
+            let env_file = paths::vault_env_file(&service.name);
+            let file_name = env_file.file_name().unwrap_or_default().to_string_lossy();
+            writeln!(hcl).unwrap();
+            writeln!(hcl, "# Environment of service {}", service.name).unwrap();
+            writeln!(hcl, "template {{").unwrap();
+            writeln!(hcl, "  contents    = <<EOT").unwrap();
+            for (key, reference) in references {
+                writeln!(hcl, "{key}={}", reference.template()).unwrap();
+            }
+            writeln!(hcl, "EOT").unwrap();
+            writeln!(hcl, "  destination = \"{AGENT_SECRETS_DIR}/{file_name}\"").unwrap();
+            writeln!(hcl, "  perms       = \"0600\"").unwrap();
+            writeln!(hcl, "}}").unwrap();
+        }
+
+        Ok(hcl)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,57 @@
+//! # Tests for vault-agent configuration generation
+//!
+//! These tests verify that every `vault://` reference gets a template
+//! rendering it to the path the Compose file mounts it from.
+
+use super::*;
+use crate::test_support::parse_config;
+
+const VAULT_CONFIG: &str = r#"
+[project]
+name = "vault-test"
+
+[secrets.db_password]
+vault = "vault://secret/data/db#password"
+
+[secrets.tls_cert]
+file = "./certs/tls.pem"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+image = "example/web:1.0"
+
+[services.environment]
+LOG_LEVEL = "info"
+API_KEY = "vault://kv/web#api_key"
+"#;
+
+#[test]
+fn test_agent_templates_for_secrets_and_environment() {
+    let config = parse_config(VAULT_CONFIG);
+    let hcl = VaultAgentGenerator::new(&config)
+        .generate()
+        .expect("Generation should succeed");
+
+    assert!(hcl.contains("token_file_path = \"/vault/token\""));
+    assert!(hcl.contains(
+        "  contents    = \"{{ with secret \\\"secret/data/db\\\" }}{{ if .Data.metadata }}"
+    ));
+    assert!(hcl.contains("  destination = \"/vault/secrets/db_password\"\n"));
+    assert!(hcl.contains("<<EOT\nAPI_KEY={{ with secret \"kv/web\" }}"));
+    assert!(hcl.contains("  destination = \"/vault/secrets/web-app.env\"\n"));
+    assert!(!hcl.contains("tls_cert"));
+    assert!(!hcl.contains("LOG_LEVEL"));
+}
+
+#[test]
+fn test_agent_config_path() {
+    let config = parse_config(VAULT_CONFIG);
+    let files = VaultAgentGenerator::new(&config)
+        .files()
+        .expect("Generation should succeed");
+
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].0, PathBuf::from("vault-agent/agent.hcl"));
+}
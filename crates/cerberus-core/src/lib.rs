@@ -0,0 +1,54 @@
+//! # cerberus-core - Configuration model and generators of Cerberus
+//!
+//! The library half of Cerberus: the TOML configuration model and its
+//! validation, the lint rules, the built-in templates, and the generators
+//! that render Docker Compose, Nomad, Swarm, proxy and Anubis files. It
+//! performs no network access and needs no async runtime unless the
+//! `concurrent` feature is enabled; the `cerberus` binary adds the CLI, secret resolution, and the watch, scaling and
+//! deployment daemons on top of it.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use cerberus_core::{config::Config, generators::CerberusGenerator};
+//!
+//! let config = Config::load(std::path::Path::new("config.toml"))?;
+//! let files = CerberusGenerator::new(&config, "built").render()?;
+//! for path in files.keys() {
+//!     println!("{}", path.display());
+//! }
+//! # Ok::<(), cerberus_core::CerberusError>(())
+//! ```
+//!
+//! ## Stability
+//!
+//! The crate follows semantic versioning: before 1.0, breaking changes bump
+//! the minor version. Its API is the public items of [`config`], [`error`],
+//! [`generators`], [`lint`], [`report`] and [`checksum`]. Configuration keys
+//! are only ever added with defaults, so a file that parses keeps parsing
+//! until the next breaking release; build configurations by parsing TOML or
+//! with [`config::ConfigBuilder`], as new fields make struct literals fail
+//! to compile. The rendered output may change in any release, and the
+//! contents of [`templates`] are an implementation detail of the generators.
+//!
+//! ## Features
+//!
+//! - `clap`: derives `clap::ValueEnum` for the enums the CLI takes as
+//!   arguments ([`config::DeployTarget`], [`generators::GraphStyle`])
+//! - `schema`: derives `schemars::JsonSchema` for the configuration model
+//!   and adds `config::json_schema`, the JSON Schema of `config.toml`
+//! - `concurrent`: adds the `*_concurrent` methods of
+//!   [`generators::CerberusGenerator`], which render independent generators
+//!   at the same time and need a Tokio runtime
+
+pub mod checksum;
+pub mod config;
+pub mod error;
+pub mod generators;
+pub mod lint;
+pub mod report;
+pub mod templates;
+#[cfg(test)]
+mod test_support;
+
+pub use error::{CerberusError, Result};
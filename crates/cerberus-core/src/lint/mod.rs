@@ -0,0 +1,353 @@
+//! # Configuration linting for Cerberus
+//!
+//! Opinionated checks on a valid configuration, reported by
+//! `cerberus validate` with their rule ID. Each rule's severity comes from
+//! `[lint.rules]`: `error` findings fail validation, `warning` findings fail
+//! it only with `--strict`, and `info` findings are reported only.
+
+use crate::config::{
+    Config, LintRule, LintSeverity, ProxyType, UpstreamHealthConfig, is_local_upstream,
+    upstream_host,
+};
+use serde::Serialize;
+
+/// A rule violation found in the configuration
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LintFinding {
+    /// Rule that was violated
+    pub rule: LintRule,
+    /// Severity configured for the rule
+    pub severity: LintSeverity,
+    /// Proxy, service, or section the finding is about
+    pub subject: String,
+    /// What is wrong and how to fix it
+    pub message: String,
+}
+
+impl LintFinding {
+    /// Whether the finding fails validation
+    pub fn fails(&self, strict: bool) -> bool {
+        match self.severity {
+            LintSeverity::Error => true,
+            LintSeverity::Warning => strict,
+            LintSeverity::Info | LintSeverity::Off => false,
+        }
+    }
+}
+
+/// Check a configuration against every rule that is not turned off
+///
+/// Findings are ordered by rule, then by the order of the configuration.
+pub fn lint(config: &Config) -> Vec<LintFinding> {
+    let mut findings = Vec::new();
+    for rule in LintRule::ALL {
+        let severity = config.lint.severity(rule);
+        if severity == LintSeverity::Off {
+            continue;
+        }
+
+        let violations = match rule {
+            LintRule::FloatingImageTag => floating_image_tags(config),
+            LintRule::MissingHealthcheck => missing_healthchecks(config),
+            LintRule::AdminApiEnabled => exposed_admin_apis(config),
+            LintRule::NoResourceLimits => missing_resource_limits(config),
+            LintRule::AnubisDifficulty => anubis_difficulty(config),
+            LintRule::DeprecatedKey => deprecated_keys(config),
+            LintRule::MixedTimezones => mixed_timezones(config),
+            LintRule::AnubisTopology => anubis_topology(config),
+            LintRule::LocalhostUpstream => localhost_upstreams(config),
+        };
+        findings.extend(
+            violations
+                .into_iter()
+                .map(|(subject, message)| LintFinding {
+                    rule,
+                    severity,
+                    subject,
+                    message,
+                }),
+        );
+    }
+    findings
+}
+
+/// Whether an image reference can change without the configuration changing
+///
+/// Digests are pinned; tags are floating when missing, `latest`, or free of
+/// any version number (`alpine`, `stable`).
+fn is_floating(image: &str) -> bool {
+    if image.contains('@') {
+        return false;
+    }
+    // A registry port (`registry:5000/app`) is not a tag
+    let name = image.rsplit('/').next().unwrap_or(image);
+    match name.split_once(':') {
+        None => true,
+        Some((_, tag)) => tag == "latest" || !tag.chars().any(|c| c.is_ascii_digit()),
+    }
+}
+
+fn floating_image_tags(config: &Config) -> Vec<(String, String)> {
+    let mut images: Vec<(String, &str)> = config
+        .services
+        .iter()
+        .filter_map(|service| Some((service.name.clone(), service.image.as_deref()?)))
+        .collect();
+    if config.anubis.enabled {
+        images.push(("anubis".to_string(), &config.anubis.image));
+    }
+    let crowdsec = &config.security.crowdsec;
+    if crowdsec.enabled {
+        images.push(("crowdsec".to_string(), &crowdsec.image));
+        images.push(("crowdsec".to_string(), &crowdsec.bouncer_image));
+        images.push(("crowdsec".to_string(), &crowdsec.spoa_image));
+    }
+
+    images
+        .into_iter()
+        .filter(|(_, image)| is_floating(image))
+        .map(|(subject, image)| {
+            (
+                subject,
+                format!("image {image} is not pinned to a version or digest"),
+            )
+        })
+        .collect()
+}
+
+fn missing_healthchecks(config: &Config) -> Vec<(String, String)> {
+    config
+        .services
+        .iter()
+        // health.type = "none" opts out of checks altogether
+        .filter(|service| {
+            !service.has_healthcheck()
+                && service
+                    .health
+                    .as_ref()
+                    .is_none_or(UpstreamHealthConfig::is_enabled)
+        })
+        .map(|service| {
+            (
+                service.name.clone(),
+                "container has no healthcheck; add [services.healthcheck]".to_string(),
+            )
+        })
+        .collect()
+}
+
+fn exposed_admin_apis(config: &Config) -> Vec<(String, String)> {
+    let mut findings = Vec::new();
+    // The ops route puts every dashboard behind its users
+    if config.ops.domain.is_some() {
+        return findings;
+    }
+    for proxy in &config.proxies {
+        match proxy.proxy_type {
+            ProxyType::Traefik => findings.push((
+                proxy.name.clone(),
+                "Traefik API and dashboard are served without authentication (api.insecure)"
+                    .to_string(),
+            )),
+            ProxyType::HaProxy => {
+                if let Some(stats) = proxy.enabled_stats()
+                    && stats.credentials.is_none()
+                {
+                    findings.push((
+                        proxy.name.clone(),
+                        format!(
+                            "statistics page on port {} has no credentials; set stats.credentials",
+                            stats.port
+                        ),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    findings
+}
+
+fn missing_resource_limits(config: &Config) -> Vec<(String, String)> {
+    config
+        .proxies
+        .iter()
+        .filter(|proxy| {
+            proxy
+                .resources()
+                .and_then(|resources| resources.limits.as_ref())
+                .is_none_or(|limits| limits.cpus.is_none() && limits.memory.is_none())
+        })
+        .map(|proxy| {
+            (
+                proxy.name.clone(),
+                "no CPU or memory limit; set deploy.resources.limits".to_string(),
+            )
+        })
+        .collect()
+}
+
+fn anubis_difficulty(config: &Config) -> Vec<(String, String)> {
+    if !config.anubis.enabled {
+        return Vec::new();
+    }
+    let max = config.lint.max_anubis_difficulty;
+
+    let mut findings = Vec::new();
+    for container in config.anubis.containers() {
+        if container.difficulty > max {
+            findings.push((
+                container.name,
+                format!(
+                    "difficulty {} is above {max} and slows every visitor down",
+                    container.difficulty
+                ),
+            ));
+        }
+    }
+    for proxy in &config.proxies {
+        for route in &proxy.routes {
+            if let Some(difficulty) = route.difficulty.filter(|difficulty| *difficulty > max) {
+                findings.push((
+                    format!("{} route {}", proxy.name, route.domain),
+                    format!("route difficulty {difficulty} is above {max}"),
+                ));
+            }
+        }
+    }
+    findings
+}
+
+/// Renamed keys still used under their deprecated name
+fn deprecated_keys(config: &Config) -> Vec<(String, String)> {
+    config
+        .deprecations
+        .iter()
+        .map(|deprecated| (deprecated.key.clone(), deprecated.message()))
+        .collect()
+}
+
+/// Services whose `TZ` differs from the zone every other container logs in
+///
+/// Proxies, Anubis and the bundled services follow `project.timezone`, or
+/// the image default (usually UTC) without it, so a service's own `TZ`
+/// makes its log timestamps disagree with the proxies in front of it.
+fn mixed_timezones(config: &Config) -> Vec<(String, String)> {
+    let project = config.project.timezone.as_deref();
+    config
+        .services
+        .iter()
+        .filter_map(|service| {
+            let timezone = service.environment.get("TZ")?;
+            let message = match project {
+                Some(project) if project == timezone => return None,
+                Some(project) => format!(
+                    "logs in TZ={timezone} while the other containers use project.timezone {project}; remove its TZ"
+                ),
+                None => format!(
+                    "logs in TZ={timezone} while the other containers use their image default; set project.timezone instead"
+                ),
+            };
+            Some((service.name.clone(), message))
+        })
+        .collect()
+}
+
+/// Anubis containers nothing sends requests to, or that pass them nowhere
+///
+/// A layer-1 proxy whose `default_upstream` points elsewhere bypasses
+/// Anubis, and a `target` naming no proxy behind layer 1 and no service
+/// leaves every solved challenge with nowhere to go. Addresses with a dot
+/// or an IPv6 literal are outside the stack and taken as given.
+fn anubis_topology(config: &Config) -> Vec<(String, String)> {
+    if !config.anubis.enabled {
+        return Vec::new();
+    }
+
+    let mut findings = Vec::new();
+    let edges: Vec<&str> = config
+        .proxies
+        .iter()
+        .filter(|proxy| proxy.layer.unwrap_or(1) == 1)
+        .map(|proxy| proxy.name.as_str())
+        .collect();
+    if !edges.is_empty()
+        && !config
+            .proxies
+            .iter()
+            .any(|proxy| config.sends_to_anubis(proxy))
+    {
+        findings.push((
+            "anubis".to_string(),
+            format!(
+                "no layer-1 proxy passes requests to Anubis: {} set default_upstream \
+                 elsewhere; point one at {} or leave it unset",
+                edges.join(", "),
+                config.anubis.url()
+            ),
+        ));
+    }
+
+    for container in config.anubis.containers() {
+        let host = upstream_host(&container.target);
+        let behind = config
+            .proxies
+            .iter()
+            .any(|proxy| proxy.name == host && proxy.layer.unwrap_or(1) > 1)
+            || config.services.iter().any(|service| service.name == host);
+        if !host.contains(['.', '[']) && !behind {
+            findings.push((
+                container.name,
+                format!(
+                    "target {} is neither a proxy behind layer 1 nor a service; \
+                     point it at the next layer",
+                    container.target
+                ),
+            ));
+        }
+    }
+    findings
+}
+
+/// Upstreams that point at the loopback or unspecified address
+///
+/// Inside a container `localhost` is the proxy itself, so such an upstream
+/// only ever reaches the proxy. Services on the Docker host are reachable
+/// as `host.docker.internal` or by the host's address.
+fn localhost_upstreams(config: &Config) -> Vec<(String, String)> {
+    let services = config.services.iter().flat_map(|service| {
+        std::iter::once(&service.upstream)
+            .chain(&service.candidate_upstream)
+            .chain(&service.backup_upstreams)
+            .map(|upstream| (&service.name, upstream))
+    });
+    let proxies = config.proxies.iter().flat_map(|proxy| {
+        proxy
+            .default_upstream
+            .iter()
+            .chain(proxy.routes.iter().map(|route| &route.upstream))
+            .map(|upstream| (&proxy.name, upstream))
+    });
+    let pools = config
+        .upstreams
+        .iter()
+        .flat_map(|pool| pool.servers.iter().map(|server| (&pool.name, &server.url)));
+
+    services
+        .chain(proxies)
+        .chain(pools)
+        .filter(|(_, upstream)| is_local_upstream(upstream))
+        .map(|(owner, upstream)| {
+            (
+                owner.clone(),
+                format!(
+                    "upstream {upstream} points at the container itself; \
+                     use the service name, or host.docker.internal for the host"
+                ),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
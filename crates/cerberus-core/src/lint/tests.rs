@@ -0,0 +1,345 @@
+//! # Tests for configuration linting
+//!
+//! These tests verify each rule, severity overrides from `[lint.rules]`,
+//! and which findings fail validation.
+
+use super::*;
+use crate::config::DeprecatedKey;
+use crate::test_support::parse_config;
+use pretty_assertions::assert_eq;
+
+/// Helper function to list the rule and subject of every finding
+fn violations(config: &Config) -> Vec<(&'static str, String)> {
+    lint(config)
+        .into_iter()
+        .map(|finding| (finding.rule.id(), finding.subject))
+        .collect()
+}
+
+#[test]
+fn test_floating_image_tags() {
+    for (image, floating) in [
+        ("example/web", true),
+        ("example/web:latest", true),
+        ("redis:alpine", true),
+        ("registry:5000/web", true),
+        ("example/web:1.4.2", false),
+        ("registry:5000/web:2024-05", false),
+        ("example/web@sha256:abc123", false),
+    ] {
+        assert_eq!(is_floating(image), floating, "{image}");
+    }
+}
+
+#[test]
+fn test_default_rules() {
+    let config = parse_config(
+        r#"
+[project]
+name = "lint-test"
+
+[anubis]
+enabled = true
+image = "ghcr.io/techarohq/anubis:v1.18.0"
+difficulty = 7
+
+[[proxies]]
+name = "proxy"
+type = "traefik"
+external_port = 80
+
+[proxies.deploy.resources.limits]
+memory = "256M"
+
+[[proxies]]
+name = "proxy-2"
+type = "haproxy"
+layer = 2
+
+[proxies.stats]
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+image = "example/web:latest"
+"#,
+    );
+
+    assert_eq!(
+        violations(&config),
+        vec![
+            ("floating-image-tag", "web-app".to_string()),
+            ("missing-healthcheck", "web-app".to_string()),
+            ("admin-api-enabled", "proxy".to_string()),
+            ("admin-api-enabled", "proxy-2".to_string()),
+            ("no-resource-limits", "proxy-2".to_string()),
+            ("anubis-difficulty", "anubis".to_string()),
+        ]
+    );
+
+    let findings = lint(&config);
+    assert_eq!(findings[4].severity, LintSeverity::Info);
+    assert!(findings[0].fails(true));
+    assert!(!findings[0].fails(false));
+    assert!(!findings[4].fails(true));
+}
+
+#[test]
+fn test_configured_severities() {
+    let config = parse_config(
+        r#"
+[project]
+name = "lint-test"
+
+[lint]
+max_anubis_difficulty = 8
+
+[lint.rules]
+floating-image-tag = "error"
+missing-healthcheck = "off"
+no-resource-limits = "off"
+
+[anubis]
+enabled = true
+image = "ghcr.io/techarohq/anubis:v1.18.0"
+difficulty = 7
+target = "http://web-app:3000"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+image = "example/web"
+"#,
+    );
+
+    let findings = lint(&config);
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].rule, LintRule::FloatingImageTag);
+    assert_eq!(findings[0].severity, LintSeverity::Error);
+    assert!(findings[0].fails(false));
+}
+
+#[test]
+fn test_anubis_topology() {
+    let content = r#"
+[project]
+name = "lint-test"
+
+[lint.rules]
+missing-healthcheck = "off"
+no-resource-limits = "off"
+admin-api-enabled = "off"
+
+[anubis]
+enabled = true
+image = "ghcr.io/techarohq/anubis:v1.18.0"
+
+[[proxies]]
+name = "edge"
+type = "haproxy"
+external_port = 80
+
+[[proxies]]
+name = "proxy-2"
+type = "caddy"
+layer = 2
+"#;
+    let config = parse_config(content);
+    assert_eq!(violations(&config), vec![]);
+
+    // A layer-1 upstream elsewhere and a target outside the stack
+    let config = parse_config(&content.replace(
+        "external_port = 80",
+        "external_port = 80\ndefault_upstream = \"http://proxy-2:80\"",
+    ));
+    let findings = lint(&config);
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].rule, LintRule::AnubisTopology);
+    assert!(
+        findings[0]
+            .message
+            .contains("edge set default_upstream elsewhere")
+    );
+
+    let config = parse_config(&content.replace("name = \"proxy-2\"", "name = \"cache\""));
+    assert_eq!(
+        violations(&config),
+        vec![("anubis-topology", "anubis".to_string())]
+    );
+    let config = parse_config(&content.replace(
+        "enabled = true",
+        "enabled = true\ntarget = \"http://backend.internal:80\"",
+    ));
+    assert_eq!(violations(&config), vec![]);
+}
+
+#[test]
+fn test_unknown_rule_rejected() {
+    let error = toml::from_str::<Config>(
+        r#"
+[project]
+name = "lint-test"
+
+[lint.rules]
+no-such-rule = "error"
+"#,
+    )
+    .expect_err("Unknown rules should be rejected")
+    .to_string();
+    assert!(error.contains("no-such-rule"), "{error}");
+}
+
+#[test]
+fn test_deprecated_keys() {
+    let mut config = parse_config(
+        r#"
+[project]
+name = "lint-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+upstream = "http://192.0.2.1:3000"
+"#,
+    );
+    // Loading records deprecated keys; the alias already read the value
+    assert_eq!(
+        config.proxies[0].default_upstream.as_deref(),
+        Some("http://192.0.2.1:3000")
+    );
+    config.deprecations = vec![DeprecatedKey {
+        key: "proxies[0].upstream".to_string(),
+        replacement: "proxies[0].default_upstream".to_string(),
+        since: "0.1.0",
+        removal: "0.3.0",
+        schema_version: 2,
+    }];
+
+    let findings: Vec<LintFinding> = lint(&config)
+        .into_iter()
+        .filter(|finding| finding.rule == LintRule::DeprecatedKey)
+        .collect();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].subject, "proxies[0].upstream");
+    assert_eq!(findings[0].severity, LintSeverity::Warning);
+    assert!(
+        findings[0]
+            .message
+            .contains("removed in 0.3.0; use proxies[0].default_upstream")
+    );
+}
+
+#[test]
+fn test_mixed_timezones() {
+    let config_with = |timezone: &str| {
+        parse_config(&format!(
+            r#"
+[project]
+name = "lint-test"
+{timezone}
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "api"
+domain = "api.example.com"
+upstream = "http://api:3000"
+
+[services.environment]
+TZ = "Asia/Tokyo"
+
+[[services]]
+name = "web"
+domain = "www.example.com"
+upstream = "http://web:3000"
+"#
+        ))
+    };
+    let mixed = |config: &Config| -> Vec<LintFinding> {
+        lint(config)
+            .into_iter()
+            .filter(|finding| finding.rule == LintRule::MixedTimezones)
+            .collect()
+    };
+
+    // Only api sets a zone; everything else logs in the image default
+    let findings = mixed(&config_with(""));
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].subject, "api");
+    assert_eq!(findings[0].severity, LintSeverity::Warning);
+    assert!(findings[0].message.contains("set project.timezone instead"));
+
+    let findings = mixed(&config_with(r#"timezone = "UTC""#));
+    assert_eq!(findings.len(), 1);
+    assert!(
+        findings[0]
+            .message
+            .contains("TZ=Asia/Tokyo while the other containers use project.timezone UTC")
+    );
+
+    assert!(mixed(&config_with(r#"timezone = "Asia/Tokyo""#)).is_empty());
+}
+
+#[test]
+fn test_localhost_upstreams() {
+    let content = r#"
+[project]
+name = "localhost-test"
+
+[lint.rules]
+missing-healthcheck = "off"
+no-resource-limits = "off"
+floating-image-tag = "off"
+
+[[proxies]]
+name = "edge"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+"#;
+    let config = parse_config(content);
+    assert_eq!(violations(&config), vec![]);
+
+    for upstream in [
+        "http://localhost:3000",
+        "http://127.0.0.1:3000",
+        "http://[::1]:3000",
+        "http://0.0.0.0:3000",
+        "app.localhost:3000",
+    ] {
+        let config = parse_config(&content.replace("http://web-app:3000", upstream));
+        assert_eq!(
+            violations(&config),
+            vec![("localhost-upstream", "web-app".to_string())],
+            "{upstream}"
+        );
+    }
+
+    let config = parse_config(&content.replace(
+        "external_port = 80",
+        "external_port = 80\ndefault_upstream = \"http://127.0.0.1:8080\"",
+    ));
+    let findings = lint(&config);
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].subject, "edge");
+    assert!(
+        findings[0]
+            .message
+            .contains("upstream http://127.0.0.1:8080 points at the container itself")
+    );
+}
@@ -0,0 +1,32 @@
+//! Findings of the checks run on generated files
+//!
+//! Shared by the syntax checks of [`CerberusGenerator`] and the live DNS
+//! comparison of [`DnsGenerator`], and serialized in the CLI's
+//! machine-readable reports.
+//!
+//! [`CerberusGenerator`]: crate::generators::CerberusGenerator
+//! [`DnsGenerator`]: crate::generators::DnsGenerator
+
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Outcome of one validation check
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FindingStatus {
+    /// The file passed the check
+    Ok,
+    /// The file failed the check
+    Error,
+}
+
+/// A validation check performed on a generated file
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Finding {
+    /// File that was checked
+    pub file: PathBuf,
+    /// Whether the check passed
+    pub status: FindingStatus,
+    /// What was checked, or why it failed
+    pub message: String,
+}
@@ -0,0 +1,10 @@
+//! Helpers shared by the unit tests of this crate
+
+use crate::config::Config;
+
+/// Helper function to parse a configuration from TOML
+pub(crate) fn parse_config(content: &str) -> Config {
+    let config: Config = toml::from_str(content).expect("Failed to parse config");
+    config.validate().expect("Config should be valid");
+    config
+}
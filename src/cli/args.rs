@@ -0,0 +1,320 @@
+//! # Command-line arguments
+//!
+//! The commands and options of the `cerberus` binary. Parsing, the shell
+//! completions of `cerberus completions` and the manual pages of
+//! `cerberus man` are all derived from these definitions.
+
+use super::{CleanKeep, OutputFormat};
+use crate::config::{AppTemplate, DeployTarget};
+use crate::generators::GraphStyle;
+use crate::{CerberusError, Result};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Multi-layer proxy architecture system
+#[derive(Debug, Parser)]
+#[command(name = "cerberus", version)]
+pub struct Cli {
+    /// Configuration file path
+    #[arg(short, long, value_name = "FILE", default_value = "config.toml")]
+    pub config: PathBuf,
+
+    /// Output directory for generated files (overrides [outputs])
+    #[arg(short, long, value_name = "DIR", default_value = "built")]
+    pub output: PathBuf,
+
+    /// age identity file for encrypted secrets (default: $SOPS_AGE_KEY_FILE)
+    #[arg(long, value_name = "FILE", global = true)]
+    pub age_key: Option<PathBuf>,
+
+    /// Result format: text logs, or a JSON/YAML report on stdout
+    #[arg(long, value_name = "FORMAT", default_value = "text", global = true)]
+    pub format: OutputFormat,
+
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+impl Cli {
+    /// Write the completion script of a shell
+    pub fn completions(shell: Shell, out: &mut dyn Write) {
+        clap_complete::generate(shell, &mut Self::command(), "cerberus", out);
+    }
+
+    /// Write the cerberus(1) manual page
+    ///
+    /// # Errors
+    /// Returns error if the page cannot be written
+    pub fn man_page(out: &mut dyn Write) -> Result<()> {
+        clap_mangen::Man::new(Self::command()).render(out)?;
+        Ok(())
+    }
+
+    /// Write a manual page for `cerberus` and each of its commands into `dir`
+    ///
+    /// # Errors
+    /// Returns error if the directory or a page cannot be written
+    pub fn man_pages(dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir).map_err(|e| CerberusError::io(dir, e))?;
+        clap_mangen::generate_to(Self::command(), dir).map_err(|e| CerberusError::io(dir, e))
+    }
+}
+
+/// Subcommands of `cerberus`
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Write a starter configuration for an application template
+    Init {
+        /// Application the configuration is written for
+        #[arg(long, value_name = "TEMPLATE")]
+        template: AppTemplate,
+
+        /// Public domain of the application (default: <template>.example.com)
+        #[arg(long, value_name = "DOMAIN")]
+        domain: Option<String>,
+
+        /// Address of the application (default: http://<template>:<port>)
+        #[arg(long, value_name = "URL")]
+        upstream: Option<String>,
+
+        /// Overwrite an existing configuration file
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Generate all configuration files
+    Generate {
+        /// Overwrite generated files even if they were edited by hand
+        #[arg(long)]
+        force: bool,
+
+        /// Render vault:// references with a vault-agent sidecar instead of reading Vault
+        #[arg(long)]
+        no_resolve: bool,
+
+        /// Render all files in memory without writing them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Fail if the files on disk differ from a fresh rendering; nothing is written
+        #[arg(long, conflicts_with_all = ["force", "dry_run"])]
+        frozen: bool,
+
+        /// Render this deployment target instead of project.target and [outputs]
+        #[arg(long, value_name = "TARGET")]
+        target: Option<DeployTarget>,
+    },
+
+    /// Validate configuration and generated files
+    Validate {
+        /// Fail on lint warnings as well as errors
+        #[arg(long)]
+        strict: bool,
+
+        /// Validate every example config in DIR instead of --config
+        #[arg(long, value_name = "DIR")]
+        examples_dir: Option<PathBuf>,
+    },
+
+    /// Clean output directory
+    Clean {
+        /// Only remove the files recorded in the generation manifest
+        #[arg(long)]
+        generated_only: bool,
+
+        /// Runtime data to leave in place (comma-separated)
+        #[arg(long, value_name = "DATA", value_delimiter = ',')]
+        keep: Vec<CleanKeep>,
+
+        /// Remove files Cerberus did not create without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Upgrade the configuration and its included files to the current schema version
+    Migrate,
+
+    /// Resolve the remote `extends` base again and pin it in cerberus.lock
+    Lock,
+
+    /// List or restore earlier generations
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+
+    /// Regenerate configuration files when the config changes
+    Watch {
+        /// Additional file or directory to watch
+        #[arg(long, value_name = "PATH")]
+        path: Vec<PathBuf>,
+
+        /// Run docker compose up -d after each regeneration
+        #[arg(long)]
+        up: bool,
+
+        /// Reload proxy containers whose config changed in place
+        #[arg(long)]
+        reload: bool,
+    },
+
+    /// Advance a blue/green or canary release and regenerate
+    Promote {
+        /// Set the canary traffic share instead of promoting it
+        #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+        weight: Option<u8>,
+    },
+
+    /// Regenerate and apply only the changes to the running stack
+    Upgrade {
+        /// Show the upgrade plan without writing or applying it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Overwrite generated files even if they were edited by hand
+        #[arg(long)]
+        force: bool,
+
+        /// Render vault:// references with a vault-agent sidecar instead of reading Vault
+        #[arg(long)]
+        no_resolve: bool,
+
+        /// Time each restarted service has to become healthy
+        #[arg(long, value_name = "SECONDS", default_value_t = 120)]
+        health_timeout: u64,
+    },
+
+    /// Copy the generated configuration into running proxies and reload them
+    Reload {
+        /// Reload only this proxy instead of every proxy
+        #[arg(value_name = "PROXY")]
+        proxy: Option<String>,
+    },
+
+    /// Push the generated files to remote storage as a versioned bundle
+    Publish {
+        /// s3://bucket/prefix, ssh://[user@]host[:port]/path or git+<url>[#branch]
+        #[arg(value_name = "TARGET")]
+        target: String,
+
+        /// Show the version and files without publishing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Show state, health, ports, and usage of the running stack
+    Status,
+
+    /// Boot the generated stack, send requests through layer 1, then tear it down
+    Test {
+        /// Seconds the stack may take to start, and each probe to pass
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            default_value_t = 120,
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        timeout: u64,
+
+        /// Leave the stack running after the probes
+        #[arg(long)]
+        keep_running: bool,
+    },
+
+    /// Live dashboard of containers, request rates, events, and certificates
+    Top {
+        /// Seconds between samples
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            default_value_t = 2,
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        interval: u64,
+    },
+
+    /// DNS records required by the service domains
+    Dns {
+        #[command(subcommand)]
+        command: DnsCommand,
+    },
+
+    /// Render the proxy layers, services, networks, and dependencies as a graph
+    Graph {
+        /// Graph syntax written to stdout
+        #[arg(long, value_name = "STYLE", default_value = "mermaid")]
+        style: GraphStyle,
+    },
+
+    /// Inspect the loaded configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Print the JSON Schema of the configuration file to stdout
+    Schema,
+
+    /// Send an alert to the channels routed for an event
+    Notify {
+        /// Event the alert is routed by
+        #[arg(value_name = "EVENT", value_parser = ["scaling", "cert_expiry", "deploy"])]
+        event: String,
+
+        /// Alert summary
+        #[arg(value_name = "TITLE")]
+        title: String,
+
+        /// Alert body
+        #[arg(long, value_name = "TEXT", default_value = "")]
+        message: String,
+    },
+
+    /// Print the completion script of a shell to stdout
+    Completions {
+        /// Shell the script is written for
+        #[arg(value_name = "SHELL")]
+        shell: Shell,
+    },
+
+    /// Print the cerberus(1) manual page to stdout
+    Man {
+        /// Write a page for every command into DIR instead
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+}
+
+/// Subcommands of `cerberus history`
+#[derive(Debug, Subcommand)]
+pub enum HistoryCommand {
+    /// List the recorded generations
+    List,
+
+    /// Write the files of an earlier generation back
+    Restore {
+        /// Generation to restore, as shown by history list
+        #[arg(value_name = "ID")]
+        id: String,
+    },
+}
+
+/// Subcommands of `cerberus dns`
+#[derive(Debug, Subcommand)]
+pub enum DnsCommand {
+    /// Verify that live DNS matches the record plan
+    Check,
+}
+
+/// Subcommands of `cerberus config`
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the configuration with its includes and base merged
+    Show {
+        /// Fill in every default and mark where each value was set
+        #[arg(long)]
+        effective: bool,
+    },
+}
@@ -1,5 +1,425 @@
 //! # CLI module for Cerberus
 //!
-//! Command-line interface utilities and handlers.
+//! The command-line arguments ([`Cli`]), and machine-readable reports for
+//! the CLI commands (`--format json|yaml`): the manifest of files written by
+//! `generate`, the findings and lint results of `validate`, the live DNS
+//! comparison of `dns check`, and the paths removed by `clean`, along with
+//! the plan deciding which ones.
 
-// TODO: Implement CLI utilities
+mod args;
+
+use crate::{
+    CerberusError, Result,
+    checksum::sha256_hex,
+    generators::{MANIFEST_FILE, Manifest, dns::DnsCheck, paths},
+    lint::LintFinding,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub use crate::report::{Finding, FindingStatus};
+pub use args::{Cli, Commands, ConfigCommand, DnsCommand, HistoryCommand};
+
+/// Output format for command results
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable log output
+    #[default]
+    Text,
+    /// JSON report on stdout
+    Json,
+    /// YAML report on stdout
+    Yaml,
+}
+
+impl OutputFormat {
+    /// Render a report, or `None` for text output (which only logs)
+    ///
+    /// # Errors
+    /// Returns error if the report cannot be serialized
+    pub fn render<T: Serialize>(&self, report: &T) -> Result<Option<String>> {
+        match self {
+            OutputFormat::Text => Ok(None),
+            OutputFormat::Json => Ok(Some(serde_json::to_string_pretty(report)?)),
+            OutputFormat::Yaml => serde_yaml::to_string(report).map(Some).map_err(|e| {
+                CerberusError::config(format!("Failed to serialize report as YAML: {e}"))
+            }),
+        }
+    }
+}
+
+/// A file written by `generate`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ManifestEntry {
+    /// Path of the file, including the output directory
+    pub path: PathBuf,
+    /// SHA-256 of the content, hex encoded
+    pub sha256: String,
+    /// Size in bytes
+    pub bytes: usize,
+}
+
+/// Result of `generate`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct GenerateReport {
+    /// Every written file, sorted by path
+    pub files: Vec<ManifestEntry>,
+}
+
+impl GenerateReport {
+    /// Build the manifest of every file under the given output directories
+    ///
+    /// # Errors
+    /// Returns error if a generated file cannot be read
+    pub fn from_dirs(dirs: &[PathBuf]) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        for dir in dirs {
+            for (relative, content) in crate::generators::snapshot(dir)? {
+                files.insert(dir.join(relative), content);
+            }
+        }
+        Ok(Self::from_files(&files))
+    }
+
+    /// Build the manifest of rendered files, e.g. from a dry run
+    pub fn from_files(files: &BTreeMap<PathBuf, Vec<u8>>) -> Self {
+        Self {
+            files: files
+                .iter()
+                .map(|(path, content)| ManifestEntry {
+                    path: path.clone(),
+                    sha256: sha256_hex(content),
+                    bytes: content.len(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Result of `generate --frozen`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DriftReport {
+    /// Whether the files on disk are byte-identical to a fresh rendering
+    pub frozen: bool,
+    /// Files whose content differs from the rendering
+    pub changed: Vec<PathBuf>,
+    /// Rendered files that are not on disk
+    pub missing: Vec<PathBuf>,
+    /// Files the last generation wrote that are no longer rendered
+    pub stale: Vec<PathBuf>,
+}
+
+impl DriftReport {
+    /// Compare rendered files with the output directories they belong in
+    ///
+    /// Paths include their output directory, as rendered for a dry run.
+    /// Files on disk that no generation wrote, such as logs and resolved
+    /// secrets, are not compared.
+    ///
+    /// # Errors
+    /// Returns error if a file or manifest on disk cannot be read
+    pub fn new(rendered: &BTreeMap<PathBuf, Vec<u8>>, dirs: &[PathBuf]) -> Result<Self> {
+        let mut on_disk = BTreeMap::new();
+        let mut tracked = Vec::new();
+        for dir in dirs {
+            for (relative, content) in crate::generators::snapshot(dir)? {
+                on_disk.insert(dir.join(relative), content);
+            }
+            if let Some(manifest) = Manifest::load(dir)? {
+                tracked.extend(manifest.files.into_keys().map(|path| dir.join(path)));
+            }
+        }
+
+        let mut report = Self {
+            frozen: true,
+            changed: Vec::new(),
+            missing: Vec::new(),
+            stale: Vec::new(),
+        };
+        for (path, content) in rendered {
+            match on_disk.get(path) {
+                Some(existing) if existing == content => {}
+                Some(_) => report.changed.push(path.clone()),
+                None => report.missing.push(path.clone()),
+            }
+        }
+        report.stale = tracked
+            .into_iter()
+            .filter(|path| !rendered.contains_key(path))
+            .collect();
+        report.stale.sort();
+        report.frozen =
+            report.changed.is_empty() && report.missing.is_empty() && report.stale.is_empty();
+        Ok(report)
+    }
+}
+
+/// Result of `validate`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ValidateReport {
+    /// Whether every check passed
+    pub valid: bool,
+    /// Every check performed
+    pub findings: Vec<Finding>,
+    /// Lint rule violations in the configuration
+    pub lints: Vec<LintFinding>,
+}
+
+impl ValidateReport {
+    /// Summarize a list of findings
+    pub fn new(findings: Vec<Finding>) -> Self {
+        Self {
+            valid: findings
+                .iter()
+                .all(|finding| finding.status == FindingStatus::Ok),
+            findings,
+            lints: Vec::new(),
+        }
+    }
+
+    /// Add lint findings; with `strict`, warnings fail validation too
+    pub fn with_lints(mut self, lints: Vec<LintFinding>, strict: bool) -> Self {
+        self.valid &= !lints.iter().any(|lint| lint.fails(strict));
+        self.lints = lints;
+        self
+    }
+}
+
+/// Result of `dns check`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DnsReport {
+    /// Whether every domain matches the plan
+    pub valid: bool,
+    /// Every domain checked
+    pub domains: Vec<DnsCheck>,
+}
+
+impl DnsReport {
+    /// Summarize a list of domain checks
+    pub fn new(domains: Vec<DnsCheck>) -> Self {
+        Self {
+            valid: domains
+                .iter()
+                .all(|check| check.status == FindingStatus::Ok),
+            domains,
+        }
+    }
+}
+
+/// Runtime data under the output directory that `clean --keep` leaves in place
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CleanKeep {
+    /// Proxy, Anubis and WAF logs under `built/logs`
+    Logs,
+    /// Certificates and keys: a `certs` directory and `.pem`, `.crt` or `.key` files
+    Certs,
+}
+
+impl CleanKeep {
+    /// Whether a path relative to the output directory holds this data
+    pub fn matches(self, path: &Path) -> bool {
+        match self {
+            CleanKeep::Logs => path.starts_with(crate::generators::paths::LOGS_DIR),
+            CleanKeep::Certs => {
+                path.components()
+                    .any(|component| component.as_os_str() == "certs")
+                    || path.extension().is_some_and(|extension| {
+                        ["pem", "crt", "key"].contains(&&*extension.to_string_lossy())
+                    })
+            }
+        }
+    }
+}
+
+/// What `clean` removes
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CleanOptions {
+    /// Only remove the files recorded in the output directory's manifest
+    pub generated_only: bool,
+    /// Runtime data to leave in place
+    pub keep: Vec<CleanKeep>,
+}
+
+/// Files `clean` would remove from an output directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct CleanPlan {
+    /// The output directory
+    pub dir: PathBuf,
+    /// Files to remove, relative to the output directory
+    pub remove: Vec<PathBuf>,
+    /// Files to remove that Cerberus did not write
+    pub unmanaged: Vec<PathBuf>,
+    /// Files left in place
+    pub kept: Vec<PathBuf>,
+}
+
+impl CleanPlan {
+    /// Sort the files of an output directory into removed and kept ones
+    ///
+    /// Files recorded in the manifest, the manifest itself and resolved
+    /// secrets were written by Cerberus; anything else is unmanaged.
+    ///
+    /// # Errors
+    /// Returns error if the directory cannot be read, or if `generated_only`
+    /// is set and the directory has no manifest
+    pub fn new(dir: &Path, options: &CleanOptions) -> Result<Self> {
+        let mut plan = Self {
+            dir: dir.to_path_buf(),
+            remove: Vec::new(),
+            unmanaged: Vec::new(),
+            kept: Vec::new(),
+        };
+        if !dir.exists() {
+            return Ok(plan);
+        }
+
+        let manifest = Manifest::load(dir)?;
+        if options.generated_only && manifest.is_none() {
+            return Err(CerberusError::validation(format!(
+                "{} has no {MANIFEST_FILE}, so the generated files cannot be told apart; \
+                 run clean without --generated-only",
+                dir.display()
+            )));
+        }
+
+        for path in list_files(dir)? {
+            let generated = path == Path::new(MANIFEST_FILE)
+                || path.starts_with(paths::SECRETS_DIR)
+                || manifest
+                    .as_ref()
+                    .is_some_and(|manifest| manifest.files.contains_key(&path));
+            if options.keep.iter().any(|keep| keep.matches(&path))
+                || (options.generated_only && !generated)
+            {
+                plan.kept.push(path);
+            } else {
+                if !generated {
+                    plan.unmanaged.push(path.clone());
+                }
+                plan.remove.push(path);
+            }
+        }
+        Ok(plan)
+    }
+}
+
+/// Result of `clean`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CleanReport {
+    /// Files removed, followed by the empty directories removed (the
+    /// output directory itself last)
+    pub removed: Vec<PathBuf>,
+    /// Files left in place
+    pub kept: Vec<PathBuf>,
+}
+
+impl CleanReport {
+    /// Remove the planned files and report what was deleted
+    ///
+    /// Empty directories are removed too, and the whole output directory
+    /// once nothing is kept in it.
+    ///
+    /// # Errors
+    /// Returns error if a file or directory cannot be removed
+    pub async fn clean(plan: &CleanPlan) -> Result<Self> {
+        let dir = &plan.dir;
+        let mut removed: Vec<PathBuf> = plan.remove.iter().map(|path| dir.join(path)).collect();
+        let kept = plan.kept.iter().map(|path| dir.join(path)).collect();
+
+        if plan.kept.is_empty() {
+            if dir.exists() {
+                tokio::fs::remove_dir_all(dir)
+                    .await
+                    .map_err(|e| CerberusError::io(dir, e))?;
+                removed.push(dir.clone());
+            }
+            return Ok(Self { removed, kept });
+        }
+
+        for path in &removed {
+            tokio::fs::remove_file(path)
+                .await
+                .map_err(|e| CerberusError::io(path, e))?;
+        }
+        prune_empty_dirs(dir, &mut removed)?;
+
+        Ok(Self { removed, kept })
+    }
+}
+
+/// Ask a yes/no question on the terminal; anything but `y` or `yes` declines
+///
+/// Without a terminal on stdin nobody can answer, so the question is declined.
+///
+/// # Errors
+/// Returns error if stdin or stderr cannot be used
+pub fn confirm(question: &str) -> Result<bool> {
+    use std::io::{IsTerminal, Write};
+
+    if !std::io::stdin().is_terminal() {
+        return Ok(false);
+    }
+    let mut stderr = std::io::stderr();
+    write!(stderr, "{question} [y/N] ")
+        .and_then(|()| stderr.flush())
+        .map_err(|e| CerberusError::io("<stderr>", e))?;
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| CerberusError::io("<stdin>", e))?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// Remove the empty directories under `dir`, deepest first
+///
+/// Symbolic links are never followed, so directories they point to are
+/// left alone.
+fn prune_empty_dirs(dir: &Path, removed: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir).map_err(|e| CerberusError::io(dir, e))? {
+        let entry = entry.map_err(|e| CerberusError::io(dir, e))?;
+        let path = entry.path();
+        let file_type = entry.file_type().map_err(|e| CerberusError::io(&path, e))?;
+        if !file_type.is_dir() {
+            continue;
+        }
+        prune_empty_dirs(&path, removed)?;
+        let mut entries = std::fs::read_dir(&path).map_err(|e| CerberusError::io(&path, e))?;
+        if entries.next().is_none() {
+            std::fs::remove_dir(&path).map_err(|e| CerberusError::io(&path, e))?;
+            removed.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Every file under `dir`, relative to it and sorted
+///
+/// A symbolic link is listed as one file, even when it points to a
+/// directory, so removing it only unlinks it.
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    fn walk(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in std::fs::read_dir(dir).map_err(|e| CerberusError::io(dir, e))? {
+            let entry = entry.map_err(|e| CerberusError::io(dir, e))?;
+            let path = entry.path();
+            let file_type = entry.file_type().map_err(|e| CerberusError::io(&path, e))?;
+            if file_type.is_dir() {
+                walk(root, &path, files)?;
+            } else {
+                files.push(path.strip_prefix(root).unwrap_or(&path).to_path_buf());
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    walk(dir, dir, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,411 @@
+//! # Tests for CLI reports
+//!
+//! These tests verify the command-line definitions and the machine-readable
+//! reports emitted with `--format json|yaml`.
+
+use super::*;
+use crate::config::{LintRule, LintSeverity};
+use clap::{CommandFactory, Parser};
+use clap_complete::Shell;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_generate_report_hashes_written_files() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let nested = temp_dir.path().join("anubis");
+    fs::create_dir_all(&nested).expect("Failed to create dir");
+    fs::write(temp_dir.path().join("docker-compose.yaml"), "hello").expect("Failed to write");
+    fs::write(nested.join("botPolicy.json"), "{}").expect("Failed to write");
+
+    let report =
+        GenerateReport::from_dirs(&[temp_dir.path().to_path_buf()]).expect("Manifest should build");
+
+    assert_eq!(report.files.len(), 2);
+    assert_eq!(report.files[0].path, nested.join("botPolicy.json"));
+    assert_eq!(
+        report.files[1],
+        ManifestEntry {
+            path: temp_dir.path().join("docker-compose.yaml"),
+            sha256: "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".to_string(),
+            bytes: 5,
+        }
+    );
+}
+
+#[test]
+fn test_output_format_render() {
+    let report = ValidateReport::new(vec![Finding {
+        file: PathBuf::from("built/docker-compose.yaml"),
+        status: FindingStatus::Ok,
+        message: "Valid YAML".to_string(),
+    }]);
+
+    assert_eq!(OutputFormat::Text.render(&report).unwrap(), None);
+
+    let json = OutputFormat::Json.render(&report).unwrap().unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&json).expect("Should be valid JSON");
+    assert_eq!(parsed["valid"], true);
+    assert_eq!(parsed["findings"][0]["status"], "ok");
+    assert_eq!(parsed["findings"][0]["file"], "built/docker-compose.yaml");
+
+    let yaml = OutputFormat::Yaml.render(&report).unwrap().unwrap();
+    let parsed: serde_yaml::Value = serde_yaml::from_str(&yaml).expect("Should be valid YAML");
+    assert_eq!(parsed["valid"], serde_yaml::Value::Bool(true));
+}
+
+#[test]
+fn test_validate_report_invalid_on_error_finding() {
+    let report = ValidateReport::new(vec![
+        Finding {
+            file: PathBuf::from("built/docker-compose.yaml"),
+            status: FindingStatus::Ok,
+            message: "Valid YAML".to_string(),
+        },
+        Finding {
+            file: PathBuf::from("built/anubis/botPolicy.json"),
+            status: FindingStatus::Error,
+            message: "Invalid Anubis bot policy JSON".to_string(),
+        },
+    ]);
+
+    assert!(!report.valid);
+    assert!(ValidateReport::new(Vec::new()).valid);
+}
+
+#[tokio::test]
+async fn test_clean_report_lists_removed_paths() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let output_dir = temp_dir.path().join("built");
+    fs::create_dir_all(output_dir.join("anubis")).expect("Failed to create dir");
+    fs::write(output_dir.join("docker-compose.yaml"), "a").expect("Failed to write");
+    fs::write(output_dir.join("anubis/botPolicy.json"), "b").expect("Failed to write");
+
+    let plan = CleanPlan::new(&output_dir, &CleanOptions::default()).expect("Plan should build");
+    let report = CleanReport::clean(&plan)
+        .await
+        .expect("Clean should succeed");
+
+    assert_eq!(
+        report.removed,
+        vec![
+            output_dir.join("anubis/botPolicy.json"),
+            output_dir.join("docker-compose.yaml"),
+            output_dir.clone(),
+        ]
+    );
+    assert!(!output_dir.exists());
+
+    let plan = CleanPlan::new(&output_dir, &CleanOptions::default()).expect("Plan should build");
+    let report = CleanReport::clean(&plan)
+        .await
+        .expect("Cleaning a missing directory should succeed");
+    assert!(report.removed.is_empty());
+}
+
+#[tokio::test]
+async fn test_clean_keeps_runtime_data() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let output_dir = temp_dir.path().join("built");
+    for dir in ["anubis", "built/logs", "certs", "notes"] {
+        fs::create_dir_all(output_dir.join(dir)).expect("Failed to create dir");
+    }
+    let files = BTreeMap::from([
+        (PathBuf::from("docker-compose.yaml"), b"a".to_vec()),
+        (PathBuf::from("anubis/botPolicy.json"), b"b".to_vec()),
+    ]);
+    for (path, content) in &files {
+        fs::write(output_dir.join(path), content).expect("Failed to write");
+    }
+    Manifest::from_files(&files)
+        .write(&output_dir)
+        .expect("Failed to write manifest");
+    fs::write(output_dir.join("built/logs/proxy.log"), "log").expect("Failed to write");
+    fs::write(output_dir.join("certs/example.com.crt"), "cert").expect("Failed to write");
+    fs::write(output_dir.join("notes/todo.txt"), "mine").expect("Failed to write");
+
+    let plan = CleanPlan::new(
+        &output_dir,
+        &CleanOptions {
+            generated_only: false,
+            keep: vec![CleanKeep::Logs, CleanKeep::Certs],
+        },
+    )
+    .expect("Plan should build");
+    assert_eq!(plan.unmanaged, vec![PathBuf::from("notes/todo.txt")]);
+    assert_eq!(
+        plan.kept,
+        vec![
+            PathBuf::from("built/logs/proxy.log"),
+            PathBuf::from("certs/example.com.crt"),
+        ]
+    );
+
+    let plan = CleanPlan::new(
+        &output_dir,
+        &CleanOptions {
+            generated_only: true,
+            keep: vec![CleanKeep::Logs],
+        },
+    )
+    .expect("Plan should build");
+    assert!(plan.unmanaged.is_empty());
+    let report = CleanReport::clean(&plan)
+        .await
+        .expect("Clean should succeed");
+    assert_eq!(
+        report.removed,
+        vec![
+            output_dir.join(MANIFEST_FILE),
+            output_dir.join("anubis/botPolicy.json"),
+            output_dir.join("docker-compose.yaml"),
+            output_dir.join("anubis"),
+        ]
+    );
+    assert!(output_dir.join("built/logs/proxy.log").exists());
+    assert!(output_dir.join("certs/example.com.crt").exists());
+    assert!(output_dir.join("notes/todo.txt").exists());
+
+    // Without a manifest the generated files cannot be told apart
+    let error = CleanPlan::new(
+        &output_dir,
+        &CleanOptions {
+            generated_only: true,
+            keep: Vec::new(),
+        },
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("has no .cerberus-manifest.json"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_clean_unlinks_symlinked_directories() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let output_dir = temp_dir.path().join("built");
+    let app_data = temp_dir.path().join("appdata");
+    fs::create_dir_all(output_dir.join("built/logs")).expect("Failed to create dir");
+    fs::create_dir_all(app_data.join("uploads")).expect("Failed to create dir");
+    fs::write(app_data.join("uploads/photo.jpg"), "photo").expect("Failed to write");
+    fs::write(output_dir.join("docker-compose.yaml"), "a").expect("Failed to write");
+    fs::write(output_dir.join("built/logs/proxy.log"), "log").expect("Failed to write");
+    std::os::unix::fs::symlink(&app_data, output_dir.join("data")).expect("Failed to link");
+
+    let plan = CleanPlan::new(
+        &output_dir,
+        &CleanOptions {
+            generated_only: false,
+            keep: vec![CleanKeep::Logs],
+        },
+    )
+    .expect("Plan should build");
+    assert_eq!(
+        plan.remove,
+        vec![PathBuf::from("data"), PathBuf::from("docker-compose.yaml")]
+    );
+    let report = CleanReport::clean(&plan)
+        .await
+        .expect("Clean should succeed");
+
+    // Only the link is removed, not the directory it points to
+    assert!(report.removed.contains(&output_dir.join("data")));
+    assert!(fs::symlink_metadata(output_dir.join("data")).is_err());
+    assert!(app_data.join("uploads/photo.jpg").exists());
+    assert!(output_dir.join("built/logs/proxy.log").exists());
+}
+
+#[test]
+fn test_generate_report_from_rendered_files() {
+    let files = BTreeMap::from([
+        (
+            PathBuf::from("built/docker-compose.yaml"),
+            b"hello".to_vec(),
+        ),
+        (PathBuf::from("built/ROUTES.md"), Vec::new()),
+    ]);
+
+    let report = GenerateReport::from_files(&files);
+
+    assert_eq!(report.files.len(), 2);
+    assert_eq!(report.files[0].path, PathBuf::from("built/ROUTES.md"));
+    assert_eq!(report.files[0].bytes, 0);
+    assert_eq!(
+        report.files[1].sha256,
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+}
+
+#[test]
+fn test_validate_report_lints() {
+    let lints = vec![LintFinding {
+        rule: LintRule::MissingHealthcheck,
+        severity: LintSeverity::Warning,
+        subject: "web-app".to_string(),
+        message: "container has no healthcheck".to_string(),
+    }];
+
+    let report = ValidateReport::new(Vec::new()).with_lints(lints.clone(), false);
+    assert!(report.valid, "Warnings pass without --strict");
+
+    let json = OutputFormat::Json.render(&report).unwrap().unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&json).expect("Should be valid JSON");
+    assert_eq!(parsed["lints"][0]["rule"], "missing-healthcheck");
+    assert_eq!(parsed["lints"][0]["severity"], "warning");
+
+    assert!(
+        !ValidateReport::new(Vec::new())
+            .with_lints(lints, true)
+            .valid
+    );
+}
+
+/// Helper function to load a configuration whose maps have many keys
+fn map_heavy_config() -> crate::config::Config {
+    let config: crate::config::Config = toml::from_str(
+        r#"
+[project]
+name = "frozen-test"
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+default_upstream = "http://web-app:3000"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+environment = { RUST_LOG = "info", DATABASE_URL = "postgres://db/app", PORT = "3000", TZ = "UTC", LANG = "C.UTF-8", REDIS_URL = "redis://cache" }
+labels = { "com.example.team" = "web", "com.example.tier" = "frontend", "com.example.owner" = "ops" }
+
+[networks.backend]
+driver = "bridge"
+
+[networks.frontend]
+driver = "bridge"
+
+[volumes.data]
+labels = { "com.example.backup" = "daily", "com.example.owner" = "ops", "com.example.tier" = "db", "com.example.team" = "data" }
+
+[volumes.cache]
+"#,
+    )
+    .expect("Failed to parse config");
+    config.validate().expect("Config should be valid");
+    config
+}
+
+#[tokio::test]
+async fn test_drift_report_detects_changed_missing_and_stale_files() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let output = temp_dir.path().join("built");
+    crate::Cerberus::from_config(map_heavy_config(), &output)
+        .generate_all()
+        .await
+        .expect("Generation should succeed");
+
+    // A separately loaded configuration renders the same bytes
+    let rendered = crate::Cerberus::from_config(map_heavy_config(), &output)
+        .generate_to_memory()
+        .expect("Rendering should succeed");
+    let dirs = [output.clone()];
+    let report = DriftReport::new(&rendered, &dirs).expect("Should compare");
+    assert!(report.frozen, "{report:?}");
+
+    // Runtime data is not part of the comparison
+    fs::create_dir_all(output.join("logs")).expect("Failed to create dir");
+    fs::write(output.join("logs/access.log"), "GET /\n").expect("Failed to write");
+    let compose = output.join("docker-compose.yaml");
+    fs::write(&compose, "services: {}\n").expect("Failed to write");
+    let proxy_config = rendered
+        .keys()
+        .find(|path| path.starts_with(output.join("proxy-configs")))
+        .expect("A proxy config should be rendered")
+        .clone();
+    fs::remove_file(&proxy_config).expect("Failed to remove");
+    let mut manifest = Manifest::load(&output)
+        .expect("Manifest should load")
+        .expect("Manifest should exist");
+    manifest
+        .files
+        .insert(PathBuf::from("old.conf"), "0".repeat(64));
+    manifest.write(&output).expect("Failed to write manifest");
+
+    let report = DriftReport::new(&rendered, &dirs).expect("Should compare");
+    assert!(!report.frozen);
+    assert_eq!(report.changed, vec![compose]);
+    assert_eq!(report.missing, vec![proxy_config]);
+    assert_eq!(report.stale, vec![output.join("old.conf")]);
+}
+
+#[test]
+fn test_cli_definitions() {
+    Cli::command().debug_assert();
+
+    let cli = Cli::try_parse_from([
+        "cerberus",
+        "-o",
+        "out",
+        "clean",
+        "--keep",
+        "logs,certs",
+        "--format",
+        "json",
+    ])
+    .expect("Arguments should parse");
+    assert_eq!(cli.output, PathBuf::from("out"));
+    assert_eq!(cli.format, OutputFormat::Json);
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Clean { keep, yes: false, .. }) if keep == [CleanKeep::Logs, CleanKeep::Certs]
+    ));
+
+    let cli = Cli::try_parse_from(["cerberus", "history", "restore", "41"])
+        .expect("Arguments should parse");
+    assert!(matches!(
+        cli.command,
+        Some(Commands::History { command: HistoryCommand::Restore { id } }) if id == "41"
+    ));
+
+    for args in [
+        &["cerberus", "generate", "--frozen", "--dry-run"][..],
+        &["cerberus", "promote", "--weight", "101"],
+        &["cerberus", "test", "--timeout", "0"],
+        &["cerberus", "notify", "outage", "Down"],
+        &["cerberus", "completions", "tcsh"],
+    ] {
+        assert!(Cli::try_parse_from(args).is_err(), "{args:?}");
+    }
+}
+
+#[test]
+fn test_completions_and_man_pages() {
+    let mut script = Vec::new();
+    Cli::completions(Shell::Zsh, &mut script);
+    let script = String::from_utf8(script).expect("Script should be UTF-8");
+    assert!(script.starts_with("#compdef cerberus"));
+    assert!(script.contains("'--examples-dir=[Validate every example config in DIR"));
+    assert!(
+        script.contains(
+            ":shell -- Shell the script is written for:(bash elvish fish powershell zsh)"
+        )
+    );
+
+    let mut page = Vec::new();
+    Cli::man_page(&mut page).expect("Page should render");
+    let page = String::from_utf8(page).expect("Page should be UTF-8");
+    assert!(page.contains(".TH cerberus 1"));
+    assert!(page.contains("cerberus\\-completions(1)"));
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let dir = temp_dir.path().join("man1");
+    Cli::man_pages(&dir).expect("Pages should be written");
+    for page in [
+        "cerberus.1",
+        "cerberus-generate.1",
+        "cerberus-history-restore.1",
+    ] {
+        assert!(dir.join(page).is_file(), "{page}");
+    }
+}
@@ -0,0 +1,125 @@
+//! # Release promotion for Cerberus
+//!
+//! Advances a blue/green or canary release by editing `[deployment.strategy]`
+//! in the configuration file. Formatting and comments in the file are kept;
+//! only the values that change are rewritten. The edited configuration is
+//! validated before it is written back.
+
+use crate::{
+    CerberusError, Result,
+    config::{Config, DeploymentColor, StrategyType},
+};
+use std::path::Path;
+use toml_edit::{DocumentMut, value};
+
+/// What a promotion changed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Promotion {
+    /// Blue/green traffic switched to the given pool
+    Switched(DeploymentColor),
+    /// Canary share set to the given percentage
+    Weighted(u8),
+    /// Candidates replaced the current upstreams of these services
+    Promoted(Vec<String>),
+}
+
+/// Promote the release described in a configuration file
+///
+/// For `blue_green` the active pool is flipped. For `canary`, `weight` sets
+/// the share of traffic sent to the candidates; without it, each
+/// `candidate_upstream` becomes the service's `upstream`.
+///
+/// # Errors
+/// Returns error if the file cannot be read or written, has no deployment
+/// strategy, or the promoted configuration is invalid
+pub fn promote(config_path: &Path, weight: Option<u8>) -> Result<Promotion> {
+    let content =
+        std::fs::read_to_string(config_path).map_err(|e| CerberusError::io(config_path, e))?;
+
+    let (updated, promotion) = promote_content(&content, weight)?;
+    toml::from_str::<Config>(&updated)
+        .map_err(|e| CerberusError::toml_parse(config_path, e))?
+        .validate()?;
+
+    std::fs::write(config_path, updated).map_err(|e| CerberusError::io(config_path, e))?;
+    Ok(promotion)
+}
+
+/// Apply a promotion to configuration file content
+///
+/// # Errors
+/// Returns error if the content cannot be parsed or has no deployment strategy
+pub fn promote_content(content: &str, weight: Option<u8>) -> Result<(String, Promotion)> {
+    let config: Config = toml::from_str(content)
+        .map_err(|e| CerberusError::config(format!("Failed to parse configuration: {e}")))?;
+    let strategy = config.deployment.strategy.ok_or_else(|| {
+        CerberusError::validation("Nothing to promote: [deployment.strategy] is not configured")
+    })?;
+
+    let mut document: DocumentMut = content
+        .parse()
+        .map_err(|e| CerberusError::config(format!("Failed to parse configuration: {e}")))?;
+
+    let promotion = match (strategy.strategy_type, weight) {
+        (StrategyType::BlueGreen, Some(_)) => {
+            return Err(CerberusError::validation(
+                "--weight only applies to canary deployments",
+            ));
+        }
+        (StrategyType::BlueGreen, None) => {
+            let active = match strategy.active {
+                DeploymentColor::Blue => DeploymentColor::Green,
+                DeploymentColor::Green => DeploymentColor::Blue,
+            };
+            let name = match active {
+                DeploymentColor::Blue => "blue",
+                DeploymentColor::Green => "green",
+            };
+            document["deployment"]["strategy"]["active"] = value(name);
+            Promotion::Switched(active)
+        }
+        (StrategyType::Canary, Some(weight)) => {
+            if weight > 100 {
+                return Err(CerberusError::validation(format!(
+                    "deployment.strategy weight must be between 0 and 100, got {weight}"
+                )));
+            }
+            document["deployment"]["strategy"]["weight"] = value(i64::from(weight));
+            Promotion::Weighted(weight)
+        }
+        (StrategyType::Canary, None) => Promotion::Promoted(promote_candidates(&mut document)?),
+    };
+
+    Ok((document.to_string(), promotion))
+}
+
+/// Replace each service's `upstream` with its `candidate_upstream`
+fn promote_candidates(document: &mut DocumentMut) -> Result<Vec<String>> {
+    let mut promoted = Vec::new();
+
+    if let Some(services) = document
+        .get_mut("services")
+        .and_then(|item| item.as_array_of_tables_mut())
+    {
+        for service in services.iter_mut() {
+            let Some(candidate) = service.remove("candidate_upstream") else {
+                continue;
+            };
+            service["upstream"] = candidate;
+            if let Some(name) = service.get("name").and_then(|name| name.as_str()) {
+                promoted.push(name.to_string());
+            }
+        }
+    }
+
+    if promoted.is_empty() {
+        return Err(CerberusError::validation(
+            "Nothing to promote: no service has a candidate_upstream",
+        ));
+    }
+
+    Ok(promoted)
+}
+
+#[cfg(test)]
+mod tests;
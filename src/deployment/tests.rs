@@ -0,0 +1,87 @@
+//! # Tests for release promotion
+//!
+//! These tests verify that promotions rewrite only the deployment values
+//! and keep the rest of the configuration file intact.
+
+use super::*;
+use std::fs;
+use tempfile::TempDir;
+
+/// Helper function to build a config with a deployment strategy
+fn create_config(strategy: &str) -> String {
+    format!(
+        r#"# Release config
+[project]
+name = "deploy-test"
+
+[deployment.strategy]
+{strategy}
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-blue:3000" # current release
+candidate_upstream = "http://web-green:3000"
+"#
+    )
+}
+
+#[test]
+fn test_promote_blue_green_flips_active() {
+    let content = create_config(r#"type = "blue_green""#);
+
+    let (updated, promotion) = promote_content(&content, None).unwrap();
+    assert_eq!(promotion, Promotion::Switched(DeploymentColor::Green));
+    assert!(updated.contains(r#"active = "green""#));
+    assert!(updated.contains("# current release"));
+
+    let (updated, promotion) = promote_content(&updated, None).unwrap();
+    assert_eq!(promotion, Promotion::Switched(DeploymentColor::Blue));
+    assert!(updated.contains(r#"active = "blue""#));
+
+    assert!(promote_content(&content, Some(50)).is_err());
+}
+
+#[test]
+fn test_promote_canary_weight() {
+    let content = create_config("type = \"canary\"\nweight = 10");
+
+    let (updated, promotion) = promote_content(&content, Some(50)).unwrap();
+    assert_eq!(promotion, Promotion::Weighted(50));
+    assert!(updated.contains("weight = 50"));
+    assert!(updated.contains("candidate_upstream"));
+}
+
+#[test]
+fn test_promote_canary_replaces_upstream() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, create_config(r#"type = "canary""#)).unwrap();
+
+    let promotion = promote(&config_path, None).unwrap();
+    assert_eq!(promotion, Promotion::Promoted(vec!["web-app".to_string()]));
+
+    let config = Config::load(&config_path).unwrap();
+    assert_eq!(config.services[0].upstream, "http://web-green:3000");
+    assert_eq!(config.services[0].candidate_upstream, None);
+    assert!(
+        fs::read_to_string(&config_path)
+            .unwrap()
+            .starts_with("# Release config")
+    );
+
+    // Nothing left to promote
+    assert!(promote(&config_path, None).is_err());
+}
+
+#[test]
+fn test_promote_without_strategy() {
+    let content = create_config("").replace("[deployment.strategy]", "");
+
+    assert!(promote_content(&content, None).is_err());
+}
@@ -0,0 +1,184 @@
+//! # Example configuration fixtures
+//!
+//! Validates a directory of example configurations in one go, so a
+//! downstream repository can keep its real and edge-case configs next to
+//! each other and catch regressions when it upgrades Cerberus or edits
+//! shared templates. Every example is loaded, linted, and rendered in
+//! memory for each of its `[outputs]`; the rendered files are then checked
+//! like `cerberus validate` checks a generated directory. Nothing is
+//! written to disk.
+//!
+//! The directory holds either `*.toml` files or subdirectories with a
+//! `config.toml` (for examples that `include` other files). Examples named
+//! `*.invalid.toml` document configurations that must be rejected, and pass
+//! only when loading them fails.
+//!
+//! ```no_run
+//! # fn main() -> cerberus::Result<()> {
+//! let report = cerberus::examples::validate_dir(std::path::Path::new("examples"), false)?;
+//! assert!(report.valid, "{:#?}", report.examples);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cli::{Finding, FindingStatus};
+use crate::config::Config;
+use crate::generators::CerberusGenerator;
+use crate::lint::{self, LintFinding};
+use crate::{Cerberus, CerberusError, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Suffix of examples that must fail to load
+const INVALID_SUFFIX: &str = ".invalid.toml";
+
+/// Result of validating one example configuration
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ExampleResult {
+    /// Configuration file of the example
+    pub config: PathBuf,
+    /// Whether the example behaved as expected
+    pub valid: bool,
+    /// Whether the example is expected to be rejected
+    pub expect_invalid: bool,
+    /// Why the configuration could not be loaded or rendered
+    pub error: Option<String>,
+    /// Syntax checks of the rendered files
+    pub findings: Vec<Finding>,
+    /// Lint rule violations in the configuration
+    pub lints: Vec<LintFinding>,
+}
+
+impl ExampleResult {
+    /// Why the example failed, one line per problem
+    pub fn problems(&self, strict: bool) -> Vec<String> {
+        self.error
+            .iter()
+            .cloned()
+            .chain(
+                self.findings
+                    .iter()
+                    .filter(|finding| finding.status == FindingStatus::Error)
+                    .map(|finding| finding.message.clone()),
+            )
+            .chain(
+                self.lints
+                    .iter()
+                    .filter(|lint| lint.fails(strict))
+                    .map(|lint| format!("[{}] {}: {}", lint.rule.id(), lint.subject, lint.message)),
+            )
+            .collect()
+    }
+}
+
+/// Result of `validate --examples-dir`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ExamplesReport {
+    /// Whether every example behaved as expected
+    pub valid: bool,
+    /// Every example checked, in path order
+    pub examples: Vec<ExampleResult>,
+}
+
+/// Validate every example configuration in a directory
+///
+/// With `strict`, lint warnings fail an example as well as errors.
+///
+/// # Errors
+/// Returns error if the directory cannot be read or holds no examples
+pub fn validate_dir(dir: &Path, strict: bool) -> Result<ExamplesReport> {
+    let configs = example_configs(dir)?;
+    if configs.is_empty() {
+        return Err(CerberusError::config(format!(
+            "No example configurations in {}; add *.toml files or directories with a config.toml",
+            dir.display()
+        )));
+    }
+
+    let examples: Vec<ExampleResult> = configs
+        .iter()
+        .map(|config| validate_example(config, strict))
+        .collect();
+    Ok(ExamplesReport {
+        valid: examples.iter().all(|example| example.valid),
+        examples,
+    })
+}
+
+/// Validate one example configuration
+///
+/// Rendered paths are prefixed with the example's output directories, as
+/// they would be generated from the current directory.
+pub fn validate_example(path: &Path, strict: bool) -> ExampleResult {
+    let expect_invalid = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with(INVALID_SUFFIX));
+    let mut result = ExampleResult {
+        config: path.to_path_buf(),
+        valid: false,
+        expect_invalid,
+        error: None,
+        findings: Vec::new(),
+        lints: Vec::new(),
+    };
+
+    let config = match Config::load(path) {
+        Ok(config) => config,
+        Err(e) => {
+            result.valid = expect_invalid;
+            result.error = Some(e.to_string());
+            return result;
+        }
+    };
+    if expect_invalid {
+        result.error =
+            Some("Configuration loaded, but the example expects it to be rejected".to_string());
+        return result;
+    }
+
+    result.lints = lint::lint(&config);
+    let cerberus = Cerberus::from_config(config, Path::new("built"));
+    for (config, output_dir) in cerberus.outputs() {
+        let generator = CerberusGenerator::new(&config, output_dir.to_string_lossy().to_string());
+        match generator.render() {
+            Ok(files) => result.findings.extend(generator.check_rendered(&files)),
+            Err(e) => {
+                result.error = Some(e.to_string());
+                return result;
+            }
+        }
+    }
+
+    result.valid = result
+        .findings
+        .iter()
+        .all(|finding| finding.status == FindingStatus::Ok)
+        && !result.lints.iter().any(|lint| lint.fails(strict));
+    result
+}
+
+/// Example configuration files in a directory, sorted by path
+fn example_configs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(dir).map_err(|e| CerberusError::io(dir, e))?;
+
+    let mut configs = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| CerberusError::io(dir, e))?.path();
+        if path.is_dir() {
+            let config = path.join("config.toml");
+            if config.is_file() {
+                configs.push(config);
+            }
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            configs.push(path);
+        }
+    }
+    configs.sort();
+    Ok(configs)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,105 @@
+//! # Tests for example configuration fixtures
+//!
+//! These tests verify that a directory of examples is rendered and checked
+//! in memory, and that `*.invalid.toml` examples pass only when rejected.
+
+use super::*;
+use tempfile::TempDir;
+
+/// A minimal valid configuration
+const VALID: &str = r#"
+[project]
+name = "example"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://192.0.2.1:3000"
+"#;
+
+/// Write files into a new temporary directory
+fn examples_dir(files: &[(&str, &str)]) -> TempDir {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+    for (path, content) in files {
+        let path = dir.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn test_examples_are_rendered_and_checked() {
+    let dir = examples_dir(&[
+        ("basic.toml", VALID),
+        ("layered/config.toml", VALID),
+        ("layered/README.md", "not an example"),
+        (
+            "empty-name.invalid.toml",
+            &VALID.replace("name = \"example\"", "name = \"\""),
+        ),
+    ]);
+    let report = validate_dir(dir.path(), false).expect("Examples should be read");
+
+    assert!(report.valid, "{:#?}", report.examples);
+    let configs: Vec<&Path> = report
+        .examples
+        .iter()
+        .map(|example| example.config.strip_prefix(dir.path()).unwrap())
+        .collect();
+    assert_eq!(
+        configs,
+        vec![
+            Path::new("basic.toml"),
+            Path::new("empty-name.invalid.toml"),
+            Path::new("layered/config.toml"),
+        ]
+    );
+
+    let basic = &report.examples[0];
+    assert!(!basic.expect_invalid);
+    assert_eq!(basic.error, None);
+    assert!(
+        basic
+            .findings
+            .iter()
+            .any(|finding| finding.file == Path::new("built/docker-compose.yaml"))
+    );
+
+    let rejected = &report.examples[1];
+    assert!(rejected.expect_invalid);
+    assert!(
+        rejected
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Project name cannot be empty")
+    );
+}
+
+#[test]
+fn test_examples_fail_on_regressions() {
+    let dir = examples_dir(&[
+        ("accepted.invalid.toml", VALID),
+        ("broken.toml", "[project]\nname = 1\n"),
+    ]);
+    let report = validate_dir(dir.path(), false).expect("Examples should be read");
+
+    assert!(!report.valid);
+    assert!(report.examples.iter().all(|example| !example.valid));
+    assert!(
+        report.examples[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("expects it to be rejected")
+    );
+
+    let empty = TempDir::new().expect("Failed to create temp dir");
+    assert!(validate_dir(empty.path(), false).is_err());
+}
@@ -0,0 +1,259 @@
+//! # Generation history
+//!
+//! Every generation is recorded under `.cerberus/history` next to the
+//! configuration so an earlier set of generated files can be inspected or
+//! restored. Files are stored content-addressed: each distinct content is
+//! written once as a zstd-compressed blob named by its SHA-256, and a run
+//! only adds a small manifest mapping paths to blobs. Most files do not change between runs,
+//! so keeping dozens of runs costs little more than the files that changed.
+//!
+//! ```text
+//! .cerberus/history/
+//! ├── objects/3f/a2c1….zst   file contents, named by their SHA-256
+//! └── runs/000042.json       paths and blobs of one generation
+//! ```
+
+use crate::{CerberusError, Result, checksum::sha256_hex, generators::Manifest};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Compression level of the blobs; zstd's default trades little speed for size
+const ZSTD_LEVEL: i32 = 3;
+
+/// History directory of a configuration file
+pub fn history_dir(config_path: &Path) -> PathBuf {
+    let config_dir = config_path.parent().unwrap_or(Path::new("."));
+    config_dir.join(".cerberus").join("history")
+}
+
+/// One recorded generation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HistoryEntry {
+    /// Sequential identifier, e.g. "000042"
+    pub id: String,
+    /// Seconds since the Unix epoch when the run was recorded
+    pub created: u64,
+    /// Directory the files were generated into
+    pub output_dir: PathBuf,
+    /// SHA-256 of each file, keyed by its path relative to the output directory
+    pub files: BTreeMap<PathBuf, String>,
+}
+
+/// Content-addressed store of generated file sets
+pub struct HistoryStore {
+    root: PathBuf,
+}
+
+impl HistoryStore {
+    /// Open the store rooted at a history directory
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn objects_dir(&self) -> PathBuf {
+        self.root.join("objects")
+    }
+
+    fn runs_dir(&self) -> PathBuf {
+        self.root.join("runs")
+    }
+
+    fn object_path(&self, digest: &str) -> PathBuf {
+        let (prefix, rest) = digest.split_at(2.min(digest.len()));
+        self.objects_dir().join(prefix).join(format!("{rest}.zst"))
+    }
+
+    /// Record the files of an output directory
+    ///
+    /// Only contents not already in the store are written. Returns `None`
+    /// when the files are identical to the latest run of the same directory.
+    ///
+    /// # Errors
+    /// Returns error if the output directory cannot be read or the store
+    /// cannot be written
+    pub fn record(&self, output_dir: &Path) -> Result<Option<HistoryEntry>> {
+        let files = crate::watch::snapshot(output_dir)?;
+        let checksums = Manifest::from_files(&files).files;
+
+        let entries = self.entries()?;
+        if entries
+            .iter()
+            .rev()
+            .find(|entry| entry.output_dir == output_dir)
+            .is_some_and(|latest| latest.files == checksums)
+        {
+            return Ok(None);
+        }
+
+        for (path, content) in &files {
+            let object = self.object_path(&checksums[path]);
+            if object.exists() {
+                continue;
+            }
+            if let Some(parent) = object.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| CerberusError::io(parent, e))?;
+            }
+            let compressed = zstd::encode_all(content.as_slice(), ZSTD_LEVEL)
+                .map_err(|e| CerberusError::io(&object, e))?;
+            std::fs::write(&object, compressed).map_err(|e| CerberusError::io(&object, e))?;
+        }
+
+        let next = entries
+            .last()
+            .and_then(|entry| entry.id.parse::<u64>().ok())
+            .map_or(1, |id| id + 1);
+        let entry = HistoryEntry {
+            id: format!("{next:06}"),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            output_dir: output_dir.to_path_buf(),
+            files: checksums,
+        };
+
+        let runs = self.runs_dir();
+        std::fs::create_dir_all(&runs).map_err(|e| CerberusError::io(&runs, e))?;
+        let path = runs.join(format!("{}.json", entry.id));
+        std::fs::write(&path, serde_json::to_string_pretty(&entry)?)
+            .map_err(|e| CerberusError::io(&path, e))?;
+        Ok(Some(entry))
+    }
+
+    /// Recorded runs, oldest first
+    ///
+    /// # Errors
+    /// Returns error if a run manifest cannot be read or parsed
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>> {
+        let runs = self.runs_dir();
+        if !runs.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for dir_entry in std::fs::read_dir(&runs).map_err(|e| CerberusError::io(&runs, e))? {
+            let path = dir_entry.map_err(|e| CerberusError::io(&runs, e))?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let content = std::fs::read(&path).map_err(|e| CerberusError::io(&path, e))?;
+            let entry: HistoryEntry = serde_json::from_slice(&content).map_err(|e| {
+                CerberusError::config(format!("Invalid history run {}: {e}", path.display()))
+            })?;
+            entries.push(entry);
+        }
+        entries.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(entries)
+    }
+
+    /// Find a run by its identifier; leading zeros may be left out
+    ///
+    /// # Errors
+    /// Returns error if no run has the identifier
+    pub fn entry(&self, id: &str) -> Result<HistoryEntry> {
+        let wanted = id.parse::<u64>().ok();
+        self.entries()?
+            .into_iter()
+            .find(|entry| entry.id == id || wanted.is_some() && entry.id.parse().ok() == wanted)
+            .ok_or_else(|| CerberusError::config(format!("No generation {id} in the history")))
+    }
+
+    /// Contents of every file of a run
+    ///
+    /// # Errors
+    /// Returns error if a blob is missing, cannot be decompressed, or does not
+    /// match its checksum
+    pub fn files(&self, entry: &HistoryEntry) -> Result<BTreeMap<PathBuf, Vec<u8>>> {
+        let mut files = BTreeMap::new();
+        for (path, digest) in &entry.files {
+            let object = self.object_path(digest);
+            let compressed = std::fs::read(&object).map_err(|e| CerberusError::io(&object, e))?;
+            let content = zstd::decode_all(compressed.as_slice()).ok();
+            let Some(content) = content.filter(|content| sha256_hex(content) == *digest) else {
+                return Err(CerberusError::config(format!(
+                    "History blob {} of {} is corrupt",
+                    object.display(),
+                    path.display()
+                )));
+            };
+            files.insert(path.clone(), content);
+        }
+        Ok(files)
+    }
+
+    /// Write the files of a run back into an output directory
+    ///
+    /// Generated files the run does not have are removed, and the checksum
+    /// manifest is rewritten so the next generation does not take the
+    /// restored files for hand edits. Returns the number of files written.
+    ///
+    /// # Errors
+    /// Returns error if the run cannot be read or the files cannot be written
+    pub fn restore(&self, entry: &HistoryEntry, output_dir: &Path) -> Result<usize> {
+        let files = self.files(entry)?;
+
+        for path in crate::watch::snapshot(output_dir)?.keys() {
+            if !files.contains_key(path) {
+                let stale = output_dir.join(path);
+                std::fs::remove_file(&stale).map_err(|e| CerberusError::io(&stale, e))?;
+            }
+        }
+        for (path, content) in &files {
+            let file_path = output_dir.join(path);
+            if let Some(parent) = file_path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| CerberusError::io(parent, e))?;
+            }
+            std::fs::write(&file_path, content).map_err(|e| CerberusError::io(&file_path, e))?;
+            crate::generators::set_executable(&file_path)?;
+        }
+        Manifest::from_files(&files).write(output_dir)?;
+        Ok(files.len())
+    }
+
+    /// Keep the latest `keep` runs and drop blobs no kept run refers to
+    ///
+    /// Returns the number of runs removed.
+    ///
+    /// # Errors
+    /// Returns error if the store cannot be read or cleaned up
+    pub fn prune(&self, keep: usize) -> Result<usize> {
+        let entries = self.entries()?;
+        let removed = entries.len().saturating_sub(keep);
+        for entry in &entries[..removed] {
+            let path = self.runs_dir().join(format!("{}.json", entry.id));
+            std::fs::remove_file(&path).map_err(|e| CerberusError::io(&path, e))?;
+        }
+
+        let referenced: BTreeSet<PathBuf> = entries[removed..]
+            .iter()
+            .flat_map(|entry| entry.files.values())
+            .map(|digest| self.object_path(digest))
+            .collect();
+        let objects = self.objects_dir();
+        if objects.exists() {
+            for prefix in std::fs::read_dir(&objects).map_err(|e| CerberusError::io(&objects, e))? {
+                let prefix = prefix.map_err(|e| CerberusError::io(&objects, e))?.path();
+                for object in
+                    std::fs::read_dir(&prefix).map_err(|e| CerberusError::io(&prefix, e))?
+                {
+                    let object = object.map_err(|e| CerberusError::io(&prefix, e))?.path();
+                    if !referenced.contains(&object) {
+                        std::fs::remove_file(&object).map_err(|e| CerberusError::io(&object, e))?;
+                    }
+                }
+                if std::fs::read_dir(&prefix)
+                    .map_err(|e| CerberusError::io(&prefix, e))?
+                    .next()
+                    .is_none()
+                {
+                    std::fs::remove_dir(&prefix).map_err(|e| CerberusError::io(&prefix, e))?;
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,171 @@
+//! Tests for the generation history
+
+use super::*;
+use crate::generators::MANIFEST_FILE;
+use pretty_assertions::assert_eq;
+use tempfile::TempDir;
+
+/// Number of blobs in the store
+fn object_count(store: &HistoryStore) -> usize {
+    std::fs::read_dir(store.objects_dir())
+        .map(|prefixes| {
+            prefixes
+                .map(|prefix| std::fs::read_dir(prefix.unwrap().path()).unwrap().count())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn write(dir: &Path, path: &str, content: &str) {
+    let path = dir.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_history_dir_next_to_config() {
+    assert_eq!(
+        history_dir(Path::new("/srv/edge/config.toml")),
+        Path::new("/srv/edge/.cerberus/history")
+    );
+}
+
+#[test]
+fn test_record_deduplicates_contents() {
+    let output = TempDir::new().unwrap();
+    let store_dir = TempDir::new().unwrap();
+    let store = HistoryStore::new(store_dir.path());
+
+    write(output.path(), "docker-compose.yaml", "services: {}\n");
+    write(output.path(), "proxy/Caddyfile", ":80 {}\n");
+    write(output.path(), MANIFEST_FILE, "{}");
+    let first = store
+        .record(output.path())
+        .unwrap()
+        .expect("first run is recorded");
+    assert_eq!(first.id, "000001");
+    // The checksum manifest is bookkeeping and not recorded
+    assert_eq!(
+        first.files.keys().collect::<Vec<_>>(),
+        vec![
+            Path::new("docker-compose.yaml"),
+            Path::new("proxy/Caddyfile")
+        ]
+    );
+    assert_eq!(object_count(&store), 2);
+
+    // Nothing changed: no new run
+    assert!(store.record(output.path()).unwrap().is_none());
+
+    // Only the changed file adds a blob
+    write(output.path(), "proxy/Caddyfile", ":8080 {}\n");
+    let second = store
+        .record(output.path())
+        .unwrap()
+        .expect("change is recorded");
+    assert_eq!(second.id, "000002");
+    assert_eq!(object_count(&store), 3);
+    assert_eq!(store.entries().unwrap(), vec![first.clone(), second]);
+    assert_eq!(store.entry("1").unwrap(), first);
+    assert!(
+        store
+            .entry("7")
+            .unwrap_err()
+            .to_string()
+            .contains("No generation 7 in the history")
+    );
+}
+
+#[test]
+fn test_restore_rolls_back_output() {
+    let output = TempDir::new().unwrap();
+    let store_dir = TempDir::new().unwrap();
+    let store = HistoryStore::new(store_dir.path());
+
+    write(output.path(), "docker-compose.yaml", "version: 1\n");
+    let first = store.record(output.path()).unwrap().unwrap();
+
+    write(output.path(), "docker-compose.yaml", "version: 2\n");
+    write(output.path(), "proxy/nginx.conf", "events {}\n");
+    store.record(output.path()).unwrap().unwrap();
+
+    assert_eq!(store.restore(&first, output.path()).unwrap(), 1);
+    assert_eq!(
+        std::fs::read_to_string(output.path().join("docker-compose.yaml")).unwrap(),
+        "version: 1\n"
+    );
+    assert!(!output.path().join("proxy/nginx.conf").exists());
+    // The restored files are not mistaken for hand edits
+    let manifest = Manifest::load(output.path()).unwrap().unwrap();
+    assert_eq!(manifest.files, first.files);
+    assert!(manifest.modified_files(output.path()).unwrap().is_empty());
+
+    // A damaged blob is reported instead of restored
+    let blob = store.object_path(&first.files[Path::new("docker-compose.yaml")]);
+    std::fs::write(&blob, "tampered").unwrap();
+    assert!(
+        store
+            .restore(&first, output.path())
+            .unwrap_err()
+            .to_string()
+            .contains("is corrupt")
+    );
+}
+
+#[test]
+fn test_prune_drops_unreferenced_blobs() {
+    let output = TempDir::new().unwrap();
+    let store_dir = TempDir::new().unwrap();
+    let store = HistoryStore::new(store_dir.path());
+
+    write(output.path(), "shared.conf", "unchanged\n");
+    for version in 1..=4 {
+        write(
+            output.path(),
+            "docker-compose.yaml",
+            &format!("version: {version}\n"),
+        );
+        store.record(output.path()).unwrap().unwrap();
+    }
+    assert_eq!(object_count(&store), 5);
+
+    assert_eq!(store.prune(2).unwrap(), 2);
+    let ids: Vec<String> = store
+        .entries()
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.id)
+        .collect();
+    assert_eq!(ids, vec!["000003", "000004"]);
+    // The shared file and the two kept versions remain
+    assert_eq!(object_count(&store), 3);
+    for entry in store.entries().unwrap() {
+        assert_eq!(store.files(&entry).unwrap().len(), 2);
+    }
+
+    // Numbering continues after pruning
+    write(output.path(), "docker-compose.yaml", "version: 5\n");
+    assert_eq!(store.record(output.path()).unwrap().unwrap().id, "000005");
+}
+
+#[test]
+fn test_blobs_are_compressed() {
+    let output = TempDir::new().unwrap();
+    let store_dir = TempDir::new().unwrap();
+    let store = HistoryStore::new(store_dir.path());
+
+    let content = "proxy_set_header X-Forwarded-For $proxy_add_x_forwarded_for;\n".repeat(200);
+    write(output.path(), "proxy/nginx.conf", &content);
+    let entry = store.record(output.path()).unwrap().unwrap();
+
+    let blob = store.object_path(&entry.files[Path::new("proxy/nginx.conf")]);
+    assert_eq!(blob.extension().unwrap(), "zst");
+    assert!(std::fs::metadata(&blob).unwrap().len() < content.len() as u64);
+
+    std::fs::remove_dir_all(output.path()).unwrap();
+    store.restore(&entry, output.path()).unwrap();
+    assert_eq!(
+        std::fs::read(output.path().join("proxy/nginx.conf")).unwrap(),
+        content.as_bytes()
+    );
+}
@@ -17,15 +17,31 @@
 //! - **Auto-Scaling**: CPU/Memory/Connection-based scaling
 //! - **DDoS Protection**: Anubis AI Firewall integration
 //! - **Template System**: Pre-configured setups for common use cases
+//!
+//! The configuration model, validation and generators live in the
+//! `cerberus-core` crate and are re-exported here; this crate adds the CLI,
+//! secret resolution, and the daemons.
 
 pub mod cli;
-pub mod config;
-pub mod error;
-pub mod generators;
+pub mod deployment;
+pub mod examples;
+pub mod history;
+pub mod notifications;
+pub mod publish;
+pub mod reload;
 pub mod scaling;
-pub mod templates;
+pub mod secrets;
+pub mod status;
+#[cfg(test)]
+mod test_support;
+pub mod testing;
+pub mod top;
+pub mod upgrade;
+pub mod watch;
 
-pub use error::{CerberusError, Result};
+pub use cerberus_core::{
+    CerberusError, Result, checksum, config, error, generators, lint, report, templates,
+};
 
 /// The main Cerberus application struct
 ///
@@ -37,6 +53,18 @@ pub struct Cerberus {
     config: config::Config,
     /// Output directory for generated files
     output_dir: std::path::PathBuf,
+    /// Overwrite generated files that were edited by hand
+    force: bool,
+    /// age identity file used to decrypt encrypted secrets
+    age_key: Option<std::path::PathBuf>,
+    /// Leave `vault://` references to a vault-agent sidecar
+    vault_agent: bool,
+    /// Directory keeping rendered template fragments between runs
+    fragment_cache: Option<std::path::PathBuf>,
+    /// Directory generations are recorded in
+    history: Option<std::path::PathBuf>,
+    /// Generators rendering the output files
+    generators: generators::GeneratorRegistry,
 }
 
 impl Cerberus {
@@ -51,10 +79,97 @@ impl Cerberus {
     pub fn new(config_path: &std::path::Path, output_dir: &std::path::Path) -> Result<Self> {
         let config = config::Config::load(config_path)?;
 
-        Ok(Self {
+        Ok(Self::from_config(config, output_dir))
+    }
+
+    /// Create a Cerberus instance from an already built configuration
+    ///
+    /// Use with [`config::ConfigBuilder`] to skip the TOML file. The
+    /// configuration is expected to be validated, as `build()` does; its
+    /// upstreams are normalized here in case it was not.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration to generate from
+    /// * `output_dir` - Directory where generated files will be written
+    pub fn from_config(mut config: config::Config, output_dir: &std::path::Path) -> Self {
+        config.normalize_upstreams();
+        Self {
             config,
             output_dir: output_dir.to_path_buf(),
-        })
+            force: false,
+            age_key: None,
+            vault_agent: false,
+            fragment_cache: None,
+            history: None,
+            generators: generators::GeneratorRegistry::builtin(),
+        }
+    }
+
+    /// Overwrite generated files even if they were edited by hand
+    ///
+    /// Without it, generation fails when a file recorded in the output
+    /// directory's checksum manifest changed since it was generated.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Decrypt age-encrypted secrets with this identity file
+    ///
+    /// Without it, the `SOPS_AGE_KEY_FILE` environment variable is used.
+    pub fn age_key(mut self, age_key: Option<std::path::PathBuf>) -> Self {
+        self.age_key = age_key;
+        self
+    }
+
+    /// Render `vault://` references with a vault-agent sidecar
+    ///
+    /// Without it, the values are read from Vault at `$VAULT_ADDR` while
+    /// generating.
+    pub fn vault_agent(mut self, vault_agent: bool) -> Self {
+        self.vault_agent = vault_agent;
+        self
+    }
+
+    /// Keep rendered per-service fragments in a directory between runs
+    ///
+    /// Repeated generations, as in watch mode, then only render the
+    /// fragments of services whose configuration changed.
+    pub fn fragment_cache(mut self, dir: Option<std::path::PathBuf>) -> Self {
+        self.fragment_cache = dir;
+        self
+    }
+
+    /// Record each generation in a history directory
+    ///
+    /// Recording follows `[history]`: nothing is kept when it is disabled,
+    /// and runs beyond `keep` are pruned.
+    pub fn history(mut self, dir: Option<std::path::PathBuf>) -> Self {
+        self.history = dir;
+        self
+    }
+
+    /// Render for a deployment target instead of `project.target`
+    ///
+    /// Declared `[outputs]` are dropped: the target is rendered into the
+    /// output directory.
+    ///
+    /// # Errors
+    /// Returns error if the configuration uses options the target cannot
+    /// honour
+    pub fn target(mut self, target: config::DeployTarget) -> Result<Self> {
+        self.config.project.target = target;
+        self.config.outputs.clear();
+        self.config.validate()?;
+        Ok(self)
+    }
+
+    /// Render the files of a custom generator along with the built-in ones
+    ///
+    /// Generation fails if it writes a path another generator writes.
+    pub fn register(mut self, generator: impl generators::Generator + 'static) -> Self {
+        self.generators.register(generator);
+        self
     }
 
     /// Generate all configuration files
@@ -68,12 +183,193 @@ impl Cerberus {
         let generator = generators::CerberusGenerator::new(
             &self.config,
             self.output_dir.to_string_lossy().to_string(),
-        );
+        )
+        .force(self.force)
+        .vault_agent(self.vault_agent)
+        .fragment_cache(self.fragment_cache.clone())
+        .registry(self.generators.clone());
+
+        generator.generate_all_concurrent().await?;
+        self.write_secrets(&self.config, &self.output_dir).await?;
+        self.record_history(&self.output_dir)
+    }
 
-        generator.generate_all().await?;
+    /// Generate every output declared under `[outputs]`
+    ///
+    /// Each output is rendered into its own directory for its deployment
+    /// target. Without declared outputs this behaves like [`generate_all`].
+    /// Returns the directories that were written.
+    ///
+    /// [`generate_all`]: Cerberus::generate_all
+    ///
+    /// # Errors
+    /// Returns error if any generation step fails
+    pub async fn generate_outputs(&self) -> Result<Vec<std::path::PathBuf>> {
+        let mut written = Vec::new();
+        for (config, output_dir) in self.outputs() {
+            let generator = generators::CerberusGenerator::new(
+                &config,
+                output_dir.to_string_lossy().to_string(),
+            )
+            .force(self.force)
+            .vault_agent(self.vault_agent)
+            // Each output prunes its own fragments
+            .fragment_cache(
+                self.fragment_cache
+                    .as_ref()
+                    .map(|dir| dir.join(config.project.target.as_str())),
+            )
+            .registry(self.generators.clone());
+
+            if !self.config.outputs.is_empty() {
+                tracing::info!(
+                    "Generating {} output in {}",
+                    config.project.target.as_str(),
+                    output_dir.display()
+                );
+            }
+            generator.generate_all_concurrent().await?;
+            self.write_secrets(&config, &output_dir).await?;
+            self.record_history(&output_dir)?;
+            written.push(output_dir);
+        }
+        Ok(written)
+    }
+
+    /// Render all configuration files without writing them
+    ///
+    /// Returns the files [`generate_all`] would write, keyed by their path
+    /// including the output directory. Nothing on disk is touched, so the
+    /// result can be compared between runs or against a previous generation.
+    ///
+    /// [`generate_all`]: Cerberus::generate_all
+    ///
+    /// # Errors
+    /// Returns error if any generation step fails
+    pub fn generate_to_memory(
+        &self,
+    ) -> Result<std::collections::BTreeMap<std::path::PathBuf, Vec<u8>>> {
+        self.render_into(&self.config, &self.output_dir)
+    }
+
+    /// Render all configuration files as typed artifacts without writing them
+    ///
+    /// Each [`generators::GeneratedArtifact`] carries its path relative to
+    /// the output directory, the kind of generator that produced it, and its
+    /// content, so the files can be filtered, post-processed, or written to
+    /// another store instead of the output directory. Secret values are not
+    /// part of the plan.
+    ///
+    /// # Errors
+    /// Returns error if any generation step fails
+    pub fn generate_plan(&self) -> Result<Vec<generators::GeneratedArtifact>> {
+        generators::CerberusGenerator::new(
+            &self.config,
+            self.output_dir.to_string_lossy().to_string(),
+        )
+        .vault_agent(self.vault_agent)
+        .registry(self.generators.clone())
+        .plan()
+    }
+
+    /// Render every output declared under `[outputs]` without writing them
+    ///
+    /// The in-memory counterpart of [`generate_outputs`].
+    ///
+    /// [`generate_outputs`]: Cerberus::generate_outputs
+    ///
+    /// # Errors
+    /// Returns error if any generation step fails
+    pub fn generate_outputs_to_memory(
+        &self,
+    ) -> Result<std::collections::BTreeMap<std::path::PathBuf, Vec<u8>>> {
+        let mut files = std::collections::BTreeMap::new();
+        for (config, output_dir) in self.outputs() {
+            files.extend(self.render_into(&config, &output_dir)?);
+        }
+        Ok(files)
+    }
+
+    /// Resolve the secret values of a Compose output next to its files
+    ///
+    /// The values stay out of the rendered files and the manifest.
+    async fn write_secrets(
+        &self,
+        config: &config::Config,
+        output_dir: &std::path::Path,
+    ) -> Result<()> {
+        if config.project.target == config::DeployTarget::Compose {
+            secrets::write_secrets(
+                config,
+                output_dir,
+                self.age_key.as_deref(),
+                self.vault_agent,
+            )
+            .await?;
+        }
         Ok(())
     }
 
+    /// Record a written output directory in the history, if one is kept
+    fn record_history(&self, output_dir: &std::path::Path) -> Result<()> {
+        let settings = &self.config.history;
+        let Some(dir) = self.history.as_ref().filter(|_| settings.enabled) else {
+            return Ok(());
+        };
+
+        let store = history::HistoryStore::new(dir);
+        if let Some(entry) = store.record(output_dir)? {
+            tracing::info!(
+                "Recorded generation {} of {} in the history",
+                entry.id,
+                output_dir.display()
+            );
+        }
+        store.prune(settings.keep)?;
+        Ok(())
+    }
+
+    /// Directory of every declared output, or the output directory without any
+    pub fn output_dirs(&self) -> Vec<std::path::PathBuf> {
+        self.outputs().into_iter().map(|(_, dir)| dir).collect()
+    }
+
+    /// Configuration and output directory of every declared output
+    pub(crate) fn outputs(&self) -> Vec<(config::Config, std::path::PathBuf)> {
+        if self.config.outputs.is_empty() {
+            return vec![(self.config.clone(), self.output_dir.clone())];
+        }
+
+        self.config
+            .outputs
+            .iter()
+            .map(|(target, output)| {
+                (
+                    self.config.for_output(*target, output),
+                    std::path::PathBuf::from(output.path.trim_end_matches('/')),
+                )
+            })
+            .collect()
+    }
+
+    /// Render a configuration with paths prefixed by its output directory
+    fn render_into(
+        &self,
+        config: &config::Config,
+        output_dir: &std::path::Path,
+    ) -> Result<std::collections::BTreeMap<std::path::PathBuf, Vec<u8>>> {
+        let generator =
+            generators::CerberusGenerator::new(config, output_dir.to_string_lossy().to_string())
+                .vault_agent(self.vault_agent)
+                .registry(self.generators.clone());
+
+        Ok(generator
+            .render()?
+            .into_iter()
+            .map(|(path, content)| (output_dir.join(path), content))
+            .collect())
+    }
+
     /// Validate generated configurations
     ///
     /// Performs syntax validation on generated Docker Compose and other files
@@ -86,10 +382,23 @@ impl Cerberus {
             self.output_dir.to_string_lossy().to_string(),
         );
 
-        generator.validate_generated().await?;
+        generator.validate_generated()?;
         Ok(())
     }
 
+    /// Check generated configurations without failing on invalid files
+    ///
+    /// # Errors
+    /// Returns error if a generated file cannot be read
+    pub async fn validation_findings(&self) -> Result<Vec<report::Finding>> {
+        let generator = generators::CerberusGenerator::new(
+            &self.config,
+            self.output_dir.to_string_lossy().to_string(),
+        );
+
+        generator.check_generated()
+    }
+
     /// Clean generated files
     ///
     /// Removes all generated configuration files and directories
@@ -102,7 +411,7 @@ impl Cerberus {
             self.output_dir.to_string_lossy().to_string(),
         );
 
-        generator.clean().await?;
+        generator.clean()?;
         Ok(())
     }
 
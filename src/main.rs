@@ -5,20 +5,138 @@
 //! ## Usage
 //!
 //! ```bash
-//! # Generate all configuration files
+//! # Start a configuration from an application template
+//! cerberus init --template misskey --domain mi.example.com
+//!
+//! # Generate all configuration files (every `[outputs]` entry, if declared)
 //! cerberus generate
 //!
+//! # Generate only the project target into a specific directory
+//! cerberus -o built generate
+//!
+//! # Render a stack file for docker stack deploy instead of the project target
+//! cerberus generate --target swarm
+//!
+//! # Regenerate even if generated files were edited by hand
+//! cerberus generate --force
+//!
+//! # Decrypt age-encrypted secrets with a specific identity
+//! cerberus --age-key ~/.config/sops/age/keys.txt generate
+//!
+//! # Leave vault:// references to a vault-agent sidecar instead of reading Vault
+//! cerberus generate --no-resolve
+//!
 //! # Validate existing configuration
 //! cerberus validate
 //!
+//! # Also fail on lint warnings ([lint.rules] sets each rule's severity)
+//! cerberus validate --strict
+//!
+//! # Render and check every example config in a directory (no config.toml needed)
+//! cerberus validate --examples-dir ./examples
+//!
 //! # Clean generated files
 //! cerberus clean
+//!
+//! # Remove only what the last generation wrote, keeping logs and certificates
+//! cerberus clean --generated-only --keep logs,certs
+//!
+//! # Upgrade the config to the current schema_version, renaming deprecated keys
+//! cerberus migrate
+//!
+//! # Move the pinned revision of a remote `extends` base in cerberus.lock
+//! cerberus lock
+//!
+//! # List earlier generations and roll back to one of them
+//! cerberus history list
+//! cerberus history restore 41
+//!
+//! # Regenerate whenever the configuration changes
+//! cerberus watch --reload
+//!
+//! # Shift 50% of traffic to the canary, then promote it
+//! cerberus promote --weight 50
+//! cerberus promote
+//!
+//! # Apply a new generation to the running stack, touching only what changed
+//! cerberus upgrade --dry-run
+//! cerberus upgrade --health-timeout 300
+//!
+//! # Copy the generated configuration into running proxies and reload them in place
+//! cerberus reload
+//! cerberus reload proxy-2
+//!
+//! # Push the last generation to S3, an SSH host or a git branch as a versioned bundle
+//! cerberus publish s3://cerberus-bundles/prod
+//! cerberus publish ssh://deploy@edge-1.example.com/srv/cerberus
+//! cerberus publish git+ssh://git@example.com/ops/bundles.git#prod
+//!
+//! # Show the containers of the deployed stack; fails if any is missing or unhealthy
+//! cerberus status
+//!
+//! # Boot the generated stack, probe health, routing and challenges, then tear it down
+//! cerberus test --timeout 180
+//!
+//! # Live dashboard with request rates; reload a proxy or scale a layer (`tui` feature)
+//! cerberus top --interval 5
+//!
+//! # Compare live DNS with the record plan from [dns] before deploying
+//! cerberus dns check
+//!
+//! # Render the topology as Mermaid (default) or Graphviz DOT
+//! cerberus graph > topology.mmd
+//! cerberus graph --style dot | dot -Tsvg -o topology.svg
+//!
+//! # The configuration as used: defaults, includes and base merged, each value with its source
+//! cerberus config show --effective
+//!
+//! # JSON Schema of config.toml for editors and CI (no config.toml needed)
+//! cerberus schema > cerberus.schema.json
+//!
+//! # Forward an alert to the channels routed for its event
+//! cerberus notify cert_expiry "Certificate expires soon" --message "app.example.com: 7 days"
+//!
+//! # Fail in CI when the committed output differs from what the configuration renders
+//! cerberus generate --frozen
+//!
+//! # Render everything in memory and list what would be written
+//! cerberus generate --dry-run
+//!
+//! # Shell completions and the manual page, from the same command definitions
+//! cerberus completions zsh > ~/.zfunc/_cerberus
+//! cerberus man > cerberus.1
+//! cerberus man --out-dir /usr/local/share/man/man1
+//!
+//! # Machine-readable results for CI (manifest, findings, removed paths)
+//! cerberus --format json generate
+//! cerberus --format yaml validate
 //! ```
 
-use clap::{Arg, Command};
-use std::path::PathBuf;
-use tracing::{error, info};
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info, warn};
 
+use cerberus::cli::{
+    CleanOptions, CleanPlan, CleanReport, Cli, Commands, ConfigCommand, DnsReport, DriftReport,
+    FindingStatus, GenerateReport, HistoryCommand, OutputFormat, ValidateReport, confirm,
+};
+use cerberus::config::{self, DeployTarget, LintSeverity, NotificationEvent};
+use cerberus::deployment::{self, Promotion};
+use cerberus::examples;
+use cerberus::generators::{COMPOSE_FILE, DnsGenerator, GraphGenerator};
+use cerberus::history::{self, HistoryStore};
+use cerberus::lint;
+use cerberus::notifications::{Notification, Notifier};
+use cerberus::publish::{self, Bundle, PublishTarget};
+use cerberus::reload;
+use cerberus::status::StackStatus;
+use cerberus::testing::SmokeTest;
+#[cfg(feature = "tui")]
+use cerberus::top::{self, Dashboard};
+use cerberus::upgrade::{self, UpgradePlan, Upgrader};
+use cerberus::watch::{self, WatchOptions};
 use cerberus::{Cerberus, Result};
 
 /// Main entry point for the Cerberus CLI application
@@ -27,65 +145,640 @@ use cerberus::{Cerberus, Result};
 /// execution of the requested subcommand.
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize structured logging with tracing
-    tracing_subscriber::fmt::init();
-
-    let matches = Command::new("cerberus")
-        .version("0.1.0")
-        .about("Multi-layer proxy architecture system")
-        .arg(
-            Arg::new("config")
-                .short('c')
-                .long("config")
-                .value_name("FILE")
-                .help("Configuration file path")
-                .default_value("config.toml"),
-        )
-        .arg(
-            Arg::new("output")
-                .short('o')
-                .long("output")
-                .value_name("DIR")
-                .help("Output directory for generated files")
-                .default_value("built"),
-        )
-        .subcommand(
-            Command::new("generate")
-                .about("Generate all configuration files")
-                .arg(
-                    Arg::new("force")
-                        .long("force")
-                        .help("Overwrite existing files")
-                        .action(clap::ArgAction::SetTrue),
-                ),
-        )
-        .subcommand(Command::new("validate").about("Validate configuration and generated files"))
-        .subcommand(Command::new("clean").about("Clean output directory"))
-        .get_matches();
-
-    let config_path = PathBuf::from(matches.get_one::<String>("config").unwrap());
-    let output_dir = PathBuf::from(matches.get_one::<String>("output").unwrap());
-
-    let cerberus = Cerberus::new(&config_path, &output_dir)?;
-
-    match matches.subcommand() {
-        Some(("generate", _sub_matches)) => {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    let format = cli.format;
+
+    // Initialize structured logging with tracing; reports own stdout
+    if cfg!(feature = "tui") && matches!(cli.command, Some(Commands::Top { .. })) {
+        // Log lines would tear the dashboard's screen
+        tracing_subscriber::fmt().with_writer(std::io::sink).init();
+    } else if format == OutputFormat::Text {
+        tracing_subscriber::fmt::init();
+    } else {
+        tracing_subscriber::fmt()
+            .with_writer(std::io::stderr)
+            .init();
+    }
+
+    let config_path = cli.config;
+    let output_dir = cli.output;
+    let age_key = cli.age_key;
+    // An explicit --output renders the project target there instead of [outputs]
+    let declared_outputs = matches.value_source("output") == Some(ValueSource::DefaultValue);
+
+    // Completions and manual pages describe the CLI, not a configuration
+    match &cli.command {
+        Some(Commands::Completions { shell }) => {
+            Cli::completions(*shell, &mut std::io::stdout());
+            return Ok(());
+        }
+        Some(Commands::Man { out_dir: None }) => {
+            return Cli::man_page(&mut std::io::stdout());
+        }
+        Some(Commands::Man { out_dir: Some(dir) }) => {
+            Cli::man_pages(dir)?;
+            info!("Wrote the manual pages to {}", dir.display());
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    // Watch mode tolerates an invalid config so it can be fixed while running
+    if let Some(Commands::Watch { path, up, reload }) = &cli.command {
+        let options = WatchOptions {
+            config_path,
+            output_dir,
+            extra_paths: path.clone(),
+            compose_up: *up,
+            reload: *reload,
+            age_key,
+        };
+        return watch::run(options).await;
+    }
+
+    // The configuration does not exist yet
+    if let Some(Commands::Init {
+        template,
+        domain,
+        upstream,
+        force,
+    }) = &cli.command
+    {
+        let domain = domain
+            .clone()
+            .unwrap_or_else(|| format!("{template}.example.com"));
+        let upstream = upstream
+            .clone()
+            .unwrap_or_else(|| template.default_upstream());
+        config::write_starter_config(&config_path, *template, &domain, &upstream, *force)?;
+        info!(
+            "Wrote a {} configuration for {domain} to {}",
+            template.description(),
+            config_path.display()
+        );
+        return Ok(());
+    }
+
+    // The schema describes the model, not the loaded configuration
+    if let Some(Commands::Schema) = &cli.command {
+        println!("{}", serde_json::to_string_pretty(&config::json_schema())?);
+        return Ok(());
+    }
+
+    // Moving the pin must not depend on the currently pinned base
+    if let Some(Commands::Lock) = &cli.command {
+        match config::update_lock(&config_path)? {
+            Some(locked) => info!(
+                "Pinned {} at {} in {}",
+                locked.source,
+                locked.revision,
+                config::LOCK_FILE
+            ),
+            None => info!("The configuration does not extend a remote base; nothing to lock"),
+        }
+        return Ok(());
+    }
+
+    // Publishing ships the generated files; the configuration is not read
+    if let Some(Commands::Publish { target, dry_run }) = &cli.command {
+        let target: PublishTarget = target.parse()?;
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let bundle = Bundle::new(&output_dir, created)?;
+
+        if *dry_run {
+            for file in &bundle.files {
+                info!("Would publish {}", file.display());
+            }
+            info!(
+                "Dry run completed; {} would be published to {}",
+                bundle.version,
+                target.location(&bundle.version)
+            );
+            return Ok(());
+        }
+
+        let report = publish::publish(&bundle, &target).await?;
+        if let Some(rendered) = format.render(&report)? {
+            println!("{rendered}");
+        }
+        info!(
+            "Published {} file(s) as {} to {}",
+            report.files, report.version, report.location
+        );
+        return Ok(());
+    }
+
+    // Rolling back must work even when the current config does not load
+    if let Some(Commands::History { command }) = &cli.command {
+        let store = HistoryStore::new(history::history_dir(&config_path));
+        match command {
+            HistoryCommand::Restore { id } => {
+                let entry = store.entry(id)?;
+                // An explicit --output restores there instead of where it was generated
+                let target = if declared_outputs {
+                    entry.output_dir.clone()
+                } else {
+                    output_dir
+                };
+                let restored = store.restore(&entry, &target)?;
+                info!(
+                    "Restored {restored} file(s) of generation {} into {}",
+                    entry.id,
+                    target.display()
+                );
+            }
+            HistoryCommand::List => {
+                let entries = store.entries()?;
+                match format.render(&entries)? {
+                    Some(rendered) => println!("{rendered}"),
+                    None => {
+                        let now = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map(|elapsed| elapsed.as_secs())
+                            .unwrap_or_default();
+                        for entry in &entries {
+                            info!(
+                                "{}  {:>5}m ago  {} file(s) in {}",
+                                entry.id,
+                                now.saturating_sub(entry.created) / 60,
+                                entry.files.len(),
+                                entry.output_dir.display()
+                            );
+                        }
+                        info!("{} generation(s) recorded", entries.len());
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    // Migration rewrites the files before any of them is loaded
+    if let Some(Commands::Migrate) = &cli.command {
+        let report = config::migrate(&config_path)?;
+        for migration in config::MIGRATIONS
+            .iter()
+            .filter(|migration| migration.version > report.from)
+        {
+            info!(
+                "Schema version {}: {}",
+                migration.version, migration.summary
+            );
+        }
+        for (file, key) in &report.renamed {
+            info!(
+                "{}: renamed {} to {}",
+                file.display(),
+                key.key,
+                key.replacement
+            );
+        }
+        info!(
+            "Migrated schema version {} to {} ({} renamed key(s))",
+            report.from,
+            report.to,
+            report.renamed.len()
+        );
+        return Ok(());
+    }
+
+    // Examples are validated on their own, without the project config
+    if let Some(Commands::Validate {
+        strict,
+        examples_dir: Some(dir),
+    }) = &cli.command
+    {
+        let strict = *strict;
+        let report = examples::validate_dir(dir, strict)?;
+        match format.render(&report)? {
+            Some(rendered) => println!("{rendered}"),
+            None => {
+                for example in &report.examples {
+                    let config = example.config.display();
+                    match (example.valid, &example.error) {
+                        (true, Some(reason)) => info!("{config}: rejected as expected: {reason}"),
+                        (true, None) => info!("{config}: ok"),
+                        (false, _) => {
+                            for problem in example.problems(strict) {
+                                error!("{config}: {problem}");
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if !report.valid {
+            std::process::exit(1);
+        }
+        info!("All {} example(s) validated", report.examples.len());
+        return Ok(());
+    }
+
+    // Promotion edits the config file before it is loaded
+    let mut promoted = None;
+    if let Some(Commands::Promote { weight }) = &cli.command {
+        let summary = match deployment::promote(&config_path, *weight)? {
+            Promotion::Switched(color) => format!("Switched traffic to the {color:?} pool"),
+            Promotion::Weighted(weight) => format!("Canary now receives {weight}% of traffic"),
+            Promotion::Promoted(services) => {
+                format!("Promoted candidates for: {}", services.join(", "))
+            }
+        };
+        info!("{}", summary);
+        promoted = Some(summary);
+    }
+
+    let (generating, force, vault_agent, target) = match &cli.command {
+        Some(Commands::Generate {
+            force,
+            no_resolve,
+            target,
+            ..
+        }) => (true, *force, *no_resolve, *target),
+        Some(Commands::Upgrade {
+            force, no_resolve, ..
+        }) => (true, *force, *no_resolve, None),
+        _ => (false, false, false, None),
+    };
+    let mut cerberus = Cerberus::new(&config_path, &output_dir)?
+        .history(Some(history::history_dir(&config_path)))
+        .force(force)
+        .age_key(age_key)
+        .vault_agent(vault_agent);
+    if let Some(target) = target {
+        cerberus = cerberus.target(target)?;
+    }
+    // An explicit --target is rendered into the output directory like --output
+    let declared_outputs = declared_outputs && target.is_none();
+
+    // Deprecated keys still work until their removal version; say what to change
+    if generating {
+        for deprecated in &cerberus.config().deprecations {
+            warn!("{}", deprecated.message());
+        }
+    }
+
+    if let Some(summary) = promoted {
+        let notification =
+            Notification::new(NotificationEvent::Deploy, "Release promoted", summary);
+        if let Err(e) = Notifier::new(cerberus.config()).send(&notification).await {
+            warn!("Deploy notification failed: {}", e);
+        }
+    }
+
+    match cli.command {
+        Some(Commands::Generate { frozen: true, .. }) => {
+            info!("Checking generated files against the configuration...");
+            let (files, dirs) = if declared_outputs {
+                (
+                    cerberus.generate_outputs_to_memory()?,
+                    cerberus.output_dirs(),
+                )
+            } else {
+                (cerberus.generate_to_memory()?, vec![output_dir.clone()])
+            };
+            let report = DriftReport::new(&files, &dirs)?;
+            match format.render(&report)? {
+                Some(rendered) => println!("{rendered}"),
+                None => {
+                    for path in &report.changed {
+                        error!("Changed: {}", path.display());
+                    }
+                    for path in &report.missing {
+                        error!("Missing: {}", path.display());
+                    }
+                    for path in &report.stale {
+                        error!("No longer generated: {}", path.display());
+                    }
+                }
+            }
+            if !report.frozen {
+                error!(
+                    "Generated files are out of date; run `cerberus generate` and commit the result"
+                );
+                std::process::exit(1);
+            }
+            info!("Generated files match the configuration");
+        }
+        Some(Commands::Generate { dry_run: true, .. }) => {
+            info!("Rendering configuration files (dry run)...");
+            let files = if declared_outputs {
+                cerberus.generate_outputs_to_memory()?
+            } else {
+                cerberus.generate_to_memory()?
+            };
+            let report = GenerateReport::from_files(&files);
+            match format.render(&report)? {
+                Some(rendered) => println!("{rendered}"),
+                None => {
+                    for file in &report.files {
+                        info!("Would write {} ({} bytes)", file.path.display(), file.bytes);
+                    }
+                }
+            }
+            info!("Dry run completed; nothing was written");
+        }
+        Some(Commands::Generate { .. }) | Some(Commands::Promote { .. }) => {
             info!("Generating configuration files...");
-            cerberus.generate_all().await?;
+            let written = if declared_outputs {
+                cerberus.generate_outputs().await?
+            } else {
+                cerberus.generate_all().await?;
+                vec![output_dir.clone()]
+            };
             info!("Configuration generation completed successfully");
+            if let Some(report) = format.render(&GenerateReport::from_dirs(&written)?)? {
+                println!("{report}");
+            }
         }
-        Some(("validate", _sub_matches)) => {
+        Some(Commands::Validate { strict, .. }) => {
             info!("Validating configuration...");
-            cerberus.validate().await?;
-            info!("Configuration validation completed successfully");
+            let lints = lint::lint(cerberus.config());
+            if format == OutputFormat::Text {
+                cerberus.validate().await?;
+                for finding in &lints {
+                    let line = format!(
+                        "[{}] {}: {}",
+                        finding.rule.id(),
+                        finding.subject,
+                        finding.message
+                    );
+                    match finding.severity {
+                        LintSeverity::Error => error!("{line}"),
+                        LintSeverity::Warning => warn!("{line}"),
+                        _ => info!("{line}"),
+                    }
+                }
+                let failed = lints.iter().filter(|lint| lint.fails(strict)).count();
+                if failed > 0 {
+                    error!("{failed} lint finding(s) fail validation");
+                    std::process::exit(1);
+                }
+                info!("Configuration validation completed successfully");
+            } else {
+                let report = ValidateReport::new(cerberus.validation_findings().await?)
+                    .with_lints(lints, strict);
+                if let Some(rendered) = format.render(&report)? {
+                    println!("{rendered}");
+                }
+                if !report.valid {
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Upgrade {
+            dry_run,
+            health_timeout,
+            ..
+        }) => {
+            if cerberus.config().project.target != DeployTarget::Compose {
+                error!("upgrade applies Docker Compose stacks; the project target is not compose");
+                std::process::exit(1);
+            }
+
+            let deployed = watch::snapshot(&output_dir)?;
+            // Rendered paths include the output directory; snapshots do not
+            let next = cerberus
+                .generate_to_memory()?
+                .into_iter()
+                .map(|(path, content)| {
+                    let relative = path.strip_prefix(&output_dir).unwrap_or(&path);
+                    (relative.to_path_buf(), content)
+                })
+                .collect();
+            let plan = UpgradePlan::between(cerberus.config(), &deployed, &next)?;
+            match format.render(&plan)? {
+                Some(rendered) => println!("{rendered}"),
+                None => {
+                    for step in &plan.steps {
+                        info!("Plan: {:?} {} ({})", step.action, step.service, step.reason);
+                    }
+                    info!("{} service(s) unchanged", plan.unchanged.len());
+                }
+            }
+
+            // Fail before any service is touched rather than on a bind halfway through
+            let conflicts = upgrade::port_conflicts(&output_dir, &plan, &next).await?;
+            if !conflicts.is_empty() {
+                for conflict in &conflicts {
+                    error!("{conflict}");
+                }
+                error!(
+                    "{} port conflict(s); free the ports or change them in the configuration. Nothing was applied",
+                    conflicts.len()
+                );
+                std::process::exit(1);
+            }
+
+            if dry_run {
+                info!("Dry run completed; nothing was written or applied");
+                return Ok(());
+            }
+
+            cerberus.generate_all().await?;
+            Upgrader::new(&output_dir)
+                .health_timeout(Duration::from_secs(health_timeout))
+                .apply(
+                    &plan,
+                    deployed.get(Path::new(COMPOSE_FILE)).map(Vec::as_slice),
+                )
+                .await?;
+
+            if !plan.is_empty() {
+                let summary = plan
+                    .steps
+                    .iter()
+                    .map(|step| format!("{:?} {}", step.action, step.service))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let notification =
+                    Notification::new(NotificationEvent::Deploy, "Stack upgraded", summary);
+                if let Err(e) = Notifier::new(cerberus.config()).send(&notification).await {
+                    warn!("Deploy notification failed: {}", e);
+                }
+            }
+            info!("Upgrade completed");
+        }
+        Some(Commands::Reload { proxy }) => {
+            let proxies: Vec<&config::ProxyConfig> = match proxy {
+                Some(name) => match cerberus.config().proxies.iter().find(|p| p.name == name) {
+                    Some(proxy) => vec![proxy],
+                    None => {
+                        error!("Unknown proxy: {}", name);
+                        std::process::exit(1);
+                    }
+                },
+                None => cerberus.config().proxies.iter().collect(),
+            };
+
+            let report = reload::reload(cerberus.config(), &output_dir, &proxies).await?;
+            match format.render(&report)? {
+                Some(rendered) => println!("{rendered}"),
+                None => {
+                    for proxy in &report.skipped {
+                        warn!("{} has no running container; skipped", proxy);
+                    }
+                }
+            }
+            if report.reloaded.is_empty() {
+                error!("No running proxy container to reload; start the stack first");
+                std::process::exit(1);
+            }
+            info!("Reloaded {} container(s)", report.reloaded.len());
+        }
+        Some(Commands::Status) => {
+            let status = StackStatus::collect(&output_dir).await?;
+            match format.render(&status)? {
+                Some(rendered) => println!("{rendered}"),
+                None => print!("{}", status.table()),
+            }
+            if !status.healthy {
+                for problem in status.problems() {
+                    error!("{problem}");
+                }
+                std::process::exit(1);
+            }
+            info!("All services are running");
+        }
+        Some(Commands::Test {
+            timeout,
+            keep_running,
+        }) => {
+            info!("Starting the stack in {}...", output_dir.display());
+            let report = SmokeTest::new(&output_dir, Duration::from_secs(timeout))
+                .keep_running(keep_running)
+                .run(cerberus.config())
+                .await?;
+            if let Some(rendered) = format.render(&report)? {
+                println!("{rendered}");
+            } else {
+                for probe in &report.probes {
+                    let host = probe.host.as_deref().unwrap_or("-");
+                    match probe.status {
+                        FindingStatus::Ok => {
+                            info!("✓ {} [{host}] {}", probe.name, probe.message)
+                        }
+                        _ => error!("✗ {} [{host}] {}", probe.name, probe.message),
+                    }
+                }
+            }
+            if !report.passed {
+                std::process::exit(1);
+            }
+            info!("All {} probes passed", report.probes.len());
+        }
+        #[cfg(feature = "tui")]
+        Some(Commands::Top { interval }) => {
+            let dashboard = Dashboard::new(cerberus.config().clone(), &output_dir);
+            top::ui::run(dashboard, Duration::from_secs(interval)).await?;
         }
-        Some(("clean", _sub_matches)) => {
+        #[cfg(not(feature = "tui"))]
+        Some(Commands::Top { .. }) => {
+            error!("This build has no dashboard; rebuild with `--features tui`");
+            std::process::exit(1);
+        }
+        Some(Commands::Dns { .. }) => {
+            let generator = DnsGenerator::new(cerberus.config());
+            if !generator.is_needed() {
+                error!("No deployment hosts configured; set dns.hosts");
+                std::process::exit(1);
+            }
+
+            info!("Checking DNS records...");
+            let report = DnsReport::new(generator.check());
+            match format.render(&report)? {
+                Some(rendered) => println!("{rendered}"),
+                None => {
+                    for check in &report.domains {
+                        if check.status == FindingStatus::Ok {
+                            info!("{}: {}", check.domain, check.message);
+                        } else {
+                            error!("{}: {}", check.domain, check.message);
+                        }
+                    }
+                }
+            }
+            if !report.valid {
+                std::process::exit(1);
+            }
+            info!("DNS matches the record plan");
+        }
+        Some(Commands::Graph { style }) => {
+            print!("{}", GraphGenerator::new(cerberus.config()).render(style));
+        }
+        Some(Commands::Config {
+            command: ConfigCommand::Show { effective },
+        }) => {
+            if effective {
+                print!(
+                    "{}",
+                    config::effective_toml(&config_path, cerberus.config())?
+                );
+            } else {
+                print!("{}", config::merged_toml(&config_path)?);
+            }
+        }
+        Some(Commands::Notify {
+            event,
+            title,
+            message,
+        }) => {
+            let event = match event.as_str() {
+                "scaling" => NotificationEvent::Scaling,
+                "cert_expiry" => NotificationEvent::CertExpiry,
+                _ => NotificationEvent::Deploy,
+            };
+            let notification = Notification::new(event, title, message);
+            let delivered = Notifier::new(cerberus.config()).send(&notification).await?;
+            info!("Notification delivered to {} channel(s)", delivered);
+        }
+        Some(Commands::Clean {
+            generated_only,
+            keep,
+            yes,
+        }) => {
             info!("Cleaning output directory...");
-            if output_dir.exists() {
-                tokio::fs::remove_dir_all(&output_dir).await?;
+            let options = CleanOptions {
+                generated_only,
+                keep,
+            };
+            let plan = CleanPlan::new(&output_dir, &options)?;
+            if !plan.unmanaged.is_empty() && !yes {
+                for path in &plan.unmanaged {
+                    warn!(
+                        "Not created by Cerberus: {}",
+                        output_dir.join(path).display()
+                    );
+                }
+                if !confirm(&format!(
+                    "Delete {} file(s) Cerberus did not create?",
+                    plan.unmanaged.len()
+                ))? {
+                    error!(
+                        "Clean aborted; rerun with --generated-only or --keep to leave those \
+                         files, or --yes to delete them"
+                    );
+                    std::process::exit(1);
+                }
+            }
+            let report = CleanReport::clean(&plan).await?;
+            if report.removed.is_empty() && report.kept.is_empty() {
+                info!("Output directory does not exist");
+            } else if report.kept.is_empty() {
                 info!("Output directory cleaned");
             } else {
-                info!("Output directory does not exist");
+                info!(
+                    "Removed {} path(s), kept {} file(s)",
+                    report.removed.len(),
+                    report.kept.len()
+                );
+            }
+            if let Some(rendered) = format.render(&report)? {
+                println!("{rendered}");
             }
         }
         _ => {
@@ -0,0 +1,352 @@
+//! # Alert notifications for Cerberus
+//!
+//! Forwards events (scaling, certificate expiry, deployments) to the channels
+//! configured under `[notifications.channels]`. `[notifications.routes]`
+//! decides which channels receive each event, so scaling alerts can go to an
+//! operations channel while certificate warnings go to a security channel.
+//!
+//! Requests are delivered with the `curl` binary, which speaks both HTTPS and
+//! SMTP; no channel-specific client is linked into Cerberus.
+
+use crate::{
+    CerberusError, Result,
+    config::{ChannelConfig, Config, NotificationEvent},
+};
+use serde_json::json;
+use std::process::Stdio;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+
+/// Seconds before a delivery attempt is abandoned
+const DELIVERY_TIMEOUT_SECS: &str = "30";
+
+/// An event to forward to the routed channels
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// Event the notification is routed by
+    pub event: NotificationEvent,
+    /// Short summary (email subject, first line of chat messages)
+    pub title: String,
+    /// Message body
+    pub message: String,
+    /// Unix time in milliseconds the event occurred
+    pub timestamp: u128,
+}
+
+impl Notification {
+    /// Create a notification for an event that just occurred
+    pub fn new(
+        event: NotificationEvent,
+        title: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            event,
+            title: title.into(),
+            message: message.into(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// A delivery request for one channel
+#[derive(Debug, Clone, PartialEq)]
+pub enum Delivery {
+    /// JSON request to an HTTP API
+    Http {
+        /// HTTP method
+        method: &'static str,
+        /// Request URL
+        url: String,
+        /// Extra request headers
+        headers: Vec<(String, String)>,
+        /// JSON body
+        body: serde_json::Value,
+    },
+    /// Email submitted to an SMTP server
+    Mail {
+        /// Server URL
+        url: String,
+        /// Sender address
+        from: String,
+        /// Recipient addresses
+        to: Vec<String>,
+        /// `user:password` credentials
+        credentials: Option<String>,
+        /// RFC 5322 message
+        message: String,
+    },
+}
+
+/// Routes notifications to the configured channels
+pub struct Notifier<'a> {
+    config: &'a Config,
+}
+
+impl<'a> Notifier<'a> {
+    /// Create a new notifier
+    pub fn new(config: &'a Config) -> Self {
+        Self { config }
+    }
+
+    /// Channels an event is routed to
+    pub fn channels_for(&self, event: NotificationEvent) -> &[String] {
+        self.config
+            .notifications
+            .routes
+            .get(&event)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Build the delivery request for each channel routed for a notification
+    ///
+    /// `env` resolves the environment variables named by the channels'
+    /// `*_env` fields.
+    ///
+    /// # Errors
+    /// Returns error if a channel's secret is not set
+    pub fn deliveries(
+        &self,
+        notification: &Notification,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Vec<(String, Delivery)>> {
+        let project = &self.config.project.name;
+        let title = format!("[{project}] {}", notification.title);
+        let text = format!("{title}\n{}", notification.message);
+        let secret = |channel: &str, var: &str| {
+            env(var).ok_or_else(|| {
+                CerberusError::notification(
+                    channel,
+                    format!("environment variable {var} is not set"),
+                )
+            })
+        };
+
+        let mut deliveries = Vec::new();
+        for name in self.channels_for(notification.event) {
+            let Some(channel) = self.config.notifications.channels.get(name) else {
+                continue;
+            };
+
+            let delivery = match channel {
+                ChannelConfig::Smtp {
+                    url,
+                    from,
+                    to,
+                    username,
+                    password_env,
+                } => {
+                    let credentials = match (username, password_env) {
+                        (Some(username), Some(var)) => {
+                            Some(format!("{username}:{}", secret(name, var)?))
+                        }
+                        (Some(username), None) => Some(format!("{username}:")),
+                        (None, _) => None,
+                    };
+                    Delivery::Mail {
+                        url: url.clone(),
+                        from: from.clone(),
+                        to: to.clone(),
+                        credentials,
+                        message: format!(
+                            "From: {from}\r\nTo: {}\r\nSubject: {title}\r\n\
+                             Content-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+                            to.join(", "),
+                            notification.message.replace('\n', "\r\n")
+                        ),
+                    }
+                }
+                ChannelConfig::Telegram {
+                    bot_token_env,
+                    chat_id,
+                } => Delivery::Http {
+                    method: "POST",
+                    url: format!(
+                        "https://api.telegram.org/bot{}/sendMessage",
+                        secret(name, bot_token_env)?
+                    ),
+                    headers: Vec::new(),
+                    body: json!({ "chat_id": chat_id, "text": text }),
+                },
+                ChannelConfig::Discord { webhook_url } => Delivery::Http {
+                    method: "POST",
+                    url: webhook_url.clone(),
+                    headers: Vec::new(),
+                    body: json!({ "content": text }),
+                },
+                ChannelConfig::Matrix {
+                    homeserver,
+                    room_id,
+                    access_token_env,
+                } => Delivery::Http {
+                    method: "PUT",
+                    url: format!(
+                        "{}/_matrix/client/v3/rooms/{}/send/m.room.message/cerberus-{}",
+                        homeserver.trim_end_matches('/'),
+                        encode_path_segment(room_id),
+                        notification.timestamp
+                    ),
+                    headers: vec![(
+                        "Authorization".to_string(),
+                        format!("Bearer {}", secret(name, access_token_env)?),
+                    )],
+                    body: json!({ "msgtype": "m.text", "body": text }),
+                },
+                ChannelConfig::Webhook { url, headers } => Delivery::Http {
+                    method: "POST",
+                    url: url.clone(),
+                    headers: headers
+                        .iter()
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect(),
+                    body: json!({
+                        "project": project,
+                        "event": notification.event.as_str(),
+                        "title": notification.title,
+                        "message": notification.message,
+                        "timestamp": notification.timestamp,
+                    }),
+                },
+            };
+            deliveries.push((name.clone(), delivery));
+        }
+
+        Ok(deliveries)
+    }
+
+    /// Send a notification to every routed channel
+    ///
+    /// Every channel is attempted even if an earlier one fails. Returns the
+    /// number of channels the notification was delivered to.
+    ///
+    /// # Errors
+    /// Returns the first delivery error once all channels were attempted
+    pub async fn send(&self, notification: &Notification) -> Result<usize> {
+        let mut delivered = 0;
+        let mut first_error = None;
+
+        let deliveries = self.deliveries(notification, |var| std::env::var(var).ok())?;
+        for (channel, delivery) in deliveries {
+            match deliver(&channel, &delivery).await {
+                Ok(()) => {
+                    tracing::info!(
+                        "Sent {} notification to {}",
+                        notification.event.as_str(),
+                        channel
+                    );
+                    delivered += 1;
+                }
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(delivered),
+        }
+    }
+}
+
+/// Percent-encode a URL path segment
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Deliver one request with curl, passing the payload on stdin
+async fn deliver(channel: &str, delivery: &Delivery) -> Result<()> {
+    let mut command = tokio::process::Command::new("curl");
+    command.args([
+        "--silent",
+        "--show-error",
+        "--fail",
+        "--max-time",
+        DELIVERY_TIMEOUT_SECS,
+    ]);
+
+    let payload = match delivery {
+        Delivery::Http {
+            method,
+            url,
+            headers,
+            body,
+        } => {
+            command
+                .args(["--request", method])
+                .args(["--header", "Content-Type: application/json"]);
+            for (key, value) in headers {
+                command.arg("--header").arg(format!("{key}: {value}"));
+            }
+            command.args(["--data-binary", "@-"]).arg(url);
+            body.to_string()
+        }
+        Delivery::Mail {
+            url,
+            from,
+            to,
+            credentials,
+            message,
+        } => {
+            command.arg("--url").arg(url).arg("--mail-from").arg(from);
+            for recipient in to {
+                command.arg("--mail-rcpt").arg(recipient);
+            }
+            if let Some(credentials) = credentials {
+                command.arg("--user").arg(credentials);
+            }
+            if url.starts_with("smtp://") {
+                command.arg("--ssl-reqd");
+            }
+            command.args(["--upload-file", "-"]);
+            message.clone()
+        }
+    };
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| CerberusError::notification(channel, format!("failed to run curl: {e}")))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(payload.as_bytes())
+            .await
+            .map_err(|e| CerberusError::notification(channel, e.to_string()))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| CerberusError::notification(channel, e.to_string()))?;
+    if !output.status.success() {
+        return Err(CerberusError::notification(
+            channel,
+            format!(
+                "delivery failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
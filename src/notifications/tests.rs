@@ -0,0 +1,195 @@
+//! # Tests for alert notifications
+//!
+//! These tests verify event routing and the requests built for each channel
+//! type; nothing is sent.
+
+use super::*;
+use crate::test_support::parse_config;
+use pretty_assertions::assert_eq;
+
+fn notification(event: NotificationEvent) -> Notification {
+    Notification {
+        event,
+        title: "Certificate expires soon".to_string(),
+        message: "app.example.com expires in 7 days".to_string(),
+        timestamp: 1_700_000_000_000,
+    }
+}
+
+fn env(var: &str) -> Option<String> {
+    match var {
+        "TG_TOKEN" => Some("123:abc".to_string()),
+        "MATRIX_TOKEN" => Some("syt_token".to_string()),
+        "SMTP_PASSWORD" => Some("hunter2".to_string()),
+        _ => None,
+    }
+}
+
+const CONFIG: &str = r#"
+[project]
+name = "notify"
+
+[notifications.channels.ops]
+type = "discord"
+webhook_url = "https://discord.com/api/webhooks/1/abc"
+
+[notifications.channels.chat]
+type = "telegram"
+bot_token_env = "TG_TOKEN"
+chat_id = "-10042"
+
+[notifications.channels.room]
+type = "matrix"
+homeserver = "https://matrix.example.org/"
+room_id = "!ops:example.org"
+access_token_env = "MATRIX_TOKEN"
+
+[notifications.channels.security]
+type = "smtp"
+url = "smtps://mail.example.com:465"
+from = "cerberus@example.com"
+to = ["sec@example.com", "oncall@example.com"]
+username = "cerberus"
+password_env = "SMTP_PASSWORD"
+
+[notifications.channels.hook]
+type = "webhook"
+url = "https://hooks.example.com/cerberus"
+headers = { "X-Token" = "abc" }
+
+[notifications.routes]
+scaling = ["ops"]
+cert_expiry = ["security", "chat", "room"]
+deploy = ["hook"]
+"#;
+
+#[test]
+fn test_events_routed_to_their_channels() {
+    let config = parse_config(CONFIG);
+    let notifier = Notifier::new(&config);
+
+    let channels = |event| {
+        notifier
+            .deliveries(&notification(event), env)
+            .expect("Deliveries should build")
+            .into_iter()
+            .map(|(channel, _)| channel)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(channels(NotificationEvent::Scaling), vec!["ops"]);
+    assert_eq!(
+        channels(NotificationEvent::CertExpiry),
+        vec!["security", "chat", "room"]
+    );
+    assert_eq!(channels(NotificationEvent::Deploy), vec!["hook"]);
+}
+
+#[test]
+fn test_unrouted_event_sends_nothing() {
+    let config = parse_config(
+        r#"
+[project]
+name = "notify"
+
+[notifications.channels.ops]
+type = "discord"
+webhook_url = "https://discord.com/api/webhooks/1/abc"
+"#,
+    );
+    let notifier = Notifier::new(&config);
+
+    let deliveries = notifier
+        .deliveries(&notification(NotificationEvent::Deploy), env)
+        .expect("Deliveries should build");
+    assert!(deliveries.is_empty());
+}
+
+#[test]
+fn test_channel_requests() {
+    let config = parse_config(CONFIG);
+    let notifier = Notifier::new(&config);
+    let deliveries = notifier
+        .deliveries(&notification(NotificationEvent::CertExpiry), env)
+        .expect("Deliveries should build");
+
+    assert_eq!(
+        deliveries[0].1,
+        Delivery::Mail {
+            url: "smtps://mail.example.com:465".to_string(),
+            from: "cerberus@example.com".to_string(),
+            to: vec![
+                "sec@example.com".to_string(),
+                "oncall@example.com".to_string()
+            ],
+            credentials: Some("cerberus:hunter2".to_string()),
+            message: "From: cerberus@example.com\r\nTo: sec@example.com, oncall@example.com\r\n\
+                      Subject: [notify] Certificate expires soon\r\n\
+                      Content-Type: text/plain; charset=utf-8\r\n\r\n\
+                      app.example.com expires in 7 days\r\n"
+                .to_string(),
+        }
+    );
+
+    assert_eq!(
+        deliveries[1].1,
+        Delivery::Http {
+            method: "POST",
+            url: "https://api.telegram.org/bot123:abc/sendMessage".to_string(),
+            headers: Vec::new(),
+            body: json!({
+                "chat_id": "-10042",
+                "text": "[notify] Certificate expires soon\napp.example.com expires in 7 days",
+            }),
+        }
+    );
+
+    let Delivery::Http {
+        method,
+        url,
+        headers,
+        ..
+    } = &deliveries[2].1
+    else {
+        panic!("Matrix should deliver over HTTP");
+    };
+    assert_eq!(*method, "PUT");
+    assert_eq!(
+        url,
+        "https://matrix.example.org/_matrix/client/v3/rooms/%21ops%3Aexample.org/send/m.room.message/cerberus-1700000000000"
+    );
+    assert_eq!(
+        headers,
+        &vec![("Authorization".to_string(), "Bearer syt_token".to_string())]
+    );
+}
+
+#[test]
+fn test_webhook_payload_carries_event() {
+    let config = parse_config(CONFIG);
+    let notifier = Notifier::new(&config);
+    let deliveries = notifier
+        .deliveries(&notification(NotificationEvent::Deploy), env)
+        .expect("Deliveries should build");
+
+    let Delivery::Http { headers, body, .. } = &deliveries[0].1 else {
+        panic!("Webhooks should deliver over HTTP");
+    };
+    assert_eq!(headers, &vec![("X-Token".to_string(), "abc".to_string())]);
+    assert_eq!(body["project"], "notify");
+    assert_eq!(body["event"], "deploy");
+    assert_eq!(body["timestamp"], 1_700_000_000_000u64);
+}
+
+#[test]
+fn test_missing_secret_is_an_error() {
+    let config = parse_config(CONFIG);
+    let notifier = Notifier::new(&config);
+
+    let error = notifier
+        .deliveries(&notification(NotificationEvent::CertExpiry), |_| None)
+        .expect_err("Missing secrets should be rejected")
+        .to_string();
+    assert!(error.contains("channel security"), "{error}");
+    assert!(error.contains("SMTP_PASSWORD is not set"), "{error}");
+}
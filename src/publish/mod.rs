@@ -0,0 +1,528 @@
+//! # Publishing generated bundles
+//!
+//! Pushes the files of the last generation to remote storage so edge nodes
+//! can pull a known-good bundle instead of generating locally. A bundle is
+//! exactly what the checksum manifest records, plus the manifest itself;
+//! files edited by hand since the generation are refused, and resolved
+//! secret values and logs are never published.
+//!
+//! Every publish lands under its own version, named by the UTC time and the
+//! manifest digest (`20261017T120000Z-3fa2c1d09b7e`). The pointer to the
+//! current version only moves once the whole bundle is uploaded, so a node
+//! pulling at any moment gets either the previous bundle or the new one:
+//!
+//! - S3 (`s3://bucket/prefix`): `aws s3 sync` into
+//!   `<prefix>/releases/<version>/`, then the `<prefix>/CURRENT` object is
+//!   overwritten with the version
+//! - SSH (`ssh://[user@]host[:port]/path`): `rsync` into
+//!   `<path>/releases/<version>/`, then the `<path>/current` symlink is
+//!   replaced with a rename
+//! - Git (`git+<url>[#branch]`): the bundle replaces the branch's tree in one
+//!   commit tagged `cerberus/<version>`; branch and tag are pushed with
+//!   `--atomic`
+
+use crate::{
+    CerberusError, Result,
+    checksum::sha256_hex,
+    generators::{MANIFEST_FILE, Manifest},
+};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Branch a `git+` target publishes to without a `#branch` fragment
+const DEFAULT_BRANCH: &str = "main";
+
+/// Prefix of the tag naming each version in a git target
+const TAG_PREFIX: &str = "cerberus/";
+
+/// Identity of publish commits when git has none configured
+const COMMIT_IDENTITY: [&str; 4] = [
+    "-c",
+    "user.name=Cerberus",
+    "-c",
+    "user.email=cerberus@localhost",
+];
+
+/// Remote storage a bundle is published to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublishTarget {
+    /// S3 bucket, through the AWS CLI
+    S3 {
+        /// Bucket name
+        bucket: String,
+        /// Key prefix without surrounding slashes; empty for the bucket root
+        prefix: String,
+    },
+    /// Directory on a host reachable over SSH, through rsync
+    Ssh {
+        /// `host` or `user@host`
+        host: String,
+        /// SSH port, if not the default
+        port: Option<u16>,
+        /// Absolute directory on the host
+        path: String,
+    },
+    /// Branch of a git repository
+    Git {
+        /// Repository URL as git understands it
+        url: String,
+        /// Branch the bundle is committed to
+        branch: String,
+    },
+}
+
+impl FromStr for PublishTarget {
+    type Err = CerberusError;
+
+    fn from_str(target: &str) -> Result<Self> {
+        let invalid = |reason: &str| CerberusError::publish(target, reason);
+
+        if let Some(rest) = target.strip_prefix("s3://") {
+            let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+            if bucket.is_empty() {
+                return Err(invalid("no bucket; expected s3://bucket/prefix"));
+            }
+            return Ok(Self::S3 {
+                bucket: bucket.to_string(),
+                prefix: prefix.trim_matches('/').to_string(),
+            });
+        }
+
+        if let Some(rest) = target.strip_prefix("ssh://") {
+            let Some((authority, path)) = rest.split_once('/') else {
+                return Err(invalid("no path; expected ssh://[user@]host[:port]/path"));
+            };
+            let (host, port) = match authority.rsplit_once(':') {
+                Some((host, port)) => (
+                    host,
+                    Some(
+                        port.parse::<u16>()
+                            .map_err(|_| invalid(&format!("invalid port `{port}`")))?,
+                    ),
+                ),
+                None => (authority, None),
+            };
+            let path = path.trim_end_matches('/');
+            if host.is_empty() || path.is_empty() {
+                return Err(invalid(
+                    "no host or path; expected ssh://[user@]host[:port]/path",
+                ));
+            }
+            return Ok(Self::Ssh {
+                host: host.to_string(),
+                port,
+                path: format!("/{path}"),
+            });
+        }
+
+        if let Some(rest) = target.strip_prefix("git+") {
+            let (url, branch) = rest.split_once('#').unwrap_or((rest, DEFAULT_BRANCH));
+            if url.is_empty() || branch.is_empty() {
+                return Err(invalid(
+                    "no repository or branch; expected git+<url>[#branch]",
+                ));
+            }
+            return Ok(Self::Git {
+                url: url.to_string(),
+                branch: branch.to_string(),
+            });
+        }
+
+        Err(invalid(
+            "unknown scheme; expected s3://, ssh:// or git+<url>",
+        ))
+    }
+}
+
+impl fmt::Display for PublishTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::S3 { bucket, prefix } if prefix.is_empty() => write!(f, "s3://{bucket}"),
+            Self::S3 { bucket, prefix } => write!(f, "s3://{bucket}/{prefix}"),
+            Self::Ssh {
+                host,
+                port: Some(port),
+                path,
+            } => write!(f, "ssh://{host}:{port}{path}"),
+            Self::Ssh { host, path, .. } => write!(f, "ssh://{host}{path}"),
+            Self::Git { url, branch } => write!(f, "git+{url}#{branch}"),
+        }
+    }
+}
+
+impl PublishTarget {
+    /// Where a version of the bundle is stored
+    pub fn location(&self, version: &str) -> String {
+        match self {
+            Self::S3 { bucket, prefix } => {
+                format!("s3://{bucket}/{}releases/{version}/", key_prefix(prefix))
+            }
+            Self::Ssh { host, path, .. } => format!("{host}:{path}/releases/{version}/"),
+            Self::Git { url, .. } => format!("{url} {TAG_PREFIX}{version}"),
+        }
+    }
+}
+
+/// Files of the last generation in an output directory, ready to publish
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bundle {
+    /// The output directory
+    pub dir: PathBuf,
+    /// Version the bundle is published as
+    pub version: String,
+    /// Files relative to the output directory, the manifest included
+    pub files: Vec<PathBuf>,
+}
+
+impl Bundle {
+    /// Collect the files recorded in an output directory's manifest
+    ///
+    /// `created` (seconds since the Unix epoch) dates the version.
+    ///
+    /// # Errors
+    /// Returns error if the directory has no manifest, or if a recorded file
+    /// is missing or was edited since it was generated
+    pub fn new(dir: &Path, created: u64) -> Result<Self> {
+        let Some(manifest) = Manifest::load(dir)? else {
+            return Err(CerberusError::validation(format!(
+                "{} has no {MANIFEST_FILE}; run `cerberus generate` first",
+                dir.display()
+            )));
+        };
+
+        let missing: Vec<String> = manifest
+            .files
+            .keys()
+            .filter(|path| !dir.join(path).exists())
+            .map(|path| path.display().to_string())
+            .collect();
+        if !missing.is_empty() {
+            return Err(CerberusError::validation(format!(
+                "generated files are missing from {}: {}; run `cerberus generate` again",
+                dir.display(),
+                missing.join(", ")
+            )));
+        }
+        let modified = manifest.modified_files(dir)?;
+        if !modified.is_empty() {
+            let modified: Vec<String> = modified
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            return Err(CerberusError::validation(format!(
+                "generated files were edited by hand: {}; only a generation can be published",
+                modified.join(", ")
+            )));
+        }
+
+        let digest = sha256_hex(&serde_json::to_vec(&manifest)?);
+        let mut files: Vec<PathBuf> = manifest.files.into_keys().collect();
+        files.push(PathBuf::from(MANIFEST_FILE));
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            version: format!("{}-{}", utc_timestamp(created), &digest[..12]),
+            files,
+        })
+    }
+
+    /// Copy the bundle's files into an empty directory, keeping their modes
+    ///
+    /// # Errors
+    /// Returns error if a file cannot be copied
+    pub fn stage(&self, staging: &Path) -> Result<()> {
+        for path in &self.files {
+            let target = staging.join(path);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| CerberusError::io(parent, e))?;
+            }
+            let source = self.dir.join(path);
+            std::fs::copy(&source, &target).map_err(|e| CerberusError::io(&source, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of `publish`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PublishReport {
+    /// Target the bundle was published to
+    pub target: String,
+    /// Version the bundle was published as
+    pub version: String,
+    /// Where the version is stored
+    pub location: String,
+    /// Files in the bundle, the manifest included
+    pub files: usize,
+}
+
+/// Publish a bundle and point the target's current version at it
+///
+/// # Errors
+/// Returns error if staging fails or a tool (`aws`, `ssh`, `rsync`, `git`)
+/// fails; the current version then still points at the previous bundle
+pub async fn publish(bundle: &Bundle, target: &PublishTarget) -> Result<PublishReport> {
+    let work = std::env::temp_dir().join(format!(
+        "cerberus-publish-{}-{}",
+        std::process::id(),
+        bundle.version
+    ));
+    let result = async {
+        let staging = work.join("bundle");
+        bundle.stage(&staging)?;
+        match target {
+            PublishTarget::S3 { bucket, prefix } => {
+                publish_s3(target, bucket, prefix, &work, &bundle.version).await
+            }
+            PublishTarget::Ssh { host, port, path } => {
+                publish_ssh(target, host, *port, path, &staging, &bundle.version).await
+            }
+            PublishTarget::Git { url, branch } => {
+                publish_git(target, url, branch, &work, bundle).await
+            }
+        }
+    }
+    .await;
+    let _ = std::fs::remove_dir_all(&work);
+    result?;
+
+    Ok(PublishReport {
+        target: target.to_string(),
+        version: bundle.version.clone(),
+        location: target.location(&bundle.version),
+        files: bundle.files.len(),
+    })
+}
+
+/// Sync the release prefix, then overwrite the `CURRENT` object
+async fn publish_s3(
+    target: &PublishTarget,
+    bucket: &str,
+    prefix: &str,
+    work: &Path,
+    version: &str,
+) -> Result<()> {
+    let staging = work.join("bundle");
+    let pointer = work.join("CURRENT");
+    std::fs::write(&pointer, format!("{version}\n")).map_err(|e| CerberusError::io(&pointer, e))?;
+
+    let root = format!("s3://{bucket}/{}", key_prefix(prefix));
+    run(
+        target,
+        None,
+        "aws",
+        &[
+            "s3",
+            "sync",
+            "--no-progress",
+            &staging.to_string_lossy(),
+            &target.location(version),
+        ],
+    )
+    .await?;
+    run(
+        target,
+        None,
+        "aws",
+        &[
+            "s3",
+            "cp",
+            "--no-progress",
+            &pointer.to_string_lossy(),
+            &format!("{root}CURRENT"),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Rsync into a new release directory, then swap the `current` symlink
+async fn publish_ssh(
+    target: &PublishTarget,
+    host: &str,
+    port: Option<u16>,
+    path: &str,
+    staging: &Path,
+    version: &str,
+) -> Result<()> {
+    let ssh: Vec<String> = match port {
+        Some(port) => vec!["-p".to_string(), port.to_string()],
+        None => Vec::new(),
+    };
+    let remote = |script: String| {
+        let mut args = ssh.clone();
+        args.extend([host.to_string(), script]);
+        args
+    };
+    let release = format!("{path}/releases/{version}");
+
+    let args = remote(format!("mkdir -p {}", shell_quote(&release)));
+    run(target, None, "ssh", &strs(&args)).await?;
+
+    let mut args = vec!["-a".to_string()];
+    if !ssh.is_empty() {
+        args.extend(["-e".to_string(), format!("ssh {}", ssh.join(" "))]);
+    }
+    args.extend([
+        format!("{}/", staging.to_string_lossy()),
+        format!("{host}:{release}/"),
+    ]);
+    run(target, None, "rsync", &strs(&args)).await?;
+
+    // rename(2) replaces the old link in one step
+    let link = format!("{path}/current");
+    let args = remote(format!(
+        "ln -sfn {} {} && mv -T {} {}",
+        shell_quote(&format!("releases/{version}")),
+        shell_quote(&format!("{link}.tmp")),
+        shell_quote(&format!("{link}.tmp")),
+        shell_quote(&link)
+    ));
+    run(target, None, "ssh", &strs(&args)).await?;
+    Ok(())
+}
+
+/// Commit the bundle as the branch's whole tree, tag it, and push both
+async fn publish_git(
+    target: &PublishTarget,
+    url: &str,
+    branch: &str,
+    work: &Path,
+    bundle: &Bundle,
+) -> Result<()> {
+    let version = &bundle.version;
+    let checkout = work.join("checkout");
+    let checkout_arg = checkout.to_string_lossy();
+    run(target, None, "git", &["init", "-q", &checkout_arg]).await?;
+
+    let git = Some(checkout.as_path());
+    // A branch that does not exist yet starts without history
+    if run(
+        target,
+        git,
+        "git",
+        &["fetch", "-q", "--depth", "1", url, branch],
+    )
+    .await
+    .is_ok()
+    {
+        run(
+            target,
+            git,
+            "git",
+            &["checkout", "-q", "-B", branch, "FETCH_HEAD"],
+        )
+        .await?;
+    } else {
+        let head = format!("refs/heads/{branch}");
+        run(target, git, "git", &["symbolic-ref", "HEAD", &head]).await?;
+    }
+    run(
+        target,
+        git,
+        "git",
+        &["rm", "-rq", "--ignore-unmatch", "--", "."],
+    )
+    .await?;
+
+    bundle.stage(&checkout)?;
+    run(target, git, "git", &["add", "-A"]).await?;
+
+    let mut commit: Vec<&str> = Vec::new();
+    if run(target, git, "git", &["config", "user.email"])
+        .await
+        .is_err()
+    {
+        commit.extend(COMMIT_IDENTITY);
+    }
+    let message = format!("Publish {version}");
+    commit.extend(["commit", "-q", "--allow-empty", "-m", &message]);
+    run(target, git, "git", &commit).await?;
+
+    let tag = format!("{TAG_PREFIX}{version}");
+    run(target, git, "git", &["tag", &tag]).await?;
+    let branch_ref = format!("HEAD:refs/heads/{branch}");
+    let tag_ref = format!("refs/tags/{tag}");
+    run(
+        target,
+        git,
+        "git",
+        &["push", "-q", "--atomic", url, &branch_ref, &tag_ref],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Run a tool, failing with its stderr
+async fn run(
+    target: &PublishTarget,
+    dir: Option<&Path>,
+    program: &str,
+    args: &[&str],
+) -> Result<String> {
+    let mut command = tokio::process::Command::new(program);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command.args(args).output().await.map_err(|e| {
+        CerberusError::publish(target.to_string(), format!("failed to run {program}: {e}"))
+    })?;
+    if !output.status.success() {
+        return Err(CerberusError::publish(
+            target.to_string(),
+            format!(
+                "`{program} {}` exited with {}: {}",
+                args.join(" "),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn strs(args: &[String]) -> Vec<&str> {
+    args.iter().map(String::as_str).collect()
+}
+
+/// S3 key prefix with its trailing slash, or nothing for the bucket root
+fn key_prefix(prefix: &str) -> String {
+    if prefix.is_empty() {
+        String::new()
+    } else {
+        format!("{prefix}/")
+    }
+}
+
+/// Quote a word for a POSIX shell on the remote host
+fn shell_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+/// `YYYYMMDDTHHMMSSZ` for seconds since the Unix epoch
+pub fn utc_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        time / 3_600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,225 @@
+//! Tests for publishing generated bundles
+
+use super::*;
+use crate::generators::snapshot;
+use pretty_assertions::assert_eq;
+use tempfile::TempDir;
+
+fn write(dir: &Path, path: &str, content: &str) {
+    let path = dir.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+/// Helper function to lay out a generated output directory with its manifest
+fn generated(dir: &Path) {
+    write(dir, "docker-compose.yaml", "services: {}\n");
+    write(dir, "proxy-configs/edge/Caddyfile", ":80 {}\n");
+    let files = snapshot(dir).unwrap();
+    Manifest::from_files(&files).write(dir).unwrap();
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .expect("git should run");
+    assert!(
+        output.status.success(),
+        "git {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn test_parse_publish_targets() {
+    assert_eq!(
+        "s3://bundles/prod/edge/".parse::<PublishTarget>().unwrap(),
+        PublishTarget::S3 {
+            bucket: "bundles".to_string(),
+            prefix: "prod/edge".to_string(),
+        }
+    );
+    assert_eq!(
+        "ssh://deploy@edge-1.example.com:2222/srv/cerberus/"
+            .parse::<PublishTarget>()
+            .unwrap(),
+        PublishTarget::Ssh {
+            host: "deploy@edge-1.example.com".to_string(),
+            port: Some(2222),
+            path: "/srv/cerberus".to_string(),
+        }
+    );
+    assert_eq!(
+        "git+ssh://git@example.com/ops/bundles.git#prod"
+            .parse::<PublishTarget>()
+            .unwrap(),
+        PublishTarget::Git {
+            url: "ssh://git@example.com/ops/bundles.git".to_string(),
+            branch: "prod".to_string(),
+        }
+    );
+
+    for target in [
+        "s3://bundles/prod/edge",
+        "s3://bundles",
+        "ssh://edge-1:2222/srv/cerberus",
+        "git+https://example.com/ops/bundles.git#main",
+    ] {
+        assert_eq!(target.parse::<PublishTarget>().unwrap().to_string(), target);
+    }
+
+    for invalid in [
+        "s3://",
+        "ssh://edge-1",
+        "ssh://edge-1:ssh/srv",
+        "git+#main",
+        "https://example.com/bundles",
+    ] {
+        assert!(
+            invalid.parse::<PublishTarget>().is_err(),
+            "{invalid} should be rejected"
+        );
+    }
+}
+
+#[test]
+fn test_release_locations() {
+    let version = "20231114T221320Z-0123456789ab";
+    let location = |target: &str| target.parse::<PublishTarget>().unwrap().location(version);
+
+    assert_eq!(
+        location("s3://bundles/prod"),
+        "s3://bundles/prod/releases/20231114T221320Z-0123456789ab/"
+    );
+    assert_eq!(
+        location("s3://bundles"),
+        "s3://bundles/releases/20231114T221320Z-0123456789ab/"
+    );
+    assert_eq!(
+        location("ssh://edge-1/srv/cerberus"),
+        "edge-1:/srv/cerberus/releases/20231114T221320Z-0123456789ab/"
+    );
+}
+
+#[test]
+fn test_utc_timestamp() {
+    assert_eq!(utc_timestamp(0), "19700101T000000Z");
+    assert_eq!(utc_timestamp(1_700_000_000), "20231114T221320Z");
+    assert_eq!(utc_timestamp(1_709_164_800), "20240229T000000Z");
+}
+
+#[test]
+fn test_bundle_holds_the_last_generation() {
+    let output = TempDir::new().unwrap();
+    generated(output.path());
+    // Runtime data and secrets are not part of the generation
+    write(output.path(), "built/logs/access.log", "GET /\n");
+    write(output.path(), "secrets/web.env", "TOKEN=hunter2\n");
+
+    let bundle = Bundle::new(output.path(), 1_700_000_000).unwrap();
+    assert_eq!(
+        bundle.files,
+        vec![
+            PathBuf::from("docker-compose.yaml"),
+            PathBuf::from("proxy-configs/edge/Caddyfile"),
+            PathBuf::from(MANIFEST_FILE),
+        ]
+    );
+    assert!(bundle.version.starts_with("20231114T221320Z-"));
+
+    // The same files get the same digest; other files another
+    let again = Bundle::new(output.path(), 1_700_000_000).unwrap();
+    assert_eq!(again.version, bundle.version);
+    let other = TempDir::new().unwrap();
+    write(other.path(), "docker-compose.yaml", "services: {web: {}}\n");
+    Manifest::from_files(&snapshot(other.path()).unwrap())
+        .write(other.path())
+        .unwrap();
+    assert_ne!(
+        Bundle::new(other.path(), 1_700_000_000).unwrap().version,
+        bundle.version
+    );
+
+    let staging = TempDir::new().unwrap();
+    bundle.stage(staging.path()).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(staging.path().join("proxy-configs/edge/Caddyfile")).unwrap(),
+        ":80 {}\n"
+    );
+    assert!(staging.path().join(MANIFEST_FILE).exists());
+    assert!(!staging.path().join("secrets").exists());
+    assert!(!staging.path().join("built").exists());
+}
+
+#[test]
+fn test_bundle_rejects_an_unknown_or_edited_generation() {
+    let output = TempDir::new().unwrap();
+    let error = Bundle::new(output.path(), 0).unwrap_err().to_string();
+    assert!(error.contains("cerberus generate"), "{error}");
+
+    generated(output.path());
+    write(
+        output.path(),
+        "docker-compose.yaml",
+        "services: {edited: {}}\n",
+    );
+    let error = Bundle::new(output.path(), 0).unwrap_err().to_string();
+    assert!(error.contains("docker-compose.yaml"), "{error}");
+
+    std::fs::remove_file(output.path().join("docker-compose.yaml")).unwrap();
+    let error = Bundle::new(output.path(), 0).unwrap_err().to_string();
+    assert!(error.contains("missing"), "{error}");
+}
+
+#[tokio::test]
+async fn test_publish_to_git_branch() {
+    let remote = TempDir::new().unwrap();
+    git(remote.path(), &["init", "-q", "--bare"]);
+    let target: PublishTarget = format!("git+file://{}#edge", remote.path().display())
+        .parse()
+        .unwrap();
+
+    let output = TempDir::new().unwrap();
+    generated(output.path());
+    let first = Bundle::new(output.path(), 1_700_000_000).unwrap();
+    let report = publish(&first, &target).await.unwrap();
+    assert_eq!(report.version, first.version);
+    assert_eq!(report.files, 3);
+
+    // A file the next generation drops disappears from the branch
+    std::fs::remove_dir_all(output.path().join("proxy-configs")).unwrap();
+    Manifest::from_files(&snapshot(output.path()).unwrap())
+        .write(output.path())
+        .unwrap();
+    let second = Bundle::new(output.path(), 1_700_000_060).unwrap();
+    publish(&second, &target).await.unwrap();
+
+    let tree = git(remote.path(), &["ls-tree", "-r", "--name-only", "edge"]);
+    assert_eq!(
+        tree.lines().collect::<Vec<_>>(),
+        vec![MANIFEST_FILE, "docker-compose.yaml"]
+    );
+    let tags = git(remote.path(), &["tag", "--list"]);
+    assert_eq!(
+        tags.lines().collect::<Vec<_>>(),
+        vec![
+            format!("{TAG_PREFIX}{}", first.version),
+            format!("{TAG_PREFIX}{}", second.version),
+        ]
+    );
+    // Each earlier version stays reachable by its tag
+    let first_tree = git(
+        remote.path(),
+        &[
+            "ls-tree",
+            "-r",
+            "--name-only",
+            &format!("{TAG_PREFIX}{}", first.version),
+        ],
+    );
+    assert!(first_tree.contains("proxy-configs/edge/Caddyfile"));
+}
@@ -0,0 +1,448 @@
+//! # Live reload of running proxies
+//!
+//! Loads a new generation into running proxy containers. Generating
+//! replaces the output directory, so the bind mounts of a running container
+//! keep showing the files it started with.
+//!
+//! The generated stack mounts proxy configuration read-only, so a proxy
+//! cannot rewrite its own files on the host. Such a container is restarted,
+//! which binds the new generation, after the proxy's syntax check passed on
+//! the new files in a throwaway container of the same image; a generation
+//! that fails it leaves the running container alone.
+//!
+//! A container whose configuration is mounted writable reloads in place:
+//! the proxy's generated files are copied into it with `docker exec`, files
+//! the new generation no longer has are removed, and a copy that fails the
+//! syntax check is rolled back before the proxy is told anything. Each proxy
+//! type reloads the way it supports:
+//!
+//! - Nginx: `nginx -s reload`
+//! - HAProxy: the master process re-executes on its reload signal, as the
+//!   master CLI's `reload` command does
+//! - Caddy: `caddy reload` through the admin API on the container's loopback
+//! - Traefik: the file provider picks up the dynamic files by itself; a
+//!   changed static `traefik.yml` restarts the container
+//! - Varnish: `varnishreload`
+
+use crate::{
+    CerberusError, Result,
+    config::{Config, ProxyConfig, ProxyType},
+    status::{self, StackStatus},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+
+/// Generated proxy configurations, relative to the output directory
+const PROXY_CONFIGS_DIR: &str = "proxy-configs";
+
+/// Draining entrypoint, mounted on its own and only read at start
+const ENTRYPOINT_FILE: &str = "entrypoint.sh";
+
+/// Write stdin to the file named by `$1`, creating its directory
+const WRITE_SCRIPT: &str = r#"mkdir -p "${1%/*}" && cat > "$1""#;
+
+/// How a running proxy loads new configuration
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReloadMethod {
+    /// Run a shell command in the container
+    Exec(String),
+    /// Send a signal to the container's main process
+    Signal(&'static str),
+    /// The proxy watches its files; a changed static file needs a restart
+    Watch {
+        /// Static configuration in the container, read only at start
+        static_file: String,
+    },
+    /// Restart the container, which binds the new generation again
+    Restart,
+}
+
+impl ReloadMethod {
+    /// How a proxy reloads in place
+    pub fn of(proxy: &ProxyConfig) -> Self {
+        let dir = proxy.proxy_type.config_dir();
+        match proxy.proxy_type {
+            ProxyType::Nginx => Self::Exec("nginx -s reload".to_string()),
+            // The master process re-executes, as the master CLI's `reload` does
+            ProxyType::HaProxy => Self::Signal(proxy.reload_signal().unwrap_or("USR2")),
+            ProxyType::Caddy => Self::Exec(format!(
+                "caddy reload --config {dir}/Caddyfile --adapter caddyfile"
+            )),
+            ProxyType::Traefik => Self::Watch {
+                static_file: format!("{dir}/traefik.yml"),
+            },
+            ProxyType::Varnish => Self::Exec("varnishreload".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ReloadMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exec(command) => write!(f, "{command}"),
+            Self::Signal(signal) => write!(f, "SIG{signal}"),
+            Self::Watch { .. } => write!(f, "file provider"),
+            Self::Restart => write!(f, "restart"),
+        }
+    }
+}
+
+/// Generated file and where it goes in the container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFile {
+    /// Path in the container
+    pub target: String,
+    /// File content
+    pub content: Vec<u8>,
+}
+
+/// What reloading one proxy takes
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReloadPlan {
+    /// Proxy name
+    pub proxy: String,
+    /// Directory in the container that holds only generated files
+    pub managed_dir: String,
+    /// Files copied into the container
+    pub files: Vec<ConfigFile>,
+    /// Shell command that checks the copied files
+    pub check: Option<String>,
+    /// How the proxy loads them
+    pub method: ReloadMethod,
+}
+
+impl ReloadPlan {
+    /// Plan the reload of a proxy from the files of a generation
+    ///
+    /// `files` holds file contents keyed by their path relative to the
+    /// output directory, as returned by [`crate::watch::snapshot`].
+    ///
+    /// # Errors
+    /// Returns error if the generation has no configuration for the proxy
+    pub fn new(proxy: &ProxyConfig, files: &BTreeMap<PathBuf, Vec<u8>>) -> Result<Self> {
+        let root = Path::new(PROXY_CONFIGS_DIR).join(&proxy.name);
+        let config_dir = proxy.proxy_type.config_dir();
+        let copied: Vec<ConfigFile> = files
+            .iter()
+            .filter_map(|(path, content)| {
+                let relative = path.strip_prefix(&root).ok()?;
+                (relative != Path::new(ENTRYPOINT_FILE)).then(|| ConfigFile {
+                    target: format!("{config_dir}/{}", relative.display()),
+                    content: content.clone(),
+                })
+            })
+            .collect();
+        if copied.is_empty() {
+            return Err(CerberusError::proxy_config(
+                &proxy.name,
+                format!(
+                    "no generated configuration in {}; run `cerberus generate` first",
+                    root.display()
+                ),
+            ));
+        }
+
+        // Nginx keeps the image's own files next to the mounted conf.d
+        let managed_dir = match proxy.proxy_type {
+            ProxyType::Nginx => format!("{config_dir}/conf.d"),
+            _ => config_dir.to_string(),
+        };
+
+        Ok(Self {
+            proxy: proxy.name.clone(),
+            managed_dir,
+            files: copied,
+            check: proxy.proxy_type.check_command(),
+            method: ReloadMethod::of(proxy),
+        })
+    }
+
+    /// Load the files into a running container
+    ///
+    /// Returns how the proxy loaded them: [`ReloadMethod::Restart`] when a
+    /// file lies on a read-only mount, the plan's method otherwise.
+    ///
+    /// # Errors
+    /// Returns error if Docker fails, or if the new files fail the check;
+    /// the container then keeps running its previous configuration
+    pub async fn apply(&self, container: &str) -> Result<ReloadMethod> {
+        let mounts = mounts(container).await?;
+        if self
+            .files
+            .iter()
+            .any(|file| read_only(&mounts, &file.target))
+        {
+            self.restart(container).await?;
+            return Ok(ReloadMethod::Restart);
+        }
+        self.copy(container).await?;
+        Ok(self.method.clone())
+    }
+
+    /// Check the new generation in a throwaway container, then restart
+    ///
+    /// The check container takes the mounts and network of the running one,
+    /// so it sees the files the output directory holds now and resolves the
+    /// same upstream names.
+    async fn restart(&self, container: &str) -> Result<()> {
+        if let Some(check) = &self.check {
+            let image =
+                status::docker(&["inspect", "--format", "{{.Config.Image}}", container]).await?;
+            let network = format!("container:{container}");
+            let args = [
+                "run",
+                "--rm",
+                "--volumes-from",
+                container,
+                "--network",
+                &network,
+                "--entrypoint",
+                "sh",
+                image.trim(),
+                "-c",
+                check,
+            ];
+            if let Err(e) = status::docker(&args).await {
+                return Err(CerberusError::proxy_config(
+                    &self.proxy,
+                    format!("the new configuration fails its check, {container} left running: {e}"),
+                ));
+            }
+        }
+        status::docker(&["restart", container]).await?;
+        Ok(())
+    }
+
+    /// Copy the files into a container with writable mounts and reload it
+    async fn copy(&self, container: &str) -> Result<()> {
+        // Files the copy replaces or removes, kept to roll a failed check back
+        let present = list_files(container, &self.managed_dir).await?;
+        let mut previous = BTreeMap::new();
+        for target in self.files.iter().map(|file| &file.target).chain(&present) {
+            let content = exec(container, &["cat", target], None).await.ok();
+            previous.insert(target.clone(), content);
+        }
+
+        for file in &self.files {
+            write_file(container, &file.target, &file.content).await?;
+        }
+        let stale: Vec<&str> = present
+            .iter()
+            .filter(|path| !self.files.iter().any(|file| &file.target == *path))
+            .map(String::as_str)
+            .collect();
+        if !stale.is_empty() {
+            exec(container, &[&["rm", "-f"], stale.as_slice()].concat(), None).await?;
+        }
+
+        if let Some(check) = &self.check
+            && let Err(e) = exec(container, &["sh", "-c", check], None).await
+        {
+            restore(container, &previous).await?;
+            return Err(CerberusError::proxy_config(
+                &self.proxy,
+                format!("the new configuration fails its check, previous files restored: {e}"),
+            ));
+        }
+
+        match &self.method {
+            ReloadMethod::Exec(command) => {
+                exec(container, &["sh", "-c", command], None).await?;
+            }
+            ReloadMethod::Signal(signal) => {
+                status::docker(&["kill", "--signal", signal, container]).await?;
+            }
+            ReloadMethod::Watch { static_file } => {
+                let next = self
+                    .files
+                    .iter()
+                    .find(|file| &file.target == static_file)
+                    .map(|file| &file.content);
+                if previous.get(static_file).and_then(Option::as_ref) != next {
+                    tracing::info!("{static_file} changed; restarting {container}");
+                    status::docker(&["restart", container]).await?;
+                }
+            }
+            ReloadMethod::Restart => {
+                status::docker(&["restart", container]).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Mount of a container, as reported by `docker inspect`
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct Mount {
+    /// Path in the container
+    #[serde(rename = "Destination")]
+    pub destination: String,
+    /// Whether the container can write to it
+    #[serde(rename = "RW")]
+    pub rw: bool,
+}
+
+/// Whether a path in the container lies on a read-only mount
+pub fn read_only(mounts: &[Mount], target: &str) -> bool {
+    mounts
+        .iter()
+        .filter(|mount| Path::new(target).starts_with(&mount.destination))
+        // The innermost mount decides
+        .max_by_key(|mount| mount.destination.len())
+        .is_some_and(|mount| !mount.rw)
+}
+
+/// Mounts of a container
+async fn mounts(container: &str) -> Result<Vec<Mount>> {
+    let output = status::docker(&["inspect", "--format", "{{json .Mounts}}", container]).await?;
+    serde_json::from_str(&output)
+        .map_err(|e| CerberusError::docker(format!("unexpected mounts of {container}: {e}")))
+}
+
+/// Container a proxy was reloaded in
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Reloaded {
+    /// Proxy name
+    pub proxy: String,
+    /// Container name
+    pub container: String,
+    /// How the proxy loaded the files, e.g. `nginx -s reload`
+    pub method: String,
+    /// Files copied into the container
+    pub files: usize,
+}
+
+/// Result of `reload`
+#[derive(Debug, Clone, Serialize, PartialEq, Default)]
+pub struct ReloadReport {
+    /// Containers reloaded, in proxy order
+    pub reloaded: Vec<Reloaded>,
+    /// Proxies without a running container
+    pub skipped: Vec<String>,
+}
+
+/// Reload proxies from the files generated into `output_dir`
+///
+/// Every running container of each proxy is reloaded; proxies without one
+/// are skipped.
+///
+/// # Errors
+/// Returns error at the first container that cannot be reloaded; later
+/// containers are left untouched
+pub async fn reload(
+    config: &Config,
+    output_dir: &Path,
+    proxies: &[&ProxyConfig],
+) -> Result<ReloadReport> {
+    let files = crate::watch::snapshot(output_dir)?;
+    let status = StackStatus::collect(output_dir).await?;
+
+    let mut report = ReloadReport::default();
+    for proxy in proxies {
+        let plan = ReloadPlan::new(proxy, &files)?;
+        let services = proxy.container_names(config.project.scaling);
+        let containers: Vec<&str> = status
+            .containers
+            .iter()
+            .filter(|container| services.contains(&container.service))
+            .filter(|container| container.state == "running")
+            .map(|container| container.name.as_str())
+            .collect();
+        if containers.is_empty() {
+            report.skipped.push(proxy.name.clone());
+            continue;
+        }
+
+        for container in containers {
+            let method = plan.apply(container).await?;
+            tracing::info!("Reloaded {} ({})", container, method);
+            report.reloaded.push(Reloaded {
+                proxy: proxy.name.clone(),
+                container: container.to_string(),
+                method: method.to_string(),
+                files: plan.files.len(),
+            });
+        }
+    }
+    Ok(report)
+}
+
+/// Regular files under a directory of a container, leaving nested mounts out
+async fn list_files(container: &str, dir: &str) -> Result<Vec<String>> {
+    let output = exec(container, &["find", dir, "-xdev", "-type", "f"], None).await?;
+    Ok(String::from_utf8_lossy(&output)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Replace a file in a container
+async fn write_file(container: &str, target: &str, content: &[u8]) -> Result<()> {
+    exec(
+        container,
+        &["sh", "-c", WRITE_SCRIPT, "sh", target],
+        Some(content),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Put back the files a container had before a copy
+async fn restore(container: &str, previous: &BTreeMap<String, Option<Vec<u8>>>) -> Result<()> {
+    for (target, content) in previous {
+        match content {
+            Some(content) => write_file(container, target, content).await?,
+            None => {
+                exec(container, &["rm", "-f", target], None).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run a command in a container as root, feeding it `input` on stdin
+async fn exec(container: &str, command: &[&str], input: Option<&[u8]>) -> Result<Vec<u8>> {
+    let mut child = tokio::process::Command::new("docker")
+        .args(["exec", "--user", "0"])
+        .args(input.map(|_| "--interactive"))
+        .arg(container)
+        .args(command)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| CerberusError::docker(format!("failed to run docker: {e}")))?;
+
+    if let (Some(content), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin
+            .write_all(content)
+            .await
+            .map_err(|e| CerberusError::docker(format!("failed to write to {container}: {e}")))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| CerberusError::docker(format!("failed to run docker: {e}")))?;
+    if !output.status.success() {
+        return Err(CerberusError::docker(format!(
+            "`{}` in {container} exited with {}: {}",
+            command.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,179 @@
+//! # Tests for live reload
+//!
+//! These tests verify which generated files are copied into a proxy's
+//! containers, where they go, and how each proxy type checks and reloads
+//! them.
+
+use super::*;
+use crate::Cerberus;
+use pretty_assertions::assert_eq;
+
+/// Helper function to render a stack with one proxy of every type
+///
+/// `extra` is appended to the configuration.
+fn render_stack(extra: &str) -> (Config, BTreeMap<PathBuf, Vec<u8>>) {
+    let config: Config = toml::from_str(&format!(
+        r#"
+[project]
+name = "reload-test"
+
+[[proxies]]
+name = "edge"
+type = "haproxy"
+external_port = 80
+default_upstream = "http://caddy:80"
+
+[[proxies]]
+name = "caddy"
+type = "caddy"
+layer = 2
+default_upstream = "http://nginx:80"
+
+[[proxies]]
+name = "nginx"
+type = "nginx"
+layer = 3
+default_upstream = "http://traefik:80"
+
+[[proxies]]
+name = "traefik"
+type = "traefik"
+layer = 4
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+{extra}
+"#
+    ))
+    .expect("Failed to parse config");
+    config.validate().expect("Config should be valid");
+
+    // An empty output directory keeps the rendered paths relative
+    let files = Cerberus::from_config(config.clone(), Path::new(""))
+        .generate_to_memory()
+        .expect("Rendering should succeed");
+    (config, files)
+}
+
+/// Helper function to plan the reload of a proxy
+fn plan(config: &Config, files: &BTreeMap<PathBuf, Vec<u8>>, name: &str) -> ReloadPlan {
+    let proxy = config
+        .proxies
+        .iter()
+        .find(|proxy| proxy.name == name)
+        .expect("Proxy should exist");
+    ReloadPlan::new(proxy, files).expect("Should plan")
+}
+
+/// Helper function to list the container paths a plan copies
+fn targets(plan: &ReloadPlan) -> Vec<&str> {
+    plan.files.iter().map(|file| file.target.as_str()).collect()
+}
+
+#[test]
+fn test_reload_plan_per_proxy_type() {
+    let (config, files) = render_stack("");
+
+    let edge = plan(&config, &files, "edge");
+    assert_eq!(targets(&edge), vec!["/usr/local/etc/haproxy/haproxy.cfg"]);
+    assert_eq!(edge.managed_dir, "/usr/local/etc/haproxy");
+    assert_eq!(
+        edge.check.as_deref(),
+        Some("haproxy -c -q -f /usr/local/etc/haproxy/haproxy.cfg")
+    );
+    assert_eq!(edge.method, ReloadMethod::Signal("USR2"));
+
+    let caddy = plan(&config, &files, "caddy");
+    assert_eq!(targets(&caddy), vec!["/etc/caddy/Caddyfile"]);
+    assert_eq!(
+        caddy.method,
+        ReloadMethod::Exec(
+            "caddy reload --config /etc/caddy/Caddyfile --adapter caddyfile".to_string()
+        )
+    );
+    // The admin API caddy reload talks to stays on the container's loopback
+    let caddyfile = String::from_utf8(caddy.files[0].content.clone()).unwrap();
+    assert!(caddyfile.contains("\tadmin localhost:2019\n"));
+
+    let nginx = plan(&config, &files, "nginx");
+    assert!(
+        targets(&nginx)
+            .iter()
+            .all(|target| target.starts_with("/etc/nginx/conf.d/"))
+    );
+    assert!(targets(&nginx).contains(&"/etc/nginx/conf.d/web_app.conf"));
+    assert_eq!(nginx.managed_dir, "/etc/nginx/conf.d");
+    assert_eq!(nginx.check.as_deref(), Some("nginx -t -q"));
+    assert_eq!(nginx.method.to_string(), "nginx -s reload");
+
+    let traefik = plan(&config, &files, "traefik");
+    assert!(targets(&traefik).contains(&"/etc/traefik/traefik.yml"));
+    assert!(targets(&traefik).contains(&"/etc/traefik/dynamic/web-app.yml"));
+    assert_eq!(traefik.check, None);
+    assert_eq!(
+        traefik.method,
+        ReloadMethod::Watch {
+            static_file: "/etc/traefik/traefik.yml".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_reload_plan_skips_drain_entrypoint() {
+    let (config, files) = render_stack(
+        r#"
+[proxies.drain]
+entrypoint = true
+"#,
+    );
+    // The table above belongs to the last proxy
+    let traefik = plan(&config, &files, "traefik");
+    assert!(
+        files.contains_key(Path::new("proxy-configs/traefik/entrypoint.sh")),
+        "the draining entrypoint should be generated"
+    );
+    assert!(
+        targets(&traefik)
+            .iter()
+            .all(|target| !target.ends_with(ENTRYPOINT_FILE))
+    );
+}
+
+#[test]
+fn test_reload_plan_requires_generated_files() {
+    let (config, _) = render_stack("");
+    let result = ReloadPlan::new(&config.proxies[0], &BTreeMap::new());
+
+    assert!(result.is_err());
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("run `cerberus generate` first")
+    );
+}
+
+#[test]
+fn test_read_only_mounts_restart() {
+    let mounts: Vec<Mount> = serde_json::from_str(
+        r#"[
+            {"Type": "bind", "Source": "/srv/built/proxy-configs/nginx/conf.d",
+             "Destination": "/etc/nginx/conf.d", "Mode": "", "RW": true},
+            {"Type": "bind", "Source": "/srv/built/proxy-configs/nginx/nginx.conf",
+             "Destination": "/etc/nginx/nginx.conf", "Mode": "ro", "RW": false},
+            {"Type": "bind", "Source": "/srv/built/proxy-configs/caddy",
+             "Destination": "/etc/caddy", "Mode": "ro", "RW": false}
+        ]"#,
+    )
+    .expect("Should parse docker inspect output");
+
+    assert!(!read_only(&mounts, "/etc/nginx/conf.d/web_app.conf"));
+    assert!(read_only(&mounts, "/etc/nginx/nginx.conf"));
+    assert!(read_only(&mounts, "/etc/caddy/Caddyfile"));
+    // A sibling path is not under the mount, and unmounted paths are writable
+    assert!(!read_only(&mounts, "/etc/caddy-extra/Caddyfile"));
+    assert!(!read_only(&mounts, "/usr/local/etc/haproxy/haproxy.cfg"));
+    assert_eq!(ReloadMethod::Restart.to_string(), "restart");
+}
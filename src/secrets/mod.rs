@@ -0,0 +1,416 @@
+//! # Secret resolution for Cerberus
+//!
+//! Docker Compose mounts secrets from files, environment variables, or
+//! external stores. Secrets whose value Cerberus produces itself are
+//! resolved when generating:
+//!
+//! - inline `content` and `command` secrets (such as
+//!   `op read op://vault/item/field`) are written to `secrets/` in the
+//!   output directory, which carries a `.gitignore`;
+//! - `vault://<path>#<key>` secrets and service environment values are read
+//!   from the Vault HTTP API at `$VAULT_ADDR` and written to `secrets/` too,
+//!   unless a vault-agent sidecar renders them instead (`--no-resolve`);
+//! - SOPS- and age-encrypted files are decrypted to tmpfs under
+//!   [`RUNTIME_DIR`] and never touch the output directory.
+//!
+//! The values never enter the rendered files or the checksum manifest.
+
+use crate::{
+    CerberusError, Result,
+    config::{Config, EncryptedFormat, SecretConfig, VaultRef},
+};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+
+pub use crate::generators::paths::{RUNTIME_DIR, SECRETS_DIR, decrypted_path, vault_env_file};
+
+/// Environment variable naming the age identity file
+///
+/// The variable SOPS itself reads, so one key serves both formats.
+pub const AGE_KEY_ENV: &str = "SOPS_AGE_KEY_FILE";
+
+/// Environment variable holding the Vault server address
+pub const VAULT_ADDR_ENV: &str = "VAULT_ADDR";
+
+/// Environment variable holding the Vault token
+///
+/// Without it, the token `vault login` stores in `~/.vault-token` is used.
+pub const VAULT_TOKEN_ENV: &str = "VAULT_TOKEN";
+
+/// Seconds before a Vault request is abandoned
+const VAULT_TIMEOUT_SECS: &str = "30";
+
+/// Resolve the value of a secret Cerberus writes into the output directory
+///
+/// Returns `None` for secrets Docker reads on its own (files, environment
+/// variables, and external secrets), for encrypted secrets, which are
+/// decrypted with [`decrypt`], and for Vault secrets, which are read with
+/// [`Vault`].
+///
+/// # Errors
+/// Returns error if the command fails or prints nothing
+pub async fn resolve(name: &str, secret: &SecretConfig) -> Result<Option<String>> {
+    match secret {
+        SecretConfig::Content { content } => Ok(Some(content.clone())),
+        SecretConfig::Exec { command } => run_command(name, command).await.map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Run a secret command through the shell and return its stdout
+async fn run_command(name: &str, command: &str) -> Result<String> {
+    let mut process = tokio::process::Command::new("sh");
+    process.args(["-c", command]);
+    let stdout = run(name, &mut process, "command").await?;
+
+    let value = String::from_utf8(stdout)
+        .map_err(|_| CerberusError::secret(name, "command printed invalid UTF-8"))?;
+    // Password manager CLIs end the value with a newline
+    let value = value
+        .strip_suffix('\n')
+        .map(|value| value.strip_suffix('\r').unwrap_or(value))
+        .unwrap_or(&value)
+        .to_string();
+    if value.is_empty() {
+        return Err(CerberusError::secret(name, "command printed nothing"));
+    }
+    Ok(value)
+}
+
+/// Decrypt a SOPS- or age-encrypted secret file
+///
+/// `age_key` is the age identity file; without it the [`AGE_KEY_ENV`]
+/// variable is used. SOPS falls back to its other key sources.
+///
+/// # Errors
+/// Returns error if no age key is available or decryption fails
+pub async fn decrypt(
+    name: &str,
+    file: &str,
+    format: EncryptedFormat,
+    age_key: Option<&Path>,
+) -> Result<Vec<u8>> {
+    let age_key = age_key
+        .map(Path::to_path_buf)
+        .or_else(|| std::env::var_os(AGE_KEY_ENV).map(PathBuf::from));
+
+    let (tool, mut process) = match format {
+        EncryptedFormat::Sops => {
+            let mut process = tokio::process::Command::new("sops");
+            process.args(["--decrypt", file]);
+            if let Some(key) = &age_key {
+                process.env(AGE_KEY_ENV, key);
+            }
+            ("sops", process)
+        }
+        EncryptedFormat::Age => {
+            let Some(key) = &age_key else {
+                return Err(CerberusError::secret(
+                    name,
+                    format!("no age identity; pass --age-key or set {AGE_KEY_ENV}"),
+                ));
+            };
+            let mut process = tokio::process::Command::new("age");
+            process.arg("--decrypt").arg("-i").arg(key).arg(file);
+            ("age", process)
+        }
+    };
+
+    run(name, &mut process, tool).await
+}
+
+/// Reads secrets from the Vault HTTP API with curl
+///
+/// Every path is requested once, however many fields are read from it.
+#[derive(Debug)]
+pub struct Vault {
+    address: String,
+    token: String,
+    cache: HashMap<String, serde_json::Value>,
+}
+
+impl Vault {
+    /// Connect with the address and token from the environment
+    ///
+    /// # Errors
+    /// Returns error if no address or token is available
+    pub fn from_env(name: &str) -> Result<Self> {
+        let address = std::env::var(VAULT_ADDR_ENV).map_err(|_| {
+            CerberusError::secret(
+                name,
+                format!("{VAULT_ADDR_ENV} is not set; export it or pass --no-resolve"),
+            )
+        })?;
+        let token = match std::env::var(VAULT_TOKEN_ENV) {
+            Ok(token) => token,
+            Err(_) => std::env::var_os("HOME")
+                .map(|home| Path::new(&home).join(".vault-token"))
+                .and_then(|path| std::fs::read_to_string(path).ok())
+                .ok_or_else(|| {
+                    CerberusError::secret(
+                        name,
+                        format!("no Vault token; set {VAULT_TOKEN_ENV} or run `vault login`"),
+                    )
+                })?,
+        };
+        Ok(Self::new(address, token.trim()))
+    }
+
+    /// Connect to a Vault server with a token
+    pub fn new(address: impl Into<String>, token: impl Into<String>) -> Self {
+        Self {
+            address: address.into().trim_end_matches('/').to_string(),
+            token: token.into(),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Read one field of a secret
+    ///
+    /// # Errors
+    /// Returns error if the request fails or the secret has no such field
+    pub async fn read(&mut self, name: &str, reference: &VaultRef) -> Result<String> {
+        if !self.cache.contains_key(&reference.path) {
+            let response = self.request(name, &reference.path).await?;
+            self.cache.insert(reference.path.clone(), response);
+        }
+        field(name, &self.cache[&reference.path], reference)
+    }
+
+    async fn request(&self, name: &str, path: &str) -> Result<serde_json::Value> {
+        let mut process = tokio::process::Command::new("curl");
+        process
+            .args([
+                "--silent",
+                "--show-error",
+                "--fail",
+                "--max-time",
+                VAULT_TIMEOUT_SECS,
+                // Headers come from stdin so the token stays out of `ps`
+                "--header",
+                "@-",
+            ])
+            .arg(format!("{}/v1/{path}", self.address))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut headers = format!("X-Vault-Token: {}\n", self.token);
+        if let Ok(namespace) = std::env::var("VAULT_NAMESPACE") {
+            headers.push_str(&format!("X-Vault-Namespace: {namespace}\n"));
+        }
+
+        let mut child = process
+            .spawn()
+            .map_err(|e| CerberusError::secret(name, format!("failed to run curl: {e}")))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(headers.as_bytes())
+                .await
+                .map_err(|e| CerberusError::secret(name, e.to_string()))?;
+        }
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|e| CerberusError::secret(name, e.to_string()))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(CerberusError::secret(
+                name,
+                format!("Vault request for {path} failed: {}", stderr.trim()),
+            ));
+        }
+
+        serde_json::from_slice(&output.stdout).map_err(|e| {
+            CerberusError::secret(name, format!("Vault returned invalid JSON for {path}: {e}"))
+        })
+    }
+}
+
+/// Pick a field out of a Vault read response
+///
+/// KV version 2 responses nest the fields under `data.data`.
+fn field(name: &str, response: &serde_json::Value, reference: &VaultRef) -> Result<String> {
+    let data = &response["data"];
+    let fields = if data["metadata"].is_object() {
+        &data["data"]
+    } else {
+        data
+    };
+    match &fields[&reference.key] {
+        serde_json::Value::String(value) => Ok(value.clone()),
+        serde_json::Value::Null => Err(CerberusError::secret(
+            name,
+            format!("{} has no field {}", reference.path, reference.key),
+        )),
+        value => Ok(value.to_string()),
+    }
+}
+
+/// Run a resolver process and return its stdout
+async fn run(name: &str, process: &mut tokio::process::Command, what: &str) -> Result<Vec<u8>> {
+    let output = process
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| CerberusError::secret(name, format!("failed to run {what}: {e}")))?;
+
+    // The command line is not echoed: it may carry tokens of its own
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(CerberusError::secret(
+            name,
+            format!("{what} exited with {}: {}", output.status, stderr.trim()),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Resolve every secret Cerberus provides the value of
+///
+/// Inline, command, and Vault secrets are written to `secrets/<name>` in the
+/// output directory, and the `vault://` environment values of each service
+/// to its [`vault_env_file`]. Encrypted secrets are decrypted to
+/// [`decrypted_path`]. Every file is readable only by the owner. With
+/// `vault_agent`, Vault is not contacted: the sidecar renders those files.
+/// Returns the number of files written.
+///
+/// # Errors
+/// Returns error if a secret cannot be resolved or written
+pub async fn write_secrets(
+    config: &Config,
+    output_dir: &Path,
+    age_key: Option<&Path>,
+    vault_agent: bool,
+) -> Result<usize> {
+    let mut secrets: Vec<(&String, &SecretConfig)> = config.secrets.iter().collect();
+    secrets.sort_by_key(|(name, _)| *name);
+    let dir = output_dir.join(SECRETS_DIR);
+    let mut vault: Option<Vault> = None;
+
+    let mut written = 0;
+    for (name, secret) in secrets {
+        let value = match secret {
+            SecretConfig::Encrypted { file, format } => {
+                let value = decrypt(name, file, *format, age_key).await?;
+                let path = decrypted_path(&config.project.name, name);
+                create_private_dir(path.parent().unwrap_or(Path::new(RUNTIME_DIR)))?;
+                write_private(&path, &value)?;
+                tracing::info!("Resolved secret {name}");
+                written += 1;
+                continue;
+            }
+            SecretConfig::Vault { .. } if vault_agent => {
+                create_secrets_dir(&dir)?;
+                continue;
+            }
+            SecretConfig::Vault { vault: uri } => {
+                let reference = vault_ref(name, uri)?;
+                connect(&mut vault, name)?.read(name, &reference).await?
+            }
+            _ => match resolve(name, secret).await? {
+                Some(value) => value,
+                None => continue,
+            },
+        };
+        create_secrets_dir(&dir)?;
+        write_private(&dir.join(name), value.as_bytes())?;
+        tracing::info!("Resolved secret {name}");
+        written += 1;
+    }
+
+    for service in &config.services {
+        let references = service
+            .vault_environment()
+            .map_err(|e| CerberusError::secret(&service.name, e.to_string()))?;
+        if references.is_empty() {
+            continue;
+        }
+        create_secrets_dir(&dir)?;
+        if vault_agent {
+            continue;
+        }
+
+        let mut values = BTreeMap::new();
+        for (key, reference) in references {
+            let name = format!("{} {key}", service.name);
+            let value = connect(&mut vault, &name)?.read(&name, &reference).await?;
+            values.insert(key, value);
+        }
+        let env: String = values
+            .iter()
+            .map(|(key, value)| format!("{key}={value}\n"))
+            .collect();
+        write_private(
+            &output_dir.join(vault_env_file(&service.name)),
+            env.as_bytes(),
+        )?;
+        tracing::info!("Resolved Vault environment for {}", service.name);
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// Parse the reference of a Vault secret
+fn vault_ref(name: &str, uri: &str) -> Result<VaultRef> {
+    match VaultRef::parse(uri) {
+        Ok(Some(reference)) => Ok(reference),
+        Ok(None) => Err(CerberusError::secret(name, "not a vault:// reference")),
+        Err(e) => Err(CerberusError::secret(name, e)),
+    }
+}
+
+/// Connect to Vault on first use
+fn connect<'a>(vault: &'a mut Option<Vault>, name: &str) -> Result<&'a mut Vault> {
+    match vault {
+        Some(vault) => Ok(vault),
+        None => Ok(vault.insert(Vault::from_env(name)?)),
+    }
+}
+
+/// Create the `secrets/` directory, kept out of version control
+fn create_secrets_dir(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir).map_err(|e| CerberusError::io(dir, e))?;
+    let gitignore = dir.join(".gitignore");
+    std::fs::write(&gitignore, "*\n").map_err(|e| CerberusError::io(&gitignore, e))
+}
+
+/// Create a directory only its owner can enter
+fn create_private_dir(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir).map_err(|e| CerberusError::io(dir, e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+            .map_err(|e| CerberusError::io(dir, e))?;
+    }
+    Ok(())
+}
+
+/// Write a file only its owner can read
+fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .map_err(|e| CerberusError::io(path, e))?;
+        file.write_all(content)
+            .map_err(|e| CerberusError::io(path, e))?;
+    }
+    #[cfg(not(unix))]
+    std::fs::write(path, content).map_err(|e| CerberusError::io(path, e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
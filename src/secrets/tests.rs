@@ -0,0 +1,284 @@
+//! # Tests for secret resolution
+//!
+//! These tests verify that command secrets are read from stdout, that
+//! failing commands are reported, how encrypted files are decrypted and
+//! Vault secrets read, and how resolved values are written and kept out of
+//! the rendered files.
+
+use super::*;
+use pretty_assertions::assert_eq;
+
+/// Helper function to build a command secret
+fn exec(command: &str) -> SecretConfig {
+    SecretConfig::Exec {
+        command: command.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_exec_secret_reads_stdout() {
+    let value = resolve("db_password", &exec("printf 'hunter2\\n'"))
+        .await
+        .expect("Command should succeed");
+    assert_eq!(value, Some("hunter2".to_string()));
+
+    // Docker reads file secrets itself
+    let file = SecretConfig::File {
+        file: "./db_password.txt".to_string(),
+    };
+    assert_eq!(resolve("db_password", &file).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_exec_secret_failures() {
+    let error = resolve("db_password", &exec("echo 'not signed in' >&2; exit 1"))
+        .await
+        .expect_err("A failing command should be rejected")
+        .to_string();
+    assert!(error.contains("Secret db_password"), "{error}");
+    assert!(error.contains("not signed in"), "{error}");
+
+    let error = resolve("db_password", &exec("true"))
+        .await
+        .expect_err("An empty value should be rejected")
+        .to_string();
+    assert!(error.contains("command printed nothing"), "{error}");
+}
+
+#[tokio::test]
+async fn test_write_secrets_keeps_values_private() {
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let config: Config = toml::from_str(
+        r#"
+[project]
+name = "secrets-test"
+
+[secrets.api_token]
+command = "echo s3cr3t"
+
+[secrets.external]
+external = true
+"#,
+    )
+    .expect("Failed to parse config");
+
+    let written = write_secrets(&config, temp_dir.path(), None, false)
+        .await
+        .expect("Secrets should be written");
+    assert_eq!(written, 1);
+
+    let dir = temp_dir.path().join(SECRETS_DIR);
+    assert_eq!(
+        std::fs::read_to_string(dir.join("api_token")).unwrap(),
+        "s3cr3t"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join(".gitignore")).unwrap(),
+        "*\n"
+    );
+    assert!(!dir.join("external").exists());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(dir.join("api_token"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn test_decrypted_path_is_per_project() {
+    assert_eq!(
+        decrypted_path("my-stack", "db_password"),
+        PathBuf::from("/dev/shm/cerberus/my-stack/db_password")
+    );
+}
+
+#[tokio::test]
+async fn test_age_decrypt_requires_identity() {
+    // An identity from the environment would be used instead
+    if std::env::var_os(AGE_KEY_ENV).is_some() {
+        return;
+    }
+    let error = decrypt("db_password", "db_password.age", EncryptedFormat::Age, None)
+        .await
+        .expect_err("Decrypting without an identity should be rejected")
+        .to_string();
+    assert!(error.contains("Secret db_password"), "{error}");
+    assert!(error.contains("--age-key"), "{error}");
+}
+
+/// Helper function to build a Vault reference
+fn vault_ref(path: &str, key: &str) -> VaultRef {
+    VaultRef {
+        path: path.to_string(),
+        key: key.to_string(),
+    }
+}
+
+#[test]
+fn test_vault_fields_from_kv_v1_and_v2() {
+    let v2 = serde_json::json!({
+        "data": { "data": { "password": "hunter2" }, "metadata": { "version": 3 } }
+    });
+    let v1 = serde_json::json!({ "data": { "password": "hunter2", "port": 5432 } });
+
+    let reference = vault_ref("secret/data/db", "password");
+    assert_eq!(field("db", &v2, &reference).unwrap(), "hunter2");
+    assert_eq!(field("db", &v1, &reference).unwrap(), "hunter2");
+    assert_eq!(
+        field("db", &v1, &vault_ref("secret/db", "port")).unwrap(),
+        "5432"
+    );
+
+    let error = field("db", &v2, &vault_ref("secret/data/db", "user"))
+        .expect_err("A missing field should be rejected")
+        .to_string();
+    assert!(
+        error.contains("secret/data/db has no field user"),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn test_vault_read_sends_token_once_per_path() {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("Failed to accept");
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            request.push(line.trim().to_string());
+        }
+        let body = r#"{"data":{"data":{"user":"app","password":"hunter2"},"metadata":{}}}"#;
+        write!(
+            &stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        request
+    });
+
+    let mut vault = Vault::new(address, "s.token");
+    let password = vault
+        .read("db", &vault_ref("secret/data/db", "password"))
+        .await
+        .expect("Read should succeed");
+    // Served from the cache; the server only answers once
+    let user = vault
+        .read("db", &vault_ref("secret/data/db", "user"))
+        .await
+        .expect("Read should succeed");
+    assert_eq!((password.as_str(), user.as_str()), ("hunter2", "app"));
+
+    let request = server.join().unwrap();
+    assert_eq!(request[0], "GET /v1/secret/data/db HTTP/1.1");
+    assert!(request.contains(&"X-Vault-Token: s.token".to_string()));
+}
+
+#[tokio::test]
+async fn test_vault_agent_mode_leaves_references_unresolved() {
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let config: Config = toml::from_str(
+        r#"
+[project]
+name = "secrets-test"
+
+[secrets.db_password]
+vault = "vault://secret/data/db#password"
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+upstream = "http://web-app:3000"
+
+[services.environment]
+API_KEY = "vault://kv/web#api_key"
+"#,
+    )
+    .expect("Failed to parse config");
+
+    // Vault is never contacted, so no address or token is needed
+    let written = write_secrets(&config, temp_dir.path(), None, true)
+        .await
+        .expect("Secrets should be left to the agent");
+    assert_eq!(written, 0);
+
+    let dir = temp_dir.path().join(SECRETS_DIR);
+    assert!(dir.join(".gitignore").exists());
+    assert!(!dir.join("db_password").exists());
+    assert!(!temp_dir.path().join(vault_env_file("web-app")).exists());
+}
+
+#[tokio::test]
+async fn test_command_secrets_stay_out_of_rendered_files() {
+    let config: Config = toml::from_str(
+        r#"
+[project]
+name = "secret-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+external_port = 80
+secrets = ["api_token"]
+
+[secrets.api_token]
+command = "echo from-password-manager"
+"#,
+    )
+    .expect("Failed to parse config");
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let output_dir = temp_dir.path().join("built");
+    let cerberus = crate::Cerberus::from_config(config, &output_dir);
+
+    let rendered = cerberus
+        .generate_to_memory()
+        .expect("Rendering should succeed");
+    let plan = cerberus.generate_plan().expect("Planning should succeed");
+    cerberus
+        .generate_all()
+        .await
+        .expect("Generation should succeed");
+
+    assert_eq!(
+        std::fs::read_to_string(output_dir.join("secrets/api_token"))
+            .expect("The secret should be resolved"),
+        "from-password-manager"
+    );
+    assert!(
+        rendered
+            .values()
+            .all(|content| !String::from_utf8_lossy(content).contains("from-password-manager"))
+    );
+    assert_eq!(
+        plan.iter()
+            .map(|artifact| output_dir.join(&artifact.path))
+            .collect::<Vec<_>>(),
+        rendered.keys().cloned().collect::<Vec<_>>()
+    );
+    assert!(
+        plan.iter()
+            .all(|artifact| !artifact.content.contains("from-password-manager"))
+    );
+    assert_eq!(
+        crate::generators::snapshot(&output_dir)
+            .expect("Snapshot should succeed")
+            .into_iter()
+            .map(|(path, content)| (output_dir.join(path), content))
+            .collect::<BTreeMap<_, _>>(),
+        rendered
+    );
+}
@@ -0,0 +1,315 @@
+//! # Live stack status
+//!
+//! Asks the Docker daemon for the containers of a deployed output directory
+//! and reports, per container, its state, health, restart count, published
+//! ports, and resource usage. Containers are found by the `cerberus.*`
+//! labels of the generated Compose file and the Compose working directory
+//! label, so other stacks on the same host are left out. The stack counts
+//! as healthy when every labelled Compose service has a container and none
+//! of them stopped or fails its healthcheck.
+
+use crate::generators::COMPOSE_FILE;
+use crate::{CerberusError, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+
+/// Label the Compose generator puts on every service it manages
+pub(crate) const SERVICE_LABEL: &str = "cerberus.service";
+
+/// Compose label holding the directory a container's project runs from
+pub(crate) const WORKING_DIR_LABEL: &str = "com.docker.compose.project.working_dir";
+
+/// Compose label holding a container's service name
+const COMPOSE_SERVICE_LABEL: &str = "com.docker.compose.service";
+
+/// `docker stats` columns: name, CPU, and memory usage
+const STATS_FORMAT: &str = "{{.Name}}\t{{.CPUPerc}}\t{{.MemUsage}}";
+
+/// State of one container of the stack
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ContainerStatus {
+    /// Compose service the container belongs to
+    pub service: String,
+    /// Container name
+    pub name: String,
+    /// Docker state, e.g. `running` or `exited`
+    pub state: String,
+    /// Healthcheck status, if the container has a healthcheck
+    pub health: Option<String>,
+    /// Times Docker restarted the container
+    pub restarts: u64,
+    /// Published ports, e.g. `80->80/tcp`
+    pub ports: Vec<String>,
+    /// CPU usage, e.g. `0.52%`
+    pub cpu: Option<String>,
+    /// Memory usage, e.g. `24.1MiB / 1GiB`
+    pub memory: Option<String>,
+}
+
+impl ContainerStatus {
+    /// Why the container counts against the stack's health, if it does
+    pub fn problem(&self) -> Option<String> {
+        if self.state != "running" {
+            Some(format!("{} is {}", self.name, self.state))
+        } else if self.health.as_deref() == Some("unhealthy") {
+            Some(format!("{} is unhealthy", self.name))
+        } else {
+            None
+        }
+    }
+}
+
+/// Result of `status`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StackStatus {
+    /// Whether every expected service runs and none is unhealthy
+    pub healthy: bool,
+    /// Containers of the stack, ordered by service
+    pub containers: Vec<ContainerStatus>,
+    /// Expected services without any container
+    pub missing: Vec<String>,
+}
+
+impl StackStatus {
+    /// Query Docker for the stack deployed from an output directory
+    ///
+    /// # Errors
+    /// Returns error if the Compose file cannot be read or Docker cannot be
+    /// queried
+    pub async fn collect(output_dir: &Path) -> Result<Self> {
+        let compose_path = output_dir.join(COMPOSE_FILE);
+        let compose = tokio::fs::read(&compose_path)
+            .await
+            .map_err(|e| CerberusError::io(&compose_path, e))?;
+        let expected = expected_services(&compose)?;
+
+        let stack_dir =
+            std::path::absolute(output_dir).unwrap_or_else(|_| output_dir.to_path_buf());
+        let ids = docker(&[
+            "ps",
+            "-a",
+            "-q",
+            "--filter",
+            &format!("label={SERVICE_LABEL}"),
+            "--filter",
+            &format!("label={WORKING_DIR_LABEL}={}", stack_dir.display()),
+        ])
+        .await?;
+        let ids: Vec<&str> = ids.split_whitespace().collect();
+        if ids.is_empty() {
+            return Ok(Self::new(&expected, Vec::new()));
+        }
+
+        let inspect = docker(&[&["inspect"], ids.as_slice()].concat()).await?;
+        let mut containers = parse_inspect(inspect.as_bytes())?;
+
+        // Usage is a best-effort extra; state and health decide the result
+        let stats = [
+            &["stats", "--no-stream", "--format", STATS_FORMAT],
+            ids.as_slice(),
+        ]
+        .concat();
+        match docker(&stats).await {
+            Ok(output) => apply_stats(&mut containers, &output),
+            Err(e) => tracing::warn!("Could not read resource usage: {e}"),
+        }
+
+        Ok(Self::new(&expected, containers))
+    }
+
+    /// Compare the containers found with the services expected
+    pub fn new(expected: &BTreeSet<String>, mut containers: Vec<ContainerStatus>) -> Self {
+        containers.sort_by(|a, b| (&a.service, &a.name).cmp(&(&b.service, &b.name)));
+        let missing: Vec<String> = expected
+            .iter()
+            .filter(|service| {
+                !containers
+                    .iter()
+                    .any(|container| &container.service == *service)
+            })
+            .cloned()
+            .collect();
+        let healthy = missing.is_empty()
+            && containers
+                .iter()
+                .all(|container| container.problem().is_none());
+
+        Self {
+            healthy,
+            containers,
+            missing,
+        }
+    }
+
+    /// Why the stack is not healthy, one line per service or container
+    pub fn problems(&self) -> Vec<String> {
+        self.missing
+            .iter()
+            .map(|service| format!("{service} has no container"))
+            .chain(self.containers.iter().filter_map(ContainerStatus::problem))
+            .collect()
+    }
+
+    /// Containers as an aligned text table
+    pub fn table(&self) -> String {
+        let header = [
+            "SERVICE",
+            "CONTAINER",
+            "STATE",
+            "HEALTH",
+            "RESTARTS",
+            "PORTS",
+            "CPU",
+            "MEMORY",
+        ]
+        .map(str::to_string);
+        let rows: Vec<[String; 8]> = self
+            .containers
+            .iter()
+            .map(|container| {
+                [
+                    container.service.clone(),
+                    container.name.clone(),
+                    container.state.clone(),
+                    container.health.clone().unwrap_or_else(|| "-".to_string()),
+                    container.restarts.to_string(),
+                    if container.ports.is_empty() {
+                        "-".to_string()
+                    } else {
+                        container.ports.join(", ")
+                    },
+                    container.cpu.clone().unwrap_or_else(|| "-".to_string()),
+                    container.memory.clone().unwrap_or_else(|| "-".to_string()),
+                ]
+            })
+            .collect();
+
+        let mut widths = [0; 8];
+        for row in std::iter::once(&header).chain(&rows) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        let mut table = String::new();
+        for row in std::iter::once(&header).chain(&rows) {
+            let mut line = String::new();
+            for (width, cell) in widths.iter().zip(row) {
+                write!(line, "{cell:<width$}  ").unwrap();
+            }
+            writeln!(table, "{}", line.trim_end()).unwrap();
+        }
+        table
+    }
+}
+
+/// Compose services carrying the `cerberus.service` label
+pub(crate) fn expected_services(compose: &[u8]) -> Result<BTreeSet<String>> {
+    let compose: serde_yaml::Value = serde_yaml::from_slice(compose)
+        .map_err(|e| CerberusError::config(format!("Invalid {COMPOSE_FILE}: {e}")))?;
+
+    let mut services = BTreeSet::new();
+    for (name, definition) in compose["services"].as_mapping().into_iter().flatten() {
+        let labelled = match &definition["labels"] {
+            serde_yaml::Value::Sequence(labels) => labels.iter().any(|label| {
+                label
+                    .as_str()
+                    .is_some_and(|label| label.split('=').next() == Some(SERVICE_LABEL))
+            }),
+            serde_yaml::Value::Mapping(labels) => labels.contains_key(SERVICE_LABEL),
+            _ => false,
+        };
+        if labelled && let Some(name) = name.as_str() {
+            services.insert(name.to_string());
+        }
+    }
+    Ok(services)
+}
+
+/// Containers from `docker inspect` output
+pub(crate) fn parse_inspect(output: &[u8]) -> Result<Vec<ContainerStatus>> {
+    let containers: Vec<Value> = serde_json::from_slice(output)?;
+    Ok(containers
+        .iter()
+        .map(|container| {
+            let name = container["Name"]
+                .as_str()
+                .unwrap_or_default()
+                .trim_start_matches('/')
+                .to_string();
+
+            // {"80/tcp": [{"HostIp": "0.0.0.0", "HostPort": "80"}, ...], "9000/tcp": null}
+            let mut ports = BTreeSet::new();
+            for (target, bindings) in container["NetworkSettings"]["Ports"]
+                .as_object()
+                .into_iter()
+                .flatten()
+            {
+                for binding in bindings.as_array().into_iter().flatten() {
+                    if let Some(host_port) = binding["HostPort"].as_str() {
+                        ports.insert(format!("{host_port}->{target}"));
+                    }
+                }
+            }
+
+            ContainerStatus {
+                service: container["Config"]["Labels"][COMPOSE_SERVICE_LABEL]
+                    .as_str()
+                    .map_or_else(|| name.clone(), str::to_string),
+                state: container["State"]["Status"]
+                    .as_str()
+                    .unwrap_or("unknown")
+                    .to_string(),
+                health: container["State"]["Health"]["Status"]
+                    .as_str()
+                    .map(str::to_string),
+                restarts: container["RestartCount"].as_u64().unwrap_or_default(),
+                ports: ports.into_iter().collect(),
+                cpu: None,
+                memory: None,
+                name,
+            }
+        })
+        .collect())
+}
+
+/// Fill in usage from `docker stats` lines of name, CPU, and memory
+pub(crate) fn apply_stats(containers: &mut [ContainerStatus], output: &str) {
+    let usage: BTreeMap<&str, (&str, &str)> = output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            Some((fields.next()?, (fields.next()?, fields.next()?)))
+        })
+        .collect();
+    for container in containers {
+        if let Some((cpu, memory)) = usage.get(container.name.as_str()) {
+            container.cpu = Some(cpu.to_string());
+            container.memory = Some(memory.to_string());
+        }
+    }
+}
+
+/// Run a docker command and return its standard output
+pub(crate) async fn docker(args: &[&str]) -> Result<String> {
+    let output = tokio::process::Command::new("docker")
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| CerberusError::docker(format!("failed to run docker: {e}")))?;
+    if !output.status.success() {
+        return Err(CerberusError::docker(format!(
+            "docker {} exited with {}: {}",
+            args[0],
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests;
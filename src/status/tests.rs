@@ -0,0 +1,105 @@
+//! # Tests for live stack status
+//!
+//! These tests verify that Docker's output is read into container states
+//! and that missing or failing services make the stack unhealthy.
+
+use super::*;
+
+/// `docker inspect` output for a healthy proxy and a restarting backend
+const INSPECT: &str = r#"[
+  {
+    "Name": "/proxy",
+    "RestartCount": 0,
+    "State": {"Status": "running", "Health": {"Status": "healthy"}},
+    "Config": {"Labels": {"com.docker.compose.service": "proxy", "cerberus.service": "proxy"}},
+    "NetworkSettings": {"Ports": {
+      "80/tcp": [{"HostIp": "0.0.0.0", "HostPort": "80"}, {"HostIp": "::", "HostPort": "80"}],
+      "9000/tcp": null
+    }}
+  },
+  {
+    "Name": "/stack-web-app-1",
+    "RestartCount": 4,
+    "State": {"Status": "restarting"},
+    "Config": {"Labels": {"com.docker.compose.service": "web-app"}},
+    "NetworkSettings": {"Ports": {}}
+  }
+]"#;
+
+#[test]
+fn test_expected_services_are_labelled() {
+    let compose = br#"
+services:
+  proxy:
+    image: nginx
+    labels:
+      - "cerberus.service=proxy"
+  web-app:
+    image: example/web
+    labels:
+      cerberus.service: backend
+  migrate:
+    image: example/migrate
+"#;
+    let expected = expected_services(compose).unwrap();
+
+    assert_eq!(
+        expected.into_iter().collect::<Vec<_>>(),
+        vec!["proxy", "web-app"]
+    );
+}
+
+#[test]
+fn test_inspect_and_stats_fill_container_status() {
+    let mut containers = parse_inspect(INSPECT.as_bytes()).unwrap();
+    apply_stats(&mut containers, "proxy\t0.52%\t24.1MiB / 1GiB\n");
+
+    assert_eq!(
+        containers[0],
+        ContainerStatus {
+            service: "proxy".to_string(),
+            name: "proxy".to_string(),
+            state: "running".to_string(),
+            health: Some("healthy".to_string()),
+            restarts: 0,
+            ports: vec!["80->80/tcp".to_string()],
+            cpu: Some("0.52%".to_string()),
+            memory: Some("24.1MiB / 1GiB".to_string()),
+        }
+    );
+    assert_eq!(containers[1].service, "web-app");
+    assert_eq!(containers[1].restarts, 4);
+    assert_eq!(containers[1].health, None);
+    assert_eq!(containers[1].cpu, None);
+}
+
+#[test]
+fn test_missing_and_failing_services_are_unhealthy() {
+    let containers = parse_inspect(INSPECT.as_bytes()).unwrap();
+    let expected: BTreeSet<String> = ["anubis", "proxy", "web-app"]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let status = StackStatus::new(&expected, containers.clone());
+
+    assert!(!status.healthy);
+    assert_eq!(status.missing, vec!["anubis"]);
+    assert_eq!(
+        status.problems(),
+        vec!["anubis has no container", "stack-web-app-1 is restarting"]
+    );
+
+    let table = status.table();
+    assert!(table.starts_with("SERVICE  CONTAINER        STATE       HEALTH   RESTARTS"));
+    assert!(
+        table.contains("\nproxy    proxy            running     healthy  0         80->80/tcp")
+    );
+    assert!(table.contains("\nweb-app  stack-web-app-1  restarting  -        4         -"));
+
+    let running: Vec<ContainerStatus> = containers
+        .into_iter()
+        .filter(|container| container.state == "running")
+        .collect();
+    let expected: BTreeSet<String> = ["proxy".to_string()].into();
+    assert!(StackStatus::new(&expected, running).healthy);
+}
@@ -0,0 +1,393 @@
+//! # Smoke tests of a generated stack
+//!
+//! `cerberus test` boots the Compose stack of an output directory, sends
+//! HTTP requests through the layer-1 proxy, and tears the stack down again.
+//! Each probe checks one promise of the configuration:
+//!
+//! - the edge proxy answers `/health`
+//! - every service domain is routed to its upstream (no 502/503/504) with
+//!   the security headers every proxy template adds
+//! - with `[anubis]` enabled, domains of the protected routes answer a
+//!   browser with the Anubis challenge page
+//!
+//! Requests are sent with the `curl` binary against the published port on
+//! the loopback address, with the domain in the `Host` header, so no DNS
+//! records are needed. They carry `X-Forwarded-Proto: https` so that
+//! `[tls.redirect_http]` does not answer them with a redirect.
+
+use crate::config::{Config, RouteType};
+use crate::generators::COMPOSE_FILE;
+use crate::report::FindingStatus;
+use crate::{CerberusError, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+/// Browser user agent; Anubis only challenges clients claiming to be browsers
+const BROWSER_USER_AGENT: &str =
+    "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+
+/// Seconds a single request may take
+const REQUEST_TIMEOUT_SECS: &str = "10";
+
+/// Pause between attempts of a failing probe
+const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What a probe expects from the stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Expectation {
+    /// `200` from the proxy's own health endpoint
+    Health,
+    /// A response from the upstream, with the security headers
+    Routed,
+    /// The Anubis challenge page
+    Challenge,
+}
+
+impl Expectation {
+    /// Check a response, describing what passed or what went wrong
+    pub fn check(self, response: &HttpResponse) -> std::result::Result<String, String> {
+        match self {
+            Expectation::Health if response.status == 200 => {
+                Ok("Health endpoint answered 200".to_string())
+            }
+            Expectation::Health => Err(format!(
+                "Health endpoint answered {} instead of 200",
+                response.status
+            )),
+            Expectation::Routed if matches!(response.status, 502..=504) => Err(format!(
+                "Proxy answered {}; the upstream is unreachable",
+                response.status
+            )),
+            Expectation::Routed => match response.header("X-Content-Type-Options") {
+                Some(value) if value.eq_ignore_ascii_case("nosniff") => Ok(format!(
+                    "Routed to the upstream ({}) with security headers",
+                    response.status
+                )),
+                _ => Err(format!(
+                    "Response ({}) lacks the X-Content-Type-Options: nosniff security header",
+                    response.status
+                )),
+            },
+            Expectation::Challenge
+                if response.status < 500 && response.body.to_lowercase().contains("anubis") =>
+            {
+                Ok(format!("Anubis challenge served ({})", response.status))
+            }
+            Expectation::Challenge => Err(format!(
+                "Expected the Anubis challenge page, got {}",
+                response.status
+            )),
+        }
+    }
+}
+
+/// One HTTP request sent through the edge proxy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probe {
+    /// Short description, e.g. `route web-app`
+    pub name: String,
+    /// Domain sent in the `Host` header
+    pub host: Option<String>,
+    /// Request path
+    pub path: String,
+    /// What the response must look like
+    pub expect: Expectation,
+}
+
+/// Requests checking a configuration, and the host port they are sent to
+///
+/// # Errors
+/// Returns error if no proxy publishes a port to send requests to
+pub fn plan(config: &Config) -> Result<(u16, Vec<Probe>)> {
+    let edge = config
+        .proxies
+        .iter()
+        .filter(|proxy| proxy.external_port.is_some())
+        .min_by_key(|proxy| proxy.layer.unwrap_or(0))
+        .ok_or_else(|| {
+            CerberusError::validation("cerberus test needs a proxy with an external_port")
+        })?;
+    let port = edge.external_port.unwrap_or_default();
+
+    let mut probes = vec![Probe {
+        name: format!("health {}", edge.name),
+        host: None,
+        path: "/health".to_string(),
+        expect: Expectation::Health,
+    }];
+    for service in &config.services {
+        for host in service.hosts() {
+            probes.push(Probe {
+                name: format!("route {}", service.name),
+                host: Some(host.to_string()),
+                path: "/".to_string(),
+                expect: Expectation::Routed,
+            });
+        }
+    }
+    if config.anubis.enabled {
+        let mut domains: Vec<&str> = Vec::new();
+        for route in edge
+            .routes
+            .iter()
+            .filter(|route| route.route_type != RouteType::Direct && !route.protocol.is_stream())
+        {
+            // A wildcard is not a host a request can name
+            if !route.domain.contains('*') && !domains.contains(&route.domain.as_str()) {
+                domains.push(&route.domain);
+            }
+        }
+        for domain in domains {
+            probes.push(Probe {
+                name: format!("challenge {domain}"),
+                host: Some(domain.to_string()),
+                path: "/".to_string(),
+                expect: Expectation::Challenge,
+            });
+        }
+    }
+    Ok((port, probes))
+}
+
+/// Status, headers and body of an HTTP response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    /// Status code
+    pub status: u16,
+    /// Header names and values, in order
+    pub headers: Vec<(String, String)>,
+    /// Body, decoded lossily as UTF-8
+    pub body: String,
+}
+
+impl HttpResponse {
+    /// Parse the output of `curl --include`
+    ///
+    /// Interim `1xx` responses are skipped, so the last response is returned.
+    pub fn parse(output: &[u8]) -> Option<Self> {
+        let text = String::from_utf8_lossy(output);
+        let mut rest: &str = &text;
+        loop {
+            let (head, body) = rest
+                .split_once("\r\n\r\n")
+                .or_else(|| rest.split_once("\n\n"))
+                .unwrap_or((rest, ""));
+            let mut lines = head.lines();
+            let status: u16 = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+            if (100..200).contains(&status) && body.starts_with("HTTP/") {
+                rest = body;
+                continue;
+            }
+            let headers = lines
+                .filter_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    Some((name.trim().to_string(), value.trim().to_string()))
+                })
+                .collect();
+            return Some(Self {
+                status,
+                headers,
+                body: body.to_string(),
+            });
+        }
+    }
+
+    /// Value of a header, matched case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Outcome of one probe
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ProbeResult {
+    /// Short description of the probe
+    pub name: String,
+    /// URL requested
+    pub url: String,
+    /// Domain sent in the `Host` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// What the probe expected
+    pub expect: Expectation,
+    /// `ok` or `error`
+    pub status: FindingStatus,
+    /// What passed, or what went wrong
+    pub message: String,
+}
+
+/// Result of `test`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TestReport {
+    /// Whether every probe passed
+    pub passed: bool,
+    /// Every probe sent
+    pub probes: Vec<ProbeResult>,
+}
+
+impl TestReport {
+    /// Summarize the probe results
+    pub fn new(probes: Vec<ProbeResult>) -> Self {
+        Self {
+            passed: probes.iter().all(|probe| probe.status == FindingStatus::Ok),
+            probes,
+        }
+    }
+}
+
+/// Boots a generated stack, probes it, and tears it down
+#[derive(Debug, Clone)]
+pub struct SmokeTest {
+    /// Output directory holding the generated Compose file
+    output_dir: PathBuf,
+    /// How long the stack may take to start and each probe to pass
+    timeout: Duration,
+    /// Leave the stack running after the probes, e.g. to debug a failure
+    keep_running: bool,
+}
+
+impl SmokeTest {
+    /// Smoke test for the stack generated into an output directory
+    pub fn new(output_dir: &Path, timeout: Duration) -> Self {
+        Self {
+            output_dir: output_dir.to_path_buf(),
+            timeout,
+            keep_running: false,
+        }
+    }
+
+    /// Leave the stack running after the probes
+    pub fn keep_running(mut self, keep_running: bool) -> Self {
+        self.keep_running = keep_running;
+        self
+    }
+
+    /// Start the stack, send every probe, and stop the stack again
+    ///
+    /// The stack is torn down, volumes included, even if a probe or the
+    /// startup fails.
+    ///
+    /// # Errors
+    /// Returns error if the stack cannot be started or stopped, or curl
+    /// cannot be run
+    pub async fn run(&self, config: &Config) -> Result<TestReport> {
+        let (port, probes) = plan(config)?;
+        if !self.output_dir.join(COMPOSE_FILE).exists() {
+            return Err(CerberusError::validation(format!(
+                "{} has no {COMPOSE_FILE}; run cerberus generate first",
+                self.output_dir.display()
+            )));
+        }
+
+        let wait_timeout = self.timeout.as_secs().to_string();
+        let result = match self
+            .compose(&[
+                "up",
+                "--detach",
+                "--build",
+                "--wait",
+                "--wait-timeout",
+                &wait_timeout,
+            ])
+            .await
+        {
+            Ok(()) => self.probe_all(port, &probes).await,
+            Err(e) => Err(e),
+        };
+
+        if !self.keep_running {
+            let down = self
+                .compose(&["down", "--volumes", "--remove-orphans"])
+                .await;
+            // A failed startup is the more useful error
+            if result.is_ok() {
+                down?;
+            }
+        }
+        result.map(TestReport::new)
+    }
+
+    /// Send every probe, retrying each until it passes or the timeout ends
+    async fn probe_all(&self, port: u16, probes: &[Probe]) -> Result<Vec<ProbeResult>> {
+        let mut results = Vec::new();
+        for probe in probes {
+            let url = format!("http://127.0.0.1:{port}{}", probe.path);
+            let started = Instant::now();
+            let outcome = loop {
+                let outcome = match request(&url, probe).await? {
+                    Some(response) => probe.expect.check(&response),
+                    None => Err("No HTTP response".to_string()),
+                };
+                if outcome.is_ok() || started.elapsed() >= self.timeout {
+                    break outcome;
+                }
+                tokio::time::sleep(RETRY_INTERVAL).await;
+            };
+            let (status, message) = match outcome {
+                Ok(message) => (FindingStatus::Ok, message),
+                Err(message) => (FindingStatus::Error, message),
+            };
+            results.push(ProbeResult {
+                name: probe.name.clone(),
+                url,
+                host: probe.host.clone(),
+                expect: probe.expect,
+                status,
+                message,
+            });
+        }
+        Ok(results)
+    }
+
+    /// Run `docker compose` on the generated Compose file
+    async fn compose(&self, args: &[&str]) -> Result<()> {
+        let output = tokio::process::Command::new("docker")
+            .arg("compose")
+            .arg("--project-directory")
+            .arg(&self.output_dir)
+            .arg("-f")
+            .arg(self.output_dir.join(COMPOSE_FILE))
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|e| CerberusError::docker(format!("failed to run docker: {e}")))?;
+        if !output.status.success() {
+            return Err(CerberusError::docker(format!(
+                "docker compose {} exited with {}: {}",
+                args[0],
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Send one probe with curl; `None` if no response arrived
+async fn request(url: &str, probe: &Probe) -> Result<Option<HttpResponse>> {
+    let mut command = tokio::process::Command::new("curl");
+    command
+        .args(["--silent", "--include", "--max-time", REQUEST_TIMEOUT_SECS])
+        .args(["--header", "X-Forwarded-Proto: https"])
+        .args(["--user-agent", BROWSER_USER_AGENT]);
+    if let Some(host) = &probe.host {
+        command.arg("--header").arg(format!("Host: {host}"));
+    }
+    let output = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| CerberusError::docker(format!("failed to run curl: {e}")))?;
+    Ok(HttpResponse::parse(&output.stdout))
+}
+
+#[cfg(test)]
+mod tests;
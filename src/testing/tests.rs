@@ -0,0 +1,174 @@
+//! # Tests for stack smoke tests
+//!
+//! These tests verify the probes planned from a configuration, the parsing
+//! of curl's output, and the checks applied to each response.
+
+use super::*;
+use crate::test_support::parse_config;
+
+fn response(status: u16, headers: &[(&str, &str)], body: &str) -> HttpResponse {
+    HttpResponse {
+        status,
+        headers: headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        body: body.to_string(),
+    }
+}
+
+#[test]
+fn test_plan_probes_edge_services_and_challenges() {
+    let config = parse_config(
+        r#"
+[project]
+name = "smoke-test"
+
+[anubis]
+enabled = true
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+external_port = 8080
+layer = 1
+default_upstream = "http://anubis:8080"
+
+[[proxies.routes]]
+type = "conditional"
+domain = "blog.example.com"
+upstream = "http://proxy-2:80"
+
+[[proxies.routes]]
+type = "direct"
+domain = "static.example.com"
+upstream = "http://proxy-2:80"
+
+[[proxies]]
+name = "proxy-2"
+type = "caddy"
+layer = 2
+
+[[services]]
+name = "web-app"
+domain = "app.example.com"
+domains = ["www.example.com"]
+upstream = "http://192.0.2.1:3000"
+"#,
+    );
+
+    let (port, probes) = plan(&config).expect("Plan should build");
+    assert_eq!(port, 8080);
+    let summary: Vec<(&str, Option<&str>, Expectation)> = probes
+        .iter()
+        .map(|probe| (probe.name.as_str(), probe.host.as_deref(), probe.expect))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("health proxy", None, Expectation::Health),
+            (
+                "route web-app",
+                Some("app.example.com"),
+                Expectation::Routed
+            ),
+            (
+                "route web-app",
+                Some("www.example.com"),
+                Expectation::Routed
+            ),
+            (
+                "challenge blog.example.com",
+                Some("blog.example.com"),
+                Expectation::Challenge
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_plan_needs_a_published_proxy() {
+    let config = parse_config(
+        r#"
+[project]
+name = "smoke-test"
+
+[[proxies]]
+name = "proxy"
+type = "nginx"
+"#,
+    );
+    let error = plan(&config).unwrap_err().to_string();
+    assert!(error.contains("needs a proxy with an external_port"));
+}
+
+#[test]
+fn test_parse_curl_output() {
+    let output = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nServer: nginx\r\nX-Content-Type-Options: nosniff\r\n\r\n<html>hi</html>";
+    let parsed = HttpResponse::parse(output).expect("Response should parse");
+    assert_eq!(parsed.status, 200);
+    assert_eq!(parsed.header("x-content-type-options"), Some("nosniff"));
+    assert_eq!(parsed.body, "<html>hi</html>");
+
+    let parsed = HttpResponse::parse(b"HTTP/2 404 \r\ncontent-length: 0\r\n\r\n").unwrap();
+    assert_eq!(parsed.status, 404);
+    assert!(parsed.body.is_empty());
+
+    assert_eq!(HttpResponse::parse(b""), None);
+}
+
+#[test]
+fn test_expectations() {
+    assert!(Expectation::Health.check(&response(200, &[], "OK")).is_ok());
+    assert!(Expectation::Health.check(&response(404, &[], "")).is_err());
+
+    let secured = [("X-Content-Type-Options", "nosniff")];
+    assert!(
+        Expectation::Routed
+            .check(&response(404, &secured, ""))
+            .is_ok()
+    );
+    let error = Expectation::Routed
+        .check(&response(502, &secured, ""))
+        .unwrap_err();
+    assert!(error.contains("upstream is unreachable"));
+    let error = Expectation::Routed
+        .check(&response(200, &[], ""))
+        .unwrap_err();
+    assert!(error.contains("security header"));
+
+    assert!(
+        Expectation::Challenge
+            .check(&response(
+                200,
+                &[],
+                "<script src=\"/.within.website/x/cmd/anubis/\">"
+            ))
+            .is_ok()
+    );
+    assert!(
+        Expectation::Challenge
+            .check(&response(200, &[], "<h1>Blog</h1>"))
+            .is_err()
+    );
+}
+
+#[test]
+fn test_report_fails_on_any_failed_probe() {
+    let result = |status| ProbeResult {
+        name: "health proxy".to_string(),
+        url: "http://127.0.0.1:80/health".to_string(),
+        host: None,
+        expect: Expectation::Health,
+        status,
+        message: String::new(),
+    };
+    assert!(TestReport::new(vec![result(FindingStatus::Ok)]).passed);
+    assert!(
+        !TestReport::new(vec![
+            result(FindingStatus::Ok),
+            result(FindingStatus::Error)
+        ])
+        .passed
+    );
+}
@@ -0,0 +1,545 @@
+//! # Operations dashboard
+//!
+//! Samples a deployed output directory for `cerberus top`: the containers
+//! as `status` reports them, request rates per proxy layer, recent
+//! container starts and stops (scaling and restarts), and the expiry of
+//! the configured certificates. Rates come from the HAProxy statistics
+//! page, Traefik's Prometheus endpoint, and for Nginx and Caddy from the
+//! lines appended to their access logs in `built/logs`, and are computed
+//! between two samples. The terminal UI (`tui` feature) renders the
+//! samples and can reload a proxy or scale a layer of replicated proxies.
+
+use crate::config::{Config, ProxyConfig, ProxyType};
+use crate::generators::COMPOSE_FILE;
+use crate::status::{self, ContainerStatus, StackStatus};
+use crate::{CerberusError, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "tui")]
+pub mod ui;
+
+/// Window of container events shown, as understood by `docker events --since`
+const EVENTS_SINCE: &str = "1h";
+
+/// Most recent container events kept in a snapshot
+const MAX_EVENTS: usize = 20;
+
+/// Seconds curl waits for a metrics endpoint
+const METRICS_TIMEOUT_SECS: &str = "2";
+
+/// Port of Traefik's `health` entry point serving `/metrics`
+const TRAEFIK_METRICS_URL: &str = "http://127.0.0.1:8080/metrics";
+
+/// Requests per second of one proxy
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ProxyRate {
+    /// Proxy name (and Compose service)
+    pub name: String,
+    /// Layer the proxy runs in
+    pub layer: u8,
+    /// Requests per second since the previous sample, if measurable
+    pub requests_per_sec: Option<f64>,
+}
+
+/// Requests per second of one proxy layer
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LayerRate {
+    /// Layer number
+    pub layer: u8,
+    /// Proxies of the layer
+    pub proxies: Vec<String>,
+    /// Sum of the measurable proxy rates, if any proxy is measurable
+    pub requests_per_sec: Option<f64>,
+}
+
+/// A container of the stack starting or stopping
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StackEvent {
+    /// Unix time of the event
+    pub time: u64,
+    /// Container name
+    pub container: String,
+    /// Docker action, `start` or `die`
+    pub action: String,
+}
+
+/// Expiry of a configured certificate
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CertificateExpiry {
+    /// Domain pattern of the certificate
+    pub domain: String,
+    /// Days until the certificate expires, negative once expired
+    pub days_left: Option<i64>,
+    /// Why the expiry could not be read
+    pub error: Option<String>,
+}
+
+/// One sample of the dashboard
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Snapshot {
+    /// Containers, health, and usage
+    pub status: StackStatus,
+    /// Request rates per proxy
+    pub proxies: Vec<ProxyRate>,
+    /// Request rates per layer
+    pub layers: Vec<LayerRate>,
+    /// Recent container starts and stops, newest first
+    pub events: Vec<StackEvent>,
+    /// Expiry of the configured certificates
+    pub certificates: Vec<CertificateExpiry>,
+}
+
+/// Samples a deployed stack and runs the dashboard's actions
+#[derive(Debug)]
+pub struct Dashboard {
+    config: Config,
+    output_dir: PathBuf,
+    /// Request counter of every proxy at the previous sample
+    counters: HashMap<String, (Instant, u64)>,
+    /// Bytes read and lines counted per access log
+    log_offsets: HashMap<PathBuf, (u64, u64)>,
+}
+
+impl Dashboard {
+    /// Create a dashboard for the stack deployed from an output directory
+    pub fn new(config: Config, output_dir: &Path) -> Self {
+        Self {
+            config,
+            output_dir: output_dir.to_path_buf(),
+            counters: HashMap::new(),
+            log_offsets: HashMap::new(),
+        }
+    }
+
+    /// Proxies of the configuration
+    pub fn proxies(&self) -> &[ProxyConfig] {
+        &self.config.proxies
+    }
+
+    /// Take a sample of the stack
+    ///
+    /// Rates need a previous sample, so the first one has none.
+    ///
+    /// # Errors
+    /// Returns error if the Compose file cannot be read or Docker cannot be
+    /// queried
+    pub async fn sample(&mut self) -> Result<Snapshot> {
+        let status = StackStatus::collect(&self.output_dir).await?;
+
+        let mut proxies = Vec::new();
+        for proxy in self.config.proxies.clone() {
+            let containers = containers_of(&status, &proxy.name);
+            let total = self.request_total(&proxy, &containers).await;
+            proxies.push(ProxyRate {
+                requests_per_sec: total.and_then(|total| self.rate(&proxy.name, total)),
+                layer: proxy.layer.unwrap_or(1),
+                name: proxy.name,
+            });
+        }
+        let layers = layer_rates(&proxies);
+
+        let events = match self.events().await {
+            Ok(events) => events,
+            Err(e) => {
+                tracing::warn!("Could not read container events: {e}");
+                Vec::new()
+            }
+        };
+
+        let mut certificates = Vec::new();
+        for certificate in &self.config.tls.certificates {
+            let (days_left, error) = match certificate_expiry(&certificate.cert_file).await {
+                Ok(not_after) => (Some(days_until(not_after, unix_now())), None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            certificates.push(CertificateExpiry {
+                domain: certificate.domain.clone(),
+                days_left,
+                error,
+            });
+        }
+
+        Ok(Snapshot {
+            status,
+            proxies,
+            layers,
+            events,
+            certificates,
+        })
+    }
+
+    /// Send SIGHUP to every container of a proxy so it reloads its configuration
+    ///
+    /// # Errors
+    /// Returns error if a container cannot be signalled
+    pub async fn reload(&self, snapshot: &Snapshot, proxy: &str) -> Result<usize> {
+        let containers = containers_of(&snapshot.status, proxy);
+        for container in &containers {
+            crate::watch::reload_container(container).await?;
+        }
+        Ok(containers.len())
+    }
+
+    /// Change the replica count of the replicated proxies of a layer
+    ///
+    /// Only proxies using `deploy.replicas` can be scaled, as named
+    /// instances are separate services. A proxy publishing ports is kept
+    /// within its configured replicas, the size of its published port range.
+    /// Returns the new replica count per scaled proxy.
+    ///
+    /// # Errors
+    /// Returns error if the layer has no replicated proxy or Compose fails
+    pub async fn scale(
+        &self,
+        snapshot: &Snapshot,
+        layer: u8,
+        delta: i32,
+    ) -> Result<Vec<(String, u32)>> {
+        let targets = scale_targets(&self.config, snapshot, layer, delta)?;
+
+        let compose_file = self.output_dir.join(COMPOSE_FILE);
+        let mut command = tokio::process::Command::new("docker");
+        command
+            .arg("compose")
+            .arg("-f")
+            .arg(&compose_file)
+            .args(["up", "-d", "--no-recreate"]);
+        for (service, replicas) in &targets {
+            command.arg("--scale").arg(format!("{service}={replicas}"));
+        }
+        let output = command
+            .output()
+            .await
+            .map_err(|e| CerberusError::io(&compose_file, e))?;
+        if !output.status.success() {
+            return Err(CerberusError::Scaling {
+                message: format!(
+                    "docker compose up --scale exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            });
+        }
+
+        Ok(targets)
+    }
+
+    /// Requests a proxy has served, summed over its containers
+    async fn request_total(&mut self, proxy: &ProxyConfig, containers: &[String]) -> Option<u64> {
+        match proxy.proxy_type {
+            ProxyType::HaProxy => {
+                let stats = self.config.published_stats(proxy)?;
+                let mut total = 0;
+                for index in 0..containers.len().max(1) {
+                    let url = format!("http://127.0.0.1:{}/stats;csv", stats.port + index as u16);
+                    let csv = fetch(&url, stats.credentials.as_deref()).await?;
+                    total += parse_haproxy_requests(&csv)?;
+                }
+                Some(total)
+            }
+            ProxyType::Traefik => {
+                let mut total = 0;
+                for container in containers {
+                    let metrics = status::docker(&[
+                        "exec",
+                        container,
+                        "wget",
+                        "-qO-",
+                        "-T",
+                        METRICS_TIMEOUT_SECS,
+                        TRAEFIK_METRICS_URL,
+                    ])
+                    .await
+                    .ok()?;
+                    total += parse_traefik_requests(&metrics)?;
+                }
+                (!containers.is_empty()).then_some(total)
+            }
+            ProxyType::Nginx | ProxyType::Caddy => {
+                let files = self.config.access_log_files(proxy);
+                let mut total = 0;
+                for file in files {
+                    total += self.count_lines(&self.output_dir.join("built/logs").join(file))?;
+                }
+                Some(total)
+            }
+            ProxyType::Varnish => None,
+        }
+    }
+
+    /// Lines in an access log, reading only what was appended since last time
+    fn count_lines(&mut self, path: &Path) -> Option<u64> {
+        let mut file = std::fs::File::open(path).ok()?;
+        let size = file.metadata().ok()?.len();
+        let (mut offset, mut lines) = self.log_offsets.get(path).copied().unwrap_or_default();
+        if size < offset {
+            // Rotated or truncated
+            (offset, lines) = (0, 0);
+        }
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut appended = Vec::new();
+        file.take(size - offset).read_to_end(&mut appended).ok()?;
+        lines += appended.iter().filter(|byte| **byte == b'\n').count() as u64;
+        self.log_offsets
+            .insert(path.to_path_buf(), (offset + appended.len() as u64, lines));
+        Some(lines)
+    }
+
+    /// Requests per second since the previous counter of a proxy
+    fn rate(&mut self, proxy: &str, total: u64) -> Option<f64> {
+        let now = Instant::now();
+        let previous = self.counters.insert(proxy.to_string(), (now, total))?;
+        rate_between(previous, (now, total))
+    }
+
+    /// Recent starts and stops of the stack's containers, newest first
+    async fn events(&self) -> Result<Vec<StackEvent>> {
+        let stack_dir =
+            std::path::absolute(&self.output_dir).unwrap_or_else(|_| self.output_dir.clone());
+        let output = status::docker(&[
+            "events",
+            "--since",
+            EVENTS_SINCE,
+            "--until",
+            &unix_now().to_string(),
+            "--filter",
+            "type=container",
+            "--filter",
+            "event=start",
+            "--filter",
+            "event=die",
+            "--filter",
+            &format!("label={}", status::SERVICE_LABEL),
+            "--filter",
+            &format!(
+                "label={}={}",
+                status::WORKING_DIR_LABEL,
+                stack_dir.display()
+            ),
+            "--format",
+            "{{json .}}",
+        ])
+        .await?;
+        Ok(parse_events(&output))
+    }
+}
+
+/// Container names of a Compose service, in name order
+fn containers_of(status: &StackStatus, service: &str) -> Vec<String> {
+    status
+        .containers
+        .iter()
+        .filter(|container: &&ContainerStatus| container.service == service)
+        .map(|container| container.name.clone())
+        .collect()
+}
+
+/// Sum the proxy rates of every layer
+pub(crate) fn layer_rates(proxies: &[ProxyRate]) -> Vec<LayerRate> {
+    let mut layers: BTreeMap<u8, LayerRate> = BTreeMap::new();
+    for proxy in proxies {
+        let layer = layers.entry(proxy.layer).or_insert_with(|| LayerRate {
+            layer: proxy.layer,
+            proxies: Vec::new(),
+            requests_per_sec: None,
+        });
+        layer.proxies.push(proxy.name.clone());
+        if let Some(rate) = proxy.requests_per_sec {
+            *layer.requests_per_sec.get_or_insert(0.0) += rate;
+        }
+    }
+    layers.into_values().collect()
+}
+
+/// Requests per second between two counter readings
+///
+/// A counter that went down was reset by a restart and gives no rate.
+pub(crate) fn rate_between(previous: (Instant, u64), current: (Instant, u64)) -> Option<f64> {
+    let elapsed = current.0.duration_since(previous.0).as_secs_f64();
+    if elapsed <= 0.0 || current.1 < previous.1 {
+        return None;
+    }
+    Some((current.1 - previous.1) as f64 / elapsed)
+}
+
+/// New replica count of every replicated proxy of a layer
+pub(crate) fn scale_targets(
+    config: &Config,
+    snapshot: &Snapshot,
+    layer: u8,
+    delta: i32,
+) -> Result<Vec<(String, u32)>> {
+    let mut targets = Vec::new();
+    for proxy in &config.proxies {
+        let Some(configured) = proxy.deploy_replicas() else {
+            continue;
+        };
+        if proxy.layer.unwrap_or(1) != layer {
+            continue;
+        }
+        let running = containers_of(&snapshot.status, &proxy.name).len() as i64;
+        let mut replicas = (running + i64::from(delta)).max(1) as u32;
+        if proxy.external_port.is_some() || config.published_stats(proxy).is_some() {
+            replicas = replicas.min(configured);
+        }
+        targets.push((proxy.name.clone(), replicas));
+    }
+
+    if targets.is_empty() {
+        return Err(CerberusError::Scaling {
+            message: format!(
+                "layer {layer} has no proxy using deploy.replicas; raise instances in the configuration and regenerate"
+            ),
+        });
+    }
+    Ok(targets)
+}
+
+/// Requests received by the frontends of an HAProxy `stats;csv` page
+///
+/// The statistics frontend itself is not counted.
+pub(crate) fn parse_haproxy_requests(csv: &str) -> Option<u64> {
+    let mut lines = csv.lines();
+    let header: Vec<&str> = lines.next()?.trim_start_matches("# ").split(',').collect();
+    let column = |name: &str| header.iter().position(|field| *field == name);
+    let (proxy, server, requests) = (column("pxname")?, column("svname")?, column("req_tot")?);
+
+    let mut total = 0;
+    for line in lines {
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.get(server) == Some(&"FRONTEND") && fields.get(proxy) != Some(&"stats") {
+            total += fields
+                .get(requests)
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or_default();
+        }
+    }
+    Some(total)
+}
+
+/// Requests counted by Traefik's `traefik_entrypoint_requests_total`
+///
+/// The `health` entry point serving the metrics is not counted.
+pub(crate) fn parse_traefik_requests(metrics: &str) -> Option<u64> {
+    let mut total = 0.0;
+    let mut found = false;
+    for line in metrics.lines() {
+        let Some(sample) = line.strip_prefix("traefik_entrypoint_requests_total{") else {
+            continue;
+        };
+        let Some((labels, value)) = sample.split_once('}') else {
+            continue;
+        };
+        found = true;
+        if labels.contains("entrypoint=\"health\"") {
+            continue;
+        }
+        total += value.trim().parse::<f64>().unwrap_or_default();
+    }
+    found.then_some(total as u64)
+}
+
+/// Container events from `docker events --format '{{json .}}'`, newest first
+pub(crate) fn parse_events(output: &str) -> Vec<StackEvent> {
+    let mut events: Vec<StackEvent> = output
+        .lines()
+        .filter_map(|line| {
+            let event: Value = serde_json::from_str(line).ok()?;
+            Some(StackEvent {
+                time: event["time"].as_u64()?,
+                container: event["Actor"]["Attributes"]["name"].as_str()?.to_string(),
+                action: event["Action"].as_str()?.to_string(),
+            })
+        })
+        .collect();
+    events.reverse();
+    events.truncate(MAX_EVENTS);
+    events
+}
+
+/// Expiry of a certificate file as Unix time, read with openssl
+async fn certificate_expiry(cert_file: &str) -> Result<u64> {
+    let output = tokio::process::Command::new("openssl")
+        .args(["x509", "-noout", "-enddate", "-in", cert_file])
+        .output()
+        .await
+        .map_err(|e| CerberusError::io(cert_file, e))?;
+    if !output.status.success() {
+        return Err(CerberusError::config(format!(
+            "openssl could not read {cert_file}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_not_after(&stdout).ok_or_else(|| {
+        CerberusError::config(format!(
+            "Unexpected openssl output for {cert_file}: {stdout}"
+        ))
+    })
+}
+
+/// Unix time of openssl's `notAfter=Mar  5 12:00:00 2027 GMT`
+pub(crate) fn parse_not_after(output: &str) -> Option<u64> {
+    let date = output.trim().strip_prefix("notAfter=")?;
+    let mut fields = date.split_whitespace();
+    let month_name = fields.next()?;
+    let month = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ]
+    .iter()
+    .position(|month| *month == month_name)? as u64
+        + 1;
+    let day: u64 = fields.next()?.parse().ok()?;
+    let mut time = fields.next()?.split(':').map(|part| part.parse::<u64>());
+    let (hours, minutes, seconds) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    let year: u64 = fields.next()?.parse().ok()?;
+
+    // Days since 1970-01-01 of a proleptic Gregorian date
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y / 400;
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146_097 + day_of_era).checked_sub(719_468)?;
+
+    Some(days * 86_400 + hours * 3600 + minutes * 60 + seconds)
+}
+
+/// Whole days from now until a Unix time, negative once it passed
+pub(crate) fn days_until(time: u64, now: u64) -> i64 {
+    (time as i64 - now as i64).div_euclid(86_400)
+}
+
+/// Current Unix time
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// GET a URL with curl, optionally with basic auth
+async fn fetch(url: &str, credentials: Option<&str>) -> Option<String> {
+    let mut command = tokio::process::Command::new("curl");
+    command.args(["--silent", "--fail", "--max-time", METRICS_TIMEOUT_SECS]);
+    if let Some(credentials) = credentials {
+        command.arg("--user").arg(credentials);
+    }
+    let output = command.arg(url).output().await.ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests;
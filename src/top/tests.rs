@@ -0,0 +1,160 @@
+//! # Tests for the operations dashboard
+//!
+//! These tests verify that metrics pages, Docker events, and certificate
+//! dates are parsed, and that rates and scale targets are computed per layer.
+
+use super::*;
+use crate::test_support::parse_config;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// `stats;csv` output with a site frontend, the statistics frontend, and a backend
+const HAPROXY_CSV: &str = "# pxname,svname,qcur,qmax,scur,smax,slim,stot,req_tot\n\
+http_front,FRONTEND,,,1,5,1024,40,1200\n\
+stats,FRONTEND,,,0,1,1024,3,30\n\
+app_backend,app1,0,0,0,2,,40,\n";
+
+/// Snapshot whose stack runs the given containers of a service
+fn snapshot_with(service: &str, containers: usize) -> Snapshot {
+    let containers = (1..=containers)
+        .map(|index| ContainerStatus {
+            service: service.to_string(),
+            name: format!("stack-{service}-{index}"),
+            state: "running".to_string(),
+            health: None,
+            restarts: 0,
+            ports: Vec::new(),
+            cpu: None,
+            memory: None,
+        })
+        .collect();
+    Snapshot {
+        status: StackStatus::new(&BTreeSet::new(), containers),
+        proxies: Vec::new(),
+        layers: Vec::new(),
+        events: Vec::new(),
+        certificates: Vec::new(),
+    }
+}
+
+#[test]
+fn test_metrics_are_parsed() {
+    assert_eq!(parse_haproxy_requests(HAPROXY_CSV), Some(1200));
+    assert_eq!(parse_haproxy_requests("no header"), None);
+
+    let metrics = "# TYPE traefik_entrypoint_requests_total counter\n\
+traefik_entrypoint_requests_total{code=\"200\",entrypoint=\"web\",method=\"GET\",protocol=\"http\"} 42\n\
+traefik_entrypoint_requests_total{code=\"404\",entrypoint=\"web\",method=\"GET\",protocol=\"http\"} 3\n\
+traefik_entrypoint_requests_total{code=\"200\",entrypoint=\"health\",method=\"GET\",protocol=\"http\"} 900\n";
+    assert_eq!(parse_traefik_requests(metrics), Some(45));
+    assert_eq!(parse_traefik_requests("go_goroutines 12\n"), None);
+}
+
+#[test]
+fn test_rates_per_layer() {
+    let start = Instant::now();
+    let later = start + Duration::from_secs(2);
+    assert_eq!(rate_between((start, 100), (later, 150)), Some(25.0));
+    // A restarted proxy resets its counter
+    assert_eq!(rate_between((start, 100), (later, 10)), None);
+
+    let layers = layer_rates(&[
+        ProxyRate {
+            name: "edge".to_string(),
+            layer: 1,
+            requests_per_sec: Some(10.0),
+        },
+        ProxyRate {
+            name: "front".to_string(),
+            layer: 2,
+            requests_per_sec: Some(4.0),
+        },
+        ProxyRate {
+            name: "api".to_string(),
+            layer: 2,
+            requests_per_sec: Some(1.5),
+        },
+        ProxyRate {
+            name: "cache".to_string(),
+            layer: 3,
+            requests_per_sec: None,
+        },
+    ]);
+    assert_eq!(layers.len(), 3);
+    assert_eq!(layers[1].proxies, ["front", "api"]);
+    assert_eq!(layers[1].requests_per_sec, Some(5.5));
+    assert_eq!(layers[2].requests_per_sec, None);
+}
+
+#[test]
+fn test_events_newest_first() {
+    let output = r#"{"status":"start","Type":"container","Action":"start","Actor":{"ID":"a1","Attributes":{"name":"stack-front-2"}},"time":1700000000}
+{"status":"die","Type":"container","Action":"die","Actor":{"ID":"b2","Attributes":{"name":"edge"}},"time":1700000060}
+not json
+"#;
+    let events = parse_events(output);
+    assert_eq!(
+        events,
+        [
+            StackEvent {
+                time: 1700000060,
+                container: "edge".to_string(),
+                action: "die".to_string(),
+            },
+            StackEvent {
+                time: 1700000000,
+                container: "stack-front-2".to_string(),
+                action: "start".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_certificate_expiry() {
+    // 2027-03-05T12:00:00Z
+    let not_after = parse_not_after("notAfter=Mar  5 12:00:00 2027 GMT\n").unwrap();
+    assert_eq!(not_after, 1_804_248_000);
+    assert_eq!(days_until(not_after, not_after - 7 * 86_400 - 60), 7);
+    assert_eq!(days_until(not_after, not_after + 60), -1);
+    assert_eq!(parse_not_after("notBefore=Mar  5 12:00:00 2027 GMT"), None);
+}
+
+#[test]
+fn test_scale_targets() {
+    let config = parse_config(
+        r#"
+[project]
+name = "top-test"
+
+[[proxies]]
+name = "edge"
+type = "nginx"
+external_port = 80
+
+[[proxies]]
+name = "front"
+type = "nginx"
+layer = 2
+
+[proxies.deploy]
+replicas = 3
+"#,
+    );
+
+    let snapshot = snapshot_with("front", 3);
+    assert_eq!(
+        scale_targets(&config, &snapshot, 2, 1).unwrap(),
+        [("front".to_string(), 4)]
+    );
+    assert_eq!(
+        scale_targets(&config, &snapshot_with("front", 1), 2, -1).unwrap(),
+        [("front".to_string(), 1)]
+    );
+    assert!(
+        scale_targets(&config, &snapshot, 1, 1)
+            .unwrap_err()
+            .to_string()
+            .contains("layer 1 has no proxy using deploy.replicas")
+    );
+}
@@ -0,0 +1,344 @@
+//! Terminal UI of `cerberus top`
+//!
+//! Redraws the latest [`Snapshot`] and samples the stack again every
+//! interval. Keys: `↑`/`↓` select a proxy, `r` reloads it, `+`/`-` scale
+//! its layer, `q` quits.
+
+use super::{Dashboard, Snapshot};
+use crate::{CerberusError, Result};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::time::{Duration, Instant};
+
+/// How long a key press is waited for before the screen is redrawn
+const INPUT_POLL: Duration = Duration::from_millis(200);
+
+/// Certificates expiring within this many days are highlighted
+const EXPIRY_WARNING_DAYS: i64 = 14;
+
+/// Run the dashboard until the user quits
+///
+/// # Errors
+/// Returns error if the terminal cannot be drawn to or read from
+pub async fn run(dashboard: Dashboard, interval: Duration) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = App::new(dashboard).run(&mut terminal, interval).await;
+    ratatui::restore();
+    result
+}
+
+/// Dashboard state between redraws
+struct App {
+    dashboard: Dashboard,
+    snapshot: Option<Snapshot>,
+    selected: ListState,
+    message: String,
+}
+
+impl App {
+    fn new(dashboard: Dashboard) -> Self {
+        let mut selected = ListState::default();
+        if !dashboard.proxies().is_empty() {
+            selected.select(Some(0));
+        }
+        Self {
+            dashboard,
+            snapshot: None,
+            selected,
+            message: String::new(),
+        }
+    }
+
+    async fn run(&mut self, terminal: &mut DefaultTerminal, interval: Duration) -> Result<()> {
+        let mut sampled: Option<Instant> = None;
+        loop {
+            if sampled.is_none_or(|at| at.elapsed() >= interval) {
+                match self.dashboard.sample().await {
+                    Ok(snapshot) => self.snapshot = Some(snapshot),
+                    Err(e) => self.message = e.to_string(),
+                }
+                sampled = Some(Instant::now());
+            }
+
+            terminal
+                .draw(|frame| self.draw(frame))
+                .map_err(|e| CerberusError::io("terminal", e))?;
+
+            if !event::poll(INPUT_POLL).map_err(|e| CerberusError::io("terminal", e))? {
+                continue;
+            }
+            let Event::Key(key) = event::read().map_err(|e| CerberusError::io("terminal", e))?
+            else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+                KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+                KeyCode::Char('r') => self.reload().await,
+                KeyCode::Char('+') => self.scale(1).await,
+                KeyCode::Char('-') => self.scale(-1).await,
+                _ => {}
+            }
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let count = self.dashboard.proxies().len();
+        if count == 0 {
+            return;
+        }
+        let current = self.selected.selected().unwrap_or_default();
+        let next = (current as isize + delta).rem_euclid(count as isize) as usize;
+        self.selected.select(Some(next));
+    }
+
+    async fn reload(&mut self) {
+        let (Some(snapshot), Some(index)) = (&self.snapshot, self.selected.selected()) else {
+            return;
+        };
+        let proxy = self.dashboard.proxies()[index].name.clone();
+        self.message = match self.dashboard.reload(snapshot, &proxy).await {
+            Ok(0) => format!("{proxy} has no running container"),
+            Ok(containers) => format!("Reloaded {proxy} ({containers} container(s))"),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    async fn scale(&mut self, delta: i32) {
+        let (Some(snapshot), Some(index)) = (&self.snapshot, self.selected.selected()) else {
+            return;
+        };
+        let layer = self.dashboard.proxies()[index].layer.unwrap_or(1);
+        self.message = match self.dashboard.scale(snapshot, layer, delta).await {
+            Ok(targets) => {
+                let targets: Vec<String> = targets
+                    .iter()
+                    .map(|(proxy, replicas)| format!("{proxy}={replicas}"))
+                    .collect();
+                format!("Scaled layer {layer}: {}", targets.join(", "))
+            }
+            Err(e) => e.to_string(),
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(6),
+                Constraint::Length(8),
+                Constraint::Length(8),
+                Constraint::Length(1),
+            ])
+            .split(frame.area());
+        let middle = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(rows[1]);
+        let bottom = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(rows[2]);
+
+        self.draw_containers(frame, rows[0]);
+        self.draw_proxies(frame, middle[0]);
+        self.draw_layers(frame, middle[1]);
+        self.draw_events(frame, bottom[0]);
+        self.draw_certificates(frame, bottom[1]);
+
+        let help = "↑/↓ select proxy  r reload  +/- scale layer  q quit";
+        let footer = if self.snapshot.is_none() && self.message.is_empty() {
+            format!("Sampling...  |  {help}")
+        } else if self.message.is_empty() {
+            help.to_string()
+        } else {
+            format!("{}  |  {help}", self.message)
+        };
+        frame.render_widget(Paragraph::new(footer), rows[3]);
+    }
+
+    fn draw_containers(&self, frame: &mut Frame, area: Rect) {
+        let containers = self
+            .snapshot
+            .iter()
+            .flat_map(|snapshot| &snapshot.status.containers);
+        let rows = containers.map(|container| {
+            let style = if container.problem().is_some() {
+                Style::default().fg(Color::Red)
+            } else {
+                Style::default()
+            };
+            Row::new([
+                Cell::from(container.service.clone()),
+                Cell::from(container.name.clone()),
+                Cell::from(container.state.clone()),
+                Cell::from(container.health.clone().unwrap_or_else(|| "-".to_string())),
+                Cell::from(container.restarts.to_string()),
+                Cell::from(container.cpu.clone().unwrap_or_else(|| "-".to_string())),
+                Cell::from(container.memory.clone().unwrap_or_else(|| "-".to_string())),
+            ])
+            .style(style)
+        });
+        let title = match &self.snapshot {
+            Some(snapshot) if !snapshot.status.missing.is_empty() => {
+                format!(
+                    " Containers (missing: {}) ",
+                    snapshot.status.missing.join(", ")
+                )
+            }
+            _ => " Containers ".to_string(),
+        };
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(15),
+                Constraint::Percentage(20),
+                Constraint::Percentage(10),
+                Constraint::Percentage(10),
+                Constraint::Percentage(10),
+                Constraint::Percentage(10),
+                Constraint::Percentage(25),
+            ],
+        )
+        .header(
+            Row::new([
+                "SERVICE",
+                "CONTAINER",
+                "STATE",
+                "HEALTH",
+                "RESTARTS",
+                "CPU",
+                "MEMORY",
+            ])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(table, area);
+    }
+
+    fn draw_proxies(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .dashboard
+            .proxies()
+            .iter()
+            .map(|proxy| {
+                let rate = self
+                    .snapshot
+                    .iter()
+                    .flat_map(|snapshot| &snapshot.proxies)
+                    .find(|rate| rate.name == proxy.name)
+                    .and_then(|rate| rate.requests_per_sec);
+                ListItem::new(format!(
+                    "L{} {} ({}) {}",
+                    proxy.layer.unwrap_or(1),
+                    proxy.name,
+                    proxy.proxy_type.as_str(),
+                    format_rate(rate)
+                ))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(" Proxies "))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.selected);
+    }
+
+    fn draw_layers(&self, frame: &mut Frame, area: Rect) {
+        let rows = self
+            .snapshot
+            .iter()
+            .flat_map(|snapshot| &snapshot.layers)
+            .map(|layer| {
+                Row::new([
+                    format!("Layer {}", layer.layer),
+                    format_rate(layer.requests_per_sec),
+                    layer.proxies.join(", "),
+                ])
+            });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(9),
+                Constraint::Length(12),
+                Constraint::Min(10),
+            ],
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Request rates "),
+        );
+        frame.render_widget(table, area);
+    }
+
+    fn draw_events(&self, frame: &mut Frame, area: Rect) {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let items: Vec<ListItem> = self
+            .snapshot
+            .iter()
+            .flat_map(|snapshot| &snapshot.events)
+            .map(|event| {
+                ListItem::new(format!(
+                    "{:>4}m ago  {} {}",
+                    now.saturating_sub(event.time) / 60,
+                    event.container,
+                    event.action
+                ))
+            })
+            .collect();
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Scaling and restarts (1h) "),
+        );
+        frame.render_widget(list, area);
+    }
+
+    fn draw_certificates(&self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .snapshot
+            .iter()
+            .flat_map(|snapshot| &snapshot.certificates)
+            .map(
+                |certificate| match (certificate.days_left, &certificate.error) {
+                    (Some(days), _) => {
+                        let style = if days < EXPIRY_WARNING_DAYS {
+                            Style::default().fg(Color::Red)
+                        } else {
+                            Style::default()
+                        };
+                        ListItem::new(format!("{}: {days} days left", certificate.domain))
+                            .style(style)
+                    }
+                    (None, error) => ListItem::new(format!(
+                        "{}: {}",
+                        certificate.domain,
+                        error.as_deref().unwrap_or("unknown")
+                    ))
+                    .style(Style::default().fg(Color::Yellow)),
+                },
+            )
+            .collect();
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Certificates "),
+        );
+        frame.render_widget(list, area);
+    }
+}
+
+/// Requests per second for display
+fn format_rate(rate: Option<f64>) -> String {
+    rate.map_or_else(|| "-".to_string(), |rate| format!("{rate:.1} req/s"))
+}
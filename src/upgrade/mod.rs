@@ -0,0 +1,532 @@
+//! # Upgrades of deployed stacks
+//!
+//! Compares the files of a deployed output directory with a new generation
+//! and works out, per Compose service, the least disruptive way to apply the
+//! change. Services whose definition changed are recreated (or rebuilt when
+//! their Dockerfile changed), proxies whose mounted configuration changed
+//! are reloaded in place where the proxy supports it, and every other
+//! service keeps running. Steps run in dependency order, and each service
+//! that is started again must report healthy before the next step runs.
+//! Before the first step, the host ports of every service that is started
+//! are checked, so a port held elsewhere aborts the upgrade up front.
+
+use crate::{
+    CerberusError, Result,
+    config::{Config, ProxyConfig},
+    generators::COMPOSE_FILE,
+    watch::ChangeSummary,
+};
+use serde::Serialize;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+
+mod ports;
+pub use ports::{PortConflict, PublishedPort, port_conflicts};
+
+/// Time between two health polls of a service
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How a service is brought up to date
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpgradeAction {
+    /// Start a service that is new in this generation
+    Create,
+    /// Replace the containers because the service definition changed
+    Recreate,
+    /// Rebuild the image and replace the containers
+    Rebuild,
+    /// Signal the running proxy to reload its configuration
+    Reload,
+    /// Restart the containers so they read their changed files
+    Restart,
+    /// Stop and remove a service that is no longer generated
+    Remove,
+}
+
+/// One step of an upgrade
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct UpgradeStep {
+    /// Compose service the step applies to
+    pub service: String,
+    /// What is done to the service
+    pub action: UpgradeAction,
+    /// Signal sent by a reload
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal: Option<&'static str>,
+    /// Why the step is needed
+    pub reason: String,
+}
+
+/// Ordered steps that bring a deployed stack up to date
+#[derive(Debug, Clone, Serialize, PartialEq, Default)]
+pub struct UpgradePlan {
+    /// Steps in the order they are applied
+    pub steps: Vec<UpgradeStep>,
+    /// Services that keep running untouched
+    pub unchanged: Vec<String>,
+}
+
+impl UpgradePlan {
+    /// Plan the upgrade from the deployed files to a new generation
+    ///
+    /// Both maps hold file contents keyed by their path relative to the
+    /// output directory, as returned by [`crate::watch::snapshot`] and
+    /// [`crate::Cerberus::generate_to_memory`].
+    ///
+    /// # Errors
+    /// Returns error if either Compose file cannot be parsed
+    pub fn between(
+        config: &Config,
+        deployed: &BTreeMap<PathBuf, Vec<u8>>,
+        next: &BTreeMap<PathBuf, Vec<u8>>,
+    ) -> Result<Self> {
+        let before = compose_services(deployed)?;
+        let after = compose_services(next)?;
+
+        let summary = ChangeSummary::between(deployed, next);
+        let changed: Vec<&PathBuf> = summary
+            .added
+            .iter()
+            .chain(&summary.modified)
+            .chain(&summary.removed)
+            .filter(|path| *path != Path::new(COMPOSE_FILE))
+            .collect();
+        let changed_under = |sources: &[PathBuf]| -> Vec<String> {
+            changed
+                .iter()
+                .filter(|path| sources.iter().any(|source| path.starts_with(source)))
+                .map(|path| path.display().to_string())
+                .collect()
+        };
+
+        let mut plan = Self::default();
+        let mut steps = BTreeMap::new();
+        for (name, definition) in &after {
+            let step = |action, reason: String| UpgradeStep {
+                service: name.clone(),
+                action,
+                signal: None,
+                reason,
+            };
+
+            let rebuilt = changed_under(&build_context(definition));
+            let step = match before.get(name) {
+                None => step(UpgradeAction::Create, "new service".to_string()),
+                Some(previous) if previous != definition => {
+                    if rebuilt.is_empty() {
+                        step(
+                            UpgradeAction::Recreate,
+                            "service definition changed".to_string(),
+                        )
+                    } else {
+                        step(
+                            UpgradeAction::Rebuild,
+                            format!("service definition and {} changed", rebuilt.join(", ")),
+                        )
+                    }
+                }
+                Some(_) if !rebuilt.is_empty() => step(
+                    UpgradeAction::Rebuild,
+                    format!("{} changed", rebuilt.join(", ")),
+                ),
+                Some(_) => {
+                    let env_files = changed_under(
+                        &strings(&definition["env_file"])
+                            .into_iter()
+                            .map(relative_path)
+                            .collect::<Vec<_>>(),
+                    );
+                    let mounted = changed_under(&bind_mounts(definition));
+                    if !env_files.is_empty() {
+                        step(
+                            UpgradeAction::Recreate,
+                            format!("{} changed", env_files.join(", ")),
+                        )
+                    } else if mounted.is_empty() {
+                        plan.unchanged.push(name.clone());
+                        continue;
+                    } else {
+                        let reason = format!("{} changed", mounted.join(", "));
+                        match proxy_for(config, name).and_then(ProxyConfig::reload_signal) {
+                            Some(signal) => UpgradeStep {
+                                signal: Some(signal),
+                                ..step(UpgradeAction::Reload, reason)
+                            },
+                            None => step(UpgradeAction::Restart, reason),
+                        }
+                    }
+                }
+            };
+            steps.insert(name.clone(), step);
+        }
+
+        for name in dependency_order(config, &after) {
+            if let Some(step) = steps.remove(&name) {
+                plan.steps.push(step);
+            }
+        }
+
+        // Removed services go last, once their replacements are up
+        plan.steps.extend(
+            before
+                .keys()
+                .filter(|name| !after.contains_key(*name))
+                .map(|name| UpgradeStep {
+                    service: name.clone(),
+                    action: UpgradeAction::Remove,
+                    signal: None,
+                    reason: "no longer generated".to_string(),
+                }),
+        );
+
+        Ok(plan)
+    }
+
+    /// Whether the deployed stack is already up to date
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+/// Service definitions of the Compose file in a set of files
+fn compose_services(files: &BTreeMap<PathBuf, Vec<u8>>) -> Result<BTreeMap<String, Value>> {
+    let Some(content) = files.get(Path::new(COMPOSE_FILE)) else {
+        return Ok(BTreeMap::new());
+    };
+
+    let compose: Value = serde_yaml::from_slice(content)
+        .map_err(|e| CerberusError::config(format!("Invalid {COMPOSE_FILE}: {e}")))?;
+    Ok(compose["services"]
+        .as_mapping()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, definition)| Some((name.as_str()?.to_string(), definition.clone())))
+        .collect())
+}
+
+/// A string or a list of strings
+fn strings(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(value) => vec![value],
+        Value::Sequence(values) => values.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Host paths bind-mounted into a service
+fn bind_mounts(definition: &Value) -> Vec<PathBuf> {
+    definition["volumes"]
+        .as_sequence()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .filter_map(|volume| volume.split(':').next())
+        .filter(|source| source.starts_with("./"))
+        .map(relative_path)
+        .collect()
+}
+
+/// Build context of a service, if it builds its image
+fn build_context(definition: &Value) -> Vec<PathBuf> {
+    match &definition["build"] {
+        Value::String(context) => vec![relative_path(context)],
+        build => strings(&build["context"])
+            .into_iter()
+            .map(relative_path)
+            .collect(),
+    }
+}
+
+/// A Compose path relative to the output directory
+fn relative_path(path: &str) -> PathBuf {
+    PathBuf::from(path.trim_start_matches("./"))
+}
+
+/// Proxy a Compose service runs, including scaled instances (`proxy-2`)
+fn proxy_for<'a>(config: &'a Config, service: &str) -> Option<&'a ProxyConfig> {
+    // Exact names win: `proxy-2` is a proxy of its own, not instance 2 of `proxy`
+    config
+        .proxies
+        .iter()
+        .find(|proxy| service == proxy.name)
+        .or_else(|| {
+            config.proxies.iter().find(|proxy| {
+                service
+                    .strip_prefix(&proxy.name)
+                    .and_then(|suffix| suffix.strip_prefix('-'))
+                    .is_some_and(|instance| instance.parse::<u8>().is_ok())
+            })
+        })
+}
+
+/// Services ordered so that dependencies come first
+///
+/// Among services whose dependencies are satisfied, backends and support
+/// services go before proxies, and inner proxy layers before outer ones, so
+/// the edge is updated last.
+fn dependency_order(config: &Config, services: &BTreeMap<String, Value>) -> Vec<String> {
+    let rank = |name: &str| match proxy_for(config, name) {
+        Some(proxy) => (1, u8::MAX - proxy.layer.unwrap_or(1), name.to_string()),
+        None => (0, 0, name.to_string()),
+    };
+    let mut pending: BTreeMap<&String, BTreeSet<String>> = services
+        .iter()
+        .map(|(name, definition)| {
+            let depends_on = &definition["depends_on"];
+            let dependencies = match depends_on.as_mapping() {
+                Some(mapping) => mapping
+                    .keys()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect(),
+                None => strings(depends_on)
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+            };
+            (name, dependencies)
+        })
+        .collect();
+
+    let mut order = Vec::new();
+    while !pending.is_empty() {
+        let next = pending
+            .iter()
+            .filter(|(_, dependencies)| {
+                dependencies
+                    .iter()
+                    .all(|dependency| !pending.contains_key(dependency))
+            })
+            .map(|(name, _)| *name)
+            .min_by_key(|name| rank(name))
+            // A dependency cycle cannot be started by Compose either; keep going
+            .or_else(|| pending.keys().copied().min_by_key(|name| rank(name)));
+        let Some(next) = next else { break };
+        pending.remove(next);
+        order.push(next.clone());
+    }
+    order
+}
+
+/// Applies an upgrade plan with `docker compose`
+pub struct Upgrader {
+    output_dir: PathBuf,
+    health_timeout: Duration,
+}
+
+impl Upgrader {
+    /// Create an upgrader for the stack generated into `output_dir`
+    pub fn new(output_dir: &Path) -> Self {
+        Self {
+            output_dir: output_dir.to_path_buf(),
+            health_timeout: Duration::from_secs(120),
+        }
+    }
+
+    /// Time a started service has to become healthy
+    pub fn health_timeout(mut self, timeout: Duration) -> Self {
+        self.health_timeout = timeout;
+        self
+    }
+
+    /// Apply the plan to the running stack
+    ///
+    /// The new files must already be written to the output directory.
+    /// Removed services are stopped through the previously deployed Compose
+    /// file, which is passed in since it has been replaced on disk.
+    ///
+    /// # Errors
+    /// Returns error at the first step that fails or whose service does not
+    /// become healthy; later steps are not applied
+    pub async fn apply(&self, plan: &UpgradePlan, deployed_compose: Option<&[u8]>) -> Result<()> {
+        for step in &plan.steps {
+            let service = step.service.as_str();
+            match step.action {
+                UpgradeAction::Create | UpgradeAction::Recreate => {
+                    self.compose(service, &["up", "-d", "--no-deps", service], None)
+                        .await?;
+                }
+                UpgradeAction::Rebuild => {
+                    self.compose(
+                        service,
+                        &["up", "-d", "--no-deps", "--build", service],
+                        None,
+                    )
+                    .await?;
+                }
+                UpgradeAction::Restart => {
+                    self.compose(service, &["restart", service], None).await?;
+                }
+                UpgradeAction::Reload => {
+                    let signal = step.signal.unwrap_or("HUP");
+                    self.compose(service, &["kill", "--signal", signal, service], None)
+                        .await?;
+                }
+                UpgradeAction::Remove => {
+                    let Some(compose) = deployed_compose else {
+                        continue;
+                    };
+                    self.compose(
+                        service,
+                        &["rm", "--stop", "--force", service],
+                        Some(compose),
+                    )
+                    .await?;
+                }
+            }
+
+            if matches!(
+                step.action,
+                UpgradeAction::Create
+                    | UpgradeAction::Recreate
+                    | UpgradeAction::Rebuild
+                    | UpgradeAction::Restart
+            ) {
+                self.wait_healthy(service).await?;
+            }
+            tracing::info!("{:?} {}: {}", step.action, service, step.reason);
+        }
+        Ok(())
+    }
+
+    /// Run `docker compose` for a service
+    ///
+    /// With `compose_file`, the file is read from stdin instead of the
+    /// output directory.
+    async fn compose(
+        &self,
+        service: &str,
+        args: &[&str],
+        compose_file: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        let mut command = tokio::process::Command::new("docker");
+        command
+            .arg("compose")
+            .arg("--project-directory")
+            .arg(&self.output_dir)
+            .arg("-f");
+        match compose_file {
+            Some(_) => command.arg("-"),
+            None => command.arg(self.output_dir.join(COMPOSE_FILE)),
+        };
+
+        let mut child = command
+            .args(args)
+            .stdin(if compose_file.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| CerberusError::upgrade(service, format!("failed to run docker: {e}")))?;
+
+        if let (Some(content), Some(mut stdin)) = (compose_file, child.stdin.take()) {
+            stdin
+                .write_all(content)
+                .await
+                .map_err(|e| CerberusError::upgrade(service, e.to_string()))?;
+        }
+
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|e| CerberusError::upgrade(service, e.to_string()))?;
+        if !output.status.success() {
+            return Err(CerberusError::upgrade(
+                service,
+                format!(
+                    "docker compose {} exited with {}: {}",
+                    args.join(" "),
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        Ok(output.stdout)
+    }
+
+    /// Wait until every container of a service runs and passes its healthcheck
+    async fn wait_healthy(&self, service: &str) -> Result<()> {
+        let started = Instant::now();
+        loop {
+            let output = self
+                .compose(service, &["ps", "--all", "--format", "json", service], None)
+                .await?;
+            match service_health(&output) {
+                ServiceHealth::Healthy => return Ok(()),
+                ServiceHealth::Failed(reason) => {
+                    return Err(CerberusError::upgrade(service, reason));
+                }
+                ServiceHealth::Starting => {}
+            }
+
+            if started.elapsed() >= self.health_timeout {
+                return Err(CerberusError::upgrade(
+                    service,
+                    format!(
+                        "not healthy after {}s; remaining steps were not applied",
+                        self.health_timeout.as_secs()
+                    ),
+                ));
+            }
+            tokio::time::sleep(HEALTH_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Health of a service's containers
+#[derive(Debug, Clone, PartialEq)]
+enum ServiceHealth {
+    /// Every container runs and passes its healthcheck, if it has one
+    Healthy,
+    /// Containers are starting or their healthcheck has not passed yet
+    Starting,
+    /// A container exited or its healthcheck failed
+    Failed(String),
+}
+
+/// Health from `docker compose ps --format json`
+///
+/// Compose prints either one JSON array or one JSON object per line,
+/// depending on its version.
+fn service_health(output: &[u8]) -> ServiceHealth {
+    let text = String::from_utf8_lossy(output);
+    let containers: Vec<serde_json::Value> =
+        match serde_json::from_str::<Vec<serde_json::Value>>(text.trim()) {
+            Ok(containers) => containers,
+            Err(_) => text
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect(),
+        };
+    if containers.is_empty() {
+        return ServiceHealth::Starting;
+    }
+
+    let mut health = ServiceHealth::Healthy;
+    for container in &containers {
+        let name = container["Name"].as_str().unwrap_or("container");
+        let state = container["State"].as_str().unwrap_or_default();
+        match (state, container["Health"].as_str().unwrap_or_default()) {
+            ("running", "" | "healthy") => {}
+            ("running", "unhealthy") => {
+                return ServiceHealth::Failed(format!("{name} is unhealthy"));
+            }
+            ("exited" | "dead", _) => {
+                return ServiceHealth::Failed(format!("{name} {state}"));
+            }
+            _ => health = ServiceHealth::Starting,
+        }
+    }
+    health
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,334 @@
+//! Pre-flight check of the host ports an upgrade publishes
+//!
+//! Before any service is touched, every port published by a service the
+//! upgrade starts must be free, or held by a container of this same stack
+//! (the previous version of the service releases it when it is replaced).
+//! Ports held by other containers or processes are reported by name, rather
+//! than surfacing as a failed bind halfway through the upgrade.
+
+use super::{COMPOSE_FILE, UpgradeAction, UpgradePlan, compose_services};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, TcpListener, UdpSocket};
+use std::path::{Path, PathBuf};
+
+/// Compose label holding the directory a container's project runs from
+const WORKING_DIR_LABEL: &str = "com.docker.compose.project.working_dir";
+
+/// Compose label holding a container's project name
+const PROJECT_LABEL: &str = "com.docker.compose.project";
+
+/// A host port published by a Compose service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishedPort {
+    /// Compose service publishing the port
+    pub service: String,
+    /// Host address the port is bound to (`None` = every address)
+    pub host_ip: Option<IpAddr>,
+    /// Host port
+    pub port: u16,
+    /// `tcp` or `udp`
+    pub protocol: String,
+}
+
+impl PublishedPort {
+    /// Whether two bindings cannot coexist on the host
+    fn overlaps(&self, other: &PublishedPort) -> bool {
+        self.port == other.port
+            && self.protocol == other.protocol
+            && match (self.host_ip, other.host_ip) {
+                (Some(a), Some(b)) => a == b || a.is_unspecified() || b.is_unspecified(),
+                _ => true,
+            }
+    }
+}
+
+impl fmt::Display for PublishedPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.host_ip {
+            Some(IpAddr::V6(ip)) => write!(f, "[{ip}]:")?,
+            Some(ip) => write!(f, "{ip}:")?,
+            None => {}
+        }
+        write!(f, "{}/{}", self.port, self.protocol)
+    }
+}
+
+/// A port the upgrade would publish that something else holds
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortConflict {
+    /// The port the upgrade needs
+    pub port: PublishedPort,
+    /// What holds it, e.g. `container web-1 (compose project other)`
+    pub holder: String,
+}
+
+impl fmt::Display for PortConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} publishes {}, which is already held by {}",
+            self.port.service, self.port, self.holder
+        )
+    }
+}
+
+/// A running container and the host ports it publishes
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Container {
+    pub(super) name: String,
+    pub(super) project: Option<String>,
+    pub(super) working_dir: Option<PathBuf>,
+    pub(super) ports: Vec<PublishedPort>,
+}
+
+/// Find the ports an upgrade would fail to bind
+///
+/// `next` holds the new generation, as passed to [`UpgradePlan::between`].
+/// Only services the plan creates, recreates, or rebuilds are checked;
+/// running services keep the ports they hold.
+///
+/// # Errors
+/// Returns error if the new Compose file cannot be parsed
+pub async fn port_conflicts(
+    output_dir: &Path,
+    plan: &UpgradePlan,
+    next: &BTreeMap<PathBuf, Vec<u8>>,
+) -> crate::Result<Vec<PortConflict>> {
+    let published: Vec<PublishedPort> = compose_services(next)?
+        .iter()
+        .flat_map(|(service, definition)| published_ports(service, definition))
+        .collect();
+    let started: Vec<&PublishedPort> = published
+        .iter()
+        .filter(|port| {
+            plan.steps.iter().any(|step| {
+                step.service == port.service
+                    && matches!(
+                        step.action,
+                        UpgradeAction::Create | UpgradeAction::Recreate | UpgradeAction::Rebuild
+                    )
+            })
+        })
+        .collect();
+    if started.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let containers = running_containers().await;
+    let stack_dir = std::path::absolute(output_dir).unwrap_or_else(|_| output_dir.to_path_buf());
+
+    let mut conflicts = Vec::new();
+    for port in started {
+        if let Some(other) = published
+            .iter()
+            .find(|other| other.service != port.service && other.overlaps(port))
+        {
+            conflicts.push(PortConflict {
+                port: port.clone(),
+                holder: format!("service {} in {COMPOSE_FILE}", other.service),
+            });
+            continue;
+        }
+
+        let holder = containers
+            .iter()
+            .find(|container| container.ports.iter().any(|held| held.overlaps(port)));
+        match holder {
+            Some(container) if container.working_dir.as_deref() == Some(stack_dir.as_path()) => {}
+            Some(container) => conflicts.push(PortConflict {
+                port: port.clone(),
+                holder: match &container.project {
+                    Some(project) => {
+                        format!("container {} (compose project {project})", container.name)
+                    }
+                    None => format!("container {}", container.name),
+                },
+            }),
+            None if is_in_use(port) => conflicts.push(PortConflict {
+                port: port.clone(),
+                holder: process_holding(port)
+                    .await
+                    .unwrap_or_else(|| "another process".to_string()),
+            }),
+            None => {}
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Host ports a Compose service definition publishes
+///
+/// Reads both the short (`"127.0.0.1:8080-8081:80/udp"`) and the long
+/// (`published:`/`host_ip:`/`protocol:`) syntax.
+pub(super) fn published_ports(service: &str, definition: &Value) -> Vec<PublishedPort> {
+    let mut ports = Vec::new();
+    for entry in definition["ports"].as_sequence().into_iter().flatten() {
+        let (host_ip, range, protocol) = match entry {
+            Value::String(spec) => {
+                let (mapping, protocol) = spec.split_once('/').unwrap_or((spec, "tcp"));
+                // host_ip:published:target, published:target, or target alone
+                let mut parts = mapping.rsplitn(3, ':');
+                let _target = parts.next();
+                let Some(published) = parts.next() else {
+                    continue;
+                };
+                let host_ip = parts.next().and_then(|ip| {
+                    ip.trim_start_matches('[')
+                        .trim_end_matches(']')
+                        .parse()
+                        .ok()
+                });
+                (host_ip, published.to_string(), protocol.to_string())
+            }
+            Value::Mapping(_) => {
+                let published = match &entry["published"] {
+                    Value::Number(port) => port.to_string(),
+                    Value::String(port) => port.clone(),
+                    _ => continue,
+                };
+                (
+                    entry["host_ip"].as_str().and_then(|ip| ip.parse().ok()),
+                    published,
+                    entry["protocol"].as_str().unwrap_or("tcp").to_string(),
+                )
+            }
+            _ => continue,
+        };
+
+        for port in port_range(&range) {
+            ports.push(PublishedPort {
+                service: service.to_string(),
+                host_ip,
+                port,
+                protocol: protocol.clone(),
+            });
+        }
+    }
+    ports
+}
+
+/// Ports of `80` or `8080-8082`
+fn port_range(range: &str) -> Vec<u16> {
+    let (start, end) = range.split_once('-').unwrap_or((range, range));
+    match (start.trim().parse(), end.trim().parse()) {
+        (Ok(start), Ok(end)) => (start..=end).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Running containers with their published ports
+///
+/// Without a reachable Docker daemon no container can hold a port, and the
+/// bind test alone decides.
+async fn running_containers() -> Vec<Container> {
+    let format = format!(
+        "{{{{.Names}}}}\t{{{{.Ports}}}}\t{{{{.Label \"{PROJECT_LABEL}\"}}}}\t{{{{.Label \"{WORKING_DIR_LABEL}\"}}}}"
+    );
+    let output = tokio::process::Command::new("docker")
+        .args(["ps", "--format", &format])
+        .output()
+        .await;
+    match output {
+        Ok(output) if output.status.success() => parse_containers(&output.stdout),
+        Ok(output) => {
+            tracing::warn!(
+                "Could not list containers: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            Vec::new()
+        }
+        Err(e) => {
+            tracing::warn!("Could not list containers: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// Containers from `docker ps` lines of name, ports, project, and working dir
+pub(super) fn parse_containers(output: &[u8]) -> Vec<Container> {
+    let text = String::from_utf8_lossy(output);
+    let mut containers = Vec::new();
+    for line in text.lines() {
+        let mut fields = line.split('\t');
+        let Some(name) = fields.next().filter(|name| !name.is_empty()) else {
+            continue;
+        };
+        let bindings = fields.next().unwrap_or_default();
+        let project = fields.next().filter(|value| !value.is_empty());
+        let working_dir = fields.next().filter(|value| !value.is_empty());
+
+        // "0.0.0.0:80->80/tcp, [::]:80->80/tcp, 5432/tcp"
+        let mut ports = Vec::new();
+        for binding in bindings.split(", ") {
+            let Some((host, target)) = binding.split_once("->") else {
+                continue;
+            };
+            let Some((ip, range)) = host.rsplit_once(':') else {
+                continue;
+            };
+            let protocol = target.split_once('/').map_or("tcp", |(_, p)| p);
+            let host_ip = ip
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+                .ok();
+            for port in port_range(range) {
+                ports.push(PublishedPort {
+                    service: name.to_string(),
+                    host_ip,
+                    port,
+                    protocol: protocol.to_string(),
+                });
+            }
+        }
+
+        containers.push(Container {
+            name: name.to_string(),
+            project: project.map(str::to_string),
+            working_dir: working_dir.map(PathBuf::from),
+            ports,
+        });
+    }
+    containers
+}
+
+/// Whether binding the port on this host fails because it is taken
+///
+/// Other failures, such as missing permission for ports below 1024, are
+/// left for Docker, which binds with its own privileges.
+fn is_in_use(port: &PublishedPort) -> bool {
+    let ip = port.host_ip.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    let bound = match port.protocol.as_str() {
+        "udp" => UdpSocket::bind((ip, port.port)).map(drop),
+        _ => TcpListener::bind((ip, port.port)).map(drop),
+    };
+    matches!(bound, Err(e) if e.kind() == ErrorKind::AddrInUse)
+}
+
+/// Name and PID of the process listening on a port, from `ss`
+async fn process_holding(port: &PublishedPort) -> Option<String> {
+    let kind = if port.protocol == "udp" { "-u" } else { "-t" };
+    let output = tokio::process::Command::new("ss")
+        .args(["-H", "-l", "-n", "-p", kind])
+        .arg(format!("sport = :{}", port.port))
+        .output()
+        .await
+        .ok()?;
+    process_from_ss(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// `nginx (pid 812)` from `users:(("nginx",pid=812,fd=6))` in `ss` output
+pub(super) fn process_from_ss(output: &str) -> Option<String> {
+    let users = output.split_once("users:((\"")?.1;
+    let (name, rest) = users.split_once('"')?;
+    let pid = rest
+        .split_once("pid=")
+        .and_then(|(_, rest)| rest.split([',', ')']).next());
+    Some(match pid {
+        Some(pid) => format!("process {name} (pid {pid})"),
+        None => format!("process {name}"),
+    })
+}
@@ -0,0 +1,273 @@
+//! # Tests for upgrade planning
+//!
+//! These tests verify which services an upgrade recreates, reloads, or
+//! leaves running, the order the steps run in, and how container health is
+//! read from `docker compose ps`.
+
+use super::*;
+use crate::Cerberus;
+use pretty_assertions::assert_eq;
+
+/// Helper function to render a two-layer stack in memory
+///
+/// The edge proxy is HAProxy, proxy-2 is Caddy, and `web-app` runs its own
+/// container. `extra` is appended to the configuration.
+fn render_stack(domain: &str, extra: &str) -> (Config, BTreeMap<PathBuf, Vec<u8>>) {
+    let config: Config = toml::from_str(&format!(
+        r#"
+[project]
+name = "upgrade-test"
+
+[[proxies]]
+name = "proxy"
+type = "haproxy"
+external_port = 80
+default_upstream = "http://proxy-2:80"
+
+[[proxies]]
+name = "proxy-2"
+type = "caddy"
+layer = 2
+
+[[services]]
+name = "web-app"
+domain = "{domain}"
+upstream = "http://web-app:3000"
+image = "example/web:1.0"
+{extra}
+"#
+    ))
+    .expect("Failed to parse config");
+    config.validate().expect("Config should be valid");
+
+    // An empty output directory keeps the rendered paths relative
+    let files = Cerberus::from_config(config.clone(), Path::new(""))
+        .generate_to_memory()
+        .expect("Rendering should succeed");
+    (config, files)
+}
+
+/// Helper function to list the planned actions
+fn actions(plan: &UpgradePlan) -> Vec<(&str, UpgradeAction)> {
+    plan.steps
+        .iter()
+        .map(|step| (step.service.as_str(), step.action))
+        .collect()
+}
+
+#[test]
+fn test_first_deployment_creates_inner_layers_first() {
+    let (config, next) = render_stack("app.example.com", "");
+    let plan = UpgradePlan::between(&config, &BTreeMap::new(), &next).expect("Should plan");
+
+    assert_eq!(
+        actions(&plan),
+        vec![
+            ("web-app", UpgradeAction::Create),
+            ("proxy-2", UpgradeAction::Create),
+            ("proxy", UpgradeAction::Create),
+        ]
+    );
+}
+
+#[test]
+fn test_unchanged_stack_has_no_steps() {
+    let (config, files) = render_stack("app.example.com", "");
+    let plan = UpgradePlan::between(&config, &files, &files).expect("Should plan");
+
+    assert!(plan.is_empty());
+    assert_eq!(plan.unchanged, vec!["proxy", "proxy-2", "web-app"]);
+}
+
+#[test]
+fn test_config_change_reloads_or_restarts_proxies() {
+    let (_, deployed) = render_stack("app.example.com", "");
+    let (config, next) = render_stack("www.example.com", "");
+    let plan = UpgradePlan::between(&config, &deployed, &next).expect("Should plan");
+
+    // The backend's labels carry the domain; Caddy runs with its admin API
+    // off and is restarted, while HAProxy reloads in place
+    assert_eq!(
+        actions(&plan),
+        vec![
+            ("web-app", UpgradeAction::Recreate),
+            ("proxy-2", UpgradeAction::Restart),
+            ("proxy", UpgradeAction::Reload),
+        ]
+    );
+    assert_eq!(plan.steps[2].signal, Some("USR2"));
+    assert!(
+        plan.steps[1]
+            .reason
+            .contains("proxy-configs/proxy-2/Caddyfile")
+    );
+    assert!(plan.unchanged.is_empty());
+}
+
+#[test]
+fn test_changed_and_removed_services() {
+    let (_, deployed) = render_stack(
+        "app.example.com",
+        r#"
+[[services]]
+name = "legacy"
+domain = "legacy.example.com"
+upstream = "http://legacy:8080"
+image = "example/legacy:1.0"
+"#,
+    );
+    let (config, next) = render_stack(
+        "app.example.com",
+        r#"
+[services.environment]
+FEATURE = "on"
+"#,
+    );
+    let plan = UpgradePlan::between(&config, &deployed, &next).expect("Should plan");
+
+    let steps = actions(&plan);
+    assert_eq!(steps[0], ("web-app", UpgradeAction::Recreate));
+    assert_eq!(
+        steps.last(),
+        Some(&("legacy", UpgradeAction::Remove)),
+        "Removals run after the replacements are up"
+    );
+}
+
+#[test]
+fn test_service_health_from_compose_ps() {
+    // Newer Compose prints one object per line
+    let lines = br#"{"Name":"proxy","State":"running","Health":""}
+{"Name":"proxy-2","State":"running","Health":"healthy"}"#;
+    assert_eq!(service_health(lines), ServiceHealth::Healthy);
+
+    // Older Compose prints a single array
+    let array = br#"[{"Name":"web","State":"running","Health":"starting"}]"#;
+    assert_eq!(service_health(array), ServiceHealth::Starting);
+
+    let exited = br#"[{"Name":"web","State":"exited","Health":""}]"#;
+    assert_eq!(
+        service_health(exited),
+        ServiceHealth::Failed("web exited".to_string())
+    );
+    assert_eq!(service_health(b""), ServiceHealth::Starting);
+}
+
+/// Helper function to build a Compose file and a plan creating every service
+fn compose_with_ports(services: &[(&str, &str)]) -> (UpgradePlan, BTreeMap<PathBuf, Vec<u8>>) {
+    let mut compose = String::from("services:\n");
+    let mut plan = UpgradePlan::default();
+    for (service, port) in services {
+        compose.push_str(&format!("  {service}:\n    ports:\n      - \"{port}\"\n"));
+        plan.steps.push(UpgradeStep {
+            service: service.to_string(),
+            action: UpgradeAction::Create,
+            signal: None,
+            reason: "new service".to_string(),
+        });
+    }
+    let files = BTreeMap::from([(PathBuf::from(COMPOSE_FILE), compose.into_bytes())]);
+    (plan, files)
+}
+
+#[test]
+fn test_published_ports_short_and_long_syntax() {
+    let definition: Value = serde_yaml::from_str(
+        r#"
+ports:
+  - "80:80"
+  - "127.0.0.1:9000-9001:9000-9001"
+  - "[::1]:5353:53/udp"
+  - "3000"
+  - target: 443
+    published: 8443
+    host_ip: 10.0.0.5
+"#,
+    )
+    .unwrap();
+
+    let ports: Vec<String> = ports::published_ports("proxy", &definition)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        ports,
+        vec![
+            "80/tcp",
+            "127.0.0.1:9000/tcp",
+            "127.0.0.1:9001/tcp",
+            "[::1]:5353/udp",
+            "10.0.0.5:8443/tcp",
+        ]
+    );
+}
+
+#[test]
+fn test_port_holders_from_docker_ps_and_ss() {
+    let containers = ports::parse_containers(
+        b"web-1\t0.0.0.0:8080->80/tcp, [::]:8080->80/tcp\tother\t/srv/other\n\
+          db\t5432/tcp\t\t\n",
+    );
+    assert_eq!(containers.len(), 2);
+    assert_eq!(containers[0].name, "web-1");
+    assert_eq!(containers[0].project.as_deref(), Some("other"));
+    assert_eq!(
+        containers[0].working_dir.as_deref(),
+        Some(Path::new("/srv/other"))
+    );
+    let held: Vec<String> = containers[0]
+        .ports
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(held, vec!["0.0.0.0:8080/tcp", "[::]:8080/tcp"]);
+    // Exposed but unpublished ports hold nothing on the host
+    assert!(containers[1].ports.is_empty());
+    assert_eq!(containers[1].project, None);
+
+    assert_eq!(
+        ports::process_from_ss(
+            "LISTEN 0 511 0.0.0.0:80 0.0.0.0:* users:((\"nginx\",pid=812,fd=6))\n"
+        ),
+        Some("process nginx (pid 812)".to_string())
+    );
+    assert_eq!(ports::process_from_ss(""), None);
+}
+
+#[tokio::test]
+async fn test_port_conflicts_name_the_holder() {
+    // A port held by another process on this host
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let held = listener.local_addr().unwrap().port();
+
+    let (plan, next) = compose_with_ports(&[
+        ("proxy", &format!("127.0.0.1:{held}:80")),
+        ("web-app", "127.0.0.1:18080:80"),
+        ("admin", "18080:8080"),
+    ]);
+    let conflicts = port_conflicts(Path::new("built"), &plan, &next)
+        .await
+        .expect("Compose file should parse");
+
+    let messages: Vec<String> = conflicts.iter().map(ToString::to_string).collect();
+    assert_eq!(messages.len(), 3, "{messages:?}");
+    assert!(
+        messages[0]
+            .starts_with("admin publishes 18080/tcp, which is already held by service web-app")
+    );
+    assert!(messages[1].starts_with(&format!(
+        "proxy publishes 127.0.0.1:{held}/tcp, which is already held by "
+    )));
+    assert!(messages[2].contains("held by service admin in docker-compose.yaml"));
+
+    // Ports of running services are not checked
+    let mut plan = plan;
+    plan.steps.clear();
+    assert!(
+        port_conflicts(Path::new("built"), &plan, &next)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    drop(listener);
+}
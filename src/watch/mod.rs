@@ -0,0 +1,313 @@
+//! # Watch mode for Cerberus
+//!
+//! Watches the configuration file, the files it includes, and any extra
+//! paths for changes, regenerates all outputs, and reports what changed.
+//! Optionally applies the new configuration with `docker compose up -d` or
+//! hot-reloads the affected proxy containers with [`crate::reload`].
+
+use crate::{
+    Cerberus, CerberusError, Result,
+    config::{self, Config},
+    generators::COMPOSE_FILE,
+};
+use notify::{RecursiveMode, Watcher};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+pub use crate::generators::snapshot;
+
+/// Time to wait for further events before regenerating
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Options for watch mode
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Configuration file to watch
+    pub config_path: PathBuf,
+    /// Output directory for generated files
+    pub output_dir: PathBuf,
+    /// Additional files or directories to watch (e.g. a templates dir)
+    pub extra_paths: Vec<PathBuf>,
+    /// Run `docker compose up -d` after each successful generation
+    pub compose_up: bool,
+    /// Reload proxy containers whose configuration changed in place
+    pub reload: bool,
+    /// age identity file used to decrypt encrypted secrets
+    pub age_key: Option<PathBuf>,
+}
+
+/// Files added, modified, or removed between two generations
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangeSummary {
+    pub added: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+impl ChangeSummary {
+    /// Compare two output snapshots
+    pub fn between(
+        before: &BTreeMap<PathBuf, Vec<u8>>,
+        after: &BTreeMap<PathBuf, Vec<u8>>,
+    ) -> Self {
+        let mut summary = Self::default();
+
+        for (path, content) in after {
+            match before.get(path) {
+                None => summary.added.push(path.clone()),
+                Some(previous) if previous != content => summary.modified.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+
+        summary.removed = before
+            .keys()
+            .filter(|path| !after.contains_key(*path))
+            .cloned()
+            .collect();
+
+        summary
+    }
+
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+
+    /// Proxies whose generated configuration changed
+    pub fn affected_proxies(&self, config: &Config) -> Vec<String> {
+        let changed: Vec<&PathBuf> = self
+            .added
+            .iter()
+            .chain(&self.modified)
+            .chain(&self.removed)
+            .collect();
+
+        config
+            .proxies
+            .iter()
+            .filter(|proxy| {
+                let prefix = Path::new("proxy-configs").join(&proxy.name);
+                changed.iter().any(|path| path.starts_with(&prefix))
+            })
+            .map(|proxy| proxy.name.clone())
+            .collect()
+    }
+
+    /// Print a human-readable summary
+    pub fn print(&self) {
+        if self.is_empty() {
+            println!("No changes in generated files");
+            return;
+        }
+
+        for path in &self.added {
+            println!("  + {}", path.display());
+        }
+        for path in &self.modified {
+            println!("  ~ {}", path.display());
+        }
+        for path in &self.removed {
+            println!("  - {}", path.display());
+        }
+        println!(
+            "{} added, {} modified, {} removed",
+            self.added.len(),
+            self.modified.len(),
+            self.removed.len()
+        );
+    }
+}
+
+/// Watch for changes and regenerate until interrupted
+///
+/// # Errors
+/// Returns error if the watcher cannot be set up. Generation failures are
+/// reported and watching continues.
+pub async fn run(options: WatchOptions) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            let _ = tx.send(event);
+        }
+    })
+    .map_err(|e| CerberusError::config(format!("Failed to start file watcher: {e}")))?;
+
+    // Watch the parent directory so editors that replace the file are picked up
+    let config_dir = match options.config_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    watcher
+        .watch(&config_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| {
+            CerberusError::config(format!("Failed to watch {}: {e}", config_dir.display()))
+        })?;
+    // Included files (e.g. conf.d/*.toml) and a local base trigger regeneration as well
+    let mut extra_paths = options.extra_paths.clone();
+    let mut relevant_paths = options.extra_paths.clone();
+    let base = config::local_base(&options.config_path).unwrap_or_default();
+    for file in config::included_files(&options.config_path)
+        .unwrap_or_default()
+        .into_iter()
+        .chain(base)
+    {
+        if let Some(dir) = file.parent()
+            && !dir.as_os_str().is_empty()
+            && dir != config_dir
+            && !extra_paths.iter().any(|path| path == dir)
+        {
+            extra_paths.push(dir.to_path_buf());
+            relevant_paths.push(dir.to_path_buf());
+        }
+        relevant_paths.push(file);
+    }
+    for path in &extra_paths {
+        watcher.watch(path, RecursiveMode::Recursive).map_err(|e| {
+            CerberusError::config(format!("Failed to watch {}: {e}", path.display()))
+        })?;
+    }
+
+    let config_name = options
+        .config_path
+        .file_name()
+        .map(|name| name.to_os_string());
+    let output_dir =
+        std::fs::canonicalize(&options.output_dir).unwrap_or(options.output_dir.clone());
+
+    tracing::info!("Watching {} for changes", options.config_path.display());
+    regenerate(&options).await;
+
+    while let Some(event) = rx.recv().await {
+        if !is_relevant(&event, config_name.as_deref(), &relevant_paths, &output_dir) {
+            continue;
+        }
+
+        // Drain follow-up events from the same save
+        while let Ok(Some(_)) = tokio::time::timeout(DEBOUNCE, rx.recv()).await {}
+
+        regenerate(&options).await;
+    }
+
+    Ok(())
+}
+
+/// Whether an event touches the config file or one of the extra paths
+fn is_relevant(
+    event: &notify::Event,
+    config_name: Option<&std::ffi::OsStr>,
+    extra_paths: &[PathBuf],
+    output_dir: &Path,
+) -> bool {
+    if event.kind.is_access() {
+        return false;
+    }
+
+    event.paths.iter().any(|path| {
+        if path.starts_with(output_dir) {
+            return false;
+        }
+        config_name.is_some_and(|name| path.file_name() == Some(name))
+            || extra_paths.iter().any(|extra| {
+                std::fs::canonicalize(extra)
+                    .map(|extra| path.starts_with(extra))
+                    .unwrap_or(false)
+            })
+    })
+}
+
+/// Directory keeping rendered fragments between regenerations
+///
+/// It sits next to the config file, outside the output directory that each
+/// generation replaces.
+fn fragment_cache_dir(config_path: &Path) -> PathBuf {
+    let config_dir = config_path.parent().unwrap_or(Path::new(""));
+    config_dir.join(".cerberus").join("fragments")
+}
+
+/// Run one generation cycle, logging instead of failing
+async fn regenerate(options: &WatchOptions) {
+    if let Err(e) = try_regenerate(options).await {
+        tracing::error!("Regeneration failed: {}", e);
+    }
+}
+
+async fn try_regenerate(options: &WatchOptions) -> Result<()> {
+    let cerberus = Cerberus::new(&options.config_path, &options.output_dir)?
+        .age_key(options.age_key.clone())
+        .fragment_cache(Some(fragment_cache_dir(&options.config_path)));
+
+    let before = snapshot(&options.output_dir)?;
+    cerberus.generate_all().await?;
+    let after = snapshot(&options.output_dir)?;
+
+    let summary = ChangeSummary::between(&before, &after);
+    summary.print();
+
+    if summary.is_empty() {
+        return Ok(());
+    }
+
+    if options.compose_up {
+        compose_up(&options.output_dir).await?;
+    }
+
+    if options.reload {
+        let affected = summary.affected_proxies(cerberus.config());
+        let proxies: Vec<&config::ProxyConfig> = cerberus
+            .config()
+            .proxies
+            .iter()
+            .filter(|proxy| affected.contains(&proxy.name))
+            .collect();
+        crate::reload::reload(cerberus.config(), &options.output_dir, &proxies).await?;
+    }
+
+    Ok(())
+}
+
+/// Apply the generated compose file
+async fn compose_up(output_dir: &Path) -> Result<()> {
+    let compose_file = output_dir.join(COMPOSE_FILE);
+    let status = tokio::process::Command::new("docker")
+        .arg("compose")
+        .arg("-f")
+        .arg(&compose_file)
+        .args(["up", "-d"])
+        .status()
+        .await
+        .map_err(|e| CerberusError::io(&compose_file, e))?;
+
+    if !status.success() {
+        return Err(CerberusError::DockerComposeValidation {
+            message: format!("docker compose up -d exited with {status}"),
+        });
+    }
+
+    tracing::info!("Applied {}", compose_file.display());
+    Ok(())
+}
+
+/// Send SIGHUP to a proxy container so it reloads its configuration
+pub(crate) async fn reload_container(container: &str) -> Result<()> {
+    let status = tokio::process::Command::new("docker")
+        .args(["kill", "--signal", "HUP", container])
+        .status()
+        .await
+        .map_err(|e| CerberusError::io(container, e))?;
+
+    if !status.success() {
+        return Err(CerberusError::proxy_config(
+            container,
+            format!("failed to send SIGHUP ({status})"),
+        ));
+    }
+
+    tracing::info!("Sent SIGHUP to {}", container);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,96 @@
+//! # Tests for watch mode
+//!
+//! These tests verify output snapshots and the change summary used to
+//! report and hot-reload regenerated files.
+
+use super::*;
+use std::fs;
+use tempfile::TempDir;
+
+/// Helper function to build a snapshot from path/content pairs
+fn create_snapshot(files: &[(&str, &str)]) -> BTreeMap<PathBuf, Vec<u8>> {
+    files
+        .iter()
+        .map(|(path, content)| (PathBuf::from(path), content.as_bytes().to_vec()))
+        .collect()
+}
+
+#[test]
+fn test_change_summary_between() {
+    let before = create_snapshot(&[
+        ("docker-compose.yaml", "a"),
+        ("proxy-configs/proxy/Caddyfile", "b"),
+        ("update-script.sh", "c"),
+    ]);
+    let after = create_snapshot(&[
+        ("docker-compose.yaml", "a"),
+        ("proxy-configs/proxy/Caddyfile", "changed"),
+        ("anubis/botPolicy.json", "d"),
+    ]);
+
+    let summary = ChangeSummary::between(&before, &after);
+
+    assert_eq!(summary.added, vec![PathBuf::from("anubis/botPolicy.json")]);
+    assert_eq!(
+        summary.modified,
+        vec![PathBuf::from("proxy-configs/proxy/Caddyfile")]
+    );
+    assert_eq!(summary.removed, vec![PathBuf::from("update-script.sh")]);
+    assert!(!summary.is_empty());
+}
+
+#[test]
+fn test_change_summary_unchanged() {
+    let snapshot = create_snapshot(&[("docker-compose.yaml", "a")]);
+
+    assert!(ChangeSummary::between(&snapshot, &snapshot).is_empty());
+}
+
+#[test]
+fn test_affected_proxies() {
+    let config: Config = toml::from_str(
+        r#"
+[project]
+name = "watch-test"
+
+[[proxies]]
+name = "proxy"
+type = "caddy"
+
+[[proxies]]
+name = "proxy-2"
+type = "nginx"
+"#,
+    )
+    .expect("Failed to parse config");
+
+    let summary = ChangeSummary {
+        modified: vec![PathBuf::from("proxy-configs/proxy-2/default.conf")],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        summary.affected_proxies(&config),
+        vec!["proxy-2".to_string()]
+    );
+}
+
+#[test]
+fn test_snapshot_relative_paths() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let nested = temp_dir.path().join("proxy-configs/proxy");
+    fs::create_dir_all(&nested).expect("Failed to create dir");
+    fs::write(nested.join("Caddyfile"), "content").expect("Failed to write file");
+
+    let snapshot = snapshot(temp_dir.path()).expect("Snapshot should succeed");
+
+    assert_eq!(
+        snapshot.get(Path::new("proxy-configs/proxy/Caddyfile")),
+        Some(&b"content".to_vec())
+    );
+    assert!(
+        super::snapshot(&temp_dir.path().join("missing"))
+            .expect("Missing dir should be empty")
+            .is_empty()
+    );
+}